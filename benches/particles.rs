@@ -0,0 +1,85 @@
+// Бенчмарк интерпретатора: частицы как структуры, передаваемые между функциями.
+// Считает выделения памяти через глобальный аллокатор.
+//
+// cargo bench --bench particles
+
+use aetos::interpreter::Interpreter;
+use aetos::parser::Parser;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const FRAMES: i32 = 2000;
+
+const PARTICLES: &str = r#"
+struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    life: i32,
+}
+
+fn speed(p: Particle) -> f32 {
+    return p.vx * p.vx + p.vy * p.vy;
+}
+
+fn step(p: Particle) -> Particle {
+    return Particle { x: p.x + p.vx, y: p.y + p.vy, vx: p.vx, vy: p.vy + 0.1, life: p.life - 1 };
+}
+
+fn main() -> i32 {
+    let mut a: Particle = Particle { x: 0.0, y: 0.0, vx: 1.0, vy: 0.0, life: 1000 };
+    let mut b: Particle = Particle { x: 5.0, y: 5.0, vx: 0.5, vy: 1.0, life: 1000 };
+    let mut frame: i32 = 0;
+    let mut total: f32 = 0.0;
+    while frame < FRAMES {
+        let snapshot: Particle = a;
+        total = total + speed(snapshot) + speed(b);
+        a = step(a);
+        b = step(b);
+        frame = frame + 1;
+    }
+    return 0;
+}
+"#;
+
+fn main() {
+    let source = PARTICLES.replace("FRAMES", &FRAMES.to_string());
+    let program = Parser::new(&source).parse_program().expect("benchmark program parses");
+
+    let mut interpreter = Interpreter::new();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    interpreter
+        .interpret_program(&program, 0, 0, "bench")
+        .expect("benchmark program runs");
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    eprintln!(
+        "particles: {} frames in {:?}, {} allocations ({} per frame)",
+        FRAMES,
+        elapsed,
+        allocations,
+        allocations / FRAMES as usize
+    );
+}
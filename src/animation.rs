@@ -0,0 +1,79 @@
+// animation.rs - анимация, не зависящая от частоты кадров
+//
+// Скорость задаётся в единицах в секунду и умножается на delta_time() -
+// время предыдущего кадра в секундах. Тогда при 30 и при 144 FPS объект
+// проходит одно и то же расстояние за секунду:
+//
+//     x = move_towards(x, target_x, 200.0 * delta_time());
+//     let t: f32 = ease_in_out(elapsed / duration);
+//     y = lerp(start_y, end_y, t);
+//
+// Функции используют только +, -, * и одинаково считаются в `--strict-fp`.
+
+use std::time::Instant;
+
+/// Верхняя граница delta_time: после паузы (отладчик, перетаскивание окна)
+/// объекты не перескакивают через стены за один кадр
+pub const MAX_DELTA_TIME: f32 = 0.1;
+
+/// Линейная интерполяция от a к b; t ограничивается отрезком 0..1
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    a + (b - a) * t
+}
+
+/// Плавный разгон и торможение (smoothstep): 0 -> 0, 0.5 -> 0.5, 1 -> 1
+pub fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Сдвигает current к target не больше чем на max_delta, не проскакивая цель
+pub fn move_towards(current: f32, target: f32, max_delta: f32) -> f32 {
+    if (target - current).abs() <= max_delta {
+        target
+    } else if target > current {
+        current + max_delta
+    } else {
+        current - max_delta
+    }
+}
+
+/// Часы кадров: длительность предыдущего кадра для delta_time()
+#[derive(Debug)]
+pub struct FrameClock {
+    last_frame: Instant,
+    delta: f32,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self {
+            last_frame: Instant::now(),
+            delta: 0.0,
+        }
+    }
+
+    /// Отмечает конец кадра и возвращает его длительность в секундах
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        self.delta = now.duration_since(self.last_frame).as_secs_f32().min(MAX_DELTA_TIME);
+        self.last_frame = now;
+        self.delta
+    }
+
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Начинает отсчёт кадра заново, например после паузы
+    pub fn restart(&mut self) {
+        self.last_frame = Instant::now();
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,61 @@
+// assets.rs - пути к ресурсам программы (изображения, звуки, файлы)
+//
+// Относительный путь отсчитывается не от текущего каталога процесса, а от
+// `assets_dir` из ближайшего aetos.toml (относительно самого манифеста) или,
+// без него, от каталога скрипта. Так программа находит ресурсы и при запуске
+// из ярлыка меню "Пуск", где текущий каталог - системный.
+
+use crate::workspace::Manifest;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct AssetResolver {
+    base: PathBuf,
+}
+
+impl AssetResolver {
+    /// Ресурсы в каталоге `base`; относительный `base` фиксируется сразу,
+    /// чтобы смена текущего каталога не влияла на пути
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        let base = base.into();
+        let base = if base.is_absolute() {
+            base
+        } else {
+            std::env::current_dir().map_or(base.clone(), |cwd| cwd.join(base))
+        };
+        Self { base }
+    }
+
+    /// Ресурсы скрипта: `assets_dir` из aetos.toml или каталог скрипта
+    pub fn for_script(script: &Path) -> Self {
+        let dir = match script.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let assets_dir = Manifest::nearest(&dir).and_then(|(manifest_dir, manifest)| {
+            manifest.package?.assets_dir.map(|assets_dir| manifest_dir.join(assets_dir))
+        });
+        Self::new(assets_dir.unwrap_or(dir))
+    }
+
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Абсолютный путь остаётся как есть, относительный - от каталога ресурсов
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.base.join(path)
+        }
+    }
+}
+
+impl Default for AssetResolver {
+    /// Без скрипта (REPL, тесты) - текущий каталог
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
@@ -4,6 +4,44 @@ use std::fmt;
 pub struct Program {
     pub functions: Vec<Function>,
     pub structs: Vec<Struct>, // Добавляем структуры
+    pub enums: Vec<Enum>,
+    pub imports: Vec<Import>,
+    pub consts: Vec<Const>,
+    pub statics: Vec<Static>,
+    pub traits: Vec<Trait>,
+    pub trait_impls: Vec<TraitImpl>,
+}
+
+/// `const WIDTH: i32 = 800;` - значение из литералов и других констант,
+/// вычисляется при компиляции и видно во всех функциях
+#[derive(Debug, Clone, PartialEq)]
+pub struct Const {
+    pub name: String,
+    pub const_type: Type,
+    pub value: Expression,
+    pub is_public: bool,
+    pub line: usize,
+}
+
+/// `static mut score: i32 = 0;` - глобальная переменная, видна во всех
+/// функциях. Начальное значение - из литералов и констант, присваивается
+/// до запуска main; изменять можно только `static mut`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Static {
+    pub name: String,
+    pub static_type: Type,
+    pub value: Expression,
+    pub mutable: bool,
+    pub is_public: bool,
+    pub line: usize,
+}
+
+/// `import "math.aetos";` или `import math;` - путь относительно файла,
+/// в котором записан импорт (см. modules.rs)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub path: String,
+    pub line: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -12,18 +50,222 @@ pub struct Function {
     pub params: Vec<Parameter>,
     pub return_type: Type,
     pub body: Vec<Statement>,
+    pub is_public: bool, // объявлена с `pub`
+    pub line: usize,     // строка объявления (0 - встроенная)
+    pub attributes: Vec<Attribute>,
+    // `fn max<T>` - параметры типа; у методов `impl<T> Pair<T>` - параметры
+    // блока. Обобщённая функция заменяется копиями, см. generics.rs
+    pub type_params: Vec<String>,
+    // Номер файла в программе из нескольких модулей (modules.rs), 0 - в
+    // однофайловой; по нему тайпчекер проверяет доступ к полям без `pub`
+    pub module: usize,
+}
+
+impl Function {
+    /// Функция видна снаружи скомпилированной библиотеки
+    pub fn is_exported(&self) -> bool {
+        self.attributes.contains(&Attribute::Export) || self.attributes.contains(&Attribute::NoMangle)
+    }
+
+    pub fn is_no_mangle(&self) -> bool {
+        self.attributes.contains(&Attribute::NoMangle)
+    }
+
+    /// Функция должна давать одинаковый результат на всех машинах, см. determinism.rs
+    pub fn is_deterministic(&self) -> bool {
+        self.attributes.contains(&Attribute::Deterministic)
+    }
+
+    /// `const fn`: вызов с константными аргументами вычисляется при компиляции
+    pub fn is_const(&self) -> bool {
+        self.attributes.contains(&Attribute::Const)
+    }
+
+    /// Предусловия `@requires(...)`
+    pub fn requires(&self) -> impl Iterator<Item = &Expression> {
+        self.attributes.iter().filter_map(|attribute| match attribute {
+            Attribute::Requires(condition) => Some(condition),
+            _ => None,
+        })
+    }
+
+    /// Постусловия `@ensures(...)`, в них `result` - возвращаемое значение
+    pub fn ensures(&self) -> impl Iterator<Item = &Expression> {
+        self.attributes.iter().filter_map(|attribute| match attribute {
+            Attribute::Ensures(condition) => Some(condition),
+            _ => None,
+        })
+    }
+}
+
+/// Методы из `impl Point { ... }` хранятся среди функций программы под
+/// именем `Point::length`; у метода первый параметр - `self`
+pub fn method_name(type_name: &str, method: &str) -> String {
+    format!("{}::{}", type_name, method)
+}
+
+/// Тип и имя метода из имени функции `Point::length`
+pub fn split_method_name(name: &str) -> Option<(&str, &str)> {
+    name.split_once("::")
+}
+
+/// Перечисление, которому принадлежит вариант встроенных `Option<T>` и
+/// `Result<T, E>`; такие варианты пишутся без префикса: `Some(x)`, `None`
+pub fn builtin_variant_enum(variant: &str) -> Option<&'static str> {
+    match variant {
+        "Some" | "None" => Some("Option"),
+        "Ok" | "Err" => Some("Result"),
+        _ => None,
+    }
+}
+
+/// Аннотации перед объявлением функции: `@export`, `@no_mangle`,
+/// `@deterministic`, контракты `@requires(cond)` и `@ensures(cond)`;
+/// перед структурой - только `@derive(...)`. Const - ключевое слово
+/// `const fn`, оно хранится вместе с аннотациями
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    Export,
+    NoMangle,
+    Deterministic,
+    Const,
+    Requires(Expression),
+    Ensures(Expression),
+    Derive(Vec<Derive>),
+}
+
+impl Attribute {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "export" => Some(Attribute::Export),
+            "no_mangle" => Some(Attribute::NoMangle),
+            "deterministic" => Some(Attribute::Deterministic),
+            _ => None,
+        }
+    }
+}
+
+/// `@derive(debug, eq, clone)`: методы, которые генерирует derive.rs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derive {
+    /// `to_string(self) -> string`
+    Debug,
+    /// `eq(self, other: T) -> bool` - поле за полем
+    Eq,
+    /// `clone(self) -> T`
+    Clone,
+}
+
+impl Derive {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(Derive::Debug),
+            "eq" => Some(Derive::Eq),
+            "clone" => Some(Derive::Clone),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Derive::Debug => "debug",
+            Derive::Eq => "eq",
+            Derive::Clone => "clone",
+        }
+    }
+
+    /// Имя генерируемого метода
+    pub fn method(self) -> &'static str {
+        match self {
+            Derive::Debug => "to_string",
+            Derive::Eq => "eq",
+            Derive::Clone => "clone",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Struct {
     pub name: String,
     pub fields: Vec<StructField>,
+    pub is_public: bool,
+    pub line: usize,
+    pub derives: Vec<Derive>,
+    pub type_params: Vec<String>, // struct Pair<T>
+    pub module: usize,            // см. Function::module
+}
+
+/// trait Drawable { fn draw(self) -> void; } - методы, которые тип
+/// обязан реализовать в `impl Drawable for Sprite`. Значение типа Drawable -
+/// любая реализующая его структура или перечисление; метод выбирается по
+/// настоящему типу значения во время выполнения
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trait {
+    pub name: String,
+    pub methods: Vec<TraitMethod>,
+    pub is_public: bool,
+    pub line: usize,
+}
+
+impl Trait {
+    pub fn method(&self, name: &str) -> Option<&TraitMethod> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+}
+
+/// Сигнатура метода трейта; первый параметр - self
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitMethod {
+    pub name: String,
+    pub params: Vec<Parameter>,
+    pub return_type: Type,
+}
+
+/// impl Drawable for Sprite { ... }; сами методы - обычные функции
+/// `Sprite::draw` в Program::functions
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitImpl {
+    pub trait_name: String,
+    pub type_name: String,
+    /// Имена методов блока без типа: `draw`
+    pub methods: Vec<String>,
+    pub line: usize,
+}
+
+/// enum Shape { Circle(i32), Rect(i32, i32), Empty }
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Variant>,
+    pub is_public: bool,
+    pub line: usize,
+}
+
+impl Enum {
+    pub fn variant(&self, name: &str) -> Option<&Variant> {
+        self.variants.iter().find(|variant| variant.name == name)
+    }
+
+    /// Есть ли варианты с данными (тогда значение не умещается в один тег)
+    pub fn has_payload(&self) -> bool {
+        self.variants.iter().any(|variant| !variant.fields.is_empty())
+    }
+}
+
+/// Вариант перечисления; поля безымянные, как у кортежа
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<Type>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructField {
     pub name: String,
     pub field_type: Type,
+    pub is_public: bool,
+    /// `r: i32 = 0` - значение, если поле не указано в литерале
+    pub default: Option<Expression>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,6 +274,14 @@ pub struct Parameter {
     pub param_type: Type,
 }
 
+/// Начало оператора в исходнике: строка и столбец с 1. У операторов,
+/// которые создал компилятор (derive, стандартная библиотека), - нули
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
 // ast.rs - в enum Statement
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
@@ -40,32 +290,115 @@ pub enum Statement {
         var_type: Type,
         value: Expression,
         mutable: bool,
+        position: Position,
+    },
+    // let Point { x, y: py } = value; - пары (поле, имя переменной)
+    DestructureStruct {
+        struct_name: String,
+        bindings: Vec<(String, String)>,
+        value: Expression,
+        mutable: bool,
+        position: Position,
     },
     Assignment {  // ДОБАВЛЕНО
         name: String,
         value: Expression,
+        position: Position,
+    },
+    // name.field1.field2 = value;
+    FieldAssignment {
+        name: String,
+        fields: Vec<String>,
+        value: Expression,
+        position: Position,
+    },
+    // name[index] = value; - только у массивов фиксированной длины
+    IndexAssignment {
+        name: String,
+        index: Expression,
+        value: Expression,
+        position: Position,
     },
     Return {
         value: Expression,
+        position: Position,
     },
-    Expression(Expression),
+    Expression(Expression, Position),
     Block {
         statements: Vec<Statement>,
+        position: Position,
     },
+    // 'label: while ... - метка нужна, чтобы выйти из вложенного цикла
     While {
         condition: Expression,
         body: Vec<Statement>,
+        label: Option<String>,
+        position: Position,
+    },
+    // loop { body } - бесконечный цикл, выход только через break или return
+    Loop {
+        body: Vec<Statement>,
+        label: Option<String>,
+        position: Position,
+    },
+    // for variable in start..end { body } - end не включается
+    For {
+        variable: String,
+        start: Expression,
+        end: Expression,
+        body: Vec<Statement>,
+        label: Option<String>,
+        position: Position,
+    },
+    // break; / break 'label; - без метки относится к ближайшему циклу
+    Break {
+        label: Option<String>,
+        position: Position,
+    },
+    Continue {
+        label: Option<String>,
+        position: Position,
+    },
+    // defer expr; - выполняется при выходе из объявившего блока (обычном,
+    // через return, break, continue или `?`); несколько defer - в обратном порядке
+    Defer {
+        expression: Expression,
+        position: Position,
     },
     If {
         condition: Expression,
         then_branch: Vec<Statement>,
         else_branch: Option<Vec<Statement>>,
+        position: Position,
     },
 }
 
+impl Statement {
+    pub fn position(&self) -> Position {
+        match self {
+            Statement::VariableDeclaration { position, .. }
+            | Statement::DestructureStruct { position, .. }
+            | Statement::Assignment { position, .. }
+            | Statement::FieldAssignment { position, .. }
+            | Statement::IndexAssignment { position, .. }
+            | Statement::Return { position, .. }
+            | Statement::Expression(_, position)
+            | Statement::Block { position, .. }
+            | Statement::While { position, .. }
+            | Statement::Loop { position, .. }
+            | Statement::For { position, .. }
+            | Statement::Break { position, .. }
+            | Statement::Continue { position, .. }
+            | Statement::Defer { position, .. }
+            | Statement::If { position, .. } => *position,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
-    IntegerLiteral(i32),
+    // i64: литерал с суффиксом `i64` может не поместиться в i32
+    IntegerLiteral(i64),
     FloatLiteral(f32),
     StringLiteral(String),
     BoolLiteral(bool),
@@ -82,6 +415,8 @@ pub enum Expression {
     StructInitialization {
         struct_name: String,
         fields: Vec<(String, Expression)>,
+        // Color { r: 255, ..base } - остальные поля копируются из base
+        base: Option<Box<Expression>>,
     },
     FieldAccess {
         expression: Box<Expression>,
@@ -98,6 +433,94 @@ pub enum Expression {
         expression: Box<Expression>,
         mutable: bool,
     },
+    // ref(expr) - размещает структуру в общей куче
+    Ref {
+        expression: Box<Expression>,
+    },
+    // [a, b, c]
+    ArrayLiteral {
+        elements: Vec<Expression>,
+    },
+    // [value; count] - массив из count копий значения
+    ArrayRepeat {
+        value: Box<Expression>,
+        count: usize,
+    },
+    // array[index]
+    Index {
+        expression: Box<Expression>,
+        index: Box<Expression>,
+    },
+    // array[start..end], array[..end], array[start..] - срез без копирования
+    Slice {
+        expression: Box<Expression>,
+        start: Option<Box<Expression>>,
+        end: Option<Box<Expression>>,
+    },
+    // Direction::Up, Shape::Rect(w, h)
+    EnumVariant {
+        enum_name: String,
+        variant: String,
+        args: Vec<Expression>,
+    },
+    // match value { pattern => expression, ... }
+    Match {
+        subject: Box<Expression>,
+        arms: Vec<MatchArm>,
+    },
+    // if cond { a } else { b } - значение одной из ветвей; else обязателен
+    If {
+        condition: Box<Expression>,
+        then_branch: Box<Expression>,
+        else_branch: Box<Expression>,
+    },
+    // receiver.method(args) - вызов метода из impl-блока
+    MethodCall {
+        receiver: Box<Expression>,
+        method: String,
+        args: Vec<Expression>,
+    },
+    // expr? - значение из Some/Ok, а None/Err досрочно возвращается из функции
+    Try {
+        expression: Box<Expression>,
+    },
+    // |x: i32| -> i32 { x + 1 } - замыкание, захватывающее окружение по значению
+    Lambda {
+        params: Vec<Parameter>,
+        return_type: Type,
+        body: Vec<Statement>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Integer(i32),
+    Bool(bool),
+    // Shape::Rect(w, _) - поля варианта связываются с именами, `_` пропускает поле
+    Variant { enum_name: String, variant: String, bindings: Vec<String> },
+    Wildcard, // _
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Integer(value) => write!(f, "{}", value),
+            Pattern::Bool(value) => write!(f, "{}", value),
+            Pattern::Variant { enum_name, variant, bindings } if bindings.is_empty() => {
+                write!(f, "{}::{}", enum_name, variant)
+            }
+            Pattern::Variant { enum_name, variant, bindings } => {
+                write!(f, "{}::{}({})", enum_name, variant, bindings.join(", "))
+            }
+            Pattern::Wildcard => write!(f, "_"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -106,6 +529,7 @@ pub enum BinaryOperator {
     Subtract,
     Multiply,
     Divide,
+    Mod,
     Eq,
     Neq,
     Lt,
@@ -114,6 +538,47 @@ pub enum BinaryOperator {
     Gte,
     And,
     Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+impl BinaryOperator {
+    /// `&`, `|`, `^`, `<<`, `>>` определены только для целых
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::BitAnd | BinaryOperator::BitOr | BinaryOperator::BitXor | BinaryOperator::Shl | BinaryOperator::Shr
+        )
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Neq => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Lte => "<=",
+            BinaryOperator::Gte => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+            BinaryOperator::BitAnd => "&",
+            BinaryOperator::BitOr => "|",
+            BinaryOperator::BitXor => "^",
+            BinaryOperator::Shl => "<<",
+            BinaryOperator::Shr => ">>",
+        };
+        write!(f, "{}", symbol)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -126,6 +591,25 @@ pub enum Type {
     String,
     Void,
     Struct(String), // Тип для структур
+    Ref(Box<Type>), // ref T - общая ссылка на структуру в куче
+    Array(Box<Type>), // [T] - массив элементов одного типа
+    Slice(Box<Type>), // &[T] - часть массива; массив [T] подходит на место &[T]
+    // [T; N] - массив известной при компиляции длины; размещается на стеке,
+    // элементы можно менять через buf[i] = v. Подходит на место [T] и &[T]
+    FixedArray(Box<Type>, usize),
+    Enum(String),
+    // Встроенные обобщённые перечисления; void на месте параметра - ещё
+    // неизвестный тип (у None, у Ok без известной ошибки)
+    Option(Box<Type>),
+    Result(Box<Type>, Box<Type>),
+    // fn(i32, i32) -> i32 - значение-функция (замыкание или именованная функция)
+    Function(Vec<Type>, Box<Type>),
+    // Pair<i32> - обобщённая структура с аргументами типа; после
+    // мономорфизации заменяется на Struct("Pair<i32>")
+    Generic(String, Vec<Type>),
+    // Результат вызова, который не возвращает управление (exit); подходит
+    // на место любого типа, код после него недостижим
+    Never,
 }
 
 impl fmt::Display for Type {
@@ -139,6 +623,22 @@ impl fmt::Display for Type {
             Type::String => write!(f, "string"),
             Type::Void => write!(f, "void"),
             Type::Struct(name) => write!(f, "{}", name),
+            Type::Ref(inner) => write!(f, "ref {}", inner),
+            Type::Array(element) => write!(f, "[{}]", element),
+            Type::Slice(element) => write!(f, "&[{}]", element),
+            Type::FixedArray(element, length) => write!(f, "[{}; {}]", element, length),
+            Type::Enum(name) => write!(f, "{}", name),
+            Type::Option(inner) => write!(f, "Option<{}>", inner),
+            Type::Result(ok, err) => write!(f, "Result<{}, {}>", ok, err),
+            Type::Function(params, return_type) => {
+                let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+                write!(f, "fn({}) -> {}", params.join(", "), return_type)
+            }
+            Type::Generic(name, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}<{}>", name, args.join(", "))
+            }
+            Type::Never => write!(f, "never"),
         }
     }
 }
\ No newline at end of file
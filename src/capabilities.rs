@@ -0,0 +1,125 @@
+// capabilities.rs - возможности, которые программа получает от хоста
+//
+// Встроенные функции, затрагивающие что-то за пределами программы (окно,
+// файлы, сеть, время, случайность, потоки ОС), перед выполнением сверяются с набором
+// возможностей интерпретатора. Обычный запуск разрешает всё; в режиме
+// `--sandbox` (песочница, встраивание чужих скриптов) всё запрещено, и
+// такая функция возвращает CapabilityDenied вместо выполнения. Вычисления,
+// вывод print и строки доступны всегда.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Окно и рисование
+    Graphics,
+    /// Файлы: хранилище store_set/store_get и пути к ресурсам
+    Fs,
+    /// Сеть (http_get)
+    Net,
+    /// Потоки ОС (spawn)
+    Exec,
+    /// Часы и задержки
+    Time,
+    /// Случайные числа (random_int, random_float)
+    Random,
+}
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Graphics => "graphics",
+            Capability::Fs => "fs",
+            Capability::Net => "net",
+            Capability::Exec => "exec",
+            Capability::Time => "time",
+            Capability::Random => "random",
+        }
+    }
+
+    /// Возможность, нужная встроенной функции; None - функция чистая
+    pub fn required_by(builtin: &str) -> Option<Capability> {
+        match builtin {
+            "init_graphics" | "clear_screen" | "draw_pixel" | "draw_rect" | "draw_circle"
+            | "draw_line" | "draw_text" | "render" => Some(Capability::Graphics),
+            "store_set" | "store_get" | "resolve_asset" => Some(Capability::Fs),
+            "http_get" => Some(Capability::Net),
+            "spawn" => Some(Capability::Exec),
+            "get_time" | "sleep" | "delay" => Some(Capability::Time),
+            "random_int" | "random_float" => Some(Capability::Random),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Набор разрешённых возможностей
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub graphics: bool,
+    pub fs: bool,
+    pub net: bool,
+    pub exec: bool,
+    pub time: bool,
+    pub random: bool,
+}
+
+impl Capabilities {
+    /// Обычный запуск: разрешено всё
+    pub fn all() -> Self {
+        Self { graphics: true, fs: true, net: true, exec: true, time: true, random: true }
+    }
+
+    /// Песочница: запрещено всё
+    pub fn none() -> Self {
+        Self { graphics: false, fs: false, net: false, exec: false, time: false, random: false }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Graphics => self.graphics,
+            Capability::Fs => self.fs,
+            Capability::Net => self.net,
+            Capability::Exec => self.exec,
+            Capability::Time => self.time,
+            Capability::Random => self.random,
+        }
+    }
+
+    /// Ok, если встроенную функцию можно выполнить
+    pub fn check(&self, builtin: &str) -> Result<(), CapabilityDenied> {
+        match Capability::required_by(builtin) {
+            Some(capability) if !self.allows(capability) => Err(CapabilityDenied {
+                builtin: builtin.to_string(),
+                capability,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Встроенная функция вызвана без нужной возможности
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilityDenied {
+    pub builtin: String,
+    pub capability: Capability,
+}
+
+impl fmt::Display for CapabilityDenied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} requires the `{}` capability, which is denied in this interpreter", self.builtin, self.capability)
+    }
+}
+
+impl std::error::Error for CapabilityDenied {}
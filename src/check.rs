@@ -0,0 +1,165 @@
+// check.rs - проверка нескольких файлов (`aetosc check <dir> --recursive`)
+
+use crate::determinism;
+use crate::edition::Edition;
+use crate::lint;
+use crate::modules;
+use crate::parser::{ParseError, Parser};
+use crate::source;
+use crate::typecheck::TypeChecker;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+    /// Замечания аудита детерминизма (печатаются с --audit-determinism)
+    pub determinism: Vec<String>,
+}
+
+impl FileReport {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+fn is_aetos_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "aetos")
+}
+
+/// Простое сопоставление с шаблоном, где `*` - любая последовательность символов
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            if !name.starts_with(prefix) {
+                return false;
+            }
+            let name = &name[prefix.len()..];
+            (0..=name.len())
+                .filter(|&i| name.is_char_boundary(i))
+                .any(|i| wildcard_match(rest, &name[i..]))
+        }
+    }
+}
+
+fn collect_dir(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                collect_dir(&path, recursive, files)?;
+            }
+        } else if is_aetos_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Разворачивает аргумент командной строки в список файлов: файл,
+/// каталог (с подкаталогами при `recursive`) или шаблон вида `src/*.aetos`
+pub fn collect_sources(target: &str, recursive: bool) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let path = Path::new(target);
+
+    if target.contains('*') {
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let pattern = path.file_name().and_then(|name| name.to_str()).unwrap_or("*");
+        for entry in fs::read_dir(dir)? {
+            let entry_path = entry?.path();
+            let matches = entry_path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| wildcard_match(pattern, name));
+            if matches && entry_path.is_file() {
+                files.push(entry_path);
+            }
+        }
+    } else if path.is_dir() {
+        collect_dir(path, recursive, &mut files)?;
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// ` (byte N)` - позиция ошибки в файле как он записан на диске, с BOM и `\r\n`
+fn original_byte(file: &source::SourceFile, error: &ParseError) -> String {
+    error.position()
+        .and_then(|(line, column)| file.original_position(line, column))
+        .map_or(String::new(), |byte| format!(" (byte {})", byte))
+}
+
+/// Парсинг, проверка типов и линтер для одного файла
+pub fn check_file(path: &Path) -> FileReport {
+    let mut report = FileReport {
+        path: path.to_path_buf(),
+        error: None,
+        warnings: Vec::new(),
+        determinism: Vec::new(),
+    };
+
+    let file = match source::SourceFile::read(path) {
+        Ok(file) => file,
+        Err(e) => {
+            report.error = Some(format!("cannot read file: {}", e));
+            return report;
+        }
+    };
+
+    let source = file.text();
+    let edition = Edition::detect(source, path);
+    let program = match Parser::with_edition(source, edition).parse_program() {
+        Ok(program) => program,
+        Err(e) => {
+            report.error = Some(format!("parse error: {}{}", e, original_byte(&file, &e)));
+            return report;
+        }
+    };
+    let program = match modules::link_imports(program, path) {
+        Ok(program) => program,
+        Err(e) => {
+            report.error = Some(format!("import error: {}", e));
+            return report;
+        }
+    };
+
+    let mut type_checker = TypeChecker::new();
+    if let Err(e) = type_checker.check_program(&program) {
+        report.error = Some(format!("type error: {}", e));
+        return report;
+    }
+
+    report.warnings = type_checker.warnings().iter()
+        .chain(&lint::lint_program(&program))
+        .map(|warning| warning.to_string())
+        .collect();
+    report.determinism = determinism::audit_determinism(&program)
+        .iter()
+        .map(|issue| issue.to_string())
+        .collect();
+    report
+}
+
+/// Проверяет файлы параллельно; отчёты возвращаются в порядке `paths`
+pub fn check_files(paths: &[PathBuf]) -> Vec<FileReport> {
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(paths.len().max(1));
+    let chunk_size = paths.len().div_ceil(workers).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|path| check_file(path)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().expect("checker thread panicked"))
+            .collect()
+    })
+}
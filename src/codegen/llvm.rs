@@ -1,43 +1,132 @@
+use inkwell::basic_block::BasicBlock;
+use inkwell::builder::BuilderError;
 use inkwell::context::Context;
-use inkwell::memory_buffer::MemoryBuffer;
 use inkwell::module::Module;
-use inkwell::targets::{
-    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine,
-};
-use inkwell::types::BasicType;
-use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue};
-use inkwell::AddressSpace;
+use inkwell::passes::PassManager;
+use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine};
+use inkwell::types::{ArrayType, BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum, FunctionValue, IntValue, PointerValue};
+use inkwell::{AddressSpace, FloatPredicate, IntPredicate, OptimizationLevel};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Write;
 use std::path::Path;
 use thiserror::Error;
 
-use crate::ast::*;
+use crate::ast;
+use crate::ast::Type;
+use crate::codegen::{CodeGenError, CodeGenerator};
+use crate::generics;
+use crate::optimize::Optimizer;
+use crate::typecheck::{ExpressionTypes, TypedProgram};
 
 #[derive(Error, Debug)]
-pub enum CodeGenError {
+pub enum LLVMCodeGenError {
     #[error("LLVM code generation error: {message}")]
     LLVMError { message: String },
     
+    #[error("Unsupported type for LLVM: {ty}")]
+    UnsupportedType { ty: ast::Type },
+    
+    #[error("{feature} not implemented in LLVM backend")]
+    Unsupported { feature: String },
+    
     #[error("Undefined function: {name}")]
     UndefinedFunction { name: String },
     
     #[error("Undefined variable: {name}")]
     UndefinedVariable { name: String },
-    
-    #[error("Invalid type for code generation: {ty}")]
-    InvalidType { ty: Type },
 }
 
-type CodeGenResult<T> = Result<T, CodeGenError>;
+type LLVMCodeGenResult<T> = Result<T, LLVMCodeGenError>;
+
+impl From<LLVMCodeGenError> for CodeGenError {
+    fn from(err: LLVMCodeGenError) -> Self {
+        CodeGenError::Llvm { message: err.to_string() }
+    }
+}
+
+// Builder возвращает ошибку, только если не указан блок для вставки
+impl From<BuilderError> for LLVMCodeGenError {
+    fn from(err: BuilderError) -> Self {
+        LLVMCodeGenError::LLVMError { message: err.to_string() }
+    }
+}
+
+fn unsupported<T>(feature: &str) -> LLVMCodeGenResult<T> {
+    Err(LLVMCodeGenError::Unsupported { feature: feature.to_string() })
+}
+
+/// Куда выводит LLVM: объектник для текущей машины или ассемблер Cortex-M
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlvmTarget {
+    Native,
+    Embedded,
+}
+
+/// Бэкенды `llvm` и `embedded` реестра `Backend`
+pub struct LlvmCodeGenerator {
+    target: LlvmTarget,
+    lto: bool,
+}
+
+impl LlvmCodeGenerator {
+    pub fn new(target: LlvmTarget) -> Self {
+        Self { target, lto: false }
+    }
+
+    /// `lto = true` оставляет всю программу в одном модуле и прогоняет
+    /// межпроцедурные оптимизации (inliner, GVN, DCE) перед выводом
+    pub fn with_lto(mut self, lto: bool) -> Self {
+        self.lto = lto;
+        self
+    }
+}
+
+impl CodeGenerator for LlvmCodeGenerator {
+    fn extension(&self) -> &'static str {
+        match self.target {
+            LlvmTarget::Native => "o",
+            LlvmTarget::Embedded => "s",
+        }
+    }
+
+    fn generate(&mut self, program: &TypedProgram, output_path: &Path) -> Result<(), CodeGenError> {
+        LLVMGenerator::generate(program, output_path, self.target, self.lto)?;
+        Ok(())
+    }
+}
+
+/// Текстовый LLVM IR программы для текущей машины. Модуль уже прошёл
+/// верификатор LLVM: у каждого блока ровно один терминатор в конце
+pub fn emit_ir(program: &TypedProgram, lto: bool) -> Result<String, CodeGenError> {
+    let context = Context::create();
+    let generator = LLVMGenerator::build(&context, program, LlvmTarget::Native, lto)?;
+    Ok(generator.module.print_to_string().to_string())
+}
+
+/// Блоки цикла, в которые переходят continue и break
+struct LoopBlocks<'ctx> {
+    label: Option<String>,
+    continue_block: BasicBlock<'ctx>,
+    break_block: BasicBlock<'ctx>,
+}
 
 pub struct LLVMGenerator<'ctx> {
     context: &'ctx Context,
     module: Module<'ctx>,
     builder: inkwell::builder::Builder<'ctx>,
-    named_values: HashMap<String, inkwell::values::BasicValueEnum<'ctx>>,
+    function_values: HashMap<String, FunctionValue<'ctx>>,
+    // Переменные видимой области: аллокация и тип значения в ней
+    variables: HashMap<String, (PointerValue<'ctx>, BasicTypeEnum<'ctx>)>,
+    // Массивы [T; N] на стеке функции: тип аллокации для доступа к элементам
+    fixed_arrays: HashMap<String, ArrayType<'ctx>>,
+    // Значения констант после свёртки; подставляются на место имени
+    constants: HashMap<String, (Type, ast::Expression)>,
+    loops: Vec<LoopBlocks<'ctx>>,
+    // Типы выражений от тайпчекера: по ним выбирается ширина литералов
+    types: ExpressionTypes,
     current_function: Option<FunctionValue<'ctx>>,
+    current_return: Type,
 }
 
 impl<'ctx> LLVMGenerator<'ctx> {
@@ -49,56 +138,124 @@ impl<'ctx> LLVMGenerator<'ctx> {
             context,
             module,
             builder,
-            named_values: HashMap::new(),
+            function_values: HashMap::new(),
+            variables: HashMap::new(),
+            fixed_arrays: HashMap::new(),
+            constants: HashMap::new(),
+            loops: Vec::new(),
+            types: ExpressionTypes::default(),
             current_function: None,
+            current_return: Type::Void,
         }
     }
     
-    pub fn generate(program: &Program, output_path: &str) -> CodeGenResult<()> {
-        Target::initialize_native(&InitializationConfig::default())?;
+    pub fn generate(program: &TypedProgram, output_path: &Path, target: LlvmTarget, lto: bool) -> LLVMCodeGenResult<()> {
+        // Инициализируем LLVM targets
+        let initialized = match target {
+            LlvmTarget::Native => Target::initialize_native(&InitializationConfig::default()),
+            LlvmTarget::Embedded => {
+                Target::initialize_arm(&InitializationConfig::default());
+                Ok(())
+            }
+        };
+        initialized.map_err(|e| LLVMCodeGenError::LLVMError {
+            message: format!("Failed to initialize LLVM targets: {}", e),
+        })?;
         
         let context = Context::create();
-        let mut generator = LLVMGenerator::new(&context, "aetos_module");
-        generator.add_builtin_functions();
+        let generator = LLVMGenerator::build(&context, program, target, lto)?;
+        
+        match target {
+            LlvmTarget::Native => generator.compile_to_object(output_path),
+            LlvmTarget::Embedded => generator.compile_to_embedded(output_path),
+        }
+    }
+    
+    /// Проверенный модуль программы; с `lto` - после межпроцедурных оптимизаций
+    pub fn build(context: &'ctx Context, program: &TypedProgram, target: LlvmTarget, lto: bool) -> LLVMCodeGenResult<Self> {
+        let mut generator = LLVMGenerator::new(context, "aetos_module");
+        if target == LlvmTarget::Embedded {
+            generator.add_embedded_functions();
+        }
         
-        generator.generate_program(program)?;
+        // Генерируем код для программы
+        generator.types = program.types().clone();
+        generator.generate_program(program.program())?;
         
+        // Валидируем модуль
         if let Err(e) = generator.module.verify() {
-            return Err(CodeGenError::LLVMError {
+            return Err(LLVMCodeGenError::LLVMError {
                 message: format!("Module verification failed: {}", e),
             });
         }
         
-        generator.module.print_to_stderr();
-        
-        generator.compile_to_object(output_path)?;
-        
-        Ok(())
+        if lto {
+            generator.run_lto_passes();
+        }
+        Ok(generator)
     }
     
-    pub fn generate_embedded(program: &Program, output_path: &str) -> CodeGenResult<()> {
-        let context = Context::create();
-        let mut generator = LLVMGenerator::new(&context, "aetos_embedded");
-        generator.add_embedded_functions();
-        
-        generator.generate_program(program)?;
+    fn run_lto_passes(&self) {
+        // Без точки входа нельзя удалять «неиспользуемые» функции
+        let has_main = self.module.get_function("main").is_some();
         
-        if let Err(e) = generator.module.verify() {
-            return Err(CodeGenError::LLVMError {
-                message: format!("Module verification failed: {}", e),
-            });
+        let passes: PassManager<Module<'ctx>> = PassManager::create(());
+        if has_main {
+            // Снаружи видна только main, остальное можно встроить и удалить
+            passes.add_internalize_pass(true);
+        }
+        passes.add_promote_memory_to_register_pass();
+        passes.add_always_inliner_pass();
+        passes.add_function_inlining_pass();
+        passes.add_ipsccp_pass();
+        passes.add_instruction_combining_pass();
+        passes.add_gvn_pass();
+        passes.add_cfg_simplification_pass();
+        passes.add_aggressive_dce_pass();
+        if has_main {
+            passes.add_global_dce_pass();
         }
+        passes.run_on(&self.module);
+    }
+    
+    /// Встроенные функции платы реализует прошивка, здесь только объявления
+    fn add_embedded_functions(&mut self) {
+        let void_type = self.context.void_type();
+        let i32_type = self.context.i32_type();
         
-        generator.compile_to_embedded(output_path)?;
+        let gpio_set_type = void_type.fn_type(&[i32_type.into(), i32_type.into()], false);
+        let gpio_toggle_type = void_type.fn_type(&[i32_type.into()], false);
+        let delay_type = void_type.fn_type(&[i32_type.into()], false);
         
-        Ok(())
+        for (name, fn_type) in [("gpio_set", gpio_set_type), ("gpio_toggle", gpio_toggle_type), ("delay", delay_type)] {
+            let function_value = self.module.add_function(name, fn_type, None);
+            self.function_values.insert(name.to_string(), function_value);
+        }
     }
     
-    fn generate_program(&mut self, program: &Program) -> CodeGenResult<()> {
+    fn generate_program(&mut self, program: &ast::Program) -> LLVMCodeGenResult<()> {
+        // Копии обобщённых функций; ошибки вывода типов сообщает тайпчекер
+        let program = match generics::monomorphize(program) {
+            Ok(program) => program,
+            Err(_) => Cow::Borrowed(program),
+        };
+        let program = program.as_ref();
+        
+        if !program.statics.is_empty() {
+            return unsupported("static variables");
+        }
+        let mut values = Optimizer::default().evaluate_constants(&program.consts);
+        for constant in &program.consts {
+            let value = values.remove(&constant.name).unwrap_or_else(|| constant.value.clone());
+            self.constants.insert(constant.name.clone(), (constant.const_type.clone(), value));
+        }
+        
+        // Сначала объявляем все функции
         for function in &program.functions {
             self.declare_function(function)?;
         }
         
+        // Затем генерируем тела функций
         for function in &program.functions {
             self.generate_function(function)?;
         }
@@ -106,242 +263,816 @@ impl<'ctx> LLVMGenerator<'ctx> {
         Ok(())
     }
     
-    fn declare_function(&self, function: &Function) -> CodeGenResult<()> {
-        let return_type = self.type_to_llvm_type(&function.return_type)?;
-        let param_types: Vec<inkwell::types::BasicTypeEnum<'ctx>> = function
+    fn declare_function(&mut self, function: &ast::Function) -> LLVMCodeGenResult<()> {
+        let param_types: Vec<BasicMetadataTypeEnum<'ctx>> = function
             .params
             .iter()
-            .map(|p| self.type_to_llvm_type(&p.param_type))
+            .map(|p| self.type_to_llvm_type(&p.param_type).map(Into::into))
             .collect::<Result<Vec<_>, _>>()?;
         
-        let fn_type = return_type.fn_type(&param_types, false);
+        let fn_type = match function.return_type {
+            Type::Void | Type::Never => self.context.void_type().fn_type(&param_types, false),
+            ref return_type => self.type_to_llvm_type(return_type)?.fn_type(&param_types, false),
+        };
         let function_value = self.module.add_function(&function.name, fn_type, None);
         
-        for (i, param) in function.params.iter().enumerate() {
-            function_value.get_nth_param(i as u32)
-                .unwrap()
-                .set_name(&param.name);
+        // Устанавливаем имена параметров
+        for (param_value, param) in function_value.get_param_iter().zip(&function.params) {
+            param_value.set_name(&param.name);
         }
         
+        self.function_values.insert(function.name.clone(), function_value);
         Ok(())
     }
     
-    fn generate_function(&mut self, function: &Function) -> CodeGenResult<()> {
-        let function_value = self.module.get_function(&function.name)
-            .ok_or_else(|| CodeGenError::UndefinedFunction {
+    fn generate_function(&mut self, function: &ast::Function) -> LLVMCodeGenResult<()> {
+        let function_value = *self.function_values.get(&function.name)
+            .ok_or_else(|| LLVMCodeGenError::UndefinedFunction {
                 name: function.name.clone(),
             })?;
         
         self.current_function = Some(function_value);
+        self.current_return = function.return_type.clone();
+        self.variables.clear();
+        self.fixed_arrays.clear();
         
+        // Создаем базовый блок
         let basic_block = self.context.append_basic_block(function_value, "entry");
         self.builder.position_at_end(basic_block);
         
-        self.named_values.clear();
+        // Параметры копируются в аллокации, чтобы их можно было присваивать
+        for (param_value, param) in function_value.get_param_iter().zip(&function.params) {
+            let alloca = self.build_alloca(param_value.get_type(), &param.name)?;
+            self.builder.build_store(alloca, param_value)?;
+            self.variables.insert(param.name.clone(), (alloca, param_value.get_type()));
+            if let Type::FixedArray(..) = param.param_type {
+                self.fixed_arrays.insert(param.name.clone(), param_value.get_type().into_array_type());
+            }
+        }
+        
+        // Генерируем тело функции
+        self.generate_statements(&function.body)?;
         
-        for (i, param) in function.params.iter().enumerate() {
-            let param_value = function_value.get_nth_param(i as u32).unwrap();
-            param_value.set_name(&param.name);
-            
-            let alloca = self.build_alloca(param_value.get_type(), &param.name);
-            self.builder.build_store(alloca, param_value).unwrap();
-            
-            self.named_values.insert(param.name.clone(), alloca.as_basic_value_enum());
+        // Если последний блок не завершён (нет return на всех путях), добавляем return
+        if !self.block_terminated() {
+            match function_value.get_type().get_return_type() {
+                None => {
+                    self.builder.build_return(None)?;
+                }
+                Some(return_type) => {
+                    self.builder.build_return(Some(&return_type.const_zero()))?;
+                }
+            }
         }
         
-        for statement in &function.body {
+        Ok(())
+    }
+    
+    /// Завершён ли текущий блок (return или переход). Код после терминатора
+    /// недостижим, и LLVM не допускает инструкций после него
+    fn block_terminated(&self) -> bool {
+        self.builder.get_insert_block()
+            .and_then(|block| block.get_terminator())
+            .is_some()
+    }
+    
+    /// Операторы по порядку; после return остальные недостижимы и пропускаются
+    fn generate_statements(&mut self, statements: &[ast::Statement]) -> LLVMCodeGenResult<()> {
+        for statement in statements {
+            if self.block_terminated() {
+                break;
+            }
             self.generate_statement(statement)?;
         }
-        
-        if let Type::Void = function.return_type {
-            self.builder.build_return(None).unwrap();
+        Ok(())
+    }
+    
+    /// Вложенный блок: объявленные в нём переменные не видны после него
+    fn generate_block(&mut self, statements: &[ast::Statement]) -> LLVMCodeGenResult<()> {
+        let variables = self.variables.clone();
+        let fixed_arrays = self.fixed_arrays.clone();
+        let result = self.generate_statements(statements);
+        self.variables = variables;
+        self.fixed_arrays = fixed_arrays;
+        result
+    }
+    
+    /// Блок, в котором остановилась генерация, если из него можно пройти дальше
+    fn open_block(&self) -> Option<BasicBlock<'ctx>> {
+        if self.block_terminated() {
+            None
         } else {
-            if function.name == "main" && matches!(function.return_type, Type::I32) {
-                let zero = self.context.i32_type().const_int(0, false);
-                self.builder.build_return(Some(&zero)).unwrap();
-            }
+            self.builder.get_insert_block()
+        }
+    }
+    
+    /// Продолжает генерацию в блоке после цикла. Если в него не ведёт ни
+    /// один break, код после цикла недостижим
+    fn position_after_loop(&self, end_block: BasicBlock<'ctx>) -> LLVMCodeGenResult<()> {
+        self.builder.position_at_end(end_block);
+        if end_block.get_first_use().is_none() {
+            self.builder.build_unreachable()?;
+        }
+        Ok(())
+    }
+    
+    /// Тело цикла с блоками для continue и break
+    fn generate_loop_body(
+        &mut self,
+        label: &Option<String>,
+        continue_block: BasicBlock<'ctx>,
+        break_block: BasicBlock<'ctx>,
+        body: &[ast::Statement],
+    ) -> LLVMCodeGenResult<()> {
+        self.loops.push(LoopBlocks { label: label.clone(), continue_block, break_block });
+        let result = self.generate_block(body);
+        self.loops.pop();
+        result?;
+        if !self.block_terminated() {
+            self.builder.build_unconditional_branch(continue_block)?;
         }
-        
         Ok(())
     }
     
-    fn generate_statement(&mut self, statement: &Statement) -> CodeGenResult<()> {
+    /// Цикл, к которому относится break или continue: по метке или ближайший
+    fn target_loop(&self, label: &Option<String>) -> LLVMCodeGenResult<&LoopBlocks<'ctx>> {
+        self.loops.iter().rev()
+            .find(|blocks| label.is_none() || blocks.label == *label)
+            .ok_or_else(|| LLVMCodeGenError::LLVMError {
+                message: match label {
+                    Some(label) => format!("Undefined loop label '{}", label),
+                    None => "break or continue outside of a loop".to_string(),
+                },
+            })
+    }
+    
+    fn generate_statement(&mut self, statement: &ast::Statement) -> LLVMCodeGenResult<()> {
         match statement {
-            Statement::VariableDeclaration { name, var_type: _, value } => {
+            // [T; N] - alloca в начале функции, без обращения к куче
+            ast::Statement::VariableDeclaration { name, var_type: var_type @ Type::FixedArray(_, length), value, .. } => {
+                let array_type = self.type_to_llvm_type(var_type)?.into_array_type();
+                let alloca = self.build_alloca(array_type.as_basic_type_enum(), name)?;
+                let element_type = array_type.get_element_type();
+                
+                // Значение вычисляется до объявления: в нём может быть прежняя
+                // переменная с тем же именем
+                match value {
+                    ast::Expression::ArrayRepeat { value, .. } => {
+                        let element = self.generate_expression(value)?;
+                        let element = self.coerce(element, element_type)?;
+                        for i in 0..*length {
+                            let pointer = self.constant_element_pointer(alloca, i)?;
+                            self.builder.build_store(pointer, element)?;
+                        }
+                    }
+                    ast::Expression::ArrayLiteral { elements } => {
+                        for (i, element) in elements.iter().enumerate() {
+                            let element = self.generate_expression(element)?;
+                            let element = self.coerce(element, element_type)?;
+                            let pointer = self.constant_element_pointer(alloca, i)?;
+                            self.builder.build_store(pointer, element)?;
+                        }
+                    }
+                    // Другой массив [T; N] копируется целиком
+                    _ => {
+                        let array = self.generate_expression(value)?;
+                        self.builder.build_store(alloca, array)?;
+                    }
+                }
+                self.variables.insert(name.clone(), (alloca, array_type.as_basic_type_enum()));
+                self.fixed_arrays.insert(name.clone(), array_type);
+                Ok(())
+            }
+            
+            ast::Statement::IndexAssignment { name, index, value, .. } => {
+                let index = self.generate_index(index)?;
+                let value = self.generate_expression(value)?;
+                let pointer = self.element_pointer(name, index)?;
+                let value = self.coerce(value, self.fixed_arrays[name].get_element_type())?;
+                self.builder.build_store(pointer, value)?;
+                Ok(())
+            }
+            
+            ast::Statement::VariableDeclaration { name, var_type, value, .. } => {
                 let value_llvm = self.generate_expression(value)?;
-                let alloca = self.build_alloca(value_llvm.get_type(), name);
-                self.builder.build_store(alloca, value_llvm).unwrap();
+                let value_type = self.type_to_llvm_type(var_type)?;
+                let value_llvm = self.coerce(value_llvm, value_type)?;
+                let alloca = self.build_alloca(value_type, name)?;
+                self.builder.build_store(alloca, value_llvm)?;
                 
-                self.named_values.insert(name.clone(), alloca.as_basic_value_enum());
+                self.variables.insert(name.clone(), (alloca, value_type));
+                self.fixed_arrays.remove(name);
+                Ok(())
+            }
+            
+            ast::Statement::Assignment { name, value, .. } => {
+                let value = self.generate_expression(value)?;
+                let (pointer, value_type) = *self.variables.get(name)
+                    .ok_or_else(|| LLVMCodeGenError::UndefinedVariable {
+                        name: name.clone(),
+                    })?;
+                let value = self.coerce(value, value_type)?;
+                self.builder.build_store(pointer, value)?;
                 Ok(())
             }
             
-            Statement::Return { value } => {
+            ast::Statement::Return { value, .. } => {
+                // `return;` разбирается как возврат 0; в void-функции значение
+                // вычисляется только ради побочных эффектов
+                if let Type::Void | Type::Never = self.current_return {
+                    self.generate_effect(value)?;
+                    if !self.block_terminated() {
+                        self.builder.build_return(None)?;
+                    }
+                    return Ok(());
+                }
                 let return_value = self.generate_expression(value)?;
-                self.builder.build_return(Some(&return_value)).unwrap();
+                let return_type = self.type_to_llvm_type(&self.current_return.clone())?;
+                let return_value = self.coerce(return_value, return_type)?;
+                self.builder.build_return(Some(&return_value))?;
+                Ok(())
+            }
+            
+            ast::Statement::Expression(expr, _) => {
+                self.generate_effect(expr)
+            }
+            
+            ast::Statement::Block { statements, .. } => {
+                self.generate_block(statements)
+            }
+            
+            ast::Statement::While { condition, body, label, .. } => {
+                let function = self.function()?;
+                let condition_block = self.context.append_basic_block(function, "while_condition");
+                let body_block = self.context.append_basic_block(function, "while_body");
+                let end_block = self.context.append_basic_block(function, "while_end");
+                
+                // Переходим к блоку условия
+                self.builder.build_unconditional_branch(condition_block)?;
+                self.builder.position_at_end(condition_block);
+                
+                // Генерируем условие
+                let cond_value = self.generate_expression(condition)?;
+                let bool_cond = self.to_bool(cond_value, "while_cond")?;
+                self.builder.build_conditional_branch(bool_cond, body_block, end_block)?;
+                
+                // Тело цикла; после return в конце тела возврата к условию нет
+                self.builder.position_at_end(body_block);
+                self.generate_loop_body(label, condition_block, end_block, body)?;
+                
+                // Конец цикла
+                self.builder.position_at_end(end_block);
+                Ok(())
+            }
+            
+            ast::Statement::Loop { body, label, .. } => {
+                // Из loop выходят через break или return; без break код после
+                // цикла недостижим
+                let function = self.function()?;
+                let body_block = self.context.append_basic_block(function, "loop_body");
+                let end_block = self.context.append_basic_block(function, "loop_end");
+                
+                self.builder.build_unconditional_branch(body_block)?;
+                self.builder.position_at_end(body_block);
+                self.generate_loop_body(label, body_block, end_block, body)?;
+                
+                self.position_after_loop(end_block)
+            }
+            
+            ast::Statement::For { variable, start, end, body, label, .. } => {
+                // Границы вычисляются один раз; счётчик виден только в теле
+                let start = self.generate_expression(start)?;
+                let counter_type = start.get_type();
+                let end = self.generate_expression(end)?;
+                let end = self.coerce(end, counter_type)?.into_int_value();
+                let counter = self.build_alloca(counter_type, variable)?;
+                self.builder.build_store(counter, start)?;
+                
+                let function = self.function()?;
+                let condition_block = self.context.append_basic_block(function, "for_condition");
+                let body_block = self.context.append_basic_block(function, "for_body");
+                let step_block = self.context.append_basic_block(function, "for_step");
+                let end_block = self.context.append_basic_block(function, "for_end");
+                
+                self.builder.build_unconditional_branch(condition_block)?;
+                self.builder.position_at_end(condition_block);
+                let current = self.builder.build_load(counter, variable)?.into_int_value();
+                let in_range = self.builder.build_int_compare(IntPredicate::SLT, current, end, "for_cond")?;
+                self.builder.build_conditional_branch(in_range, body_block, end_block)?;
+                
+                self.builder.position_at_end(body_block);
+                let variables = self.variables.clone();
+                let fixed_arrays = self.fixed_arrays.clone();
+                self.variables.insert(variable.clone(), (counter, counter_type));
+                self.fixed_arrays.remove(variable);
+                let result = self.generate_loop_body(label, step_block, end_block, body);
+                self.variables = variables;
+                self.fixed_arrays = fixed_arrays;
+                result?;
+                
+                // continue переходит сюда, к следующему значению счётчика
+                self.builder.position_at_end(step_block);
+                let current = self.builder.build_load(counter, variable)?.into_int_value();
+                let one = current.get_type().const_int(1, false);
+                let next = self.builder.build_int_add(current, one, "for_next")?;
+                self.builder.build_store(counter, next)?;
+                self.builder.build_unconditional_branch(condition_block)?;
+                
+                self.builder.position_at_end(end_block);
+                Ok(())
+            }
+            
+            ast::Statement::Break { label, .. } => {
+                let break_block = self.target_loop(label)?.break_block;
+                self.builder.build_unconditional_branch(break_block)?;
                 Ok(())
             }
             
-            Statement::Expression(expr) => {
-                self.generate_expression(expr)?;
+            ast::Statement::Continue { label, .. } => {
+                let continue_block = self.target_loop(label)?.continue_block;
+                self.builder.build_unconditional_branch(continue_block)?;
                 Ok(())
             }
             
-            Statement::Block { statements } => {
-                for stmt in statements {
-                    self.generate_statement(stmt)?;
+            ast::Statement::If { condition, then_branch, else_branch, .. } => {
+                let function = self.function()?;
+                let then_block = self.context.append_basic_block(function, "if_then");
+                // Без else ложное условие ведёт сразу в блок после if
+                let else_block = match else_branch {
+                    Some(_) => self.context.append_basic_block(function, "if_else"),
+                    None => self.context.append_basic_block(function, "if_end"),
+                };
+                
+                // Генерируем условие
+                let cond_value = self.generate_expression(condition)?;
+                let bool_cond = self.to_bool(cond_value, "if_cond")?;
+                self.builder.build_conditional_branch(bool_cond, then_block, else_block)?;
+                
+                // Then branch
+                self.builder.position_at_end(then_block);
+                self.generate_block(then_branch)?;
+                let then_end = self.open_block();
+                
+                let Some(else_branch) = else_branch else {
+                    if let Some(then_end) = then_end {
+                        self.builder.position_at_end(then_end);
+                        self.builder.build_unconditional_branch(else_block)?;
+                    }
+                    self.builder.position_at_end(else_block);
+                    return Ok(());
+                };
+                
+                // Else branch
+                self.builder.position_at_end(else_block);
+                self.generate_block(else_branch)?;
+                let else_end = self.open_block();
+                
+                // Блок слияния нужен, только если хотя бы одна ветка не завершилась
+                // return; иначе код после if недостижим и generate_statements его пропустит
+                let open_ends: Vec<_> = then_end.into_iter().chain(else_end).collect();
+                if !open_ends.is_empty() {
+                    let end_block = self.context.append_basic_block(function, "if_end");
+                    for block in open_ends {
+                        self.builder.position_at_end(block);
+                        self.builder.build_unconditional_branch(end_block)?;
+                    }
+                    self.builder.position_at_end(end_block);
                 }
                 Ok(())
             }
+            
+            ast::Statement::DestructureStruct { .. } => unsupported("struct destructuring"),
+            ast::Statement::FieldAssignment { .. } => unsupported("struct field assignment"),
+            ast::Statement::Defer { .. } => unsupported("defer"),
         }
     }
     
-    fn generate_expression(&self, expression: &Expression) -> CodeGenResult<BasicValueEnum<'ctx>> {
+    /// Выражение-оператор: результат не нужен, и вызов void-функции допустим
+    fn generate_effect(&mut self, expression: &ast::Expression) -> LLVMCodeGenResult<()> {
         match expression {
-            Expression::IntegerLiteral(value) => {
-                Ok(self.context.i32_type().const_int(*value as u64, false).into())
+            ast::Expression::FunctionCall { name, args } => {
+                self.generate_call(name, args)?;
+            }
+            ast::Expression::If { condition, then_branch, else_branch } => {
+                let function = self.function()?;
+                let then_block = self.context.append_basic_block(function, "if_then");
+                let else_block = self.context.append_basic_block(function, "if_else");
+                let end_block = self.context.append_basic_block(function, "if_end");
+                
+                let cond_value = self.generate_expression(condition)?;
+                let bool_cond = self.to_bool(cond_value, "if_cond")?;
+                self.builder.build_conditional_branch(bool_cond, then_block, else_block)?;
+                
+                for (block, branch) in [(then_block, then_branch), (else_block, else_branch)] {
+                    self.builder.position_at_end(block);
+                    self.generate_effect(branch)?;
+                    if !self.block_terminated() {
+                        self.builder.build_unconditional_branch(end_block)?;
+                    }
+                }
+                self.builder.position_at_end(end_block);
+            }
+            _ => {
+                self.generate_expression(expression)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Вызов с аргументами, приведёнными к типам параметров; None у void-функции
+    fn generate_call(&mut self, name: &str, args: &[ast::Expression]) -> LLVMCodeGenResult<Option<BasicValueEnum<'ctx>>> {
+        let function = *self.function_values.get(name)
+            .ok_or_else(|| LLVMCodeGenError::UndefinedFunction {
+                name: name.to_string(),
+            })?;
+        let param_types = function.get_type().get_param_types();
+        
+        let mut arg_values: Vec<BasicMetadataValueEnum<'ctx>> = Vec::with_capacity(args.len());
+        for (arg, param_type) in args.iter().zip(param_types) {
+            let value = self.generate_expression(arg)?;
+            arg_values.push(self.coerce(value, param_type)?.into());
+        }
+        
+        let call = self.builder.build_call(function, &arg_values, "calltmp")?;
+        Ok(call.try_as_basic_value().left())
+    }
+    
+    fn generate_expression(&mut self, expression: &ast::Expression) -> LLVMCodeGenResult<BasicValueEnum<'ctx>> {
+        match expression {
+            ast::Expression::IntegerLiteral(value) => {
+                let int_type = match self.types.get(expression) {
+                    Some(Type::I64) => self.context.i64_type(),
+                    _ => self.context.i32_type(),
+                };
+                Ok(int_type.const_int(*value as u64, true).into())
+            }
+            
+            ast::Expression::FloatLiteral(value) => {
+                let float_type = match self.types.get(expression) {
+                    Some(Type::F64) => self.context.f64_type(),
+                    _ => self.context.f32_type(),
+                };
+                Ok(float_type.const_float(*value as f64).into())
             }
             
-            Expression::Variable(name) => {
-                let variable = self.named_values.get(name)
-                    .ok_or_else(|| CodeGenError::UndefinedVariable {
+            ast::Expression::StringLiteral(value) => {
+                // Глобальная строка с нулём в конце; байты (в том числе `\n`
+                // и кавычки) копируются как есть
+                let global = self.builder.build_global_string_ptr(value, "str")?;
+                Ok(global.as_pointer_value().into())
+            }
+            
+            ast::Expression::BoolLiteral(value) => {
+                Ok(self.context.bool_type().const_int(*value as u64, false).into())
+            }
+            
+            ast::Expression::Variable(name) => {
+                if let Some(&(pointer, _)) = self.variables.get(name) {
+                    // Загружаем значение из аллокации
+                    return Ok(self.builder.build_load(pointer, name)?);
+                }
+                let (const_type, value) = self.constants.get(name).cloned()
+                    .ok_or_else(|| LLVMCodeGenError::UndefinedVariable {
                         name: name.clone(),
                     })?;
-                
-                Ok(self.builder.build_load(variable.get_type(), *variable, name).unwrap())
+                let value = self.generate_expression(&value)?;
+                let const_type = self.type_to_llvm_type(&const_type)?;
+                self.coerce(value, const_type)
             }
             
-            Expression::BinaryExpression { left, operator, right } => {
+            ast::Expression::BinaryExpression { left, operator, right } => {
                 let left_val = self.generate_expression(left)?;
                 let right_val = self.generate_expression(right)?;
+                self.generate_binary(operator, left_val, right_val)
+            }
+
+            ast::Expression::TypeCast { expression, target_type } => {
+                let value = self.generate_expression(expression)?;
+                let target_llvm_type = self.type_to_llvm_type(target_type)?;
                 
-                match operator {
-                    BinaryOperator::Add => {
-                        Ok(self.builder.build_int_add(
-                            left_val.into_int_value(),
-                            right_val.into_int_value(),
-                            "addtmp",
-                        ).unwrap().into())
+                match (value.get_type(), target_llvm_type) {
+                    (t1, t2) if t1.is_float_type() && t2.is_int_type() => {
+                        Ok(self.builder.build_float_to_signed_int(
+                            value.into_float_value(),
+                            target_llvm_type.into_int_type(),
+                            "cast",
+                        )?.into())
                     }
-                    
-                    BinaryOperator::Subtract => {
-                        Ok(self.builder.build_int_sub(
-                            left_val.into_int_value(),
-                            right_val.into_int_value(),
-                            "subtmp",
-                        ).unwrap().into())
-                    }
-                    
-                    BinaryOperator::Multiply => {
-                        Ok(self.builder.build_int_mul(
-                            left_val.into_int_value(),
-                            right_val.into_int_value(),
-                            "multmp",
-                        ).unwrap().into())
-                    }
-                    
-                    BinaryOperator::Divide => {
-                        Ok(self.builder.build_int_signed_div(
-                            left_val.into_int_value(),
-                            right_val.into_int_value(),
-                            "divtmp",
-                        ).unwrap().into())
-                    }
-                    
-                    BinaryOperator::Eq => {
-                        Ok(self.builder.build_int_compare(
-                            inkwell::IntPredicate::EQ,
-                            left_val.into_int_value(),
-                            right_val.into_int_value(),
-                            "eqtmp",
-                        ).unwrap().into())
-                    }
-                    
-                    BinaryOperator::Neq => {
-                        Ok(self.builder.build_int_compare(
-                            inkwell::IntPredicate::NE,
-                            left_val.into_int_value(),
-                            right_val.into_int_value(),
-                            "neqtmp",
-                        ).unwrap().into())
+                    // В bool превращается любое ненулевое значение
+                    (_, t2) if *target_type == Type::Bool && t2.is_int_type() => {
+                        Ok(self.to_bool(value, "cast")?.into())
                     }
+                    _ => self.coerce(value, target_llvm_type),
                 }
             }
             
-            Expression::FunctionCall { name, args } => {
-                let function = self.module.get_function(name)
-                    .ok_or_else(|| CodeGenError::UndefinedFunction {
-                        name: name.clone(),
-                    })?;
-                
-                let arg_values: Vec<BasicValueEnum<'ctx>> = args
-                    .iter()
-                    .map(|arg| self.generate_expression(arg))
-                    .collect::<Result<Vec<_>, _>>()?;
-                
-                Ok(self.builder.build_call(function, &arg_values, "calltmp")
-                   .unwrap()
-                   .try_as_basic_value()
-                   .left()
-                   .unwrap())
+            ast::Expression::FunctionCall { name, args } => {
+                self.generate_call(name, args)?
+                    .ok_or_else(|| LLVMCodeGenError::LLVMError {
+                        message: format!("Function {} returns no value", name),
+                    })
             }
             
-            Expression::Move { expression } => {
+            ast::Expression::Index { expression, index } => {
+                let ast::Expression::Variable(name) = &**expression else {
+                    return unsupported("indexing of non-variable arrays");
+                };
+                let index = self.generate_index(index)?;
+                let pointer = self.element_pointer(name, index)?;
+                Ok(self.builder.build_load(pointer, "element")?)
+            }
+            
+            ast::Expression::MethodCall { receiver, method, .. } if method == "len" => {
+                let length = match &**receiver {
+                    ast::Expression::Variable(name) => self.fixed_arrays.get(name).map(|array_type| array_type.len()),
+                    _ => None,
+                };
+                let Some(length) = length else {
+                    return unsupported("len() of arrays other than fixed-size array variables");
+                };
+                Ok(self.context.i32_type().const_int(length as u64, false).into())
+            }
+            
+            ast::Expression::Move { expression } => {
                 self.generate_expression(expression)
             }
             
-            Expression::Borrow { expression, mutable: _ } => {
+            ast::Expression::Borrow { expression, .. } => {
                 self.generate_expression(expression)
             }
+            
+            ast::Expression::If { condition, then_branch, else_branch } => {
+                let function = self.function()?;
+                let then_block = self.context.append_basic_block(function, "ifx_then");
+                let else_block = self.context.append_basic_block(function, "ifx_else");
+                let end_block = self.context.append_basic_block(function, "ifx_end");
+                
+                let cond_value = self.generate_expression(condition)?;
+                let bool_cond = self.to_bool(cond_value, "ifx_cond")?;
+                self.builder.build_conditional_branch(bool_cond, then_block, else_block)?;
+                
+                // Ветви могут добавить свои блоки, поэтому в phi идут блоки, где они закончились
+                self.builder.position_at_end(then_block);
+                let then_value = self.generate_expression(then_branch)?;
+                let value_type = match self.types.get(expression) {
+                    Some(ty) => self.type_to_llvm_type(&ty.clone())?,
+                    None => then_value.get_type(),
+                };
+                let then_value = self.coerce(then_value, value_type)?;
+                let then_end = self.builder.get_insert_block().unwrap();
+                self.builder.build_unconditional_branch(end_block)?;
+                
+                self.builder.position_at_end(else_block);
+                let else_value = self.generate_expression(else_branch)?;
+                let else_value = self.coerce(else_value, value_type)?;
+                let else_end = self.builder.get_insert_block().unwrap();
+                self.builder.build_unconditional_branch(end_block)?;
+                
+                self.builder.position_at_end(end_block);
+                let phi = self.builder.build_phi(value_type, "ifx_value")?;
+                phi.add_incoming(&[(&then_value, then_end), (&else_value, else_end)]);
+                Ok(phi.as_basic_value())
+            }
+            
+            ast::Expression::StructInitialization { .. } => unsupported("structs"),
+            ast::Expression::FieldAccess { .. } => unsupported("struct field access"),
+            ast::Expression::Ref { .. } => unsupported("ref"),
+            ast::Expression::ArrayLiteral { .. } | ast::Expression::ArrayRepeat { .. } => {
+                unsupported("heap arrays")
+            }
+            ast::Expression::Slice { .. } => unsupported("slices"),
+            ast::Expression::EnumVariant { .. } => unsupported("enums"),
+            ast::Expression::Match { .. } => unsupported("match"),
+            ast::Expression::MethodCall { method, .. } => unsupported(&format!("method call {}()", method)),
+            ast::Expression::Try { .. } => unsupported("the ? operator"),
+            ast::Expression::Lambda { .. } => unsupported("closures"),
         }
     }
     
-    fn build_alloca(&self, ty: inkwell::types::BasicTypeEnum<'ctx>, name: &str) -> inkwell::values::PointerValue<'ctx> {
-        let builder = self.context.create_builder();
-        let entry_block = self.current_function.unwrap().get_first_basic_block().unwrap();
+    fn generate_binary(
+        &mut self,
+        operator: &ast::BinaryOperator,
+        left_val: BasicValueEnum<'ctx>,
+        right_val: BasicValueEnum<'ctx>,
+    ) -> LLVMCodeGenResult<BasicValueEnum<'ctx>> {
+        // && и || работают с любыми условиями, приведёнными к i1
+        if let ast::BinaryOperator::And | ast::BinaryOperator::Or = operator {
+            let left = self.to_bool(left_val, "lhs")?;
+            let right = self.to_bool(right_val, "rhs")?;
+            let value = match operator {
+                ast::BinaryOperator::And => self.builder.build_and(left, right, "andtmp")?,
+                _ => self.builder.build_or(left, right, "ortmp")?,
+            };
+            return Ok(value.into());
+        }
         
-        if let Some(first_instr) = entry_block.get_first_instruction() {
-            builder.position_before(&first_instr);
-        } else {
-            builder.position_at_end(entry_block);
+        // В смешанном выражении (2 * 1.5) целый операнд приводится к
+        // дробному, узкий - к широкому
+        let common_type = self.common_type(left_val.get_type(), right_val.get_type());
+        let left_val = self.coerce(left_val, common_type)?;
+        let right_val = self.coerce(right_val, common_type)?;
+        
+        if common_type.is_float_type() {
+            let (left, right) = (left_val.into_float_value(), right_val.into_float_value());
+            let compare = |predicate| self.builder.build_float_compare(predicate, left, right, "fcmptmp");
+            let value: BasicValueEnum<'ctx> = match operator {
+                ast::BinaryOperator::Add => self.builder.build_float_add(left, right, "faddtmp")?.into(),
+                ast::BinaryOperator::Subtract => self.builder.build_float_sub(left, right, "fsubtmp")?.into(),
+                ast::BinaryOperator::Multiply => self.builder.build_float_mul(left, right, "fmultmp")?.into(),
+                ast::BinaryOperator::Divide => self.builder.build_float_div(left, right, "fdivtmp")?.into(),
+                ast::BinaryOperator::Mod => self.builder.build_float_rem(left, right, "fremtmp")?.into(),
+                ast::BinaryOperator::Eq => compare(FloatPredicate::OEQ)?.into(),
+                ast::BinaryOperator::Neq => compare(FloatPredicate::ONE)?.into(),
+                ast::BinaryOperator::Lt => compare(FloatPredicate::OLT)?.into(),
+                ast::BinaryOperator::Gt => compare(FloatPredicate::OGT)?.into(),
+                ast::BinaryOperator::Lte => compare(FloatPredicate::OLE)?.into(),
+                ast::BinaryOperator::Gte => compare(FloatPredicate::OGE)?.into(),
+                _ => {
+                    return Err(LLVMCodeGenError::LLVMError {
+                        message: format!("Operator {} is not defined for floats", operator),
+                    })
+                }
+            };
+            return Ok(value);
         }
         
-        builder.build_alloca(ty, name).unwrap()
+        if !common_type.is_int_type() {
+            return unsupported(&format!("operator {} on non-numeric values", operator));
+        }
+        let (left, right) = (left_val.into_int_value(), right_val.into_int_value());
+        let compare = |predicate| self.builder.build_int_compare(predicate, left, right, "cmptmp");
+        let value: BasicValueEnum<'ctx> = match operator {
+            ast::BinaryOperator::Add => self.builder.build_int_add(left, right, "addtmp")?.into(),
+            ast::BinaryOperator::Subtract => self.builder.build_int_sub(left, right, "subtmp")?.into(),
+            ast::BinaryOperator::Multiply => self.builder.build_int_mul(left, right, "multmp")?.into(),
+            ast::BinaryOperator::Divide => self.builder.build_int_signed_div(left, right, "divtmp")?.into(),
+            ast::BinaryOperator::Mod => self.builder.build_int_signed_rem(left, right, "remtmp")?.into(),
+            ast::BinaryOperator::Eq => compare(IntPredicate::EQ)?.into(),
+            ast::BinaryOperator::Neq => compare(IntPredicate::NE)?.into(),
+            ast::BinaryOperator::Lt => compare(IntPredicate::SLT)?.into(),
+            ast::BinaryOperator::Gt => compare(IntPredicate::SGT)?.into(),
+            ast::BinaryOperator::Lte => compare(IntPredicate::SLE)?.into(),
+            ast::BinaryOperator::Gte => compare(IntPredicate::SGE)?.into(),
+            ast::BinaryOperator::BitAnd => self.builder.build_and(left, right, "bitandtmp")?.into(),
+            ast::BinaryOperator::BitOr => self.builder.build_or(left, right, "bitortmp")?.into(),
+            ast::BinaryOperator::BitXor => self.builder.build_xor(left, right, "bitxortmp")?.into(),
+            ast::BinaryOperator::Shl | ast::BinaryOperator::Shr => {
+                // Сдвиг на >= ширины в LLVM даёт poison; маскируем
+                // число бит, как это делают WASM и интерпретатор
+                let width = left.get_type().get_bit_width() as u64;
+                let mask = left.get_type().const_int(width - 1, false);
+                let amount = self.builder.build_and(right, mask, "shamt")?;
+                if *operator == ast::BinaryOperator::Shl {
+                    self.builder.build_left_shift(left, amount, "shltmp")?.into()
+                } else {
+                    self.builder.build_right_shift(left, amount, true, "shrtmp")?.into()
+                }
+            }
+            ast::BinaryOperator::And | ast::BinaryOperator::Or => unreachable!("handled above"),
+        };
+        Ok(value)
     }
     
-    fn type_to_llvm_type(&self, ty: &Type) -> CodeGenResult<inkwell::types::BasicTypeEnum<'ctx>> {
-        match ty {
-            Type::I32 => Ok(self.context.i32_type().as_basic_type_enum()),
-            Type::I64 => Ok(self.context.i64_type().as_basic_type_enum()),
-            Type::F32 => Ok(self.context.f32_type().as_basic_type_enum()),
-            Type::F64 => Ok(self.context.f64_type().as_basic_type_enum()),
-            Type::Bool => Ok(self.context.bool_type().as_basic_type_enum()),
-            Type::Void => Ok(self.context.void_type().as_basic_type_enum()),
+    /// Тип, к которому приводятся оба операнда: дробный, если есть дробный,
+    /// иначе более широкий
+    fn common_type(&self, left: BasicTypeEnum<'ctx>, right: BasicTypeEnum<'ctx>) -> BasicTypeEnum<'ctx> {
+        match (left, right) {
+            (BasicTypeEnum::FloatType(l), BasicTypeEnum::FloatType(r)) => {
+                if l == self.context.f64_type() { left } else { r.into() }
+            }
+            (BasicTypeEnum::FloatType(_), _) => left,
+            (_, BasicTypeEnum::FloatType(_)) => right,
+            (BasicTypeEnum::IntType(l), BasicTypeEnum::IntType(r)) if r.get_bit_width() > l.get_bit_width() => right,
+            _ => left,
+        }
+    }
+    
+    /// Приводит значение к типу переменной, параметра или результата:
+    /// целое к дробному, узкое к широкому; bool расширяется без знака
+    fn coerce(&self, value: BasicValueEnum<'ctx>, target: BasicTypeEnum<'ctx>) -> LLVMCodeGenResult<BasicValueEnum<'ctx>> {
+        if value.get_type() == target {
+            return Ok(value);
+        }
+        let converted: BasicValueEnum<'ctx> = match (value, target) {
+            (BasicValueEnum::IntValue(int), BasicTypeEnum::FloatType(float_type)) => {
+                if int.get_type().get_bit_width() == 1 {
+                    self.builder.build_unsigned_int_to_float(int, float_type, "itofp")?.into()
+                } else {
+                    self.builder.build_signed_int_to_float(int, float_type, "itofp")?.into()
+                }
+            }
+            (BasicValueEnum::IntValue(int), BasicTypeEnum::IntType(int_type)) => {
+                let signed = int.get_type().get_bit_width() != 1;
+                self.builder.build_int_cast_sign_flag(int, int_type, signed, "icast")?.into()
+            }
+            (BasicValueEnum::FloatValue(float), BasicTypeEnum::FloatType(float_type)) => {
+                self.builder.build_float_cast(float, float_type, "fcast")?.into()
+            }
+            _ => {
+                return Err(LLVMCodeGenError::LLVMError {
+                    message: format!("Cannot convert {} to {}", value.get_type(), target),
+                })
+            }
+        };
+        Ok(converted)
+    }
+    
+    /// Условие ветвления как i1: целое и дробное сравниваются с нулём своего типа
+    fn to_bool(&self, value: BasicValueEnum<'ctx>, name: &str) -> LLVMCodeGenResult<IntValue<'ctx>> {
+        match value {
+            BasicValueEnum::IntValue(int) if int.get_type().get_bit_width() == 1 => Ok(int),
+            BasicValueEnum::IntValue(int) => {
+                Ok(self.builder.build_int_compare(IntPredicate::NE, int, int.get_type().const_zero(), name)?)
+            }
+            BasicValueEnum::FloatValue(float) => {
+                Ok(self.builder.build_float_compare(FloatPredicate::ONE, float, float.get_type().const_zero(), name)?)
+            }
+            _ => Err(LLVMCodeGenError::LLVMError {
+                message: format!("Cannot use {} as a condition", value.get_type()),
+            }),
         }
     }
     
-    fn add_builtin_functions(&self) {
-        // Add standard library functions if needed
+    /// Индекс массива как i32
+    fn generate_index(&mut self, index: &ast::Expression) -> LLVMCodeGenResult<IntValue<'ctx>> {
+        let index = self.generate_expression(index)?;
+        Ok(self.coerce(index, self.context.i32_type().into())?.into_int_value())
     }
     
-    fn add_embedded_functions(&self) {
-        let void_type = self.context.void_type();
+    fn function(&self) -> LLVMCodeGenResult<FunctionValue<'ctx>> {
+        self.current_function.ok_or_else(|| LLVMCodeGenError::LLVMError {
+            message: "Code generation outside of a function".to_string(),
+        })
+    }
+    
+    fn build_alloca(&self, ty: BasicTypeEnum<'ctx>, name: &str) -> LLVMCodeGenResult<PointerValue<'ctx>> {
+        let builder = self.context.create_builder();
+        let entry_block = self.function()?.get_first_basic_block().unwrap();
+        
+        if let Some(first_instr) = entry_block.get_first_instruction() {
+            builder.position_before(&first_instr);
+        } else {
+            builder.position_at_end(entry_block);
+        }
+        
+        Ok(builder.build_alloca(ty, name)?)
+    }
+    
+    /// Адрес элемента массива [T; N]; индекс вне границ - llvm.trap
+    fn element_pointer(&self, name: &str, index: IntValue<'ctx>) -> LLVMCodeGenResult<PointerValue<'ctx>> {
+        let array_type = *self.fixed_arrays.get(name)
+            .ok_or_else(|| LLVMCodeGenError::UndefinedVariable {
+                name: name.to_string(),
+            })?;
         let i32_type = self.context.i32_type();
         
-        let gpio_set_type = void_type.fn_type(&[i32_type.into(), i32_type.into()], false);
-        self.module.add_function("gpio_set", gpio_set_type, None);
+        // Сравнение без знака ловит и отрицательный индекс
+        let function = self.function()?;
+        let trap_block = self.context.append_basic_block(function, "index_out_of_bounds");
+        let ok_block = self.context.append_basic_block(function, "index_ok");
+        let length = i32_type.const_int(array_type.len() as u64, false);
+        let out_of_bounds = self.builder.build_int_compare(IntPredicate::UGE, index, length, "oob")?;
+        self.builder.build_conditional_branch(out_of_bounds, trap_block, ok_block)?;
         
-        let gpio_toggle_type = void_type.fn_type(&[i32_type.into()], false);
-        self.module.add_function("gpio_toggle", gpio_toggle_type, None);
+        self.builder.position_at_end(trap_block);
+        let trap = self.module.get_function("llvm.trap").unwrap_or_else(|| {
+            self.module.add_function("llvm.trap", self.context.void_type().fn_type(&[], false), None)
+        });
+        self.builder.build_call(trap, &[], "")?;
+        self.builder.build_unreachable()?;
         
-        let delay_type = void_type.fn_type(&[i32_type.into()], false);
-        self.module.add_function("delay", delay_type, None);
+        self.builder.position_at_end(ok_block);
+        let (array, _) = self.variables[name];
+        let zero = i32_type.const_zero();
+        Ok(unsafe { self.builder.build_in_bounds_gep(array, &[zero, index], "element_ptr") }?)
     }
     
-    fn compile_to_object(&self, output_path: &str) -> CodeGenResult<()> {
+    /// Адрес элемента с известным индексом - без проверки границ
+    fn constant_element_pointer(&self, array: PointerValue<'ctx>, index: usize) -> LLVMCodeGenResult<PointerValue<'ctx>> {
+        let i32_type = self.context.i32_type();
+        let indices = [i32_type.const_zero(), i32_type.const_int(index as u64, false)];
+        Ok(unsafe { self.builder.build_in_bounds_gep(array, &indices, "element_ptr") }?)
+    }
+    
+    fn type_to_llvm_type(&self, ty: &ast::Type) -> LLVMCodeGenResult<BasicTypeEnum<'ctx>> {
+        match ty {
+            ast::Type::I32 => Ok(self.context.i32_type().as_basic_type_enum()),
+            ast::Type::I64 => Ok(self.context.i64_type().as_basic_type_enum()),
+            ast::Type::F32 => Ok(self.context.f32_type().as_basic_type_enum()),
+            ast::Type::F64 => Ok(self.context.f64_type().as_basic_type_enum()),
+            ast::Type::Bool => Ok(self.context.bool_type().as_basic_type_enum()),
+            ast::Type::String => Ok(self.context.i8_type().ptr_type(AddressSpace::default()).as_basic_type_enum()),
+            ast::Type::FixedArray(element, length) => {
+                Ok(self.type_to_llvm_type(element)?.array_type(*length as u32).as_basic_type_enum())
+            }
+            _ => Err(LLVMCodeGenError::UnsupportedType { ty: ty.clone() }),
+        }
+    }
+    
+    fn compile_to_object(&self, output_path: &Path) -> LLVMCodeGenResult<()> {
         let target_triple = TargetMachine::get_default_triple();
         let target = Target::from_triple(&target_triple)
-            .map_err(|e| CodeGenError::LLVMError {
+            .map_err(|e| LLVMCodeGenError::LLVMError {
                 message: format!("Failed to get target: {}", e),
             })?;
         
@@ -353,60 +1084,52 @@ impl<'ctx> LLVMGenerator<'ctx> {
                 &target_triple,
                 &cpu,
                 &features,
-                inkwell::OptimizationLevel::Default,
+                OptimizationLevel::Default,
                 RelocMode::Default,
                 CodeModel::Default,
             )
-            .ok_or_else(|| CodeGenError::LLVMError {
+            .ok_or_else(|| LLVMCodeGenError::LLVMError {
                 message: "Failed to create target machine".to_string(),
             })?;
         
+        // Компилируем в объектный файл
         target_machine
-            .write_to_file(&self.module, FileType::Object, Path::new(output_path))
-            .map_err(|e| CodeGenError::LLVMError {
+            .write_to_file(&self.module, FileType::Object, output_path)
+            .map_err(|e| LLVMCodeGenError::LLVMError {
                 message: format!("Failed to write object file: {}", e),
             })?;
         
+        println!("Generated native object file: {}", output_path.display());
         Ok(())
     }
     
-    fn compile_to_embedded(&self, output_path: &str) -> CodeGenResult<()> {
-        let target_triple = "arm-none-eabi";
-        let target = Target::from_triple(target_triple)
-            .map_err(|e| CodeGenError::LLVMError {
+    fn compile_to_embedded(&self, output_path: &Path) -> LLVMCodeGenResult<()> {
+        let target_triple = inkwell::targets::TargetTriple::create("arm-none-eabi");
+        let target = Target::from_triple(&target_triple)
+            .map_err(|e| LLVMCodeGenError::LLVMError {
                 message: format!("Failed to get ARM target: {}", e),
             })?;
         
         let target_machine = target
             .create_target_machine(
-                target_triple,
+                &target_triple,
                 "cortex-m3",
                 "+thumb-mode",
-                inkwell::OptimizationLevel::Size,
+                OptimizationLevel::Less,
                 RelocMode::Static,
                 CodeModel::Small,
             )
-            .ok_or_else(|| CodeGenError::LLVMError {
+            .ok_or_else(|| LLVMCodeGenError::LLVMError {
                 message: "Failed to create embedded target machine".to_string(),
             })?;
         
-        let asm_output = format!("{}.s", output_path);
         target_machine
-            .write_to_file(&self.module, FileType::Assembly, Path::new(&asm_output))
-            .map_err(|e| CodeGenError::LLVMError {
+            .write_to_file(&self.module, FileType::Assembly, output_path)
+            .map_err(|e| LLVMCodeGenError::LLVMError {
                 message: format!("Failed to write assembly file: {}", e),
             })?;
         
-        println!("Generated assembly for embedded: {}", asm_output);
-        
+        println!("Generated assembly for embedded: {}", output_path.display());
         Ok(())
     }
 }
-
-impl From<inkwell::support::LLVMString> for CodeGenError {
-    fn from(err: inkwell::support::LLVMString) -> Self {
-        CodeGenError::LLVMError {
-            message: err.to_string(),
-        }
-    }
-}
@@ -1,15 +1,122 @@
 // file name: mod.rs
 pub mod wasm;
+#[cfg(feature = "llvm")]
+pub mod llvm;
 
-use crate::ast::Program;
+use crate::ast::Function;
+use crate::typecheck::TypedProgram;
+use std::fmt;
+use std::path::Path;
 use thiserror::Error;
 
+/// Вид выходного артефакта: исполняемая программа или библиотека
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrateType {
+    #[default]
+    Bin,
+    Lib,
+}
+
+impl CrateType {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bin" => Some(CrateType::Bin),
+            "lib" => Some(CrateType::Lib),
+            _ => None,
+        }
+    }
+}
+
+/// Имя символа функции в выходном модуле.
+/// `@no_mangle` оставляет имя как есть, иначе оно кодируется вместе с
+/// именем крейта: `_AN<длина><крейт><длина><функция>`.
+pub fn symbol_name(crate_name: &str, function: &Function) -> String {
+    if function.is_no_mangle() {
+        function.name.clone()
+    } else {
+        format!("_AN{}{}{}{}", crate_name.len(), crate_name, function.name.len(), function.name)
+    }
+}
+
+/// Бэкенд компиляции (`aetosc compile --backend`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Текстовый WASM (.wat)
+    #[default]
+    Wasm,
+    /// Объектный файл для текущей машины через LLVM
+    Llvm,
+    /// Ассемблер Cortex-M через LLVM, GPIO и delay - внешние функции прошивки
+    Embedded,
+}
+
+impl Backend {
+    pub const NAMES: [&'static str; 3] = ["wasm", "llvm", "embedded"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "wasm" => Some(Backend::Wasm),
+            "llvm" => Some(Backend::Llvm),
+            "embedded" => Some(Backend::Embedded),
+            _ => None,
+        }
+    }
+
+    /// Генератор выбранного бэкенда; бэкенды LLVM доступны, только если
+    /// компилятор собран с feature `llvm`. `lto` (межпроцедурные оптимизации
+    /// всей программы) есть только у бэкендов LLVM
+    pub fn generator(self, crate_type: CrateType, crate_name: &str, lto: bool) -> Result<Box<dyn CodeGenerator>, CodeGenError> {
+        match self {
+            Backend::Wasm if lto => Err(CodeGenError::Unsupported { feature: "LTO".to_string() }),
+            Backend::Wasm => Ok(Box::new(wasm::WasmGenerator::with_crate_type(crate_type, crate_name))),
+            #[cfg(feature = "llvm")]
+            Backend::Llvm => Ok(Box::new(llvm::LlvmCodeGenerator::new(llvm::LlvmTarget::Native).with_lto(lto))),
+            #[cfg(feature = "llvm")]
+            Backend::Embedded => Ok(Box::new(llvm::LlvmCodeGenerator::new(llvm::LlvmTarget::Embedded).with_lto(lto))),
+            #[cfg(not(feature = "llvm"))]
+            Backend::Llvm | Backend::Embedded => Err(CodeGenError::Unavailable { backend: self }),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Backend::Wasm => "wasm",
+            Backend::Llvm => "llvm",
+            Backend::Embedded => "embedded",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum CodeGenError {
-    #[error("WASM error")]
-    WASM(String),
+    #[error("Backend {backend} is not available: the compiler was built without the `llvm` feature")]
+    Unavailable { backend: Backend },
+
+    #[error("{feature} not implemented in WASM backend")]
+    Unsupported { feature: String },
+
+    #[error("Undefined variable {name} in code generation")]
+    UndefinedVariable { name: String },
+
+    #[error("Undefined function {name} in code generation")]
+    UndefinedFunction { name: String },
+
+    #[error("LLVM code generation error: {message}")]
+    Llvm { message: String },
+
+    #[error("Cannot write output: {0}")]
+    Io(#[from] std::io::Error),
 }
 
+/// Общий интерфейс бэкендов: программа после проверки типов и оптимизации
+/// превращается в файл по пути `output_path`. Типы выражений бэкенд берёт
+/// из `TypedProgram`, а не выводит заново
 pub trait CodeGenerator {
-    fn generate(program: &Program, output_path: &str) -> Result<(), CodeGenError>;
+    /// Расширение выходного файла, если путь не указан
+    fn extension(&self) -> &'static str;
+
+    fn generate(&mut self, program: &TypedProgram, output_path: &Path) -> Result<(), CodeGenError>;
 }
\ No newline at end of file
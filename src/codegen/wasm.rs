@@ -1,265 +1,614 @@
 // src/codegen/wasm.rs
 use crate::ast::*;
-use std::collections::HashMap;
+use crate::codegen::{symbol_name, CodeGenError, CodeGenerator, CrateType};
+use crate::generics;
+use crate::optimize::Optimizer;
+use crate::stdlib::{self, Builtin};
+use crate::typecheck::{ExpressionTypes, TypedProgram};
+use crate::visit::{self, walk_statement, Visitor};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Начало кучи в линейной памяти; массивы и перечисления с данными
+/// размещаются выше простым смещением указателя `$__heap_ptr` и не освобождаются
+const HEAP_START: i32 = 1024;
+
+/// Адрес первого строкового литерала; 0 остаётся нулевым указателем
+const STRING_DATA_START: i32 = 16;
+
+/// Вершина стека `$__stack_ptr` - конец единственной страницы памяти. Стек
+/// растёт вниз, навстречу куче; на нём лежат массивы фиксированной длины
+const STACK_TOP: i32 = 65536;
+
+/// Модуль встроенных функций: их реализует среда, загружающая модуль
+const IMPORT_MODULE: &str = "env";
 
 pub struct WasmGenerator {
-    type_section: String,
-    function_section: String,
-    export_section: String,
-    code_section: String,
-    function_types: HashMap<String, (Vec<String>, String)>,
+    function_params: HashMap<String, Vec<Type>>,
+    function_returns: HashMap<String, Type>,
+    // Импорты вызванных встроенных функций в порядке первого вызова:
+    // WAT-имя и объявление `(import ...)`
+    imports: Vec<(String, String)>,
+    // Перечисления: без данных значение - индекс варианта (тег), с данными -
+    // указатель на блок [тег][поля варианта]
+    enums: HashMap<String, Enum>,
+    // Константы верхнего уровня: тип и значение, свёрнутое при компиляции;
+    // обращение к константе встраивается на месте
+    constants: HashMap<String, (Type, Expression)>,
+    // Статические переменные - глобальные переменные модуля; начальное
+    // значение свёрнуто до литерала
+    statics: Vec<Static>,
     current_function: String,
+    // WAT-имена видимых переменных. В WAT у функции одно пространство
+    // локальных имён, поэтому каждое объявление получает своё: затеняющая
+    // переменная `x` становится `$x_1`, `$x_2` и так далее
     locals: HashMap<String, String>,
+    // Исходные типы локальных переменных - для размера элементов массивов
+    local_types: HashMap<String, Type>,
+    // Объявленные локальные функции в порядке объявления: WAT-имя и тип
+    declared_locals: Vec<(String, String)>,
+    // Занятые WAT-имена функции, включая параметры
+    local_names: HashSet<String>,
+    // Типы служебных локальных переменных `__tmpN` по номерам
+    temps: Vec<String>,
+    // Есть ли у функции кадр на стеке (массивы [T; N]) и сколько байт в нём
+    // уже занято; адрес кадра до выделения - в локальной `$__frame_base`
+    has_frame: bool,
+    frame_size: i32,
+    // Метки объемлющих циклов (внутренний последний); номер в списке - суффикс
+    // WAT-меток $break_N и $continue_N
+    loops: Vec<Option<String>>,
+    // defer генерируемых блоков (внутренний последний) и число циклов вокруг
+    // каждого блока: break к циклу N выполняет defer блоков глубже N
+    deferred: Vec<(usize, Vec<Deferred>)>,
+    // Строковые литералы в сегменте данных: UTF-8 с нулём в конце, адрес - указатель
     strings: Vec<String>,
+    string_offsets: HashMap<String, i32>,
+    string_data_end: i32,
     code: String,
+    // Типы выражений от тайпчекера; без них тип угадывается по форме выражения
+    types: ExpressionTypes,
+    crate_type: CrateType,
+    crate_name: String,
 }
 
 impl WasmGenerator {
     pub fn new() -> Self {
         Self {
-            type_section: String::new(),
-            function_section: String::new(),
-            export_section: String::new(),
-            code_section: String::new(),
-            function_types: HashMap::new(),
+            function_params: HashMap::new(),
+            function_returns: HashMap::new(),
+            imports: Vec::new(),
+            enums: HashMap::new(),
+            constants: HashMap::new(),
+            statics: Vec::new(),
             current_function: String::new(),
             locals: HashMap::new(),
+            local_types: HashMap::new(),
+            declared_locals: Vec::new(),
+            local_names: HashSet::new(),
+            temps: Vec::new(),
+            has_frame: false,
+            frame_size: 0,
+            loops: Vec::new(),
+            deferred: Vec::new(),
             strings: Vec::new(),
+            string_offsets: HashMap::new(),
+            string_data_end: STRING_DATA_START,
             code: String::new(),
+            types: ExpressionTypes::default(),
+            crate_type: CrateType::Bin,
+            crate_name: "main".to_string(),
         }
     }
 
-    pub fn generate(&mut self, program: &Program) -> String {
+    pub fn with_crate_type(crate_type: CrateType, crate_name: &str) -> Self {
+        Self {
+            crate_type,
+            crate_name: crate_name.to_string(),
+            ..Self::new()
+        }
+    }
+
+    /// Модуль для проверенной программы: типы выражений берутся из проверки
+    pub fn generate_typed(&mut self, program: &TypedProgram) -> Result<String, CodeGenError> {
+        self.types = program.types().clone();
+        let module = self.generate(program.program());
+        self.types = ExpressionTypes::default();
+        module
+    }
+
+    /// Текст модуля: импорты встроенных функций, память с глобальными
+    /// переменными и строками, функции и экспорты
+    pub fn generate(&mut self, program: &Program) -> Result<String, CodeGenError> {
+        // Копии обобщённых функций; ошибки вывода типов сообщает тайпчекер
+        let program = match generics::monomorphize(program) {
+            Ok(program) => program,
+            Err(_) => Cow::Borrowed(program),
+        };
+        let program = program.as_ref();
         // Сначала собираем информацию о типах функций
         for function in &program.functions {
-            let param_types: Vec<String> = function.params
-                .iter()
-                .map(|p| self.type_to_wasm(&p.param_type))
-                .collect();
-            let return_type = self.type_to_wasm(&function.return_type);
-            self.function_types.insert(function.name.clone(), (param_types, return_type));
+            let param_types = function.params.iter().map(|p| p.param_type.clone()).collect();
+            self.function_params.insert(function.name.clone(), param_types);
+            self.function_returns.insert(function.name.clone(), function.return_type.clone());
         }
 
-        // Генерируем секции
-        self.generate_type_section(&program);
-        self.generate_function_section(&program);
-        self.generate_export_section(&program);
-        self.generate_code_section(&program);
+        let optimizer = Optimizer::default();
+        let mut values = optimizer.evaluate_constants(&program.consts);
+        let mut static_values = optimizer.evaluate_statics(&values, &program.statics);
+        for constant in &program.consts {
+            let value = values.remove(&constant.name).unwrap_or_else(|| constant.value.clone());
+            self.constants.insert(constant.name.clone(), (constant.const_type.clone(), value));
+        }
+        self.statics = program.statics.iter()
+            .map(|static_def| Static {
+                value: static_values.remove(&static_def.name).unwrap_or_else(|| static_def.value.clone()),
+                ..static_def.clone()
+            })
+            .collect();
+
+        for enum_def in &program.enums {
+            self.enums.insert(enum_def.name.clone(), enum_def.clone());
+        }
 
-        // Собираем итоговый модуль WASM
+        // Импорты и строки появляются по ходу генерации функций, а куча
+        // начинается за строками, поэтому память - после кода
+        let mut functions = String::new();
+        for function in &program.functions {
+            functions.push_str(&self.generate_function(function)?);
+        }
+        let memory = self.generate_memory_section()?;
+
+        // Собираем итоговый модуль WASM; импорты по правилам WASM - до
+        // определений функций, памяти и глобальных переменных
         let mut wasm_module = String::new();
         wasm_module.push_str("(module\n");
-        wasm_module.push_str(&self.type_section);
-        wasm_module.push_str(&self.function_section);
-        wasm_module.push_str(&self.export_section);
-        wasm_module.push_str(&self.code_section);
+        for (_, import) in &self.imports {
+            wasm_module.push_str(import);
+        }
+        wasm_module.push_str(&memory);
+        wasm_module.push_str(&functions);
+        wasm_module.push_str(&self.generate_export_section(program));
         wasm_module.push_str(")\n");
-        
-        wasm_module
+
+        Ok(wasm_module)
     }
 
-    fn generate_type_section(&mut self, program: &Program) {
-        self.type_section.push_str("  (type (func");
-        
-        for (i, function) in program.functions.iter().enumerate() {
-            let (param_types, return_type) = self.function_types.get(&function.name).unwrap();
-            
-            self.type_section.push_str(&format!(
-                "\n    (type ${} (func (param {}) (result {})))",
-                i,
-                param_types.join(" "),
-                return_type
+    fn generate_memory_section(&mut self) -> Result<String, CodeGenError> {
+        // Память экспортируется: через неё среда читает строки и массивы
+        let mut section = "  (memory (export \"memory\") 1)\n".to_string();
+        // Глобальные переменные - до сегмента данных: строковое значение
+        // добавляет строку в этот сегмент
+        let globals = self.statics.clone().iter()
+            .map(|static_def| self.static_global(static_def))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !self.strings.is_empty() {
+            let mut bytes = Vec::new();
+            for value in &self.strings {
+                bytes.extend_from_slice(value.as_bytes());
+                bytes.push(0);
+            }
+            section.push_str(&format!(
+                "  (data (i32.const {}) \"{}\")\n",
+                STRING_DATA_START,
+                wat_string(&bytes)
             ));
         }
-        
-        self.type_section.push_str("\n  ))\n");
+        // Выравниваем кучу на 8 байт, если строки не поместились до HEAP_START
+        let heap_start = HEAP_START.max((self.string_data_end + 7) & !7);
+        section.push_str(&format!(
+            "  (global $__heap_ptr (mut i32) (i32.const {}))\n",
+            heap_start
+        ));
+        section.push_str(&format!(
+            "  (global $__stack_ptr (mut i32) (i32.const {}))\n",
+            STACK_TOP
+        ));
+        for global in globals {
+            section.push_str(&global);
+        }
+        Ok(section)
     }
 
-    fn generate_function_section(&mut self, program: &Program) {
-        self.function_section.push_str("  (func");
-        
-        for (i, function) in program.functions.iter().enumerate() {
-            self.function_section.push_str(&format!(
-                "\n    (func ${} (type ${})",
-                function.name, i
-            ));
-            
-            // Объявляем параметры
-            for param in &function.params {
-                let wasm_type = self.type_to_wasm(&param.param_type);
-                self.function_section.push_str(&format!(
-                    "\n      (param ${} {})",
-                    param.name, wasm_type
-                ));
-            }
-            
-            // Объявляем возвращаемый тип
-            let return_type = self.type_to_wasm(&function.return_type);
-            if return_type != "void" {
-                self.function_section.push_str(&format!("\n      (result {})", return_type));
-            }
-            
-            self.function_section.push_str("\n    )");
+    /// `(global $score (mut i32) (i32.const 0))`
+    fn static_global(&mut self, static_def: &Static) -> Result<String, CodeGenError> {
+        let wasm_type = self.type_to_wasm(&static_def.static_type);
+        let value = match &static_def.value {
+            Expression::IntegerLiteral(value) => value.to_string(),
+            Expression::FloatLiteral(value) => value.to_string(),
+            Expression::BoolLiteral(value) => (*value as i32).to_string(),
+            Expression::StringLiteral(value) => self.string_offset(value).to_string(),
+            _ => return Err(unsupported(format!(
+                "Static {} initializer that does not fold to a literal",
+                static_def.name
+            ))),
+        };
+        let global_type = if static_def.mutable {
+            format!("(mut {})", wasm_type)
+        } else {
+            wasm_type.clone()
+        };
+        Ok(format!("  (global ${} {} ({}.const {}))\n", static_def.name, global_type, wasm_type, value))
+    }
+
+    fn static_type(&self, name: &str) -> Option<&Type> {
+        if self.local_types.contains_key(name) {
+            return None;
         }
-        
-        self.function_section.push_str("\n  )\n");
+        self.statics.iter()
+            .find(|static_def| static_def.name == name)
+            .map(|static_def| &static_def.static_type)
     }
 
-    fn generate_export_section(&mut self, program: &Program) {
-        self.export_section.push_str("  (export");
-        
+    /// Адрес литерала в сегменте данных; одинаковые строки хранятся один раз
+    fn string_offset(&mut self, value: &str) -> i32 {
+        if let Some(&offset) = self.string_offsets.get(value) {
+            return offset;
+        }
+        let offset = self.string_data_end;
+        self.string_data_end += value.len() as i32 + 1;
+        self.strings.push(value.to_string());
+        self.string_offsets.insert(value.to_string(), offset);
+        offset
+    }
+
+    fn generate_export_section(&self, program: &Program) -> String {
+        let mut section = String::new();
         for function in &program.functions {
-            if function.name == "main" {
-                self.export_section.push_str(&format!(
-                    "\n    (export \"main\" (func ${}))",
-                    function.name
-                ));
-            }
+            let export_name = if function.is_exported() {
+                symbol_name(&self.crate_name, function)
+            } else if function.name == "main" && self.crate_type == CrateType::Bin {
+                "main".to_string()
+            } else {
+                continue;
+            };
+            section.push_str(&format!(
+                "  (export \"{}\" (func {}))\n",
+                export_name, function_id(&function.name)
+            ));
         }
-        
-        self.export_section.push_str("\n  )\n");
+        section
     }
 
-    fn generate_code_section(&mut self, program: &Program) {
-        self.code_section.push_str("  (code");
+    /// `(func $name (param $a i32) (result i32) (local ...) код)`
+    fn generate_function(&mut self, function: &Function) -> Result<String, CodeGenError> {
+        self.current_function = function.name.clone();
+        self.locals.clear();
+        self.local_types.clear();
+        self.declared_locals.clear();
+        self.local_names.clear();
+        self.temps.clear();
+        self.code.clear();
+        self.has_frame = uses_fixed_arrays(function);
+        self.frame_size = 0;
+        // Массив из кадра нельзя вернуть: кадр освобождается при выходе
+        if self.has_frame && matches!(function.return_type, Type::FixedArray(..)) {
+            return Err(unsupported(format!("Returning fixed-size arrays from {}", function.name)));
+        }
         
-        for function in &program.functions {
-            self.current_function = function.name.clone();
-            self.locals.clear();
-            self.code.clear();
-            
-            // Генерируем код функции
-            for statement in &function.body {
-                self.generate_statement(statement);
-            }
-            
-            // Добавляем неявный возврат для void функций
-            if function.return_type == Type::Void {
+        for param in &function.params {
+            self.locals.insert(param.name.clone(), param.name.clone());
+            self.local_types.insert(param.name.clone(), param.param_type.clone());
+            self.local_names.insert(param.name.clone());
+        }
+        
+        // Генерируем код функции
+        self.generate_block(&function.body)?;
+        
+        match function.return_type {
+            // Неявный возврат в конце функции без результата
+            Type::Void | Type::Never => {
+                self.generate_frame_release();
                 self.code.push_str("return\n");
             }
-            
-            self.code_section.push_str(&format!(
-                "\n    (func ${}\n      {}\n    )",
-                function.name, self.code
+            // Функция с результатом выходит только через return, поэтому
+            // конец тела недостижим
+            _ if !matches!(function.body.last(), Some(Statement::Return { .. })) => {
+                self.code.push_str("unreachable\n");
+            }
+            _ => {}
+        }
+        
+        // Кадр выделяется в начале функции, когда известен его размер
+        let mut frame_locals = Vec::new();
+        if self.has_frame {
+            self.code.insert_str(0, &format!(
+                "global.get $__stack_ptr\nlocal.tee $__frame_base\ni32.const {}\ni32.sub\nglobal.set $__stack_ptr\n",
+                self.frame_size
             ));
+            frame_locals.push(("__frame_base".to_string(), "i32".to_string()));
         }
         
-        self.code_section.push_str("\n  )\n");
+        // Локальные переменные объявляются в заголовке функции до кода, а
+        // появляются по ходу генерации
+        let temps = std::mem::take(&mut self.temps).into_iter()
+            .enumerate()
+            .map(|(index, wasm_type)| (format!("__tmp{}", index), wasm_type));
+        let declarations: String = std::mem::take(&mut self.declared_locals).into_iter()
+            .chain(frame_locals)
+            .chain(temps)
+            .map(|(name, wasm_type)| format!("    (local ${} {})\n", name, wasm_type))
+            .collect();
+        let params: String = function.params.iter()
+            .map(|param| format!(" (param ${} {})", param.name, self.type_to_wasm(&param.param_type)))
+            .collect();
+        
+        Ok(format!(
+            "  (func {}{}{}\n{}{}  )\n",
+            function_id(&function.name),
+            params,
+            self.result_type(&function.return_type),
+            declarations,
+            self.code
+        ))
     }
 
-    pub fn generate_statement(&mut self, statement: &Statement) {
+    pub fn generate_statement(&mut self, statement: &Statement) -> Result<(), CodeGenError> {
         match statement {
-            Statement::VariableDeclaration { name, var_type, value, mutable: _ } => {
-                // Генерируем значение выражения
-                self.generate_expression(value);
-                
-                // Определяем тип для WebAssembly
-                let wasm_type = match var_type {
-                    Type::I32 | Type::Bool => "i32",
-                    Type::I64 => "i64",
-                    Type::F32 => "f32",
-                    Type::F64 => "f64",
-                    Type::String => "i32", // указатель на строку
-                    Type::Void => unreachable!("Cannot declare variable of type void"),
-                    Type::Struct(_) => "i32", // указатель на структуру
-                };
+            // Массив фиксированной длины лежит в кадре функции, а не в куче
+            Statement::VariableDeclaration { name, var_type: var_type @ Type::FixedArray(element, length), value, .. } => {
+                let bytes = 4 + Self::element_size(element) as i32 * *length as i32;
+                self.frame_size += (bytes + 7) & !7;
+                // Переменная видна только после инициализации: `let a = a;`
+                // читает внешнюю `a`
+                let local = self.new_local(name, var_type);
+                self.code.push_str("local.get $__frame_base\n");
+                self.code.push_str(&format!("i32.const {}\n", self.frame_size));
+                self.code.push_str("i32.sub\n");
+                self.code.push_str(&format!("local.set ${}\n", local));
+                self.generate_array_init(&local, element, *length, value)?;
+                self.locals.insert(name.clone(), local);
+                self.local_types.insert(name.clone(), var_type.clone());
+            }
+            
+            Statement::VariableDeclaration { name, var_type, value, mutable: _, .. } => {
+                if matches!(var_type, Type::Void | Type::Never) {
+                    return Err(unsupported(format!("Variable {} of type {}", name, var_type)));
+                }
                 
-                // Сохраняем переменную в локальной области видимости
-                self.locals.insert(name.clone(), wasm_type.to_string());
+                // Генерируем значение выражения до объявления: в нём видна
+                // затеняемая переменная
+                self.generate_expression(value)?;
                 
                 // Сохраняем значение в локальной переменной
-                self.code.push_str(&format!("local.set ${}\n", name));
+                let local = self.declare_local(name, var_type);
+                self.code.push_str(&format!("local.set ${}\n", local));
             }
             
-            Statement::Assignment { name, value } => {
-                // Проверяем, что переменная существует
-                if !self.locals.contains_key(name) {
-                    panic!("Assignment to undefined variable: {}", name);
+            Statement::Assignment { name, value, .. } if self.static_type(name).is_some() => {
+                self.generate_expression(value)?;
+                self.code.push_str(&format!("global.set ${}\n", name));
+            }
+            
+            // Присваивание копирует элементы в память переменной: иначе
+            // запись buf[i] = v была бы видна через другой массив
+            Statement::Assignment { name, value, .. } if matches!(self.local_types.get(name), Some(Type::FixedArray(..))) => {
+                if let Some(Type::FixedArray(element, length)) = self.local_types.get(name).cloned() {
+                    let local = self.local(name)?;
+                    self.generate_array_init(&local, &element, length, value)?;
                 }
+            }
+            
+            Statement::Assignment { name, value, .. } => {
+                // Проверяем, что переменная существует
+                let local = self.local(name)?;
                 
                 // Генерируем значение выражения
-                self.generate_expression(value);
+                self.generate_expression(value)?;
                 
                 // Сохраняем значение в существующей переменной
-                self.code.push_str(&format!("local.set ${}\n", name));
+                self.code.push_str(&format!("local.set ${}\n", local));
             }
             
-            Statement::Return { value } => {
-                self.generate_expression(value);
-                self.code.push_str("return\n");
+            Statement::DestructureStruct { .. } => {
+                return Err(unsupported("Struct destructuring"));
             }
             
-            Statement::Expression(expr) => {
-                self.generate_expression(expr);
-                // Для выражений, которые не используются, выбрасываем результат
-                self.code.push_str("drop\n");
+            Statement::FieldAssignment { .. } => {
+                // TODO: Реализовать запись полей структур
+                return Err(unsupported("Field assignment"));
             }
             
-            Statement::Block { statements } => {
-                // Сохраняем текущие локальные переменные
-                let old_locals = self.locals.clone();
+            Statement::IndexAssignment { name, index, value, .. } => {
+                let element_type = match self.local_types.get(name) {
+                    Some(Type::FixedArray(element, _)) => (**element).clone(),
+                    _ => return Err(unsupported("Index assignment outside fixed-size arrays")),
+                };
+                let size = Self::element_size(&element_type);
+                let array = self.local(name)?;
+                let position = self.temp_local();
                 
-                // Генерируем все операторы в блоке
-                for stmt in statements {
-                    self.generate_statement(stmt);
-                }
+                self.generate_expression(index)?;
+                self.code.push_str(&format!("local.set ${}\n", position));
+                
+                // Выход за границы (в том числе отрицательный индекс) - ловушка
+                self.code.push_str(&format!("local.get ${}\n", position));
+                self.code.push_str(&format!("local.get ${}\n", array));
+                self.code.push_str("i32.load\n");
+                self.code.push_str("i32.ge_u\n");
+                self.code.push_str("if\n");
+                self.code.push_str("unreachable\n");
+                self.code.push_str("end\n");
                 
-                // Восстанавливаем локальные переменные (убираем те, что были объявлены в блоке)
-                self.locals = old_locals;
+                self.code.push_str(&format!("local.get ${}\n", array));
+                self.code.push_str(&format!("local.get ${}\n", position));
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.mul\n");
+                self.code.push_str("i32.add\n");
+                self.generate_expression(value)?;
+                self.code.push_str(&format!("{}.store offset=4\n", self.type_to_wasm(&element_type)));
             }
             
-            Statement::While { condition, body } => {
-                // Начало цикла
-                self.code.push_str("block\n");
-                self.code.push_str("loop\n");
+            Statement::Return { value, .. } if self.deferred.iter().any(|(_, expressions)| !expressions.is_empty()) => {
+                // Значение вычисляется до defer и сохраняется на время их выполнения
+                self.generate_expression(value)?;
+                let wasm_type = self.type_to_wasm(&self.expression_type(value));
+                let result = self.typed_temp_local(&wasm_type);
+                self.code.push_str(&format!("local.set ${}\n", result));
+                self.generate_pending_deferred(0)?;
+                self.code.push_str(&format!("local.get ${}\n", result));
+                self.generate_frame_release();
+                self.code.push_str("return\n");
+            }
+            
+            Statement::Return { value, .. } => {
+                self.generate_expression(value)?;
+                self.generate_frame_release();
+                self.code.push_str("return\n");
+            }
+            
+            Statement::Expression(expr, _) => {
+                self.generate_expression(expr)?;
+                // Неиспользуемое значение выбрасываем; у вызова void его нет
+                if !matches!(self.expression_type(expr), Type::Void | Type::Never) {
+                    self.code.push_str("drop\n");
+                }
+            }
+            
+            // Код генерируется в конце блока и перед переходами из него
+            Statement::Defer { expression, .. } => {
+                let deferred = Deferred {
+                    expression: expression.clone(),
+                    locals: self.locals.clone(),
+                    local_types: self.local_types.clone(),
+                };
+                self.deferred.last_mut()
+                    .expect("defer outside of a block")
+                    .1.push(deferred);
+            }
+            
+            Statement::Block { statements, .. } => {
+                self.generate_block(statements)?;
+            }
+            
+            Statement::While { condition, body, label, .. } => {
+                // Начало цикла; continue - переход к проверке условия
+                let id = self.loops.len();
+                self.code.push_str(&format!("block $break_{}\n", id));
+                self.code.push_str(&format!("loop $continue_{}\n", id));
                 
                 // Генерируем условие
-                self.generate_expression(condition);
+                self.generate_expression(condition)?;
                 self.code.push_str("i32.eqz\n");
-                self.code.push_str("br_if 1\n"); // Выход из цикла если условие ложно
+                self.code.push_str(&format!("br_if $break_{}\n", id)); // Выход из цикла если условие ложно
                 
                 // Тело цикла
-                for stmt in body {
-                    self.generate_statement(stmt);
+                self.loops.push(label.clone());
+                self.generate_block(body)?;
+                self.loops.pop();
+                
+                self.code.push_str(&format!("br $continue_{}\n", id)); // Возврат к началу цикла
+                self.code.push_str("end\n");
+                self.code.push_str("end\n");
+            }
+            
+            Statement::Loop { body, label, .. } => {
+                // Без проверки условия: выход только через br $break_N или return
+                let id = self.loops.len();
+                self.code.push_str(&format!("block $break_{}\n", id));
+                self.code.push_str(&format!("loop $continue_{}\n", id));
+                
+                self.loops.push(label.clone());
+                self.generate_block(body)?;
+                self.loops.pop();
+                
+                self.code.push_str(&format!("br $continue_{}\n", id));
+                self.code.push_str("end\n");
+                self.code.push_str("end\n");
+                // Без break конец блока недостижим: валидатору не нужно
+                // значение для возврата из функции после цикла
+                if !visit::loop_exits(label, body).breaks {
+                    self.code.push_str("unreachable\n");
                 }
+            }
+            
+            Statement::Break { label, .. } | Statement::Continue { label, .. } => {
+                let kind = if matches!(statement, Statement::Break { .. }) { "break" } else { "continue" };
+                let id = match label {
+                    Some(label) => self.loops.iter().rposition(|known| known.as_ref() == Some(label)),
+                    None => self.loops.len().checked_sub(1),
+                }.ok_or_else(|| unsupported(format!("{} outside of a loop", kind)))?;
+                self.generate_pending_deferred(id + 1)?;
+                self.code.push_str(&format!("br ${}_{}\n", kind, id));
+            }
+            
+            Statement::For { variable, start, end, body, label, .. } => {
+                // Счётчик и граница (вычисляется один раз) - локальные i32;
+                // счётчик виден только в теле цикла
+                let end_local = self.temp_local();
+                let counter = self.new_local(variable, &Type::I32);
+                
+                self.generate_expression(start)?;
+                self.code.push_str(&format!("local.set ${}\n", counter));
+                self.generate_expression(end)?;
+                self.code.push_str(&format!("local.set ${}\n", end_local));
+                
+                let outer_locals = self.locals.clone();
+                let outer_types = self.local_types.clone();
+                self.locals.insert(variable.clone(), counter.clone());
+                self.local_types.insert(variable.clone(), Type::I32);
+                
+                // continue выходит из блока тела к увеличению счётчика
+                let id = self.loops.len();
+                self.code.push_str(&format!("block $break_{}\n", id));
+                self.code.push_str(&format!("loop $for_{}\n", id));
+                
+                self.code.push_str(&format!("local.get ${}\n", counter));
+                self.code.push_str(&format!("local.get ${}\n", end_local));
+                self.code.push_str("i32.ge_s\n");
+                self.code.push_str(&format!("br_if $break_{}\n", id)); // Выход, когда счётчик дошёл до границы
+                
+                self.code.push_str(&format!("block $continue_{}\n", id));
+                self.loops.push(label.clone());
+                self.generate_block(body)?;
+                self.loops.pop();
+                self.code.push_str("end\n");
                 
-                self.code.push_str("br 0\n"); // Возврат к началу цикла
+                self.code.push_str(&format!("local.get ${}\n", counter));
+                self.code.push_str("i32.const 1\n");
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.set ${}\n", counter));
+                self.code.push_str(&format!("br $for_{}\n", id));
                 self.code.push_str("end\n");
                 self.code.push_str("end\n");
+                self.locals = outer_locals;
+                self.local_types = outer_types;
             }
             
-            Statement::If { condition, then_branch, else_branch } => {
+            Statement::If { condition, then_branch, else_branch, .. } => {
                 // Генерируем условие
-                self.generate_expression(condition);
+                self.generate_expression(condition)?;
                 
                 self.code.push_str("if\n");
                 
                 // Ветка then
-                for stmt in then_branch {
-                    self.generate_statement(stmt);
-                }
+                self.generate_block(then_branch)?;
                 
                 if let Some(else_branch) = else_branch {
                     self.code.push_str("else\n");
                     // Ветка else
-                    for stmt in else_branch {
-                        self.generate_statement(stmt);
-                    }
+                    self.generate_block(else_branch)?;
                 }
                 
                 self.code.push_str("end\n");
             }
         }
+        Ok(())
     }
 
-    fn generate_expression(&mut self, expression: &Expression) {
+    fn generate_expression(&mut self, expression: &Expression) -> Result<(), CodeGenError> {
         match expression {
+            // Литерал мог принять ожидаемый тип (`let x: i64 = 5;`)
             Expression::IntegerLiteral(value) => {
-                self.code.push_str(&format!("i32.const {}\n", value));
+                let t = self.type_to_wasm(&self.expression_type(expression));
+                self.code.push_str(&format!("{}.const {}\n", t, value));
             }
             
             Expression::FloatLiteral(value) => {
-                self.code.push_str(&format!("f32.const {}\n", value));
+                let t = self.type_to_wasm(&self.expression_type(expression));
+                self.code.push_str(&format!("{}.const {}\n", t, value));
             }
             
             Expression::BoolLiteral(value) => {
@@ -268,34 +617,61 @@ impl WasmGenerator {
             
             Expression::StringLiteral(value) => {
                 // Сохраняем строку в памяти и возвращаем указатель
-                let ptr = self.strings.len() as i32;
-                self.strings.push(value.clone());
+                let ptr = self.string_offset(value);
                 self.code.push_str(&format!("i32.const {}\n", ptr));
             }
             
+            Expression::Variable(name) if !self.local_types.contains_key(name) && self.constants.contains_key(name) => {
+                let value = self.constants[name].1.clone();
+                self.generate_expression(&value)?;
+            }
+            
+            Expression::Variable(name) if self.static_type(name).is_some() => {
+                self.code.push_str(&format!("global.get ${}\n", name));
+            }
+            
             Expression::Variable(name) => {
                 // Загружаем значение переменной
-                self.code.push_str(&format!("local.get ${}\n", name));
+                let local = self.local(name)?;
+                self.code.push_str(&format!("local.get ${}\n", local));
             }
             
             Expression::BinaryExpression { left, operator, right } => {
-                // Генерируем левый операнд
-                self.generate_expression(left);
-                // Генерируем правый операнд
-                self.generate_expression(right);
+                if self.expression_type(left) == Type::String || self.expression_type(right) == Type::String {
+                    return Err(unsupported("String operations"));
+                }
                 
-                // Генерируем операцию
+                // Операнды смешанного выражения (2 * 1.5) приводятся к общему типу
+                let left_type = self.expression_type(left);
+                let right_type = self.expression_type(right);
+                let operand_type = Self::common_numeric_type(&left_type, &right_type);
+                self.generate_expression(left)?;
+                self.generate_conversion(&left_type, &operand_type);
+                self.generate_expression(right)?;
+                self.generate_conversion(&right_type, &operand_type);
+                
+                let t = self.type_to_wasm(&operand_type);
+                let float = matches!(operand_type, Type::F32 | Type::F64);
+                // У целых деление, остаток, сдвиг и сравнения - знаковые
+                let signed = |name: &str| if float { name.to_string() } else { format!("{}_s", name) };
                 match operator {
-                    BinaryOperator::Add => self.code.push_str("i32.add\n"),
-                    BinaryOperator::Subtract => self.code.push_str("i32.sub\n"),
-                    BinaryOperator::Multiply => self.code.push_str("i32.mul\n"),
-                    BinaryOperator::Divide => self.code.push_str("i32.div_s\n"),
-                    BinaryOperator::Eq => self.code.push_str("i32.eq\n"),
-                    BinaryOperator::Neq => self.code.push_str("i32.ne\n"),
-                    BinaryOperator::Lt => self.code.push_str("i32.lt_s\n"),
-                    BinaryOperator::Gt => self.code.push_str("i32.gt_s\n"),
-                    BinaryOperator::Lte => self.code.push_str("i32.le_s\n"),
-                    BinaryOperator::Gte => self.code.push_str("i32.ge_s\n"),
+                    BinaryOperator::Add => self.code.push_str(&format!("{}.add\n", t)),
+                    BinaryOperator::Subtract => self.code.push_str(&format!("{}.sub\n", t)),
+                    BinaryOperator::Multiply => self.code.push_str(&format!("{}.mul\n", t)),
+                    BinaryOperator::Divide => self.code.push_str(&format!("{}.{}\n", t, signed("div"))),
+                    BinaryOperator::Mod if float => return Err(unsupported("Float remainder")),
+                    BinaryOperator::Mod => self.code.push_str(&format!("{}.rem_s\n", t)),
+                    BinaryOperator::BitAnd => self.code.push_str(&format!("{}.and\n", t)),
+                    BinaryOperator::BitOr => self.code.push_str(&format!("{}.or\n", t)),
+                    BinaryOperator::BitXor => self.code.push_str(&format!("{}.xor\n", t)),
+                    BinaryOperator::Shl => self.code.push_str(&format!("{}.shl\n", t)),
+                    BinaryOperator::Shr => self.code.push_str(&format!("{}.shr_s\n", t)),
+                    BinaryOperator::Eq => self.code.push_str(&format!("{}.eq\n", t)),
+                    BinaryOperator::Neq => self.code.push_str(&format!("{}.ne\n", t)),
+                    BinaryOperator::Lt => self.code.push_str(&format!("{}.{}\n", t, signed("lt"))),
+                    BinaryOperator::Gt => self.code.push_str(&format!("{}.{}\n", t, signed("gt"))),
+                    BinaryOperator::Lte => self.code.push_str(&format!("{}.{}\n", t, signed("le"))),
+                    BinaryOperator::Gte => self.code.push_str(&format!("{}.{}\n", t, signed("ge"))),
                     BinaryOperator::And => {
                         // Логическое И: a && b эквивалентно (a != 0) && (b != 0)
                         self.code.push_str("i32.and\n");
@@ -312,46 +688,805 @@ impl WasmGenerator {
             }
             
             Expression::FunctionCall { name, args } => {
-                // Генерируем аргументы
-                for arg in args.iter().rev() {
-                    self.generate_expression(arg);
+                if let Some(Type::Function(..)) = self.local_types.get(name) {
+                    return Err(unsupported("Function values"));
+                }
+                if self.is_numeric_builtin(name) {
+                    return self.generate_range_builtin(name, args);
                 }
                 
+                // Пользовательская функция или импорт встроенной
+                let builtin = stdlib::lookup(name).filter(|_| !self.function_returns.contains_key(name));
+                let (id, param_types) = match (builtin, self.function_params.get(name)) {
+                    (Some(builtin), _) => self.builtin_import(builtin, name, args),
+                    (None, Some(params)) => (function_id(name), params.clone()),
+                    (None, None) => return Err(CodeGenError::UndefinedFunction { name: name.clone() }),
+                };
+                
+                self.generate_call_args(args, &param_types)?;
+                
                 // Вызываем функцию
-                self.code.push_str(&format!("call ${}\n", name));
+                self.code.push_str(&format!("call {}\n", id));
+                // exit не возвращает управление: дальше стек произвольный
+                if builtin.is_some_and(|builtin| builtin.return_type == Type::Never) {
+                    self.code.push_str("unreachable\n");
+                }
             }
             
-            Expression::StructInitialization { struct_name: _, fields: _ } => {
+            Expression::StructInitialization { .. } => {
                 // TODO: Реализовать инициализацию структур
-                panic!("Struct initialization not implemented in WASM backend");
+                return Err(unsupported("Struct initialization"));
             }
             
             Expression::FieldAccess { expression: _, field_name: _ } => {
                 // TODO: Реализовать доступ к полям структур
-                panic!("Field access not implemented in WASM backend");
+                return Err(unsupported("Field access"));
             }
             
             Expression::TypeCast { expression, target_type } => {
-                // Генерируем выражение
-                self.generate_expression(expression);
-                
-                // Генерируем приведение типа
-                match target_type {
-                    Type::I32 => self.code.push_str("i32.trunc_f32_s\n"),
-                    Type::F32 => self.code.push_str("f32.convert_i32_s\n"),
-                    _ => panic!("Unsupported type cast in WASM: {:?}", target_type),
+                let source_type = self.expression_type(expression);
+                self.generate_expression(expression)?;
+                match (&source_type, target_type) {
+                    (Type::F32, Type::I32) => self.code.push_str("i32.trunc_f32_s\n"),
+                    (Type::I64, Type::I32) => self.code.push_str("i32.wrap_i64\n"),
+                    (Type::F64, Type::F32) => self.code.push_str("f32.demote_f64\n"),
+                    // Расширения и приведение к трейту (тот же указатель)
+                    (source, target) => self.generate_conversion(source, target),
                 }
             }
             
-            Expression::Move { expression: _ } => {
-                // В WebAssembly нет семантики перемещения
-                panic!("Move semantics not implemented in WASM backend");
+            // Перемещение проверено тайпчекером; в коде это то же значение
+            Expression::Move { expression } => {
+                self.generate_expression(expression)?;
             }
             
             Expression::Borrow { expression: _, mutable: _ } => {
                 // В WebAssembly нет семантики заимствования
-                panic!("Borrow semantics not implemented in WASM backend");
+                return Err(unsupported("Borrow semantics"));
             }
+            
+            Expression::Ref { expression: _ } => {
+                // TODO: Нужна куча в линейной памяти
+                return Err(unsupported("References"));
+            }
+            
+            Expression::ArrayLiteral { elements } => {
+                // Раскладка массива: [длина: i32][элементы подряд]. Тайпчекер
+                // записывает литерал как [T; N]; вне объявления [T; N] он в куче
+                let element_type = match self.expression_type(expression) {
+                    Type::Array(element) | Type::FixedArray(element, _) => *element,
+                    other => return Err(unsupported(format!("Array literal of type {}", other))),
+                };
+                let size = Self::element_size(&element_type);
+                let base = self.temp_local();
+                
+                // Выделяем память сдвигом указателя кучи
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("local.set ${}\n", base));
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("i32.const {}\n", 4 + size * elements.len()));
+                self.code.push_str("i32.add\n");
+                self.code.push_str("global.set $__heap_ptr\n");
+                
+                self.code.push_str(&format!("local.get ${}\n", base));
+                self.code.push_str(&format!("i32.const {}\n", elements.len()));
+                self.code.push_str("i32.store\n");
+                
+                let store = format!("{}.store", self.type_to_wasm(&element_type));
+                for (i, element) in elements.iter().enumerate() {
+                    self.code.push_str(&format!("local.get ${}\n", base));
+                    self.generate_expression(element)?;
+                    self.code.push_str(&format!("{} offset={}\n", store, 4 + size * i));
+                }
+                
+                self.code.push_str(&format!("local.get ${}\n", base));
+            }
+            
+            // Вне объявления массива фиксированной длины - временный массив в куче
+            Expression::ArrayRepeat { value: _, count } => {
+                let element_type = match self.expression_type(expression) {
+                    Type::Array(element) | Type::FixedArray(element, _) => *element,
+                    other => return Err(unsupported(format!("Array repeat of type {}", other))),
+                };
+                let base = self.temp_local();
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("local.set ${}\n", base));
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("i32.const {}\n", 4 + Self::element_size(&element_type) * count));
+                self.code.push_str("i32.add\n");
+                self.code.push_str("global.set $__heap_ptr\n");
+                self.generate_array_init(&base, &element_type, *count, expression)?;
+                self.code.push_str(&format!("local.get ${}\n", base));
+            }
+            
+            Expression::Index { expression: array, index } => {
+                let element_type = self.expression_type(expression);
+                let size = Self::element_size(&element_type);
+                let base = self.temp_local();
+                let position = self.temp_local();
+                
+                self.generate_expression(array)?;
+                self.code.push_str(&format!("local.set ${}\n", base));
+                self.generate_expression(index)?;
+                self.code.push_str(&format!("local.set ${}\n", position));
+                
+                // Выход за границы (в том числе отрицательный индекс) - ловушка
+                self.code.push_str(&format!("local.get ${}\n", position));
+                self.code.push_str(&format!("local.get ${}\n", base));
+                self.code.push_str("i32.load\n");
+                self.code.push_str("i32.ge_u\n");
+                self.code.push_str("if\n");
+                self.code.push_str("unreachable\n");
+                self.code.push_str("end\n");
+                
+                self.code.push_str(&format!("local.get ${}\n", base));
+                self.code.push_str(&format!("local.get ${}\n", position));
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.mul\n");
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("{}.load offset=4\n", self.type_to_wasm(&element_type)));
+            }
+            
+            // Массивы неизменяемы, поэтому срез - копия диапазона с той же
+            // раскладкой [длина][элементы]: индексация и len работают одинаково,
+            // а массив передаётся туда, где ждут срез, без преобразования
+            Expression::Slice { expression: array, start, end } => {
+                let element_type = match self.expression_type(expression) {
+                    Type::Slice(element) => *element,
+                    other => return Err(unsupported(format!("Slice of type {}", other))),
+                };
+                let size = Self::element_size(&element_type);
+                let source = self.temp_local();
+                let from = self.temp_local();
+                let to = self.temp_local();
+                let base = self.temp_local();
+                let i = self.temp_local();
+                
+                self.generate_expression(array)?;
+                self.code.push_str(&format!("local.set ${}\n", source));
+                match start {
+                    Some(start) => self.generate_expression(start)?,
+                    None => self.code.push_str("i32.const 0\n"),
+                }
+                self.code.push_str(&format!("local.set ${}\n", from));
+                match end {
+                    Some(end) => self.generate_expression(end)?,
+                    None => {
+                        self.code.push_str(&format!("local.get ${}\n", source));
+                        self.code.push_str("i32.load\n");
+                    }
+                }
+                self.code.push_str(&format!("local.set ${}\n", to));
+                
+                // start > end или end > длины (в том числе отрицательные границы) - ловушка
+                self.code.push_str(&format!("local.get ${}\n", from));
+                self.code.push_str(&format!("local.get ${}\n", to));
+                self.code.push_str("i32.gt_u\n");
+                self.code.push_str(&format!("local.get ${}\n", to));
+                self.code.push_str(&format!("local.get ${}\n", source));
+                self.code.push_str("i32.load\n");
+                self.code.push_str("i32.gt_u\n");
+                self.code.push_str("i32.or\n");
+                self.code.push_str("if\n");
+                self.code.push_str("unreachable\n");
+                self.code.push_str("end\n");
+                
+                // Выделяем 4 + size * (end - start) байт
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("local.set ${}\n", base));
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("local.get ${}\n", to));
+                self.code.push_str(&format!("local.get ${}\n", from));
+                self.code.push_str("i32.sub\n");
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.mul\n");
+                self.code.push_str("i32.const 4\n");
+                self.code.push_str("i32.add\n");
+                self.code.push_str("i32.add\n");
+                self.code.push_str("global.set $__heap_ptr\n");
+                
+                self.code.push_str(&format!("local.get ${}\n", base));
+                self.code.push_str(&format!("local.get ${}\n", to));
+                self.code.push_str(&format!("local.get ${}\n", from));
+                self.code.push_str("i32.sub\n");
+                self.code.push_str("i32.store\n");
+                
+                // Копируем элементы по одному: i - смещение в байтах от начала данных
+                let load = format!("{}.load", self.type_to_wasm(&element_type));
+                let store = format!("{}.store", self.type_to_wasm(&element_type));
+                self.code.push_str("i32.const 0\n");
+                self.code.push_str(&format!("local.set ${}\n", i));
+                self.code.push_str("block\n");
+                self.code.push_str("loop\n");
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str(&format!("local.get ${}\n", base));
+                self.code.push_str("i32.load\n");
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.mul\n");
+                self.code.push_str("i32.ge_u\n");
+                self.code.push_str("br_if 1\n");
+                self.code.push_str(&format!("local.get ${}\n", base));
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.get ${}\n", source));
+                self.code.push_str(&format!("local.get ${}\n", from));
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.mul\n");
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("{} offset=4\n", load));
+                self.code.push_str(&format!("{} offset=4\n", store));
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.set ${}\n", i));
+                self.code.push_str("br 0\n");
+                self.code.push_str("end\n");
+                self.code.push_str("end\n");
+                
+                self.code.push_str(&format!("local.get ${}\n", base));
+            }
+            
+            Expression::MethodCall { receiver, method, .. } if method == "len" && matches!(
+                self.expression_type(receiver),
+                Type::Array(_) | Type::Slice(_) | Type::FixedArray(..)
+            ) => {
+                self.generate_expression(receiver)?;
+                self.code.push_str("i32.load\n");
+            }
+            
+            Expression::MethodCall { receiver, method, args } => {
+                // self - первый параметр функции `Type::method`
+                let type_name = match self.expression_type(receiver) {
+                    Type::Struct(name) | Type::Enum(name) => name,
+                    other => return Err(unsupported(format!("Method call on {}", other))),
+                };
+                let name = method_name(&type_name, method);
+                let params = self.function_params.get(&name).cloned().unwrap_or_default();
+                self.generate_expression(receiver)?;
+                self.generate_call_args(args, params.get(1..).unwrap_or_default())?;
+                self.code.push_str(&format!("call {}\n", function_id(&name)));
+            }
+            
+            // Point::new(..) - функция из impl-блока
+            Expression::EnumVariant { enum_name, variant, args } if self.function_returns.contains_key(&method_name(enum_name, variant)) => {
+                let name = method_name(enum_name, variant);
+                let params = self.function_params[&name].clone();
+                self.generate_call_args(args, &params)?;
+                self.code.push_str(&format!("call {}\n", function_id(&name)));
+            }
+            
+            Expression::EnumVariant { enum_name, .. } if enum_name == "Option" || enum_name == "Result" => {
+                // TODO: Нужна раскладка вариантов с полями обобщённых типов
+                return Err(unsupported("Option and Result"));
+            }
+            
+            Expression::Try { .. } => {
+                return Err(unsupported("Operator ?"));
+            }
+            
+            Expression::Lambda { .. } => {
+                return Err(unsupported("Closures"));
+            }
+            
+            Expression::EnumVariant { enum_name, variant, args } => {
+                let index = self.variant_index(enum_name, variant)?;
+                if !self.enum_def(enum_name)?.has_payload() {
+                    self.code.push_str(&format!("i32.const {}\n", index));
+                    return Ok(());
+                }
+                
+                let offsets = self.field_offsets(enum_name, variant)?;
+                let size = offsets.last().map_or(4, |(offset, ty)| offset + Self::element_size(ty));
+                let base = self.temp_local();
+                
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("local.set ${}\n", base));
+                self.code.push_str("global.get $__heap_ptr\n");
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.add\n");
+                self.code.push_str("global.set $__heap_ptr\n");
+                
+                self.code.push_str(&format!("local.get ${}\n", base));
+                self.code.push_str(&format!("i32.const {}\n", index));
+                self.code.push_str("i32.store\n");
+                for (arg, (offset, ty)) in args.iter().zip(offsets) {
+                    self.code.push_str(&format!("local.get ${}\n", base));
+                    self.generate_expression(arg)?;
+                    let arg_type = self.expression_type(arg);
+                    self.generate_conversion(&arg_type, &ty);
+                    self.code.push_str(&format!("{}.store offset={}\n", self.type_to_wasm(&ty), offset));
+                }
+                
+                self.code.push_str(&format!("local.get ${}\n", base));
+            }
+            
+            Expression::Match { subject, arms } => {
+                // Цепочка if/else по веткам; ни одна не подошла - ловушка
+                let boxed = self.payload_enum(&self.expression_type(subject));
+                let value = self.temp_local();
+                self.generate_expression(subject)?;
+                self.code.push_str(&format!("local.set ${}\n", value));
+                
+                let block_type = self.result_type(&self.expression_type(expression));
+                
+                let mut open_ifs = 0;
+                let mut has_wildcard = false;
+                for arm in arms {
+                    let constant = match &arm.pattern {
+                        Pattern::Wildcard => {
+                            self.generate_expression(&arm.body)?;
+                            has_wildcard = true;
+                            break;
+                        }
+                        Pattern::Integer(value) => *value,
+                        Pattern::Bool(value) => *value as i32,
+                        Pattern::Variant { enum_name, variant, .. } => self.variant_index(enum_name, variant)?,
+                    };
+                    self.code.push_str(&format!("local.get ${}\n", value));
+                    if boxed {
+                        self.code.push_str("i32.load\n"); // тег
+                    }
+                    self.code.push_str(&format!("i32.const {}\n", constant));
+                    self.code.push_str("i32.eq\n");
+                    self.code.push_str(&format!("if{}\n", block_type));
+                    // Привязки образца видны только в своей ветке
+                    let outer_locals = self.locals.clone();
+                    let outer_types = self.local_types.clone();
+                    if let Pattern::Variant { enum_name, variant, bindings } = &arm.pattern {
+                        let offsets = self.field_offsets(enum_name, variant)?;
+                        for (binding, (offset, ty)) in bindings.iter().zip(offsets) {
+                            if binding == "_" {
+                                continue;
+                            }
+                            self.code.push_str(&format!("local.get ${}\n", value));
+                            self.code.push_str(&format!("{}.load offset={}\n", self.type_to_wasm(&ty), offset));
+                            let local = self.declare_local(binding, &ty);
+                            self.code.push_str(&format!("local.set ${}\n", local));
+                        }
+                    }
+                    self.generate_expression(&arm.body)?;
+                    self.locals = outer_locals;
+                    self.local_types = outer_types;
+                    self.code.push_str("else\n");
+                    open_ifs += 1;
+                }
+                if !has_wildcard {
+                    self.code.push_str("unreachable\n");
+                }
+                for _ in 0..open_ifs {
+                    self.code.push_str("end\n");
+                }
+            }
+            
+            Expression::If { condition, then_branch, else_branch } => {
+                let block_type = self.result_type(&self.expression_type(expression));
+                self.generate_expression(condition)?;
+                self.code.push_str(&format!("if{}\n", block_type));
+                self.generate_expression(then_branch)?;
+                self.code.push_str("else\n");
+                self.generate_expression(else_branch)?;
+                self.code.push_str("end\n");
+            }
+        }
+        Ok(())
+    }
+    
+    /// Перечисление из программы; встроенные Option и Result бэкенд пока
+    /// не раскладывает в памяти
+    fn enum_def(&self, enum_name: &str) -> Result<&Enum, CodeGenError> {
+        self.enums.get(enum_name).ok_or_else(|| unsupported("Option and Result"))
+    }
+    
+    fn variant_index(&self, enum_name: &str, variant: &str) -> Result<i32, CodeGenError> {
+        self.enum_def(enum_name)?.variants.iter()
+            .position(|v| v.name == variant)
+            .map(|index| index as i32)
+            .ok_or_else(|| unsupported(format!("Variant {}::{}", enum_name, variant)))
+    }
+    
+    /// Смещения полей варианта в блоке: тег занимает первые 4 байта
+    fn field_offsets(&self, enum_name: &str, variant: &str) -> Result<Vec<(usize, Type)>, CodeGenError> {
+        let fields = &self.enum_def(enum_name)?.variant(variant)
+            .ok_or_else(|| unsupported(format!("Variant {}::{}", enum_name, variant)))?
+            .fields;
+        let mut offset = 4;
+        Ok(fields.iter()
+            .map(|ty| {
+                let field = (offset, ty.clone());
+                offset += Self::element_size(ty);
+                field
+            })
+            .collect())
+    }
+    
+    /// Значение перечисления хранится в куче (есть варианты с данными)
+    fn payload_enum(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Enum(name) | Type::Struct(name) => self.enums.get(name).is_some_and(Enum::has_payload),
+            _ => false,
+        }
+    }
+    
+    /// Операторы блока, затем его defer в обратном порядке. Объявленные в
+    /// блоке переменные после него не видны
+    fn generate_block(&mut self, statements: &[Statement]) -> Result<(), CodeGenError> {
+        let outer_locals = self.locals.clone();
+        let outer_types = self.local_types.clone();
+        self.deferred.push((self.loops.len(), Vec::new()));
+        for statement in statements {
+            self.generate_statement(statement)?;
+        }
+        if let Some((_, deferred)) = self.deferred.pop() {
+            for deferred in deferred.into_iter().rev() {
+                self.generate_deferred(deferred)?;
+            }
+        }
+        self.locals = outer_locals;
+        self.local_types = outer_types;
+        Ok(())
+    }
+    
+    /// Выражение defer с переменными, видимыми в месте defer: переход из
+    /// вложенного блока не должен подменять их затеняющими
+    fn generate_deferred(&mut self, deferred: Deferred) -> Result<(), CodeGenError> {
+        let locals = std::mem::replace(&mut self.locals, deferred.locals);
+        let local_types = std::mem::replace(&mut self.local_types, deferred.local_types);
+        self.generate_statement(&Statement::Expression(deferred.expression, Position::default()))?;
+        self.locals = locals;
+        self.local_types = local_types;
+        Ok(())
+    }
+    
+    /// defer блоков, из которых выходит переход: return - всех (`loops` = 0),
+    /// break и continue цикла N - лежащих внутри его тела (`loops` = N + 1)
+    fn generate_pending_deferred(&mut self, loops: usize) -> Result<(), CodeGenError> {
+        let pending: Vec<Deferred> = self.deferred.iter().rev()
+            .filter(|(depth, _)| *depth >= loops)
+            .flat_map(|(_, deferred)| deferred.iter().rev().cloned())
+            .collect();
+        for deferred in pending {
+            self.generate_deferred(deferred)?;
+        }
+        Ok(())
+    }
+    
+    /// Освобождает кадр функции перед выходом из неё
+    fn generate_frame_release(&mut self) {
+        if self.has_frame {
+            self.code.push_str("local.get $__frame_base\n");
+            self.code.push_str("global.set $__stack_ptr\n");
+        }
+    }
+    
+    /// Заполняет массив [element; length] по адресу из локальной `target`:
+    /// литерал пишется на место, другой массив копируется словами по 4 байта
+    fn generate_array_init(&mut self, target: &str, element: &Type, length: usize, value: &Expression) -> Result<(), CodeGenError> {
+        let size = Self::element_size(element);
+        let store = format!("{}.store", self.type_to_wasm(element));
+        match value {
+            Expression::ArrayLiteral { elements } => {
+                self.code.push_str(&format!("local.get ${}\n", target));
+                self.code.push_str(&format!("i32.const {}\n", length));
+                self.code.push_str("i32.store\n");
+                for (i, element) in elements.iter().enumerate() {
+                    self.code.push_str(&format!("local.get ${}\n", target));
+                    self.generate_expression(element)?;
+                    self.code.push_str(&format!("{} offset={}\n", store, 4 + size * i));
+                }
+            }
+            Expression::ArrayRepeat { value, count } => {
+                self.generate_expression(value)?;
+                let filler = self.typed_temp_local(&self.type_to_wasm(element));
+                self.code.push_str(&format!("local.set ${}\n", filler));
+                self.code.push_str(&format!("local.get ${}\n", target));
+                self.code.push_str(&format!("i32.const {}\n", count));
+                self.code.push_str("i32.store\n");
+                
+                // i - смещение в байтах от начала данных
+                let i = self.temp_local();
+                self.code.push_str("i32.const 0\n");
+                self.code.push_str(&format!("local.set ${}\n", i));
+                self.code.push_str("block\n");
+                self.code.push_str("loop\n");
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str(&format!("i32.const {}\n", size * count));
+                self.code.push_str("i32.ge_u\n");
+                self.code.push_str("br_if 1\n");
+                self.code.push_str(&format!("local.get ${}\n", target));
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.get ${}\n", filler));
+                self.code.push_str(&format!("{} offset=4\n", store));
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str(&format!("i32.const {}\n", size));
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.set ${}\n", i));
+                self.code.push_str("br 0\n");
+                self.code.push_str("end\n");
+                self.code.push_str("end\n");
+            }
+            _ => {
+                let source = self.temp_local();
+                let i = self.temp_local();
+                self.generate_expression(value)?;
+                self.code.push_str(&format!("local.set ${}\n", source));
+                self.code.push_str("i32.const 0\n");
+                self.code.push_str(&format!("local.set ${}\n", i));
+                self.code.push_str("block\n");
+                self.code.push_str("loop\n");
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str(&format!("i32.const {}\n", 4 + size * length));
+                self.code.push_str("i32.ge_u\n");
+                self.code.push_str("br_if 1\n");
+                self.code.push_str(&format!("local.get ${}\n", target));
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.get ${}\n", source));
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str("i32.add\n");
+                self.code.push_str("i32.load\n");
+                self.code.push_str("i32.store\n");
+                self.code.push_str(&format!("local.get ${}\n", i));
+                self.code.push_str("i32.const 4\n");
+                self.code.push_str("i32.add\n");
+                self.code.push_str(&format!("local.set ${}\n", i));
+                self.code.push_str("br 0\n");
+                self.code.push_str("end\n");
+                self.code.push_str("end\n");
+            }
+        }
+        Ok(())
+    }
+    
+    /// Служебная локальная переменная i32 для промежуточных значений
+    fn temp_local(&mut self) -> String {
+        self.typed_temp_local("i32")
+    }
+    
+    fn typed_temp_local(&mut self, wasm_type: &str) -> String {
+        let name = format!("__tmp{}", self.temps.len());
+        self.temps.push(wasm_type.to_string());
+        name
+    }
+    
+    /// Новая локальная переменная для `name`: имя, ещё не занятое в функции.
+    /// Видимой под именем `name` она становится после `declare_local` или
+    /// вставки в `locals`
+    fn new_local(&mut self, name: &str, ty: &Type) -> String {
+        let mut local = name.to_string();
+        let mut suffix = 0;
+        while self.local_names.contains(&local) {
+            suffix += 1;
+            local = format!("{}_{}", name, suffix);
+        }
+        self.local_names.insert(local.clone());
+        self.declared_locals.push((local.clone(), self.type_to_wasm(ty)));
+        local
+    }
+    
+    /// Объявляет переменную `name` в текущей области видимости
+    fn declare_local(&mut self, name: &str, ty: &Type) -> String {
+        let local = self.new_local(name, ty);
+        self.locals.insert(name.to_string(), local.clone());
+        self.local_types.insert(name.to_string(), ty.clone());
+        local
+    }
+    
+    /// WAT-имя видимой переменной
+    fn local(&self, name: &str) -> Result<String, CodeGenError> {
+        self.locals.get(name)
+            .cloned()
+            .ok_or_else(|| CodeGenError::UndefinedVariable { name: name.to_string() })
+    }
+    
+    /// Аргументы вызова в порядке параметров, с приведением к их типам
+    fn generate_call_args(&mut self, args: &[Expression], params: &[Type]) -> Result<(), CodeGenError> {
+        for (i, arg) in args.iter().enumerate() {
+            self.generate_expression(arg)?;
+            if let Some(param_type) = params.get(i) {
+                let arg_type = self.expression_type(arg);
+                self.generate_conversion(&arg_type, param_type);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Импорт встроенной функции из модуля `env`: WAT-имя и типы параметров.
+    /// У функций с любым числом аргументов (print, format) и обобщённых импорт
+    /// свой для каждого набора типов аргументов: `print("x")` -
+    /// `print_string`, `print(1, 2.0)` - `print_i32_f32`
+    fn builtin_import(&mut self, builtin: &Builtin, name: &str, args: &[Expression]) -> (String, Vec<Type>) {
+        let (field, params) = if builtin.variadic || !builtin.type_params.is_empty() {
+            let arg_types: Vec<Type> = args.iter().map(|arg| self.expression_type(arg)).collect();
+            let field = arg_types.iter()
+                .fold(name.to_string(), |field, ty| format!("{}_{}", field, type_suffix(ty)));
+            (field, arg_types)
+        } else {
+            (name.to_string(), builtin.param_types())
+        };
+        // Префикс не даёт импорту совпасть с пользовательской функцией
+        let id = function_id(&format!("{}.{}", IMPORT_MODULE, field));
+        if !self.imports.iter().any(|(known, _)| *known == id) {
+            let call = Expression::FunctionCall { name: name.to_string(), args: args.to_vec() };
+            let return_type = self.expression_type(&call);
+            let param_list: String = params.iter()
+                .map(|ty| format!(" (param {})", self.type_to_wasm(ty)))
+                .collect();
+            let import = format!(
+                "  (import \"{}\" \"{}\" (func {}{}{}))\n",
+                IMPORT_MODULE, field, id, param_list, self.result_type(&return_type)
+            );
+            self.imports.push((id.clone(), import));
+        }
+        (id, params)
+    }
+    
+    /// Встроенная функция, перегруженная для i32 и f32, а не пользовательская
+    fn is_numeric_builtin(&self, name: &str) -> bool {
+        !self.function_returns.contains_key(name) && stdlib::lookup(name).is_some_and(|builtin| builtin.numeric)
+    }
+    
+    /// clamp, wrap и map_range (ranges.rs) без вызова: аргументы кладутся во
+    /// временные переменные, результат считается на месте
+    fn generate_range_builtin(&mut self, name: &str, args: &[Expression]) -> Result<(), CodeGenError> {
+        let ty = self.expression_type(&Expression::FunctionCall { name: name.to_string(), args: args.to_vec() });
+        let t = self.type_to_wasm(&ty);
+        let mut values = Vec::new();
+        for arg in args {
+            let local = self.typed_temp_local(&t);
+            self.generate_expression(arg)?;
+            let arg_type = self.expression_type(arg);
+            self.generate_conversion(&arg_type, &ty);
+            self.code.push_str(&format!("local.set ${}\n", local));
+            values.push(local);
+        }
+        let get = |index: usize| format!("local.get ${}\n", values[index]);
+        let code = match (name, ty == Type::F32) {
+            ("clamp", true) => format!("{}{}f32.max\n{}f32.min\n", get(0), get(1), get(2)),
+            // max и min через select: первое значение, если условие истинно
+            ("clamp", false) => format!(
+                "{x}{lo}{x}{lo}i32.gt_s\nselect\nlocal.set ${xs}\n{x}{hi}{x}{hi}i32.lt_s\nselect\n",
+                x = get(0), lo = get(1), hi = get(2), xs = values[0],
+            ),
+            // size = hi - lo; lo + (x - lo) по модулю size
+            ("wrap", true) => format!(
+                "{hi}{lo}f32.sub\nlocal.set ${hs}\n{x}{lo}f32.sub\nlocal.set ${xs}\n{lo}{x}{hi}{x}{hi}f32.div\nf32.floor\nf32.mul\nf32.sub\nf32.add\n",
+                x = get(0), lo = get(1), hi = get(2), xs = values[0], hs = values[2],
+            ),
+            ("wrap", false) => format!(
+                "{hi}{lo}i32.sub\nlocal.set ${hs}\n{x}{lo}i32.sub\n{hi}i32.rem_s\nlocal.set ${xs}\n{x}{hi}i32.add\n{x}{x}i32.const 0\ni32.lt_s\nselect\n{lo}i32.add\n",
+                x = get(0), lo = get(1), hi = get(2), xs = values[0], hs = values[2],
+            ),
+            // b1 + (x - a1) * (b2 - b1) / (a2 - a1)
+            (_, float) => format!(
+                "{b1}{x}{a1}{t}.sub\n{b2}{b1}{t}.sub\n{t}.mul\n{a2}{a1}{t}.sub\n{t}.{div}\n{t}.add\n",
+                x = get(0), a1 = get(1), a2 = get(2), b1 = get(3), b2 = get(4), t = t,
+                div = if float { "div" } else { "div_s" },
+            ),
+        };
+        self.code.push_str(&code);
+        Ok(())
+    }
+    
+    fn element_size(ty: &Type) -> usize {
+        match ty {
+            Type::I64 | Type::F64 => 8,
+            _ => 4,
+        }
+    }
+    
+    /// Тип выражения по уже проверенной программе; нужен там, где от типа
+    /// зависят инструкции (размер и загрузка элементов массива, целая или
+    /// дробная арифметика)
+    fn expression_type(&self, expression: &Expression) -> Type {
+        if let Some(ty) = self.types.get(expression) {
+            return ty.clone();
+        }
+        match expression {
+            Expression::IntegerLiteral(_) => Type::I32,
+            Expression::FloatLiteral(_) => Type::F32,
+            Expression::StringLiteral(_) => Type::String,
+            Expression::BoolLiteral(_) => Type::Bool,
+            Expression::Variable(name) => self.local_types.get(name)
+                .or_else(|| self.constants.get(name).map(|(ty, _)| ty))
+                .or_else(|| self.static_type(name))
+                .cloned()
+                .unwrap_or(Type::I32),
+            Expression::BinaryExpression { left, operator, .. } => match operator {
+                BinaryOperator::Add
+                | BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Mod => self.expression_type(left),
+                operator if operator.is_bitwise() => self.expression_type(left),
+                _ => Type::Bool,
+            },
+            Expression::FunctionCall { name, args } if self.is_numeric_builtin(name) => {
+                match args.iter().all(|arg| self.expression_type(arg) == Type::I32) {
+                    true => Type::I32,
+                    false => Type::F32,
+                }
+            }
+            Expression::FunctionCall { name, .. } => self.function_returns.get(name)
+                .cloned()
+                .or_else(|| stdlib::lookup(name).map(|builtin| builtin.return_type.clone()))
+                .unwrap_or(Type::I32),
+            Expression::StructInitialization { struct_name, .. } => Type::Struct(struct_name.clone()),
+            Expression::TypeCast { target_type, .. } => target_type.clone(),
+            Expression::Move { expression } | Expression::Borrow { expression, .. } => {
+                self.expression_type(expression)
+            }
+            Expression::Ref { expression } => Type::Ref(Box::new(self.expression_type(expression))),
+            Expression::ArrayLiteral { elements } => Type::Array(Box::new(
+                elements.first().map_or(Type::Void, |element| self.expression_type(element)),
+            )),
+            Expression::ArrayRepeat { value, count } => Type::FixedArray(Box::new(self.expression_type(value)), *count),
+            Expression::Index { expression, .. } => match self.expression_type(expression) {
+                Type::Array(element) | Type::Slice(element) | Type::FixedArray(element, _) => *element,
+                _ => Type::I32,
+            },
+            Expression::Slice { expression, .. } => match self.expression_type(expression) {
+                Type::Array(element) | Type::Slice(element) | Type::FixedArray(element, _) => Type::Slice(element),
+                _ => Type::I32,
+            },
+            Expression::FieldAccess { .. } => Type::I32,
+            Expression::EnumVariant { enum_name, variant, .. } if self.function_returns.contains_key(&method_name(enum_name, variant)) => {
+                self.function_returns[&method_name(enum_name, variant)].clone()
+            }
+            Expression::EnumVariant { enum_name, .. } => Type::Enum(enum_name.clone()),
+            Expression::MethodCall { receiver, method, .. } if method == "len" && matches!(
+                self.expression_type(receiver),
+                Type::Array(_) | Type::Slice(_) | Type::FixedArray(..)
+            ) => Type::I32,
+            Expression::MethodCall { receiver, method, .. } => match self.expression_type(receiver) {
+                Type::Struct(name) | Type::Enum(name) => self.function_returns
+                    .get(&method_name(&name, method))
+                    .cloned()
+                    .unwrap_or(Type::I32),
+                _ => Type::I32,
+            },
+            Expression::Match { arms, .. } => arms.first()
+                .map_or(Type::Void, |arm| self.expression_type(&arm.body)),
+            Expression::If { then_branch, .. } => self.expression_type(then_branch),
+            Expression::Try { expression } => match self.expression_type(expression) {
+                Type::Option(inner) | Type::Result(inner, _) => *inner,
+                _ => Type::I32,
+            },
+            Expression::Lambda { params, return_type, .. } => Type::Function(
+                params.iter().map(|param| param.param_type.clone()).collect(),
+                Box::new(return_type.clone()),
+            ),
+        }
+    }
+
+    /// Тип, в котором считается арифметика или сравнение смешанных операндов
+    fn common_numeric_type(left: &Type, right: &Type) -> Type {
+        [Type::F64, Type::F32, Type::I64]
+            .into_iter()
+            .find(|ty| left == ty || right == ty)
+            .unwrap_or(Type::I32)
+    }
+
+    /// Приведение значения на вершине стека от `from` к `to`
+    fn generate_conversion(&mut self, from: &Type, to: &Type) {
+        let instruction = match (from, to) {
+            (Type::I32, Type::F32) => "f32.convert_i32_s",
+            (Type::I32, Type::F64) => "f64.convert_i32_s",
+            (Type::F32, Type::F64) => "f64.promote_f32",
+            (Type::I32, Type::I64) => "i64.extend_i32_s",
+            _ => return,
+        };
+        self.code.push_str(instruction);
+        self.code.push('\n');
+    }
+
+    /// `(result t)` функции или блока; у void и ! результата нет
+    fn result_type(&self, ty: &Type) -> String {
+        match ty {
+            Type::Void | Type::Never => String::new(),
+            ty => format!(" (result {})", self.type_to_wasm(ty)),
         }
     }
 
@@ -363,8 +1498,83 @@ impl WasmGenerator {
             Type::F64 => "f64".to_string(),
             Type::Bool => "i32".to_string(), // bool представляется как i32
             Type::String => "i32".to_string(), // указатель на строку
-            Type::Void => "void".to_string(),
-            Type::Struct(_) => "i32".to_string(), // указатель на структуру
+            Type::Void | Type::Never => "void".to_string(),
+            Type::Struct(_) | Type::Generic(..) | Type::Ref(_) => "i32".to_string(), // указатель на структуру
+            Type::Array(_) | Type::Slice(_) | Type::FixedArray(..) => "i32".to_string(), // указатель на массив
+            Type::Enum(_) | Type::Option(_) | Type::Result(..) => "i32".to_string(), // тег или указатель на вариант
+            Type::Function(..) => "i32".to_string(), // индекс в таблице функций
         }
     }
-}
\ No newline at end of file
+}
+
+fn unsupported(feature: impl Into<String>) -> CodeGenError {
+    CodeGenError::Unsupported { feature: feature.into() }
+}
+
+/// WAT-имя функции: символы, недопустимые в идентификаторе (пробелы и
+/// запятые в именах копий вроде `Pair<i32, f32>::new`), заменяются на `_`
+fn function_id(name: &str) -> String {
+    let id: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c) { c } else { '_' })
+        .collect();
+    format!("${}", id)
+}
+
+/// Часть имени импорта по типу аргумента: `string`, `i32`, `_i32_` для `[i32]`
+fn type_suffix(ty: &Type) -> String {
+    ty.to_string().chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Байты для строки WAT: печатный ASCII как есть, остальное (переводы строк,
+/// кавычки, обратная косая черта, не-ASCII UTF-8) - как `\XX`
+fn wat_string(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if (0x20..=0x7E).contains(&byte) && byte != b'"' && byte != b'\\' {
+            text.push(byte as char);
+        } else {
+            text.push_str(&format!("\\{:02x}", byte));
+        }
+    }
+    text
+}
+
+impl CodeGenerator for WasmGenerator {
+    fn extension(&self) -> &'static str {
+        "wat"
+    }
+
+    fn generate(&mut self, program: &TypedProgram, output_path: &Path) -> Result<(), CodeGenError> {
+        let module = self.generate_typed(program)?;
+        fs::write(output_path, module)?;
+        Ok(())
+    }
+}
+
+/// Выражение defer и переменные, видимые в месте defer
+#[derive(Clone)]
+struct Deferred {
+    expression: Expression,
+    locals: HashMap<String, String>,
+    local_types: HashMap<String, Type>,
+}
+
+/// Объявляет ли функция массивы [T; N], которым нужен кадр на стеке
+fn uses_fixed_arrays(function: &Function) -> bool {
+    struct FixedArrayFinder(bool);
+
+    impl Visitor for FixedArrayFinder {
+        fn visit_statement(&mut self, statement: &Statement) {
+            if let Statement::VariableDeclaration { var_type: Type::FixedArray(..), .. } = statement {
+                self.0 = true;
+            }
+            walk_statement(self, statement);
+        }
+    }
+
+    let mut finder = FixedArrayFinder(false);
+    finder.visit_function(function);
+    finder.0
+}
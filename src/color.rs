@@ -0,0 +1,73 @@
+// color.rs - палитра, безопасная для дальтоников, и контраст по WCAG 2.x
+//
+// Палитра Окабе-Ито различима при всех распространённых формах
+// дальтонизма. Контраст считается по формуле WCAG: (L1 + 0.05) / (L2 + 0.05),
+// где L - относительная яркость в линейном sRGB; результат от 1 до 21.
+
+/// Минимальный контраст обычного текста (WCAG AA)
+pub const WCAG_AA_TEXT: f32 = 4.5;
+
+/// Именованные цвета для palette("имя")
+pub const PALETTE: &[(&str, [u8; 3])] = &[
+    ("black", [0, 0, 0]),
+    ("white", [255, 255, 255]),
+    ("orange", [230, 159, 0]),
+    ("sky_blue", [86, 180, 233]),
+    ("bluish_green", [0, 158, 115]),
+    ("yellow", [240, 228, 66]),
+    ("blue", [0, 114, 178]),
+    ("vermillion", [213, 94, 0]),
+    ("reddish_purple", [204, 121, 167]),
+];
+
+pub fn palette(name: &str) -> Option<[u8; 3]> {
+    PALETTE.iter().find(|(color, _)| *color == name).map(|(_, rgb)| *rgb)
+}
+
+/// Относительная яркость цвета sRGB
+pub fn relative_luminance(rgb: [u8; 3]) -> f32 {
+    luminance(rgb) as f32
+}
+
+/// Контраст двух цветов, порядок аргументов не важен
+pub fn contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f32 {
+    // Считаем в f64, чтобы чёрный на белом давал ровно 21
+    let (la, lb) = (luminance(a), luminance(b));
+    ((la.max(lb) + 0.05) / (la.min(lb) + 0.05)) as f32
+}
+
+fn luminance(rgb: [u8; 3]) -> f64 {
+    let linear = |channel: u8| {
+        let c = channel as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(rgb[0]) + 0.7152 * linear(rgb[1]) + 0.0722 * linear(rgb[2])
+}
+
+/// Цвет пикселя буфера кадра 0x00RRGGBB
+pub fn from_pixel(pixel: u32) -> [u8; 3] {
+    [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]
+}
+
+/// Средний цвет прямоугольника кадра (часть за границами не учитывается)
+pub fn average_color(buffer: &[u32], width: usize, height: usize, x: usize, y: usize, w: usize, h: usize) -> [u8; 3] {
+    let mut sum = [0u64; 3];
+    let mut count = 0u64;
+    for py in y..(y + h).min(height) {
+        for px in x..(x + w).min(width) {
+            let rgb = from_pixel(buffer[py * width + px]);
+            for (total, channel) in sum.iter_mut().zip(rgb) {
+                *total += channel as u64;
+            }
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return [0, 0, 0];
+    }
+    sum.map(|total| (total / count) as u8)
+}
@@ -0,0 +1,144 @@
+// const_eval.rs - вычисление `const fn` при компиляции
+//
+// Оптимизатор заменяет вызов const fn с литеральными аргументами его
+// результатом: `sine_table(64)` становится литералом массива, `shade(3)` -
+// литералом структуры Color. Вызов выполняет интерпретатор без
+// возможностей (capabilities.rs) и с ограничением числа шагов, а тайпчекер
+// заранее проверяет, что const fn вызывает только const fn и чистые
+// встроенные функции и не трогает статические переменные. Если вычислить
+// не удалось (деление на ноль, превышен лимит), вызов остаётся как есть и
+// выполнится во время работы программы.
+
+use crate::ast::*;
+use crate::capabilities::Capabilities;
+use crate::interpreter::{EvalLimits, Interpreter, RuntimeValue};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+/// Встроенные функции, которые можно вызывать из const fn: результат
+/// зависит только от аргументов
+pub const CONST_BUILTINS: &[&str] = &[
+    "sin", "cos", "fast_sin", "fast_cos", "lerp", "ease_in_out", "move_towards",
+    "clamp", "wrap", "map_range",
+    "palette", "contrast_ratio", "assert",
+    "str_len", "substring", "char_at", "contains", "split", "to_upper", "to_lower", "parse_i32", "format",
+];
+
+/// Лимиты одного вычисления: цикл на миллион итераций ещё проходит,
+/// бесконечный - нет
+pub const CONST_EVAL_LIMITS: EvalLimits = EvalLimits {
+    max_steps: 1_000_000,
+    max_depth: 256,
+};
+
+pub fn is_const_builtin(name: &str) -> bool {
+    CONST_BUILTINS.contains(&name)
+}
+
+pub struct ConstEvaluator {
+    interpreter: Interpreter,
+    const_functions: HashSet<String>,
+}
+
+impl fmt::Debug for ConstEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConstEvaluator")
+            .field("const_functions", &self.const_functions)
+            .finish()
+    }
+}
+
+impl ConstEvaluator {
+    /// None - в программе нет const fn или её константы не вычисляются
+    pub fn new(program: &Program) -> Option<Self> {
+        let const_functions: HashSet<String> = program.functions.iter()
+            .filter(|function| function.is_const())
+            .map(|function| function.name.clone())
+            .collect();
+        if const_functions.is_empty() {
+            return None;
+        }
+        let mut interpreter = Interpreter::with_capabilities(Capabilities::none());
+        interpreter.capture_output();
+        interpreter.set_limits(CONST_EVAL_LIMITS);
+        interpreter.load_program(program).ok()?;
+        Some(Self { interpreter, const_functions })
+    }
+
+    pub fn is_const_function(&self, name: &str) -> bool {
+        self.const_functions.contains(name)
+    }
+
+    /// Результат вызова в виде литерала; None - вызов не сворачивается
+    pub fn evaluate(&mut self, name: &str, args: &[Expression]) -> Option<Expression> {
+        if !self.is_const_function(name) {
+            return None;
+        }
+        let args = args.iter().map(literal_value).collect::<Option<Vec<_>>>()?;
+        self.interpreter.set_limits(CONST_EVAL_LIMITS);
+        let value = self.interpreter.call_function(name, &args).ok()?;
+        value_literal(&value)
+    }
+}
+
+/// Значение литерала: числа, строки, bool и составленные из них массивы,
+/// структуры и варианты перечислений
+pub fn literal_value(expression: &Expression) -> Option<RuntimeValue> {
+    Some(match expression {
+        Expression::IntegerLiteral(value) => i32::try_from(*value).map_or(RuntimeValue::Long(*value), RuntimeValue::Integer),
+        Expression::FloatLiteral(value) => RuntimeValue::Float(*value),
+        Expression::BoolLiteral(value) => RuntimeValue::Boolean(*value),
+        Expression::StringLiteral(value) => RuntimeValue::String(Rc::from(value.as_str())),
+        Expression::ArrayLiteral { elements } => RuntimeValue::Array(Rc::new(
+            elements.iter().map(literal_value).collect::<Option<Vec<_>>>()?,
+        )),
+        Expression::ArrayRepeat { value, count } => RuntimeValue::Array(Rc::new(vec![literal_value(value)?; *count])),
+        Expression::StructInitialization { struct_name, fields, base: None } => {
+            let mut values = HashMap::new();
+            for (name, value) in fields {
+                values.insert(name.clone(), literal_value(value)?);
+            }
+            RuntimeValue::Struct(Rc::from(struct_name.as_str()), Rc::new(values))
+        }
+        Expression::EnumVariant { enum_name, variant, args } => RuntimeValue::Enum(
+            Rc::from(enum_name.as_str()),
+            Rc::from(variant.as_str()),
+            Rc::new(args.iter().map(literal_value).collect::<Option<Vec<_>>>()?),
+        ),
+        _ => return None,
+    })
+}
+
+/// Литерал, дающий значение; ссылки, срезы и функции литерала не имеют
+pub fn value_literal(value: &RuntimeValue) -> Option<Expression> {
+    Some(match value {
+        RuntimeValue::Integer(value) => Expression::IntegerLiteral(i64::from(*value)),
+        RuntimeValue::Long(value) => Expression::IntegerLiteral(*value),
+        // У inf и NaN нет литерала
+        RuntimeValue::Float(value) if value.is_finite() => Expression::FloatLiteral(*value),
+        RuntimeValue::Boolean(value) => Expression::BoolLiteral(*value),
+        RuntimeValue::String(value) => Expression::StringLiteral(value.to_string()),
+        RuntimeValue::Array(elements) => Expression::ArrayLiteral {
+            elements: elements.iter().map(value_literal).collect::<Option<Vec<_>>>()?,
+        },
+        RuntimeValue::Struct(name, fields) => {
+            // Порядок полей HashMap случаен, а результат сборки должен быть стабильным
+            let mut names: Vec<&String> = fields.keys().collect();
+            names.sort();
+            Expression::StructInitialization {
+                struct_name: name.to_string(),
+                fields: names.into_iter()
+                    .map(|field| Some((field.clone(), value_literal(&fields[field])?)))
+                    .collect::<Option<Vec<_>>>()?,
+                base: None,
+            }
+        }
+        RuntimeValue::Enum(enum_name, variant, fields) => Expression::EnumVariant {
+            enum_name: enum_name.to_string(),
+            variant: variant.to_string(),
+            args: fields.iter().map(value_literal).collect::<Option<Vec<_>>>()?,
+        },
+        _ => return None,
+    })
+}
@@ -0,0 +1,116 @@
+// crash.rs - отчёт о падении интерпретатора или графического движка
+//
+// Когда программа завершается ошибкой выполнения или паникой, aetosc run
+// пишет текстовый отчёт и печатает путь к нему. В отчёте есть всё, что
+// нужно для воспроизведения: версия компилятора, хэш исходника (видно,
+// тот ли файл прислан), строка и столбец оператора, на котором всё
+// сломалось, стек вызовов с позицией в каждой функции и последние строки
+// вывода программы.
+
+use crate::ast::Position;
+use crate::storage;
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Сколько последних строк вывода попадает в отчёт
+pub const RECENT_OUTPUT_LINES: usize = 20;
+
+/// Вызов функции в стеке интерпретатора
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub function: String,
+    /// Выполняемый оператор: у внутреннего вызова - упавший, у внешних -
+    /// оператор с вызовом. До первого оператора - строка объявления функции
+    /// и столбец 0
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashReport {
+    pub error: String,
+    pub source_path: String,
+    pub source_hash: u64,
+    /// Оператор, на котором произошла ошибка (внутренний из вложенных),
+    /// и его строка исходника
+    pub statement: Option<(Position, String)>,
+    /// Внешний вызов первый
+    pub call_stack: Vec<StackFrame>,
+    pub recent_output: Vec<String>,
+}
+
+impl CrashReport {
+    pub fn new(error: &str, source_path: &str, source: &str) -> Self {
+        Self {
+            error: error.to_string(),
+            source_path: source_path.to_string(),
+            source_hash: source_hash(source),
+            statement: None,
+            call_stack: Vec::new(),
+            recent_output: Vec::new(),
+        }
+    }
+
+    /// Запоминает позицию упавшего оператора вместе с его строкой из `source`
+    pub fn set_statement(&mut self, position: Position, source: &str) {
+        let text = source.lines().nth(position.line.saturating_sub(1)).unwrap_or_default();
+        self.statement = Some((position, text.trim().to_string()));
+    }
+
+    pub fn render(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "Aetos crash report");
+        let _ = writeln!(report, "compiler: aetosc {}", env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(report, "source: {} (fnv1a {:016x})", self.source_path, self.source_hash);
+        let _ = writeln!(report, "error: {}", self.error);
+
+        if let Some((position, text)) = &self.statement {
+            let _ = writeln!(report, "\nstatement (line {}, column {}):\n    {}", position.line, position.column, text);
+        }
+
+        let _ = writeln!(report, "\ncall stack (innermost first):");
+        if self.call_stack.is_empty() {
+            let _ = writeln!(report, "    <empty>");
+        }
+        for frame in self.call_stack.iter().rev() {
+            let _ = writeln!(report, "    fn {} (line {}, column {})", frame.function, frame.line, frame.column);
+        }
+
+        let _ = writeln!(report, "\nrecent output:");
+        if self.recent_output.is_empty() {
+            let _ = writeln!(report, "    <none>");
+        }
+        for line in &self.recent_output {
+            let _ = writeln!(report, "    {}", line);
+        }
+        report
+    }
+
+    /// Пишет отчёт в `dir` и возвращает путь к файлу
+    pub fn write_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let path = dir.join(format!("crash-{}-{}.txt", seconds, std::process::id()));
+        std::fs::write(&path, self.render())?;
+        Ok(path)
+    }
+}
+
+/// Каталог отчётов: рядом с хранилищем программ, иначе во временном каталоге
+pub fn crash_dir() -> PathBuf {
+    storage::data_dir()
+        .map(|dir| dir.join("aetos").join("crashes"))
+        .unwrap_or_else(|| std::env::temp_dir().join("aetos-crashes"))
+}
+
+/// FNV-1a: в отличие от `DefaultHasher` не меняется между версиями Rust,
+/// поэтому хэш из отчёта можно сравнить с файлом у себя
+pub fn source_hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
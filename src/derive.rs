@@ -0,0 +1,193 @@
+// derive.rs - методы структур из `@derive(debug, eq, clone)`
+//
+// Методы строятся как обычные функции AST (`Point::to_string`, `Point::eq`,
+// `Point::clone`) по всей программе, уже объединённой с модулями: тайпчекер
+// проверяет их вместе с функциями пользователя, а интерпретатор загружает
+// вместе с ними. В исходнике их нет, поэтому lint, индекс символов и
+// статистика их не видят.
+//
+//     debug: `Point { x: 1, y: 2 }`, поля в порядке объявления; вложенная
+//            структура печатается своим to_string, перечисление - вариантом
+//     eq:    `==` для чисел, строк и перечислений без полей, eq для структур
+//     clone: `Point { ..self }`; ref-поля у копии остаются общими
+
+use crate::ast::*;
+
+/// Поле, для типа которого метод построить нельзя
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeriveError {
+    pub struct_name: String,
+    pub derive: Derive,
+    pub field: String,
+    pub found: Type,
+}
+
+/// Как поле участвует в to_string и eq
+enum FieldKind<'a> {
+    /// Числа, bool и строки: `+` и `==`
+    Scalar,
+    /// Перечисление без полей: match по вариантам и `==`
+    UnitEnum(&'a Enum),
+    /// Структура: её собственные to_string и eq
+    Struct,
+    Unsupported,
+}
+
+/// Методы всех структур с @derive и поля, из-за которых метод не построен
+pub fn derived_functions(program: &Program) -> (Vec<Function>, Vec<DeriveError>) {
+    let mut functions = Vec::new();
+    let mut errors = Vec::new();
+    for struct_def in &program.structs {
+        for &derive in &struct_def.derives {
+            match derive_method(program, struct_def, derive) {
+                Ok(function) => functions.push(function),
+                Err(error) => errors.push(error),
+            }
+        }
+    }
+    (functions, errors)
+}
+
+fn derive_method(program: &Program, struct_def: &Struct, derive: Derive) -> Result<Function, DeriveError> {
+    let self_type = Type::Struct(struct_def.name.clone());
+    let mut params = vec![parameter("self", self_type.clone())];
+    let (return_type, value) = match derive {
+        Derive::Debug => (Type::String, debug_body(program, struct_def)?),
+        Derive::Eq => {
+            params.push(parameter("other", self_type.clone()));
+            (Type::Bool, eq_body(program, struct_def)?)
+        }
+        Derive::Clone => (self_type, Expression::StructInitialization {
+            struct_name: struct_def.name.clone(),
+            fields: Vec::new(),
+            base: Some(Box::new(variable("self"))),
+        }),
+    };
+    Ok(Function {
+        name: method_name(&struct_def.name, derive.method()),
+        params,
+        return_type,
+        body: vec![Statement::Return { value, position: Position::default() }],
+        is_public: struct_def.is_public,
+        line: struct_def.line,
+        attributes: Vec::new(),
+        type_params: Vec::new(),
+        module: struct_def.module,
+    })
+}
+
+/// "Point { x: " + self.x + ", y: " + self.y + " }"
+fn debug_body(program: &Program, struct_def: &Struct) -> Result<Expression, DeriveError> {
+    if struct_def.fields.is_empty() {
+        return Ok(Expression::StringLiteral(format!("{} {{}}", struct_def.name)));
+    }
+    let mut text = Expression::StringLiteral(format!("{} {{ ", struct_def.name));
+    for (i, field) in struct_def.fields.iter().enumerate() {
+        let separator = if i == 0 { "" } else { ", " };
+        text = concat(text, Expression::StringLiteral(format!("{}{}: ", separator, field.name)));
+        let value = self_field(field);
+        let shown = match field_kind(program, &field.field_type) {
+            FieldKind::Scalar => value,
+            FieldKind::UnitEnum(enum_def) => Expression::Match {
+                subject: Box::new(value),
+                arms: enum_def.variants.iter()
+                    .map(|variant| MatchArm {
+                        pattern: Pattern::Variant {
+                            enum_name: enum_def.name.clone(),
+                            variant: variant.name.clone(),
+                            bindings: Vec::new(),
+                        },
+                        body: Expression::StringLiteral(format!("{}::{}", enum_def.name, variant.name)),
+                    })
+                    .collect(),
+            },
+            FieldKind::Struct => method_call(value, "to_string", Vec::new()),
+            FieldKind::Unsupported => return Err(unsupported(struct_def, Derive::Debug, field)),
+        };
+        text = concat(text, shown);
+    }
+    Ok(concat(text, Expression::StringLiteral(" }".to_string())))
+}
+
+/// self.x == other.x && self.inner.eq(other.inner)
+fn eq_body(program: &Program, struct_def: &Struct) -> Result<Expression, DeriveError> {
+    let mut result: Option<Expression> = None;
+    for field in &struct_def.fields {
+        let left = self_field(field);
+        let right = Expression::FieldAccess {
+            expression: Box::new(variable("other")),
+            field_name: field.name.clone(),
+        };
+        let equal = match field_kind(program, &field.field_type) {
+            FieldKind::Scalar | FieldKind::UnitEnum(_) => Expression::BinaryExpression {
+                left: Box::new(left),
+                operator: BinaryOperator::Eq,
+                right: Box::new(right),
+            },
+            FieldKind::Struct => method_call(left, "eq", vec![right]),
+            FieldKind::Unsupported => return Err(unsupported(struct_def, Derive::Eq, field)),
+        };
+        result = Some(match result {
+            Some(previous) => Expression::BinaryExpression {
+                left: Box::new(previous),
+                operator: BinaryOperator::And,
+                right: Box::new(equal),
+            },
+            None => equal,
+        });
+    }
+    Ok(result.unwrap_or(Expression::BoolLiteral(true)))
+}
+
+fn field_kind<'a>(program: &'a Program, field_type: &Type) -> FieldKind<'a> {
+    match field_type {
+        Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::Bool | Type::String => FieldKind::Scalar,
+        // Перечисления в аннотациях записываются так же, как структуры
+        Type::Struct(name) | Type::Enum(name) => match program.enums.iter().find(|e| e.name == *name) {
+            Some(enum_def) if enum_def.has_payload() => FieldKind::Unsupported,
+            Some(enum_def) => FieldKind::UnitEnum(enum_def),
+            None => FieldKind::Struct,
+        },
+        _ => FieldKind::Unsupported,
+    }
+}
+
+fn unsupported(struct_def: &Struct, derive: Derive, field: &StructField) -> DeriveError {
+    DeriveError {
+        struct_name: struct_def.name.clone(),
+        derive,
+        field: field.name.clone(),
+        found: field.field_type.clone(),
+    }
+}
+
+fn parameter(name: &str, param_type: Type) -> Parameter {
+    Parameter { name: name.to_string(), param_type }
+}
+
+fn variable(name: &str) -> Expression {
+    Expression::Variable(name.to_string())
+}
+
+fn self_field(field: &StructField) -> Expression {
+    Expression::FieldAccess {
+        expression: Box::new(variable("self")),
+        field_name: field.name.clone(),
+    }
+}
+
+fn method_call(receiver: Expression, method: &str, args: Vec<Expression>) -> Expression {
+    Expression::MethodCall {
+        receiver: Box::new(receiver),
+        method: method.to_string(),
+        args,
+    }
+}
+
+fn concat(left: Expression, right: Expression) -> Expression {
+    Expression::BinaryExpression {
+        left: Box::new(left),
+        operator: BinaryOperator::Add,
+        right: Box::new(right),
+    }
+}
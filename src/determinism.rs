@@ -0,0 +1,145 @@
+// determinism.rs - аудит детерминизма (`aetosc check --audit-determinism`)
+//
+// Функции с `@deterministic` (шаг симуляции для lockstep-мультиплеера или
+// повторов) должны давать одинаковый результат на всех машинах. Аудит
+// обходит граф вызовов от каждой такой функции и сообщает о вызовах
+// встроенных функций, результат которых зависит от времени, ввода,
+// файловой системы, сети, генератора случайных чисел или планировщика
+// потоков. Ввод и время нужно
+// передавать в детерминированную функцию аргументами.
+
+use crate::ast::*;
+use crate::visit::{self, Visitor};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+/// Недетерминированные встроенные функции и источник недетерминизма
+pub const NONDETERMINISTIC_BUILTINS: &[(&str, &str)] = &[
+    ("get_time", "time"),
+    ("delta_time", "time"),
+    ("is_key_pressed", "input"),
+    ("key_just_pressed", "input"),
+    ("key_just_released", "input"),
+    ("is_paused", "input"),
+    ("get_typed_text", "input"),
+    ("window_should_close", "input"),
+    ("store_get", "file"),
+    ("store_set", "file"),
+    ("resolve_asset", "file"),
+    ("http_get", "network"),
+    ("random_int", "random"),
+    ("random_float", "random"),
+    ("spawn", "threads"),
+    ("channel_recv", "threads"),
+    ("mem_stats", "memory"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeterminismIssue {
+    /// Функция с `@deterministic`, от которой найден вызов
+    pub root: String,
+    /// Цепочка вызовов от root до функции, вызывающей builtin
+    pub path: Vec<String>,
+    pub builtin: String,
+    pub source: &'static str,
+}
+
+impl fmt::Display for DeterminismIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "@deterministic fn {} calls {}() ({})", self.root, self.builtin, self.source)?;
+        if self.path.len() > 1 {
+            write!(f, " via {}", self.path.join(" -> "))?;
+        }
+        Ok(())
+    }
+}
+
+fn nondeterminism_source(name: &str) -> Option<&'static str> {
+    NONDETERMINISTIC_BUILTINS.iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, source)| *source)
+}
+
+/// Вызовы внутри функции, включая контракты; BTreeSet - чтобы отчёт был стабильным
+#[derive(Default)]
+struct CallCollector {
+    calls: BTreeSet<String>,
+    // Тип получателя без проверки типов неизвестен: x.update() может
+    // вызвать update любого impl-блока
+    methods: BTreeSet<String>,
+}
+
+impl Visitor for CallCollector {
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::FunctionCall { name, .. } => {
+                self.calls.insert(name.clone());
+            }
+            Expression::EnumVariant { enum_name, variant, .. } => {
+                self.calls.insert(method_name(enum_name, variant));
+            }
+            Expression::MethodCall { method, .. } => {
+                self.methods.insert(method.clone());
+            }
+            _ => {}
+        }
+        visit::walk_expression(self, expression);
+    }
+}
+
+/// Находит недетерминированные вызовы, достижимые из функций с `@deterministic`.
+/// Для каждой пары (функция, builtin) сообщается кратчайшая цепочка вызовов.
+pub fn audit_determinism(program: &Program) -> Vec<DeterminismIssue> {
+    let call_graph: BTreeMap<&str, BTreeSet<String>> = program.functions.iter()
+        .map(|function| {
+            let mut collector = CallCollector::default();
+            collector.visit_function(function);
+            let mut calls = collector.calls;
+            calls.extend(program.functions.iter()
+                .filter(|callee| split_method_name(&callee.name)
+                    .is_some_and(|(_, method)| collector.methods.contains(method)))
+                .map(|callee| callee.name.clone()));
+            (function.name.as_str(), calls)
+        })
+        .collect();
+
+    let mut issues = Vec::new();
+    for root in program.functions.iter().filter(|function| function.is_deterministic()) {
+        // Обход в ширину: parents хранит, откуда пришли в каждую функцию
+        let mut parents: BTreeMap<&str, Option<&str>> = BTreeMap::new();
+        let mut order = Vec::new();
+        let mut queue = VecDeque::from([root.name.as_str()]);
+        parents.insert(&root.name, None);
+        while let Some(name) = queue.pop_front() {
+            order.push(name);
+            for callee in call_graph.get(name).into_iter().flatten() {
+                if call_graph.contains_key(callee.as_str()) && !parents.contains_key(callee.as_str()) {
+                    parents.insert(callee, Some(name));
+                    queue.push_back(callee);
+                }
+            }
+        }
+
+        for name in order {
+            for callee in &call_graph[name] {
+                let Some(source) = nondeterminism_source(callee) else {
+                    continue;
+                };
+                let mut path = vec![name.to_string()];
+                let mut current = name;
+                while let Some(Some(parent)) = parents.get(current) {
+                    path.push(parent.to_string());
+                    current = parent;
+                }
+                path.reverse();
+                issues.push(DeterminismIssue {
+                    root: root.name.clone(),
+                    path,
+                    builtin: callee.clone(),
+                    source,
+                });
+            }
+        }
+    }
+    issues
+}
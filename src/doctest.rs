@@ -0,0 +1,149 @@
+// doctest.rs - примеры из doc-комментариев как тесты (`aetosc test`)
+//
+// Блок ``` внутри комментариев `///` выполняется интерпретатором вместе с
+// объявлениями файла. Если в блоке нет `fn main`, его код
+// оборачивается в main. Комментарии `// => текст` задают ожидаемые строки
+// вывода по порядку. Блоки ```ignore и ```text пропускаются.
+
+use crate::ast::Program;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::typecheck::TypeChecker;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocTest {
+    /// Имя функции, типа, трейта или константы, к которым относится комментарий
+    pub item: String,
+    /// Строка начала блока в исходном файле (с 1)
+    pub line: usize,
+    pub code: String,
+    pub expected_output: Vec<String>,
+}
+
+fn item_name(line: &str) -> Option<String> {
+    let mut words = line.split(|c: char| c.is_whitespace() || matches!(c, '(' | '{' | '<' | ':'));
+    loop {
+        match words.next()? {
+            "fn" | "struct" | "enum" | "trait" | "const" | "static" => {
+                return words.find(|word| !word.is_empty()).map(str::to_string);
+            }
+            _ => continue,
+        }
+    }
+}
+
+/// Извлекает все доктесты из исходного кода
+pub fn extract(source: &str) -> Vec<DocTest> {
+    let mut tests = Vec::new();
+    let mut pending: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut current: Option<(usize, Vec<String>)> = None;
+    let mut skipping = false;
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = raw_line.trim();
+
+        if let Some(doc) = line.strip_prefix("///") {
+            let doc = doc.strip_prefix(' ').unwrap_or(doc);
+            if let Some(info) = doc.trim().strip_prefix("```") {
+                if let Some(block) = current.take() {
+                    pending.push(block);
+                } else if skipping {
+                    skipping = false;
+                } else if info.is_empty() || info == "aetos" {
+                    current = Some((index + 2, Vec::new()));
+                } else {
+                    skipping = true;
+                }
+            } else if let Some((_, lines)) = &mut current {
+                lines.push(doc.to_string());
+            }
+            continue;
+        }
+
+        if line.is_empty() || pending.is_empty() {
+            continue;
+        }
+
+        // Первая строка после doc-комментария - документируемый элемент
+        let item = item_name(line).unwrap_or_else(|| "<unknown>".to_string());
+        for (start_line, lines) in pending.drain(..) {
+            let expected_output = lines.iter()
+                .filter_map(|line| line.split_once("// =>"))
+                .map(|(_, expected)| expected.trim().to_string())
+                .collect();
+            tests.push(DocTest {
+                item: item.clone(),
+                line: start_line,
+                code: lines.join("\n"),
+                expected_output,
+            });
+        }
+    }
+
+    tests
+}
+
+/// Добавляет в программу примера объявления модуля; одноимённые объявления
+/// из самого примера важнее
+fn merge_module(program: &mut Program, module: &Program) {
+    fn merge<T: Clone>(items: &mut Vec<T>, from: &[T], name: impl Fn(&T) -> &str) {
+        let defined: Vec<String> = items.iter().map(|item| name(item).to_string()).collect();
+        items.extend(from.iter()
+            .filter(|item| name(item) != "main" && !defined.iter().any(|known| known == name(item)))
+            .cloned());
+    }
+
+    merge(&mut program.functions, &module.functions, |f| &f.name);
+    merge(&mut program.structs, &module.structs, |s| &s.name);
+    merge(&mut program.enums, &module.enums, |e| &e.name);
+    merge(&mut program.consts, &module.consts, |c| &c.name);
+    merge(&mut program.statics, &module.statics, |s| &s.name);
+    merge(&mut program.traits, &module.traits, |t| &t.name);
+    for trait_impl in &module.trait_impls {
+        if !program.trait_impls.contains(trait_impl) {
+            program.trait_impls.push(trait_impl.clone());
+        }
+    }
+}
+
+impl DocTest {
+    fn program_source(&self) -> String {
+        if self.code.contains("fn main") {
+            self.code.clone()
+        } else {
+            format!("fn main() -> i32 {{\n{}\nreturn 0;\n}}\n", self.code)
+        }
+    }
+
+    /// Компилирует и выполняет пример вместе с объявлениями из `module`
+    pub fn run(&self, module: &Program) -> Result<(), String> {
+        let source = self.program_source();
+        let mut program = Parser::new(&source)
+            .parse_program()
+            .map_err(|e| format!("parse error: {}", e))?;
+
+        merge_module(&mut program, module);
+
+        TypeChecker::new()
+            .check_program(&program)
+            .map_err(|e| format!("type error: {}", e))?;
+
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter
+            .interpret_program(&program, 800, 600, &self.item)
+            .map_err(|e| format!("runtime error: {}", e))?;
+
+        if !self.expected_output.is_empty() {
+            let output = interpreter.take_output();
+            if output != self.expected_output {
+                return Err(format!(
+                    "output mismatch\n  expected: {:?}\n  found:    {:?}",
+                    self.expected_output, output
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
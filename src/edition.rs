@@ -0,0 +1,78 @@
+// edition.rs - редакции языка
+//
+// Редакция выбирается прагмой `//! edition: 2024` в начале файла или полем
+// `edition = "2024"` в секции [package] aetos.toml рядом со скриптом
+// (или выше по дереву).
+// Прагма имеет приоритет. Без указания используется последняя редакция;
+// код, где новые ключевые слова - идентификаторы, помечается `//! edition: 2023`.
+
+use crate::workspace::Manifest;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Edition {
+    E2023,
+    E2024,
+}
+
+impl Edition {
+    pub const LATEST: Edition = Edition::E2024;
+
+    pub fn parse(value: &str) -> Option<Edition> {
+        match value.trim() {
+            "2023" => Some(Edition::E2023),
+            "2024" => Some(Edition::E2024),
+            _ => None,
+        }
+    }
+
+    /// Ищет прагму `//! edition: NNNN` в ведущих комментариях файла
+    pub fn from_pragma(source: &str) -> Option<Edition> {
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("//!") {
+                if let Some(value) = rest.trim().strip_prefix("edition:") {
+                    return Edition::parse(value);
+                }
+                continue;
+            }
+            if line.starts_with("//") {
+                continue;
+            }
+            break;
+        }
+        None
+    }
+
+    /// Ищет `edition = "NNNN"` в [package] aetos.toml, начиная с каталога `dir`
+    pub fn from_manifest(dir: &Path) -> Option<Edition> {
+        let (_, manifest) = Manifest::nearest(dir)?;
+        Edition::parse(&manifest.package?.edition?)
+    }
+
+    /// Редакция для файла: прагма, затем aetos.toml, затем последняя редакция
+    pub fn detect(source: &str, source_path: &Path) -> Edition {
+        Edition::from_pragma(source)
+            .or_else(|| source_path.parent().and_then(Edition::from_manifest))
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Edition {
+    fn default() -> Self {
+        Edition::LATEST
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Edition::E2023 => write!(f, "2023"),
+            Edition::E2024 => write!(f, "2024"),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// embed.rs - Aetos как скриптовый язык игрового движка на Rust
+//
+//     let mut script = CompiledScript::compile(&source)?;
+//     script.call("update", &[RuntimeValue::Float(dt)])?;
+//     // файл изменился - подменяем код, не перезапуская игру
+//     script.reload(&new_source)?;
+//
+// Разбор и проверка типов выполняются один раз при компиляции; call
+// проверяет только аргументы по сохранённым сигнатурам. Если новая версия
+// скрипта не компилируется, reload возвращает ошибку и продолжает работать
+// старая. Статические переменные (`static mut`) переживают reload: значение
+// остаётся, если в новой версии есть static с тем же именем и типом, даже
+// когда изменился инициализатор. Новый static или static со сменившимся
+// типом получает начальное значение, удалённый - пропадает. Остальное
+// состояние между вызовами хранит движок.
+//
+// Чужие скрипты (моды, песочница) компилируются через
+// compile_with_capabilities(source, Capabilities::none()): окно, файлы,
+// время и потоки им недоступны.
+
+use crate::ast::{Program, Type};
+use crate::capabilities::Capabilities;
+use crate::interpreter::{Interpreter, RuntimeValue};
+use crate::parser::{ParseError, Parser};
+use crate::typecheck::{TypeCheckErrors, TypeChecker};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ScriptError {
+    #[error("Parse error: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("Type errors:\n{0}")]
+    Type(#[from] TypeCheckErrors),
+
+    #[error("Undefined function: {0}")]
+    UndefinedFunction(String),
+
+    #[error("Function {function} expects {expected} arguments, found {found}")]
+    ArgumentCount { function: String, expected: usize, found: usize },
+
+    #[error("Function {function}: argument `{param}` must be {expected}")]
+    ArgumentType { function: String, param: String, expected: Type },
+
+    #[error("Runtime error: {0}")]
+    Runtime(String),
+}
+
+/// Сигнатура функции скрипта: параметры и тип результата
+#[derive(Debug, Clone)]
+struct Signature {
+    params: Vec<(String, Type)>,
+    return_type: Type,
+}
+
+pub struct CompiledScript {
+    interpreter: Interpreter,
+    signatures: HashMap<String, Signature>,
+    source_hash: u64,
+}
+
+impl CompiledScript {
+    /// Разбирает и проверяет скрипт; main не нужен и не запускается
+    pub fn compile(source: &str) -> Result<Self, ScriptError> {
+        Self::compile_with_capabilities(source, Capabilities::all())
+    }
+
+    /// Как compile, но встроенные функции вне `capabilities` возвращают ошибку
+    pub fn compile_with_capabilities(source: &str, capabilities: Capabilities) -> Result<Self, ScriptError> {
+        let program = Self::check(source)?;
+        let mut interpreter = Interpreter::with_capabilities(capabilities);
+        interpreter.load_program(&program)
+            .map_err(|e| ScriptError::Runtime(e.to_string()))?;
+        Ok(Self {
+            interpreter,
+            signatures: Self::signatures(&program),
+            source_hash: hash(source),
+        })
+    }
+
+    /// Горячая замена кода. Возвращает false, если исходник не изменился
+    /// (повторная проверка не нужна); при ошибке остаётся прежняя версия
+    pub fn reload(&mut self, source: &str) -> Result<bool, ScriptError> {
+        let source_hash = hash(source);
+        if source_hash == self.source_hash {
+            return Ok(false);
+        }
+        let program = Self::check(source)?;
+        self.interpreter.load_program(&program)
+            .map_err(|e| ScriptError::Runtime(e.to_string()))?;
+        self.signatures = Self::signatures(&program);
+        self.source_hash = source_hash;
+        Ok(true)
+    }
+
+    /// Вызывает функцию скрипта; void-функции возвращают RuntimeValue::Void
+    pub fn call(&mut self, name: &str, args: &[RuntimeValue]) -> Result<RuntimeValue, ScriptError> {
+        let signature = self.signatures.get(name)
+            .ok_or_else(|| ScriptError::UndefinedFunction(name.to_string()))?;
+        if args.len() != signature.params.len() {
+            return Err(ScriptError::ArgumentCount {
+                function: name.to_string(),
+                expected: signature.params.len(),
+                found: args.len(),
+            });
+        }
+        for (arg, (param, expected)) in args.iter().zip(&signature.params) {
+            if !value_has_type(arg, expected) {
+                return Err(ScriptError::ArgumentType {
+                    function: name.to_string(),
+                    param: param.clone(),
+                    expected: expected.clone(),
+                });
+            }
+        }
+        self.interpreter.call_function(name, args)
+            .map_err(|e| ScriptError::Runtime(e.to_string()))
+    }
+
+    pub fn has_function(&self, name: &str) -> bool {
+        self.signatures.contains_key(name)
+    }
+
+    pub fn return_type(&self, name: &str) -> Option<&Type> {
+        self.signatures.get(name).map(|signature| &signature.return_type)
+    }
+
+    /// Интерпретатор скрипта - для настройки (strict_fp, хранилище, перехват вывода)
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    fn check(source: &str) -> Result<Program, ScriptError> {
+        let program = Parser::new(source).parse_program()?;
+        TypeChecker::new().check_program(&program)?;
+        Ok(program)
+    }
+
+    fn signatures(program: &Program) -> HashMap<String, Signature> {
+        program.functions.iter()
+            .map(|function| {
+                let signature = Signature {
+                    params: function.params.iter()
+                        .map(|param| (param.name.clone(), param.param_type.clone()))
+                        .collect(),
+                    return_type: function.return_type.clone(),
+                };
+                (function.name.clone(), signature)
+            })
+            .collect()
+    }
+}
+
+fn hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Соответствует ли значение от движка типу параметра (i32 подходит и к
+/// вещественным параметрам, как в самом языке)
+fn value_has_type(value: &RuntimeValue, ty: &Type) -> bool {
+    match (value, ty) {
+        (RuntimeValue::Integer(_), Type::I32 | Type::I64 | Type::F32 | Type::F64) => true,
+        (RuntimeValue::Long(_), Type::I64) => true,
+        (RuntimeValue::Float(_), Type::F32 | Type::F64) => true,
+        (RuntimeValue::Boolean(_), Type::Bool) => true,
+        (RuntimeValue::String(_), Type::String) => true,
+        (RuntimeValue::Struct(name, _), Type::Struct(expected)) => **name == **expected,
+        (RuntimeValue::Enum(name, _, _), Type::Enum(expected) | Type::Struct(expected)) => **name == **expected,
+        (RuntimeValue::Enum(name, variant, fields), Type::Option(inner)) if &**name == "Option" => {
+            &**variant == "None" || fields.iter().all(|value| value_has_type(value, inner))
+        }
+        (RuntimeValue::Enum(name, variant, fields), Type::Result(ok, err)) if &**name == "Result" => {
+            let expected = if &**variant == "Ok" { ok } else { err };
+            fields.iter().all(|value| value_has_type(value, expected))
+        }
+        (RuntimeValue::Array(elements), Type::Array(element)) => {
+            elements.iter().all(|value| value_has_type(value, element))
+        }
+        (RuntimeValue::Ref(cell), Type::Ref(inner)) => value_has_type(&cell.borrow(), inner),
+        (RuntimeValue::Function(closure), Type::Function(params, return_type)) => {
+            closure.function.params.len() == params.len()
+                && closure.function.params.iter().zip(params).all(|(param, ty)| param.param_type == *ty)
+                && closure.function.return_type == **return_type
+        }
+        _ => false,
+    }
+}
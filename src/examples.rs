@@ -0,0 +1,48 @@
+// examples.rs - примеры, встроенные в бинарник (`aetosc examples`)
+
+use include_dir::{include_dir, Dir};
+
+static EXAMPLES_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/examples");
+
+pub struct Example {
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+impl Example {
+    /// Графический пример (открывает окно) или консольный
+    pub fn is_graphics(&self) -> bool {
+        self.source.contains("init_graphics") || self.source.contains("render()")
+    }
+
+    /// Первая строка-комментарий файла, если есть
+    pub fn description(&self) -> Option<&'static str> {
+        self.source.lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .and_then(|line| line.strip_prefix("//"))
+            .map(str::trim)
+            .filter(|comment| !comment.ends_with(".aetos"))
+    }
+}
+
+/// Все встроенные примеры, отсортированные по имени
+pub fn all() -> Vec<Example> {
+    let mut examples: Vec<Example> = EXAMPLES_DIR.files()
+        .filter(|file| file.path().extension().is_some_and(|ext| ext == "aetos"))
+        .filter_map(|file| {
+            Some(Example {
+                name: file.path().file_stem()?.to_str()?,
+                source: file.contents_utf8()?,
+            })
+        })
+        .collect();
+    examples.sort_by_key(|example| example.name);
+    examples
+}
+
+/// Поиск примера по имени (с расширением `.aetos` или без)
+pub fn find(name: &str) -> Option<Example> {
+    let name = name.strip_suffix(".aetos").unwrap_or(name);
+    all().into_iter().find(|example| example.name == name)
+}
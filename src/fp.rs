@@ -0,0 +1,91 @@
+// fp.rs - детерминированная арифметика с плавающей точкой (`--strict-fp`)
+//
+// В строгом режиме интерпретатор ведёт себя так же, как скомпилированный
+// код (WASM f32 и LLVM без fast-math), и одинаково на всех машинах:
+// - операции над f32 следуют IEEE 754: деление на ноль даёт ±inf или NaN,
+//   а не ошибку выполнения;
+// - любое сравнение с NaN ложно, кроме `!=`;
+// - sin/cos считаются здесь только через +, -, *, / над f64 (они
+//   округляются по IEEE одинаково везде) вместо системной libm, результат
+//   которой зависит от платформы.
+//
+// Этого достаточно, чтобы записи повторов и lockstep-логика мультиплеера
+// давали побитово одинаковые результаты.
+//
+// Здесь же таблица для fast_sin/fast_cos (`--fast-math`): она строится
+// этим же sin, так что быстрые функции тоже одинаковы на всех машинах.
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::sync::OnceLock;
+
+/// Точек таблицы на период; с линейной интерполяцией погрешность < 1e-6
+const TABLE_SIZE: usize = 4096;
+
+/// Синус с одинаковым результатом на всех платформах
+pub fn sin(x: f32) -> f32 {
+    if !x.is_finite() {
+        return f32::NAN;
+    }
+    sin_f64(x as f64) as f32
+}
+
+/// Косинус с одинаковым результатом на всех платформах
+pub fn cos(x: f32) -> f32 {
+    if !x.is_finite() {
+        return f32::NAN;
+    }
+    sin_f64(x as f64 + FRAC_PI_2) as f32
+}
+
+fn sin_f64(x: f64) -> f64 {
+    // Приводим к [-pi, pi], затем к [-pi/2, pi/2] через sin(pi - x) = sin(x)
+    let mut x = x - TAU * (x / TAU).round();
+    if x > FRAC_PI_2 {
+        x = PI - x;
+    } else if x < -FRAC_PI_2 {
+        x = -PI - x;
+    }
+
+    // Ряд Тейлора до x^17: на [-pi/2, pi/2] погрешность < 1e-13,
+    // что намного точнее f32
+    let x2 = x * x;
+    let mut term = 1.0;
+    for n in (1..=8).rev() {
+        let k = (2 * n) as f64;
+        term = 1.0 - x2 / (k * (k + 1.0)) * term;
+    }
+    x * term
+}
+
+/// Синус по таблице: быстрее sin, точность - до шестого знака
+pub fn fast_sin(x: f32) -> f32 {
+    table_lookup(x as f64 * (TABLE_SIZE as f64 / TAU))
+}
+
+/// Косинус по таблице: синус, сдвинутый на четверть периода
+pub fn fast_cos(x: f32) -> f32 {
+    table_lookup(x as f64 * (TABLE_SIZE as f64 / TAU) + (TABLE_SIZE / 4) as f64)
+}
+
+/// Значение в точке `position` (в делениях таблицы) с линейной интерполяцией
+fn table_lookup(position: f64) -> f32 {
+    if !position.is_finite() {
+        return f32::NAN;
+    }
+    let position = position.rem_euclid(TABLE_SIZE as f64);
+    let index = (position as usize).min(TABLE_SIZE - 1);
+    let fraction = (position - index as f64) as f32;
+    let table = sine_table();
+    table[index] + (table[index + 1] - table[index]) * fraction
+}
+
+/// Один период синуса; последняя точка повторяет первую, чтобы
+/// интерполяции не нужно было заворачивать индекс
+fn sine_table() -> &'static [f32] {
+    static TABLE: OnceLock<Vec<f32>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        (0..=TABLE_SIZE)
+            .map(|i| sin_f64(i as f64 * TAU / TABLE_SIZE as f64) as f32)
+            .collect()
+    })
+}
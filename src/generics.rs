@@ -0,0 +1,966 @@
+// generics.rs - обобщённые функции и структуры
+//
+//     fn max<T>(a: T, b: T) -> T { if a > b { a } else { b } }
+//     struct Pair<T> { first: T, second: T }
+//     impl<T> Pair<T> { fn swap(self) -> Pair<T> { ... } }
+//
+// Обобщённых значений во время выполнения нет: для каждого набора типов,
+// с которым объявление используется, строится отдельная копия - функция
+// `max<i32>`, структура `Pair<f32>` со всеми методами `Pair<f32>::swap` -
+// и вызовы с типами переписываются на неё. Тайпчекер проверяет копии как
+// обычные функции, поэтому ошибка в теле (`a > b` для структуры)
+// сообщается для конкретного типа, а объявление, которое нигде не
+// используется, не проверяется вовсе. Интерпретатор и бэкенды получают
+// программу, в которой параметров типа уже нет.
+//
+// Аргументы типа выводятся из аргументов вызова и полей литерала, а если
+// их мало (`let p: Pair<f32> = Pair::empty();`) - из ожидаемого типа:
+// объявленного у переменной, параметра или возвращаемого значения.
+
+use crate::ast::*;
+use crate::stdlib;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use thiserror::Error;
+
+/// Сколько копий одного объявления допустимо: больше бывает только у
+/// объявлений, которые создают сами себя со всё более длинным типом
+const MAX_INSTANCES: usize = 64;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum GenericError {
+    #[error("Cannot infer type parameter {param} of {name}")]
+    CannotInfer { name: String, param: String },
+
+    #[error("Type parameter {param} of {name} is both {} and {}", types.0, types.1)]
+    ConflictingTypes { name: String, param: String, types: Box<(Type, Type)> },
+
+    #[error("{name} expects {expected} type arguments, found {found}")]
+    TypeArgumentCount { name: String, expected: usize, found: usize },
+
+    #[error("Generic struct {name} needs type arguments: {name}<...>")]
+    MissingTypeArguments { name: String },
+
+    #[error("Too many instances of {name}: it instantiates itself with ever larger types")]
+    TooManyInstances { name: String },
+}
+
+type GenericResult<T> = Result<T, GenericError>;
+
+/// Имя копии: `max<i32>`, `Pair<i32, f32>`
+pub fn instance_name(name: &str, args: &[Type]) -> String {
+    Type::Generic(name.to_string(), args.to_vec()).to_string()
+}
+
+/// Программа без параметров типа; без обобщённых объявлений - она же
+pub fn monomorphize(program: &Program) -> GenericResult<Cow<'_, Program>> {
+    let generic = program.functions.iter().any(|function| !function.type_params.is_empty())
+        || program.structs.iter().any(|struct_def| !struct_def.type_params.is_empty());
+    if !generic {
+        return Ok(Cow::Borrowed(program));
+    }
+    Monomorphizer::new(program).run().map(Cow::Owned)
+}
+
+/// Параметры типа копии и их значения
+type Substitution = Vec<(String, Type)>;
+
+/// Копия, тело которой ещё не переписано
+struct PendingFunction {
+    function: Function,
+    origin: String,
+    substitution: Substitution,
+}
+
+struct Monomorphizer<'a> {
+    program: &'a Program,
+    generic_functions: HashMap<&'a str, &'a Function>,
+    generic_structs: HashMap<&'a str, &'a Struct>,
+    /// Конкретные структуры и копии: по ним определяются типы полей
+    structs: HashMap<String, Struct>,
+    enums: HashMap<String, Enum>,
+    /// Параметры и результат конкретных функций и копий
+    signatures: HashMap<String, (Vec<Type>, Type)>,
+    globals: HashMap<String, Type>,
+    /// Копия структуры -> обобщённая структура и аргументы типа
+    instances: HashMap<String, (String, Vec<Type>)>,
+    instance_counts: HashMap<String, usize>,
+    /// Готовые копии по имени объявления, в порядке создания
+    instance_functions: HashMap<String, Vec<Function>>,
+    instance_structs: HashMap<String, Vec<String>>,
+    pending: VecDeque<PendingFunction>,
+    /// Копии структур, у которых ещё не переписаны значения по умолчанию
+    pending_defaults: Vec<(String, Substitution)>,
+    // Состояние переписываемой функции
+    scopes: Vec<(String, Type)>,
+    return_type: Type,
+}
+
+impl<'a> Monomorphizer<'a> {
+    fn new(program: &'a Program) -> Self {
+        let mut structs = HashMap::new();
+        // Color стандартной библиотеки есть и без объявления
+        let color = stdlib::color_struct();
+        structs.insert(color.name.clone(), color);
+        Self {
+            program,
+            generic_functions: program.functions.iter()
+                .filter(|function| !function.type_params.is_empty())
+                .map(|function| (function.name.as_str(), function))
+                .collect(),
+            generic_structs: program.structs.iter()
+                .filter(|struct_def| !struct_def.type_params.is_empty())
+                .map(|struct_def| (struct_def.name.as_str(), struct_def))
+                .collect(),
+            structs,
+            enums: HashMap::new(),
+            signatures: HashMap::new(),
+            globals: HashMap::new(),
+            instances: HashMap::new(),
+            instance_counts: HashMap::new(),
+            instance_functions: HashMap::new(),
+            instance_structs: HashMap::new(),
+            pending: VecDeque::new(),
+            pending_defaults: Vec::new(),
+            scopes: Vec::new(),
+            return_type: Type::Void,
+        }
+    }
+
+    fn run(mut self) -> GenericResult<Program> {
+        let program = self.program;
+        let mut output = Program {
+            functions: Vec::new(),
+            structs: Vec::new(),
+            enums: Vec::new(),
+            imports: program.imports.clone(),
+            consts: Vec::new(),
+            statics: Vec::new(),
+            traits: Vec::new(),
+            trait_impls: program.trait_impls.clone(),
+        };
+
+        // Сначала типы объявлений: они нужны, чтобы вывести типы в телах
+        let mut structs = Vec::new();
+        for struct_def in program.structs.iter().filter(|struct_def| struct_def.type_params.is_empty()) {
+            let mut struct_def = struct_def.clone();
+            for field in &mut struct_def.fields {
+                field.field_type = self.concrete(&field.field_type, &[])?;
+            }
+            self.structs.insert(struct_def.name.clone(), struct_def.clone());
+            structs.push(struct_def);
+        }
+        for enum_def in &program.enums {
+            let mut enum_def = enum_def.clone();
+            for variant in &mut enum_def.variants {
+                for field in &mut variant.fields {
+                    *field = self.concrete(field, &[])?;
+                }
+            }
+            self.enums.insert(enum_def.name.clone(), enum_def.clone());
+            output.enums.push(enum_def);
+        }
+        for trait_def in &program.traits {
+            let mut trait_def = trait_def.clone();
+            for method in &mut trait_def.methods {
+                for param in &mut method.params {
+                    param.param_type = self.concrete(&param.param_type, &[])?;
+                }
+                method.return_type = self.concrete(&method.return_type, &[])?;
+            }
+            output.traits.push(trait_def);
+        }
+        let mut functions = Vec::new();
+        for function in program.functions.iter().filter(|function| function.type_params.is_empty()) {
+            let mut function = function.clone();
+            for param in &mut function.params {
+                param.param_type = self.concrete(&param.param_type, &[])?;
+            }
+            function.return_type = self.concrete(&function.return_type, &[])?;
+            self.signatures.insert(function.name.clone(), signature(&function));
+            functions.push(function);
+        }
+
+        // Константы и статики видны во всех телах
+        for constant in &program.consts {
+            let mut constant = constant.clone();
+            constant.const_type = self.concrete(&constant.const_type, &[])?;
+            self.expression(&mut constant.value, Some(&constant.const_type))?;
+            self.globals.insert(constant.name.clone(), constant.const_type.clone());
+            output.consts.push(constant);
+        }
+        for static_def in &program.statics {
+            let mut static_def = static_def.clone();
+            static_def.static_type = self.concrete(&static_def.static_type, &[])?;
+            self.expression(&mut static_def.value, Some(&static_def.static_type))?;
+            self.globals.insert(static_def.name.clone(), static_def.static_type.clone());
+            output.statics.push(static_def);
+        }
+        for struct_def in &mut structs {
+            for field in &mut struct_def.fields {
+                if let Some(default) = &mut field.default {
+                    self.expression(default, Some(&field.field_type))?;
+                }
+            }
+        }
+        for function in &mut functions {
+            self.function_body(function, &[])?;
+        }
+
+        // Копии могут использовать новые копии
+        loop {
+            if let Some(PendingFunction { mut function, origin, substitution }) = self.pending.pop_front() {
+                self.function_body(&mut function, &substitution)?;
+                self.instance_functions.entry(origin).or_default().push(function);
+            } else if let Some((instance, substitution)) = self.pending_defaults.pop() {
+                let mut struct_def = self.structs[&instance].clone();
+                for field in &mut struct_def.fields {
+                    if let Some(default) = &mut field.default {
+                        self.substitute_types(default, &substitution)?;
+                        self.expression(default, Some(&field.field_type))?;
+                    }
+                }
+                self.structs.insert(instance, struct_def);
+            } else {
+                break;
+            }
+        }
+
+        // Копии встают на место обобщённого объявления
+        let mut functions = functions.into_iter();
+        for function in &program.functions {
+            if function.type_params.is_empty() {
+                output.functions.extend(functions.next());
+            } else if let Some(instances) = self.instance_functions.remove(&function.name) {
+                output.functions.extend(instances);
+            }
+        }
+        let mut structs = structs.into_iter();
+        for struct_def in &program.structs {
+            if struct_def.type_params.is_empty() {
+                output.structs.extend(structs.next());
+            } else {
+                for instance in self.instance_structs.get(&struct_def.name).into_iter().flatten() {
+                    output.structs.push(self.structs[instance].clone());
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Тип без параметров: T заменяется по substitution, Pair<i32> - копией
+    fn concrete(&mut self, ty: &Type, substitution: &[(String, Type)]) -> GenericResult<Type> {
+        Ok(match ty {
+            Type::Struct(name) => match substitution.iter().find(|(param, _)| param == name) {
+                Some((_, arg)) => arg.clone(),
+                None if self.generic_structs.contains_key(name.as_str()) => {
+                    return Err(GenericError::MissingTypeArguments { name: name.clone() });
+                }
+                None => ty.clone(),
+            },
+            Type::Generic(name, args) => {
+                let args = args.iter()
+                    .map(|arg| self.concrete(arg, substitution))
+                    .collect::<GenericResult<Vec<_>>>()?;
+                Type::Struct(self.instantiate_struct(name, &args)?)
+            }
+            Type::Ref(inner) => Type::Ref(Box::new(self.concrete(inner, substitution)?)),
+            Type::Array(element) => Type::Array(Box::new(self.concrete(element, substitution)?)),
+            Type::Slice(element) => Type::Slice(Box::new(self.concrete(element, substitution)?)),
+            Type::FixedArray(element, length) => Type::FixedArray(Box::new(self.concrete(element, substitution)?), *length),
+            Type::Option(inner) => Type::Option(Box::new(self.concrete(inner, substitution)?)),
+            Type::Result(ok, err) => Type::Result(
+                Box::new(self.concrete(ok, substitution)?),
+                Box::new(self.concrete(err, substitution)?),
+            ),
+            Type::Function(params, return_type) => Type::Function(
+                params.iter()
+                    .map(|param| self.concrete(param, substitution))
+                    .collect::<GenericResult<Vec<_>>>()?,
+                Box::new(self.concrete(return_type, substitution)?),
+            ),
+            _ => ty.clone(),
+        })
+    }
+
+    fn count_instance(&mut self, name: &str) -> GenericResult<()> {
+        let count = self.instance_counts.entry(name.to_string()).or_default();
+        *count += 1;
+        if *count > MAX_INSTANCES {
+            return Err(GenericError::TooManyInstances { name: name.to_string() });
+        }
+        Ok(())
+    }
+
+    /// Копия обобщённой структуры вместе со всеми её методами
+    fn instantiate_struct(&mut self, name: &str, args: &[Type]) -> GenericResult<String> {
+        let Some(&generic) = self.generic_structs.get(name) else {
+            if self.structs.contains_key(name) || self.program.structs.iter().any(|struct_def| struct_def.name == name) {
+                return Err(GenericError::TypeArgumentCount { name: name.to_string(), expected: 0, found: args.len() });
+            }
+            // Неизвестную структуру сообщит тайпчекер
+            return Ok(instance_name(name, args));
+        };
+        if args.len() != generic.type_params.len() {
+            return Err(GenericError::TypeArgumentCount {
+                name: name.to_string(),
+                expected: generic.type_params.len(),
+                found: args.len(),
+            });
+        }
+        let instance = instance_name(name, args);
+        if self.structs.contains_key(&instance) {
+            return Ok(instance);
+        }
+        self.count_instance(name)?;
+        let substitution: Substitution = generic.type_params.iter().cloned().zip(args.iter().cloned()).collect();
+
+        // Копия известна до типов полей: поле может ссылаться на саму структуру
+        let mut struct_def = Struct { name: instance.clone(), type_params: Vec::new(), ..generic.clone() };
+        self.structs.insert(instance.clone(), struct_def.clone());
+        self.instances.insert(instance.clone(), (name.to_string(), args.to_vec()));
+        for field in &mut struct_def.fields {
+            field.field_type = self.concrete(&field.field_type, &substitution)?;
+        }
+        self.structs.insert(instance.clone(), struct_def);
+        self.instance_structs.entry(name.to_string()).or_default().push(instance.clone());
+        self.pending_defaults.push((instance.clone(), substitution.clone()));
+
+        let program = self.program;
+        for function in &program.functions {
+            if let Some((_, method)) = split_method_name(&function.name).filter(|(type_name, _)| *type_name == name) {
+                self.instantiate_function(function, method_name(&instance, method), substitution.clone())?;
+            }
+        }
+        Ok(instance)
+    }
+
+    /// Копия функции под именем `name`; тело переписывается позже
+    fn instantiate_function(&mut self, generic: &Function, name: String, substitution: Substitution) -> GenericResult<()> {
+        if self.signatures.contains_key(&name) {
+            return Ok(());
+        }
+        self.count_instance(&generic.name)?;
+        let mut function = Function { name: name.clone(), type_params: Vec::new(), ..generic.clone() };
+        for param in &mut function.params {
+            param.param_type = self.concrete(&param.param_type, &substitution)?;
+        }
+        function.return_type = self.concrete(&function.return_type, &substitution)?;
+        self.signatures.insert(name, signature(&function));
+        self.pending.push_back(PendingFunction { function, origin: generic.name.clone(), substitution });
+        Ok(())
+    }
+
+    /// Аргументы типа обобщённой функции по аргументам вызова и ожидаемому результату
+    fn infer_call(&mut self, generic: &Function, type_params: &[String], args: &mut [Expression], expected: Option<&Type>) -> GenericResult<Vec<Type>> {
+        let mut bindings = vec![None; type_params.len()];
+        for (arg, param) in args.iter_mut().zip(&generic.params) {
+            // Параметр без T подсказывает тип аргументу, как у обычной функции
+            let hint = match mentions(&param.param_type, type_params) {
+                true => None,
+                false => Some(self.concrete(&param.param_type, &[])?),
+            };
+            if let Some(arg_type) = self.expression(arg, hint.as_ref())? {
+                self.unify(&generic.name, type_params, &param.param_type, &arg_type, &mut bindings)?;
+            }
+        }
+        if let Some(expected) = expected.filter(|_| bindings.iter().any(Option::is_none)) {
+            self.unify(&generic.name, type_params, &generic.return_type, expected, &mut bindings)?;
+        }
+        bindings.into_iter().zip(type_params)
+            .map(|(binding, param)| binding.ok_or_else(|| GenericError::CannotInfer {
+                name: generic.name.clone(),
+                param: param.clone(),
+            }))
+            .collect()
+    }
+
+    /// Сопоставляет тип с параметрами и конкретный тип, запоминая значения параметров
+    fn unify(&self, name: &str, type_params: &[String], generic: &Type, actual: &Type, bindings: &mut [Option<Type>]) -> GenericResult<()> {
+        match (generic, actual) {
+            // Тип None и Ok без ошибки пока неизвестен
+            (_, Type::Never | Type::Void) => {}
+            (Type::Struct(param), _) if type_params.contains(param) => {
+                let index = type_params.iter().position(|other| other == param).unwrap();
+                match &bindings[index] {
+                    None => bindings[index] = Some(actual.clone()),
+                    Some(bound) if same_type(bound, actual) => {}
+                    Some(bound) => return Err(GenericError::ConflictingTypes {
+                        name: name.to_string(),
+                        param: param.clone(),
+                        types: Box::new((bound.clone(), actual.clone())),
+                    }),
+                }
+            }
+            (Type::Ref(generic), Type::Ref(actual))
+            | (Type::Array(generic), Type::Array(actual) | Type::FixedArray(actual, _))
+            | (Type::FixedArray(generic, _), Type::FixedArray(actual, _))
+            | (Type::Slice(generic), Type::Array(actual) | Type::Slice(actual) | Type::FixedArray(actual, _))
+            | (Type::Option(generic), Type::Option(actual)) => {
+                self.unify(name, type_params, generic, actual, bindings)?;
+            }
+            (Type::Result(generic_ok, generic_err), Type::Result(ok, err)) => {
+                self.unify(name, type_params, generic_ok, ok, bindings)?;
+                self.unify(name, type_params, generic_err, err, bindings)?;
+            }
+            (Type::Function(generic_params, generic_return), Type::Function(params, return_type)) => {
+                for (generic, actual) in generic_params.iter().zip(params) {
+                    self.unify(name, type_params, generic, actual, bindings)?;
+                }
+                self.unify(name, type_params, generic_return, return_type, bindings)?;
+            }
+            (Type::Generic(struct_name, generic_args), Type::Struct(instance)) => {
+                if let Some((_, args)) = self.instances.get(instance).filter(|(origin, _)| origin == struct_name) {
+                    for (generic, actual) in generic_args.iter().zip(args) {
+                        self.unify(name, type_params, generic, actual, bindings)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Копия обобщённой структуры, которой является тип (в том числе через ref)
+    fn instance_of(&self, ty: &Type, struct_name: &str) -> Option<Vec<Type>> {
+        match ty {
+            Type::Ref(inner) => self.instance_of(inner, struct_name),
+            Type::Struct(instance) => self.instances.get(instance)
+                .filter(|(origin, _)| origin == struct_name)
+                .map(|(_, args)| args.clone()),
+            _ => None,
+        }
+    }
+
+    /// Заменяет параметры типа в аннотациях тела копии
+    fn substitute_types(&mut self, expression: &mut Expression, substitution: &[(String, Type)]) -> GenericResult<()> {
+        let mut substituter = Substituter { monomorphizer: self, substitution, error: None };
+        crate::visit::VisitorMut::visit_expression_mut(&mut substituter, expression);
+        substituter.error.map_or(Ok(()), Err)
+    }
+
+    fn function_body(&mut self, function: &mut Function, substitution: &[(String, Type)]) -> GenericResult<()> {
+        {
+            let mut substituter = Substituter { monomorphizer: self, substitution, error: None };
+            crate::visit::walk_function_mut(&mut substituter, function);
+            if let Some(error) = substituter.error {
+                return Err(error);
+            }
+        }
+        self.scopes = function.params.iter()
+            .map(|param| (param.name.clone(), param.param_type.clone()))
+            .collect();
+        self.return_type = function.return_type.clone();
+        for attribute in &mut function.attributes {
+            if let Attribute::Requires(condition) | Attribute::Ensures(condition) = attribute {
+                self.scopes.push(("result".to_string(), function.return_type.clone()));
+                self.expression(condition, Some(&Type::Bool))?;
+                self.scopes.pop();
+            }
+        }
+        self.statements(&mut function.body)
+    }
+
+    fn variable(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev()
+            .find(|(variable, _)| variable == name)
+            .map(|(_, ty)| ty.clone())
+            .or_else(|| self.globals.get(name).cloned())
+    }
+
+    fn field_type(&self, ty: &Type, field: &str) -> Option<Type> {
+        match ty {
+            Type::Ref(inner) => self.field_type(inner, field),
+            Type::Struct(name) => self.structs.get(name)?
+                .fields.iter()
+                .find(|candidate| candidate.name == field)
+                .map(|field| field.field_type.clone()),
+            _ => None,
+        }
+    }
+
+    fn statements(&mut self, statements: &mut [Statement]) -> GenericResult<()> {
+        let scope = self.scopes.len();
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        self.scopes.truncate(scope);
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &mut Statement) -> GenericResult<()> {
+        match statement {
+            Statement::VariableDeclaration { name, var_type, value, .. } => {
+                self.expression(value, Some(var_type))?;
+                self.scopes.push((name.clone(), var_type.clone()));
+            }
+            Statement::DestructureStruct { struct_name, bindings, value, .. } => {
+                let value_type = self.expression(value, None)?;
+                if let Some(&generic) = self.generic_structs.get(struct_name.as_str()) {
+                    let args = value_type.as_ref()
+                        .and_then(|ty| self.instance_of(ty, struct_name))
+                        .ok_or_else(|| GenericError::CannotInfer {
+                            name: struct_name.clone(),
+                            param: generic.type_params[0].clone(),
+                        })?;
+                    *struct_name = instance_name(struct_name, &args);
+                }
+                let struct_type = Type::Struct(struct_name.clone());
+                for (field, variable) in bindings.iter() {
+                    if let Some(field_type) = self.field_type(&struct_type, field) {
+                        self.scopes.push((variable.clone(), field_type));
+                    }
+                }
+            }
+            Statement::Assignment { name, value, .. } => {
+                let expected = self.variable(name);
+                self.expression(value, expected.as_ref())?;
+            }
+            Statement::FieldAssignment { name, fields, value, .. } => {
+                let mut expected = self.variable(name);
+                for field in fields.iter() {
+                    expected = expected.and_then(|ty| self.field_type(&ty, field));
+                }
+                self.expression(value, expected.as_ref())?;
+            }
+            Statement::IndexAssignment { name, index, value, .. } => {
+                self.expression(index, Some(&Type::I32))?;
+                let expected = match self.variable(name) {
+                    Some(Type::FixedArray(element, _)) => Some(*element),
+                    _ => None,
+                };
+                self.expression(value, expected.as_ref())?;
+            }
+            Statement::Return { value, .. } => {
+                let expected = self.return_type.clone();
+                self.expression(value, Some(&expected))?;
+            }
+            Statement::Expression(expression, _) | Statement::Defer { expression, .. } => {
+                self.expression(expression, None)?;
+            }
+            Statement::Block { statements, .. } | Statement::Loop { body: statements, .. } => self.statements(statements)?,
+            Statement::While { condition, body, .. } => {
+                self.expression(condition, Some(&Type::Bool))?;
+                self.statements(body)?;
+            }
+            Statement::For { variable, start, end, body, .. } => {
+                let counter = self.expression(start, None)?.unwrap_or(Type::I32);
+                self.expression(end, Some(&counter))?;
+                self.scopes.push((variable.clone(), counter));
+                self.statements(body)?;
+                self.scopes.pop();
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.expression(condition, Some(&Type::Bool))?;
+                self.statements(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.statements(else_branch)?;
+                }
+            }
+            Statement::Break { .. } | Statement::Continue { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Переписывает обобщённые вызовы и литералы в выражении; возвращает
+    /// его тип, если его можно определить
+    fn expression(&mut self, expression: &mut Expression, expected: Option<&Type>) -> GenericResult<Option<Type>> {
+        Ok(match expression {
+            Expression::IntegerLiteral(_) => Some(Type::I32),
+            Expression::FloatLiteral(_) => Some(Type::F32),
+            Expression::StringLiteral(_) => Some(Type::String),
+            Expression::BoolLiteral(_) => Some(Type::Bool),
+            Expression::Variable(name) => self.variable(name).or_else(|| {
+                // Имя функции - значение-функция
+                self.signatures.get(name.as_str())
+                    .map(|(params, return_type)| Type::Function(params.clone(), Box::new(return_type.clone())))
+            }),
+            Expression::BinaryExpression { left, operator, right } => {
+                let operand = match operator {
+                    BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply
+                    | BinaryOperator::Divide | BinaryOperator::Mod => expected,
+                    _ => None,
+                };
+                let left = self.expression(left, operand)?;
+                let right = self.expression(right, left.as_ref().or(operand))?;
+                match operator {
+                    BinaryOperator::Eq | BinaryOperator::Neq | BinaryOperator::Lt | BinaryOperator::Gt
+                    | BinaryOperator::Lte | BinaryOperator::Gte | BinaryOperator::And | BinaryOperator::Or => Some(Type::Bool),
+                    _ => left.or(right),
+                }
+            }
+            Expression::FunctionCall { name, args } => self.function_call(name, args, expected)?,
+            Expression::StructInitialization { struct_name, fields, base } => {
+                let base_type = match base {
+                    Some(base) => self.expression(base, None)?,
+                    None => None,
+                };
+                if let Some(&generic) = self.generic_structs.get(struct_name.as_str()) {
+                    let args = self.infer_literal(generic, fields, base_type.as_ref(), expected)?;
+                    *struct_name = self.instantiate_struct(struct_name, &args)?;
+                } else {
+                    for (field, value) in fields.iter_mut() {
+                        let hint = self.field_type(&Type::Struct(struct_name.clone()), field);
+                        self.expression(value, hint.as_ref())?;
+                    }
+                }
+                Some(Type::Struct(struct_name.clone()))
+            }
+            Expression::FieldAccess { expression, field_name } => {
+                let ty = self.expression(expression, None)?;
+                ty.and_then(|ty| self.field_type(&ty, field_name))
+            }
+            Expression::TypeCast { expression, target_type } => {
+                self.expression(expression, None)?;
+                Some(target_type.clone())
+            }
+            Expression::Move { expression } | Expression::Borrow { expression, .. } => self.expression(expression, expected)?,
+            Expression::Ref { expression } => {
+                let inner = match expected {
+                    Some(Type::Ref(inner)) => Some(&**inner),
+                    _ => None,
+                };
+                self.expression(expression, inner)?.map(|ty| Type::Ref(Box::new(ty)))
+            }
+            Expression::ArrayLiteral { elements } => {
+                let mut element_type = match expected {
+                    Some(Type::Array(element) | Type::Slice(element) | Type::FixedArray(element, _)) => Some((**element).clone()),
+                    _ => None,
+                };
+                for element in elements.iter_mut() {
+                    let ty = self.expression(element, element_type.as_ref())?;
+                    element_type = element_type.or(ty);
+                }
+                element_type.map(|element| Type::Array(Box::new(element)))
+            }
+            Expression::ArrayRepeat { value, count } => {
+                let element = match expected {
+                    Some(Type::Array(element) | Type::Slice(element) | Type::FixedArray(element, _)) => Some(&**element),
+                    _ => None,
+                };
+                self.expression(value, element)?.map(|element| Type::FixedArray(Box::new(element), *count))
+            }
+            Expression::Index { expression, index } => {
+                self.expression(index, Some(&Type::I32))?;
+                match self.expression(expression, None)? {
+                    Some(Type::Array(element) | Type::Slice(element) | Type::FixedArray(element, _)) => Some(*element),
+                    _ => None,
+                }
+            }
+            Expression::Slice { expression, start, end } => {
+                for bound in [start, end].into_iter().flatten() {
+                    self.expression(bound, Some(&Type::I32))?;
+                }
+                match self.expression(expression, None)? {
+                    Some(Type::Array(element) | Type::Slice(element) | Type::FixedArray(element, _)) => Some(Type::Slice(element)),
+                    _ => None,
+                }
+            }
+            Expression::EnumVariant { enum_name, variant, args } => self.enum_variant(enum_name, variant, args, expected)?,
+            Expression::Match { subject, arms } => {
+                let subject_type = self.expression(subject, None)?;
+                let mut result = None;
+                for arm in arms.iter_mut() {
+                    let scope = self.scopes.len();
+                    if let Pattern::Variant { enum_name, variant, bindings } = &arm.pattern {
+                        let fields = subject_type.as_ref()
+                            .map(|ty| self.variant_fields(ty, enum_name, variant))
+                            .unwrap_or_default();
+                        for (binding, field) in bindings.iter().zip(fields) {
+                            self.scopes.push((binding.clone(), field));
+                        }
+                    }
+                    let ty = self.expression(&mut arm.body, expected)?;
+                    self.scopes.truncate(scope);
+                    if result.is_none() && ty != Some(Type::Never) {
+                        result = ty;
+                    }
+                }
+                result
+            }
+            Expression::If { condition, then_branch, else_branch } => {
+                self.expression(condition, Some(&Type::Bool))?;
+                let then_type = self.expression(then_branch, expected)?;
+                let else_type = self.expression(else_branch, expected)?;
+                then_type.filter(|ty| *ty != Type::Never).or(else_type)
+            }
+            Expression::MethodCall { receiver, method, args } => {
+                let receiver_type = self.expression(receiver, None)?;
+                self.method_call(receiver_type, method, args)?
+            }
+            Expression::Try { expression } => match self.expression(expression, None)? {
+                Some(Type::Option(inner) | Type::Result(inner, _)) => Some(*inner),
+                _ => None,
+            },
+            Expression::Lambda { params, return_type, body } => {
+                let scope = self.scopes.len();
+                self.scopes.extend(params.iter().map(|param| (param.name.clone(), param.param_type.clone())));
+                let outer_return = std::mem::replace(&mut self.return_type, return_type.clone());
+                self.statements(body)?;
+                self.return_type = outer_return;
+                self.scopes.truncate(scope);
+                Some(Type::Function(
+                    params.iter().map(|param| param.param_type.clone()).collect(),
+                    Box::new(return_type.clone()),
+                ))
+            }
+        })
+    }
+
+    fn function_call(&mut self, name: &mut String, args: &mut [Expression], expected: Option<&Type>) -> GenericResult<Option<Type>> {
+        // Локальная переменная-функция закрывает одноимённую функцию
+        if let Some(variable) = self.variable(name) {
+            let (params, return_type) = match variable {
+                Type::Function(params, return_type) => (params, Some(*return_type)),
+                _ => (Vec::new(), None),
+            };
+            self.arguments(args, &params)?;
+            return Ok(return_type);
+        }
+        if let Some(&generic) = self.generic_functions.get(name.as_str()) {
+            let type_args = self.infer_call(generic, &generic.type_params, args, expected)?;
+            let substitution = generic.type_params.iter().cloned().zip(type_args.iter().cloned()).collect();
+            let instance = instance_name(name, &type_args);
+            self.instantiate_function(generic, instance.clone(), substitution)?;
+            let return_type = self.signatures[&instance].1.clone();
+            *name = instance;
+            return Ok(Some(return_type));
+        }
+        if let Some((params, return_type)) = self.signatures.get(name.as_str()).cloned() {
+            self.arguments(args, &params)?;
+            return Ok(Some(return_type));
+        }
+        let Some(builtin) = stdlib::lookup(name) else {
+            self.arguments(args, &[])?;
+            return Ok(None);
+        };
+        let params: Vec<Type> = builtin.params.iter().map(|(_, ty)| ty.clone()).collect();
+        if builtin.numeric {
+            // clamp(1, 0, 10) - целый, если все аргументы целые
+            let types = self.arguments(args, &[])?;
+            let integer = types.iter().all(|ty| *ty == Some(Type::I32));
+            return Ok(Some(if integer { Type::I32 } else { Type::F32 }));
+        }
+        if !builtin.type_params.is_empty() {
+            // map(values, f) - тип результата выводится из аргументов
+            let types = self.arguments(args, &[])?;
+            let Some(types) = types.into_iter().collect::<Option<Vec<_>>>() else {
+                return Ok(None);
+            };
+            let bindings = builtin.bind_type_params(&types);
+            return Ok(Some(stdlib::substitute(&builtin.return_type, &bindings)));
+        }
+        self.arguments(args, &params)?;
+        Ok(Some(builtin.return_type.clone()))
+    }
+
+    fn arguments(&mut self, args: &mut [Expression], params: &[Type]) -> GenericResult<Vec<Option<Type>>> {
+        let mut types = Vec::new();
+        for (i, arg) in args.iter_mut().enumerate() {
+            types.push(self.expression(arg, params.get(i))?);
+        }
+        Ok(types)
+    }
+
+    /// Вариант перечисления, Some/Ok/Err или статический метод `Pair::new(..)`
+    fn enum_variant(&mut self, enum_name: &mut String, variant: &str, args: &mut [Expression], expected: Option<&Type>) -> GenericResult<Option<Type>> {
+        let generic_method = self.generic_structs.get(enum_name.as_str())
+            .zip(self.generic_functions.get(method_name(enum_name, variant).as_str()));
+        if let Some((&struct_def, &generic)) = generic_method {
+            let type_args = self.infer_call(generic, &struct_def.type_params, args, expected)?;
+            *enum_name = self.instantiate_struct(enum_name, &type_args)?;
+            return Ok(self.signatures.get(&method_name(enum_name, variant)).map(|(_, return_type)| return_type.clone()));
+        }
+
+        let function = method_name(enum_name, variant);
+        if let Some((params, return_type)) = self.signatures.get(&function).cloned() {
+            self.arguments(args, &params)?;
+            return Ok(Some(return_type));
+        }
+        let hint = |ty: Option<&Type>| ty.cloned().filter(|ty| *ty != Type::Void);
+        Ok(Some(match (enum_name.as_str(), variant) {
+            ("Option", "Some") => {
+                let inner = match expected {
+                    Some(Type::Option(inner)) => hint(Some(inner)),
+                    _ => None,
+                };
+                let ty = self.arguments(args, inner.as_slice())?.pop().flatten();
+                Type::Option(Box::new(ty.or(inner).unwrap_or(Type::Void)))
+            }
+            ("Option", "None") => expected.cloned().unwrap_or(Type::Option(Box::new(Type::Void))),
+            ("Result", "Ok" | "Err") => {
+                let (ok, err) = match expected {
+                    Some(Type::Result(ok, err)) => (hint(Some(ok)), hint(Some(err))),
+                    _ => (None, None),
+                };
+                let is_ok = variant == "Ok";
+                let known = if is_ok { &ok } else { &err };
+                let ty = self.arguments(args, known.as_slice())?.pop().flatten().or_else(|| known.clone());
+                let (ok, err) = if is_ok { (ty, err) } else { (ok, ty) };
+                Type::Result(Box::new(ok.unwrap_or(Type::Void)), Box::new(err.unwrap_or(Type::Void)))
+            }
+            _ => {
+                let fields = self.enums.get(enum_name.as_str())
+                    .and_then(|enum_def| enum_def.variant(variant))
+                    .map(|variant| variant.fields.clone())
+                    .unwrap_or_default();
+                self.arguments(args, &fields)?;
+                Type::Enum(enum_name.clone())
+            }
+        }))
+    }
+
+    fn method_call(&mut self, receiver: Option<Type>, method: &str, args: &mut [Expression]) -> GenericResult<Option<Type>> {
+        let type_name = match &receiver {
+            Some(Type::Struct(name) | Type::Enum(name)) => Some(name.clone()),
+            Some(Type::Ref(inner)) => match &**inner {
+                Type::Struct(name) => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some((params, return_type)) = type_name.as_ref()
+            .and_then(|type_name| self.signatures.get(&method_name(type_name, method)))
+            .cloned()
+        {
+            self.arguments(args, params.get(1..).unwrap_or_default())?;
+            return Ok(Some(return_type));
+        }
+        self.arguments(args, &[])?;
+        // Методы из @derive и трейтов
+        let derived = type_name.as_ref()
+            .and_then(|type_name| self.structs.get(type_name))
+            .and_then(|struct_def| struct_def.derives.iter().find(|derive| derive.method() == method));
+        if let Some(derive) = derived {
+            return Ok(Some(match derive {
+                Derive::Debug => Type::String,
+                Derive::Eq => Type::Bool,
+                Derive::Clone => receiver.unwrap_or(Type::Void),
+            }));
+        }
+        let trait_method = type_name.as_ref()
+            .and_then(|type_name| self.program.traits.iter().find(|trait_def| trait_def.name == *type_name))
+            .and_then(|trait_def| trait_def.method(method));
+        Ok(match (trait_method, &receiver) {
+            (Some(trait_method), _) => Some(trait_method.return_type.clone()),
+            (None, Some(Type::Array(_) | Type::Slice(_) | Type::FixedArray(..) | Type::String)) if method == "len" => Some(Type::I32),
+            _ => None,
+        })
+    }
+
+    /// Поля варианта в образце match для значения типа ty
+    fn variant_fields(&self, ty: &Type, enum_name: &str, variant: &str) -> Vec<Type> {
+        match (ty, variant) {
+            (Type::Option(inner), "Some") | (Type::Result(inner, _), "Ok") | (Type::Result(_, inner), "Err") => vec![(**inner).clone()],
+            _ => self.enums.get(enum_name)
+                .and_then(|enum_def| enum_def.variant(variant))
+                .map(|variant| variant.fields.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Аргументы типа литерала обобщённой структуры: по ожидаемому типу,
+    /// `..base` и значениям полей
+    fn infer_literal(&mut self, generic: &Struct, fields: &mut [(String, Expression)], base: Option<&Type>, expected: Option<&Type>) -> GenericResult<Vec<Type>> {
+        let type_params = &generic.type_params;
+        let mut bindings: Vec<Option<Type>> = expected.or(base)
+            .and_then(|ty| self.instance_of(ty, &generic.name))
+            .map_or_else(|| vec![None; type_params.len()], |args| args.into_iter().map(Some).collect());
+        for (field, value) in fields.iter_mut() {
+            let Some(declared) = generic.fields.iter().find(|candidate| candidate.name == *field) else {
+                self.expression(value, None)?;
+                continue;
+            };
+            // Поле с известными параметрами подсказывает тип значению
+            let substitution: Substitution = type_params.iter().cloned()
+                .zip(bindings.iter().cloned())
+                .filter_map(|(param, binding)| binding.map(|binding| (param, binding)))
+                .collect();
+            let hint = match mentions(&declared.field_type, type_params) && substitution.len() < type_params.len() {
+                true => None,
+                false => Some(self.concrete(&declared.field_type, &substitution)?),
+            };
+            if let Some(value_type) = self.expression(value, hint.as_ref())? {
+                self.unify(&generic.name, type_params, &declared.field_type, &value_type, &mut bindings)?;
+            }
+        }
+        bindings.into_iter().zip(type_params)
+            .map(|(binding, param)| binding.ok_or_else(|| GenericError::CannotInfer {
+                name: generic.name.clone(),
+                param: param.clone(),
+            }))
+            .collect()
+    }
+}
+
+/// Заменяет параметры типа и обобщённые структуры в аннотациях тела:
+/// у переменных, приведений и замыканий
+struct Substituter<'m, 'a, 's> {
+    monomorphizer: &'m mut Monomorphizer<'a>,
+    substitution: &'s [(String, Type)],
+    error: Option<GenericError>,
+}
+
+impl Substituter<'_, '_, '_> {
+    fn replace(&mut self, ty: &mut Type) {
+        if self.error.is_some() {
+            return;
+        }
+        match self.monomorphizer.concrete(ty, self.substitution) {
+            Ok(concrete) => *ty = concrete,
+            Err(error) => self.error = Some(error),
+        }
+    }
+}
+
+impl crate::visit::VisitorMut for Substituter<'_, '_, '_> {
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        if let Statement::VariableDeclaration { var_type, .. } = statement {
+            self.replace(var_type);
+        }
+        crate::visit::walk_statement_mut(self, statement);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        match expression {
+            Expression::TypeCast { target_type, .. } => self.replace(target_type),
+            Expression::Lambda { params, return_type, .. } => {
+                for param in params {
+                    self.replace(&mut param.param_type);
+                }
+                self.replace(return_type);
+            }
+            _ => {}
+        }
+        crate::visit::walk_expression_mut(self, expression);
+    }
+}
+
+fn signature(function: &Function) -> (Vec<Type>, Type) {
+    (
+        function.params.iter().map(|param| param.param_type.clone()).collect(),
+        function.return_type.clone(),
+    )
+}
+
+/// Встречается ли в типе один из параметров
+fn mentions(ty: &Type, type_params: &[String]) -> bool {
+    match ty {
+        Type::Struct(name) => type_params.contains(name),
+        Type::Generic(_, args) => args.iter().any(|arg| mentions(arg, type_params)),
+        Type::Ref(inner) | Type::Array(inner) | Type::Slice(inner) | Type::FixedArray(inner, _) | Type::Option(inner) => mentions(inner, type_params),
+        Type::Result(ok, err) => mentions(ok, type_params) || mentions(err, type_params),
+        Type::Function(params, return_type) => {
+            params.iter().any(|param| mentions(param, type_params)) || mentions(return_type, type_params)
+        }
+        _ => false,
+    }
+}
+
+/// Перечисление в аннотации записано как Struct, в значении - как Enum
+fn same_type(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (Type::Struct(a) | Type::Enum(a), Type::Struct(b) | Type::Enum(b)) => a == b,
+        _ => a == b,
+    }
+}
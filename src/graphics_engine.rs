@@ -1,60 +1,450 @@
-use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::animation::FrameClock;
+use crate::color;
+use crate::font;
+use minifb::{InputCallback, Key, MouseButton, MouseMode, Window, WindowOptions};
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Сколько последних строк console_log показывает оверлей
+pub const CONSOLE_LINES: usize = 8;
+
+/// Интервал кадра при включённой синхронизации (~60 FPS)
+const FRAME_INTERVAL: Duration = Duration::from_micros(16600);
+
+/// Параметры окна: из `--width/--height/--title/--vsync` или из init_graphics
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowConfig {
+    pub width: usize,
+    pub height: usize,
+    pub title: String,
+    /// Ограничивать частоту кадров; без неё цикл кадра крутится без пауз
+    pub vsync: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            title: "AetOS Program".to_string(),
+            vsync: true,
+        }
+    }
+}
+
+/// Журнал сообщений console_log для оверлея поверх кадра
+pub struct ConsoleLog {
+    lines: VecDeque<String>,
+    capacity: usize,
+    pub visible: bool,
+}
+
+impl ConsoleLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+            visible: false,
+        }
+    }
+
+    /// Добавляет сообщение; старые строки вытесняются
+    pub fn push(&mut self, message: &str) {
+        for line in message.lines() {
+            if self.lines.len() == self.capacity {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(line.to_string());
+        }
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Рисует журнал в верхней части кадра на затемнённой подложке
+    pub fn draw(&self, buffer: &mut [u32], width: usize, height: usize) {
+        let panel_height = (self.lines.len() * font::LINE_HEIGHT + 4).min(height);
+        for pixel in &mut buffer[..panel_height * width] {
+            // Затемняем фон в 4 раза, чтобы текст читался поверх любой сцены
+            *pixel = (*pixel >> 2) & 0x3F3F3F;
+        }
+        for (index, line) in self.lines().enumerate() {
+            font::draw_text(buffer, width, height, 4, 2 + index * font::LINE_HEIGHT, line, 0x7FFF7F);
+        }
+    }
+}
+
+/// Счётчики кадра для debug_hud(true): время кадра и число draw-вызовов
+/// знает движок, шаги (выполненные операторы), выделения и живые структуры
+/// ("сущности") - интерпретатор
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    /// Длительность предыдущего кадра в секундах
+    pub frame_time: f32,
+    pub steps: usize,
+    pub draw_calls: usize,
+    pub entities: usize,
+    pub allocations: usize,
+}
+
+impl FrameStats {
+    pub fn fps(&self) -> u32 {
+        if self.frame_time > 0.0 {
+            (1.0 / self.frame_time).round() as u32
+        } else {
+            0
+        }
+    }
+
+    /// Строки оверлея
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("FPS {}", self.fps()),
+            format!("frame {:.1} ms", self.frame_time * 1000.0),
+            format!("steps {}", self.steps),
+            format!("draws {}", self.draw_calls),
+            format!("entities {}", self.entities),
+            format!("allocs {}", self.allocations),
+        ]
+    }
+}
+
+/// Оверлей debug_hud(true) в правом верхнем углу кадра
+#[derive(Debug, Default)]
+pub struct DebugHud {
+    pub visible: bool,
+    stats: FrameStats,
+}
+
+impl DebugHud {
+    /// Запоминает счётчики кадра, который сейчас будет показан
+    pub fn record(&mut self, stats: FrameStats) {
+        self.stats = stats;
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    pub fn draw(&self, buffer: &mut [u32], width: usize, height: usize) {
+        let lines = self.stats.lines();
+        let text_width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) * font::CHAR_ADVANCE;
+        let panel_width = (text_width + 8).min(width);
+        let panel_height = (lines.len() * font::LINE_HEIGHT + 4).min(height);
+        let left = width - panel_width;
+        for row in buffer.chunks_mut(width).take(panel_height) {
+            for pixel in &mut row[left..] {
+                *pixel = (*pixel >> 2) & 0x3F3F3F;
+            }
+        }
+        for (index, line) in lines.iter().enumerate() {
+            font::draw_text(buffer, width, height, left + 4, 2 + index * font::LINE_HEIGHT, line, 0xFFFF7F);
+        }
+    }
+}
+
+/// Набранный на клавиатуре текст для get_typed_text(). Регистр и раскладку
+/// (в том числе Shift) уже учитывает система; Backspace и Enter приходят
+/// отдельными сигналами и записываются как '\u{8}' и '\n'.
+#[derive(Debug, Default)]
+pub struct TextInput {
+    pending: String,
+}
+
+impl TextInput {
+    /// Символ из окна; управляющие символы отбрасываются, их передаёт `key_down`
+    pub fn add_char(&mut self, code: u32) {
+        if let Some(c) = char::from_u32(code).filter(|c| !c.is_control()) {
+            self.pending.push(c);
+        }
+    }
+
+    pub fn key_down(&mut self, key: Key) {
+        match key {
+            Key::Backspace => self.pending.push('\u{8}'),
+            Key::Enter | Key::NumPadEnter => self.pending.push('\n'),
+            _ => {}
+        }
+    }
+
+    /// Забирает всё, что набрано с прошлого вызова
+    pub fn take(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Клавиши, состояние которых опрашивается каждый кадр
+const TRACKED_KEYS: [Key; 46] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J,
+    Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T,
+    Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+    Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::Up, Key::Down, Key::Left, Key::Right,
+    Key::Space, Key::Enter, Key::Escape,
+    Key::F5, Key::F6, Key::F7,
+];
+
+/// Снимок клавиатуры за текущий и предыдущий кадр: is_key_pressed держится,
+/// пока клавиша зажата, а key_just_pressed/key_just_released срабатывают
+/// ровно в одном кадре
+#[derive(Debug, Default)]
+pub struct KeyboardState {
+    down: HashSet<Key>,
+    previous: HashSet<Key>,
+}
+
+impl KeyboardState {
+    /// Новый кадр: `is_down` сообщает, зажата ли клавиша сейчас
+    pub fn update(&mut self, is_down: impl Fn(Key) -> bool) {
+        self.previous = std::mem::take(&mut self.down);
+        self.down = TRACKED_KEYS.iter().copied().filter(|&key| is_down(key)).collect();
+    }
+
+    pub fn is_down(&self, key: Key) -> bool {
+        self.down.contains(&key)
+    }
+
+    pub fn just_pressed(&self, key: Key) -> bool {
+        self.down.contains(&key) && !self.previous.contains(&key)
+    }
+
+    pub fn just_released(&self, key: Key) -> bool {
+        !self.down.contains(&key) && self.previous.contains(&key)
+    }
+}
+
+/// Во сколько раз F7 замедляет кадры
+pub const SLOW_MOTION_FACTOR: u32 = 4;
+
+/// Отладочное управление циклом кадра: F5 - пауза, F6 - один кадр на паузе,
+/// F7 - замедление. Нажатия обрабатываются движком, программа их не видит
+#[derive(Debug, Default)]
+pub struct FrameControl {
+    paused: bool,
+    // Кадров, которые можно выполнить, несмотря на паузу
+    frames_allowed: usize,
+    slow_motion: bool,
+}
+
+impl FrameControl {
+    pub fn handle_keys(&mut self, just_pressed: impl Fn(Key) -> bool) {
+        if just_pressed(Key::F5) {
+            self.paused = !self.paused;
+            // Ещё один кадр после паузы: программа видит is_paused() и
+            // успевает нарисовать заставку паузы
+            self.frames_allowed = usize::from(self.paused);
+        }
+        if just_pressed(Key::F6) && self.paused {
+            self.frames_allowed += 1;
+        }
+        if just_pressed(Key::F7) {
+            self.slow_motion = !self.slow_motion;
+        }
+    }
+
+    /// Можно ли начать следующий кадр; разрешённый на паузе кадр расходуется
+    pub fn advance(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        if self.frames_allowed > 0 {
+            self.frames_allowed -= 1;
+            return true;
+        }
+        false
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn slow_motion(&self) -> bool {
+        self.slow_motion
+    }
+}
+
+// Окно владеет callback'ом, поэтому буфер разделяется с движком через Rc
+struct TextInputCallback(Rc<RefCell<TextInput>>);
+
+impl InputCallback for TextInputCallback {
+    fn add_char(&mut self, uni_char: u32) {
+        self.0.borrow_mut().add_char(uni_char);
+    }
+
+    fn set_key_state(&mut self, key: Key, state: bool) {
+        if state {
+            self.0.borrow_mut().key_down(key);
+        }
+    }
+}
+
+/// Клавиша по коду из is_key_pressed: код символа (`'w'`, `'W'`, `'7'`,
+/// `' '`, `'\n'`) или один из старых кодов стрелок 37-40 (как в браузере).
+/// Из-за них `'%'`, `'&'`, `'\''` и `'('` означают стрелки
+pub fn key_from_code(code: i32) -> Option<Key> {
+    const LETTERS: [Key; 26] = [
+        Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J,
+        Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T,
+        Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+        Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    ];
+    let key = match code {
+        37 => Key::Left,
+        38 => Key::Up,
+        39 => Key::Right,
+        40 => Key::Down,
+        32 => Key::Space,
+        10 | 13 => Key::Enter,
+        27 => Key::Escape,
+        48..=57 => DIGITS[(code - 48) as usize],
+        65..=90 => LETTERS[(code - 65) as usize],
+        97..=122 => LETTERS[(code - 97) as usize],
+        _ => return None,
+    };
+    Some(key)
+}
 
 pub struct GraphicsEngine {
     window: Window,
     buffer: Vec<u32>,
     width: usize,
     height: usize,
-    keys_pressed: HashMap<Key, bool>,
+    vsync: bool,
+    keys: KeyboardState,
+    pub frame_control: FrameControl,
     mouse_pos: (f32, f32),
     mouse_buttons: [bool; 3], // left, right, middle
     start_time: f64,
+    // Оверлей консоли, переключается клавишей ` (grave)
+    pub console: ConsoleLog,
+    console_key_down: bool,
+    overlay_buffer: Vec<u32>,
+    text_input: Rc<RefCell<TextInput>>,
+    // Текст, набранный за предыдущий кадр
+    typed_text: String,
+    // Предупреждать о тексте с контрастом ниже WCAG AA (`--contrast-warnings`);
+    // о каждой паре цветов сообщается один раз
+    pub contrast_warnings: bool,
+    warned_contrasts: HashSet<([u8; 3], [u8; 3])>,
+    frame_clock: FrameClock,
+    pub debug_hud: DebugHud,
+    // draw_* с прошлого render
+    draw_calls: usize,
 }
 
 impl GraphicsEngine {
-    pub fn new(width: usize, height: usize, title: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut window = Window::new(
-            title,
-            width,
-            height,
-            WindowOptions {
-                resize: true,
-                ..WindowOptions::default()
-            },
-        )?;
-
-        window.limit_update_rate(Some(std::time::Duration::from_micros(16600))); // ~60 FPS
+    pub fn new(config: &WindowConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let text_input = Rc::new(RefCell::new(TextInput::default()));
+        let window = Self::open_window(config, &text_input)?;
 
         Ok(Self {
             window,
-            buffer: vec![0; width * height],
-            width,
-            height,
-            keys_pressed: HashMap::new(),
+            buffer: vec![0; config.width * config.height],
+            width: config.width,
+            height: config.height,
+            vsync: config.vsync,
+            keys: KeyboardState::default(),
+            frame_control: FrameControl::default(),
             mouse_pos: (0.0, 0.0),
             mouse_buttons: [false; 3],
             start_time: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs_f64(),
+            console: ConsoleLog::new(CONSOLE_LINES),
+            console_key_down: false,
+            overlay_buffer: Vec::new(),
+            text_input,
+            typed_text: String::new(),
+            contrast_warnings: false,
+            warned_contrasts: HashSet::new(),
+            frame_clock: FrameClock::new(),
+            debug_hud: DebugHud::default(),
+            draw_calls: 0,
         })
     }
 
+    fn open_window(config: &WindowConfig, text_input: &Rc<RefCell<TextInput>>) -> Result<Window, Box<dyn std::error::Error>> {
+        let mut window = Window::new(
+            &config.title,
+            config.width,
+            config.height,
+            WindowOptions {
+                resize: true,
+                ..WindowOptions::default()
+            },
+        )?;
+        window.limit_update_rate(config.vsync.then_some(FRAME_INTERVAL));
+        window.set_input_callback(Box::new(TextInputCallback(text_input.clone())));
+        Ok(window)
+    }
+
+    /// Повторный init_graphics: minifb не меняет размер окна, поэтому при
+    /// новом размере окно открывается заново, а при том же меняется только заголовок
+    pub fn reconfigure(&mut self, config: &WindowConfig) -> Result<(), Box<dyn std::error::Error>> {
+        if (config.width, config.height) == (self.width, self.height) {
+            self.window.set_title(&config.title);
+        } else {
+            self.window = Self::open_window(config, &self.text_input)?;
+            self.width = config.width;
+            self.height = config.height;
+            self.buffer = vec![0; config.width * config.height];
+        }
+        if config.vsync != self.vsync {
+            self.window.limit_update_rate(config.vsync.then_some(FRAME_INTERVAL));
+            self.vsync = config.vsync;
+        }
+        Ok(())
+    }
+
     pub fn update(&mut self) -> bool {
-        // Обновляем состояние клавиш
-        for key in [
-            Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J,
-            Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T,
-            Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
-            Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
-            Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
-            Key::Up, Key::Down, Key::Left, Key::Right,
-            Key::Space, Key::Enter, Key::Escape,
-        ] {
-            self.keys_pressed.insert(key, self.window.is_key_down(key));
+        self.poll_input();
+        if !self.frame_control.advance() && !self.wait_while_paused() {
+            return false;
         }
+        if self.frame_control.slow_motion() {
+            std::thread::sleep(FRAME_INTERVAL * (SLOW_MOTION_FACTOR - 1));
+        }
+
+        self.typed_text = self.text_input.borrow_mut().take();
+        self.frame_clock.tick();
+
+        !self.should_close()
+    }
+
+    /// Держит цикл программы, пока пауза не снята (F5) или не разрешён кадр (F6);
+    /// окно при этом продолжает обрабатывать события. false - окно закрыли
+    fn wait_while_paused(&mut self) -> bool {
+        while !self.should_close() {
+            self.window.update();
+            std::thread::sleep(FRAME_INTERVAL);
+            self.poll_input();
+            if self.frame_control.advance() {
+                // Время на паузе не попадает в delta_time
+                self.frame_clock.restart();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn poll_input(&mut self) {
+        // Обновляем состояние клавиш
+        let window = &self.window;
+        self.keys.update(|key| window.is_key_down(key));
+        let keys = &self.keys;
+        self.frame_control.handle_keys(|key| keys.just_pressed(key));
 
         // Обновляем состояние мыши
         if let Some((x, y)) = self.window.get_mouse_pos(MouseMode::Clamp) {
@@ -65,7 +455,27 @@ impl GraphicsEngine {
         self.mouse_buttons[1] = self.window.get_mouse_down(MouseButton::Right);
         self.mouse_buttons[2] = self.window.get_mouse_down(MouseButton::Middle);
 
-        self.window.is_open() && !self.window.is_key_down(Key::Escape)
+        // Переключаем оверлей по нажатию, а не пока клавиша удерживается
+        let console_key_down = self.window.is_key_down(Key::Backquote);
+        if console_key_down && !self.console_key_down {
+            self.console.toggle();
+        }
+        self.console_key_down = console_key_down;
+    }
+
+    /// Длительность предыдущего кадра в секундах; при замедлении (F7) -
+    /// игровое время, а не реальное
+    pub fn delta_time(&self) -> f32 {
+        if self.frame_control.slow_motion() {
+            self.frame_clock.delta() / SLOW_MOTION_FACTOR as f32
+        } else {
+            self.frame_clock.delta()
+        }
+    }
+
+    /// Окно закрыто пользователем или нажат Escape
+    pub fn should_close(&self) -> bool {
+        !self.window.is_open() || self.window.is_key_down(Key::Escape)
     }
 
     pub fn clear(&mut self, r: u8, g: u8, b: u8) {
@@ -75,7 +485,18 @@ impl GraphicsEngine {
         }
     }
 
+    /// Длительность предыдущего кадра в секундах без учёта замедления (F7)
+    pub fn frame_time(&self) -> f32 {
+        self.frame_clock.delta()
+    }
+
+    /// Сколько раз вызывались draw_* с прошлого render
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
     pub fn draw_pixel(&mut self, x: i32, y: i32, r: u8, g: u8, b: u8) {
+        self.draw_calls += 1;
         if x >= 0 && x < self.width as i32 && y >= 0 && y < self.height as i32 {
             let index = y as usize * self.width + x as usize;
             self.buffer[index] = Self::rgb_to_u32(r, g, b);
@@ -83,6 +504,7 @@ impl GraphicsEngine {
     }
 
     pub fn draw_rect(&mut self, x: i32, y: i32, width: i32, height: i32, r: u8, g: u8, b: u8) {
+        self.draw_calls += 1;
         let color = Self::rgb_to_u32(r, g, b);
         for py in y..y + height {
             for px in x..x + width {
@@ -95,6 +517,7 @@ impl GraphicsEngine {
     }
 
     pub fn draw_circle(&mut self, center_x: i32, center_y: i32, radius: i32, r: u8, g: u8, b: u8) {
+        self.draw_calls += 1;
         let color = Self::rgb_to_u32(r, g, b);
         let radius_sq = radius * radius;
 
@@ -113,6 +536,7 @@ impl GraphicsEngine {
     }
 
     pub fn draw_line(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, r: u8, g: u8, b: u8) {
+        self.draw_calls += 1;
         let color = Self::rgb_to_u32(r, g, b);
         let dx = (x2 - x1).abs();
         let dy = -(y2 - y1).abs();
@@ -145,9 +569,50 @@ impl GraphicsEngine {
         }
     }
 
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, r: u8, g: u8, b: u8) {
+        self.draw_calls += 1;
+        if x < 0 || y < 0 {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if self.contrast_warnings {
+            self.check_text_contrast(x, y, text, [r, g, b]);
+        }
+        font::draw_text(&mut self.buffer, self.width, self.height, x, y, text, Self::rgb_to_u32(r, g, b));
+    }
+
+    /// Сравнивает цвет текста со средним цветом фона под ним
+    fn check_text_contrast(&mut self, x: usize, y: usize, text: &str, foreground: [u8; 3]) {
+        let text_width = text.chars().count() * font::CHAR_ADVANCE;
+        let background = color::average_color(&self.buffer, self.width, self.height, x, y, text_width, font::GLYPH_HEIGHT);
+        let ratio = color::contrast_ratio(foreground, background);
+        if ratio < color::WCAG_AA_TEXT && self.warned_contrasts.insert((foreground, background)) {
+            eprintln!(
+                "warning: text {:?} at ({}, {}) has contrast {:.2}:1 against its background, below WCAG AA {}:1",
+                text, x, y, ratio, color::WCAG_AA_TEXT
+            );
+        }
+    }
+
     pub fn render(&mut self) {
+        self.draw_calls = 0;
+        if !self.console.visible && !self.debug_hud.visible {
+            self.window
+                .update_with_buffer(&self.buffer, self.width, self.height)
+                .unwrap();
+            return;
+        }
+
+        // Оверлеи рисуются в отдельный буфер, чтобы не портить кадр программы
+        self.overlay_buffer.clone_from(&self.buffer);
+        if self.console.visible {
+            self.console.draw(&mut self.overlay_buffer, self.width, self.height);
+        }
+        if self.debug_hud.visible {
+            self.debug_hud.draw(&mut self.overlay_buffer, self.width, self.height);
+        }
         self.window
-            .update_with_buffer(&self.buffer, self.width, self.height)
+            .update_with_buffer(&self.overlay_buffer, self.width, self.height)
             .unwrap();
     }
 
@@ -160,7 +625,22 @@ impl GraphicsEngine {
     }
 
     pub fn is_key_pressed(&self, key: Key) -> bool {
-        *self.keys_pressed.get(&key).unwrap_or(&false)
+        self.keys.is_down(key)
+    }
+
+    /// Клавиша нажата в этом кадре (в прошлом была отпущена)
+    pub fn key_just_pressed(&self, key: Key) -> bool {
+        self.keys.just_pressed(key)
+    }
+
+    /// Клавиша отпущена в этом кадре (в прошлом была зажата)
+    pub fn key_just_released(&self, key: Key) -> bool {
+        self.keys.just_released(key)
+    }
+
+    /// Символы, набранные с прошлого кадра (см. `TextInput`)
+    pub fn typed_text(&self) -> &str {
+        &self.typed_text
     }
 
     // Новые методы для работы с мышью
@@ -2,9 +2,10 @@ use crate::ast::Program;
 use crate::parser::Parser;
 use crate::typecheck::TypeChecker;
 use crate::interpreter::Interpreter;
+use crate::lint;
+use crate::source;
 use std::error::Error;
 use std::io::{self, Write};
-use std::fs;
 
 const HELP_TEXT: &str = r#"
 Aetos Interactive Environment (v0.3.0)
@@ -138,6 +139,7 @@ impl AetosIDE {
                     println!("Last parsed program:");
                     println!("  Functions: {}", program.functions.len());
                     println!("  Structs: {}", program.structs.len());
+                    println!("  Enums: {}", program.enums.len());
                     for func in &program.functions {
                         println!("  Function: {}", func.name);
                     }
@@ -168,7 +170,7 @@ impl AetosIDE {
             }
             ".load" if parts.len() > 1 => {
                 let filename = parts[1];
-                match fs::read_to_string(filename) {
+                match source::read_to_string(filename) {
                     Ok(content) => {
                         println!("Loaded {} ({} bytes)", filename, content.len());
                         // Show first few lines
@@ -221,15 +223,19 @@ impl AetosIDE {
     }
 
     fn run_file(&self, filename: &str) -> Result<String, Box<dyn Error>> {
-        let source = fs::read_to_string(filename)?;
+        let source = source::read_to_string(filename)?;
         let mut parser = Parser::new(&source);
         let program = parser.parse_program()?;
         
         let mut type_checker = TypeChecker::new();
         type_checker.check_program(&program)?;
         
-        Ok(format!("✓ Successfully parsed {} ({} functions)", 
-                  filename, program.functions.len()))
+        let mut report = format!("✓ Successfully parsed {} ({} functions)", 
+                  filename, program.functions.len());
+        for warning in lint::lint_program(&program) {
+            report.push_str(&format!("\n⚠  {}", warning));
+        }
+        Ok(report)
     }
 }
 
@@ -0,0 +1,365 @@
+// index.rs - индекс символов проекта (файл `.aetos-index`)
+//
+// `aetosc check` и `aetosc compile` записывают в каталог проекта JSON с
+// объявлениями верхнего уровня каждого файла (функции, методы, структуры,
+// перечисления, константы, статические переменные) и всеми упоминаниями
+// их имён. Редактор по индексу сразу переходит к определению и ищет
+// символы по проекту, не разбирая заново все файлы: достаточно сверить
+// хэш файла и переразобрать только изменившиеся. Запросы редактора
+// выполняет `aetosc symbols definition|references|search`.
+//
+// Упоминания находятся по токенам, без областей видимости: параметр с
+// именем функции тоже считается упоминанием, а `x.update()` относится к
+// методу update любого типа (как в determinism.rs).
+
+use crate::ast::*;
+use crate::crash::source_hash;
+use crate::edition::Edition;
+use crate::lexer::{Lexer, Token};
+use crate::parser::Parser;
+use crate::source;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const INDEX_FILE: &str = ".aetos-index";
+
+/// Версия формата; индекс другой версии строится заново
+pub const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Const,
+    Static,
+}
+
+/// Объявление: имя методов - `Point::length`, позиция - имени в объявлении
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Упоминание имени: `length`, `Point::length` или `.length` (вызов через точку)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Occurrence {
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileIndex {
+    /// FNV-1a исходника, см. crash::source_hash
+    pub hash: u64,
+    pub symbols: Vec<Symbol>,
+    pub occurrences: Vec<Occurrence>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// `lib/shapes.aetos:11:12` - формат, который понимают редакторы
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    pub version: u32,
+    /// Путь относительно каталога индекса -> символы файла
+    pub files: BTreeMap<String, FileIndex>,
+}
+
+impl Default for SymbolIndex {
+    fn default() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+impl SymbolIndex {
+    /// Индекс из `dir`; нет файла, он повреждён или старой версии - пустой
+    pub fn load(dir: &Path) -> Self {
+        std::fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str::<SymbolIndex>(&text).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default()
+    }
+
+    /// Обновляет в индексе из `dir` записи `files` и пишет его обратно.
+    /// Файлы, которых больше нет, из индекса удаляются
+    pub fn update(dir: &Path, files: &[PathBuf]) -> io::Result<PathBuf> {
+        let mut index = Self::load(dir);
+        index.files.retain(|file, _| dir.join(file).is_file());
+        for path in files {
+            let source = source::read_to_string(path)?;
+            let file = relative_path(dir, path);
+            let edition = Edition::detect(&source, path);
+            index.index_source(&file, &source, edition);
+        }
+        let path = dir.join(INDEX_FILE);
+        std::fs::write(&path, serde_json::to_string(&index).unwrap_or_default())?;
+        Ok(path)
+    }
+
+    /// Переиндексирует файл; если он не разбирается, остаётся прежняя запись
+    pub fn index_source(&mut self, file: &str, source: &str, edition: Edition) {
+        if self.is_fresh(file, source) {
+            return;
+        }
+        let Ok(program) = Parser::with_edition(source, edition).parse_program() else {
+            return;
+        };
+        let mut kinds = BTreeMap::new();
+        for function in &program.functions {
+            let kind = match split_method_name(&function.name) {
+                Some(_) => SymbolKind::Method,
+                None => SymbolKind::Function,
+            };
+            kinds.insert(function.name.clone(), kind);
+        }
+        kinds.extend(program.structs.iter().map(|s| (s.name.clone(), SymbolKind::Struct)));
+        kinds.extend(program.enums.iter().map(|e| (e.name.clone(), SymbolKind::Enum)));
+        kinds.extend(program.traits.iter().map(|t| (t.name.clone(), SymbolKind::Trait)));
+        for trait_def in &program.traits {
+            kinds.extend(trait_def.methods.iter().map(|m| (method_name(&trait_def.name, &m.name), SymbolKind::Method)));
+        }
+        kinds.extend(program.consts.iter().map(|c| (c.name.clone(), SymbolKind::Const)));
+        kinds.extend(program.statics.iter().map(|s| (s.name.clone(), SymbolKind::Static)));
+
+        let (symbols, occurrences) = scan_tokens(source, edition, &kinds);
+        self.files.insert(file.to_string(), FileIndex {
+            hash: source_hash(source),
+            symbols,
+            occurrences,
+        });
+    }
+
+    /// Запись файла соответствует исходнику и переразбирать его не нужно
+    pub fn is_fresh(&self, file: &str, source: &str) -> bool {
+        self.files.get(file).is_some_and(|entry| entry.hash == source_hash(source))
+    }
+
+    /// Объявления с именем `name` во всех файлах
+    pub fn definitions(&self, name: &str) -> Vec<(Location, &Symbol)> {
+        let mut found = Vec::new();
+        for (file, entry) in &self.files {
+            for symbol in entry.symbols.iter().filter(|symbol| symbol.name == name) {
+                found.push((location(file, symbol.line, symbol.column), symbol));
+            }
+        }
+        found
+    }
+
+    /// Символы, имя которых содержит `query` без учёта регистра (поиск по проекту)
+    pub fn workspace_symbols(&self, query: &str) -> Vec<(Location, &Symbol)> {
+        let query = query.to_lowercase();
+        let mut found = Vec::new();
+        for (file, entry) in &self.files {
+            for symbol in entry.symbols.iter().filter(|symbol| symbol.name.to_lowercase().contains(&query)) {
+                found.push((location(file, symbol.line, symbol.column), symbol));
+            }
+        }
+        found
+    }
+
+    /// Определение символа под позицией: объявление в том же файле, иначе
+    /// единственное объявление в проекте
+    pub fn definition_at(&self, file: &str, line: usize, column: usize) -> Option<Location> {
+        let entry = self.files.get(file)?;
+        let covers = |name: &str, at_line: usize, at_column: usize| {
+            let length = short_name(name).chars().count();
+            at_line == line && (at_column..at_column + length).contains(&column)
+        };
+        if let Some(symbol) = entry.symbols.iter().find(|symbol| covers(&symbol.name, symbol.line, symbol.column)) {
+            return Some(location(file, symbol.line, symbol.column));
+        }
+        let occurrence = entry.occurrences.iter()
+            .find(|occurrence| covers(&occurrence.name, occurrence.line, occurrence.column))?;
+        self.resolve(file, &occurrence.name).into_iter().next()
+    }
+
+    /// Упоминания символа, объявленного в `file`, по всему проекту
+    pub fn references(&self, file: &str, name: &str) -> Vec<Location> {
+        let target = match self.definitions(name).into_iter().find(|(location, _)| location.file == file) {
+            Some((location, _)) => location,
+            None => return Vec::new(),
+        };
+        let dotted = split_method_name(name).map(|(_, method)| format!(".{}", method));
+        let mut found = Vec::new();
+        for (other, entry) in &self.files {
+            for occurrence in &entry.occurrences {
+                if occurrence.name != name && Some(&occurrence.name) != dotted.as_ref() {
+                    continue;
+                }
+                if self.resolve(other, &occurrence.name).contains(&target) {
+                    found.push(location(other, occurrence.line, occurrence.column));
+                }
+            }
+        }
+        found
+    }
+
+    /// Упоминания символа под позицией - его объявления или упоминания
+    pub fn references_at(&self, file: &str, line: usize, column: usize) -> Vec<Location> {
+        let Some(target) = self.definition_at(file, line, column) else {
+            return Vec::new();
+        };
+        let symbol = self.files.get(&target.file)
+            .and_then(|entry| entry.symbols.iter()
+                .find(|symbol| symbol.line == target.line && symbol.column == target.column));
+        match symbol {
+            Some(symbol) => self.references(&target.file, &symbol.name),
+            None => Vec::new(),
+        }
+    }
+
+    /// Объявления, к которым относится упоминание `name` в файле `file`
+    fn resolve(&self, file: &str, name: &str) -> Vec<Location> {
+        let matches = |symbol: &Symbol| match name.strip_prefix('.') {
+            Some(method) => split_method_name(&symbol.name).is_some_and(|(_, m)| m == method),
+            None => symbol.name == name,
+        };
+        let in_file: Vec<Location> = self.files.get(file)
+            .map(|entry| entry.symbols.iter()
+                .filter(|symbol| matches(symbol))
+                .map(|symbol| location(file, symbol.line, symbol.column))
+                .collect())
+            .unwrap_or_default();
+        if !in_file.is_empty() {
+            return in_file;
+        }
+        let elsewhere: Vec<Location> = self.files.iter()
+            .flat_map(|(other, entry)| entry.symbols.iter()
+                .filter(|symbol| matches(symbol))
+                .map(move |symbol| location(other, symbol.line, symbol.column)))
+            .collect();
+        // Одноимённые функции в разных программах (например, main) - неоднозначно
+        if elsewhere.len() == 1 || name.starts_with('.') {
+            elsewhere
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+fn location(file: &str, line: usize, column: usize) -> Location {
+    Location { file: file.to_string(), line, column }
+}
+
+fn short_name(name: &str) -> &str {
+    let name = name.trim_start_matches('.');
+    split_method_name(name).map_or(name, |(_, method)| method)
+}
+
+/// Путь с `/` относительно каталога индекса, чтобы индекс переносился между ОС
+pub fn relative_path(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    relative.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Позиции объявлений из `kinds` и упоминаний имён; какие из упоминаний
+/// относятся к символам проекта, решается при запросе (символ может быть
+/// объявлен в другом файле)
+fn scan_tokens(source: &str, edition: Edition, kinds: &BTreeMap<String, SymbolKind>) -> (Vec<Symbol>, Vec<Occurrence>) {
+    let mut symbols = Vec::new();
+    let mut occurrences = Vec::new();
+
+    let mut lexer = Lexer::with_edition(source, edition);
+    let mut previous: [Option<Token>; 2] = [None, None];
+    // Тип impl-блока (или трейт) и глубина скобок, на которой он открыт
+    let mut pending_impl = None;
+    let mut current_impl: Option<(String, usize)> = None;
+    let mut depth = 0;
+
+    while let Some((token, line)) = lexer.next_with_line() {
+        let column = lexer.column();
+        match &token {
+            Token::BraceOpen => {
+                depth += 1;
+                if let Some(type_name) = pending_impl.take() {
+                    current_impl = Some((type_name, depth));
+                }
+            }
+            Token::BraceClose => {
+                if current_impl.as_ref().is_some_and(|(_, open)| *open == depth) {
+                    current_impl = None;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            // Имя типа - первое имя после `impl`, не считая `impl<T>`
+            Token::KeywordImpl => pending_impl = Some(String::new()),
+            Token::Identifier(name) => {
+                let declared = match (&previous[0], &previous[1]) {
+                    (_, Some(Token::KeywordFn)) => Some(match &current_impl {
+                        Some((type_name, _)) => method_name(type_name, name),
+                        None => name.clone(),
+                    }),
+                    (_, Some(Token::KeywordStruct | Token::KeywordEnum | Token::KeywordConst | Token::KeywordStatic))
+                    | (Some(Token::KeywordStatic), Some(Token::KeywordMut)) => Some(name.clone()),
+                    (_, Some(Token::KeywordTrait)) => {
+                        pending_impl = Some(name.clone());
+                        Some(name.clone())
+                    }
+                    (_, previous) if pending_impl.as_deref() == Some("")
+                        && !matches!(previous, Some(Token::OperatorLt | Token::Comma)) =>
+                    {
+                        pending_impl = Some(name.clone());
+                        None
+                    }
+                    // impl Drawable for Sprite - методы принадлежат Sprite
+                    (Some(Token::Identifier(_)), Some(Token::KeywordFor)) if pending_impl.is_some() => {
+                        pending_impl = Some(name.clone());
+                        None
+                    }
+                    _ => None,
+                };
+                match declared {
+                    Some(declared) => {
+                        if let Some(&kind) = kinds.get(&declared) {
+                            symbols.push(Symbol { name: declared, kind, line, column });
+                        }
+                    }
+                    // Имена новых локальных переменных - не упоминания
+                    None if pending_impl.is_none() && matches!(
+                        (&previous[0], &previous[1]),
+                        (_, Some(Token::KeywordLet | Token::KeywordFor)) | (Some(Token::KeywordLet), Some(Token::KeywordMut))
+                    ) => {}
+                    None => {
+                        let qualified = match (&previous[0], &previous[1]) {
+                            (Some(Token::Identifier(type_name)), Some(Token::ColonColon)) => method_name(type_name, name),
+                            (_, Some(Token::Dot)) => format!(".{}", name),
+                            _ => name.clone(),
+                        };
+                        occurrences.push(Occurrence { name: qualified, line, column });
+                    }
+                }
+            }
+            _ => {}
+        }
+        previous = [previous[1].take(), Some(token)];
+    }
+    (symbols, occurrences)
+}
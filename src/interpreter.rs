@@ -1,68 +1,1166 @@
 // interpreter.rs - исправленная версия
 
 use crate::ast::*;
-use std::collections::HashMap;
+use crate::stdlib;
+use crate::fp;
+use crate::color;
+use crate::animation;
+use crate::ranges;
+use crate::strings;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use rand::Rng;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
 use std::time::{SystemTime, UNIX_EPOCH};
-use crate::graphics_engine::GraphicsEngine;
-use minifb::Key;
+use crate::graphics_engine::{key_from_code, FrameStats, GraphicsEngine, WindowConfig};
+use crate::storage::Store;
+use crate::assets::AssetResolver;
+use crate::crash::{self, CrashReport, StackFrame};
+use crate::capabilities::Capabilities;
+use crate::derive;
+use crate::generics;
 
+// Составные значения разделяются через Rc: копирование переменной или
+// передача в функцию не копирует поля, копия создаётся только при записи.
+// Ref - исключение: все копии ссылки указывают на одну структуру в куче,
+// и запись через любую из них видна остальным. Память освобождается
+// подсчётом ссылок, поэтому циклы (a.next = a) не собираются.
 #[derive(Debug, Clone)]
 pub enum RuntimeValue {
     Integer(i32),
+    // i64; арифметика проверяет переполнение. Целый литерал становится i64
+    // по объявленному типу переменной, параметра или результата
+    Long(i64),
+    Float(f32),
+    Boolean(bool),
+    String(Rc<str>),
+    Struct(Rc<str>, Rc<HashMap<String, RuntimeValue>>),
+    Ref(Rc<RefCell<RuntimeValue>>),
+    Array(Rc<Vec<RuntimeValue>>),
+    Slice(Rc<Vec<RuntimeValue>>, usize, usize), // буфер массива и границы start..end
+    Enum(Rc<str>, Rc<str>, Rc<Vec<RuntimeValue>>), // перечисление, вариант и его поля
+    Function(Rc<Closure>),
+    Void,
+}
+
+/// Значение-функция: тело и окружение, захваченное при создании замыкания.
+/// У именованной функции окружение пустое, у замыкания имя пустое
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Function,
+    pub captured: HashMap<String, RuntimeValue>,
+}
+
+impl RuntimeValue {
+    /// Значение для места с объявленным типом: целый литерал в f32 - число
+    /// с плавающей точкой, в i64 - i64
+    pub fn with_type(self, ty: &Type) -> RuntimeValue {
+        match (self, ty) {
+            (RuntimeValue::Integer(i), Type::F32 | Type::F64) => RuntimeValue::Float(i as f32),
+            (RuntimeValue::Integer(i), Type::I64) => RuntimeValue::Long(i as i64),
+            (value, _) => value,
+        }
+    }
+
+    /// Новое значение переменной в представлении прежнего (i32-литерал в i64)
+    fn like(self, previous: &RuntimeValue) -> RuntimeValue {
+        match (self, previous) {
+            (RuntimeValue::Integer(i), RuntimeValue::Long(_)) => RuntimeValue::Long(i as i64),
+            (value, _) => value,
+        }
+    }
+
+    /// Запись поля структуры с копированием при записи (copy-on-write)
+    pub fn set_field(&mut self, field_name: &str, value: RuntimeValue) -> Result<(), String> {
+        self.set_path(&[field_name.to_string()], value)
+    }
+
+    /// Запись по цепочке полей `a.b.c`; через Ref запись идёт в общую структуру
+    pub fn set_path(&mut self, path: &[String], value: RuntimeValue) -> Result<(), String> {
+        let Some((field_name, rest)) = path.split_first() else {
+            *self = value;
+            return Ok(());
+        };
+        match self {
+            RuntimeValue::Struct(_, fields) => {
+                let fields = Rc::make_mut(fields);
+                match fields.get_mut(field_name) {
+                    Some(slot) => slot.set_path(rest, value),
+                    None => Err(format!("Undefined field: {}", field_name)),
+                }
+            }
+            RuntimeValue::Ref(cell) => cell.borrow_mut().set_path(path, value),
+            _ => Err("Field assignment on non-struct value".to_string()),
+        }
+    }
+
+    /// Элементы массива или видимая часть среза
+    pub fn elements(&self) -> Option<&[RuntimeValue]> {
+        match self {
+            RuntimeValue::Array(elements) => Some(elements),
+            RuntimeValue::Slice(elements, start, end) => Some(&elements[*start..*end]),
+            _ => None,
+        }
+    }
+
+    /// Чтение поля структуры (в том числе через Ref)
+    pub fn get_field(&self, field_name: &str) -> Option<RuntimeValue> {
+        match self {
+            RuntimeValue::Struct(_, fields) => fields.get(field_name).cloned(),
+            RuntimeValue::Ref(cell) => cell.borrow().get_field(field_name),
+            _ => None,
+        }
+    }
+}
+
+thread_local! {
+    // Адреса ссылок, содержимое которых сейчас обходится (печать, снимок для потока)
+    static VISITING_REFS: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+/// Обходит содержимое ссылки; None, если она уже обходится выше по цепочке
+/// (a.next = a) - иначе обход цикла не закончится
+fn visit_ref<R>(cell: &Rc<RefCell<RuntimeValue>>, visit: impl FnOnce(&RuntimeValue) -> R) -> Option<R> {
+    let ptr = Rc::as_ptr(cell) as usize;
+    if !VISITING_REFS.with(|visiting| visiting.borrow_mut().insert(ptr)) {
+        return None;
+    }
+    let result = visit(&cell.borrow());
+    VISITING_REFS.with(|visiting| visiting.borrow_mut().remove(&ptr));
+    Some(result)
+}
+
+impl std::fmt::Display for RuntimeValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RuntimeValue::Integer(i) => write!(f, "{}", i),
+            RuntimeValue::Long(i) => write!(f, "{}", i),
+            RuntimeValue::Float(v) => write!(f, "{}", v),
+            RuntimeValue::Boolean(b) => write!(f, "{}", b),
+            RuntimeValue::String(s) => write!(f, "{}", s),
+            RuntimeValue::Struct(name, fields) => {
+                let mut names: Vec<&String> = fields.keys().collect();
+                names.sort();
+                let parts: Vec<String> = names.iter()
+                    .map(|field| format!("{}: {}", field, fields[*field]))
+                    .collect();
+                write!(f, "{} {{ {} }}", name, parts.join(", "))
+            }
+            // Повторная ссылка в цикле печатается без содержимого
+            RuntimeValue::Ref(cell) => visit_ref(cell, |value| write!(f, "ref {}", value))
+                .unwrap_or_else(|| match &*cell.borrow() {
+                    RuntimeValue::Struct(name, _) => write!(f, "ref {} {{ ... }}", name),
+                    _ => write!(f, "ref ..."),
+                }),
+            RuntimeValue::Array(_) | RuntimeValue::Slice(..) => {
+                let parts: Vec<String> = self.elements().unwrap_or_default().iter()
+                    .map(|element| element.to_string())
+                    .collect();
+                write!(f, "[{}]", parts.join(", "))
+            }
+            // Варианты Option и Result печатаются без префикса, как пишутся в коде
+            RuntimeValue::Enum(name, variant, fields) => {
+                if builtin_variant_enum(variant) != Some(&**name) {
+                    write!(f, "{}::", name)?;
+                }
+                if fields.is_empty() {
+                    return write!(f, "{}", variant);
+                }
+                let parts: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+                write!(f, "{}({})", variant, parts.join(", "))
+            }
+            RuntimeValue::Function(closure) if closure.function.name.is_empty() => write!(f, "<closure>"),
+            RuntimeValue::Function(closure) => write!(f, "<fn {}>", closure.function.name),
+            RuntimeValue::Void => write!(f, "()"),
+        }
+    }
+}
+
+/// Значение, передаваемое между потоками (RuntimeValue содержит Rc и не Send)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThreadValue {
+    Integer(i32),
+    Long(i64),
     Float(f32),
     Boolean(bool),
     String(String),
-    Struct(String, HashMap<String, RuntimeValue>),
+    Struct(String, Vec<(String, ThreadValue)>),
+    Array(Vec<ThreadValue>),
+    Enum(String, String, Vec<ThreadValue>),
     Void,
 }
 
+impl From<&RuntimeValue> for ThreadValue {
+    fn from(value: &RuntimeValue) -> Self {
+        match value {
+            RuntimeValue::Integer(i) => ThreadValue::Integer(*i),
+            RuntimeValue::Long(i) => ThreadValue::Long(*i),
+            RuntimeValue::Float(f) => ThreadValue::Float(*f),
+            RuntimeValue::Boolean(b) => ThreadValue::Boolean(*b),
+            RuntimeValue::String(s) => ThreadValue::String(s.to_string()),
+            RuntimeValue::Struct(name, fields) => ThreadValue::Struct(
+                name.to_string(),
+                fields.iter()
+                    .map(|(field, value)| (field.clone(), ThreadValue::from(value)))
+                    .collect(),
+            ),
+            // В другой поток уходит снимок структуры, а не общая ссылка;
+            // цикл в снимке обрывается
+            RuntimeValue::Ref(cell) => visit_ref(cell, |value| ThreadValue::from(value)).unwrap_or(ThreadValue::Void),
+            // Срез копируется: буфер массива общий только внутри потока
+            RuntimeValue::Array(_) | RuntimeValue::Slice(..) => ThreadValue::Array(
+                value.elements().unwrap_or_default().iter().map(ThreadValue::from).collect(),
+            ),
+            RuntimeValue::Enum(name, variant, fields) => ThreadValue::Enum(
+                name.to_string(),
+                variant.to_string(),
+                fields.iter().map(ThreadValue::from).collect(),
+            ),
+            // Замыкание держит Rc-окружение и в другой поток не передаётся
+            RuntimeValue::Function(_) | RuntimeValue::Void => ThreadValue::Void,
+        }
+    }
+}
+
+impl From<ThreadValue> for RuntimeValue {
+    fn from(value: ThreadValue) -> Self {
+        match value {
+            ThreadValue::Integer(i) => RuntimeValue::Integer(i),
+            ThreadValue::Long(i) => RuntimeValue::Long(i),
+            ThreadValue::Float(f) => RuntimeValue::Float(f),
+            ThreadValue::Boolean(b) => RuntimeValue::Boolean(b),
+            ThreadValue::String(s) => RuntimeValue::String(Rc::from(s)),
+            ThreadValue::Struct(name, fields) => RuntimeValue::Struct(
+                Rc::from(name),
+                Rc::new(fields.into_iter()
+                    .map(|(field, value)| (field, RuntimeValue::from(value)))
+                    .collect()),
+            ),
+            ThreadValue::Array(elements) => RuntimeValue::Array(Rc::new(
+                elements.into_iter().map(RuntimeValue::from).collect(),
+            )),
+            ThreadValue::Enum(name, variant, fields) => RuntimeValue::Enum(
+                Rc::from(name),
+                Rc::from(variant),
+                Rc::new(fields.into_iter().map(RuntimeValue::from).collect()),
+            ),
+            ThreadValue::Void => RuntimeValue::Void,
+        }
+    }
+}
+
+/// Каналы для обмена сообщениями между потоками; канал создаётся
+/// при первом обращении по его номеру. Отправить в канал может любой
+/// работающий поток, поэтому чтение из пустого канала - ошибка, когда все
+/// остальные потоки завершились или сами ждут (в channel_recv или join)
+#[derive(Default)]
+pub struct ChannelHub {
+    state: Mutex<HubState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct HubState {
+    queues: HashMap<i32, VecDeque<ThreadValue>>,
+    // Работающие потоки из spawn, главный поток не считается
+    spawned: usize,
+    // Потоки, заблокированные в channel_recv или join
+    waiting: usize,
+}
+
+impl HubState {
+    /// Все потоки, кроме текущего, ждут - сообщение не придёт
+    fn nobody_can_send(&self) -> bool {
+        self.waiting >= self.spawned
+    }
+}
+
+impl ChannelHub {
+    pub fn send(&self, channel: i32, value: ThreadValue) {
+        let mut state = self.state.lock().unwrap();
+        state.queues.entry(channel).or_default().push_back(value);
+        self.ready.notify_all();
+    }
+
+    /// Блокирует поток до появления сообщения в канале
+    pub fn recv(&self, channel: i32) -> Result<ThreadValue, String> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(value) = state.queues.get_mut(&channel).and_then(VecDeque::pop_front) {
+                return Ok(value);
+            }
+            if state.nobody_can_send() {
+                return Err(format!("channel_recv: channel {} is empty and no running thread can send to it", channel));
+            }
+            state.waiting += 1;
+            state = self.ready.wait(state).unwrap();
+            state.waiting -= 1;
+        }
+    }
+
+    fn thread_started(&self) {
+        self.state.lock().unwrap().spawned += 1;
+    }
+
+    fn thread_finished(&self) {
+        self.state.lock().unwrap().spawned -= 1;
+        self.ready.notify_all();
+    }
+
+    /// Поток ждёт завершения другого в join; ожидающие в channel_recv
+    /// перепроверяют, может ли им ещё кто-то отправить
+    fn join_started(&self) {
+        self.state.lock().unwrap().waiting += 1;
+        self.ready.notify_all();
+    }
+
+    fn join_finished(&self) {
+        self.state.lock().unwrap().waiting -= 1;
+    }
+
+    /// Число сообщений, ещё не прочитанных ни из одного канала
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().queues.values().map(VecDeque::len).sum()
+    }
+}
+
+/// Снимок использования памяти интерпретатором (`mem_stats()`, `--mem-report`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemStats {
+    /// Значения, достижимые из переменных (включая поля структур)
+    pub live_values: usize,
+    /// Различные экземпляры структур среди живых значений
+    pub live_structs: usize,
+    pub interned_strings: usize,
+    /// Созданные структуры и строки с момента запуска
+    pub allocations: usize,
+    pub threads: usize,
+    pub coroutines: usize,
+    pub queued_messages: usize,
+}
+
+impl std::fmt::Display for MemStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "live_values={} live_structs={} interned_strings={} allocations={} threads={} coroutines={} queued_messages={}",
+            self.live_values,
+            self.live_structs,
+            self.interned_strings,
+            self.allocations,
+            self.threads,
+            self.coroutines,
+            self.queued_messages,
+        )
+    }
+}
+
+type ThreadResult = Result<ThreadValue, String>;
+
+/// Окно закрыто или вызван exit: выполнение сворачивается из любой глубины вложенных циклов
+/// и вызовов до interpret_program, где это считается нормальным завершением
+#[derive(Debug)]
+struct ExitRequested;
+
+impl std::fmt::Display for ExitRequested {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "window closed")
+    }
+}
+
+impl std::error::Error for ExitRequested {}
+
+/// `?` на None или Err: значение поднимается до interpret_function и
+/// возвращается из текущей функции
+#[derive(Debug)]
+struct EarlyReturn(RuntimeValue);
+
+impl std::fmt::Display for EarlyReturn {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "`?` returned {} outside of a function", self.0)
+    }
+}
+
+impl std::error::Error for EarlyReturn {}
+
+// Графика и хранилище доступны только из главного потока
+const MAIN_THREAD_ONLY: &[&str] = &[
+    "init_graphics", "clear_screen", "draw_pixel", "draw_rect",
+    "draw_circle", "draw_line", "draw_text", "render", "is_key_pressed",
+    "key_just_pressed", "key_just_released", "is_paused",
+    "get_typed_text", "store_set", "store_get", "window_should_close", "on_exit", "exit",
+];
+
+/// Кадр сопрограммы: позиция в списке операторов. Для циклов хранится
+/// условие; `scopes` - сколько областей видимости кадр открыл, не считая
+/// области тела текущей итерации цикла
+struct CoroutineFrame {
+    statements: Vec<Statement>,
+    pc: usize,
+    looping: Option<CoroutineLoop>,
+    scopes: usize,
+}
+
+/// Цикл в кадре сопрограммы: после тела выполняется шаг (`i = i + 1` у for),
+/// затем проверяется условие
+#[derive(Clone)]
+struct CoroutineLoop {
+    condition: Expression,
+    step: Option<Statement>,
+    label: Option<String>,
+}
+
+/// break или continue, ещё не дошедший до своего цикла
+enum LoopExit {
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+/// Сопрограмма, приостановленная на yield_frame()
+struct Coroutine {
+    frames: Vec<CoroutineFrame>,
+    variables: HashMap<String, RuntimeValue>,
+    scopes: Vec<Scope>,
+}
+
+/// Область видимости блока: объявленные в нём имена и значения внешних
+/// переменных, которые они перекрыли (None - имя было свободно)
+type Scope = Vec<(String, Option<RuntimeValue>)>;
+
 // В interpreter.rs добавьте поле start_time
 pub struct Interpreter {
     variables: HashMap<String, RuntimeValue>,
+    // Открытые блоки текущей функции, внутренний - последний
+    scopes: Vec<Scope>,
     functions: HashMap<String, Function>,
+    // Значения констант верхнего уровня и их объявления (для потоков)
+    constants: HashMap<String, RuntimeValue>,
+    const_decls: Vec<Const>,
+    // Значения полей по умолчанию по структурам и их объявления (для потоков)
+    struct_defaults: HashMap<String, Vec<(String, RuntimeValue)>>,
+    struct_decls: Vec<Struct>,
+    // Статические переменные: объявленный тип и текущее значение
+    statics: HashMap<String, (Type, RuntimeValue)>,
+    // Выполнен return: охватывающие операторы прерываются, а значение
+    // поднимается до interpret_function
+    returning: bool,
+    // Выполнен break/continue: операторы до его цикла прерываются
+    loop_exit: Option<LoopExit>,
+    // Выражения defer ещё не закрытых блоков; блок запоминает длину стека
+    // при входе и при выходе выполняет всё, что записано после неё
+    deferred: Vec<Expression>,
     graphics_engine: Option<GraphicsEngine>,
     pub should_exit: bool,
     start_time: std::time::Instant, // Добавьте это поле
+    // Интернированные имена структур и строковые литералы
+    interned: HashMap<String, Rc<str>>,
+    // Потоки, запущенные через spawn, и общие каналы
+    threads: HashMap<i32, JoinHandle<ThreadResult>>,
+    next_thread_id: i32,
+    channels: Arc<ChannelHub>,
+    is_main_thread: bool,
+    // Сопрограммы, запущенные через start_coroutine
+    coroutines: HashMap<i32, Coroutine>,
+    next_coroutine_id: i32,
+    // Перехваченный вывод print (для доктестов), None - печать в stdout
+    captured_output: Option<Vec<String>>,
+    // Детерминированная семантика f32, см. fp.rs
+    strict_fp: bool,
+    // Счётчик выделений структур и строк для mem_stats()
+    allocations: usize,
+    // Имя программы и её хранилище store_set/store_get (открывается при первом обращении)
+    program_name: String,
+    store: Option<Store>,
+    // Передаётся графическому движку при создании окна
+    contrast_warnings: bool,
+    // Окно по умолчанию; размер и заголовок переопределяет init_graphics
+    window_config: WindowConfig,
+    // Заголовок из `--title`; без него окно называется по программе
+    window_title: Option<String>,
+    // Функция, зарегистрированная через on_exit
+    exit_hook: Option<String>,
+    // Код из exit(код)
+    exit_code: Option<i32>,
+    // Для отчёта о падении: текущие вызовы, стек и оператор в момент первой
+    // ошибки (пока она всплывает, вызовы уже сняты) и последние строки вывода
+    call_stack: Vec<StackFrame>,
+    crash_site: Option<(Vec<StackFrame>, Option<Position>)>,
+    recent_output: VecDeque<String>,
+    // Каталог, от которого отсчитываются пути к ресурсам
+    assets: AssetResolver,
+    // Что программе разрешено за пределами вычислений, см. capabilities.rs
+    capabilities: Capabilities,
+    // Вычисление при компиляции (const_eval.rs): не больше max_steps
+    // операторов и итераций и max_depth вложенных вызовов
+    limits: Option<EvalLimits>,
+    steps: usize,
+    // steps и allocations на момент прошлого render - для debug_hud
+    frame_start: (usize, usize),
+}
+
+/// Ограничения, после которых вычисление прерывается ошибкой
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalLimits {
+    pub max_steps: usize,
+    pub max_depth: usize,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_capabilities(Capabilities::all())
+    }
+
+    /// Интерпретатор с ограниченными возможностями (`--sandbox`, встраивание)
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
         Self {
             variables: HashMap::new(),
+            scopes: Vec::new(),
             functions: HashMap::new(),
+            constants: HashMap::new(),
+            const_decls: Vec::new(),
+            struct_defaults: HashMap::new(),
+            struct_decls: Vec::new(),
+            statics: HashMap::new(),
+            returning: false,
+            loop_exit: None,
+            deferred: Vec::new(),
             graphics_engine: None,
             should_exit: false,
             start_time: std::time::Instant::now(), // Инициализируйте здесь
+            interned: HashMap::new(),
+            threads: HashMap::new(),
+            next_thread_id: 1,
+            channels: Arc::new(ChannelHub::default()),
+            is_main_thread: true,
+            coroutines: HashMap::new(),
+            next_coroutine_id: 1,
+            captured_output: None,
+            strict_fp: false,
+            allocations: 0,
+            program_name: "aetos".to_string(),
+            store: None,
+            contrast_warnings: false,
+            window_config: WindowConfig::default(),
+            window_title: None,
+            exit_hook: None,
+            exit_code: None,
+            call_stack: Vec::new(),
+            crash_site: None,
+            recent_output: VecDeque::new(),
+            assets: AssetResolver::default(),
+            capabilities,
+            limits: None,
+            steps: 0,
+            frame_start: (0, 0),
         }
     }
 
-    pub fn interpret_program(&mut self, program: &Program, width: usize, height: usize, title: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Сначала собираем все пользовательские функции
-        for function in &program.functions {
-            self.functions.insert(function.name.clone(), function.clone());
+    /// Текущая статистика памяти
+    pub fn mem_stats(&self) -> MemStats {
+        let mut stats = MemStats {
+            interned_strings: self.interned.len(),
+            allocations: self.allocations,
+            threads: self.threads.len(),
+            coroutines: self.coroutines.len(),
+            queued_messages: self.channels.pending(),
+            ..MemStats::default()
+        };
+        
+        let mut seen = HashSet::new();
+        let scopes = std::iter::once(&self.variables)
+            .chain(self.coroutines.values().map(|coroutine| &coroutine.variables));
+        for value in scopes.flat_map(HashMap::values) {
+            Self::count_live(value, &mut stats, &mut seen);
+        }
+        stats
+    }
+
+    fn count_live(value: &RuntimeValue, stats: &mut MemStats, seen: &mut HashSet<usize>) {
+        stats.live_values += 1;
+        match value {
+            RuntimeValue::Struct(_, fields) => {
+                // Общие (ещё не скопированные) поля считаем один раз
+                if seen.insert(Rc::as_ptr(fields) as usize) {
+                    stats.live_structs += 1;
+                    for field in fields.values() {
+                        Self::count_live(field, stats, seen);
+                    }
+                }
+            }
+            RuntimeValue::Ref(cell) => {
+                if seen.insert(Rc::as_ptr(cell) as usize) {
+                    Self::count_live(&cell.borrow(), stats, seen);
+                }
+            }
+            RuntimeValue::Array(elements) | RuntimeValue::Slice(elements, ..) | RuntimeValue::Enum(_, _, elements) => {
+                if seen.insert(Rc::as_ptr(elements) as usize) {
+                    for element in elements.iter() {
+                        Self::count_live(element, stats, seen);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Ограничивает вычисление: бесконечный цикл или рекурсия в const fn
+    /// не должны повесить компилятор. Счётчик шагов начинается заново
+    pub fn set_limits(&mut self, limits: EvalLimits) {
+        self.limits = Some(limits);
+        self.steps = 0;
+    }
+
+    fn count_step(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.steps += 1;
+        if let Some(limits) = self.limits {
+            if self.steps > limits.max_steps {
+                return Err(format!("evaluation exceeded {} steps", limits.max_steps).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Пути встроенных функций к файлам отсчитываются от каталога ресурсов
+    pub fn set_asset_resolver(&mut self, assets: AssetResolver) {
+        self.assets = assets;
+    }
+
+    /// Задаёт хранилище store_set/store_get вместо файла в каталоге данных
+    pub fn set_store(&mut self, store: Store) {
+        self.store = Some(store);
+    }
+
+    fn store(&mut self) -> Result<&mut Store, Box<dyn std::error::Error>> {
+        if self.store.is_none() {
+            let store = Store::for_program(&self.program_name)
+                .ok_or("persistent storage is unavailable: no user data directory")?;
+            self.store = Some(store);
+        }
+        Ok(self.store.as_mut().unwrap())
+    }
+
+    /// Предупреждения о низком контрасте draw_text (`--contrast-warnings`)
+    pub fn set_contrast_warnings(&mut self, enabled: bool) {
+        self.contrast_warnings = enabled;
+    }
+
+    /// Ограничение частоты кадров окна (`--vsync`)
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.window_config.vsync = vsync;
+    }
+
+    /// Заголовок окна (`--title`); имя программы для store_set остаётся прежним
+    pub fn set_window_title(&mut self, title: &str) {
+        self.window_title = Some(title.to_string());
+    }
+
+    /// Включает строгий режим плавающей точки (`--strict-fp`)
+    pub fn set_strict_fp(&mut self, strict_fp: bool) {
+        self.strict_fp = strict_fp;
+    }
+
+    /// Перенаправляет вывод print-функций в буфер вместо stdout
+    pub fn capture_output(&mut self) {
+        self.captured_output = Some(Vec::new());
+    }
+
+    /// Забирает перехваченные строки вывода
+    pub fn take_output(&mut self) -> Vec<String> {
+        self.captured_output.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn write_line(&mut self, line: String) {
+        self.remember_output(&line);
+        match &mut self.captured_output {
+            // Строка с `\n` в буфере - несколько строк, как и в терминале
+            Some(output) if line.contains('\n') => output.extend(line.split('\n').map(str::to_string)),
+            Some(output) => output.push(line),
+            None => println!("{}", line),
+        }
+    }
+
+    fn remember_output(&mut self, line: &str) {
+        if self.recent_output.len() == crash::RECENT_OUTPUT_LINES {
+            self.recent_output.pop_front();
+        }
+        self.recent_output.push_back(line.to_string());
+    }
+
+    /// Отчёт о падении с состоянием интерпретатора; после паники стек
+    /// вызовов остаётся таким, каким был в момент сбоя
+    pub fn crash_report(&self, error: &str, source_path: &str, source: &str) -> CrashReport {
+        let mut report = CrashReport::new(error, source_path, source);
+        match &self.crash_site {
+            Some((stack, statement)) => {
+                report.call_stack = stack.clone();
+                if let Some(position) = statement {
+                    report.set_statement(*position, source);
+                }
+            }
+            None => report.call_stack = self.call_stack.clone(),
         }
+        report.recent_output = self.recent_output.iter().cloned().collect();
+        report
+    }
+
+    /// Ошибка, которая всплывает через вызовы и операторы, а не управление
+    /// потоком (return, exit)
+    fn is_failure(error: &(dyn std::error::Error + 'static)) -> bool {
+        !error.is::<EarlyReturn>() && !error.is::<ExitRequested>()
+    }
+
+    fn start_coroutine(&mut self, name: &str) -> Result<i32, Box<dyn std::error::Error>> {
+        let function = self.functions.get(name)
+            .ok_or_else(|| format!("start_coroutine: undefined function: {}", name))?;
+        if !function.params.is_empty() {
+            return Err(format!("start_coroutine: function {} must not take parameters", name).into());
+        }
+
+        let coroutine = Coroutine {
+            frames: vec![CoroutineFrame {
+                statements: function.body.clone(),
+                pc: 0,
+                looping: None,
+                scopes: 0,
+            }],
+            variables: HashMap::new(),
+            scopes: Vec::new(),
+        };
+
+        let id = self.next_coroutine_id;
+        self.next_coroutine_id += 1;
+        self.coroutines.insert(id, coroutine);
+        Ok(id)
+    }
+
+    /// Выполняет сопрограмму до следующего yield_frame() или завершения.
+    /// Возвращает true, если сопрограмма ещё не завершилась.
+    fn resume_coroutine(&mut self, id: i32) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut coroutine = match self.coroutines.remove(&id) {
+            Some(coroutine) => coroutine,
+            None => return Ok(false),
+        };
+
+        std::mem::swap(&mut self.variables, &mut coroutine.variables);
+        std::mem::swap(&mut self.scopes, &mut coroutine.scopes);
+        let result = self.run_coroutine_frames(&mut coroutine.frames);
+        std::mem::swap(&mut self.variables, &mut coroutine.variables);
+        std::mem::swap(&mut self.scopes, &mut coroutine.scopes);
+
+        let alive = result?;
+        if alive {
+            self.coroutines.insert(id, coroutine);
+        }
+        Ok(alive)
+    }
+
+    fn run_coroutine_frames(&mut self, frames: &mut Vec<CoroutineFrame>) -> Result<bool, Box<dyn std::error::Error>> {
+        loop {
+            let frame = match frames.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(false),
+            };
+
+            if frame.pc >= frame.statements.len() {
+                if let Some(looping) = frame.looping.take() {
+                    // Шаг for меняет счётчик, а не одноимённую переменную тела
+                    self.exit_scope();
+                    if let Some(step) = &looping.step {
+                        self.interpret_statement(step)?;
+                    }
+                    let condition_result = self.interpret_expression(&looping.condition)?;
+                    if self.is_truthy(&condition_result) {
+                        self.enter_scope();
+                        let frame = frames.last_mut().unwrap();
+                        frame.pc = 0;
+                        frame.looping = Some(looping);
+                        continue;
+                    }
+                }
+                let frame = frames.pop().unwrap();
+                self.close_frame(&frame);
+                continue;
+            }
+
+            let statement = frame.statements[frame.pc].clone();
+            frame.pc += 1;
+
+            match statement {
+                Statement::Expression(Expression::FunctionCall { name, .. }, _) if name == "yield_frame" => {
+                    return Ok(true);
+                }
+                Statement::While { condition, body, label, .. } => {
+                    let condition_result = self.interpret_expression(&condition)?;
+                    if self.is_truthy(&condition_result) {
+                        self.enter_scope();
+                        frames.push(CoroutineFrame {
+                            statements: body,
+                            pc: 0,
+                            looping: Some(CoroutineLoop { condition, step: None, label }),
+                            scopes: 0,
+                        });
+                    }
+                }
+                Statement::Loop { body, label, .. } => {
+                    self.enter_scope();
+                    frames.push(CoroutineFrame {
+                        statements: body,
+                        pc: 0,
+                        looping: Some(CoroutineLoop { condition: Expression::BoolLiteral(true), step: None, label }),
+                        scopes: 0,
+                    });
+                }
+                Statement::For { variable, start, end, body, label, position } => {
+                    // В сопрограмме цикл выполняется как while, чтобы
+                    // yield_frame() в теле мог приостановить его. Счётчик
+                    // объявлен в своей области, тело - во вложенной
+                    self.enter_scope();
+                    let (condition, step) = self.start_coroutine_for(variable, &start, &end, position)?;
+                    let condition_result = self.interpret_expression(&condition)?;
+                    if self.is_truthy(&condition_result) {
+                        self.enter_scope();
+                        frames.push(CoroutineFrame {
+                            statements: body,
+                            pc: 0,
+                            looping: Some(CoroutineLoop { condition, step: Some(step), label }),
+                            scopes: 1,
+                        });
+                    } else {
+                        self.exit_scope();
+                    }
+                }
+                Statement::Break { ref label, .. } | Statement::Continue { ref label, .. } => {
+                    let is_break = matches!(statement, Statement::Break { .. });
+                    let label = label.clone();
+                    // Кадры внутри цикла закрываются вместе со своими областями видимости
+                    while let Some(frame) = frames.last() {
+                        let is_target = frame.looping.as_ref()
+                            .is_some_and(|looping| label.is_none() || looping.label == label);
+                        if is_target {
+                            break;
+                        }
+                        let frame = frames.pop().unwrap();
+                        self.close_frame(&frame);
+                    }
+                    let frame = frames.last_mut().ok_or("break or continue outside of a loop")?;
+                    frame.pc = frame.statements.len();
+                    if is_break {
+                        // Область тела итерации закрывается сразу, остальные - с кадром
+                        frame.looping = None;
+                        self.exit_scope();
+                    }
+                }
+                Statement::If { condition, then_branch, else_branch, .. } => {
+                    let condition_result = self.interpret_expression(&condition)?;
+                    let branch = if self.is_truthy(&condition_result) {
+                        Some(then_branch)
+                    } else {
+                        else_branch
+                    };
+                    if let Some(statements) = branch {
+                        self.enter_scope();
+                        frames.push(CoroutineFrame {
+                            statements,
+                            pc: 0,
+                            looping: None,
+                            scopes: 1,
+                        });
+                    }
+                }
+                Statement::Block { statements, .. } => {
+                    self.enter_scope();
+                    frames.push(CoroutineFrame {
+                        statements,
+                        pc: 0,
+                        looping: None,
+                        scopes: 1,
+                    });
+                }
+                Statement::Return { value, .. } => {
+                    self.interpret_expression(&value)?;
+                    frames.clear();
+                    return Ok(false);
+                }
+                // Кадр сопрограммы может закрыться и через кадр позже, после
+                // yield_frame(); выполнять отложенное в этот момент некому
+                Statement::Defer { .. } => {
+                    return Err("defer is not supported in coroutines".into());
+                }
+                other => {
+                    self.interpret_statement(&other)?;
+                }
+            }
+        }
+    }
+
+    /// `for i in a..b` как `let mut i = a; while (i < b) { body; i = i + 1; }`:
+    /// объявляет счётчик и возвращает условие и шаг цикла.
+    /// Граница вычисляется один раз и хранится под именем, недоступным из кода.
+    fn start_coroutine_for(&mut self, variable: String, start: &Expression, end: &Expression, position: Position) -> Result<(Expression, Statement), Box<dyn std::error::Error>> {
+        let end_name = format!("{}..end", variable);
+        let start = self.interpret_expression(start)?;
+        let end = self.interpret_expression(end)?;
+        self.declare(&variable, start);
+        self.declare(&end_name, end);
+        let condition = Expression::BinaryExpression {
+            left: Box::new(Expression::Variable(variable.clone())),
+            operator: BinaryOperator::Lt,
+            right: Box::new(Expression::Variable(end_name)),
+        };
+        let step = Statement::Assignment {
+            name: variable.clone(),
+            value: Expression::BinaryExpression {
+                left: Box::new(Expression::Variable(variable)),
+                operator: BinaryOperator::Add,
+                right: Box::new(Expression::IntegerLiteral(1)),
+            },
+            position,
+        };
+        Ok((condition, step))
+    }
+
+    /// Запускает пользовательскую функцию без аргументов в отдельном
+    /// интерпретаторе на новом потоке
+    fn spawn_function(&mut self, name: &str) -> Result<i32, Box<dyn std::error::Error>> {
+        let function = self.functions.get(name)
+            .cloned()
+            .ok_or_else(|| format!("spawn: undefined function: {}", name))?;
+        if !function.params.is_empty() {
+            return Err(format!("spawn: function {} must not take parameters", name).into());
+        }
+
+        let functions = self.functions.clone();
+        let const_decls = self.const_decls.clone();
+        let struct_decls = self.struct_decls.clone();
+        // Поток получает копию текущих значений статических переменных;
+        // его изменения не видны другим потокам
+        let statics: Vec<(String, Type, ThreadValue)> = self.statics.iter()
+            .map(|(name, (static_type, value))| (name.clone(), static_type.clone(), ThreadValue::from(value)))
+            .collect();
+        let channels = self.channels.clone();
+        let strict_fp = self.strict_fp;
+        let capabilities = self.capabilities;
+        self.channels.thread_started();
+        let handle = std::thread::spawn(move || -> ThreadResult {
+            let mut interpreter = Interpreter::with_capabilities(capabilities);
+            interpreter.functions = functions;
+            interpreter.channels = channels.clone();
+            interpreter.is_main_thread = false;
+            interpreter.strict_fp = strict_fp;
+            // Значения с Rc нельзя передать в поток - константы вычисляются заново
+            let result = interpreter.define_constants(&const_decls)
+                .and_then(|()| interpreter.define_struct_defaults(&struct_decls))
+                .and_then(|()| {
+                    interpreter.statics = statics.into_iter()
+                        .map(|(name, static_type, value)| (name, (static_type, RuntimeValue::from(value))))
+                        .collect();
+                    interpreter.interpret_function(&function, &[])
+                })
+                .map(|value| ThreadValue::from(&value))
+                .map_err(|e| e.to_string());
+            channels.thread_finished();
+            result
+        });
+
+        let id = self.next_thread_id;
+        self.next_thread_id += 1;
+        self.threads.insert(id, handle);
+        Ok(id)
+    }
+
+    fn join_thread(&mut self, id: i32) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        let handle = self.threads.remove(&id)
+            .ok_or_else(|| format!("join: unknown or already joined thread handle {}", id))?;
+        self.channels.join_started();
+        let result = handle.join();
+        self.channels.join_finished();
+        match result {
+            Ok(Ok(value)) => Ok(RuntimeValue::from(value)),
+            Ok(Err(message)) => Err(format!("thread {} failed: {}", id, message).into()),
+            Err(_) => Err(format!("thread {} panicked", id).into()),
+        }
+    }
+
+    fn intern(&mut self, value: &str) -> Rc<str> {
+        if let Some(interned) = self.interned.get(value) {
+            return interned.clone();
+        }
+        let interned: Rc<str> = Rc::from(value);
+        self.interned.insert(value.to_string(), interned.clone());
+        self.allocations += 1;
+        interned
+    }
+
+    pub fn interpret_program(&mut self, program: &Program, width: usize, height: usize, title: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.program_name = title.to_string();
+        
+        // Сначала собираем все пользовательские функции и константы
+        self.load_program(program)?;
+        self.crash_site = None;
 
         // Ищем функцию main
         let main_function = self.functions.get("main")
-            .ok_or("No main function found")?;
+            .ok_or("No main function found")?
+            .clone();
 
-        // Инициализируем графику если есть графические функции
-        if self.has_graphics_functions(program) {
-            self.graphics_engine = Some(GraphicsEngine::new(width, height, title)?);
+        // Инициализируем графику если есть графические функции; программа с
+        // init_graphics открывает окно сама, иначе оно мелькнуло бы в размере по умолчанию
+        self.window_config.width = width;
+        self.window_config.height = height;
+        self.window_config.title = self.window_title.clone().unwrap_or_else(|| title.to_string());
+        // Без возможности graphics окно не открывается, а рисование - ошибка
+        if self.capabilities.graphics && self.has_graphics_functions(program) && !self.calls_init_graphics(program) {
+            self.open_window()?;
         }
 
         // Выполняем main функцию - клонируем функцию чтобы избежать проблем с заимствованиями
-        let main_function_clone = main_function.clone();
-        self.interpret_function(&main_function_clone, &[])?;
+        match self.interpret_function(&main_function, &[]) {
+            Ok(_) => {}
+            Err(e) if e.is::<ExitRequested>() => {}
+            Err(e) => return Err(e),
+        }
+
+        let result = self.run_exit_hook();
+        // После exit окно закрывается сразу, процесс завершает вызывающий
+        if self.exit_code.is_some() {
+            self.graphics_engine = None;
+        }
+        result
+    }
+
+    /// Код, переданный в exit, если программа завершилась через него
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Заменяет функции интерпретатора функциями программы, не запуская main
+    /// (встраивание и горячая замена, см. embed.rs)
+    /// При ошибке в константе ничего не меняется
+    pub fn load_program(&mut self, program: &Program) -> Result<(), Box<dyn std::error::Error>> {
+        // Функции загружаются первыми: константы могут вызывать const fn.
+        // Копии обобщённых функций и методы из @derive; ошибки в них уже
+        // сообщил тайпчекер
+        let program = &*generics::monomorphize(program)?;
+        let (derived, _) = derive::derived_functions(program);
+        let functions = program.functions.iter()
+            .chain(&derived)
+            .map(|function| (function.name.clone(), function.clone()))
+            .collect();
+        let previous = std::mem::replace(&mut self.functions, functions);
+        let defined = self.define_constants(&program.consts)
+            .and_then(|()| self.define_struct_defaults(&program.structs))
+            .and_then(|()| self.define_statics(&program.statics));
+        if defined.is_err() {
+            self.functions = previous;
+        }
+        defined
+    }
+
+    /// Вычисляет константы по порядку: каждая видит объявленные выше
+    fn define_constants(&mut self, consts: &[Const]) -> Result<(), Box<dyn std::error::Error>> {
+        let previous = std::mem::take(&mut self.constants);
+        for constant in consts {
+            let value = match self.interpret_expression(&constant.value) {
+                Ok(value) => value,
+                Err(e) => {
+                    self.constants = previous;
+                    return Err(e);
+                }
+            };
+            // i32-литерал в константе f32 хранится как число с плавающей точкой
+            let value = value.with_type(&constant.const_type);
+            self.constants.insert(constant.name.clone(), value);
+        }
+        self.const_decls = consts.to_vec();
+        Ok(())
+    }
+
+    /// Вычисляет значения полей по умолчанию; они состоят из литералов и
+    /// констант, поэтому одно значение подходит для всех литералов структуры
+    fn define_struct_defaults(&mut self, structs: &[Struct]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut defaults = HashMap::new();
+        for struct_def in structs {
+            let mut values = Vec::new();
+            for field in &struct_def.fields {
+                let Some(default) = &field.default else {
+                    continue;
+                };
+                let value = self.interpret_expression(default)?.with_type(&field.field_type);
+                values.push((field.name.clone(), value));
+            }
+            if !values.is_empty() {
+                defaults.insert(struct_def.name.clone(), values);
+            }
+        }
+        self.struct_defaults = defaults;
+        self.struct_decls = structs.to_vec();
+        Ok(())
+    }
 
+    /// Присваивает начальные значения статическим переменным до запуска main.
+    /// При горячей замене (embed.rs) переменная с тем же именем и типом
+    /// сохраняет текущее значение
+    fn define_statics(&mut self, statics: &[Static]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut values = HashMap::new();
+        for static_def in statics {
+            let value = match self.statics.get(&static_def.name) {
+                Some((static_type, value)) if *static_type == static_def.static_type => value.clone(),
+                _ => self.interpret_expression(&static_def.value)?.with_type(&static_def.static_type),
+            };
+            values.insert(static_def.name.clone(), (static_def.static_type.clone(), value));
+        }
+        self.statics = values;
+        Ok(())
+    }
+
+    /// Вызывает функцию загруженной программы по имени
+    pub fn call_function(&mut self, name: &str, args: &[RuntimeValue]) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        let function = self.functions.get(name)
+            .cloned()
+            .ok_or_else(|| format!("Undefined function: {}", name))?;
+        self.interpret_function(&function, args)
+    }
+
+    /// Вызывает функцию из on_exit до того, как окно и хранилище будут закрыты
+    fn run_exit_hook(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(name) = self.exit_hook.take() else {
+            return Ok(());
+        };
+        let function = self.functions.get(&name)
+            .cloned()
+            .ok_or_else(|| format!("on_exit: undefined function: {}", name))?;
+        match self.interpret_function(&function, &[]) {
+            // Цикл кадра в обработчике при закрытом окне просто завершает его
+            Err(e) if !e.is::<ExitRequested>() => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn open_window(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match &mut self.graphics_engine {
+            Some(engine) => engine.reconfigure(&self.window_config)?,
+            None => {
+                let mut engine = GraphicsEngine::new(&self.window_config)?;
+                engine.contrast_warnings = self.contrast_warnings;
+                self.graphics_engine = Some(engine);
+            }
+        }
         Ok(())
     }
 
+    fn calls_init_graphics(&self, program: &Program) -> bool {
+        program.functions.iter()
+            .any(|function| self.contains_graphics_calls(&function.body, &["init_graphics"]))
+    }
+
     fn has_graphics_functions(&self, program: &Program) -> bool {
         // Проверяем, используются ли графические функции
         let graphics_functions = [
             "init_graphics", "clear_screen", "draw_pixel", "draw_rect", 
-            "draw_circle", "draw_line", "render", "get_time", "sleep"
+            "draw_circle", "draw_line", "draw_text", "render", "get_time", "sleep"
         ];
 
         for function in &program.functions {
@@ -76,12 +1174,12 @@ impl Interpreter {
     fn contains_graphics_calls(&self, statements: &[Statement], graphics_functions: &[&str]) -> bool {
         for statement in statements {
             match statement {
-                Statement::Expression(expr) => {
+                Statement::Expression(expr, _) => {
                     if self.expression_contains_graphics(expr, graphics_functions) {
                         return true;
                     }
                 }
-                Statement::Block { statements } => {
+                Statement::Block { statements, .. } => {
                     if self.contains_graphics_calls(statements, graphics_functions) {
                         return true;
                     }
@@ -96,7 +1194,7 @@ impl Interpreter {
                         }
                     }
                 }
-                Statement::While { body, .. } => {
+                Statement::While { body, .. } | Statement::Loop { body, .. } | Statement::For { body, .. } => {
                     if self.contains_graphics_calls(body, graphics_functions) {
                         return true;
                     }
@@ -116,86 +1214,305 @@ impl Interpreter {
                 self.expression_contains_graphics(left, graphics_functions) ||
                 self.expression_contains_graphics(right, graphics_functions)
             }
+            Expression::Match { subject, arms } => {
+                self.expression_contains_graphics(subject, graphics_functions) ||
+                arms.iter().any(|arm| self.expression_contains_graphics(&arm.body, graphics_functions))
+            }
+            Expression::If { condition, then_branch, else_branch } => {
+                [condition, then_branch, else_branch].into_iter()
+                    .any(|expression| self.expression_contains_graphics(expression, graphics_functions))
+            }
+            Expression::MethodCall { receiver, args, .. } => {
+                self.expression_contains_graphics(receiver, graphics_functions) ||
+                args.iter().any(|arg| self.expression_contains_graphics(arg, graphics_functions))
+            }
             _ => false
         }
     }
 
+    /// Обновляет окно после итерации цикла; закрытое окно завершает программу
+    fn check_exit(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        // Итерация пустого цикла - тоже шаг
+        self.count_step()?;
+        if let Some(engine) = &mut self.graphics_engine {
+            if !engine.update() {
+                self.should_exit = true;
+            }
+        }
+        if self.should_exit {
+            return Err(Box::new(ExitRequested));
+        }
+        Ok(())
+    }
+
     fn interpret_function(&mut self, function: &Function, args: &[RuntimeValue]) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        self.run_function(function, args, HashMap::new())
+    }
+
+    fn call_closure(&mut self, closure: &Closure, args: &[RuntimeValue]) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        self.run_function(&closure.function, args, closure.captured.clone())
+    }
+
+    /// Выполняет тело функции; `scope` - переменные, видимые помимо параметров
+    fn run_function(&mut self, function: &Function, args: &[RuntimeValue], scope: HashMap<String, RuntimeValue>) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        if let Some(limits) = self.limits {
+            if self.call_stack.len() >= limits.max_depth {
+                return Err(format!("evaluation exceeded {} nested calls", limits.max_depth).into());
+            }
+        }
+        self.call_stack.push(StackFrame { function: function.name.clone(), line: function.line, column: 0 });
+        // Блоки вызывающей функции не видны; ошибка может оставить блоки
+        // вызванной открытыми, поэтому стек областей восстанавливается здесь
+        let outer_scopes = std::mem::take(&mut self.scopes);
+        let result = self.run_function_body(function, args, scope);
+        self.scopes = outer_scopes;
+        if let Err(e) = &result {
+            if Self::is_failure(e.as_ref()) && self.crash_site.is_none() {
+                self.crash_site = Some((self.call_stack.clone(), None));
+            }
+        }
+        self.call_stack.pop();
+        result
+    }
+
+    fn run_function_body(&mut self, function: &Function, args: &[RuntimeValue], scope: HashMap<String, RuntimeValue>) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
         // Сохраняем текущие переменные
-        let old_variables = std::mem::take(&mut self.variables);
+        let old_variables = std::mem::replace(&mut self.variables, scope);
 
         // Устанавливаем параметры
         for (i, param) in function.params.iter().enumerate() {
             if i < args.len() {
-                self.variables.insert(param.name.clone(), args[i].clone());
+                self.variables.insert(param.name.clone(), args[i].clone().with_type(&param.param_type));
             }
         }
 
-        // Выполняем тело функции
+        for (index, condition) in function.requires().enumerate() {
+            self.check_contract(function, "precondition", index, condition)?;
+        }
+
+        // Выполняем тело функции. `?` выходит из вложенных блоков ошибкой,
+        // минуя их defer, поэтому они выполняются здесь вместе с defer тела
+        let deferred = self.deferred.len();
         let mut result = RuntimeValue::Void;
         for statement in &function.body {
-            result = self.interpret_statement(statement)?;
+            result = match self.interpret_statement(statement) {
+                Ok(value) => value,
+                Err(e) if e.is::<EarlyReturn>() => {
+                    result = e.downcast::<EarlyReturn>().map_or(RuntimeValue::Void, |early| early.0);
+                    break;
+                }
+                Err(e) => {
+                    self.deferred.truncate(deferred);
+                    return Err(e);
+                }
+            };
             
-            // Если встретили return, прерываем выполнение
-            if let Statement::Return { .. } = statement {
+            // Если встретили return (в том числе во вложенном блоке), прерываем выполнение
+            if self.returning {
+                self.returning = false;
                 break;
             }
         }
+        if let Err(e) = self.run_deferred(deferred) {
+            self.deferred.truncate(deferred);
+            return Err(e);
+        }
+
+        if function.ensures().next().is_some() {
+            self.variables.insert("result".to_string(), result.clone());
+            for (index, condition) in function.ensures().enumerate() {
+                self.check_contract(function, "postcondition", index, condition)?;
+            }
+        }
 
         // Восстанавливаем переменные
         self.variables = old_variables;
 
-        Ok(result)
+        Ok(result.with_type(&function.return_type))
+    }
+
+    fn check_contract(&mut self, function: &Function, kind: &str, index: usize, condition: &Expression) -> Result<(), Box<dyn std::error::Error>> {
+        let value = self.interpret_expression(condition)?;
+        if self.is_truthy(&value) {
+            Ok(())
+        } else {
+            Err(format!("{} #{} of fn {} (line {}) failed", kind, index + 1, function.name, function.line).into())
+        }
+    }
+
+    /// Выполняет в обратном порядке defer, записанные после отметки `mark`.
+    /// return и break на это время снимаются, чтобы вызванные из отложенного
+    /// выражения функции не прервались, и затем восстанавливаются
+    fn run_deferred(&mut self, mark: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if self.deferred.len() <= mark {
+            return Ok(());
+        }
+        let returning = std::mem::take(&mut self.returning);
+        let loop_exit = self.loop_exit.take();
+        for expression in self.deferred.split_off(mark).iter().rev() {
+            self.interpret_expression(expression)?;
+        }
+        self.returning = returning;
+        self.loop_exit = loop_exit;
+        Ok(())
+    }
+
+    /// Выполнен return, break или continue: оставшиеся операторы блока пропускаются
+    fn interrupted(&self) -> bool {
+        self.returning || self.loop_exit.is_some()
+    }
+
+    /// Забирает break/continue, адресованный циклу с меткой `label`.
+    /// true - продолжить цикл (continue); false - выйти из него: это break
+    /// этого цикла или выход, который ещё нужно передать внешнему циклу
+    fn take_continue(&mut self, label: &Option<String>) -> bool {
+        let target = match &self.loop_exit {
+            Some(LoopExit::Break(target) | LoopExit::Continue(target)) => target,
+            None => return true,
+        };
+        if target.is_some() && target != label {
+            return false;
+        }
+        matches!(self.loop_exit.take(), Some(LoopExit::Continue(_)))
     }
 
     fn interpret_statement(&mut self, statement: &Statement) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        self.count_step()?;
+        // Позиция оператора в кадре; после вложенного оператора кадр снова
+        // указывает на объемлющий. У операторов без позиции (созданных
+        // компилятором) остаётся прежняя
+        let position = statement.position();
+        let outer = match self.call_stack.last_mut() {
+            Some(frame) if position.line != 0 => {
+                let outer = (frame.line, frame.column);
+                (frame.line, frame.column) = (position.line, position.column);
+                Some(outer)
+            }
+            _ => None,
+        };
+        let result = self.execute_statement(statement);
+        match &result {
+            // Первым ошибку видит самый внутренний оператор
+            Err(e) if Self::is_failure(e.as_ref()) => {
+                if self.crash_site.is_none() {
+                    let position = Some(position).filter(|position| position.line != 0);
+                    self.crash_site = Some((self.call_stack.clone(), position));
+                }
+            }
+            _ => {
+                if let (Some(frame), Some((line, column))) = (self.call_stack.last_mut(), outer) {
+                    (frame.line, frame.column) = (line, column);
+                }
+            }
+        }
+        result
+    }
+
+    fn execute_statement(&mut self, statement: &Statement) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
         match statement {
             // interpreter.rs - в функции interpret_statement
-            Statement::VariableDeclaration { name, var_type: _, value, mutable } => {
+            Statement::VariableDeclaration { name, var_type, value, mutable, .. } => {
+                let value = self.interpret_expression(value)?.with_type(var_type);
+                self.declare(name, value);
+                Ok(RuntimeValue::Void)
+            }
+
+            // Раскрывается в обращения к полям значения
+            Statement::DestructureStruct { bindings, value, .. } => {
                 let value = self.interpret_expression(value)?;
-                self.variables.insert(name.clone(), value);
+                for (field, binding) in bindings {
+                    let field_value = value.get_field(field)
+                        .ok_or_else(|| format!("Undefined field: {}", field))?;
+                    self.declare(binding, field_value);
+                }
                 Ok(RuntimeValue::Void)
             }
 
             // interpreter.rs - добавьте в interpret_statement
-            Statement::Assignment { name, value } => {
+            Statement::Assignment { name, value, .. } => {
                 let new_value = self.interpret_expression(value)?;
                 // interpreter.rs - исправьте строку 163
                 if let Some(old_value) = self.variables.get_mut(name) {
-                    // Убрали лишний & перед name
-                    *old_value = new_value;
+                    // i64-переменная остаётся i64 после присваивания литерала
+                    *old_value = new_value.like(old_value);
+                } else if let Some((_, old_value)) = self.statics.get_mut(name) {
+                    *old_value = new_value.like(old_value);
                 } else {
                     return Err(format!("Undefined variable: {}", name).into());
                 }
                 Ok(RuntimeValue::Void)
             }
             
-            Statement::Return { value } => {
-                let result = self.interpret_expression(value)?;
-                Ok(result)
+            Statement::FieldAssignment { name, fields, value, .. } => {
+                let new_value = self.interpret_expression(value)?;
+                let target = self.variables.get_mut(name)
+                    .ok_or_else(|| format!("Undefined variable: {}", name))?;
+                target.set_path(fields, new_value)?;
+                Ok(RuntimeValue::Void)
+            }
+            
+            // Буфер, общий с копиями массива и срезами, копируется перед записью
+            Statement::IndexAssignment { name, index, value, .. } => {
+                let new_value = self.interpret_expression(value)?;
+                let index = match self.interpret_expression(index)? {
+                    RuntimeValue::Integer(index) => index,
+                    _ => return Err("Array index must be i32".into()),
+                };
+                let Some(RuntimeValue::Array(elements)) = self.variables.get_mut(name) else {
+                    return Err(format!("Cannot assign to an element of {}", name).into());
+                };
+                let length = elements.len();
+                let slot = usize::try_from(index).ok()
+                    .and_then(|i| Rc::make_mut(elements).get_mut(i))
+                    .ok_or_else(|| format!("Index out of bounds: index {} but length is {}", index, length))?;
+                *slot = new_value;
+                Ok(RuntimeValue::Void)
+            }
+            
+            Statement::Return { value, .. } => {
+                let result = self.interpret_expression(value)?;
+                self.returning = true;
+                Ok(result)
+            }
+            
+            Statement::Break { label, .. } => {
+                self.loop_exit = Some(LoopExit::Break(label.clone()));
+                Ok(RuntimeValue::Void)
+            }
+            
+            Statement::Continue { label, .. } => {
+                self.loop_exit = Some(LoopExit::Continue(label.clone()));
+                Ok(RuntimeValue::Void)
             }
             
-            Statement::Expression(expr) => {
+            Statement::Expression(expr, _) => {
                 self.interpret_expression(expr)?;
                 Ok(RuntimeValue::Void)
             }
             
-            Statement::Block { statements } => {
-                // Сохраняем текущие переменные
-                let old_variables = self.variables.clone();
+            Statement::Defer { expression, .. } => {
+                self.deferred.push(expression.clone());
+                Ok(RuntimeValue::Void)
+            }
+            
+            Statement::Block { statements, .. } => {
+                self.enter_scope();
+                let deferred = self.deferred.len();
                 
                 let mut result = RuntimeValue::Void;
                 for stmt in statements {
                     result = self.interpret_statement(stmt)?;
+                    if self.interrupted() {
+                        break;
+                    }
                 }
-                
-                // Восстанавливаем переменные
-                self.variables = old_variables;
+                self.run_deferred(deferred)?;
+                self.exit_scope();
                 Ok(result)
             }
 
             // interpreter.rs - в interpret_statement для VariableDeclaration
-            Statement::VariableDeclaration { name, var_type: _, value, mutable } => {
+            Statement::VariableDeclaration { name, var_type: _, value, mutable, .. } => {
                 if self.variables.contains_key(name) && !mutable {
                     return Err(format!("Cannot reassign immutable variable: {}", name).into());
                 }
@@ -205,87 +1522,108 @@ impl Interpreter {
                 Ok(RuntimeValue::Void)
             }
             
-            Statement::While { condition, body } => {
-                // ВАЖНО: сохраняем переменные перед циклом
-                let old_variables = self.variables.clone();
+            Statement::While { condition, body, label, .. } => self.run_loop(Some(condition), body, label),
+            
+            Statement::Loop { body, label, .. } => self.run_loop(None, body, label),
             
-                loop {
-                    // Вычисляем условие
-                    let condition_result = self.interpret_expression(condition)?;
-                    let condition_value = self.is_truthy(&condition_result);
+            Statement::For { variable, start, end, body, label, .. } => {
+                let (start, end) = match (self.interpret_expression(start)?, self.interpret_expression(end)?) {
+                    (RuntimeValue::Integer(start), RuntimeValue::Integer(end)) => (start, end),
+                    _ => return Err("for: range bounds must be i32".into()),
+                };
                 
-                    if !condition_value {
-                        break;
-                    }
-                    
-                    // ВАЖНО: НЕ сохраняем переменные перед выполнением тела
-                    // Это позволяет переменным сохраняться между итерациями
-                    
-                    // Выполняем тело цикла
+                // Счётчик объявлен в области цикла, тело каждой итерации - во вложенной
+                self.enter_scope();
+                self.declare(variable, RuntimeValue::Integer(start));
+                for i in start..end {
+                    self.variables.insert(variable.clone(), RuntimeValue::Integer(i));
+                    self.enter_scope();
+                    let deferred = self.deferred.len();
                     for stmt in body {
-                        self.interpret_statement(stmt)?;
-                    }
-                    
-                    // Проверяем выход из графического цикла
-                    if let Some(engine) = &mut self.graphics_engine {
-                        if !engine.update() {
-                            self.should_exit = true;
+                        let value = self.interpret_statement(stmt)?;
+                        if self.returning {
+                            self.run_deferred(deferred)?;
+                            self.exit_scope();
+                            self.exit_scope();
+                            return Ok(value);
+                        }
+                        if self.loop_exit.is_some() {
                             break;
                         }
                     }
-                    
-                    if self.should_exit {
+                    self.run_deferred(deferred)?;
+                    self.exit_scope();
+                    if self.loop_exit.is_some() && !self.take_continue(label) {
                         break;
                     }
+                    
+                    self.check_exit()?;
                 }
-                
-                // ВАЖНО: НЕ восстанавливаем исходные переменные после цикла
-                // Это позволяет изменениям переменных сохраняться после цикла
+                self.exit_scope();
                 
                 Ok(RuntimeValue::Void)
             }
             
-            Statement::If { condition, then_branch, else_branch } => {
+            Statement::If { condition, then_branch, else_branch, .. } => {
                 let condition_result = self.interpret_expression(condition)?;
                 
-                // Сохраняем переменные перед ветвлением
-                let old_variables = self.variables.clone();
-                
-                if self.is_truthy(&condition_result) {
-                    for stmt in then_branch {
-                        self.interpret_statement(stmt)?;
-                    }
-                } else if let Some(else_branch) = else_branch {
-                    for stmt in else_branch {
-                        self.interpret_statement(stmt)?;
+                let branch = if self.is_truthy(&condition_result) {
+                    Some(then_branch)
+                } else {
+                    else_branch.as_ref()
+                };
+                self.enter_scope();
+                let deferred = self.deferred.len();
+                let mut result = RuntimeValue::Void;
+                for stmt in branch.into_iter().flatten() {
+                    let value = self.interpret_statement(stmt)?;
+                    if self.interrupted() {
+                        result = value;
+                        break;
                     }
                 }
-                
-                // Восстанавливаем переменные после ветвления
-                self.variables = old_variables;
-                Ok(RuntimeValue::Void)
+                self.run_deferred(deferred)?;
+                self.exit_scope();
+                Ok(result)
             }
         }
     }
 
     fn interpret_expression(&mut self, expr: &Expression) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
         match expr {
-            Expression::IntegerLiteral(value) => Ok(RuntimeValue::Integer(*value)),
+            // Литерал за пределами i32 бывает только у i64 (проверено тайпчекером)
+            Expression::IntegerLiteral(value) => Ok(i32::try_from(*value)
+                .map_or(RuntimeValue::Long(*value), RuntimeValue::Integer)),
             Expression::FloatLiteral(value) => Ok(RuntimeValue::Float(*value)),
-            Expression::StringLiteral(value) => Ok(RuntimeValue::String(value.clone())),
+            Expression::StringLiteral(value) => Ok(RuntimeValue::String(self.intern(value))),
             Expression::BoolLiteral(value) => Ok(RuntimeValue::Boolean(*value)),
             
             Expression::Variable(name) => {
-                self.variables.get(name)
-                    .cloned()
-                    .ok_or_else(|| format!("Undefined variable: {}", name).into())
+                if let Some(value) = self.variables.get(name)
+                    .or_else(|| self.constants.get(name))
+                    .or_else(|| self.statics.get(name).map(|(_, value)| value))
+                {
+                    return Ok(value.clone());
+                }
+                // Имя пользовательской функции - значение-функция без окружения
+                match self.functions.get(name) {
+                    Some(function) => Ok(RuntimeValue::Function(Rc::new(Closure {
+                        function: function.clone(),
+                        captured: HashMap::new(),
+                    }))),
+                    None => Err(format!("Undefined variable: {}", name).into()),
+                }
             }
             
             Expression::BinaryExpression { left, operator, right } => {
                 let left_val = self.interpret_expression(left)?;
                 let right_val = self.interpret_expression(right)?;
                 
-                self.evaluate_binary_operation(&left_val, operator, &right_val)
+                let result = self.evaluate_binary_operation(&left_val, operator, &right_val)?;
+                if let RuntimeValue::String(_) = result {
+                    self.allocations += 1; // результат склейки строк
+                }
+                Ok(result)
             }
             
             Expression::FunctionCall { name, args } => {
@@ -293,6 +1631,12 @@ impl Interpreter {
                     .map(|arg| self.interpret_expression(arg))
                     .collect::<Result<Vec<_>, _>>()?;
                 
+                // Переменная-функция закрывает одноимённые функции
+                if let Some(RuntimeValue::Function(closure)) = self.variables.get(name) {
+                    let closure = closure.clone();
+                    return self.call_closure(&closure, &arg_values);
+                }
+                
                 // Сначала проверяем встроенные функции
                 if self.is_builtin_function(name) {
                     self.call_builtin_function(name, &arg_values)
@@ -308,23 +1652,40 @@ impl Interpreter {
                 }
             }
             
-            Expression::StructInitialization { struct_name, fields } => {
+            Expression::StructInitialization { struct_name, fields, base } => {
                 let mut field_values = HashMap::new();
                 for (field_name, field_expr) in fields {
                     let value = self.interpret_expression(field_expr)?;
                     field_values.insert(field_name.clone(), value);
                 }
-                Ok(RuntimeValue::Struct(struct_name.clone(), field_values))
+                // Как в Rust, base вычисляется после явных полей
+                if let Some(base) = base {
+                    let base_fields = match self.interpret_expression(base)? {
+                        RuntimeValue::Struct(_, base_fields) => base_fields,
+                        RuntimeValue::Ref(cell) => match &*cell.borrow() {
+                            RuntimeValue::Struct(_, base_fields) => base_fields.clone(),
+                            _ => return Err("Struct update base is not a struct".into()),
+                        },
+                        _ => return Err("Struct update base is not a struct".into()),
+                    };
+                    for (field_name, value) in base_fields.iter() {
+                        field_values.entry(field_name.clone()).or_insert_with(|| value.clone());
+                    }
+                } else if let Some(defaults) = self.struct_defaults.get(struct_name) {
+                    for (field_name, value) in defaults {
+                        field_values.entry(field_name.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+                self.allocations += 1;
+                Ok(RuntimeValue::Struct(self.intern(struct_name), Rc::new(field_values)))
             }
             
             Expression::FieldAccess { expression, field_name } => {
                 let struct_val = self.interpret_expression(expression)?;
-                if let RuntimeValue::Struct(_, fields) = struct_val {
-                    fields.get(field_name)
-                        .cloned()
-                        .ok_or_else(|| format!("Undefined field: {}", field_name).into())
-                } else {
-                    Err("Field access on non-struct value".into())
+                match struct_val {
+                    RuntimeValue::Struct(..) | RuntimeValue::Ref(_) => struct_val.get_field(field_name)
+                        .ok_or_else(|| format!("Undefined field: {}", field_name).into()),
+                    _ => Err("Field access on non-struct value".into()),
                 }
             }
 
@@ -334,7 +1695,11 @@ impl Interpreter {
                 match (value, target_type) {
                     (RuntimeValue::Integer(i), Type::F32) => Ok(RuntimeValue::Float(i as f32)),
                     (RuntimeValue::Float(f), Type::I32) => Ok(RuntimeValue::Integer(f as i32)),
-                    (RuntimeValue::Integer(i), Type::I64) => Ok(RuntimeValue::Integer(i)), // временно
+                    (RuntimeValue::Integer(i), Type::I64) => Ok(RuntimeValue::Long(i as i64)),
+                    (RuntimeValue::Float(f), Type::I64) => Ok(RuntimeValue::Long(f as i64)),
+                    // Старшие биты отбрасываются, как i32.wrap_i64 в WASM
+                    (RuntimeValue::Long(i), Type::I32) => Ok(RuntimeValue::Integer(i as i32)),
+                    (RuntimeValue::Long(i), Type::F32 | Type::F64) => Ok(RuntimeValue::Float(i as f32)),
                     (RuntimeValue::Integer(i), Type::F64) => Ok(RuntimeValue::Float(i as f32)), // временно
                     (value, _) => Ok(value), // Если типы совпадают или преобразование не нужно
                 }
@@ -343,37 +1708,220 @@ impl Interpreter {
             // Пока упрощенно обрабатываем move и borrow
             Expression::Move { expression } => self.interpret_expression(expression),
             Expression::Borrow { expression, .. } => self.interpret_expression(expression),
+            
+            Expression::Ref { expression } => {
+                let value = self.interpret_expression(expression)?;
+                self.allocations += 1;
+                Ok(RuntimeValue::Ref(Rc::new(RefCell::new(value))))
+            }
+            
+            Expression::ArrayLiteral { elements } => {
+                let values = elements.iter()
+                    .map(|element| self.interpret_expression(element))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.allocations += 1;
+                Ok(RuntimeValue::Array(Rc::new(values)))
+            }
+            
+            Expression::ArrayRepeat { value, count } => {
+                let value = self.interpret_expression(value)?;
+                self.allocations += 1;
+                Ok(RuntimeValue::Array(Rc::new(vec![value; *count])))
+            }
+            
+            Expression::Index { expression, index } => {
+                let array = self.interpret_expression(expression)?;
+                let index = self.interpret_expression(index)?;
+                match (array.elements(), index) {
+                    (Some(elements), RuntimeValue::Integer(i)) => {
+                        usize::try_from(i).ok()
+                            .and_then(|i| elements.get(i).cloned())
+                            .ok_or_else(|| format!("Index out of bounds: index {} but length is {}", i, elements.len()).into())
+                    }
+                    _ => Err("Indexing a non-array value".into()),
+                }
+            }
+            
+            // Срез делит буфер с массивом; границы проверяются здесь, а не при обращении
+            Expression::Slice { expression, start, end } => {
+                let (elements, offset, length) = match self.interpret_expression(expression)? {
+                    RuntimeValue::Array(elements) => {
+                        let length = elements.len();
+                        (elements, 0, length)
+                    }
+                    RuntimeValue::Slice(elements, start, end) => (elements, start, end - start),
+                    _ => return Err("Slicing a non-array value".into()),
+                };
+                let mut bound = |bound: &Option<Box<Expression>>, default: usize| -> Result<i32, Box<dyn std::error::Error>> {
+                    match bound {
+                        Some(expression) => match self.interpret_expression(expression)? {
+                            RuntimeValue::Integer(value) => Ok(value),
+                            _ => Err("Slice bounds must be i32".into()),
+                        },
+                        None => Ok(default as i32),
+                    }
+                };
+                let from = bound(start, 0)?;
+                let to = bound(end, length)?;
+                if from < 0 || from > to || to as usize > length {
+                    return Err(format!("Slice out of bounds: {}..{} but length is {}", from, to, length).into());
+                }
+                Ok(RuntimeValue::Slice(elements, offset + from as usize, offset + to as usize))
+            }
+            
+            Expression::MethodCall { receiver, method, args } => {
+                // self получает копию значения, в том числе из ref
+                let receiver = match self.interpret_expression(receiver)? {
+                    RuntimeValue::Ref(cell) => cell.borrow().clone(),
+                    value => value,
+                };
+                let type_name = match &receiver {
+                    RuntimeValue::Struct(name, _) | RuntimeValue::Enum(name, _, _) => name.clone(),
+                    RuntimeValue::Array(_) | RuntimeValue::Slice(..) if method == "len" => {
+                        let length = receiver.elements().map_or(0, <[RuntimeValue]>::len);
+                        return Ok(RuntimeValue::Integer(length as i32));
+                    }
+                    _ => return Err(format!("Method call {} on non-struct value", method).into()),
+                };
+                let mut arg_values = vec![receiver];
+                for arg in args {
+                    arg_values.push(self.interpret_expression(arg)?);
+                }
+                let name = method_name(&type_name, method);
+                let function = self.functions.get(&name)
+                    .cloned()
+                    .ok_or_else(|| format!("Undefined method: {}", name))?;
+                self.interpret_function(&function, &arg_values)
+            }
+            
+            // Point::new(..) - функция из impl-блока; тайпчекер не допускает
+            // совпадения её имени с вариантом перечисления
+            Expression::EnumVariant { enum_name, variant, args } if self.functions.contains_key(&method_name(enum_name, variant)) => {
+                let arg_values: Vec<RuntimeValue> = args.iter()
+                    .map(|arg| self.interpret_expression(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let function = self.functions[&method_name(enum_name, variant)].clone();
+                self.interpret_function(&function, &arg_values)
+            }
+            
+            Expression::EnumVariant { enum_name, variant, args } => {
+                let fields = args.iter()
+                    .map(|arg| self.interpret_expression(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !fields.is_empty() {
+                    self.allocations += 1;
+                }
+                Ok(RuntimeValue::Enum(self.intern(enum_name), self.intern(variant), Rc::new(fields)))
+            }
+            
+            Expression::Match { subject, arms } => {
+                let value = self.interpret_expression(subject)?;
+                for arm in arms {
+                    let Some(bindings) = Self::pattern_matches(&arm.pattern, &value) else {
+                        continue;
+                    };
+                    // Поля варианта видны только в ветке и перекрывают внешние переменные
+                    self.enter_scope();
+                    for (name, value) in bindings {
+                        self.declare(&name, value);
+                    }
+                    let result = self.interpret_expression(&arm.body);
+                    self.exit_scope();
+                    return result;
+                }
+                Err(format!("No match arm for value {}", value).into())
+            }
+            
+            Expression::If { condition, then_branch, else_branch } => {
+                let condition_result = self.interpret_expression(condition)?;
+                if self.is_truthy(&condition_result) {
+                    self.interpret_expression(then_branch)
+                } else {
+                    self.interpret_expression(else_branch)
+                }
+            }
+            
+            Expression::Try { expression } => {
+                let value = self.interpret_expression(expression)?;
+                match &value {
+                    RuntimeValue::Enum(_, variant, fields) if &**variant == "Some" || &**variant == "Ok" => Ok(fields[0].clone()),
+                    RuntimeValue::Enum(_, variant, _) if &**variant == "None" || &**variant == "Err" => {
+                        Err(Box::new(EarlyReturn(value)))
+                    }
+                    _ => Err(format!("Operator ? applied to {}", value).into()),
+                }
+            }
+            
+            // Окружение захватывается по значению: составные значения
+            // разделяются через Rc, копия создаётся только при записи
+            Expression::Lambda { params, return_type, body } => {
+                self.allocations += 1;
+                Ok(RuntimeValue::Function(Rc::new(Closure {
+                    function: Function {
+                        name: String::new(),
+                        params: params.clone(),
+                        return_type: return_type.clone(),
+                        body: body.clone(),
+                        is_public: false,
+                        line: 0,
+                        attributes: Vec::new(),
+                        type_params: Vec::new(),
+                        module: 0,
+                    },
+                    captured: self.variables.clone(),
+                })))
+            }
         }
     }
 
+    /// Подходит ли значение под образец; Some - переменные, связанные образцом
+    fn pattern_matches(pattern: &Pattern, value: &RuntimeValue) -> Option<Vec<(String, RuntimeValue)>> {
+        let matched = match (pattern, value) {
+            (Pattern::Wildcard, _) => true,
+            (Pattern::Integer(expected), RuntimeValue::Integer(actual)) => expected == actual,
+            (Pattern::Integer(expected), RuntimeValue::Long(actual)) => *expected as i64 == *actual,
+            (Pattern::Bool(expected), RuntimeValue::Boolean(actual)) => expected == actual,
+            (Pattern::Variant { enum_name, variant, bindings }, RuntimeValue::Enum(name, actual, fields)) => {
+                if **name != **enum_name || **actual != **variant {
+                    return None;
+                }
+                return Some(bindings.iter()
+                    .zip(fields.iter())
+                    .filter(|(binding, _)| *binding != "_")
+                    .map(|(binding, field)| (binding.clone(), field.clone()))
+                    .collect());
+            }
+            _ => false,
+        };
+        matched.then(Vec::new)
+    }
+
     fn is_builtin_function(&self, name: &str) -> bool {
-        matches!(name, 
-            "print_i32" | "print_string" | "print" |
-            "gpio_set" | "gpio_toggle" | "delay" |
-            // Графические функции
-            "init_graphics" | "clear_screen" | "draw_pixel" | "draw_rect" | 
-            "draw_circle" | "draw_line" | "render" | "get_time" | "sleep" | "is_key_pressed"
-        )
-    }   
+        stdlib::is_builtin(name)
+    }
 
     fn call_builtin_function(&mut self, name: &str, args: &[RuntimeValue]) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        if !self.is_main_thread && MAIN_THREAD_ONLY.contains(&name) {
+            return Err(format!("{} can only be called from the main thread", name).into());
+        }
+        self.capabilities.check(name)?;
+
         match name {
             // Встроенные функции вывода
             "print_i32" => {
                 if let RuntimeValue::Integer(value) = &args[0] {
-                    println!("{}", value);
+                    self.write_line(value.to_string());
                 }
                 Ok(RuntimeValue::Void)
             }
             "print" => {
-                if let RuntimeValue::Integer(value) = &args[0] {
-                    println!("{}", value);
-                }
+                let parts: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                self.write_line(parts.join(" "));
                 Ok(RuntimeValue::Void)
             }
             "print_string" => {
                 if let RuntimeValue::String(value) = &args[0] {
-                    println!("{}", value);
+                    self.write_line(value.to_string());
                 }
                 Ok(RuntimeValue::Void)
             }
@@ -396,7 +1944,19 @@ impl Interpreter {
             
             // Графические функции
             "init_graphics" => {
-                // Уже инициализировано при запуске
+                let (width, height, title) = match (&args[0], &args[1], &args[2]) {
+                    (RuntimeValue::Integer(width), RuntimeValue::Integer(height), RuntimeValue::String(title)) => {
+                        (*width, *height, title.to_string())
+                    }
+                    _ => return Err("init_graphics expects (i32, i32, string)".into()),
+                };
+                if width <= 0 || height <= 0 {
+                    return Err(format!("init_graphics: window size must be positive, got {}x{}", width, height).into());
+                }
+                self.window_config.width = width as usize;
+                self.window_config.height = height as usize;
+                self.window_config.title = title;
+                self.open_window()?;
                 Ok(RuntimeValue::Void)
             }
             "clear_screen" => {
@@ -443,12 +2003,64 @@ impl Interpreter {
                 }
                 Ok(RuntimeValue::Void)
             }
+            "draw_text" => {
+                if let (RuntimeValue::Integer(x), RuntimeValue::Integer(y), RuntimeValue::String(text), RuntimeValue::Integer(r), RuntimeValue::Integer(g), RuntimeValue::Integer(b)) =
+                    (&args[0], &args[1], &args[2], &args[3], &args[4], &args[5]) {
+                    if let Some(engine) = &mut self.graphics_engine {
+                        engine.draw_text(*x, *y, text, *r as u8, *g as u8, *b as u8);
+                    }
+                }
+                Ok(RuntimeValue::Void)
+            }
+            "palette" => {
+                let name = args[0].to_string();
+                let [r, g, b] = color::palette(&name).ok_or_else(|| {
+                    let names: Vec<&str> = color::PALETTE.iter().map(|(name, _)| *name).collect();
+                    format!("palette: unknown color {:?}, expected one of: {}", name, names.join(", "))
+                })?;
+                let fields = [("r", r), ("g", g), ("b", b)].into_iter()
+                    .map(|(field, value)| (field.to_string(), RuntimeValue::Integer(value as i32)))
+                    .collect();
+                self.allocations += 1;
+                Ok(RuntimeValue::Struct(self.intern("Color"), Rc::new(fields)))
+            }
+            "contrast_ratio" => {
+                let a = Self::color_channels(&args[0])?;
+                let b = Self::color_channels(&args[1])?;
+                Ok(RuntimeValue::Float(color::contrast_ratio(a, b)))
+            }
             "render" => {
+                let stats = FrameStats {
+                    steps: self.steps - self.frame_start.0,
+                    allocations: self.allocations - self.frame_start.1,
+                    ..FrameStats::default()
+                };
+                self.frame_start = (self.steps, self.allocations);
+                // Обход живых значений дорог, поэтому только при включённом HUD
+                let entities = match &self.graphics_engine {
+                    Some(engine) if engine.debug_hud.visible => self.mem_stats().live_structs,
+                    _ => 0,
+                };
                 if let Some(engine) = &mut self.graphics_engine {
+                    if engine.debug_hud.visible {
+                        engine.debug_hud.record(FrameStats {
+                            frame_time: engine.frame_time(),
+                            draw_calls: engine.draw_calls(),
+                            entities,
+                            ..stats
+                        });
+                    }
                     engine.render();
                 }
                 Ok(RuntimeValue::Void)
             }
+            "debug_hud" => {
+                let enabled = self.is_truthy(&args[0]);
+                if let Some(engine) = &mut self.graphics_engine {
+                    engine.debug_hud.visible = enabled;
+                }
+                Ok(RuntimeValue::Void)
+            }
             // Затем в call_builtin_function
             "get_time" => {
                 let elapsed = self.start_time.elapsed();
@@ -460,31 +2072,428 @@ impl Interpreter {
                 }
                 Ok(RuntimeValue::Void)
             }
-            "is_key_pressed" => {
+            // Потоки и каналы
+            "spawn" => {
+                if let RuntimeValue::String(function_name) = &args[0] {
+                    let function_name = function_name.to_string();
+                    return Ok(RuntimeValue::Integer(self.spawn_function(&function_name)?));
+                }
+                Err("spawn expects a function name".into())
+            }
+            "join" => {
+                if let RuntimeValue::Integer(id) = args[0] {
+                    return match self.join_thread(id)? {
+                        RuntimeValue::Void => Ok(RuntimeValue::Integer(0)),
+                        value @ RuntimeValue::Integer(_) => Ok(value),
+                        value => Err(format!("join: thread {} returned {}, expected i32", id, value).into()),
+                    };
+                }
+                Err("join expects a thread handle".into())
+            }
+            "channel_send" => {
+                if let RuntimeValue::Integer(channel) = args[0] {
+                    self.channels.send(channel, ThreadValue::from(&args[1]));
+                }
+                Ok(RuntimeValue::Void)
+            }
+            "channel_recv" => {
+                if let RuntimeValue::Integer(channel) = args[0] {
+                    return Ok(RuntimeValue::from(self.channels.recv(channel)?));
+                }
+                Err("channel_recv expects a channel number".into())
+            }
+            // Сопрограммы
+            "start_coroutine" => {
+                if let RuntimeValue::String(function_name) = &args[0] {
+                    let function_name = function_name.to_string();
+                    return Ok(RuntimeValue::Integer(self.start_coroutine(&function_name)?));
+                }
+                Err("start_coroutine expects a function name".into())
+            }
+            "resume" => {
+                if let RuntimeValue::Integer(id) = args[0] {
+                    return Ok(RuntimeValue::Boolean(self.resume_coroutine(id)?));
+                }
+                Err("resume expects a coroutine handle".into())
+            }
+            "yield_frame" => {
+                // Сюда попадаем только вне сопрограммы: внутри неё вызов
+                // перехватывается в run_coroutine_frames
+                Err("yield_frame() can only be used as a statement in a coroutine started with start_coroutine".into())
+            }
+            "assert" => {
+                if self.is_truthy(&args[0]) {
+                    Ok(RuntimeValue::Void)
+                } else {
+                    Err(format!("assertion failed: {}", args[1]).into())
+                }
+            }
+            "console_log" => {
+                let message = args[0].to_string();
+                match &mut self.graphics_engine {
+                    Some(engine) => {
+                        engine.console.push(&message);
+                        self.remember_output(&message);
+                    }
+                    None => self.write_line(message),
+                }
+                Ok(RuntimeValue::Void)
+            }
+            "mem_stats" => {
+                let report = self.mem_stats().to_string();
+                self.allocations += 1;
+                Ok(RuntimeValue::String(Rc::from(report)))
+            }
+            "sin" | "cos" | "fast_sin" | "fast_cos" => {
+                let angle = match args[0] {
+                    RuntimeValue::Float(f) => f,
+                    RuntimeValue::Integer(i) => i as f32,
+                    _ => return Err(format!("{} expects a number", name).into()),
+                };
+                let value = match (name, self.strict_fp) {
+                    ("fast_sin", _) => fp::fast_sin(angle),
+                    ("fast_cos", _) => fp::fast_cos(angle),
+                    ("sin", true) => fp::sin(angle),
+                    ("sin", false) => angle.sin(),
+                    (_, true) => fp::cos(angle),
+                    (_, false) => angle.cos(),
+                };
+                Ok(RuntimeValue::Float(value))
+            }
+            "is_paused" => {
+                let paused = self.graphics_engine.as_ref()
+                    .is_some_and(|engine| engine.frame_control.is_paused());
+                Ok(RuntimeValue::Boolean(paused))
+            }
+            "delta_time" => {
+                let delta = self.graphics_engine.as_ref().map_or(0.0, |engine| engine.delta_time());
+                Ok(RuntimeValue::Float(delta))
+            }
+            "lerp" | "ease_in_out" | "move_towards" => {
+                let mut numbers = Vec::with_capacity(args.len());
+                for arg in args {
+                    numbers.push(match arg {
+                        RuntimeValue::Float(f) => *f,
+                        RuntimeValue::Integer(i) => *i as f32,
+                        _ => return Err(format!("{} expects numbers", name).into()),
+                    });
+                }
+                let value = match name {
+                    "lerp" => animation::lerp(numbers[0], numbers[1], numbers[2]),
+                    "ease_in_out" => animation::ease_in_out(numbers[0]),
+                    _ => animation::move_towards(numbers[0], numbers[1], numbers[2]),
+                };
+                Ok(RuntimeValue::Float(value))
+            }
+            "clamp" | "wrap" | "map_range" => {
+                // Все аргументы целые - целый результат, иначе f32
+                if let Some(n) = args.iter()
+                    .map(|arg| match arg {
+                        RuntimeValue::Integer(i) => Some(*i),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<i32>>>()
+                {
+                    let value = match name {
+                        "clamp" => Some(ranges::clamp_i32(n[0], n[1], n[2])),
+                        "wrap" => ranges::wrap_i32(n[0], n[1], n[2]),
+                        _ => ranges::map_range_i32(n[0], n[1], n[2], n[3], n[4]),
+                    };
+                    return value.map(RuntimeValue::Integer)
+                        .ok_or_else(|| format!("{}: empty range", name).into());
+                }
+                let mut n = Vec::with_capacity(args.len());
+                for arg in args {
+                    n.push(match arg {
+                        RuntimeValue::Float(f) => *f,
+                        RuntimeValue::Integer(i) => *i as f32,
+                        _ => return Err(format!("{} expects numbers", name).into()),
+                    });
+                }
+                let value = match name {
+                    "clamp" => Some(ranges::clamp_f32(n[0], n[1], n[2])),
+                    "wrap" => ranges::wrap_f32(n[0], n[1], n[2]),
+                    _ => ranges::map_range_f32(n[0], n[1], n[2], n[3], n[4]),
+                };
+                value.map(RuntimeValue::Float)
+                    .ok_or_else(|| format!("{}: empty range", name).into())
+            }
+            "is_key_pressed" | "key_just_pressed" | "key_just_released" => {
                 if let RuntimeValue::Integer(key_code) = args[0] {
                     if let Some(engine) = &self.graphics_engine {
-                        let key = match key_code {
-                            87 => Key::W,     // W
-                            83 => Key::S,     // S
-                            65 => Key::A,     // A
-                            68 => Key::D,     // D
-                            37 => Key::Left,  // Left arrow
-                            38 => Key::Up,    // Up arrow
-                            39 => Key::Right, // Right arrow
-                            40 => Key::Down,  // Down arrow
-                            32 => Key::Space, // Space
-                            _ => return Ok(RuntimeValue::Boolean(false)),
+                        let query = match name {
+                            "is_key_pressed" => GraphicsEngine::is_key_pressed,
+                            "key_just_pressed" => GraphicsEngine::key_just_pressed,
+                            _ => GraphicsEngine::key_just_released,
                         };
-                        return Ok(RuntimeValue::Boolean(engine.is_key_pressed(key)));
+                        let pressed = key_from_code(key_code).is_some_and(|key| query(engine, key));
+                        return Ok(RuntimeValue::Boolean(pressed));
                     }
                 }
                 Ok(RuntimeValue::Boolean(false))
             }
             
+            "store_set" => {
+                if let (RuntimeValue::String(key), RuntimeValue::String(value)) = (&args[0], &args[1]) {
+                    let store = self.store()?;
+                    store.set(key, value)
+                        .map_err(|e| format!("store_set: cannot save {}: {}", store.path().display(), e))?;
+                }
+                Ok(RuntimeValue::Void)
+            }
+            
+            "store_get" => {
+                // Отсутствующий ключ - пустая строка
+                if let RuntimeValue::String(key) = &args[0] {
+                    let value = self.store()?.get(key).unwrap_or("").to_string();
+                    return Ok(RuntimeValue::String(Rc::from(value)));
+                }
+                Ok(RuntimeValue::String(Rc::from("")))
+            }
+            
+            "window_should_close" => {
+                let closing = self.graphics_engine.as_ref()
+                    .is_some_and(|engine| engine.should_close());
+                Ok(RuntimeValue::Boolean(self.should_exit || closing))
+            }
+            
+            "exit" => {
+                let code = match args.first() {
+                    Some(RuntimeValue::Integer(code)) => *code,
+                    _ => return Err("exit expects an i32 code".into()),
+                };
+                self.exit_code = Some(code);
+                self.should_exit = true;
+                Err(Box::new(ExitRequested))
+            }
+            
+            "on_exit" => {
+                if let RuntimeValue::String(function_name) = &args[0] {
+                    let function = self.functions.get(&**function_name)
+                        .ok_or_else(|| format!("on_exit: undefined function: {}", function_name))?;
+                    if !function.params.is_empty() {
+                        return Err(format!("on_exit: function {} must not take parameters", function_name).into());
+                    }
+                    self.exit_hook = Some(function_name.to_string());
+                    return Ok(RuntimeValue::Void);
+                }
+                Err("on_exit expects a function name".into())
+            }
+            
+            "resolve_asset" => {
+                if let RuntimeValue::String(path) = &args[0] {
+                    let resolved = self.assets.resolve(path).to_string_lossy().into_owned();
+                    return Ok(RuntimeValue::String(Rc::from(resolved)));
+                }
+                Err("resolve_asset expects a path".into())
+            }
+            
+            "random_int" => match (&args[0], &args[1]) {
+                (RuntimeValue::Integer(lo), RuntimeValue::Integer(hi)) if lo < hi => {
+                    Ok(RuntimeValue::Integer(rand::thread_rng().gen_range(*lo..*hi)))
+                }
+                (lo, hi) => Err(format!("random_int: empty range {}..{}", lo, hi).into()),
+            },
+            
+            "random_float" => Ok(RuntimeValue::Float(rand::thread_rng().gen::<f32>())),
+            
+            "http_get" => {
+                let RuntimeValue::String(url) = &args[0] else {
+                    return Err("http_get expects a url".into());
+                };
+                let body = ureq::get(url).call()
+                    .map_err(|e| format!("http_get {}: {}", url, e))?
+                    .into_string()
+                    .map_err(|e| format!("http_get {}: {}", url, e))?;
+                Ok(RuntimeValue::String(Rc::from(body)))
+            }
+            
+            "str_len" | "substring" | "char_at" | "contains" | "split" | "to_upper" | "to_lower" | "parse_i32" | "format" => {
+                self.call_string_builtin(name, args)
+            }
+            
+            "map" | "filter" | "sort" | "fold" => self.call_collection_builtin(name, args),
+            
+            "get_typed_text" => {
+                // Без окна клавиатурного ввода нет
+                let text = self.graphics_engine.as_ref()
+                    .map_or("", |engine| engine.typed_text());
+                Ok(RuntimeValue::String(Rc::from(text)))
+            }
+            
             _ => Err(format!("Unknown builtin function: {}", name).into())
         }
     }
 
+    /// Строковые встроенные функции (см. strings.rs)
+    fn call_string_builtin(&mut self, name: &str, args: &[RuntimeValue]) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        let RuntimeValue::String(text) = &args[0] else {
+            return Err(format!("{} expects a string", name).into());
+        };
+        let integer = |index: usize| match args.get(index) {
+            Some(RuntimeValue::Integer(value)) => Ok(*value),
+            _ => Err(format!("{} expects an integer argument", name)),
+        };
+        let string = |index: usize| match args.get(index) {
+            Some(RuntimeValue::String(value)) => Ok(value.clone()),
+            _ => Err(format!("{} expects a string argument", name)),
+        };
+
+        let value = match name {
+            "str_len" => return Ok(RuntimeValue::Integer(strings::str_len(text))),
+            "contains" => return Ok(RuntimeValue::Boolean(text.contains(&*string(1)?))),
+            "parse_i32" => return Ok(RuntimeValue::Integer(strings::parse_i32(text)?)),
+            "split" => {
+                let parts = strings::split(text, &string(1)?)?;
+                self.allocations += parts.len() + 1;
+                let parts = parts.into_iter()
+                    .map(|part| RuntimeValue::String(Rc::from(part)))
+                    .collect();
+                return Ok(RuntimeValue::Array(Rc::new(parts)));
+            }
+            "substring" => strings::substring(text, integer(1)?, integer(2)?)?,
+            "char_at" => strings::char_at(text, integer(1)?)?,
+            "to_upper" => text.to_uppercase(),
+            "format" => {
+                let parts: Vec<String> = args[1..].iter().map(|arg| arg.to_string()).collect();
+                strings::format(text, &parts)?
+            }
+            _ => text.to_lowercase(),
+        };
+        self.allocations += 1;
+        Ok(RuntimeValue::String(Rc::from(value)))
+    }
+
+    /// map, filter, sort и fold: второй (у fold - третий) аргумент - функция-значение
+    fn call_collection_builtin(&mut self, name: &str, args: &[RuntimeValue]) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        let (RuntimeValue::Array(values), Some(RuntimeValue::Function(f))) = (&args[0], args.last()) else {
+            return Err(format!("{} expects an array and a function", name).into());
+        };
+        let (values, f) = (values.clone(), f.clone());
+        let result = match name {
+            "fold" => {
+                let mut accumulator = args[1].clone();
+                for value in values.iter() {
+                    accumulator = self.call_closure(&f, &[accumulator, value.clone()])?;
+                }
+                return Ok(accumulator);
+            }
+            "map" => values.iter()
+                .map(|value| self.call_closure(&f, std::slice::from_ref(value)))
+                .collect::<Result<Vec<_>, _>>()?,
+            "filter" => {
+                let mut kept = Vec::new();
+                for value in values.iter() {
+                    let keep = self.call_closure(&f, std::slice::from_ref(value))?;
+                    if self.is_truthy(&keep) {
+                        kept.push(value.clone());
+                    }
+                }
+                kept
+            }
+            _ => self.merge_sort(values.to_vec(), &f)?,
+        };
+        self.allocations += 1;
+        Ok(RuntimeValue::Array(Rc::new(result)))
+    }
+
+    /// Устойчивая сортировка слиянием с пользовательским `less`. Не sort_by:
+    /// противоречивое сравнение не должно ронять интерпретатор
+    fn merge_sort(&mut self, mut values: Vec<RuntimeValue>, less: &Closure) -> Result<Vec<RuntimeValue>, Box<dyn std::error::Error>> {
+        if values.len() <= 1 {
+            return Ok(values);
+        }
+        let right = values.split_off(values.len() / 2);
+        let left = self.merge_sort(values, less)?;
+        let right = self.merge_sort(right, less)?;
+        let mut merged = Vec::with_capacity(left.len() + right.len());
+        let mut left = left.into_iter().peekable();
+        let mut right = right.into_iter().peekable();
+        while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+            // Правый берём, только если он строго меньше: так равные сохраняют порядок
+            let right_first = self.call_closure(less, &[b.clone(), a.clone()])?;
+            if self.is_truthy(&right_first) {
+                merged.extend(right.next());
+            } else {
+                merged.extend(left.next());
+            }
+        }
+        merged.extend(left);
+        merged.extend(right);
+        Ok(merged)
+    }
+
+    /// while (`condition` задано) и loop: тело повторяется, пока условие
+    /// истинно, до break или return
+    fn run_loop(&mut self, condition: Option<&Expression>, body: &[Statement], label: &Option<String>) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        loop {
+            if let Some(condition) = condition {
+                let condition_result = self.interpret_expression(condition)?;
+                if !self.is_truthy(&condition_result) {
+                    break;
+                }
+            }
+            
+            // Тело каждой итерации - свой блок: его `let` исчезают к следующей
+            // итерации, присваивания внешним переменным сохраняются.
+            // defer тела выполняются в конце каждой итерации
+            self.enter_scope();
+            let deferred = self.deferred.len();
+            for stmt in body {
+                let value = self.interpret_statement(stmt)?;
+                if self.returning {
+                    self.run_deferred(deferred)?;
+                    self.exit_scope();
+                    return Ok(value);
+                }
+                if self.loop_exit.is_some() {
+                    break;
+                }
+            }
+            self.run_deferred(deferred)?;
+            self.exit_scope();
+            // break - выход, break/continue внешнего цикла остаётся в loop_exit
+            if self.loop_exit.is_some() && !self.take_continue(label) {
+                break;
+            }
+            
+            // Проверяем выход из графического цикла
+            self.check_exit()?;
+        }
+        
+        Ok(RuntimeValue::Void)
+    }
+
+    /// Арифметика i64: переполнение - ошибка выполнения, а не перенос
+    fn evaluate_long_operation(l: i64, operator: &BinaryOperator, r: i64) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        let checked = |value: Option<i64>| {
+            value.map(RuntimeValue::Long)
+                .ok_or_else(|| format!("Integer overflow: {} {} {} does not fit in i64", l, operator, r).into())
+        };
+        match operator {
+            BinaryOperator::Add => checked(l.checked_add(r)),
+            BinaryOperator::Subtract => checked(l.checked_sub(r)),
+            BinaryOperator::Multiply => checked(l.checked_mul(r)),
+            BinaryOperator::Divide if r == 0 => Err("Division by zero".into()),
+            BinaryOperator::Divide => checked(l.checked_div(r)),
+            BinaryOperator::Mod if r == 0 => Err("Division by zero".into()),
+            BinaryOperator::Mod => Ok(RuntimeValue::Long(l.wrapping_rem(r))),
+            BinaryOperator::Eq => Ok(RuntimeValue::Boolean(l == r)),
+            BinaryOperator::Neq => Ok(RuntimeValue::Boolean(l != r)),
+            BinaryOperator::Lt => Ok(RuntimeValue::Boolean(l < r)),
+            BinaryOperator::Gt => Ok(RuntimeValue::Boolean(l > r)),
+            BinaryOperator::Lte => Ok(RuntimeValue::Boolean(l <= r)),
+            BinaryOperator::Gte => Ok(RuntimeValue::Boolean(l >= r)),
+            BinaryOperator::BitAnd => Ok(RuntimeValue::Long(l & r)),
+            BinaryOperator::BitOr => Ok(RuntimeValue::Long(l | r)),
+            BinaryOperator::BitXor => Ok(RuntimeValue::Long(l ^ r)),
+            // Число бит по модулю 64, как i64.shl/i64.shr_s в WASM
+            BinaryOperator::Shl => Ok(RuntimeValue::Long(l.wrapping_shl(r as u32))),
+            BinaryOperator::Shr => Ok(RuntimeValue::Long(l.wrapping_shr(r as u32))),
+            BinaryOperator::And | BinaryOperator::Or => Err("Logical operations not supported for integers".into()),
+        }
+    }
+
     fn evaluate_binary_operation(&self, left: &RuntimeValue, operator: &BinaryOperator, right: &RuntimeValue) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
         println!("DEBUG INTERPRETER: Binary operation - left: {:?}, operator: {:?}, right: {:?}", left, operator, right);
     
@@ -502,30 +2511,43 @@ impl Interpreter {
                             Ok(RuntimeValue::Integer(l / r))
                         }
                     }
+                    // Остаток со знаком делимого, как i32.rem_s в WASM: -7 % 3 == -1
+                    BinaryOperator::Mod => {
+                        if *r == 0 {
+                            Err("Division by zero".into())
+                        } else {
+                            Ok(RuntimeValue::Integer(l.wrapping_rem(*r)))
+                        }
+                    }
                     BinaryOperator::Eq => Ok(RuntimeValue::Boolean(l == r)),
                     BinaryOperator::Neq => Ok(RuntimeValue::Boolean(l != r)),
                     BinaryOperator::Lt => Ok(RuntimeValue::Boolean(l < r)),
                     BinaryOperator::Gt => Ok(RuntimeValue::Boolean(l > r)),
                     BinaryOperator::Lte => Ok(RuntimeValue::Boolean(l <= r)),
                     BinaryOperator::Gte => Ok(RuntimeValue::Boolean(l >= r)),
+                    BinaryOperator::BitAnd => Ok(RuntimeValue::Integer(l & r)),
+                    BinaryOperator::BitOr => Ok(RuntimeValue::Integer(l | r)),
+                    BinaryOperator::BitXor => Ok(RuntimeValue::Integer(l ^ r)),
+                    // Сдвиг берёт число бит по модулю 32, как i32.shl/i32.shr_s в WASM
+                    BinaryOperator::Shl => Ok(RuntimeValue::Integer(l.wrapping_shl(*r as u32))),
+                    BinaryOperator::Shr => Ok(RuntimeValue::Integer(l.wrapping_shr(*r as u32))),
                     BinaryOperator::And | BinaryOperator::Or => {
                         Err("Logical operations not supported for integers".into())
                     }
                 }
             }
+            // i64 с литералом, оставшимся i32: литерал расширяется до i64
+            (RuntimeValue::Long(l), op, RuntimeValue::Long(r)) => Self::evaluate_long_operation(*l, op, *r),
+            (RuntimeValue::Integer(l), op, RuntimeValue::Long(r)) => Self::evaluate_long_operation(*l as i64, op, *r),
+            (RuntimeValue::Long(l), op, RuntimeValue::Integer(r)) => Self::evaluate_long_operation(*l, op, *r as i64),
             (RuntimeValue::Float(l), op, RuntimeValue::Float(r)) => {
                 println!("DEBUG INTERPRETER: Float operation: {} {:?} {}", l, op, r);
                 match op {
                     BinaryOperator::Add => Ok(RuntimeValue::Float(l + r)),
                     BinaryOperator::Subtract => Ok(RuntimeValue::Float(l - r)),
                     BinaryOperator::Multiply => Ok(RuntimeValue::Float(l * r)),
-                    BinaryOperator::Divide => {
-                        if *r == 0.0 {
-                            Err("Division by zero".into())
-                        } else {
-                            Ok(RuntimeValue::Float(l / r))
-                        }
-                    }
+                    BinaryOperator::Divide => self.divide_floats(*l, *r),
+                    BinaryOperator::Mod => self.remainder_floats(*l, *r),
                     BinaryOperator::Eq => Ok(RuntimeValue::Boolean(l == r)),
                     BinaryOperator::Neq => Ok(RuntimeValue::Boolean(l != r)),
                     BinaryOperator::Lt => Ok(RuntimeValue::Boolean(l < r)),
@@ -535,6 +2557,7 @@ impl Interpreter {
                     BinaryOperator::And | BinaryOperator::Or => {
                         Err("Logical operations not supported for floats".into())
                     }
+                    _ => Err("Bitwise operations not supported for floats".into()),
                 }
             }
             (RuntimeValue::Boolean(l), op, RuntimeValue::Boolean(r)) => {
@@ -558,13 +2581,8 @@ impl Interpreter {
                     BinaryOperator::Add => Ok(RuntimeValue::Float(l_float + r)),
                     BinaryOperator::Subtract => Ok(RuntimeValue::Float(l_float - r)),
                     BinaryOperator::Multiply => Ok(RuntimeValue::Float(l_float * r)),
-                    BinaryOperator::Divide => {
-                        if *r == 0.0 {
-                            Err("Division by zero".into())
-                        } else {
-                            Ok(RuntimeValue::Float(l_float / r))
-                        }
-                    }
+                    BinaryOperator::Divide => self.divide_floats(l_float, *r),
+                    BinaryOperator::Mod => self.remainder_floats(l_float, *r),
                     BinaryOperator::Eq => Ok(RuntimeValue::Boolean(l_float == *r)),
                     BinaryOperator::Neq => Ok(RuntimeValue::Boolean(l_float != *r)),
                     BinaryOperator::Lt => Ok(RuntimeValue::Boolean(l_float < *r)),
@@ -574,6 +2592,7 @@ impl Interpreter {
                     BinaryOperator::And | BinaryOperator::Or => {
                         Err("Logical operations not supported for mixed types".into())
                     }
+                    _ => Err("Bitwise operations not supported for mixed types".into()),
                 }
             }
             (RuntimeValue::Float(l), op, RuntimeValue::Integer(r)) => {
@@ -583,13 +2602,8 @@ impl Interpreter {
                     BinaryOperator::Add => Ok(RuntimeValue::Float(l + r_float)),
                     BinaryOperator::Subtract => Ok(RuntimeValue::Float(l - r_float)),
                     BinaryOperator::Multiply => Ok(RuntimeValue::Float(l * r_float)),
-                    BinaryOperator::Divide => {
-                        if r_float == 0.0 {
-                            Err("Division by zero".into())
-                        } else {
-                            Ok(RuntimeValue::Float(l / r_float))
-                        }
-                    }
+                    BinaryOperator::Divide => self.divide_floats(*l, r_float),
+                    BinaryOperator::Mod => self.remainder_floats(*l, r_float),
                     BinaryOperator::Eq => Ok(RuntimeValue::Boolean(*l == r_float)),
                     BinaryOperator::Neq => Ok(RuntimeValue::Boolean(*l != r_float)),
                     BinaryOperator::Lt => Ok(RuntimeValue::Boolean(*l < r_float)),
@@ -599,6 +2613,22 @@ impl Interpreter {
                     BinaryOperator::And | BinaryOperator::Or => {
                         Err("Logical operations not supported for mixed types".into())
                     }
+                    _ => Err("Bitwise operations not supported for mixed types".into()),
+                }
+            }
+            (RuntimeValue::String(l), BinaryOperator::Eq, RuntimeValue::String(r)) => Ok(RuntimeValue::Boolean(l == r)),
+            (RuntimeValue::String(l), BinaryOperator::Neq, RuntimeValue::String(r)) => Ok(RuntimeValue::Boolean(l != r)),
+            // Склейка: второй операнд (число или bool) выводится так же, как в print
+            (RuntimeValue::String(_), BinaryOperator::Add, _) | (_, BinaryOperator::Add, RuntimeValue::String(_)) => {
+                Ok(RuntimeValue::String(Rc::from(format!("{}{}", left, right))))
+            }
+            // Сравниваются только перечисления без данных (проверено при проверке типов)
+            (RuntimeValue::Enum(l_name, l, _), op, RuntimeValue::Enum(r_name, r, _)) => {
+                let same = l_name == r_name && l == r;
+                match op {
+                    BinaryOperator::Eq => Ok(RuntimeValue::Boolean(same)),
+                    BinaryOperator::Neq => Ok(RuntimeValue::Boolean(!same)),
+                    _ => Err("Enum values only support == and !=".into()),
                 }
             }
             _ => {
@@ -608,10 +2638,73 @@ impl Interpreter {
         }
     }
 
+    fn divide_floats(&self, l: f32, r: f32) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        // В строгом режиме деление на ноль даёт inf/NaN, как в WASM и LLVM
+        if r == 0.0 && !self.strict_fp {
+            Err("Division by zero".into())
+        } else {
+            Ok(RuntimeValue::Float(l / r))
+        }
+    }
+
+    fn remainder_floats(&self, l: f32, r: f32) -> Result<RuntimeValue, Box<dyn std::error::Error>> {
+        if r == 0.0 && !self.strict_fp {
+            Err("Division by zero".into())
+        } else {
+            Ok(RuntimeValue::Float(l % r))
+        }
+    }
+
+    /// Каналы r, g, b структуры Color, ограниченные диапазоном 0..=255
+    fn color_channels(value: &RuntimeValue) -> Result<[u8; 3], Box<dyn std::error::Error>> {
+        let mut rgb = [0u8; 3];
+        for (channel, field) in rgb.iter_mut().zip(["r", "g", "b"]) {
+            match value.get_field(field) {
+                Some(RuntimeValue::Integer(v)) => *channel = v.clamp(0, 255) as u8,
+                _ => return Err(format!("expected a Color with i32 field {}", field).into()),
+            }
+        }
+        Ok(rgb)
+    }
+
+    /// Блок (тело if, цикла, ветка match) открывает область видимости
+    fn enter_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    /// Закрывает блок: его переменные исчезают, перекрытые ими внешние
+    /// возвращаются, а присваивания внешним переменным сохраняются
+    fn exit_scope(&mut self) {
+        let scope = self.scopes.pop().expect("open scope");
+        for (name, shadowed) in scope.into_iter().rev() {
+            match shadowed {
+                Some(value) => self.variables.insert(name, value),
+                None => self.variables.remove(&name),
+            };
+        }
+    }
+
+    /// `let` в текущем блоке; в теле функции вне блоков - просто переменная
+    fn declare(&mut self, name: &str, value: RuntimeValue) {
+        let shadowed = self.variables.insert(name.to_string(), value);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.to_string(), shadowed));
+        }
+    }
+
+    /// Закрывает области видимости снятого кадра сопрограммы
+    fn close_frame(&mut self, frame: &CoroutineFrame) {
+        let iteration = usize::from(frame.looping.is_some());
+        for _ in 0..frame.scopes + iteration {
+            self.exit_scope();
+        }
+    }
+
     fn is_truthy(&self, value: &RuntimeValue) -> bool {
         match value {
             RuntimeValue::Boolean(b) => *b,
             RuntimeValue::Integer(i) => *i != 0,
+            RuntimeValue::Long(i) => *i != 0,
             RuntimeValue::Float(f) => *f != 0.0,
             _ => false,
         }
@@ -1,4 +1,6 @@
-use logos::Logos;
+use logos::{FilterResult, Logos};
+use crate::ast::Type;
+use crate::edition::Edition;
 
 #[derive(Logos, Debug, PartialEq, Clone)]
 pub enum Token {
@@ -63,10 +65,51 @@ pub enum Token {
     #[token("void")]
     KeywordVoid,
 
-    // Идентификаторы
-    #[regex("[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
+    // Зарезервированы начиная с редакции 2024
+    #[token("match")]
+    KeywordMatch,
+
+    #[token("enum")]
+    KeywordEnum,
+
+    #[token("loop")]
+    KeywordLoop,
+
+    #[token("const")]
+    KeywordConst,
+
+    #[token("static")]
+    KeywordStatic,
+
+    #[token("impl")]
+    KeywordImpl,
+
+    #[token("import")]
+    KeywordImport,
+
+    #[token("break")]
+    KeywordBreak,
+
+    #[token("continue")]
+    KeywordContinue,
+
+    #[token("pub")]
+    KeywordPub,
+
+    #[token("defer")]
+    KeywordDefer,
+
+    #[token("trait")]
+    KeywordTrait,
+
+    // Идентификаторы по Unicode (UAX #31): `счёт`, `größe`, `_x1`
+    #[regex(r"[\p{XID_Start}_]\p{XID_Continue}*", |lex| lex.slice().to_string())]
     Identifier(String),
 
+    // Метка цикла `'outer` (без апострофа)
+    #[regex(r"'[\p{XID_Start}_]\p{XID_Continue}*", |lex| lex.slice()[1..].to_string())]
+    Label(String),
+
     // Литералы
     #[regex("[0-9]+", |lex| lex.slice().parse().ok())]
     IntegerLiteral(i32),
@@ -74,9 +117,40 @@ pub enum Token {
     #[regex("[0-9]+\\.[0-9]+", |lex| lex.slice().parse().ok())]
     FloatLiteral(f32),
     
-    #[regex(r#""[^"]*""#, |lex| lex.slice()[1..lex.slice().len()-1].to_string())]
+    // Целое с суффиксом типа: `5i64`, `7i32`. Значение в пределах типа
+    // суффикса, поэтому у i64 оно может не поместиться в i32
+    #[regex("[0-9]+(i32|i64)", |lex| suffixed_integer(lex.slice()))]
+    SuffixedInteger((i64, Type)),
+    
+    // Дробное с суффиксом: `2f32`, `1.0f64`
+    #[regex("[0-9]+(f32|f64)", |lex| suffixed_float(lex.slice()))]
+    #[regex("[0-9]+\\.[0-9]+(f32|f64)", |lex| suffixed_float(lex.slice()))]
+    SuffixedFloat((f64, Type)),
+    
+    // Строки могут занимать несколько строк исходника; `\n`, `\t`, `\"`,
+    // `\\` и т.п. как в символах. В сырых строках r"..." и r#"..."# обратная
+    // косая черта - обычный символ, а в r#"..."# можно писать кавычки
+    #[regex(r#""([^"\\]|\\.)*""#, |lex| unescape(&lex.slice()[1..lex.slice().len()-1]))]
+    #[regex(r#"r"[^"]*""#, |lex| lex.slice()[2..lex.slice().len()-1].to_string())]
+    #[token("r#\"", raw_string)]
     StringLiteral(String),
 
+    // f"score: {score}" - парсер превращает в склейку строк
+    #[regex(r#"f"([^"\\]|\\.)*""#, |lex| unescape(&lex.slice()[2..lex.slice().len()-1]))]
+    FormatString(String),
+
+    // Символ `'w'` или `'\n'` - его код как i32 (is_key_pressed('w')).
+    // Второе правило нужно, чтобы `'ж'` не разбиралось как метка `'ж`
+    #[regex(r"'([^'\\\n]|\\.)'", |lex| char_literal(lex.slice()))]
+    #[regex(r"'\p{XID_Continue}'", |lex| char_literal(lex.slice()), priority = 6)]
+    CharLiteral(i32),
+
+    // Цвет `#ff00aa` - каналы r, g, b; парсер превращает в литерал Color.
+    // Правило захватывает всё слово, чтобы `#ff00aag` было ошибкой, а не
+    // цветом и идентификатором
+    #[regex(r"#[0-9A-Za-z_]*", |lex| color_literal(lex.slice()))]
+    ColorLiteral([u8; 3]),
+
     // Операторы
     #[token("+")]
     OperatorAdd,
@@ -95,6 +169,22 @@ pub enum Token {
     
     #[token("=")]
     OperatorAssign,
+
+    // Составное присваивание `x += 1` разворачивается парсером в `x = x + 1`
+    #[token("+=")]
+    OperatorAddAssign,
+
+    #[token("-=")]
+    OperatorSubtractAssign,
+
+    #[token("*=")]
+    OperatorMultiplyAssign,
+
+    #[token("/=")]
+    OperatorDivideAssign,
+
+    #[token("%=")]
+    OperatorModuloAssign,
     
     #[token("==")]
     OperatorEq,
@@ -123,12 +213,34 @@ pub enum Token {
     #[token("||")]
     OperatorOr,
 
+    // Параметры замыкания (`|x: i32|`) и побитовое ИЛИ
+    #[token("|")]
+    Pipe,
+
+    #[token("&")]
+    OperatorBitAnd,
+
+    #[token("^")]
+    OperatorBitXor,
+
+    #[token("<<")]
+    OperatorShl,
+
+    #[token(">>")]
+    OperatorShr,
+
     #[token("?")]
     Question,
 
     #[token(":")]
     Colon,
 
+    #[token("::")]
+    ColonColon,
+
+    #[token("=>")]
+    FatArrow,
+
     // Разделители
     #[token("(")]
     ParenOpen,
@@ -156,25 +268,189 @@ pub enum Token {
     
     #[token(".")]
     Dot,
+
+    #[token("..")]
+    DotDot,
     
     #[token("->")]
     Arrow,
 
+    #[token("@")]
+    At,
+
     // Комментарии и пробелы (игнорируются)
     #[regex(r"//[^\n]*", logos::skip)]
     #[regex(r"[ \t\n\f]+", logos::skip)]
     Error,
+
+    // `/* ... */` пропускается целиком; токен остаётся только от
+    // комментария, который не закрыт до конца файла
+    #[token("/*", block_comment)]
+    UnterminatedComment,
+
+    // Символ, с которого не начинается ни один токен (например, эмодзи
+    // вне строки); поток токенов на нём не обрывается
+    Invalid(String),
+}
+
+/// Пропускает блочный комментарий; вложенные `/* */` должны быть закрыты,
+/// чтобы закомментировать кусок кода, в котором уже есть комментарий
+fn block_comment(lex: &mut logos::Lexer<Token>) -> FilterResult<(), ()> {
+    let rest = lex.remainder();
+    let mut depth = 1;
+    let mut index = 0;
+    while index < rest.len() {
+        if rest[index..].starts_with("/*") {
+            depth += 1;
+            index += 2;
+        } else if rest[index..].starts_with("*/") {
+            depth -= 1;
+            index += 2;
+            if depth == 0 {
+                lex.bump(index);
+                return FilterResult::Skip;
+            }
+        } else {
+            index += rest[index..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    lex.bump(rest.len());
+    FilterResult::Emit(())
+}
+
+/// Символ escape-последовательности `\c` в символах и строках
+fn escaped_char(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' | '\'' | '"' => Some(c),
+        _ => None,
+    }
+}
+
+/// Сырая строка r#"..."# до первого `"#`; незакрытая - ошибка
+fn raw_string(lex: &mut logos::Lexer<Token>) -> Option<String> {
+    let end = lex.remainder().find("\"#")?;
+    let text = lex.remainder()[..end].to_string();
+    lex.bump(end + 2);
+    Some(text)
+}
+
+/// Код символа из литерала вместе с апострофами; неизвестная escape-последовательность - None
+fn char_literal(slice: &str) -> Option<i32> {
+    let inner = &slice[1..slice.len() - 1];
+    let ch = match inner.strip_prefix('\\') {
+        Some(escape) => escaped_char(escape.chars().next()?)?,
+        None => inner.chars().next()?,
+    };
+    Some(ch as i32)
+}
+
+/// Значение и тип числа с суффиксом; целое вне диапазона i32 - None
+fn suffixed_integer(slice: &str) -> Option<(i64, Type)> {
+    let (digits, suffix) = slice.split_at(slice.len() - 3);
+    match suffix {
+        "i32" => Some((i64::from(digits.parse::<i32>().ok()?), Type::I32)),
+        _ => Some((digits.parse().ok()?, Type::I64)),
+    }
+}
+
+fn suffixed_float(slice: &str) -> Option<(f64, Type)> {
+    let (digits, suffix) = slice.split_at(slice.len() - 3);
+    let ty = if suffix == "f32" { Type::F32 } else { Type::F64 };
+    Some((digits.parse().ok()?, ty))
+}
+
+/// Каналы цвета `#RRGGBB`; другое число цифр или не шестнадцатеричная цифра - None
+fn color_literal(slice: &str) -> Option<[u8; 3]> {
+    let digits = &slice[1..];
+    if digits.len() != 6 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Содержимое строки между кавычками; неизвестная escape-последовательность - None
+fn unescape(body: &str) -> Option<String> {
+    let mut text = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            text.push(escaped_char(chars.next()?)?);
+        } else {
+            text.push(c);
+        }
+    }
+    Some(text)
+}
+
+impl Token {
+    /// Редакция, с которой ключевое слово зарезервировано
+    pub fn reserved_since(&self) -> Edition {
+        match self {
+            Token::KeywordMatch |
+            Token::KeywordEnum |
+            Token::KeywordLoop |
+            Token::KeywordConst |
+            Token::KeywordStatic |
+            Token::KeywordImpl |
+            Token::KeywordImport |
+            Token::KeywordBreak |
+            Token::KeywordContinue |
+            Token::KeywordPub |
+            Token::KeywordDefer |
+            Token::KeywordTrait => Edition::E2024,
+            _ => Edition::E2023,
+        }
+    }
 }
 
 pub struct Lexer<'a> {
     inner: logos::Lexer<'a, Token>,
+    edition: Edition,
+    // Номер строки (с 1) для позиции `line_offset` в исходнике
+    line: usize,
+    line_offset: usize,
+    // Байтовое смещение начала этой строки
+    line_start: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::with_edition(input, Edition::default())
+    }
+
+    pub fn with_edition(input: &'a str, edition: Edition) -> Self {
         Self {
             inner: Token::lexer(input),
+            edition,
+            line: 1,
+            line_offset: 0,
+            line_start: 0,
+        }
+    }
+
+    /// Следующий токен вместе с номером строки, на которой он начинается
+    pub fn next_with_line(&mut self) -> Option<(Token, usize)> {
+        let token = self.next()?;
+        let start = self.inner.span().start;
+        let source = self.inner.source();
+        let skipped = &source[self.line_offset..start];
+        self.line += skipped.matches('\n').count();
+        if let Some(newline) = skipped.rfind('\n') {
+            self.line_start = self.line_offset + newline + 1;
         }
+        self.line_offset = start;
+        Some((token, self.line))
+    }
+
+    /// Колонка (с 1) последнего токена из `next_with_line` в символах,
+    /// а не в байтах: `счёт` занимает четыре колонки
+    pub fn column(&self) -> usize {
+        self.inner.source()[self.line_start..self.line_offset].chars().count() + 1
     }
 }
 
@@ -182,7 +458,14 @@ impl<'a> Iterator for Lexer<'a> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let token = self.inner.next().and_then(Result::ok);
+        let token = self.inner.next().map(|token| match token {
+            // В старых редакциях новые ключевые слова остаются идентификаторами
+            Ok(token) if token.reserved_since() > self.edition => {
+                Token::Identifier(self.inner.slice().to_string())
+            }
+            Ok(token) => token,
+            Err(()) => Token::Invalid(self.inner.slice().to_string()),
+        });
         println!("DEBUG LEXER: {:?}", token);
         token
     }
@@ -1,4 +1,5 @@
 pub mod ast;
+pub mod edition;
 pub mod lexer;
 pub mod parser;
 pub mod typecheck;
@@ -7,4 +8,32 @@ pub mod stdlib;
 pub mod optimize;
 pub mod graphics_engine;
 pub mod interpreter;
-pub mod ide;
\ No newline at end of file
+pub mod ide;
+pub mod examples;
+pub mod scaffold;
+pub mod visit;
+pub mod lint;
+pub mod doctest;
+pub mod check;
+pub mod fp;
+pub mod font;
+pub mod timings;
+pub mod storage;
+pub mod color;
+pub mod animation;
+pub mod assets;
+pub mod embed;
+pub mod strings;
+pub mod determinism;
+pub mod modules;
+pub mod source;
+pub mod crash;
+pub mod stats;
+pub mod index;
+pub mod capabilities;
+pub mod derive;
+pub mod generics;
+pub mod const_eval;
+pub mod ranges;
+pub mod workspace;
+pub mod registry;
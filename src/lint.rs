@@ -0,0 +1,374 @@
+// lint.rs - предупреждения статического анализа (не ошибки компиляции)
+
+use crate::ast::*;
+use crate::visit::{self, Visitor};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    pub function: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "warning in fn {}: {}", self.function, self.message)
+    }
+}
+
+/// Встроенные функции, которые отдают управление окну (кадр, пауза)
+const YIELDING_BUILTINS: &[&str] = &["render", "sleep", "delay", "yield_frame"];
+
+/// Графические функции, по которым программа считается графической
+const GRAPHICS_BUILTINS: &[&str] = &[
+    "init_graphics", "clear_screen", "draw_pixel", "draw_rect",
+    "draw_circle", "draw_line", "render",
+];
+
+pub fn lint_program(program: &Program) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    warnings.extend(check_render_loops(program));
+    for function in &program.functions {
+        warnings.extend(check_unused_variables(function));
+    }
+    warnings.extend(check_unreachable_functions(program));
+    warnings
+}
+
+/// Находит вызовы функций внутри поддерева
+#[derive(Default)]
+struct CallCollector {
+    calls: HashSet<String>,
+    // Имена методов: тип получателя до проверки типов неизвестен
+    methods: HashSet<String>,
+}
+
+impl Visitor for CallCollector {
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::FunctionCall { name, .. } => {
+                self.calls.insert(name.clone());
+            }
+            Expression::EnumVariant { enum_name, variant, .. } => {
+                self.calls.insert(method_name(enum_name, variant));
+            }
+            Expression::MethodCall { method, .. } => {
+                self.methods.insert(method.clone());
+            }
+            _ => {}
+        }
+        visit::walk_expression(self, expression);
+    }
+}
+
+/// Собирает самые внешние циклы кадров функции: `loop`, `while true` и
+/// `while !window_should_close()`. Циклы с границей (`for`, `while i < n`)
+/// заканчиваются сами, поэтому ищем циклы кадров и внутри них
+#[derive(Default)]
+struct FrameLoopCollector<'a> {
+    loops: Vec<&'a [Statement]>,
+}
+
+impl<'a> FrameLoopCollector<'a> {
+    fn collect(&mut self, statements: &'a [Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Loop { body, .. } => self.loops.push(body),
+                Statement::While { condition, body, .. } if is_frame_condition(condition) => self.loops.push(body),
+                Statement::While { body, .. } | Statement::For { body, .. } => self.collect(body),
+                Statement::Block { statements, .. } => self.collect(statements),
+                Statement::If { then_branch, else_branch, .. } => {
+                    self.collect(then_branch);
+                    if let Some(else_branch) = else_branch {
+                        self.collect(else_branch);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Условие цикла, который крутится до закрытия окна
+fn is_frame_condition(condition: &Expression) -> bool {
+    if let Expression::BoolLiteral(true) = condition {
+        return true;
+    }
+    let mut collector = CallCollector::default();
+    collector.visit_expression(condition);
+    collector.calls.contains("window_should_close")
+}
+
+/// Вызовы в операторах; x.method() считается вызовом method любого impl-блока
+fn calls_in(statements: &[Statement], program: &Program) -> HashSet<String> {
+    let mut collector = CallCollector::default();
+    for statement in statements {
+        collector.visit_statement(statement);
+    }
+    let mut calls = collector.calls;
+    calls.extend(program.functions.iter()
+        .filter(|function| split_method_name(&function.name)
+            .is_some_and(|(_, method)| collector.methods.contains(method)))
+        .map(|function| function.name.clone()));
+    calls
+}
+
+/// Замыкание множества вызовов через пользовательские функции
+fn transitive_calls(
+    direct: HashSet<String>,
+    call_graph: &HashMap<String, HashSet<String>>,
+) -> HashSet<String> {
+    let mut result = direct;
+    let mut queue: Vec<String> = result.iter().cloned().collect();
+    while let Some(name) = queue.pop() {
+        if let Some(callees) = call_graph.get(&name) {
+            for callee in callees {
+                if result.insert(callee.clone()) {
+                    queue.push(callee.clone());
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Цикл в графической программе, который никогда не вызывает render/sleep/delay,
+/// замораживает окно: типичная ошибка начинающих.
+fn check_render_loops(program: &Program) -> Vec<LintWarning> {
+    let call_graph: HashMap<String, HashSet<String>> = program.functions.iter()
+        .map(|f| (f.name.clone(), calls_in(&f.body, program)))
+        .collect();
+
+    let all_calls: HashSet<String> = call_graph.values().flatten().cloned().collect();
+    if !GRAPHICS_BUILTINS.iter().any(|name| all_calls.contains(*name)) {
+        return Vec::new();
+    }
+
+    // Функции, вызываемые из цикла кадров, уже работают внутри кадра
+    let mut called_in_loop = HashSet::new();
+    for function in &program.functions {
+        let mut collector = FrameLoopCollector::default();
+        collector.collect(&function.body);
+        for body in collector.loops {
+            called_in_loop.extend(transitive_calls(calls_in(body, program), &call_graph));
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for function in &program.functions {
+        if called_in_loop.contains(&function.name) {
+            continue;
+        }
+
+        let mut collector = FrameLoopCollector::default();
+        collector.collect(&function.body);
+        for body in collector.loops {
+            let calls = transitive_calls(calls_in(body, program), &call_graph);
+            if !YIELDING_BUILTINS.iter().any(|name| calls.contains(*name)) {
+                warnings.push(LintWarning {
+                    function: function.name.clone(),
+                    message: "while loop never calls render(), sleep(), delay() or yield_frame(); \
+                              the graphics window will freeze".to_string(),
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Объявленная переменная или параметр; `_x` и `self` не проверяются
+struct Binding {
+    name: String,
+    kind: &'static str,
+    used: bool,
+}
+
+/// Ищет переменные, которые ни разу не читаются. Области видимости - блоки,
+/// как при проверке типов: одноимённые переменные соседних блоков различаются
+#[derive(Default)]
+struct UnusedCollector {
+    bindings: Vec<Binding>,
+    // Индексы bindings, объявленных в каждом открытом блоке
+    scopes: Vec<Vec<usize>>,
+}
+
+impl UnusedCollector {
+    fn declare(&mut self, name: &str, kind: &'static str, used: bool) {
+        let used = used || name.starts_with('_') || name == "self";
+        self.bindings.push(Binding { name: name.to_string(), kind, used });
+        let index = self.bindings.len() - 1;
+        self.scopes.last_mut().expect("open scope").push(index);
+    }
+
+    /// Чтение имени относится к ближайшему объявлению
+    fn mark_used(&mut self, name: &str) {
+        let found = self.scopes.iter().rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|&&index| self.bindings[index].name == name)
+            .copied();
+        if let Some(index) = found {
+            self.bindings[index].used = true;
+        }
+    }
+
+    fn visit_block(&mut self, statements: &[Statement]) {
+        self.scopes.push(Vec::new());
+        for statement in statements {
+            self.visit_statement(statement);
+        }
+        self.scopes.pop();
+    }
+}
+
+impl Visitor for UnusedCollector {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                self.visit_expression(value);
+                self.declare(name, "variable", false);
+            }
+            Statement::DestructureStruct { bindings, value, .. } => {
+                self.visit_expression(value);
+                for (_, name) in bindings {
+                    self.declare(name, "variable", false);
+                }
+            }
+            // Присваивание не читает переменную: `x = 1` без чтения x бесполезно
+            Statement::Assignment { value, .. } => self.visit_expression(value),
+            Statement::FieldAssignment { name, .. } | Statement::IndexAssignment { name, .. } => {
+                self.mark_used(name);
+                visit::walk_statement(self, statement);
+            }
+            Statement::Block { statements, .. } => self.visit_block(statements),
+            Statement::Loop { body, .. } => self.visit_block(body),
+            Statement::While { condition, body, .. } => {
+                self.visit_expression(condition);
+                self.visit_block(body);
+            }
+            Statement::For { variable, start, end, body, .. } => {
+                self.visit_expression(start);
+                self.visit_expression(end);
+                self.scopes.push(Vec::new());
+                self.declare(variable, "loop variable", true);
+                self.visit_block(body);
+                self.scopes.pop();
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.visit_expression(condition);
+                self.visit_block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.visit_block(else_branch);
+                }
+            }
+            _ => visit::walk_statement(self, statement),
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Variable(name) => self.mark_used(name),
+            // Вызов переменной-замыкания
+            Expression::FunctionCall { name, .. } => {
+                self.mark_used(name);
+                visit::walk_expression(self, expression);
+            }
+            // Параметры замыкания и поля образца закрывают внешние имена,
+            // но сами не проверяются: обработчику не обязательно нужны все аргументы
+            Expression::Lambda { params, body, .. } => {
+                self.scopes.push(Vec::new());
+                for param in params {
+                    self.declare(&param.name, "parameter", true);
+                }
+                self.visit_block(body);
+                self.scopes.pop();
+            }
+            Expression::Match { subject, arms } => {
+                self.visit_expression(subject);
+                for arm in arms {
+                    self.scopes.push(Vec::new());
+                    if let Pattern::Variant { bindings, .. } = &arm.pattern {
+                        for name in bindings {
+                            self.declare(name, "binding", true);
+                        }
+                    }
+                    self.visit_expression(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            _ => visit::walk_expression(self, expression),
+        }
+    }
+}
+
+/// Неиспользуемые `let` и параметры функции
+fn check_unused_variables(function: &Function) -> Vec<LintWarning> {
+    let mut collector = UnusedCollector::default();
+    collector.scopes.push(Vec::new());
+    for param in &function.params {
+        collector.declare(&param.name, "parameter", false);
+    }
+    visit::walk_function(&mut collector, function);
+
+    collector.bindings.iter()
+        .filter(|binding| !binding.used)
+        .map(|binding| LintWarning {
+            function: function.name.clone(),
+            message: format!("unused {} `{}`; prefix it with an underscore if this is intended",
+                             binding.kind, binding.name),
+        })
+        .collect()
+}
+
+/// Имена функций, на которые ссылается поддерево: вызовы, функции-значения
+/// (`map(values, double)`) и имена в строках (`spawn("worker")`)
+#[derive(Default)]
+struct ReferenceCollector {
+    names: HashSet<String>,
+}
+
+impl Visitor for ReferenceCollector {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Variable(name) | Expression::StringLiteral(name) = expression {
+            self.names.insert(name.clone());
+        }
+        visit::walk_expression(self, expression);
+    }
+}
+
+/// Функции, до которых не доходит ни один путь вызовов из main. Программы
+/// без main (библиотеки), `pub` и экспортируемые функции не проверяются
+fn check_unreachable_functions(program: &Program) -> Vec<LintWarning> {
+    if !program.functions.iter().any(|function| function.name == "main") {
+        return Vec::new();
+    }
+
+    let call_graph: HashMap<String, HashSet<String>> = program.functions.iter()
+        .map(|function| {
+            let mut references = ReferenceCollector::default();
+            references.visit_function(function);
+            let mut callees = calls_in(&function.body, program);
+            callees.extend(references.names);
+            (function.name.clone(), callees)
+        })
+        .collect();
+
+    // Методы реализаций трейтов вызываются через dyn, их имена в коде не видны
+    let mut roots: HashSet<String> = program.trait_impls.iter()
+        .flat_map(|trait_impl| trait_impl.methods.iter()
+            .map(|method| method_name(&trait_impl.type_name, method)))
+        .collect();
+    roots.extend(program.functions.iter()
+        .filter(|function| function.name == "main" || function.is_public || function.is_exported())
+        .map(|function| function.name.clone()));
+    let reachable = transitive_calls(roots, &call_graph);
+
+    program.functions.iter()
+        .filter(|function| !reachable.contains(&function.name))
+        .map(|function| LintWarning {
+            function: function.name.clone(),
+            message: "function is never called from main".to_string(),
+        })
+        .collect()
+}
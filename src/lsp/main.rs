@@ -121,7 +121,7 @@ impl AetosREPL {
     }
 
     fn run_file(&self, filename: &str) -> Result<String, Box<dyn Error>> {
-        let source = std::fs::read_to_string(filename)?;
+        let source = aetos::source::read_to_string(filename)?;
         let mut parser = Parser::new(&source);
         let program = parser.parse_program()?;
         
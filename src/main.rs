@@ -1,8 +1,8 @@
 use clap::{Arg, Command};
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 mod ast;
+mod edition;
 mod lexer;
 mod parser;
 mod typecheck;
@@ -12,94 +12,358 @@ mod optimize;
 mod graphics_engine;
 mod interpreter;
 mod ide;
+mod examples;
+mod scaffold;
+mod visit;
+mod lint;
+mod doctest;
+mod check;
+mod fp;
+mod font;
+mod timings;
+mod storage;
+mod color;
+mod animation;
+mod assets;
+mod strings;
+mod determinism;
+mod modules;
+mod source;
+mod crash;
+mod stats;
+mod index;
+mod capabilities;
+mod derive;
+mod generics;
+mod const_eval;
+mod ranges;
+mod workspace;
+mod registry;
 
+use edition::Edition;
 use interpreter::Interpreter;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
+    // Ошибка печатается через Display, по строке на каждую ошибку
+    if let Err(e) = run_cli() {
+        for line in e.to_string().lines() {
+            eprintln!("Error: {}", line);
+        }
+        std::process::exit(1);
+    }
+}
+
+fn run_cli() -> Result<(), Box<dyn std::error::Error>> {
     let matches = Command::new("aetosc")
         .version("0.3.0")
         .about("Aetos Language Compiler")
         .subcommand(
             Command::new("graphics")
                 .about("Run graphics program in native window")
+                .args(run_args())
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Run Aetos program")
+                .args(run_args())
+        )
+        .subcommand(
+            Command::new("compile")
+                .about("Compile Aetos program")
                 .arg(
                     Arg::new("input")
                         .required(true)
                         .help("Input source file"),
                 )
                 .arg(
-                    Arg::new("width")
-                        .long("width")
-                        .short('W')
-                        .default_value("800")
-                        .help("Window width"),
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .help("Output file"),
+                )
+                .arg(
+                    Arg::new("timings")
+                        .long("timings")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print time spent in each compiler phase"),
+                )
+                .arg(
+                    Arg::new("stats-out")
+                        .long("stats-out")
+                        .value_name("FILE")
+                        .help("Write language feature and builtin usage as JSON (relative to the output directory)"),
+                )
+                .arg(
+                    Arg::new("crate-type")
+                        .long("crate-type")
+                        .value_parser(["bin", "lib"])
+                        .default_value("bin")
+                        .help("Build a program or a library exposing @export functions"),
                 )
                 .arg(
-                    Arg::new("height")
-                        .long("height")
-                        .short('H')
-                        .default_value("600")
-                        .help("Window height"),
+                    Arg::new("backend")
+                        .long("backend")
+                        .value_parser(codegen::Backend::NAMES)
+                        .default_value("wasm")
+                        .help("Code generator: wasm, llvm or embedded"),
+                )
+                .arg(
+                    Arg::new("lto")
+                        .long("lto")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Optimize the whole program before emitting code (llvm and embedded backends)"),
                 )
         )
         .subcommand(
-            Command::new("run")
-                .about("Run Aetos program")
+            Command::new("build")
+                .about("Build every package of a workspace into its target directory")
                 .arg(
-                    Arg::new("input")
+                    Arg::new("dir")
+                        .default_value(".")
+                        .help("Workspace or package directory"),
+                )
+                .arg(
+                    Arg::new("backend")
+                        .long("backend")
+                        .value_parser(codegen::Backend::NAMES)
+                        .default_value("wasm")
+                        .help("Code generator: wasm, llvm or embedded"),
+                )
+                .arg(
+                    Arg::new("timings")
+                        .long("timings")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Print time spent in each compiler phase"),
+                )
+        )
+        .subcommand(
+            Command::new("add")
+                .about("Install a library from the registry and add it to aetos.toml")
+                .arg(
+                    Arg::new("package")
                         .required(true)
-                        .help("Input source file"),
+                        .help("Package name, optionally with a version requirement (ui_kit@1.2)"),
+                )
+                .arg(
+                    Arg::new("dir")
+                        .long("dir")
+                        .default_value(".")
+                        .help("Package directory"),
+                )
+                .arg(
+                    Arg::new("registry")
+                        .long("registry")
+                        .value_name("URL")
+                        .help("Registry URL or directory (default: $AETOS_REGISTRY or [registry] in aetos.toml)"),
                 )
         )
         .subcommand(
-            Command::new("compile")
-                .about("Compile Aetos program")
+            Command::new("publish")
+                .about("Publish a package to the registry")
+                .arg(
+                    Arg::new("dir")
+                        .default_value(".")
+                        .help("Package directory"),
+                )
+                .arg(
+                    Arg::new("registry")
+                        .long("registry")
+                        .value_name("URL")
+                        .help("Registry URL or directory (default: $AETOS_REGISTRY or [registry] in aetos.toml)"),
+                )
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Run examples from doc comments as tests")
                 .arg(
                     Arg::new("input")
                         .required(true)
                         .help("Input source file"),
                 )
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Create a new Aetos project")
+                .arg(
+                    Arg::new("name")
+                        .required(true)
+                        .help("Project directory"),
+                )
                 .arg(
-                    Arg::new("output")
-                        .long("output")
-                        .short('o')
-                        .help("Output file"),
+                    Arg::new("template")
+                        .long("template")
+                        .short('t')
+                        .default_value("console")
+                        .value_parser(scaffold::Template::NAMES)
+                        .help("Project template: console, game or embedded"),
+                )
+        )
+        .subcommand(
+            Command::new("examples")
+                .about("Browse and run bundled example programs")
+                .subcommand(
+                    Command::new("list")
+                        .about("List bundled examples")
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Print the source of an example")
+                        .arg(
+                            Arg::new("name")
+                                .required(true)
+                                .help("Example name"),
+                        )
+                )
+                .subcommand(
+                    Command::new("run")
+                        .about("Run an example")
+                        .arg(
+                            Arg::new("name")
+                                .required(true)
+                                .help("Example name"),
+                        )
                 )
         )
         .subcommand(
             Command::new("ide")
                 .about("Start interactive development environment")
         )
+        .subcommand(
+            Command::new("symbols")
+                .about("Query the project symbol index (.aetos-index) for editors")
+                .subcommand(
+                    Command::new("definition")
+                        .about("Print the declaration of the symbol at a position")
+                        .args(position_args())
+                )
+                .subcommand(
+                    Command::new("references")
+                        .about("Print every use of the symbol at a position")
+                        .args(position_args())
+                )
+                .subcommand(
+                    Command::new("search")
+                        .about("Print project symbols whose name contains a query")
+                        .arg(
+                            Arg::new("query")
+                                .required(true)
+                                .help("Part of the symbol name, case-insensitive"),
+                        )
+                        .arg(
+                            Arg::new("dir")
+                                .long("dir")
+                                .default_value(".")
+                                .help("Project directory"),
+                        )
+                )
+        )
         .subcommand(
             Command::new("check")
                 .about("Check syntax and types without running")
                 .arg(
                     Arg::new("input")
                         .required(true)
-                        .help("Input source file"),
+                        .help("Input source file, directory or glob (e.g. src/*.aetos)"),
+                )
+                .arg(
+                    Arg::new("recursive")
+                        .long("recursive")
+                        .short('r')
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Check directories recursively"),
+                )
+                .arg(
+                    Arg::new("audit-determinism")
+                        .long("audit-determinism")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Report time, input, file, network, random and thread builtins reachable from @deterministic functions"),
+                )
+                .arg(
+                    Arg::new("deny-warnings")
+                        .long("deny-warnings")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Fail the check if there are any warnings (unused variables, unreachable functions, ...)"),
                 )
         )
         .get_matches();
 
     match matches.subcommand() {
-        Some(("graphics", sub_matches)) => {
-            let input_file = sub_matches.get_one::<String>("input").unwrap();
-            let width = sub_matches.get_one::<String>("width").unwrap().parse::<usize>()?;
-            let height = sub_matches.get_one::<String>("height").unwrap().parse::<usize>()?;
-            
-            run_aetos_program(input_file, width, height)
-        }
-        Some(("run", sub_matches)) => {
+        Some(("graphics" | "run", sub_matches)) => {
             let input_file = sub_matches.get_one::<String>("input").unwrap();
-            run_aetos_program(input_file, 800, 600)
+            run_aetos_program(input_file, &RunOptions::from_matches(sub_matches)?)
         }
         Some(("compile", sub_matches)) => {
             let input_file = sub_matches.get_one::<String>("input").unwrap();
-            compile_aetos_program(input_file, sub_matches.get_one::<String>("output"))
+            let crate_type = sub_matches.get_one::<String>("crate-type")
+                .and_then(|name| codegen::CrateType::parse(name))
+                .unwrap_or_default();
+            let backend = sub_matches.get_one::<String>("backend")
+                .and_then(|name| codegen::Backend::parse(name))
+                .unwrap_or_default();
+            compile_aetos_program(
+                input_file,
+                sub_matches.get_one::<String>("output"),
+                crate_type,
+                backend,
+                sub_matches.get_flag("lto"),
+                sub_matches.get_flag("timings"),
+                sub_matches.get_one::<String>("stats-out"),
+            )
+        }
+        Some(("build", sub_matches)) => {
+            let dir = sub_matches.get_one::<String>("dir").unwrap();
+            let backend = sub_matches.get_one::<String>("backend")
+                .and_then(|name| codegen::Backend::parse(name))
+                .unwrap_or_default();
+            build_workspace(Path::new(dir), backend, sub_matches.get_flag("timings"))
+        }
+        Some(("add", sub_matches)) => {
+            add_package(
+                sub_matches.get_one::<String>("package").unwrap(),
+                Path::new(sub_matches.get_one::<String>("dir").unwrap()),
+                sub_matches.get_one::<String>("registry").map(String::as_str),
+            )
+        }
+        Some(("publish", sub_matches)) => {
+            publish_package(
+                Path::new(sub_matches.get_one::<String>("dir").unwrap()),
+                sub_matches.get_one::<String>("registry").map(String::as_str),
+            )
         }
         Some(("check", sub_matches)) => {
+            let input = sub_matches.get_one::<String>("input").unwrap();
+            let recursive = sub_matches.get_flag("recursive");
+            let options = CheckOptions {
+                audit_determinism: sub_matches.get_flag("audit-determinism"),
+                deny_warnings: sub_matches.get_flag("deny-warnings"),
+            };
+            if Path::new(input).is_file() {
+                check_aetos_program(input, options)
+            } else if is_workspace_root(Path::new(input)) {
+                check_workspace(Path::new(input), options)
+            } else {
+                check_aetos_files(input, recursive, options)
+            }
+        }
+        Some(("test", sub_matches)) => {
             let input_file = sub_matches.get_one::<String>("input").unwrap();
-            check_aetos_program(input_file)
+            test_aetos_program(input_file)
+        }
+        Some(("new", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let template_name = sub_matches.get_one::<String>("template").unwrap();
+            let template = scaffold::Template::parse(template_name).unwrap();
+            
+            scaffold::create_project(Path::new(name), template)?;
+            println!("Created {} project in {}/", template_name, name);
+            println!("Run it with: aetosc run {}/main.aetos", name);
+            Ok(())
+        }
+        Some(("examples", sub_matches)) => {
+            run_examples_command(sub_matches)
+        }
+        Some(("symbols", sub_matches)) => {
+            run_symbols_command(sub_matches)
         }
         Some(("ide", _)) => {
             println!("Starting Aetos Interactive Development Environment...\n");
@@ -112,14 +376,205 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 }
 
-fn run_aetos_program(input_file: &str, width: usize, height: usize) -> Result<(), Box<dyn std::error::Error>> {
+fn parse_source(input_file: &str, source_code: &str) -> Result<ast::Program, Box<dyn std::error::Error>> {
+    let edition = Edition::detect(source_code, Path::new(input_file));
+    let mut parser = parser::Parser::with_edition(source_code, edition);
+    let program = parser.parse_program().inspect_err(|error| {
+        let Some((line, column)) = error.position() else {
+            return;
+        };
+        // Байт считается по файлу на диске: с BOM и `\r\n`, как в редакторе
+        let file = source::SourceFile::read(Path::new(input_file))
+            .unwrap_or_else(|_| source::SourceFile::new(source_code));
+        if let Some(snippet) = file.snippet(line, column) {
+            let byte = file.original_position(line, column)
+                .map_or(String::new(), |byte| format!(" (byte {})", byte));
+            eprintln!("{}:{}:{}{}\n{}", input_file, line, column, byte, snippet);
+        }
+    })?;
+    // Объявления импортированных файлов сливаются в программу
+    Ok(modules::link_imports(program, Path::new(input_file))?)
+}
+
+/// Каталог файла; для `main.aetos` без пути - текущий
+fn project_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Индекс символов для редактора; без него всё работает, поэтому ошибка
+/// записи - только предупреждение
+fn write_symbol_index(dir: &Path, files: &[PathBuf]) {
+    if let Err(e) = index::SymbolIndex::update(dir, files) {
+        eprintln!("Warning: cannot write symbol index in {}: {}", dir.display(), e);
+    }
+}
+
+/// Позиция в файле для запросов `symbols definition` и `symbols references`
+fn position_args() -> Vec<Arg> {
+    vec![
+        Arg::new("file")
+            .required(true)
+            .help("Source file"),
+        Arg::new("line")
+            .required(true)
+            .value_parser(clap::value_parser!(usize))
+            .help("Line, starting from 1"),
+        Arg::new("column")
+            .required(true)
+            .value_parser(clap::value_parser!(usize))
+            .help("Column, starting from 1"),
+    ]
+}
+
+/// Запросы редактора к индексу; файл запроса переиндексируется, если
+/// изменился, остальные берутся из `.aetos-index` как есть
+fn run_symbols_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let (name, sub_matches) = matches.subcommand()
+        .ok_or("Use `aetosc symbols definition|references|search`")?;
+    if name == "search" {
+        let dir = Path::new(sub_matches.get_one::<String>("dir").unwrap());
+        let index = index::SymbolIndex::load(dir);
+        for (location, symbol) in index.workspace_symbols(sub_matches.get_one::<String>("query").unwrap()) {
+            println!("{} {:?} {}", location, symbol.kind, symbol.name);
+        }
+        return Ok(());
+    }
+
+    let file = Path::new(sub_matches.get_one::<String>("file").unwrap());
+    let line = *sub_matches.get_one::<usize>("line").unwrap();
+    let column = *sub_matches.get_one::<usize>("column").unwrap();
+    let dir = project_dir(file);
+    index::SymbolIndex::update(&dir, &[file.to_path_buf()])?;
+    let index = index::SymbolIndex::load(&dir);
+    let relative = index::relative_path(&dir, file);
+    let locations = match name {
+        "definition" => index.definition_at(&relative, line, column).into_iter().collect(),
+        _ => index.references_at(&relative, line, column),
+    };
+    for location in locations {
+        println!("{}", location);
+    }
+    Ok(())
+}
+
+/// Аргументы `run` и `graphics`: обе команды запускают программу одинаково
+fn run_args() -> Vec<Arg> {
+    vec![
+        Arg::new("input")
+            .required(true)
+            .help("Input source file"),
+        Arg::new("width")
+            .long("width")
+            .short('W')
+            .default_value("800")
+            .help("Window width (init_graphics overrides it)"),
+        Arg::new("height")
+            .long("height")
+            .short('H')
+            .default_value("600")
+            .help("Window height (init_graphics overrides it)"),
+        Arg::new("title")
+            .long("title")
+            .help("Window title, the file name by default (init_graphics overrides it)"),
+        Arg::new("vsync")
+            .long("vsync")
+            .value_parser(["on", "off"])
+            .default_value("on")
+            .help("Limit the frame loop to ~60 FPS"),
+        Arg::new("strict-fp")
+            .long("strict-fp")
+            .action(clap::ArgAction::SetTrue)
+            .help("Deterministic IEEE f32 semantics across platforms"),
+        Arg::new("mem-report")
+            .long("mem-report")
+            .action(clap::ArgAction::SetTrue)
+            .help("Print interpreter memory statistics after the run"),
+        Arg::new("release")
+            .long("release")
+            .action(clap::ArgAction::SetTrue)
+            .help("Strip assert() and @requires/@ensures checks"),
+        Arg::new("fast-math")
+            .long("fast-math")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("strict-fp")
+            .help("Replace sin/cos with table-based fast_sin/fast_cos"),
+        Arg::new("contrast-warnings")
+            .long("contrast-warnings")
+            .action(clap::ArgAction::SetTrue)
+            .help("Warn when draw_text contrast is below WCAG AA (4.5:1)"),
+        Arg::new("sandbox")
+            .long("sandbox")
+            .action(clap::ArgAction::SetTrue)
+            .help("Deny graphics, files, time and threads: such builtins fail with a capability error"),
+    ]
+}
+
+/// Параметры запуска для `run` и `graphics`
+struct RunOptions {
+    width: usize,
+    height: usize,
+    title: Option<String>,
+    vsync: bool,
+    strict_fp: bool,
+    mem_report: bool,
+    release: bool,
+    fast_math: bool,
+    contrast_warnings: bool,
+    sandbox: bool,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            title: None,
+            vsync: true,
+            strict_fp: false,
+            mem_report: false,
+            release: false,
+            fast_math: false,
+            contrast_warnings: false,
+            sandbox: false,
+        }
+    }
+}
+
+impl RunOptions {
+    fn from_matches(matches: &clap::ArgMatches) -> Result<Self, Box<dyn std::error::Error>> {
+        let width = matches.get_one::<String>("width").unwrap().parse::<usize>()?;
+        let height = matches.get_one::<String>("height").unwrap().parse::<usize>()?;
+        if width == 0 || height == 0 {
+            return Err(format!("window size must be positive, got {}x{}", width, height).into());
+        }
+        Ok(Self {
+            width,
+            height,
+            title: matches.get_one::<String>("title").cloned(),
+            vsync: matches.get_one::<String>("vsync").unwrap() == "on",
+            strict_fp: matches.get_flag("strict-fp"),
+            mem_report: matches.get_flag("mem-report"),
+            release: matches.get_flag("release"),
+            fast_math: matches.get_flag("fast-math"),
+            contrast_warnings: matches.get_flag("contrast-warnings"),
+            sandbox: matches.get_flag("sandbox"),
+        })
+    }
+}
+
+fn run_aetos_program(input_file: &str, options: &RunOptions) -> Result<(), Box<dyn std::error::Error>> {
     println!("Running Aetos program: {}", input_file);
     
-    let source_code = fs::read_to_string(input_file)?;
-    
+    let source_code = source::read_to_string(input_file)?;
+    run_aetos_source(input_file, &source_code, options)
+}
+
+fn run_aetos_source(input_file: &str, source_code: &str, options: &RunOptions) -> Result<(), Box<dyn std::error::Error>> {
     // Парсим программу
-    let mut parser = parser::Parser::new(&source_code);
-    let program = parser.parse_program()?;
+    let program = parse_source(input_file, source_code)?;
     
     println!("Parsed {} functions and {} structs", program.functions.len(), program.structs.len());
     
@@ -127,14 +582,35 @@ fn run_aetos_program(input_file: &str, width: usize, height: usize) -> Result<()
     let mut type_checker = typecheck::TypeChecker::new();
     type_checker.check_program(&program)?;
     println!("Type checking passed!");
+    for warning in type_checker.warnings() {
+        println!("⚠  {}", warning);
+    }
     
     // Применяем оптимизации
-    let optimizer = optimize::Optimizer::default();
+    let mut optimizer = if options.release {
+        optimize::Optimizer::release()
+    } else {
+        optimize::Optimizer::default()
+    };
+    // Табличные sin/cos ломают побитовую воспроизводимость --strict-fp
+    optimizer.fast_math = options.fast_math && !options.strict_fp;
     let mut optimized_program = program;
     optimizer.optimize(&mut optimized_program);
     
     // Запускаем интерпретатор
-    let mut interpreter = Interpreter::new();
+    let capabilities = if options.sandbox {
+        capabilities::Capabilities::none()
+    } else {
+        capabilities::Capabilities::all()
+    };
+    let mut interpreter = Interpreter::with_capabilities(capabilities);
+    interpreter.set_strict_fp(options.strict_fp);
+    interpreter.set_contrast_warnings(options.contrast_warnings);
+    interpreter.set_vsync(options.vsync);
+    if let Some(title) = &options.title {
+        interpreter.set_window_title(title);
+    }
+    interpreter.set_asset_resolver(assets::AssetResolver::for_script(Path::new(input_file)));
     
     // Извлекаем имя файла для заголовка окна
     let title = input_file
@@ -143,59 +619,214 @@ fn run_aetos_program(input_file: &str, width: usize, height: usize) -> Result<()
         .unwrap_or("AetOS Program")
         .replace(".aetos", "");
     
-    match interpreter.interpret_program(&optimized_program, width, height, &title) {
-        Ok(_) => println!("Program finished successfully"),
-        Err(e) => eprintln!("Runtime error: {}", e),
+    // Паника в интерпретаторе или графическом движке тоже попадает в отчёт о падении
+    let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        interpreter.interpret_program(&optimized_program, options.width, options.height, &title)
+    }));
+    let failure = match outcome {
+        Ok(Ok(())) => {
+            println!("Program finished successfully");
+            None
+        }
+        Ok(Err(e)) => {
+            eprintln!("Runtime error: {}", e);
+            Some(e.to_string())
+        }
+        Err(panic) => {
+            let message = panic.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            eprintln!("Internal error: {}", message);
+            Some(format!("panic: {}", message))
+        }
+    };
+    if let Some(error) = failure {
+        let report = interpreter.crash_report(&error, input_file, source_code);
+        match report.write_to(&crash::crash_dir()) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Cannot write crash report: {}", e),
+        }
+    }
+    
+    if options.mem_report {
+        println!("Memory report: {}", interpreter.mem_stats());
+    }
+    
+    // exit(код): окно уже закрыто, код уходит в код завершения процесса
+    if let Some(code) = interpreter.exit_code() {
+        drop(interpreter);
+        std::process::exit(code);
     }
     
     Ok(())
 }
 
-fn compile_aetos_program(input_file: &str, output_file: Option<&String>) -> Result<(), Box<dyn std::error::Error>> {
+fn test_aetos_program(input_file: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let source_code = source::read_to_string(input_file)?;
+    let program = parse_source(input_file, &source_code)?;
+    
+    let tests = doctest::extract(&source_code);
+    println!("running {} doctests in {}", tests.len(), input_file);
+    
+    let mut failed = 0;
+    for test in &tests {
+        match test.run(&program) {
+            Ok(()) => println!("test {} (line {}) ... ok", test.item, test.line),
+            Err(message) => {
+                failed += 1;
+                println!("test {} (line {}) ... FAILED", test.item, test.line);
+                println!("  {}", message);
+            }
+        }
+    }
+    
+    println!();
+    println!("doctest result: {} passed; {} failed", tests.len() - failed, failed);
+    if failed > 0 {
+        return Err(format!("{} doctest(s) failed", failed).into());
+    }
+    Ok(())
+}
+
+fn run_examples_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    match matches.subcommand() {
+        Some(("show", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let example = examples::find(name)
+                .ok_or_else(|| format!("Unknown example: {} (see `aetosc examples list`)", name))?;
+            print!("{}", example.source);
+            Ok(())
+        }
+        Some(("run", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let example = examples::find(name)
+                .ok_or_else(|| format!("Unknown example: {} (see `aetosc examples list`)", name))?;
+            println!("Running example: {}", example.name);
+            run_aetos_source(&format!("{}.aetos", example.name), example.source, &RunOptions::default())
+        }
+        _ => {
+            println!("Bundled examples:");
+            for example in examples::all() {
+                let kind = if example.is_graphics() { "graphics" } else { "console" };
+                match example.description() {
+                    Some(description) => println!("  {:<20} [{}] {}", example.name, kind, description),
+                    None => println!("  {:<20} [{}]", example.name, kind),
+                }
+            }
+            println!();
+            println!("Use `aetosc examples show <name>` or `aetosc examples run <name>`");
+            Ok(())
+        }
+    }
+}
+
+fn compile_aetos_program(
+    input_file: &str,
+    output_file: Option<&String>,
+    crate_type: codegen::CrateType,
+    backend: codegen::Backend,
+    lto: bool,
+    show_timings: bool,
+    stats_out: Option<&String>,
+) -> Result<(), Box<dyn std::error::Error>> {
     println!("Compiling Aetos program: {}", input_file);
     
-    let source_code = fs::read_to_string(input_file)?;
+    let crate_name = Path::new(input_file)
+        .file_stem()
+        .map_or("main".to_string(), |stem| stem.to_string_lossy().to_string());
+    
+    // Недоступный бэкенд - ошибка до разбора программы
+    let mut generator = backend.generator(crate_type, &crate_name, lto)?;
+    
+    let mut timings = timings::Timings::new();
+    let source_code = source::read_to_string(input_file)?;
+    write_symbol_index(&project_dir(Path::new(input_file)), &[PathBuf::from(input_file)]);
+    
+    // Лексер работает лениво внутри парсера, поэтому отдельно замеряем
+    // один проход по токенам; время parse включает повторную лексику
+    let edition = Edition::detect(&source_code, Path::new(input_file));
+    timings.time("lex", || lexer::Lexer::with_edition(&source_code, edition).count());
     
     // Парсим программу
-    let mut parser = parser::Parser::new(&source_code);
-    let program = parser.parse_program()?;
+    let program = timings.time("parse", || parse_source(input_file, &source_code))?;
     
     println!("Parsed {} functions and {} structs", program.functions.len(), program.structs.len());
     
     // Проверяем типы
     let mut type_checker = typecheck::TypeChecker::new();
-    type_checker.check_program(&program)?;
+    timings.time("typecheck", || type_checker.check_program(&program))?;
     println!("Type checking passed!");
     
+    // Статистику считаем до оптимизации: она описывает программу как написана
+    let usage = stats_out.map(|_| stats::UsageStats::collect(&program, input_file));
+    
+    // Оптимизируем, замеряя каждый проход отдельно
+    let optimizer = optimize::Optimizer::default();
+    let mut program = program;
+    for pass in optimizer.passes() {
+        timings.time(&format!("optimize: {}", pass), || optimizer.run_pass(pass, &mut program));
+    }
+    // Оптимизация заменяет выражения, поэтому типы для бэкенда - от
+    // проверки уже оптимизированной программы
+    let typed = timings.time("typecheck: optimized", || typecheck::TypeChecker::new().check_program(&program))?;
+    
     // Определяем выходной файл
     let output_path = if let Some(output) = output_file {
         output.clone()
     } else {
         let input_path = Path::new(input_file);
-        let mut output = input_path.with_extension("wasm").to_string_lossy().to_string();
+        let extension = generator.extension();
+        let mut output = input_path.with_extension(extension).to_string_lossy().to_string();
         if output == input_file {
-            output = format!("{}.wasm", input_file);
+            output = format!("{}.{}", input_file, extension);
         }
         output
     };
     
-    // Компилируем в WASM
-    println!("Compiling to WASM: {}", output_path);
+    if crate_type == codegen::CrateType::Lib {
+        let exports: Vec<String> = program.functions.iter()
+            .filter(|function| function.is_exported())
+            .map(|function| codegen::symbol_name(&crate_name, function))
+            .collect();
+        if exports.is_empty() {
+            return Err("library has no @export or @no_mangle functions".into());
+        }
+        println!("Exported symbols: {}", exports.join(", "));
+    }
+    
+    println!("Compiling with {} backend: {}", backend, output_path);
+    timings.time("codegen", || generator.generate(&typed, Path::new(&output_path)))?;
     
-    // TODO: Реализовать компиляцию в WASM
-    println!("WASM compilation not yet implemented");
+    if let (Some(usage), Some(stats_out)) = (usage, stats_out) {
+        let path = stats::stats_path(stats_out, &output_path);
+        usage.write_to(&path)?;
+        println!("Usage statistics written to {}", path.display());
+    }
+    
+    if show_timings {
+        println!();
+        println!("{}", timings);
+    }
     
     Ok(())
 }
 
-fn check_aetos_program(input_file: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Параметры `check`
+#[derive(Clone, Copy, Default)]
+struct CheckOptions {
+    audit_determinism: bool,
+    // Предупреждения считаются ошибками (для CI)
+    deny_warnings: bool,
+}
+
+fn check_aetos_program(input_file: &str, options: CheckOptions) -> Result<(), Box<dyn std::error::Error>> {
     println!("Checking Aetos program: {}", input_file);
     
-    let source_code = fs::read_to_string(input_file)?;
+    let source_code = source::read_to_string(input_file)?;
+    write_symbol_index(&project_dir(Path::new(input_file)), &[PathBuf::from(input_file)]);
     
     // Парсим программу
-    let mut parser = parser::Parser::new(&source_code);
-    let program = parser.parse_program()?;
+    let program = parse_source(input_file, &source_code)?;
     
     println!("✓ Parsed {} functions and {} structs", program.functions.len(), program.structs.len());
     
@@ -204,6 +835,12 @@ fn check_aetos_program(input_file: &str) -> Result<(), Box<dyn std::error::Error
     type_checker.check_program(&program)?;
     println!("✓ Type checking passed!");
     
+    let lint_warnings = lint::lint_program(&program);
+    for warning in type_checker.warnings().iter().chain(&lint_warnings) {
+        println!("⚠  {}", warning);
+    }
+    let warnings = type_checker.warnings().len() + lint_warnings.len();
+    
     // Проверяем оптимизации
     let optimizer = optimize::Optimizer::default();
     let mut optimized_program = program.clone();
@@ -213,19 +850,182 @@ fn check_aetos_program(input_file: &str) -> Result<(), Box<dyn std::error::Error
         println!("⚠  Optimization may have removed some code");
     }
     
+    if options.audit_determinism {
+        let issues = determinism::audit_determinism(&program);
+        for issue in &issues {
+            println!("✗ {}", issue);
+        }
+        if !issues.is_empty() {
+            return Err(format!("{} nondeterministic call(s) in @deterministic code", issues.len()).into());
+        }
+        println!("✓ @deterministic functions are deterministic");
+    }
+    
+    if options.deny_warnings && warnings > 0 {
+        return Err(format!("{} warning(s) denied by --deny-warnings", warnings).into());
+    }
+    
     println!("✓ Program is valid Aetos code");
     
     Ok(())
 }
 
+fn check_aetos_files(target: &str, recursive: bool, options: CheckOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let files = check::collect_sources(target, recursive)?;
+    if files.is_empty() {
+        return Err(format!("No .aetos files found in {}", target).into());
+    }
+    
+    println!("Checking {} files in {}", files.len(), target);
+    
+    // Индекс лежит в проверяемом каталоге; для шаблона src/*.aetos - в src
+    let target_path = Path::new(target);
+    let dir = if target_path.is_dir() { target_path.to_path_buf() } else { project_dir(target_path) };
+    report_checks(&files, &dir, options)
+}
+
+/// Каталог с aetos.toml, в котором есть секция [workspace]
+fn is_workspace_root(dir: &Path) -> bool {
+    workspace::Manifest::read(&dir.join(workspace::MANIFEST)).is_ok_and(|manifest| manifest.workspace.is_some())
+}
+
+/// Проверяет все файлы всех пакетов, зависимости первыми
+fn check_workspace(root: &Path, options: CheckOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let workspace = workspace::Workspace::load(root)?;
+    let mut files = Vec::new();
+    for package in &workspace.packages {
+        files.extend(check::collect_sources(&package.dir.to_string_lossy(), true)?);
+    }
+    if files.is_empty() {
+        return Err(format!("No .aetos files found in workspace {}", root.display()).into());
+    }
+    
+    println!("Checking {} files in {} packages", files.len(), workspace.packages.len());
+    report_checks(&files, root, options)
+}
+
+fn report_checks(files: &[PathBuf], index_dir: &Path, options: CheckOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let reports = check::check_files(files);
+    let mut failed = 0;
+    let mut warnings = 0;
+    let mut nondeterministic = 0;
+    for report in &reports {
+        match &report.error {
+            None => println!("✓ {}", report.path.display()),
+            Some(error) => {
+                failed += 1;
+                println!("✗ {}: {}", report.path.display(), error);
+            }
+        }
+        for warning in &report.warnings {
+            warnings += 1;
+            println!("⚠  {}: {}", report.path.display(), warning);
+        }
+        if options.audit_determinism {
+            for issue in &report.determinism {
+                nondeterministic += 1;
+                println!("✗ {}: {}", report.path.display(), issue);
+            }
+        }
+    }
+    
+    write_symbol_index(index_dir, files);
+    
+    println!();
+    println!("Checked {} files: {} passed, {} failed, {} warnings",
+             reports.len(), reports.len() - failed, failed, warnings);
+    
+    if failed > 0 {
+        return Err(format!("{} file(s) failed to check", failed).into());
+    }
+    if nondeterministic > 0 {
+        return Err(format!("{} nondeterministic call(s) in @deterministic code", nondeterministic).into());
+    }
+    if options.deny_warnings && warnings > 0 {
+        return Err(format!("{} warning(s) denied by --deny-warnings", warnings).into());
+    }
+    Ok(())
+}
+
+/// Собирает пакеты рабочего пространства (или один пакет) в общий каталог
+/// target; библиотеки только проверяются - в программы они попадают через импорт
+fn build_workspace(dir: &Path, backend: codegen::Backend, show_timings: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let root = workspace::Workspace::find_root(dir).unwrap_or_else(|| dir.to_path_buf());
+    let workspace = workspace::Workspace::load(&root)?;
+    std::fs::create_dir_all(&workspace.target_dir)?;
+    println!("Building {} package(s) into {}", workspace.packages.len(), workspace.target_dir.display());
+    
+    for package in &workspace.packages {
+        println!();
+        let entry = package.entry.to_string_lossy().to_string();
+        if package.is_lib {
+            println!("Package {} (library)", package.name);
+            check_aetos_program(&entry, CheckOptions::default())?;
+            continue;
+        }
+        println!("Package {}", package.name);
+        let extension = backend.generator(codegen::CrateType::Bin, &package.name, false)?.extension();
+        let output = workspace.output_path(package, extension).to_string_lossy().to_string();
+        compile_aetos_program(&entry, Some(&output), codegen::CrateType::Bin, backend, false, show_timings, None)?;
+    }
+    
+    println!();
+    println!("Finished {} package(s)", workspace.packages.len());
+    Ok(())
+}
+
+/// `ui_kit` - старшая версия, `ui_kit@1.2` - старшая из диапазона
+fn add_package(spec: &str, dir: &Path, registry_location: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let (name, requirement) = spec.split_once('@').unwrap_or((spec, "*"));
+    let requirement = semver::VersionReq::parse(requirement)
+        .map_err(|e| format!("Invalid version requirement {}: {}", requirement, e))?;
+    let registry = registry::Registry::configured(registry_location, dir)?;
+    
+    let installed = registry.install(name, &requirement, &registry::packages_dir(dir))?;
+    for package in &installed {
+        println!("Installed {} {} into {}", package.name, package.version, package.dir.display());
+    }
+    
+    // Без явного диапазона записываем версии, совместимые с установленной
+    let written = if spec.contains('@') { requirement.to_string() } else { installed[0].version.to_string() };
+    let manifest_path = dir.join(workspace::MANIFEST);
+    let manifest = std::fs::read_to_string(&manifest_path)?;
+    std::fs::write(&manifest_path, registry::add_dependency(&manifest, name, &written))?;
+    println!("Added {} = \"{}\" to {}", name, written, manifest_path.display());
+    Ok(())
+}
+
+/// Перед публикацией пакет проверяется так же, как `aetosc check`
+fn publish_package(dir: &Path, registry_location: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let package = workspace::Package::load(dir)?;
+    let registry = registry::Registry::configured(registry_location, dir)?;
+    check_aetos_program(&package.entry.to_string_lossy(), CheckOptions::default())?;
+    
+    let (name, version, checksum) = registry.publish(dir)?;
+    println!("Published {} {} (sha256 {})", name, version, checksum);
+    Ok(())
+}
+
 fn show_help() {
     println!("Aetos Language Compiler v0.3.0");
     println!();
     println!("Usage:");
     println!("  aetosc graphics <file.aetos>    - Run graphics program");
-    println!("  aetosc run <file.aetos>         - Run console program");
-    println!("  aetosc compile <file.aetos>     - Compile to WASM");
+    println!("  aetosc run <file.aetos>         - Run console program (--strict-fp for deterministic floats)");
+    println!("                                    --sandbox denies graphics, files, time and threads");
+    println!("  aetosc compile <file.aetos>     - Compile to WASM text (--crate-type lib for libraries)");
+    println!("                                    --backend wasm|llvm|embedded selects the code generator");
+    println!("                                    --lto optimizes the whole program (llvm, embedded)");
     println!("  aetosc check <file.aetos>       - Check syntax and types");
+    println!("  aetosc check <dir> --recursive  - Check all .aetos files in a directory");
+    println!("  aetosc check <file> --audit-determinism - Find nondeterminism in @deterministic code");
+    println!("  aetosc build [dir]              - Build all packages of a workspace into its target dir");
+    println!("  aetosc add <package>[@version]  - Install a library from the registry");
+    println!("  aetosc publish [dir]            - Publish a package to the registry (--registry URL)");
+    println!("  aetosc test <file.aetos>        - Run doc comment examples");
+    println!("  aetosc new <name> [--template]  - Create project (console, game, embedded)");
+    println!("  aetosc examples list|show|run   - Browse bundled examples");
+    println!("  aetosc symbols definition|references <file> <line> <column> - Query the symbol index");
     println!("  aetosc ide                      - Start interactive IDE");
     println!("  aetosc help                     - Show this help");
     println!();
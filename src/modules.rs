@@ -0,0 +1,307 @@
+// modules.rs - программа из нескольких файлов (`import "math.aetos";`)
+//
+// Импорты разрешаются относительно файла, в котором записаны, и
+// загружаются рекурсивно; каждый файл читается один раз, поэтому
+// взаимные импорты допустимы. Объявления всех модулей сливаются в одну
+// программу с общим пространством имён: сначала зависимости, затем
+// импортирующий файл. Одинаковые имена в разных файлах - ошибка с
+// указанием обоих файлов; повторы внутри одного файла сообщает тайпчекер.
+// Импорт, начинающийся с имени зависимости из aetos.toml, загружается из
+// каталога этого пакета (workspace.rs).
+//
+// Другим файлам видны только объявления с `pub`: функции, структуры,
+// перечисления, трейты, константы и статические переменные. Методы проверяются
+// при вызове через тип (`Point::new()`); вызов через точку зависит от типа
+// получателя, который здесь неизвестен. Поля структур без `pub` проверяет
+// тайпчекер: слияние записывает в функции и структуры номер их файла.
+
+use crate::ast::*;
+use crate::edition::Edition;
+use crate::parser::{ParseError, Parser};
+use crate::visit::{self, Visitor};
+use crate::workspace::{self, normalize};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ModuleError {
+    #[error("Cannot read module {path} (imported from {importer} line {line}): {source}")]
+    Io { path: PathBuf, importer: PathBuf, line: usize, source: io::Error },
+
+    #[error("Parse error in module {path}: {error}")]
+    Parse { path: PathBuf, error: ParseError },
+
+    #[error("Duplicate {kind} {name}: declared in {first} and {second}")]
+    Duplicate { kind: &'static str, name: String, first: PathBuf, second: PathBuf },
+
+    #[error("Private {kind} {name} declared in {owner} is used in {user}; mark it `pub` to export it")]
+    Private { kind: &'static str, name: String, owner: PathBuf, user: PathBuf },
+}
+
+/// Загружает импорты программы из файла `path` и сливает их объявления в неё
+pub fn link_imports(program: Program, path: &Path) -> Result<Program, ModuleError> {
+    let mut loader = Loader::default();
+    let entry = normalize(path);
+    loader.visit(program, entry)?;
+    merge(loader.modules)
+}
+
+#[derive(Default)]
+struct Loader {
+    // Модули в порядке слияния: зависимости раньше импортирующих
+    modules: Vec<(PathBuf, Program)>,
+    seen: Vec<PathBuf>,
+}
+
+impl Loader {
+    fn visit(&mut self, program: Program, path: PathBuf) -> Result<(), ModuleError> {
+        self.seen.push(path.clone());
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        for import in &program.imports {
+            // Путь без `.` и `a/..`, чтобы один файл не загружался дважды под разными именами
+            let import_path = normalize(
+                &workspace::resolve_dependency(&dir, &import.path).unwrap_or_else(|| dir.join(&import.path)),
+            );
+            if self.seen.contains(&import_path) {
+                continue;
+            }
+            let source = crate::source::read_to_string(&import_path).map_err(|source| ModuleError::Io {
+                path: import_path.clone(),
+                importer: path.clone(),
+                line: import.line,
+                source,
+            })?;
+            let edition = Edition::detect(&source, &import_path);
+            let module = Parser::with_edition(&source, edition)
+                .parse_program()
+                .map_err(|error| ModuleError::Parse { path: import_path.clone(), error })?;
+            self.visit(module, import_path)?;
+        }
+        self.modules.push((path, program));
+        Ok(())
+    }
+}
+
+fn merge(modules: Vec<(PathBuf, Program)>) -> Result<Program, ModuleError> {
+    let mut owners: HashMap<(&'static str, String), PathBuf> = HashMap::new();
+    let mut check = |kind: &'static str, name: &str, path: &Path| -> Result<(), ModuleError> {
+        match owners.get(&(kind, name.to_string())) {
+            Some(first) if first != path => Err(ModuleError::Duplicate {
+                kind,
+                name: name.to_string(),
+                first: first.clone(),
+                second: path.to_path_buf(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                owners.insert((kind, name.to_string()), path.to_path_buf());
+                Ok(())
+            }
+        }
+    };
+
+    check_visibility(&modules)?;
+
+    let mut merged = Program {
+        functions: Vec::new(),
+        structs: Vec::new(),
+        enums: Vec::new(),
+        imports: Vec::new(),
+        consts: Vec::new(),
+        statics: Vec::new(),
+        traits: Vec::new(),
+        trait_impls: Vec::new(),
+    };
+    for (module, (path, mut program)) in modules.into_iter().enumerate() {
+        for function in &mut program.functions {
+            function.module = module;
+        }
+        for struct_def in &mut program.structs {
+            struct_def.module = module;
+        }
+        for function in &program.functions {
+            check("function", &function.name, &path)?;
+        }
+        // Структуры, перечисления и трейты делят одно пространство имён типов
+        let types = program.structs.iter().map(|s| &s.name)
+            .chain(program.enums.iter().map(|e| &e.name))
+            .chain(program.traits.iter().map(|t| &t.name));
+        for name in types {
+            check("type", name, &path)?;
+        }
+        for constant in &program.consts {
+            check("constant", &constant.name, &path)?;
+        }
+        for static_def in &program.statics {
+            check("static", &static_def.name, &path)?;
+        }
+        merged.functions.extend(program.functions);
+        merged.structs.extend(program.structs);
+        merged.enums.extend(program.enums);
+        merged.imports.extend(program.imports);
+        merged.consts.extend(program.consts);
+        merged.statics.extend(program.statics);
+        merged.traits.extend(program.traits);
+        merged.trait_impls.extend(program.trait_impls);
+    }
+    Ok(merged)
+}
+
+/// Ссылки каждого модуля на объявления без `pub` из других файлов
+fn check_visibility(modules: &[(PathBuf, Program)]) -> Result<(), ModuleError> {
+    let mut private: HashMap<(&'static str, &str), &Path> = HashMap::new();
+    for (path, program) in modules {
+        let items = program.functions.iter().map(|f| ("function", f.name.as_str(), f.is_public))
+            .chain(program.structs.iter().map(|s| ("type", s.name.as_str(), s.is_public)))
+            .chain(program.enums.iter().map(|e| ("type", e.name.as_str(), e.is_public)))
+            .chain(program.consts.iter().map(|c| ("constant", c.name.as_str(), c.is_public)))
+            .chain(program.statics.iter().map(|s| ("static", s.name.as_str(), s.is_public)))
+            .chain(program.traits.iter().map(|t| ("type", t.name.as_str(), t.is_public)));
+        for (kind, name, is_public) in items {
+            if !is_public {
+                private.insert((kind, name), path);
+            }
+        }
+    }
+
+    for (path, program) in modules {
+        for (kind, name) in references(program) {
+            if let Some(&owner) = private.get(&(kind, name.as_str())).filter(|owner| **owner != path) {
+                return Err(ModuleError::Private {
+                    kind,
+                    name,
+                    owner: owner.to_path_buf(),
+                    user: path.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Имена верхнего уровня, которые использует модуль, вместе с видом объявления
+fn references(program: &Program) -> Vec<(&'static str, String)> {
+    let mut collector = References { names: Vec::new(), locals: HashSet::new() };
+    for function in &program.functions {
+        collector.locals = visit::local_names(function);
+        for param in &function.params {
+            collector.type_names(&param.param_type);
+        }
+        collector.type_names(&function.return_type);
+        collector.visit_function(function);
+    }
+    collector.locals.clear();
+    for field in program.structs.iter().flat_map(|s| &s.fields) {
+        collector.type_names(&field.field_type);
+        if let Some(default) = &field.default {
+            collector.visit_expression(default);
+        }
+    }
+    for variant in program.enums.iter().flat_map(|e| &e.variants) {
+        for field in &variant.fields {
+            collector.type_names(field);
+        }
+    }
+    for method in program.traits.iter().flat_map(|t| &t.methods) {
+        for param in &method.params {
+            collector.type_names(&param.param_type);
+        }
+        collector.type_names(&method.return_type);
+    }
+    for trait_impl in &program.trait_impls {
+        collector.add("type", &trait_impl.trait_name);
+    }
+    for constant in &program.consts {
+        collector.type_names(&constant.const_type);
+        collector.visit_expression(&constant.value);
+    }
+    for static_def in &program.statics {
+        collector.type_names(&static_def.static_type);
+        collector.visit_expression(&static_def.value);
+    }
+    collector.names
+}
+
+struct References {
+    names: Vec<(&'static str, String)>,
+    // Локальные имена текущей функции перекрывают статические переменные и функции
+    locals: HashSet<String>,
+}
+
+impl References {
+    fn add(&mut self, kind: &'static str, name: &str) {
+        self.names.push((kind, name.to_string()));
+    }
+
+    fn type_names(&mut self, ty: &Type) {
+        match ty {
+            Type::Struct(name) | Type::Enum(name) => self.add("type", name),
+            Type::Ref(inner) | Type::Array(inner) | Type::Slice(inner) | Type::FixedArray(inner, _) | Type::Option(inner) => self.type_names(inner),
+            Type::Result(ok, error) => {
+                self.type_names(ok);
+                self.type_names(error);
+            }
+            Type::Function(params, result) => {
+                for param in params {
+                    self.type_names(param);
+                }
+                self.type_names(result);
+            }
+            Type::Generic(name, args) => {
+                self.add("type", name);
+                for arg in args {
+                    self.type_names(arg);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for References {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VariableDeclaration { var_type, .. } => self.type_names(var_type),
+            Statement::DestructureStruct { struct_name, .. } => self.add("type", struct_name),
+            Statement::Assignment { name, .. } if !self.locals.contains(name) => self.add("static", name),
+            _ => {}
+        }
+        visit::walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Variable(name) if !self.locals.contains(name) => {
+                self.add("constant", name);
+                self.add("static", name);
+                // Именованная функция как значение
+                self.add("function", name);
+            }
+            Expression::FunctionCall { name, .. } if !self.locals.contains(name) => self.add("function", name),
+            Expression::StructInitialization { struct_name, .. } => self.add("type", struct_name),
+            // Shape::Circle(..) или Point::new(..) - вариант или функция из impl
+            Expression::EnumVariant { enum_name, variant, .. } => {
+                self.add("type", enum_name);
+                self.add("function", &method_name(enum_name, variant));
+            }
+            Expression::TypeCast { target_type, .. } => self.type_names(target_type),
+            Expression::Match { arms, .. } => {
+                for arm in arms {
+                    if let Pattern::Variant { enum_name, .. } = &arm.pattern {
+                        self.add("type", enum_name);
+                    }
+                }
+            }
+            Expression::Lambda { params, return_type, .. } => {
+                for param in params {
+                    self.type_names(&param.param_type);
+                }
+                self.type_names(return_type);
+            }
+            _ => {}
+        }
+        visit::walk_expression(self, expression);
+    }
+}
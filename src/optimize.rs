@@ -1,11 +1,25 @@
 use crate::ast::*;
-use std::collections::HashMap;
+use crate::const_eval::{literal_value, ConstEvaluator};
+use crate::visit::{local_names, walk_expression, walk_expression_mut, walk_function_mut, walk_statement, Visitor, VisitorMut};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
 pub struct Optimizer {
     pub constant_folding: bool,
     pub dead_code_elimination: bool,
     pub inline_functions: bool,
+    /// Release-режим: удалить assert и контракты @requires/@ensures
+    pub strip_assertions: bool,
+    // sin/cos заменяются табличными fast_sin/fast_cos (`--fast-math`)
+    pub fast_math: bool,
+    // Свёрнутые значения констант верхнего уровня на время прохода
+    constants: RefCell<HashMap<String, Expression>>,
+    // Вычислитель вызовов const fn на время прохода, см. const_eval.rs
+    const_eval: RefCell<Option<ConstEvaluator>>,
+    // Локальные имена текущей функции: вызов через переменную-функцию
+    // с именем const fn не вычисляется
+    locals: RefCell<HashSet<String>>,
 }
 
 impl Default for Optimizer {
@@ -14,6 +28,11 @@ impl Default for Optimizer {
             constant_folding: true,
             dead_code_elimination: true,
             inline_functions: true,
+            strip_assertions: false,
+            fast_math: false,
+            constants: RefCell::new(HashMap::new()),
+            const_eval: RefCell::new(None),
+            locals: RefCell::new(HashSet::new()),
         }
     }
 }
@@ -23,23 +42,152 @@ impl Optimizer {
         Self::default()
     }
 
+    /// Оптимизации для release-сборки
+    pub fn release() -> Self {
+        Self {
+            strip_assertions: true,
+            ..Self::default()
+        }
+    }
+
     pub fn optimize(&self, program: &mut Program) {
-        if self.constant_folding {
-            self.constant_folding(program);
+        for pass in self.passes() {
+            self.run_pass(pass, program);
+        }
+    }
+
+    /// Включённые проходы в порядке выполнения
+    pub fn passes(&self) -> Vec<&'static str> {
+        [
+            ("strip_assertions", self.strip_assertions),
+            ("fast_math", self.fast_math),
+            ("constant_folding", self.constant_folding),
+            ("dead_code_elimination", self.dead_code_elimination),
+            ("inline_functions", self.inline_functions),
+        ]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    pub fn run_pass(&self, pass: &str, program: &mut Program) {
+        match pass {
+            "strip_assertions" => self.strip_assertions(program),
+            "fast_math" => self.use_fast_math(program),
+            "constant_folding" => self.constant_folding(program),
+            "dead_code_elimination" => self.dead_code_elimination(program),
+            "inline_functions" => self.inline_small_functions(program),
+            _ => {}
+        }
+    }
+
+    fn strip_assertions(&self, program: &mut Program) {
+        for function in &mut program.functions {
+            function.attributes.retain(|attribute| {
+                !matches!(attribute, Attribute::Requires(_) | Attribute::Ensures(_))
+            });
+            Self::strip_asserts_in_block(&mut function.body);
         }
-        if self.dead_code_elimination {
-            self.dead_code_elimination(program);
+    }
+
+    fn use_fast_math(&self, program: &mut Program) {
+        // Своя функция sin перекрывает встроенную, и её вызовы не трогаем
+        let user_functions: HashSet<String> = program.functions.iter()
+            .map(|function| function.name.clone())
+            .collect();
+        for function in &mut program.functions {
+            let mut locals = local_names(function);
+            locals.extend(user_functions.iter().cloned());
+            walk_function_mut(&mut FastMath { shadowed: locals }, function);
         }
-        if self.inline_functions {
-            self.inline_small_functions(program);
+    }
+
+    fn strip_asserts_in_block(statements: &mut Vec<Statement>) {
+        statements.retain(|statement| {
+            !matches!(statement, Statement::Expression(Expression::FunctionCall { name, .. }, _) if name == "assert")
+        });
+        for statement in statements {
+            match statement {
+                Statement::Block { statements, .. }
+                | Statement::While { body: statements, .. }
+                | Statement::Loop { body: statements, .. }
+                | Statement::For { body: statements, .. } => {
+                    Self::strip_asserts_in_block(statements);
+                }
+                Statement::If { then_branch, else_branch, .. } => {
+                    Self::strip_asserts_in_block(then_branch);
+                    if let Some(else_branch) = else_branch {
+                        Self::strip_asserts_in_block(else_branch);
+                    }
+                }
+                _ => {}
+            }
         }
     }
 
     // Constant Folding
     fn constant_folding(&self, program: &mut Program) {
+        // Вызовы const fn с литеральными аргументами вычисляются по
+        // программе до свёртки - как она выполнилась бы без оптимизаций
+        *self.const_eval.borrow_mut() = ConstEvaluator::new(program);
+        // Константы подставляются в функции уже свёрнутыми; тайпчекер
+        // запрещает перекрывать их локальными переменными
+        let constants = self.evaluate_constants(&program.consts);
+        for constant in &mut program.consts {
+            constant.value = constants[&constant.name].clone();
+        }
+        let statics = self.evaluate_statics(&constants, &program.statics);
+        for static_def in &mut program.statics {
+            static_def.value = statics[&static_def.name].clone();
+        }
+        // Статические переменные меняются во время работы и не подставляются
+        *self.constants.borrow_mut() = constants;
+        for field in program.structs.iter_mut().flat_map(|struct_def| &mut struct_def.fields) {
+            if let Some(default) = &field.default {
+                field.default = Some(self.fold_initializer(default, &field.field_type));
+            }
+        }
         for function in &mut program.functions {
+            *self.locals.borrow_mut() = local_names(function);
             self.fold_constants_in_function(function);
         }
+        self.locals.borrow_mut().clear();
+        self.constants.borrow_mut().clear();
+        *self.const_eval.borrow_mut() = None;
+    }
+
+    /// Значения констант верхнего уровня, свёрнутые при компиляции. Если
+    /// свернуть до литерала не удалось (например, f32-арифметика), значение -
+    /// выражение из одних литералов
+    pub fn evaluate_constants(&self, consts: &[Const]) -> HashMap<String, Expression> {
+        let mut values = HashMap::new();
+        for constant in consts {
+            *self.constants.borrow_mut() = values.clone();
+            let value = self.fold_initializer(&constant.value, &constant.const_type);
+            values.insert(constant.name.clone(), value);
+        }
+        self.constants.borrow_mut().clear();
+        values
+    }
+
+    /// Начальные значения статических переменных; в них видны только константы
+    pub fn evaluate_statics(&self, constants: &HashMap<String, Expression>, statics: &[Static]) -> HashMap<String, Expression> {
+        *self.constants.borrow_mut() = constants.clone();
+        let values = statics.iter()
+            .map(|static_def| (static_def.name.clone(), self.fold_initializer(&static_def.value, &static_def.static_type)))
+            .collect();
+        self.constants.borrow_mut().clear();
+        values
+    }
+
+    fn fold_initializer(&self, value: &Expression, value_type: &Type) -> Expression {
+        match self.fold_constants_in_expression(value.clone()) {
+            Expression::IntegerLiteral(i) if matches!(value_type, Type::F32 | Type::F64) => {
+                Expression::FloatLiteral(i as f32)
+            }
+            value => value,
+        }
     }
 
     fn fold_constants_in_function(&self, function: &mut Function) {
@@ -52,44 +200,103 @@ impl Optimizer {
 
     fn fold_constants_in_statement(&self, statement: Statement) -> Statement {
         match statement {
-            Statement::VariableDeclaration { name, var_type, value, mutable } => {
+            Statement::VariableDeclaration { name, var_type, value, mutable, position } => {
                 Statement::VariableDeclaration {
                     name,
                     var_type,
                     value: self.fold_constants_in_expression(value),
                     mutable,
+                    position,
+                }
+            }
+            Statement::DestructureStruct { struct_name, bindings, value, mutable, position } => {
+                Statement::DestructureStruct {
+                    struct_name,
+                    bindings,
+                    value: self.fold_constants_in_expression(value),
+                    mutable,
+                    position,
                 }
             }
-            Statement::Assignment { name, value } => {
+            Statement::Assignment { name, value, position } => {
                 Statement::Assignment {
                     name,
                     value: self.fold_constants_in_expression(value),
+                    position,
                 }
             }
-            Statement::Return { value } => {
+            Statement::FieldAssignment { name, fields, value, position } => {
+                Statement::FieldAssignment {
+                    name,
+                    fields,
+                    value: self.fold_constants_in_expression(value),
+                    position,
+                }
+            }
+            Statement::IndexAssignment { name, index, value, position } => {
+                Statement::IndexAssignment {
+                    name,
+                    index: self.fold_constants_in_expression(index),
+                    value: self.fold_constants_in_expression(value),
+                    position,
+                }
+            }
+            Statement::Return { value, position } => {
                 Statement::Return {
                     value: self.fold_constants_in_expression(value),
+                    position,
                 }
             }
-            Statement::Expression(expr) => {
-                Statement::Expression(self.fold_constants_in_expression(expr))
+            Statement::Expression(expr, position) => {
+                Statement::Expression(self.fold_constants_in_expression(expr), position)
+            }
+            Statement::Defer { expression, position } => {
+                Statement::Defer {
+                    expression: self.fold_constants_in_expression(expression),
+                    position,
+                }
             }
-            Statement::Block { statements } => {
+            Statement::Block { statements, position } => {
                 Statement::Block {
                     statements: statements.into_iter()
                         .map(|s| self.fold_constants_in_statement(s))
                         .collect(),
+                    position,
                 }
             }
-            Statement::While { condition, body } => {
+            Statement::While { condition, body, label, position } => {
                 Statement::While {
                     condition: self.fold_constants_in_expression(condition),
                     body: body.into_iter()
                         .map(|s| self.fold_constants_in_statement(s))
                         .collect(),
+                    label,
+                    position,
+                }
+            }
+            Statement::Loop { body, label, position } => {
+                Statement::Loop {
+                    body: body.into_iter()
+                        .map(|s| self.fold_constants_in_statement(s))
+                        .collect(),
+                    label,
+                    position,
+                }
+            }
+            Statement::For { variable, start, end, body, label, position } => {
+                Statement::For {
+                    variable,
+                    start: self.fold_constants_in_expression(start),
+                    end: self.fold_constants_in_expression(end),
+                    body: body.into_iter()
+                        .map(|s| self.fold_constants_in_statement(s))
+                        .collect(),
+                    label,
+                    position,
                 }
             }
-            Statement::If { condition, then_branch, else_branch } => {
+            Statement::Break { .. } | Statement::Continue { .. } => statement,
+            Statement::If { condition, then_branch, else_branch, position } => {
                 Statement::If {
                     condition: self.fold_constants_in_expression(condition),
                     then_branch: then_branch.into_iter()
@@ -100,6 +307,7 @@ impl Optimizer {
                             .map(|s| self.fold_constants_in_statement(s))
                             .collect()
                     }),
+                    position,
                 }
             }
         }
@@ -107,32 +315,49 @@ impl Optimizer {
 
     fn analyze_variable_usage(&self, statement: &Statement, used_variables: &mut HashMap<String, usize>) {
         match statement {
-            Statement::VariableDeclaration { value, .. } => {
+            Statement::VariableDeclaration { value, .. } | Statement::DestructureStruct { value, .. } => {
                 self.analyze_expression_usage(value, used_variables);
             }
-            Statement::Assignment { name, value } => {
+            Statement::Assignment { name, value, .. } | Statement::FieldAssignment { name, value, .. } => {
                 // При присваивании переменная используется (пишется)
                 *used_variables.entry(name.clone()).or_insert(0) += 1;
                 self.analyze_expression_usage(value, used_variables);
             }
-            Statement::Return { value } => {
+            Statement::IndexAssignment { name, index, value, .. } => {
+                *used_variables.entry(name.clone()).or_insert(0) += 1;
+                self.analyze_expression_usage(index, used_variables);
+                self.analyze_expression_usage(value, used_variables);
+            }
+            Statement::Return { value, .. } => {
                 self.analyze_expression_usage(value, used_variables);
             }
-            Statement::Expression(expr) => {
+            Statement::Expression(expr, _) | Statement::Defer { expression: expr, .. } => {
                 self.analyze_expression_usage(expr, used_variables);
             }
-            Statement::Block { statements } => {
+            Statement::Block { statements, .. } => {
                 for stmt in statements {
                     self.analyze_variable_usage(stmt, used_variables);
                 }
             }
-            Statement::While { condition, body } => {
+            Statement::While { condition, body, .. } => {
                 self.analyze_expression_usage(condition, used_variables);
                 for stmt in body {
                     self.analyze_variable_usage(stmt, used_variables);
                 }
             }
-            Statement::If { condition, then_branch, else_branch } => {
+            Statement::Loop { body, .. } => {
+                for stmt in body {
+                    self.analyze_variable_usage(stmt, used_variables);
+                }
+            }
+            Statement::For { start, end, body, .. } => {
+                self.analyze_expression_usage(start, used_variables);
+                self.analyze_expression_usage(end, used_variables);
+                for stmt in body {
+                    self.analyze_variable_usage(stmt, used_variables);
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
                 self.analyze_expression_usage(condition, used_variables);
                 for stmt in then_branch {
                     self.analyze_variable_usage(stmt, used_variables);
@@ -143,19 +368,21 @@ impl Optimizer {
                     }
                 }
             }
+            Statement::Break { .. } | Statement::Continue { .. } => {}
         }
     }
 
     fn try_inline_statement(&self, statement: &Statement, function_map: &HashMap<String, Function>) -> Option<Vec<Statement>> {
-        if let Statement::Expression(Expression::FunctionCall { name, args }) = statement {
+        if let Statement::Expression(Expression::FunctionCall { name, args }, position) = statement {
             if let Some(target_function) = function_map.get(name) {
-                return self.inline_function_call(target_function, args);
+                return self.inline_function_call(target_function, args, *position);
             }
         }
         None
     }
 
-    fn inline_function_call(&self, target_function: &Function, args: &[Expression]) -> Option<Vec<Statement>> {
+    /// `position` - оператор вызова: на нём вычисляются аргументы
+    fn inline_function_call(&self, target_function: &Function, args: &[Expression], position: Position) -> Option<Vec<Statement>> {
         if target_function.params.len() != args.len() {
             return None;
         }
@@ -169,12 +396,22 @@ impl Optimizer {
                 var_type: param.param_type.clone(),
                 value: arg.clone(),
                 mutable: false,
+                position,
             });
         }
         
-        // Копируем тело функции
-        for statement in &target_function.body {
-            inlined_body.push(statement.clone());
+        // Копируем тело функции. Значение return в конце отбрасывается (вызов -
+        // отдельный оператор), а return в другом месте вернул бы из вызывающей
+        // функции - такие функции не встраиваем
+        let (last, rest) = target_function.body.split_last()?;
+        if rest.iter().any(contains_return) {
+            return None;
+        }
+        inlined_body.extend(rest.iter().cloned());
+        match last {
+            Statement::Return { value, position } => inlined_body.push(Statement::Expression(value.clone(), *position)),
+            statement if contains_return(statement) => return None,
+            statement => inlined_body.push(statement.clone()),
         }
         
         Some(inlined_body)
@@ -182,24 +419,46 @@ impl Optimizer {
 
     fn fold_constants_in_expression(&self, expr: Expression) -> Expression {
         match expr {
+            Expression::Variable(name) => match self.constants.borrow().get(&name) {
+                Some(value) => value.clone(),
+                None => Expression::Variable(name),
+            },
             Expression::BinaryExpression { left, operator, right } => {
                 let left = Box::new(self.fold_constants_in_expression(*left));
                 let right = Box::new(self.fold_constants_in_expression(*right));
 
-                // Попробуем свернуть константы
-                if let (Expression::IntegerLiteral(left_val), Expression::IntegerLiteral(right_val)) = (&*left, &*right) {
+                // Попробуем свернуть константы: арифметика i32, литералы i64 не трогаем
+                if let (Some(left_val), Some(right_val)) = (i32_literal(&left), i32_literal(&right)) {
                     match operator {
                         BinaryOperator::Add => {
-                            return Expression::IntegerLiteral(left_val + right_val);
+                            return Expression::IntegerLiteral(i64::from(left_val + right_val));
                         }
                         BinaryOperator::Subtract => {
-                            return Expression::IntegerLiteral(left_val - right_val);
+                            return Expression::IntegerLiteral(i64::from(left_val - right_val));
                         }
                         BinaryOperator::Multiply => {
-                            return Expression::IntegerLiteral(left_val * right_val);
+                            return Expression::IntegerLiteral(i64::from(left_val * right_val));
+                        }
+                        BinaryOperator::Divide if right_val != 0 => {
+                            return Expression::IntegerLiteral(i64::from(left_val / right_val));
+                        }
+                        BinaryOperator::Mod if right_val != 0 => {
+                            return Expression::IntegerLiteral(i64::from(left_val.wrapping_rem(right_val)));
+                        }
+                        BinaryOperator::BitAnd => {
+                            return Expression::IntegerLiteral(i64::from(left_val & right_val));
                         }
-                        BinaryOperator::Divide if *right_val != 0 => {
-                            return Expression::IntegerLiteral(left_val / right_val);
+                        BinaryOperator::BitOr => {
+                            return Expression::IntegerLiteral(i64::from(left_val | right_val));
+                        }
+                        BinaryOperator::BitXor => {
+                            return Expression::IntegerLiteral(i64::from(left_val ^ right_val));
+                        }
+                        BinaryOperator::Shl => {
+                            return Expression::IntegerLiteral(i64::from(left_val.wrapping_shl(right_val as u32)));
+                        }
+                        BinaryOperator::Shr => {
+                            return Expression::IntegerLiteral(i64::from(left_val.wrapping_shr(right_val as u32)));
                         }
                         BinaryOperator::Eq => {
                             return Expression::BoolLiteral(left_val == right_val);
@@ -248,24 +507,38 @@ impl Optimizer {
 
             // Рекурсивно обрабатываем другие выражения
             Expression::FunctionCall { name, args } => {
-                Expression::FunctionCall {
-                    name,
-                    args: args.into_iter()
-                        .map(|arg| self.fold_constants_in_expression(arg))
-                        .collect(),
-                }
-            }
-            Expression::StructInitialization { struct_name, fields } => {
+                let args: Vec<Expression> = args.into_iter()
+                    .map(|arg| self.fold_constants_in_expression(arg))
+                    .collect();
+                let value = match self.locals.borrow().contains(&name) {
+                    true => None,
+                    false => self.const_eval.borrow_mut().as_mut()
+                        .and_then(|evaluator| evaluator.evaluate(&name, &args)),
+                };
+                value.unwrap_or(Expression::FunctionCall { name, args })
+            }
+            Expression::StructInitialization { struct_name, fields, base } => {
                 Expression::StructInitialization {
                     struct_name,
                     fields: fields.into_iter()
                         .map(|(name, expr)| (name, self.fold_constants_in_expression(expr)))
                         .collect(),
+                    base: base.map(|base| Box::new(self.fold_constants_in_expression(*base))),
                 }
             }
             Expression::FieldAccess { expression, field_name } => {
+                let expression = self.fold_constants_in_expression(*expression);
+                // #ff8800.g -> 136: остальные поля - литералы, отбросить их можно.
+                // Поле со значением по умолчанию в литерале не записано
+                if let Expression::StructInitialization { fields, base: None, .. } = &expression {
+                    if fields.iter().all(|(_, value)| literal_value(value).is_some()) {
+                        if let Some((_, value)) = fields.iter().find(|(name, _)| *name == field_name) {
+                            return value.clone();
+                        }
+                    }
+                }
                 Expression::FieldAccess {
-                    expression: Box::new(self.fold_constants_in_expression(*expression)),
+                    expression: Box::new(expression),
                     field_name,
                 }
             }
@@ -280,17 +553,135 @@ impl Optimizer {
                     mutable,
                 }
             }
+            Expression::Ref { expression } => {
+                Expression::Ref {
+                    expression: Box::new(self.fold_constants_in_expression(*expression)),
+                }
+            }
+            Expression::ArrayLiteral { elements } => {
+                Expression::ArrayLiteral {
+                    elements: elements.into_iter()
+                        .map(|element| self.fold_constants_in_expression(element))
+                        .collect(),
+                }
+            }
+            Expression::ArrayRepeat { value, count } => {
+                Expression::ArrayRepeat {
+                    value: Box::new(self.fold_constants_in_expression(*value)),
+                    count,
+                }
+            }
+            Expression::Index { expression, index } => {
+                Expression::Index {
+                    expression: Box::new(self.fold_constants_in_expression(*expression)),
+                    index: Box::new(self.fold_constants_in_expression(*index)),
+                }
+            }
+            Expression::Slice { expression, start, end } => {
+                Expression::Slice {
+                    expression: Box::new(self.fold_constants_in_expression(*expression)),
+                    start: start.map(|start| Box::new(self.fold_constants_in_expression(*start))),
+                    end: end.map(|end| Box::new(self.fold_constants_in_expression(*end))),
+                }
+            }
+            Expression::EnumVariant { enum_name, variant, args } => {
+                Expression::EnumVariant {
+                    enum_name,
+                    variant,
+                    args: args.into_iter()
+                        .map(|arg| self.fold_constants_in_expression(arg))
+                        .collect(),
+                }
+            }
+            Expression::Match { subject, arms } => {
+                Expression::Match {
+                    subject: Box::new(self.fold_constants_in_expression(*subject)),
+                    arms: arms.into_iter()
+                        .map(|arm| MatchArm {
+                            pattern: arm.pattern,
+                            body: self.fold_constants_in_expression(arm.body),
+                        })
+                        .collect(),
+                }
+            }
+            Expression::If { condition, then_branch, else_branch } => {
+                // Ветвь с известным условием заменяет всё выражение
+                match self.fold_constants_in_expression(*condition) {
+                    Expression::BoolLiteral(true) => self.fold_constants_in_expression(*then_branch),
+                    Expression::BoolLiteral(false) => self.fold_constants_in_expression(*else_branch),
+                    condition => Expression::If {
+                        condition: Box::new(condition),
+                        then_branch: Box::new(self.fold_constants_in_expression(*then_branch)),
+                        else_branch: Box::new(self.fold_constants_in_expression(*else_branch)),
+                    },
+                }
+            }
+            Expression::MethodCall { receiver, method, args } => {
+                Expression::MethodCall {
+                    receiver: Box::new(self.fold_constants_in_expression(*receiver)),
+                    method,
+                    args: args.into_iter()
+                        .map(|arg| self.fold_constants_in_expression(arg))
+                        .collect(),
+                }
+            }
+            Expression::Try { expression } => {
+                Expression::Try {
+                    expression: Box::new(self.fold_constants_in_expression(*expression)),
+                }
+            }
+            Expression::Lambda { params, return_type, body } => {
+                Expression::Lambda {
+                    params,
+                    return_type,
+                    body: body.into_iter()
+                        .map(|stmt| self.fold_constants_in_statement(stmt))
+                        .collect(),
+                }
+            }
             other => other,
         }
     }
 
     // Dead Code Elimination
     fn dead_code_elimination(&self, program: &mut Program) {
+        // Своя функция exit перекрывает встроенную, и та может вернуть управление
+        let builtin_exit = !program.functions.iter().any(|function| function.name == "exit");
         for function in &mut program.functions {
+            if builtin_exit {
+                Self::prune_after_exit(&mut function.body);
+            }
             self.eliminate_dead_code_in_function(function);
         }
     }
 
+    /// Отбрасывает операторы после exit(...): до них выполнение не дойдёт
+    fn prune_after_exit(statements: &mut Vec<Statement>) {
+        let exit_position = statements.iter().position(|statement| {
+            matches!(statement, Statement::Expression(Expression::FunctionCall { name, .. }, _) if name == "exit")
+        });
+        if let Some(position) = exit_position {
+            statements.truncate(position + 1);
+        }
+        for statement in statements {
+            match statement {
+                Statement::Block { statements, .. }
+                | Statement::While { body: statements, .. }
+                | Statement::Loop { body: statements, .. }
+                | Statement::For { body: statements, .. } => {
+                    Self::prune_after_exit(statements);
+                }
+                Statement::If { then_branch, else_branch, .. } => {
+                    Self::prune_after_exit(then_branch);
+                    if let Some(else_branch) = else_branch {
+                        Self::prune_after_exit(else_branch);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     fn eliminate_dead_code_in_function(&self, function: &mut Function) {
         let mut used_variables = HashMap::new();
         let mut new_body = Vec::new();
@@ -323,15 +714,20 @@ impl Optimizer {
                 self.analyze_expression_usage(left, used_variables);
                 self.analyze_expression_usage(right, used_variables);
             }
-            Expression::FunctionCall { args, .. } => {
+            Expression::FunctionCall { name, args } => {
+                // Вызов может идти через переменную-функцию
+                *used_variables.entry(name.clone()).or_insert(0) += 1;
                 for arg in args {
                     self.analyze_expression_usage(arg, used_variables);
                 }
             }
-            Expression::StructInitialization { fields, .. } => {
+            Expression::StructInitialization { fields, base, .. } => {
                 for (_, expr) in fields {
                     self.analyze_expression_usage(expr, used_variables);
                 }
+                if let Some(base) = base {
+                    self.analyze_expression_usage(base, used_variables);
+                }
             }
             Expression::FieldAccess { expression, .. } => {
                 self.analyze_expression_usage(expression, used_variables);
@@ -339,9 +735,57 @@ impl Optimizer {
             Expression::Move { expression } => {
                 self.analyze_expression_usage(expression, used_variables);
             }
-            Expression::Borrow { expression, .. } => {
+            Expression::Borrow { expression, .. } | Expression::Ref { expression } => {
                 self.analyze_expression_usage(expression, used_variables);
             }
+            Expression::ArrayLiteral { elements } => {
+                for element in elements {
+                    self.analyze_expression_usage(element, used_variables);
+                }
+            }
+            Expression::ArrayRepeat { value, .. } => {
+                self.analyze_expression_usage(value, used_variables);
+            }
+            Expression::Index { expression, index } => {
+                self.analyze_expression_usage(expression, used_variables);
+                self.analyze_expression_usage(index, used_variables);
+            }
+            Expression::Slice { expression, start, end } => {
+                self.analyze_expression_usage(expression, used_variables);
+                for bound in [start, end].into_iter().flatten() {
+                    self.analyze_expression_usage(bound, used_variables);
+                }
+            }
+            Expression::EnumVariant { args, .. } => {
+                for arg in args {
+                    self.analyze_expression_usage(arg, used_variables);
+                }
+            }
+            Expression::Match { subject, arms } => {
+                self.analyze_expression_usage(subject, used_variables);
+                for arm in arms {
+                    self.analyze_expression_usage(&arm.body, used_variables);
+                }
+            }
+            Expression::If { condition, then_branch, else_branch } => {
+                for expression in [condition, then_branch, else_branch] {
+                    self.analyze_expression_usage(expression, used_variables);
+                }
+            }
+            Expression::MethodCall { receiver, args, .. } => {
+                self.analyze_expression_usage(receiver, used_variables);
+                for arg in args {
+                    self.analyze_expression_usage(arg, used_variables);
+                }
+            }
+            Expression::Try { expression } => {
+                self.analyze_expression_usage(expression, used_variables);
+            }
+            Expression::Lambda { body, .. } => {
+                for stmt in body {
+                    self.analyze_variable_usage(stmt, used_variables);
+                }
+            }
             _ => {}
         }
     }
@@ -362,17 +806,27 @@ impl Optimizer {
 
     fn should_inline_function(&self, function: &Function) -> bool {
         // Инлайним только маленькие функции (до 5 statements)
+        // Обобщённая функция ещё не знает своих типов, см. generics.rs
+        function.type_params.is_empty() &&
         function.body.len() <= 5 && 
         !function.name.starts_with("print") && // Не инлайним функции ввода-вывода
-        function.params.len() <= 3
+        function.params.len() <= 3 &&
+        // Контракты проверяются при вызове, их нельзя потерять при встраивании
+        function.requires().chain(function.ensures()).next().is_none() &&
+        // `?` после встраивания вернул бы из вызывающей функции
+        !uses_try(function) &&
+        // а defer выполнился бы при выходе из её блока, а не после вызова
+        !function.body.iter().any(|statement| matches!(statement, Statement::Defer { .. }))
     }
 
     fn inline_functions_in_body(&self, function: &mut Function, function_map: &HashMap<String, Function>) {
         let mut new_body = Vec::new();
         
         for statement in function.body.drain(..) {
+            // Своя область видимости: параметры двух встроенных вызовов
+            // не сталкиваются между собой и с переменными вызывающей функции
             if let Some(inlined) = self.try_inline_statement(&statement, function_map) {
-                new_body.extend(inlined);
+                new_body.push(Statement::Block { statements: inlined, position: statement.position() });
             } else {
                 new_body.push(statement);
             }
@@ -380,4 +834,68 @@ impl Optimizer {
         
         function.body = new_body;
     }
+}
+
+/// Значение целого литерала, если оно помещается в i32
+fn i32_literal(expression: &Expression) -> Option<i32> {
+    match expression {
+        Expression::IntegerLiteral(value) => i32::try_from(*value).ok(),
+        _ => None,
+    }
+}
+
+fn contains_return(statement: &Statement) -> bool {
+    struct ReturnFinder(bool);
+
+    impl Visitor for ReturnFinder {
+        fn visit_statement(&mut self, statement: &Statement) {
+            if let Statement::Return { .. } = statement {
+                self.0 = true;
+            }
+            walk_statement(self, statement);
+        }
+    }
+
+    let mut finder = ReturnFinder(false);
+    finder.visit_statement(statement);
+    finder.0
+}
+
+/// Вызовы sin/cos -> fast_sin/fast_cos, если ни одно из имён не перекрыто
+struct FastMath {
+    shadowed: HashSet<String>,
+}
+
+impl VisitorMut for FastMath {
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        if let Expression::FunctionCall { name, .. } = expression {
+            let fast = match name.as_str() {
+                "sin" => Some("fast_sin"),
+                "cos" => Some("fast_cos"),
+                _ => None,
+            };
+            // Перекрыта может быть и сама fast_sin
+            if let Some(fast) = fast.filter(|fast| !self.shadowed.contains(name) && !self.shadowed.contains(*fast)) {
+                *name = fast.to_string();
+            }
+        }
+        walk_expression_mut(self, expression);
+    }
+}
+
+fn uses_try(function: &Function) -> bool {
+    struct TryFinder(bool);
+
+    impl Visitor for TryFinder {
+        fn visit_expression(&mut self, expression: &Expression) {
+            if let Expression::Try { .. } = expression {
+                self.0 = true;
+            }
+            walk_expression(self, expression);
+        }
+    }
+
+    let mut finder = TryFinder(false);
+    finder.visit_function(function);
+    finder.0
 }
\ No newline at end of file
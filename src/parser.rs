@@ -1,4 +1,5 @@
 use crate::ast::*;
+use crate::edition::Edition;
 use crate::lexer::{Lexer, Token};
 use thiserror::Error;
 
@@ -12,6 +13,31 @@ pub enum ParseError {
     
     #[error("Invalid syntax: {message}")]
     InvalidSyntax { message: String },
+    
+    #[error("Invalid character {text:?} at line {line}, column {column}")]
+    InvalidCharacter { text: String, line: usize, column: usize },
+    
+    #[error("Unterminated block comment starting at line {line}, column {column}")]
+    UnterminatedComment { line: usize, column: usize },
+    
+    #[error("Unknown escape sequence in string {text} at line {line}, column {column}")]
+    InvalidEscape { text: String, line: usize, column: usize },
+    
+    #[error("Invalid color literal {text} at line {line}, column {column}: expected #RRGGBB")]
+    InvalidColor { text: String, line: usize, column: usize },
+}
+
+impl ParseError {
+    /// Строка и колонка ошибки, если лексер её указал
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            ParseError::InvalidCharacter { line, column, .. }
+            | ParseError::UnterminatedComment { line, column }
+            | ParseError::InvalidEscape { line, column, .. }
+            | ParseError::InvalidColor { line, column, .. } => Some((*line, *column)),
+            _ => None,
+        }
+    }
 }
 
 type ParseResult<T> = Result<T, ParseError>;
@@ -20,24 +46,100 @@ pub struct Parser<'a> {
     lexer: Lexer<'a>,
     current_token: Option<Token>,
     peek_token: Option<Token>,
+    current_line: usize,
+    peek_line: usize,
+    current_column: usize,
+    peek_column: usize,
+    edition: Edition,
+    // В заголовке `for` за `n {` следует тело цикла, а не литерал структуры
+    struct_literals_allowed: bool,
+    // Первая ошибка лексера: неизвестный символ или незакрытый `/*`
+    lexer_error: Option<ParseError>,
+    // Сколько значений блоков (`{ a + b }`) разобрано; по приросту счётчика
+    // видно, что оператор заканчивается значением
+    block_values: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
-        let mut lexer = Lexer::new(input);
-        let current_token = lexer.next();
-        let peek_token = lexer.next();
-        
-        Self {
+        Self::with_edition(input, Edition::default())
+    }
+
+    pub fn with_edition(input: &'a str, edition: Edition) -> Self {
+        let lexer = Lexer::with_edition(input, edition);
+        let mut parser = Self {
             lexer,
-            current_token,
-            peek_token,
+            current_token: None,
+            peek_token: None,
+            current_line: 0,
+            peek_line: 0,
+            current_column: 0,
+            peek_column: 0,
+            edition,
+            struct_literals_allowed: true,
+            lexer_error: None,
+            block_values: 0,
+        };
+        parser.next_token();
+        parser.next_token();
+        parser
+    }
+
+    fn split_token(token: Option<(Token, usize)>) -> (Option<Token>, usize) {
+        match token {
+            Some((token, line)) => (Some(token), line),
+            None => (None, 0),
         }
     }
 
+    /// Строка текущего токена (с 1)
+    pub fn current_line(&self) -> usize {
+        self.current_line
+    }
+
+    /// Строка и столбец текущего токена - начало разбираемого оператора
+    fn position(&self) -> Position {
+        Position { line: self.current_line, column: self.current_column }
+    }
+
+    pub fn edition(&self) -> Edition {
+        self.edition
+    }
+
     fn next_token(&mut self) {
         self.current_token = self.peek_token.take();
-        self.peek_token = self.lexer.next();
+        self.current_line = self.peek_line;
+        self.current_column = self.peek_column;
+        let (peek_token, peek_line) = Self::split_token(self.lexer.next_with_line());
+        if self.lexer_error.is_none() {
+            self.lexer_error = match &peek_token {
+                // Закрытая строка не стала токеном только из-за escape-последовательности
+                Some(Token::Invalid(text)) if text.len() > 1 && text.ends_with('"')
+                    && (text.starts_with('"') || text.starts_with("f\"")) => Some(ParseError::InvalidEscape {
+                    text: text.clone(),
+                    line: peek_line,
+                    column: self.lexer.column(),
+                }),
+                Some(Token::Invalid(text)) if text.len() > 1 && text.starts_with('#') => Some(ParseError::InvalidColor {
+                    text: text.clone(),
+                    line: peek_line,
+                    column: self.lexer.column(),
+                }),
+                Some(Token::Invalid(text)) => Some(ParseError::InvalidCharacter {
+                    text: text.clone(),
+                    line: peek_line,
+                    column: self.lexer.column(),
+                }),
+                Some(Token::UnterminatedComment) => Some(ParseError::UnterminatedComment {
+                    line: peek_line,
+                    column: self.lexer.column(),
+                }),
+                _ => None,
+            };
+        }
+        self.peek_token = peek_token;
+        self.peek_line = peek_line;
+        self.peek_column = self.lexer.column();
     }
 
     fn expect_token(&mut self, expected: Token) -> ParseResult<()> {
@@ -87,52 +189,216 @@ impl<'a> Parser<'a> {
     }
 
     pub fn parse_program(&mut self) -> ParseResult<Program> {
+        let result = self.parse_declarations();
+        // Ошибка разбора после неизвестного символа или незакрытого
+        // комментария - их следствие, поэтому сообщаем о них самих
+        match self.lexer_error.take() {
+            Some(error) => Err(error),
+            None => result,
+        }
+    }
+
+    fn parse_declarations(&mut self) -> ParseResult<Program> {
         let mut functions = Vec::new();
         let mut structs = Vec::new();
+        let mut enums = Vec::new();
+        let mut imports = Vec::new();
+        let mut consts = Vec::new();
+        let mut statics = Vec::new();
+        let mut traits = Vec::new();
+        let mut trait_impls = Vec::new();
         
         while self.current_token.is_some() {
+            // Аннотации и `pub` относятся к следующему объявлению
+            let line = self.current_line;
+            let mut attributes = self.parse_attributes()?;
+            let is_public = self.parse_visibility();
+            if self.parse_const_fn() {
+                attributes.push(Attribute::Const);
+            }
+            
             match &self.current_token {
+                Some(Token::KeywordFn) if attributes.iter().any(|attribute| matches!(attribute, Attribute::Derive(_))) => {
+                    return Err(ParseError::InvalidSyntax {
+                        message: "@derive is only allowed on structs".to_string(),
+                    });
+                }
                 Some(Token::KeywordFn) => {
-                    functions.push(self.parse_function()?);
+                    let mut function = self.parse_function(None)?;
+                    function.is_public = is_public;
+                    function.line = line;
+                    function.attributes = attributes;
+                    functions.push(function);
                 }
                 Some(Token::KeywordStruct) => {
-                    structs.push(self.parse_struct()?);
+                    let mut derives = Vec::new();
+                    for attribute in attributes {
+                        match attribute {
+                            Attribute::Derive(names) => derives.extend(names),
+                            _ => {
+                                return Err(ParseError::InvalidSyntax {
+                                    message: "Only @derive is allowed on structs".to_string(),
+                                });
+                            }
+                        }
+                    }
+                    let mut struct_def = self.parse_struct()?;
+                    struct_def.is_public = is_public;
+                    struct_def.line = line;
+                    struct_def.derives = derives;
+                    structs.push(struct_def);
                     
                     if self.current_token_is(&Token::Semicolon) {
                         self.next_token();
                     }
                 }
+                _ if !attributes.is_empty() => {
+                    return Err(ParseError::InvalidSyntax {
+                        message: "Attributes are only allowed on functions and structs".to_string(),
+                    });
+                }
+                Some(Token::KeywordImpl) if !is_public => {
+                    let (methods, trait_impl) = self.parse_impl()?;
+                    functions.extend(methods);
+                    trait_impls.extend(trait_impl);
+                }
+                Some(Token::KeywordTrait) => {
+                    let mut trait_def = self.parse_trait()?;
+                    trait_def.is_public = is_public;
+                    trait_def.line = line;
+                    traits.push(trait_def);
+                }
+                Some(Token::KeywordImport) if !is_public => {
+                    imports.push(self.parse_import()?);
+                }
+                Some(Token::KeywordConst) => {
+                    let mut constant = self.parse_const()?;
+                    constant.is_public = is_public;
+                    constant.line = line;
+                    consts.push(constant);
+                }
+                Some(Token::KeywordStatic) => {
+                    let mut static_def = self.parse_static()?;
+                    static_def.is_public = is_public;
+                    static_def.line = line;
+                    statics.push(static_def);
+                }
+                Some(Token::KeywordEnum) => {
+                    let mut enum_def = self.parse_enum()?;
+                    enum_def.is_public = is_public;
+                    enum_def.line = line;
+                    enums.push(enum_def);
+                }
+                _ if is_public => {
+                    return Err(ParseError::InvalidSyntax {
+                        message: "Expected function, struct, enum, trait, const or static after `pub`".to_string(),
+                    });
+                }
                 Some(Token::Semicolon) => {
                     self.next_token();
                 }
                 _ => {
                     return Err(ParseError::InvalidSyntax {
-                        message: "Expected function or struct declaration".to_string(),
+                        message: "Expected function, struct, enum, trait, impl, const or static declaration".to_string(),
                     });
                 }
             }
         }
         
-        Ok(Program { functions, structs })
+        Ok(Program { functions, structs, enums, imports, consts, statics, traits, trait_impls })
+    }
+
+    fn parse_attributes(&mut self) -> ParseResult<Vec<Attribute>> {
+        let mut attributes = Vec::new();
+        
+        while self.current_token_is(&Token::At) {
+            self.next_token();
+            let name = self.expect_identifier()?;
+            let attribute = match name.as_str() {
+                "requires" | "ensures" => {
+                    self.expect_token(Token::ParenOpen)?;
+                    let condition = self.parse_expression()?;
+                    self.expect_token(Token::ParenClose)?;
+                    if name == "requires" {
+                        Attribute::Requires(condition)
+                    } else {
+                        Attribute::Ensures(condition)
+                    }
+                }
+                "derive" => {
+                    self.expect_token(Token::ParenOpen)?;
+                    let mut derives = Vec::new();
+                    while !self.current_token_is(&Token::ParenClose) {
+                        let derive_name = self.expect_identifier()?;
+                        derives.push(Derive::parse(&derive_name).ok_or_else(|| ParseError::InvalidSyntax {
+                            message: format!("Unknown derive: {}, expected debug, eq or clone", derive_name),
+                        })?);
+                        if self.current_token_is(&Token::Comma) {
+                            self.next_token();
+                        } else {
+                            break;
+                        }
+                    }
+                    self.expect_token(Token::ParenClose)?;
+                    Attribute::Derive(derives)
+                }
+                _ => Attribute::parse(&name).ok_or_else(|| ParseError::InvalidSyntax {
+                    message: format!("Unknown attribute: @{}", name),
+                })?,
+            };
+            attributes.push(attribute);
+        }
+        
+        Ok(attributes)
+    }
+
+    /// `const` перед `fn`; `const NAME: T = ...` остаётся константой
+    fn parse_const_fn(&mut self) -> bool {
+        if self.current_token_is(&Token::KeywordConst) && self.peek_token == Some(Token::KeywordFn) {
+            self.next_token();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_visibility(&mut self) -> bool {
+        if self.current_token_is(&Token::KeywordPub) {
+            self.next_token();
+            true
+        } else {
+            false
+        }
     }
 
     fn parse_struct(&mut self) -> ParseResult<Struct> {
+        let line = self.current_line;
         self.expect_token(Token::KeywordStruct)?;
         
         let name = self.expect_identifier()?;
+        let type_params = self.parse_type_params()?;
         
         self.expect_token(Token::BraceOpen)?;
         
         let mut fields = Vec::new();
         while !self.current_token_is(&Token::BraceClose) {
+            let is_public = self.parse_visibility();
             let field_name = self.expect_identifier()?;
             
             self.expect_token(Token::Colon)?;
             let field_type = self.parse_type()?;
+            let default = if self.current_token_is(&Token::OperatorAssign) {
+                self.next_token();
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
             
             fields.push(StructField {
                 name: field_name,
                 field_type,
+                is_public,
+                default,
             });
             
             if self.current_token_is(&Token::Comma) {
@@ -144,41 +410,340 @@ impl<'a> Parser<'a> {
         
         self.expect_token(Token::BraceClose)?;
         
-        Ok(Struct { name, fields })
+        Ok(Struct {
+            name,
+            fields,
+            is_public: false,
+            line,
+            derives: Vec::new(),
+            type_params,
+            module: 0,
+        })
+    }
+
+    fn parse_enum(&mut self) -> ParseResult<Enum> {
+        let line = self.current_line;
+        self.expect_token(Token::KeywordEnum)?;
+        
+        let name = self.expect_identifier()?;
+        
+        self.expect_token(Token::BraceOpen)?;
+        
+        let mut variants = Vec::new();
+        while !self.current_token_is(&Token::BraceClose) {
+            let variant_name = self.expect_identifier()?;
+            let mut fields = Vec::new();
+            if self.current_token_is(&Token::ParenOpen) {
+                self.next_token();
+                while !self.current_token_is(&Token::ParenClose) {
+                    fields.push(self.parse_type()?);
+                    if self.current_token_is(&Token::Comma) {
+                        self.next_token();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect_token(Token::ParenClose)?;
+            }
+            variants.push(Variant { name: variant_name, fields });
+            
+            if self.current_token_is(&Token::Comma) {
+                self.next_token();
+            } else {
+                break;
+            }
+        }
+        
+        self.expect_token(Token::BraceClose)?;
+        
+        Ok(Enum {
+            name,
+            variants,
+            is_public: false,
+            line,
+        })
     }
 
-    fn parse_function(&mut self) -> ParseResult<Function> {
+    /// `const NAME: type = value;`
+    fn parse_const(&mut self) -> ParseResult<Const> {
+        self.expect_token(Token::KeywordConst)?;
+        let name = self.expect_identifier()?;
+        self.expect_token(Token::Colon)?;
+        let const_type = self.parse_type()?;
+        self.expect_token(Token::OperatorAssign)?;
+        let value = self.parse_expression()?;
+        self.expect_token(Token::Semicolon)?;
+        Ok(Const {
+            name,
+            const_type,
+            value,
+            is_public: false,
+            line: 0,
+        })
+    }
+
+    /// `static NAME: type = value;` или `static mut NAME: type = value;`
+    fn parse_static(&mut self) -> ParseResult<Static> {
+        self.expect_token(Token::KeywordStatic)?;
+        let mutable = self.current_token_is(&Token::KeywordMut);
+        if mutable {
+            self.next_token();
+        }
+        let name = self.expect_identifier()?;
+        self.expect_token(Token::Colon)?;
+        let static_type = self.parse_type()?;
+        self.expect_token(Token::OperatorAssign)?;
+        let value = self.parse_expression()?;
+        self.expect_token(Token::Semicolon)?;
+        Ok(Static {
+            name,
+            static_type,
+            value,
+            mutable,
+            is_public: false,
+            line: 0,
+        })
+    }
+
+    /// `import "path/math.aetos";` или `import math;` (то же, что "math.aetos")
+    fn parse_import(&mut self) -> ParseResult<Import> {
+        let line = self.current_line;
+        self.expect_token(Token::KeywordImport)?;
+        let path = match &self.current_token {
+            Some(Token::StringLiteral(path)) => path.clone(),
+            Some(Token::Identifier(name)) => format!("{}.aetos", name),
+            other => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "module path".to_string(),
+                    found: other.as_ref().map_or("EOF".to_string(), |t| format!("{:?}", t)),
+                });
+            }
+        };
+        self.next_token();
+        self.expect_token(Token::Semicolon)?;
+        Ok(Import { path, line })
+    }
+
+    /// `<T, U>` после имени функции или структуры; без `<` - пустой список
+    fn parse_type_params(&mut self) -> ParseResult<Vec<String>> {
+        let mut params = Vec::new();
+        if !self.current_token_is(&Token::OperatorLt) {
+            return Ok(params);
+        }
+        self.next_token();
+        while !self.current_token_is(&Token::OperatorGt) {
+            params.push(self.expect_identifier()?);
+            if self.current_token_is(&Token::Comma) {
+                self.next_token();
+            } else {
+                break;
+            }
+        }
+        self.expect_token(Token::OperatorGt)?;
+        Ok(params)
+    }
+
+    /// `impl Point { fn length(self) -> f32 { ... } }` - функции блока
+    /// становятся функциями `Point::length`; `impl Drawable for Point`
+    /// дополнительно записывает, что Point реализует трейт.
+    /// `impl<T> Pair<T> { ... }` - методы обобщённой структуры
+    fn parse_impl(&mut self) -> ParseResult<(Vec<Function>, Option<TraitImpl>)> {
+        let line = self.current_line;
+        self.expect_token(Token::KeywordImpl)?;
+        let type_params = self.parse_type_params()?;
+        let mut type_name = self.expect_identifier()?;
+        let mut type_args = self.parse_type_params()?;
+        let mut trait_name = None;
+        if self.current_token_is(&Token::KeywordFor) {
+            self.next_token();
+            if !type_params.is_empty() {
+                return Err(ParseError::InvalidSyntax {
+                    message: format!("Trait {} cannot be implemented for a generic struct", type_name),
+                });
+            }
+            trait_name = Some(std::mem::replace(&mut type_name, self.expect_identifier()?));
+            type_args = self.parse_type_params()?;
+        }
+        // Аргументы типа повторяют параметры блока: impl<T> Pair<T>
+        if type_args != type_params {
+            return Err(ParseError::InvalidSyntax {
+                message: format!("impl block for {} must list the type parameters as impl<{}> {}<{}>",
+                    type_name, type_args.join(", "), type_name, type_args.join(", ")),
+            });
+        }
+        let self_type = if type_params.is_empty() {
+            Type::Struct(type_name.clone())
+        } else {
+            Type::Generic(type_name.clone(), type_params.iter().map(|param| Type::Struct(param.clone())).collect())
+        };
+        self.expect_token(Token::BraceOpen)?;
+        
+        let mut methods = Vec::new();
+        while !self.current_token_is(&Token::BraceClose) {
+            let line = self.current_line;
+            let mut attributes = self.parse_attributes()?;
+            let is_public = self.parse_visibility();
+            if self.parse_const_fn() {
+                attributes.push(Attribute::Const);
+            }
+            let mut method = self.parse_function(Some(&self_type))?;
+            method.name = method_name(&type_name, &method.name);
+            method.is_public = is_public;
+            method.line = line;
+            method.attributes = attributes;
+            method.type_params = type_params.clone();
+            methods.push(method);
+        }
+        
+        self.expect_token(Token::BraceClose)?;
+        let trait_impl = trait_name.map(|trait_name| TraitImpl {
+            methods: methods.iter()
+                .filter_map(|method| split_method_name(&method.name))
+                .map(|(_, method)| method.to_string())
+                .collect(),
+            trait_name,
+            type_name,
+            line,
+        });
+        Ok((methods, trait_impl))
+    }
+
+    /// `trait Drawable { fn draw(self) -> void; }` - только сигнатуры
+    fn parse_trait(&mut self) -> ParseResult<Trait> {
+        self.expect_token(Token::KeywordTrait)?;
+        let name = self.expect_identifier()?;
+        self.expect_token(Token::BraceOpen)?;
+        
+        let mut methods = Vec::new();
+        while !self.current_token_is(&Token::BraceClose) {
+            self.expect_token(Token::KeywordFn)?;
+            let method = self.expect_identifier()?;
+            self.expect_token(Token::ParenOpen)?;
+            let params = self.parse_parameters(Some(&Type::Struct(name.clone())))?;
+            self.expect_token(Token::ParenClose)?;
+            if params.first().is_none_or(|param| param.name != "self") {
+                return Err(ParseError::InvalidSyntax {
+                    message: format!("Trait method {}::{} must take self as its first parameter", name, method),
+                });
+            }
+            self.expect_token(Token::Arrow)?;
+            let return_type = self.parse_type()?;
+            self.expect_token(Token::Semicolon)?;
+            methods.push(TraitMethod { name: method, params, return_type });
+        }
+        
+        self.expect_token(Token::BraceClose)?;
+        Ok(Trait {
+            name,
+            methods,
+            is_public: false,
+            line: 0,
+        })
+    }
+
+    /// `impl_type` - тип impl-блока, в котором первым параметром может быть `self`
+    fn parse_function(&mut self, impl_type: Option<&Type>) -> ParseResult<Function> {
+        let line = self.current_line;
         self.expect_token(Token::KeywordFn)?;
         
         let name = self.expect_identifier()?;
+        let type_params = self.parse_type_params()?;
+        // Тип метода выводится из self, для собственных параметров неоткуда
+        if let (Some(impl_type), false) = (impl_type, type_params.is_empty()) {
+            return Err(ParseError::InvalidSyntax {
+                message: format!("Method {} of {} cannot declare type parameters", name, impl_type),
+            });
+        }
         
         self.expect_token(Token::ParenOpen)?;
-        let params = self.parse_parameters()?;
+        let params = self.parse_parameters(impl_type)?;
         self.expect_token(Token::ParenClose)?;
         
         self.expect_token(Token::Arrow)?;
         let return_type = self.parse_type()?;
         
-        self.expect_token(Token::BraceOpen)?;
-        let body = self.parse_block()?;
-        self.expect_token(Token::BraceClose)?;
+        let body = self.parse_function_body(&return_type)?;
         
         Ok(Function {
             name,
             params,
             return_type,
             body,
+            is_public: false,
+            line,
+            attributes: Vec::new(),
+            type_params,
+            module: 0,
         })
     }
 
-    fn parse_parameters(&mut self) -> ParseResult<Vec<Parameter>> {
+    /// `{ ... }` тела функции или замыкания
+    fn parse_function_body(&mut self, return_type: &Type) -> ParseResult<Vec<Statement>> {
+        // Значения блоков снаружи не относятся к телу (и наоборот)
+        let values_before = self.block_values;
+        let struct_literals_allowed = self.struct_literals_allowed;
+        self.struct_literals_allowed = true;
+        
+        self.expect_token(Token::BraceOpen)?;
+        let mut body = self.parse_block()?;
+        self.expect_token(Token::BraceClose)?;
+        
+        self.block_values = values_before;
+        self.struct_literals_allowed = struct_literals_allowed;
+        
+        // match в конце тела функции с результатом - её значение
+        if *return_type != Type::Void {
+            if let Some(Statement::Expression(expr @ Expression::Match { .. }, position)) = body.last() {
+                let (value, position) = (expr.clone(), *position);
+                *body.last_mut().unwrap() = Statement::Return { value, position };
+            }
+        }
+        
+        Ok(body)
+    }
+
+    /// `|x: i32, y: i32| -> i32 { x + y }` или `|| -> void { ... }`, первый токен уже разобран
+    fn parse_lambda(&mut self, has_params: bool) -> ParseResult<Expression> {
+        let mut params = Vec::new();
+        if has_params {
+            while !self.current_token_is(&Token::Pipe) {
+                let name = self.expect_identifier()?;
+                self.expect_token(Token::Colon)?;
+                let param_type = self.parse_type()?;
+                params.push(Parameter { name, param_type });
+                
+                if self.current_token_is(&Token::Comma) {
+                    self.next_token();
+                } else {
+                    break;
+                }
+            }
+            self.expect_token(Token::Pipe)?;
+        }
+        
+        self.expect_token(Token::Arrow)?;
+        let return_type = self.parse_type()?;
+        let body = self.parse_function_body(&return_type)?;
+        
+        Ok(Expression::Lambda { params, return_type, body })
+    }
+
+    fn parse_parameters(&mut self, impl_type: Option<&Type>) -> ParseResult<Vec<Parameter>> {
         let mut params = Vec::new();
         
         while !self.current_token_is(&Token::ParenClose) {
             let name = self.expect_identifier()?;
             
-            self.expect_token(Token::Colon)?;
-            let param_type = self.parse_type()?;
+            // `self` без типа - значение типа impl-блока
+            let param_type = match impl_type {
+                Some(impl_type) if name == "self" && params.is_empty() && !self.current_token_is(&Token::Colon) => {
+                    impl_type.clone()
+                }
+                _ => {
+                    self.expect_token(Token::Colon)?;
+                    self.parse_type()?
+                }
+            };
             
             params.push(Parameter { name, param_type });
             
@@ -201,7 +766,85 @@ impl<'a> Parser<'a> {
             Some(Token::KeywordBool) => Type::Bool,
             Some(Token::KeywordString) => Type::String,
             Some(Token::KeywordVoid) => Type::Void,
+            Some(Token::Identifier(ref name)) if name == "ref" => {
+                self.next_token();
+                return Ok(Type::Ref(Box::new(self.parse_type()?)));
+            }
+            // Option<T>, Result<T, E>
+            Some(Token::Identifier(ref name)) if (name == "Option" || name == "Result") && self.peek_token_is(&Token::OperatorLt) => {
+                let is_option = name == "Option";
+                self.next_token();
+                self.next_token();
+                let inner = self.parse_type()?;
+                let parsed = if is_option {
+                    Type::Option(Box::new(inner))
+                } else {
+                    self.expect_token(Token::Comma)?;
+                    Type::Result(Box::new(inner), Box::new(self.parse_type()?))
+                };
+                // `>>` в Option<Option<i32>> закрывает сразу два типа
+                if self.current_token_is(&Token::OperatorShr) {
+                    self.current_token = Some(Token::OperatorGt);
+                } else {
+                    self.expect_token(Token::OperatorGt)?;
+                }
+                return Ok(parsed);
+            }
+            // Pair<i32> - обобщённая структура
+            Some(Token::Identifier(ref name)) if self.peek_token_is(&Token::OperatorLt) => {
+                let name = name.clone();
+                self.next_token();
+                self.next_token();
+                let mut args = vec![self.parse_type()?];
+                while self.current_token_is(&Token::Comma) {
+                    self.next_token();
+                    args.push(self.parse_type()?);
+                }
+                if self.current_token_is(&Token::OperatorShr) {
+                    self.current_token = Some(Token::OperatorGt);
+                } else {
+                    self.expect_token(Token::OperatorGt)?;
+                }
+                return Ok(Type::Generic(name, args));
+            }
             Some(Token::Identifier(ref name)) => Type::Struct(name.clone()),
+            Some(Token::BracketOpen) => {
+                self.next_token();
+                let element = self.parse_type()?;
+                if self.current_token_is(&Token::Semicolon) {
+                    self.next_token();
+                    let length = self.parse_array_length()?;
+                    self.expect_token(Token::BracketClose)?;
+                    return Ok(Type::FixedArray(Box::new(element), length));
+                }
+                self.expect_token(Token::BracketClose)?;
+                return Ok(Type::Array(Box::new(element)));
+            }
+            Some(Token::OperatorBitAnd) if self.peek_token_is(&Token::BracketOpen) => {
+                self.next_token();
+                self.next_token();
+                let element = self.parse_type()?;
+                self.expect_token(Token::BracketClose)?;
+                return Ok(Type::Slice(Box::new(element)));
+            }
+            // fn(i32, i32) -> i32
+            Some(Token::KeywordFn) => {
+                self.next_token();
+                self.expect_token(Token::ParenOpen)?;
+                let mut params = Vec::new();
+                while !self.current_token_is(&Token::ParenClose) {
+                    params.push(self.parse_type()?);
+                    if self.current_token_is(&Token::Comma) {
+                        self.next_token();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect_token(Token::ParenClose)?;
+                self.expect_token(Token::Arrow)?;
+                let return_type = self.parse_type()?;
+                return Ok(Type::Function(params, Box::new(return_type)));
+            }
             _ => return Err(ParseError::UnexpectedToken {
                 expected: "type".to_string(),
                 found: self.current_token
@@ -214,17 +857,40 @@ impl<'a> Parser<'a> {
         Ok(token_type)
     }
 
+    /// Длина в `[i32; 64]` и `[0; 64]` - целый литерал не меньше нуля
+    fn parse_array_length(&mut self) -> ParseResult<usize> {
+        match self.current_token.take() {
+            Some(Token::IntegerLiteral(value)) if value >= 0 => {
+                self.next_token();
+                Ok(value as usize)
+            }
+            Some(token) => Err(ParseError::UnexpectedToken {
+                expected: "array length".to_string(),
+                found: format!("{:?}", token),
+            }),
+            None => Err(ParseError::UnexpectedEof),
+        }
+    }
+
     fn parse_block(&mut self) -> ParseResult<Vec<Statement>> {
         let mut statements = Vec::new();
         
         while !self.current_token_is(&Token::BraceClose) {
-            statements.push(self.parse_statement()?);
+            let values_before = self.block_values;
+            let statement = self.parse_statement()?;
+            if self.block_values != values_before && !self.current_token_is(&Token::BraceClose) {
+                return Err(ParseError::InvalidSyntax {
+                    message: "Block value must be the last statement of the block".to_string(),
+                });
+            }
+            statements.push(statement);
         }
         
         Ok(statements)
     }
 
     fn parse_statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
         // Проверяем, является ли это присваиванием (идентификатор, за которым следует =)
         if let Some(Token::Identifier(_)) = &self.current_token {
             if let Some(Token::OperatorAssign) = &self.peek_token {
@@ -236,6 +902,7 @@ impl<'a> Parser<'a> {
                 return Ok(Statement::Assignment {
                     name,
                     value,
+                    position,
                 });
             }
         }
@@ -245,19 +912,112 @@ impl<'a> Parser<'a> {
             Some(Token::KeywordLet) => self.parse_variable_declaration(),
             Some(Token::KeywordReturn) => self.parse_return_statement(),
             Some(Token::KeywordIf) => self.parse_if_statement(),
-            Some(Token::KeywordWhile) => self.parse_while_statement(),
+            Some(Token::KeywordWhile) => self.parse_while_statement(None, position),
+            Some(Token::KeywordFor) => self.parse_for_statement(None, position),
+            Some(Token::KeywordLoop) => self.parse_loop_statement(None, position),
+            Some(Token::Label(_)) => self.parse_labeled_loop(),
+            Some(Token::KeywordBreak) | Some(Token::KeywordContinue) => self.parse_loop_exit(),
+            Some(Token::KeywordDefer) => self.parse_defer_statement(),
             Some(Token::BraceOpen) => self.parse_block_statement(),
             
             // Для всех остальных случаев - это выражение
             _ => {
-                let expr = self.parse_expression()?;
+                let expr = self.parse_logical_or()?;
+                
+                // buf[i] = value;
+                if let Expression::Index { expression, index } = &expr {
+                    if let Expression::Variable(name) = &**expression {
+                        let operator = self.current_token.as_ref().and_then(Self::compound_operator);
+                        if operator.is_some() || self.current_token_is(&Token::OperatorAssign) {
+                            self.next_token();
+                            let mut value = self.parse_expression()?;
+                            // buf[i] += value; - то же, что buf[i] = buf[i] + value;
+                            if let Some(operator) = operator {
+                                value = Expression::BinaryExpression {
+                                    left: Box::new(expr.clone()),
+                                    operator,
+                                    right: Box::new(value),
+                                };
+                            }
+                            self.expect_token(Token::Semicolon)?;
+                            return Ok(Statement::IndexAssignment { name: name.clone(), index: (**index).clone(), value, position });
+                        }
+                    }
+                }
+                
+                // a.b.c = value;
+                if self.current_token_is(&Token::OperatorAssign) {
+                    let (name, fields) = Self::field_path(expr)?;
+                    self.next_token();
+                    let value = self.parse_expression()?;
+                    self.expect_token(Token::Semicolon)?;
+                    if fields.is_empty() {
+                        return Ok(Statement::Assignment { name, value, position });
+                    }
+                    return Ok(Statement::FieldAssignment { name, fields, value, position });
+                }
+                
+                // a.b += value; - то же, что a.b = a.b + value;
+                if let Some(operator) = self.current_token.as_ref().and_then(Self::compound_operator) {
+                    let (name, fields) = Self::field_path(expr.clone())?;
+                    self.next_token();
+                    let value = Expression::BinaryExpression {
+                        left: Box::new(expr),
+                        operator,
+                        right: Box::new(self.parse_expression()?),
+                    };
+                    self.expect_token(Token::Semicolon)?;
+                    if fields.is_empty() {
+                        return Ok(Statement::Assignment { name, value, position });
+                    }
+                    return Ok(Statement::FieldAssignment { name, fields, value, position });
+                }
+                
+                // match на месте оператора, как и блок, не требует `;`
+                if matches!(expr, Expression::Match { .. }) && !self.current_token_is(&Token::Semicolon) {
+                    return Ok(Statement::Expression(expr, position));
+                }
+                
+                // Выражение без `;` в конце блока - его значение, как в Rust.
+                // Значение блока в хвосте функции возвращается из неё
+                if self.current_token_is(&Token::BraceClose) {
+                    self.block_values += 1;
+                    return Ok(Statement::Return { value: expr, position });
+                }
                 self.expect_token(Token::Semicolon)?;
-                Ok(Statement::Expression(expr))
+                Ok(Statement::Expression(expr, position))
+            }
+        }
+    }
+
+    fn compound_operator(token: &Token) -> Option<BinaryOperator> {
+        match token {
+            Token::OperatorAddAssign => Some(BinaryOperator::Add),
+            Token::OperatorSubtractAssign => Some(BinaryOperator::Subtract),
+            Token::OperatorMultiplyAssign => Some(BinaryOperator::Multiply),
+            Token::OperatorDivideAssign => Some(BinaryOperator::Divide),
+            Token::OperatorModuloAssign => Some(BinaryOperator::Mod),
+            _ => None,
+        }
+    }
+
+    /// Разбирает цепочку `name.field1.field2` в левой части присваивания
+    fn field_path(expr: Expression) -> ParseResult<(String, Vec<String>)> {
+        match expr {
+            Expression::FieldAccess { expression, field_name } => {
+                let (name, mut fields) = Self::field_path(*expression)?;
+                fields.push(field_name);
+                Ok((name, fields))
             }
+            Expression::Variable(name) => Ok((name, Vec::new())),
+            _ => Err(ParseError::InvalidSyntax {
+                message: "Invalid assignment target".to_string(),
+            }),
         }
     }
 
     fn parse_variable_declaration(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
         self.expect_token(Token::KeywordLet)?;
     
         let mutable = if self.current_token_is(&Token::KeywordMut) {
@@ -269,6 +1029,10 @@ impl<'a> Parser<'a> {
     
         let name = self.expect_identifier()?;
     
+        if self.current_token_is(&Token::BraceOpen) {
+            return self.parse_struct_destructuring(name, mutable, position);
+        }
+    
         self.expect_token(Token::Colon)?;
         let var_type = self.parse_type()?;
     
@@ -282,10 +1046,46 @@ impl<'a> Parser<'a> {
             var_type,
             value,
             mutable,
+            position,
+        })
+    }
+
+    // let Point { x, y: py } = value;
+    fn parse_struct_destructuring(&mut self, struct_name: String, mutable: bool, position: Position) -> ParseResult<Statement> {
+        self.expect_token(Token::BraceOpen)?;
+    
+        let mut bindings = Vec::new();
+        while !self.current_token_is(&Token::BraceClose) {
+            let field = self.expect_identifier()?;
+            let binding = if self.current_token_is(&Token::Colon) {
+                self.next_token();
+                self.expect_identifier()?
+            } else {
+                field.clone()
+            };
+            bindings.push((field, binding));
+    
+            if !self.current_token_is(&Token::BraceClose) {
+                self.expect_token(Token::Comma)?;
+            }
+        }
+        self.expect_token(Token::BraceClose)?;
+    
+        self.expect_token(Token::OperatorAssign)?;
+        let value = self.parse_expression()?;
+        self.expect_token(Token::Semicolon)?;
+    
+        Ok(Statement::DestructureStruct {
+            struct_name,
+            bindings,
+            value,
+            mutable,
+            position,
         })
     }
 
     fn parse_return_statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
         self.expect_token(Token::KeywordReturn)?;
         
         let value = if self.current_token_is(&Token::Semicolon) {
@@ -296,21 +1096,26 @@ impl<'a> Parser<'a> {
         
         self.expect_token(Token::Semicolon)?;
         
-        Ok(Statement::Return { value })
+        Ok(Statement::Return { value, position })
     }
 
     fn parse_if_statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
         self.expect_token(Token::KeywordIf)?;
         
         let condition = self.parse_expression()?;
         
+        let values_before = self.block_values;
+        
         // Обрабатываем тело if (может быть блоком или одиночным statement)
         let then_branch = if self.current_token_is(&Token::BraceOpen) {
-            self.parse_block()?
+            self.parse_braced_block()?
         } else {
             // Одиночный statement без фигурных скобок
             vec![self.parse_statement()?]
         };
+        let then_value = self.block_values != values_before;
+        let values_before = self.block_values;
         
         let else_branch = if self.current_token_is(&Token::KeywordElse) {
             self.next_token();
@@ -319,40 +1124,150 @@ impl<'a> Parser<'a> {
                 let else_if_stmt = self.parse_if_statement()?;
                 Some(vec![else_if_stmt])
             } else if self.current_token_is(&Token::BraceOpen) {
-                Some(self.parse_block()?)
+                Some(self.parse_braced_block()?)
             } else {
                 // Одиночный statement без фигурных скобок
                 Some(vec![self.parse_statement()?])
             }
-        } else {
-            None
+        } else {
+            None
+        };
+        
+        // Значение даёт либо каждая ветка, либо ни одна
+        let else_value = self.block_values != values_before;
+        if then_value != else_value {
+            return Err(ParseError::InvalidSyntax {
+                message: "Both branches of if must end with a value, or neither".to_string(),
+            });
+        }
+        
+        Ok(Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            position,
+        })
+    }
+
+    // 'outer: while ... / 'outer: for ... / 'outer: loop ...
+    fn parse_labeled_loop(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
+        let label = match self.current_token.take() {
+            Some(Token::Label(label)) => label,
+            _ => unreachable!("parse_labeled_loop called without a label"),
+        };
+        self.next_token();
+        self.expect_token(Token::Colon)?;
+        match self.current_token {
+            Some(Token::KeywordWhile) => self.parse_while_statement(Some(label), position),
+            Some(Token::KeywordFor) => self.parse_for_statement(Some(label), position),
+            Some(Token::KeywordLoop) => self.parse_loop_statement(Some(label), position),
+            _ => Err(ParseError::InvalidSyntax {
+                message: format!("Label '{} must be followed by a loop", label),
+            }),
+        }
+    }
+
+    // break; continue; break 'outer;
+    fn parse_loop_exit(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
+        let is_break = self.current_token_is(&Token::KeywordBreak);
+        self.next_token();
+        let label = match &self.current_token {
+            Some(Token::Label(label)) => {
+                let label = label.clone();
+                self.next_token();
+                Some(label)
+            }
+            _ => None,
         };
-        
-        Ok(Statement::If {
-            condition,
-            then_branch,
-            else_branch,
+        self.expect_token(Token::Semicolon)?;
+        Ok(if is_break {
+            Statement::Break { label, position }
+        } else {
+            Statement::Continue { label, position }
         })
     }
 
-    fn parse_while_statement(&mut self) -> ParseResult<Statement> {
+    // defer close_file(handle);
+    fn parse_defer_statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
+        self.expect_token(Token::KeywordDefer)?;
+        let expression = self.parse_expression()?;
+        self.expect_token(Token::Semicolon)?;
+        Ok(Statement::Defer { expression, position })
+    }
+
+    /// `position` - начало оператора вместе с меткой цикла
+    fn parse_while_statement(&mut self, label: Option<String>, position: Position) -> ParseResult<Statement> {
         self.expect_token(Token::KeywordWhile)?;
         
         let condition = self.parse_expression()?;
+        let values_before = self.block_values;
         let body = if self.current_token_is(&Token::BraceOpen) {
-            self.parse_block()?
+            self.parse_braced_block()?
         } else {
             vec![self.parse_statement()?]
         };
+        self.reject_loop_value(values_before)?;
+        
+        Ok(Statement::While { condition, body, label, position })
+    }
+
+    fn parse_loop_statement(&mut self, label: Option<String>, position: Position) -> ParseResult<Statement> {
+        self.expect_token(Token::KeywordLoop)?;
+        let values_before = self.block_values;
+        let body = self.parse_braced_block()?;
+        self.reject_loop_value(values_before)?;
+        Ok(Statement::Loop { body, label, position })
+    }
+
+    fn parse_for_statement(&mut self, label: Option<String>, position: Position) -> ParseResult<Statement> {
+        self.expect_token(Token::KeywordFor)?;
+        let variable = self.expect_identifier()?;
+        self.expect_token(Token::KeywordIn)?;
+        
+        self.struct_literals_allowed = false;
+        let range = self.parse_range();
+        self.struct_literals_allowed = true;
+        let (start, end) = range?;
         
-        Ok(Statement::While { condition, body })
+        let values_before = self.block_values;
+        let body = self.parse_braced_block()?;
+        self.reject_loop_value(values_before)?;
+        Ok(Statement::For { variable, start, end, body, label, position })
+    }
+
+    /// Тело цикла не может заканчиваться значением: его некуда вернуть
+    fn reject_loop_value(&self, values_before: usize) -> ParseResult<()> {
+        if self.block_values != values_before {
+            return Err(ParseError::InvalidSyntax {
+                message: "Loop body cannot end with a value, add `;`".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn parse_range(&mut self) -> ParseResult<(Expression, Expression)> {
+        let start = self.parse_expression()?;
+        self.expect_token(Token::DotDot)?;
+        let end = self.parse_expression()?;
+        Ok((start, end))
+    }
+
+    fn parse_braced_block(&mut self) -> ParseResult<Vec<Statement>> {
+        self.expect_token(Token::BraceOpen)?;
+        let statements = self.parse_block()?;
+        self.expect_token(Token::BraceClose)?;
+        Ok(statements)
     }
 
     fn parse_block_statement(&mut self) -> ParseResult<Statement> {
+        let position = self.position();
         self.expect_token(Token::BraceOpen)?;
         let statements = self.parse_block()?;
         self.expect_token(Token::BraceClose)?;
-        Ok(Statement::Block { statements })
+        Ok(Statement::Block { statements, position })
     }
 
     fn parse_expression(&mut self) -> ParseResult<Expression> {
@@ -442,7 +1357,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_comparison(&mut self) -> ParseResult<Expression> {
-        let mut left = self.parse_term()?;
+        let mut left = self.parse_bit_or()?;
         
         while let Some(token) = &self.current_token {
             match token {
@@ -456,6 +1371,83 @@ impl<'a> Parser<'a> {
                     };
                     self.next_token();
                     
+                    let right = self.parse_bit_or()?;
+                    left = Expression::BinaryExpression {
+                        left: Box::new(left),
+                        operator,
+                        right: Box::new(right),
+                    };
+                }
+                _ => break,
+            }
+        }
+        
+        Ok(left)
+    }
+
+    // Побитовые операторы связывают сильнее сравнений, как в Rust:
+    // `flags & MASK == 0` - это `(flags & MASK) == 0`
+    fn parse_bit_or(&mut self) -> ParseResult<Expression> {
+        let mut left = self.parse_bit_xor()?;
+        
+        while let Some(Token::Pipe) = &self.current_token {
+            self.next_token();
+            let right = self.parse_bit_xor()?;
+            left = Expression::BinaryExpression {
+                left: Box::new(left),
+                operator: BinaryOperator::BitOr,
+                right: Box::new(right),
+            };
+        }
+        
+        Ok(left)
+    }
+
+    fn parse_bit_xor(&mut self) -> ParseResult<Expression> {
+        let mut left = self.parse_bit_and()?;
+        
+        while let Some(Token::OperatorBitXor) = &self.current_token {
+            self.next_token();
+            let right = self.parse_bit_and()?;
+            left = Expression::BinaryExpression {
+                left: Box::new(left),
+                operator: BinaryOperator::BitXor,
+                right: Box::new(right),
+            };
+        }
+        
+        Ok(left)
+    }
+
+    fn parse_bit_and(&mut self) -> ParseResult<Expression> {
+        let mut left = self.parse_shift()?;
+        
+        while let Some(Token::OperatorBitAnd) = &self.current_token {
+            self.next_token();
+            let right = self.parse_shift()?;
+            left = Expression::BinaryExpression {
+                left: Box::new(left),
+                operator: BinaryOperator::BitAnd,
+                right: Box::new(right),
+            };
+        }
+        
+        Ok(left)
+    }
+
+    fn parse_shift(&mut self) -> ParseResult<Expression> {
+        let mut left = self.parse_term()?;
+        
+        while let Some(token) = &self.current_token {
+            match token {
+                Token::OperatorShl | Token::OperatorShr => {
+                    let operator = match token {
+                        Token::OperatorShl => BinaryOperator::Shl,
+                        Token::OperatorShr => BinaryOperator::Shr,
+                        _ => unreachable!(),
+                    };
+                    self.next_token();
+                    
                     let right = self.parse_term()?;
                     left = Expression::BinaryExpression {
                         left: Box::new(left),
@@ -502,10 +1494,11 @@ impl<'a> Parser<'a> {
     
         while let Some(token) = &self.current_token {
             match token {
-                Token::OperatorMultiply | Token::OperatorDivide => {
+                Token::OperatorMultiply | Token::OperatorDivide | Token::OperatorModulo => {
                     let operator = match token {
                         Token::OperatorMultiply => BinaryOperator::Multiply,
                         Token::OperatorDivide => BinaryOperator::Divide,
+                        Token::OperatorModulo => BinaryOperator::Mod,
                         _ => unreachable!(),
                     };
                     self.next_token();
@@ -558,18 +1551,52 @@ impl<'a> Parser<'a> {
 
     fn parse_primary(&mut self) -> ParseResult<Expression> {
         match self.current_token.take() {
-            Some(Token::IntegerLiteral(value)) => {
+            // Символьный литерал - обычное i32 со значением кода символа
+            Some(Token::IntegerLiteral(value) | Token::CharLiteral(value)) => {
                 self.next_token();
-                Ok(Expression::IntegerLiteral(value))
+                Ok(Expression::IntegerLiteral(i64::from(value)))
             }
             Some(Token::FloatLiteral(value)) => {
                 self.next_token();
                 Ok(Expression::FloatLiteral(value))
             }
+            // 5i64 - литерал, приведённый к типу суффикса; 5i32 и 2f32 - обычные литералы
+            Some(Token::SuffixedInteger((value, ty))) => {
+                self.next_token();
+                let literal = Expression::IntegerLiteral(value);
+                Ok(match ty {
+                    Type::I64 => Expression::TypeCast { expression: Box::new(literal), target_type: ty },
+                    _ => literal,
+                })
+            }
+            Some(Token::SuffixedFloat((value, ty))) => {
+                self.next_token();
+                let literal = Expression::FloatLiteral(value as f32);
+                Ok(match ty {
+                    Type::F64 => Expression::TypeCast { expression: Box::new(literal), target_type: ty },
+                    _ => literal,
+                })
+            }
+            // #ff00aa - то же, что Color { r: 255, g: 0, b: 170 }
+            Some(Token::ColorLiteral(channels)) => {
+                self.next_token();
+                self.parse_postfix(Expression::StructInitialization {
+                    struct_name: "Color".to_string(),
+                    fields: ["r", "g", "b"].into_iter()
+                        .zip(channels)
+                        .map(|(field, value)| (field.to_string(), Expression::IntegerLiteral(i64::from(value))))
+                        .collect(),
+                    base: None,
+                })
+            }
             Some(Token::StringLiteral(value)) => {
                 self.next_token();
                 Ok(Expression::StringLiteral(value))
             }
+            Some(Token::FormatString(text)) => {
+                self.next_token();
+                self.parse_format_string(&text)
+            }
             Some(Token::KeywordTrue) => {
                 self.next_token();
                 Ok(Expression::BoolLiteral(true))
@@ -591,6 +1618,14 @@ impl<'a> Parser<'a> {
                             expression: Box::new(expr),
                         });
                     }
+                    "ref" => {
+                        self.expect_token(Token::ParenOpen)?;
+                        let expr = self.parse_expression()?;
+                        self.expect_token(Token::ParenClose)?;
+                        return Ok(Expression::Ref {
+                            expression: Box::new(expr),
+                        });
+                    }
                     "borrow" | "mut_borrow" => {
                         let mutable = name == "mut_borrow";
                         self.expect_token(Token::ParenOpen)?;
@@ -604,19 +1639,53 @@ impl<'a> Parser<'a> {
                     _ => {}
                 }
                 
+                // Some(x), None, Ok(x), Err(e) - варианты встроенных Option и Result
+                if let Some(enum_name) = builtin_variant_enum(&name) {
+                    let mut args = Vec::new();
+                    if self.current_token_is(&Token::ParenOpen) {
+                        self.next_token();
+                        args = self.parse_arguments()?;
+                        self.expect_token(Token::ParenClose)?;
+                    }
+                    return self.parse_postfix(Expression::EnumVariant {
+                        enum_name: enum_name.to_string(),
+                        variant: name,
+                        args,
+                    });
+                }
+                
                 // Проверяем, что следует дальше
-                if self.current_token_is(&Token::ParenOpen) {
+                if self.current_token_is(&Token::ColonColon) {
+                    // Вариант перечисления
+                    self.next_token();
+                    let variant = self.expect_identifier()?;
+                    let mut args = Vec::new();
+                    if self.current_token_is(&Token::ParenOpen) {
+                        self.next_token();
+                        args = self.parse_arguments()?;
+                        self.expect_token(Token::ParenClose)?;
+                    }
+                    self.parse_postfix(Expression::EnumVariant { enum_name: name, variant, args })
+                } else if self.current_token_is(&Token::ParenOpen) {
                     // Вызов функции
                     self.expect_token(Token::ParenOpen)?;
                     let args = self.parse_arguments()?;
                     self.expect_token(Token::ParenClose)?;
-                    Ok(Expression::FunctionCall { name, args })
-                } else if self.current_token_is(&Token::BraceOpen) {
+                    self.parse_postfix(Expression::FunctionCall { name, args })
+                } else if self.struct_literals_allowed && self.current_token_is(&Token::BraceOpen) {
                     // Инициализация структуры
                     self.expect_token(Token::BraceOpen)?;
                     let mut fields = Vec::new();
+                    let mut base = None;
                     
                     while !self.current_token_is(&Token::BraceClose) {
+                        // `..base` - всегда последним
+                        if self.current_token_is(&Token::DotDot) {
+                            self.next_token();
+                            base = Some(Box::new(self.parse_expression()?));
+                            break;
+                        }
+                        
                         let field_name = self.expect_identifier()?;
                         
                         self.expect_token(Token::Colon)?;
@@ -636,31 +1705,60 @@ impl<'a> Parser<'a> {
                     Ok(Expression::StructInitialization {
                         struct_name: name,
                         fields,
+                        base,
                     })
-                } else if self.current_token_is(&Token::Dot) {
-                    // Доступ к полю
-                    let mut expr = Expression::Variable(name);
-                    
-                    while self.current_token_is(&Token::Dot) {
-                        self.next_token();
-                        let field_name = self.expect_identifier()?;
-                        expr = Expression::FieldAccess {
-                            expression: Box::new(expr),
-                            field_name,
-                        };
-                    }
-                    
-                    Ok(expr)
                 } else {
-                    // Просто переменная
-                    Ok(Expression::Variable(name))
+                    // Переменная, возможно с доступом к полям и индексами
+                    self.parse_postfix(Expression::Variable(name))
                 }
             }
             Some(Token::ParenOpen) => {
                 self.next_token();
                 let expr = self.parse_expression()?;
                 self.expect_token(Token::ParenClose)?;
-                Ok(expr)
+                self.parse_postfix(expr)
+            }
+            Some(Token::KeywordMatch) => {
+                self.next_token();
+                self.parse_match()
+            }
+            Some(Token::KeywordIf) => {
+                self.next_token();
+                self.parse_if_expression()
+            }
+            Some(Token::Pipe) => {
+                self.next_token();
+                self.parse_lambda(true)
+            }
+            Some(Token::OperatorOr) => {
+                self.next_token();
+                self.parse_lambda(false)
+            }
+            Some(Token::BracketOpen) => {
+                self.next_token();
+                let mut elements = Vec::new();
+                
+                while !self.current_token_is(&Token::BracketClose) {
+                    elements.push(self.parse_expression()?);
+                    
+                    // [0; 64]
+                    if elements.len() == 1 && self.current_token_is(&Token::Semicolon) {
+                        self.next_token();
+                        let count = self.parse_array_length()?;
+                        self.expect_token(Token::BracketClose)?;
+                        let value = Box::new(elements.remove(0));
+                        return self.parse_postfix(Expression::ArrayRepeat { value, count });
+                    }
+                    
+                    if self.current_token_is(&Token::Comma) {
+                        self.next_token();
+                    } else {
+                        break;
+                    }
+                }
+                
+                self.expect_token(Token::BracketClose)?;
+                self.parse_postfix(Expression::ArrayLiteral { elements })
             }
             Some(token) => Err(ParseError::UnexpectedToken {
                 expected: "expression".to_string(),
@@ -670,6 +1768,268 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// f"x = {x}, sum = {a + b}" становится "x = " + x + ", sum = " + (a + b).
+    /// `{{` и `}}` - сами фигурные скобки
+    fn parse_format_string(&self, text: &str) -> ParseResult<Expression> {
+        let invalid = |message: &str| ParseError::InvalidSyntax {
+            message: format!("{} in format string", message),
+        };
+        let mut result: Option<Expression> = None;
+        let mut literal = String::new();
+        let mut chars = text.char_indices().peekable();
+        
+        while let Some((index, ch)) = chars.next() {
+            match ch {
+                '{' if chars.peek().map(|&(_, next)| next) == Some('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek().map(|&(_, next)| next) == Some('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(invalid("Unmatched `}`")),
+                '{' => {
+                    let start = index + 1;
+                    let end = loop {
+                        match chars.next() {
+                            Some((end, '}')) => break end,
+                            Some((_, '{')) => return Err(invalid("Nested `{`")),
+                            Some(_) => {}
+                            None => return Err(invalid("Unclosed `{`")),
+                        }
+                    };
+                    let source = &text[start..end];
+                    if source.trim().is_empty() {
+                        return Err(invalid("Empty `{}`"));
+                    }
+                    
+                    let mut parser = Parser::with_edition(source, self.edition);
+                    let value = parser.parse_expression()?;
+                    if let Some(error) = parser.lexer_error {
+                        return Err(error);
+                    }
+                    if let Some(token) = parser.current_token {
+                        return Err(ParseError::UnexpectedToken {
+                            expected: "`}` in format string".to_string(),
+                            found: format!("{:?}", token),
+                        });
+                    }
+                    
+                    // Первое слагаемое - всегда строка, тогда `+` склеивает
+                    let left = match result.take() {
+                        Some(left) if literal.is_empty() => left,
+                        Some(left) => Self::concat(left, Expression::StringLiteral(std::mem::take(&mut literal))),
+                        None => Expression::StringLiteral(std::mem::take(&mut literal)),
+                    };
+                    result = Some(Self::concat(left, value));
+                }
+                _ => literal.push(ch),
+            }
+        }
+        
+        Ok(match result {
+            Some(left) if literal.is_empty() => left,
+            Some(left) => Self::concat(left, Expression::StringLiteral(literal)),
+            None => Expression::StringLiteral(literal),
+        })
+    }
+
+    fn concat(left: Expression, right: Expression) -> Expression {
+        Expression::BinaryExpression {
+            left: Box::new(left),
+            operator: BinaryOperator::Add,
+            right: Box::new(right),
+        }
+    }
+
+    /// `match subject { pattern => expression, ... }`, ключевое слово уже разобрано
+    fn parse_match(&mut self) -> ParseResult<Expression> {
+        self.struct_literals_allowed = false;
+        let subject = self.parse_expression();
+        self.struct_literals_allowed = true;
+        let subject = subject?;
+        
+        self.expect_token(Token::BraceOpen)?;
+        let mut arms = Vec::new();
+        while !self.current_token_is(&Token::BraceClose) {
+            let pattern = self.parse_pattern()?;
+            self.expect_token(Token::FatArrow)?;
+            let body = self.parse_expression()?;
+            arms.push(MatchArm { pattern, body });
+            
+            if self.current_token_is(&Token::Comma) {
+                self.next_token();
+            } else {
+                break;
+            }
+        }
+        self.expect_token(Token::BraceClose)?;
+        
+        Ok(Expression::Match {
+            subject: Box::new(subject),
+            arms,
+        })
+    }
+
+    /// `if cond { a } else { b }` после `if`; `else if` - вложенное if
+    fn parse_if_expression(&mut self) -> ParseResult<Expression> {
+        self.struct_literals_allowed = false;
+        let condition = self.parse_expression();
+        self.struct_literals_allowed = true;
+        let condition = condition?;
+        
+        let then_branch = self.parse_branch_value()?;
+        if !self.current_token_is(&Token::KeywordElse) {
+            return Err(ParseError::InvalidSyntax {
+                message: "if used as a value must have an else branch".to_string(),
+            });
+        }
+        self.next_token();
+        let else_branch = if self.current_token_is(&Token::KeywordIf) {
+            self.next_token();
+            self.parse_if_expression()?
+        } else {
+            self.parse_branch_value()?
+        };
+        
+        Ok(Expression::If {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        })
+    }
+    
+    /// `{ expr }` - ветвь if-выражения
+    fn parse_branch_value(&mut self) -> ParseResult<Expression> {
+        self.expect_token(Token::BraceOpen)?;
+        let value = self.parse_expression()?;
+        self.expect_token(Token::BraceClose)?;
+        Ok(value)
+    }
+
+    fn parse_pattern(&mut self) -> ParseResult<Pattern> {
+        match self.current_token.take() {
+            Some(Token::IntegerLiteral(value) | Token::CharLiteral(value)) => {
+                self.next_token();
+                Ok(Pattern::Integer(value))
+            }
+            Some(Token::OperatorSubtract) => {
+                self.next_token();
+                match self.current_token.take() {
+                    Some(Token::IntegerLiteral(value)) => {
+                        self.next_token();
+                        Ok(Pattern::Integer(-value))
+                    }
+                    other => Err(ParseError::UnexpectedToken {
+                        expected: "integer literal".to_string(),
+                        found: other.map_or("EOF".to_string(), |t| format!("{:?}", t)),
+                    }),
+                }
+            }
+            Some(Token::KeywordTrue) => {
+                self.next_token();
+                Ok(Pattern::Bool(true))
+            }
+            Some(Token::KeywordFalse) => {
+                self.next_token();
+                Ok(Pattern::Bool(false))
+            }
+            Some(Token::Identifier(name)) if name == "_" => {
+                self.next_token();
+                Ok(Pattern::Wildcard)
+            }
+            Some(Token::Identifier(name)) => {
+                self.next_token();
+                let (enum_name, variant) = match builtin_variant_enum(&name) {
+                    Some(enum_name) => (enum_name.to_string(), name),
+                    None => {
+                        self.expect_token(Token::ColonColon)?;
+                        (name, self.expect_identifier()?)
+                    }
+                };
+                let mut bindings = Vec::new();
+                if self.current_token_is(&Token::ParenOpen) {
+                    self.next_token();
+                    while !self.current_token_is(&Token::ParenClose) {
+                        bindings.push(self.expect_identifier()?);
+                        if self.current_token_is(&Token::Comma) {
+                            self.next_token();
+                        } else {
+                            break;
+                        }
+                    }
+                    self.expect_token(Token::ParenClose)?;
+                }
+                Ok(Pattern::Variant { enum_name, variant, bindings })
+            }
+            Some(token) => Err(ParseError::UnexpectedToken {
+                expected: "pattern".to_string(),
+                found: format!("{:?}", token),
+            }),
+            None => Err(ParseError::UnexpectedEof),
+        }
+    }
+
+    /// Цепочка `.field`, `.method(args)` и `[index]` после выражения
+    fn parse_postfix(&mut self, mut expr: Expression) -> ParseResult<Expression> {
+        loop {
+            if self.current_token_is(&Token::Dot) {
+                self.next_token();
+                let field_name = self.expect_identifier()?;
+                if self.current_token_is(&Token::ParenOpen) {
+                    self.next_token();
+                    let args = self.parse_arguments()?;
+                    self.expect_token(Token::ParenClose)?;
+                    expr = Expression::MethodCall {
+                        receiver: Box::new(expr),
+                        method: field_name,
+                        args,
+                    };
+                    continue;
+                }
+                expr = Expression::FieldAccess {
+                    expression: Box::new(expr),
+                    field_name,
+                };
+            } else if self.current_token_is(&Token::BracketOpen) {
+                self.next_token();
+                let start = if self.current_token_is(&Token::DotDot) {
+                    None
+                } else {
+                    Some(Box::new(self.parse_expression()?))
+                };
+                if self.current_token_is(&Token::DotDot) {
+                    self.next_token();
+                    let end = if self.current_token_is(&Token::BracketClose) {
+                        None
+                    } else {
+                        Some(Box::new(self.parse_expression()?))
+                    };
+                    self.expect_token(Token::BracketClose)?;
+                    expr = Expression::Slice {
+                        expression: Box::new(expr),
+                        start,
+                        end,
+                    };
+                    continue;
+                }
+                self.expect_token(Token::BracketClose)?;
+                expr = Expression::Index {
+                    expression: Box::new(expr),
+                    index: start.expect("index before `]`"),
+                };
+            } else if self.current_token_is(&Token::Question) {
+                self.next_token();
+                expr = Expression::Try {
+                    expression: Box::new(expr),
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
     fn parse_arguments(&mut self) -> ParseResult<Vec<Expression>> {
         let mut args = Vec::new();
         
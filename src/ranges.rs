@@ -0,0 +1,61 @@
+// ranges.rs - числа в диапазоне: clamp, wrap и map_range
+//
+// Встроенные функции перегружены для i32 и f32: если все аргументы целые,
+// результат тоже целый, иначе считается в f32. Целая арифметика
+// заворачивается при переполнении так же, как i32 в WASM-бэкенде.
+//
+//     x = clamp(x, 0, width - 1);             // не выходим за экран
+//     angle = wrap(angle + spin, 0.0, 360.0);  // угол по кругу
+//     let hue: i32 = map_range(mouse_x, 0, width, 0, 255);
+//
+// Пустой диапазон (lo == hi у wrap, a1 == a2 у map_range) - ошибка
+// выполнения: None здесь; в WASM целое деление на ноль - ловушка, а f32
+// даёт NaN или бесконечность.
+
+/// x, ограниченный отрезком lo..=hi; при lo > hi результат - hi
+pub fn clamp_i32(x: i32, lo: i32, hi: i32) -> i32 {
+    x.max(lo).min(hi)
+}
+
+pub fn clamp_f32(x: f32, lo: f32, hi: f32) -> f32 {
+    x.max(lo).min(hi)
+}
+
+/// x, заведённый в полуинтервал lo..hi: wrap(5, 0, 4) = 1, wrap(-1, 0, 4) = 3
+pub fn wrap_i32(x: i32, lo: i32, hi: i32) -> Option<i32> {
+    let size = hi.wrapping_sub(lo);
+    if size == 0 {
+        return None;
+    }
+    // Остаток со знаком делимого, как i32.rem_s, поправленный до неотрицательного
+    let rest = x.wrapping_sub(lo).wrapping_rem(size);
+    let rest = if rest < 0 { rest.wrapping_add(size) } else { rest };
+    Some(lo.wrapping_add(rest))
+}
+
+pub fn wrap_f32(x: f32, lo: f32, hi: f32) -> Option<f32> {
+    let size = hi - lo;
+    if size == 0.0 {
+        return None;
+    }
+    let offset = x - lo;
+    Some(lo + (offset - size * (offset / size).floor()))
+}
+
+/// x из диапазона a1..a2, перенесённый в b1..b2 (без ограничения):
+/// map_range(5, 0, 10, 100, 200) = 150
+pub fn map_range_i32(x: i32, a1: i32, a2: i32, b1: i32, b2: i32) -> Option<i32> {
+    let from = a2.wrapping_sub(a1);
+    if from == 0 {
+        return None;
+    }
+    let scaled = x.wrapping_sub(a1).wrapping_mul(b2.wrapping_sub(b1));
+    Some(b1.wrapping_add(scaled.wrapping_div(from)))
+}
+
+pub fn map_range_f32(x: f32, a1: f32, a2: f32, b1: f32, b2: f32) -> Option<f32> {
+    if a1 == a2 {
+        return None;
+    }
+    Some(b1 + (x - a1) * (b2 - b1) / (a2 - a1))
+}
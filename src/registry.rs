@@ -0,0 +1,436 @@
+// registry.rs - реестр библиотек (`aetosc add`, `aetosc publish`)
+//
+// Реестр - статический набор файлов, который можно раздавать любым
+// HTTPS-сервером или держать в локальном каталоге:
+//
+//     index/ui_kit.json                      версии пакета
+//     packages/ui_kit/ui_kit-1.2.0.tar.gz    архив версии
+//
+// Файл индекса:
+//
+//     { "name": "ui_kit", "versions": [
+//         { "version": "1.2.0", "checksum": "<sha256 архива>",
+//           "dependencies": { "tween": "0.3" } } ] }
+//
+// `add` выбирает старшую версию из диапазона semver, сверяет sha256
+// архива с индексом и распаковывает его в .aetos/packages корня рабочего
+// пространства (workspace.rs), вместе с зависимостями из реестра.
+// `publish` упаковывает пакет (aetos.toml и файлы без каталогов target и
+// скрытых) и дописывает версию в индекс; на HTTP(S)-реестр файлы
+// отправляются запросом PUT с токеном из AETOS_REGISTRY_TOKEN.
+//
+// Адрес реестра: `--registry`, затем переменная AETOS_REGISTRY, затем
+// `[registry] url` в aetos.toml. Удаленный реестр - только по HTTPS.
+//
+// Имена пакетов из индекса и команды попадают в пути файлов, поэтому
+// допускаются только символы [a-z0-9_-]; архив распаковывается, только
+// если все его записи - файлы и каталоги внутри `{name}-{version}/`.
+
+use crate::workspace::{self, DependencySource, Manifest, Workspace, WorkspaceError, MANIFEST, PACKAGES_DIR};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+pub const REGISTRY_ENV: &str = "AETOS_REGISTRY";
+pub const TOKEN_ENV: &str = "AETOS_REGISTRY_TOKEN";
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("No registry configured: pass --registry, set {REGISTRY_ENV} or add `[registry] url` to aetos.toml")]
+    NoRegistry,
+
+    #[error("Registry {url} must use https://")]
+    InsecureRegistry { url: String },
+
+    #[error("Invalid package name {name:?}: only a-z, 0-9, _ and - are allowed")]
+    InvalidName { name: String },
+
+    #[error("Archive of {prefix} has an entry {path} outside {prefix}/")]
+    ArchiveEntry { prefix: String, path: String },
+
+    #[error("Request to {url} failed: {message}")]
+    Http { url: String, message: String },
+
+    #[error("Cannot access {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid index for {name}: {message}")]
+    Index { name: String, message: String },
+
+    #[error("Package {name} is not in the registry")]
+    NotFound { name: String },
+
+    #[error("No version of {name} matches {requirement} (available: {available})")]
+    NoMatchingVersion { name: String, requirement: String, available: String },
+
+    #[error("Checksum mismatch for {name} {version}: index has {expected}, archive is {found}")]
+    Checksum { name: String, version: String, expected: String, found: String },
+
+    #[error("Invalid version {version} of {name}: {message}")]
+    Version { name: String, version: String, message: String },
+
+    #[error("{name} {version} is already published")]
+    AlreadyPublished { name: String, version: String },
+
+    #[error("Cannot publish {name}: dependency {dependency} is a path dependency")]
+    PathDependency { name: String, dependency: String },
+
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IndexFile {
+    pub name: String,
+    pub versions: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub version: String,
+    /// sha256 архива в шестнадцатеричном виде
+    pub checksum: String,
+    /// Зависимости из реестра: имя -> диапазон версий
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// Установленный пакет
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installed {
+    pub name: String,
+    pub version: Version,
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Registry {
+    Http(String),
+    Dir(PathBuf),
+}
+
+impl Registry {
+    /// `https://...`, `file://...` или путь к каталогу; `http://` отклоняется
+    pub fn new(location: &str) -> Result<Self, RegistryError> {
+        if location.starts_with("https://") {
+            Ok(Registry::Http(location.trim_end_matches('/').to_string()))
+        } else if location.starts_with("http://") {
+            Err(RegistryError::InsecureRegistry { url: location.to_string() })
+        } else {
+            Ok(Registry::Dir(PathBuf::from(location.strip_prefix("file://").unwrap_or(location))))
+        }
+    }
+
+    /// Реестр из флага, переменной окружения или ближайшего aetos.toml
+    pub fn configured(flag: Option<&str>, dir: &Path) -> Result<Self, RegistryError> {
+        if let Some(location) = flag.map(str::to_string).or_else(|| std::env::var(REGISTRY_ENV).ok()) {
+            return Self::new(&location);
+        }
+        dir.ancestors()
+            .filter_map(|ancestor| Manifest::read(&ancestor.join(MANIFEST)).ok())
+            .find_map(|manifest| manifest.registry)
+            .ok_or(RegistryError::NoRegistry)
+            .and_then(|location| Self::new(&location))
+    }
+
+    /// Содержимое файла реестра; None - файла нет
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>, RegistryError> {
+        match self {
+            Registry::Http(base) => {
+                let url = format!("{}/{}", base, path);
+                match ureq::get(&url).call() {
+                    Ok(response) => {
+                        let mut bytes = Vec::new();
+                        response.into_reader().read_to_end(&mut bytes)
+                            .map_err(|e| RegistryError::Http { url, message: e.to_string() })?;
+                        Ok(Some(bytes))
+                    }
+                    Err(ureq::Error::Status(404, _)) => Ok(None),
+                    Err(e) => Err(RegistryError::Http { url, message: e.to_string() }),
+                }
+            }
+            Registry::Dir(dir) => {
+                let path = dir.join(path);
+                match fs::read(&path) {
+                    Ok(bytes) => Ok(Some(bytes)),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                    Err(source) => Err(RegistryError::Io { path, source }),
+                }
+            }
+        }
+    }
+
+    fn write(&self, path: &str, bytes: &[u8]) -> Result<(), RegistryError> {
+        match self {
+            Registry::Http(base) => {
+                let url = format!("{}/{}", base, path);
+                let mut request = ureq::put(&url);
+                if let Ok(token) = std::env::var(TOKEN_ENV) {
+                    request = request.set("Authorization", &format!("Bearer {}", token));
+                }
+                request.send_bytes(bytes)
+                    .map(|_| ())
+                    .map_err(|e| RegistryError::Http { url, message: e.to_string() })
+            }
+            Registry::Dir(dir) => {
+                let path = dir.join(path);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|source| RegistryError::Io { path: parent.to_path_buf(), source })?;
+                }
+                fs::write(&path, bytes).map_err(|source| RegistryError::Io { path, source })
+            }
+        }
+    }
+
+    pub fn index(&self, name: &str) -> Result<Option<IndexFile>, RegistryError> {
+        validate_name(name)?;
+        let Some(bytes) = self.read(&index_path(name))? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| RegistryError::Index { name: name.to_string(), message: e.to_string() })
+    }
+
+    /// Старшая версия пакета из диапазона
+    pub fn resolve(&self, name: &str, requirement: &VersionReq) -> Result<(Version, IndexEntry), RegistryError> {
+        let index = self.index(name)?.ok_or_else(|| RegistryError::NotFound { name: name.to_string() })?;
+        let mut versions = Vec::new();
+        for entry in &index.versions {
+            versions.push((parse_version(name, &entry.version)?, entry));
+        }
+        versions.iter()
+            .filter(|(version, _)| requirement.matches(version))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(version, entry)| (version.clone(), (*entry).clone()))
+            .ok_or_else(|| RegistryError::NoMatchingVersion {
+                name: name.to_string(),
+                requirement: requirement.to_string(),
+                available: versions.iter().map(|(version, _)| version.to_string()).collect::<Vec<_>>().join(", "),
+            })
+    }
+
+    /// Архив версии, сверенный с контрольной суммой из индекса
+    pub fn download(&self, name: &str, version: &Version, entry: &IndexEntry) -> Result<Vec<u8>, RegistryError> {
+        let archive = self.read(&archive_path(name, version))?
+            .ok_or_else(|| RegistryError::NotFound { name: format!("{} {}", name, version) })?;
+        let found = checksum(&archive);
+        if !found.eq_ignore_ascii_case(&entry.checksum) {
+            return Err(RegistryError::Checksum {
+                name: name.to_string(),
+                version: version.to_string(),
+                expected: entry.checksum.clone(),
+                found,
+            });
+        }
+        Ok(archive)
+    }
+
+    /// Ставит пакет и его зависимости из реестра в `packages_dir`;
+    /// уже установленные версии не скачиваются заново
+    pub fn install(&self, name: &str, requirement: &VersionReq, packages_dir: &Path) -> Result<Vec<Installed>, RegistryError> {
+        let mut installed = Vec::new();
+        let mut pending = vec![(name.to_string(), requirement.clone())];
+        while let Some((name, requirement)) = pending.pop() {
+            if installed.iter().any(|package: &Installed| package.name == name && requirement.matches(&package.version)) {
+                continue;
+            }
+            let (version, entry) = self.resolve(&name, &requirement)?;
+            let dir = packages_dir.join(format!("{}-{}", name, version));
+            if !dir.is_dir() {
+                let archive = self.download(&name, &version, &entry)?;
+                unpack(&archive, packages_dir, &format!("{}-{}", name, version))?;
+            }
+            for (dependency, range) in &entry.dependencies {
+                pending.push((dependency.clone(), parse_requirement(dependency, range)?));
+            }
+            installed.push(Installed { name, version, dir });
+        }
+        Ok(installed)
+    }
+
+    /// Публикует пакет из каталога `dir`; возвращает версию и контрольную сумму архива
+    pub fn publish(&self, dir: &Path) -> Result<(String, Version, String), RegistryError> {
+        let manifest_path = dir.join(MANIFEST);
+        let manifest = Manifest::read(&manifest_path)?;
+        let package = manifest.package.ok_or(WorkspaceError::NotAPackage { path: manifest_path })?;
+        let name = package.name;
+        let version = parse_version(&name, package.version.as_deref().unwrap_or_default())?;
+
+        let mut dependencies = BTreeMap::new();
+        for (dependency, source) in manifest.dependencies {
+            match source {
+                DependencySource::Registry(range) => {
+                    parse_requirement(&dependency, &range)?;
+                    dependencies.insert(dependency, range);
+                }
+                DependencySource::Path(_) => return Err(RegistryError::PathDependency { name, dependency }),
+            }
+        }
+
+        let mut index = self.index(&name)?.unwrap_or_else(|| IndexFile { name: name.clone(), versions: Vec::new() });
+        if index.versions.iter().any(|entry| parse_version(&name, &entry.version).ok().as_ref() == Some(&version)) {
+            return Err(RegistryError::AlreadyPublished { name, version: version.to_string() });
+        }
+
+        let archive = pack(dir, &format!("{}-{}", name, version))?;
+        let checksum = checksum(&archive);
+        index.versions.push(IndexEntry { version: version.to_string(), checksum: checksum.clone(), dependencies });
+
+        // Архив раньше индекса: версия в индексе всегда уже скачивается
+        self.write(&archive_path(&name, &version), &archive)?;
+        let index_json = serde_json::to_string_pretty(&index).unwrap_or_default() + "\n";
+        self.write(&index_path(&name), index_json.as_bytes())?;
+        Ok((name, version, checksum))
+    }
+}
+
+/// Имя пакета безопасно для путей: непустое, только [a-z0-9_-].
+/// Версии проходят через semver и не содержат `/` и `..`
+fn validate_name(name: &str) -> Result<(), RegistryError> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(RegistryError::InvalidName { name: name.to_string() })
+    }
+}
+
+fn index_path(name: &str) -> String {
+    format!("index/{}.json", name)
+}
+
+fn archive_path(name: &str, version: &Version) -> String {
+    format!("packages/{0}/{0}-{1}.tar.gz", name, version)
+}
+
+fn parse_version(name: &str, version: &str) -> Result<Version, RegistryError> {
+    Version::parse(version).map_err(|e| RegistryError::Version {
+        name: name.to_string(),
+        version: version.to_string(),
+        message: e.to_string(),
+    })
+}
+
+fn parse_requirement(name: &str, requirement: &str) -> Result<VersionReq, RegistryError> {
+    VersionReq::parse(requirement).map_err(|e| RegistryError::Version {
+        name: name.to_string(),
+        version: requirement.to_string(),
+        message: e.to_string(),
+    })
+}
+
+pub fn checksum(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Каталог для пакетов из реестра: общий для рабочего пространства
+pub fn packages_dir(dir: &Path) -> PathBuf {
+    Workspace::find_root(dir).unwrap_or_else(|| dir.to_path_buf()).join(PACKAGES_DIR)
+}
+
+/// Файлы пакета для архива: без target и скрытых каталогов, в стабильном порядке
+fn package_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir.join(relative))?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        let path = relative.join(&name);
+        if name.to_string_lossy().starts_with('.') || (relative.as_os_str().is_empty() && name == workspace::DEFAULT_TARGET_DIR) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            package_files(dir, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// tar.gz с файлами пакета под каталогом `prefix`; время и права
+/// одинаковые, поэтому архив и его контрольная сумма воспроизводимы
+pub fn pack(dir: &Path, prefix: &str) -> Result<Vec<u8>, RegistryError> {
+    let io_error = |source| RegistryError::Io { path: dir.to_path_buf(), source };
+    let mut files = Vec::new();
+    package_files(dir, Path::new(""), &mut files).map_err(io_error)?;
+
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for file in files {
+        let data = fs::read(dir.join(&file)).map_err(io_error)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, Path::new(prefix).join(file), data.as_slice()).map_err(io_error)?;
+    }
+    builder.into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(io_error)
+}
+
+/// Распаковывает архив в `packages_dir`. Сначала проверяются все записи:
+/// только файлы и каталоги под `prefix/` без `..`, иначе ничего не пишется
+pub fn unpack(archive: &[u8], packages_dir: &Path, prefix: &str) -> Result<(), RegistryError> {
+    let io_error = |source| RegistryError::Io { path: packages_dir.to_path_buf(), source };
+    for entry in tar::Archive::new(GzDecoder::new(archive)).entries().map_err(io_error)? {
+        let entry = entry.map_err(io_error)?;
+        let path = entry.path().map_err(io_error)?.into_owned();
+        let mut components = path.components();
+        let inside = components.next() == Some(Component::Normal(prefix.as_ref()))
+            && components.all(|component| matches!(component, Component::Normal(_)));
+        let kind = entry.header().entry_type();
+        if !inside || !(kind.is_file() || kind.is_dir()) {
+            return Err(RegistryError::ArchiveEntry { prefix: prefix.to_string(), path: path.display().to_string() });
+        }
+    }
+    fs::create_dir_all(packages_dir)
+        .and_then(|_| tar::Archive::new(GzDecoder::new(archive)).unpack(packages_dir))
+        .map_err(io_error)
+}
+
+/// aetos.toml с зависимостью `name = "requirement"`: строка заменяется,
+/// если зависимость уже есть, иначе дописывается в [dependencies]
+pub fn add_dependency(manifest: &str, name: &str, requirement: &str) -> String {
+    let line = format!("{} = \"{}\"", name, requirement);
+    let mut lines: Vec<String> = manifest.lines().map(str::to_string).collect();
+    let mut section = String::new();
+    let mut dependencies_end = None;
+    for (i, text) in lines.iter().enumerate() {
+        let trimmed = text.trim();
+        if trimmed.starts_with('[') {
+            section = trimmed.to_string();
+            continue;
+        }
+        if section == "[dependencies]" {
+            if trimmed.split_once('=').is_some_and(|(key, _)| key.trim() == name) {
+                lines[i] = line;
+                return lines.join("\n") + "\n";
+            }
+            if !trimmed.is_empty() {
+                dependencies_end = Some(i + 1);
+            }
+        }
+    }
+    match dependencies_end.or_else(|| lines.iter().position(|text| text.trim() == "[dependencies]").map(|i| i + 1)) {
+        Some(position) => lines.insert(position, line),
+        None => {
+            if lines.last().is_some_and(|text| !text.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push("[dependencies]".to_string());
+            lines.push(line);
+        }
+    }
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,122 @@
+// scaffold.rs - создание нового проекта (`aetosc new`)
+
+use crate::edition::Edition;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Template {
+    Console,
+    Game,
+    Embedded,
+}
+
+impl Template {
+    pub const NAMES: [&'static str; 3] = ["console", "game", "embedded"];
+
+    pub fn parse(name: &str) -> Option<Template> {
+        match name {
+            "console" => Some(Template::Console),
+            "game" => Some(Template::Game),
+            "embedded" => Some(Template::Embedded),
+            _ => None,
+        }
+    }
+
+    pub fn main_source(&self, project_name: &str) -> String {
+        let source = match self {
+            Template::Console => CONSOLE_TEMPLATE,
+            Template::Game => GAME_TEMPLATE,
+            Template::Embedded => EMBEDDED_TEMPLATE,
+        };
+        source.replace("{name}", project_name)
+    }
+}
+
+const CONSOLE_TEMPLATE: &str = r#"// {name}: консольная программа
+fn main() -> i32 {
+    print("Hello from", "{name}!");
+    return 0;
+}
+"#;
+
+const GAME_TEMPLATE: &str = r#"// {name}: игровой цикл с вводом и отрисовкой
+// Управление: стрелки или WASD, выход - Escape
+fn main() -> i32 {
+    init_graphics(800, 600, "{name}");
+
+    let mut x: i32 = 400;
+    let mut y: i32 = 300;
+    let speed: i32 = 4;
+
+    while true {
+        if is_key_pressed(27) {
+            break;
+        }
+        if is_key_pressed(37) || is_key_pressed('a') {
+            x = x - speed;
+        }
+        if is_key_pressed(39) || is_key_pressed('d') {
+            x = x + speed;
+        }
+        if is_key_pressed(38) || is_key_pressed('w') {
+            y = y - speed;
+        }
+        if is_key_pressed(40) || is_key_pressed('s') {
+            y = y + speed;
+        }
+
+        clear_screen(20, 20, 30);
+        draw_rect(0, 560, 800, 40, 40, 120, 40);
+        draw_circle(x, y, 20, 255, 200, 0);
+        render();
+    }
+
+    return 0;
+}
+"#;
+
+const EMBEDDED_TEMPLATE: &str = r#"// {name}: мигание светодиодом через GPIO
+fn main() -> i32 {
+    let led_pin: i32 = 13;
+
+    while true {
+        gpio_toggle(led_pin);
+        delay(500);
+    }
+
+    return 0;
+}
+"#;
+
+const GITIGNORE: &str = "*.wasm\n*.o\n*.ll\n.aetos-index\n.aetos/\n";
+
+pub fn manifest(project_name: &str) -> String {
+    format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"{}\"\nentry = \"main.aetos\"\n",
+        project_name,
+        Edition::LATEST
+    )
+}
+
+/// Создаёт каталог проекта с main.aetos, aetos.toml и .gitignore
+pub fn create_project(dir: &Path, template: Template) -> io::Result<()> {
+    if dir.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("destination {} already exists", dir.display()),
+        ));
+    }
+
+    let project_name = dir.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("aetos_project");
+
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("main.aetos"), template.main_source(project_name))?;
+    fs::write(dir.join("aetos.toml"), manifest(project_name))?;
+    fs::write(dir.join(".gitignore"), GITIGNORE)?;
+
+    Ok(())
+}
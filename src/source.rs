@@ -0,0 +1,132 @@
+// Чтение исходников: файлы из Windows приходят с BOM и переводами строк
+// `\r\n`, а лексер и диагностика рассчитаны на чистый UTF-8 с `\n`
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+const UTF8_BOM: &str = "\u{feff}";
+
+/// Ширина табуляции при выводе строки исходника в диагностике
+pub const TAB_WIDTH: usize = 4;
+
+/// Исходник после нормализации: без BOM, `\r\n` и одиночный `\r` заменены на `\n`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    text: String,
+    bom_len: usize,
+    // Смещения в нормализованном тексте, перед которыми был удалён `\r`
+    removed_cr: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(raw: &str) -> Self {
+        let (body, bom_len) = match raw.strip_prefix(UTF8_BOM) {
+            Some(body) => (body, UTF8_BOM.len()),
+            None => (raw, 0),
+        };
+
+        let mut text = String::with_capacity(body.len());
+        let mut removed_cr = Vec::new();
+        let mut chars = body.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch == '\r' {
+                if chars.peek() == Some(&'\n') {
+                    removed_cr.push(text.len());
+                    continue;
+                }
+                text.push('\n');
+            } else {
+                text.push(ch);
+            }
+        }
+
+        Self { text, bom_len, removed_cr }
+    }
+
+    /// UTF-8 с BOM или без; UTF-16 и битые последовательности - ошибка
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "UTF-16 source files are not supported, save the file as UTF-8",
+            ));
+        }
+        let raw = std::str::from_utf8(bytes).map_err(|e| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("source is not valid UTF-8 at byte {}", e.valid_up_to()),
+        ))?;
+        Ok(Self::new(raw))
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    /// Байтовое смещение в исходном файле для смещения в нормализованном тексте
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let removed = self.removed_cr.partition_point(|&position| position <= offset);
+        offset + self.bom_len + removed
+    }
+
+    /// Смещение символа в колонке `column` строки `line` (обе с 1) в
+    /// нормализованном тексте; колонка за концом строки - конец строки
+    pub fn offset(&self, line: usize, column: usize) -> Option<usize> {
+        let line_start = match line.checked_sub(2) {
+            None if line == 1 => 0,
+            None => return None,
+            Some(newlines) => self.text.match_indices('\n').nth(newlines)?.0 + 1,
+        };
+        let source_line = self.text[line_start..].split('\n').next().unwrap_or_default();
+        let in_line = source_line.char_indices()
+            .nth(column.checked_sub(1)?)
+            .map_or(source_line.len(), |(index, _)| index);
+        Some(line_start + in_line)
+    }
+
+    /// Байт в исходном файле (с BOM и `\r\n`) для строки и колонки
+    /// диагностики - так позицию считают редакторы и внешние инструменты
+    pub fn original_position(&self, line: usize, column: usize) -> Option<usize> {
+        self.offset(line, column).map(|offset| self.original_offset(offset))
+    }
+
+    /// Строка `line` с табуляцией, развёрнутой до `TAB_WIDTH`, и `^` под
+    /// колонкой `column` (в символах, как у `Lexer::column`)
+    pub fn snippet(&self, line: usize, column: usize) -> Option<String> {
+        let source_line = self.text.lines().nth(line.checked_sub(1)?)?;
+        let mut rendered = String::new();
+        let mut caret = 0;
+        for (index, ch) in source_line.chars().enumerate() {
+            if index + 1 == column {
+                caret = rendered.chars().count();
+            }
+            if ch == '\t' {
+                let width = TAB_WIDTH - rendered.chars().count() % TAB_WIDTH;
+                rendered.push_str(&" ".repeat(width));
+            } else {
+                rendered.push(ch);
+            }
+        }
+        if column > source_line.chars().count() {
+            caret = rendered.chars().count();
+        }
+
+        let gutter = line.to_string().len();
+        let mut snippet = String::new();
+        let _ = writeln!(snippet, "{} | {}", line, rendered);
+        let _ = write!(snippet, "{} | {}^", " ".repeat(gutter), " ".repeat(caret));
+        Some(snippet)
+    }
+}
+
+/// Замена `fs::read_to_string` для исходников Aetos
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    SourceFile::read(path.as_ref()).map(SourceFile::into_text)
+}
@@ -0,0 +1,181 @@
+// stats.rs - отчёт об использовании языка (`aetosc compile --stats-out stats.json`)
+//
+// Сколько раз программа использует каждую конструкцию языка и каждую
+// встроенную функцию. Отчёт только пишется в файл рядом с результатом
+// сборки и никуда не отправляется: по нему преподаватель или автор
+// библиотеки видит, какие части языка затрагивает код.
+
+use crate::ast::*;
+use crate::stdlib;
+use crate::visit::{self, Visitor};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageStats {
+    pub compiler: String,
+    pub source: String,
+    pub functions: usize,
+    pub methods: usize,
+    pub structs: usize,
+    pub enums: usize,
+    pub consts: usize,
+    pub statics: usize,
+    /// Конструкция языка -> число использований; BTreeMap - чтобы файл был стабильным
+    pub features: BTreeMap<String, usize>,
+    /// Встроенная функция -> число вызовов
+    pub builtins: BTreeMap<String, usize>,
+}
+
+impl UsageStats {
+    /// Считает использование по программе до оптимизации, то есть как она написана
+    pub fn collect(program: &Program, source: &str) -> Self {
+        let mut collector = UsageCollector {
+            // Своя функция с именем встроенной её перекрывает
+            user_functions: program.functions.iter().map(|function| function.name.clone()).collect(),
+            features: BTreeMap::new(),
+            builtins: BTreeMap::new(),
+        };
+        for function in &program.functions {
+            collector.count_function(function);
+        }
+        visit::walk_program(&mut collector, program);
+        for constant in &program.consts {
+            collector.visit_expression(&constant.value);
+        }
+        for static_def in &program.statics {
+            if static_def.mutable {
+                collector.count("static_mut");
+            }
+            collector.visit_expression(&static_def.value);
+        }
+        for default in program.structs.iter().flat_map(|s| &s.fields).filter_map(|field| field.default.as_ref()) {
+            collector.count("field_default");
+            collector.visit_expression(default);
+        }
+
+        let methods = program.functions.iter()
+            .filter(|function| split_method_name(&function.name).is_some())
+            .count();
+        Self {
+            compiler: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.to_string(),
+            functions: program.functions.len() - methods,
+            methods,
+            structs: program.structs.len(),
+            enums: program.enums.len(),
+            consts: program.consts.len(),
+            statics: program.statics.len(),
+            features: collector.features,
+            builtins: collector.builtins,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_json() + "\n")
+    }
+}
+
+/// Куда писать отчёт: относительный путь считается от каталога результата сборки
+pub fn stats_path(stats_out: &str, output_path: &str) -> PathBuf {
+    let stats_out = Path::new(stats_out);
+    match Path::new(output_path).parent() {
+        Some(dir) if stats_out.is_relative() => dir.join(stats_out),
+        _ => stats_out.to_path_buf(),
+    }
+}
+
+struct UsageCollector {
+    user_functions: BTreeSet<String>,
+    features: BTreeMap<String, usize>,
+    builtins: BTreeMap<String, usize>,
+}
+
+impl UsageCollector {
+    fn count(&mut self, feature: &str) {
+        *self.features.entry(feature.to_string()).or_default() += 1;
+    }
+
+    fn count_function(&mut self, function: &Function) {
+        if function.requires().next().is_some() || function.ensures().next().is_some() {
+            self.count("contracts");
+        }
+        for attribute in &function.attributes {
+            match attribute {
+                Attribute::Export => self.count("export"),
+                Attribute::NoMangle => self.count("no_mangle"),
+                Attribute::Deterministic => self.count("deterministic"),
+                Attribute::Const => self.count("const_fn"),
+                Attribute::Requires(_) | Attribute::Ensures(_) | Attribute::Derive(_) => {}
+            }
+        }
+    }
+}
+
+impl Visitor for UsageCollector {
+    fn visit_statement(&mut self, statement: &Statement) {
+        let feature = match statement {
+            Statement::VariableDeclaration { mutable: true, .. } => Some("let_mut"),
+            Statement::DestructureStruct { .. } => Some("destructuring"),
+            Statement::FieldAssignment { .. } => Some("field_assignment"),
+            Statement::Block { .. } => Some("block"),
+            Statement::While { .. } => Some("while"),
+            Statement::Loop { .. } => Some("loop"),
+            Statement::For { .. } => Some("for"),
+            Statement::If { else_branch: Some(_), .. } => Some("if_else"),
+            Statement::If { .. } => Some("if"),
+            Statement::Break { label: Some(_), .. } | Statement::Continue { label: Some(_), .. } => Some("labeled_jump"),
+            Statement::Break { .. } => Some("break"),
+            Statement::Continue { .. } => Some("continue"),
+            Statement::Defer { .. } => Some("defer"),
+            _ => None,
+        };
+        if let Some(feature) = feature {
+            self.count(feature);
+        }
+        visit::walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        let feature = match expression {
+            Expression::FloatLiteral(_) => Some("float_literal"),
+            Expression::StringLiteral(_) => Some("string_literal"),
+            Expression::BinaryExpression { operator, .. } if operator.is_bitwise() => Some("bitwise"),
+            Expression::FunctionCall { name, .. } if !self.user_functions.contains(name) && stdlib::is_builtin(name) => {
+                *self.builtins.entry(name.clone()).or_default() += 1;
+                None
+            }
+            Expression::StructInitialization { base: Some(_), .. } => Some("struct_update"),
+            Expression::StructInitialization { .. } => Some("struct_literal"),
+            Expression::FieldAccess { .. } => Some("field_access"),
+            Expression::TypeCast { .. } => Some("cast"),
+            Expression::Move { .. } => Some("move"),
+            Expression::Borrow { .. } => Some("borrow"),
+            Expression::Ref { .. } => Some("ref"),
+            Expression::ArrayLiteral { .. } => Some("array_literal"),
+            Expression::Index { .. } => Some("index"),
+            Expression::Slice { .. } => Some("slice"),
+            Expression::EnumVariant { enum_name, .. } if enum_name == "Option" || enum_name == "Result" => Some("option_result"),
+            Expression::EnumVariant { .. } => Some("enum_variant"),
+            Expression::Match { .. } => Some("match"),
+            Expression::If { .. } => Some("if_expression"),
+            Expression::MethodCall { .. } => Some("method_call"),
+            Expression::Try { .. } => Some("try_operator"),
+            Expression::Lambda { .. } => Some("closure"),
+            _ => None,
+        };
+        if let Some(feature) = feature {
+            self.count(feature);
+        }
+        visit::walk_expression(self, expression);
+    }
+}
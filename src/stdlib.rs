@@ -1,298 +1,354 @@
+// stdlib.rs - каноническое описание стандартной библиотеки
+//
+// Таблица встроенных функций строится один раз и используется
+// тайпчекером, интерпретатором и get_stdlib(), поэтому сигнатуры
+// не могут разойтись.
+
 use crate::ast::*;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub params: Vec<(&'static str, Type)>,
+    pub return_type: Type,
+    // Вариадические функции: params - обязательные аргументы, остальные любого типа
+    pub variadic: bool,
+    // Перегрузка для i32 и f32: параметры и результат записаны как f32,
+    // но если все аргументы i32, результат - i32
+    pub numeric: bool,
+    // Параметры типа (map, filter): в сигнатуре записаны как Struct("T"),
+    // при вызове выводятся из типов аргументов
+    pub type_params: &'static [&'static str],
+}
+
+impl Builtin {
+    fn new(name: &'static str, params: Vec<(&'static str, Type)>, return_type: Type) -> Self {
+        Self { name, params, return_type, variadic: false, numeric: false, type_params: &[] }
+    }
+
+    fn variadic(name: &'static str, params: Vec<(&'static str, Type)>, return_type: Type) -> Self {
+        Self { name, params, return_type, variadic: true, numeric: false, type_params: &[] }
+    }
+
+    fn numeric(name: &'static str, params: &[&'static str]) -> Self {
+        let params = params.iter().map(|&param| (param, Type::F32)).collect();
+        Self { name, params, return_type: Type::F32, variadic: false, numeric: true, type_params: &[] }
+    }
+
+    fn generic(name: &'static str, type_params: &'static [&'static str], params: Vec<(&'static str, Type)>, return_type: Type) -> Self {
+        Self { name, params, return_type, variadic: false, numeric: false, type_params }
+    }
+
+    pub fn param_types(&self) -> Vec<Type> {
+        self.params.iter().map(|(_, ty)| ty.clone()).collect()
+    }
+
+    /// Параметры типа по типам аргументов: побеждает первое вхождение.
+    /// Подходят ли аргументы к параметрам после подстановки, проверяет вызывающий
+    pub fn bind_type_params(&self, args: &[Type]) -> HashMap<String, Type> {
+        let mut bindings = HashMap::new();
+        for ((_, param), arg) in self.params.iter().zip(args) {
+            self.bind(param, arg, &mut bindings);
+        }
+        bindings
+    }
+
+    fn bind(&self, param: &Type, arg: &Type, bindings: &mut HashMap<String, Type>) {
+        match (param, arg) {
+            (Type::Struct(name), _) if self.type_params.contains(&name.as_str()) => {
+                bindings.entry(name.clone()).or_insert_with(|| arg.clone());
+            }
+            // &[T] принимает любой массив; элементы пустого литерала неизвестны
+            (Type::Slice(element), Type::Array(arg) | Type::Slice(arg) | Type::FixedArray(arg, _)) if **arg != Type::Void => {
+                self.bind(element, arg, bindings);
+            }
+            (Type::Function(params, return_type), Type::Function(arg_params, arg_return)) => {
+                for (param, arg) in params.iter().zip(arg_params) {
+                    self.bind(param, arg, bindings);
+                }
+                self.bind(return_type, arg_return, bindings);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Тип с подставленными параметрами; невыведенные остаются как есть
+pub fn substitute(ty: &Type, bindings: &HashMap<String, Type>) -> Type {
+    match ty {
+        Type::Struct(name) => bindings.get(name).cloned().unwrap_or_else(|| ty.clone()),
+        Type::Array(element) => Type::Array(Box::new(substitute(element, bindings))),
+        Type::Slice(element) => Type::Slice(Box::new(substitute(element, bindings))),
+        Type::Function(params, return_type) => Type::Function(
+            params.iter().map(|param| substitute(param, bindings)).collect(),
+            Box::new(substitute(return_type, bindings)),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn build_builtins() -> Vec<Builtin> {
+    use Type::*;
+
+    let rgb = || vec![("r", I32), ("g", I32), ("b", I32)];
+    let with_rgb = |mut params: Vec<(&'static str, Type)>| {
+        params.extend(rgb());
+        params
+    };
+
+    vec![
+        // Вывод
+        Builtin::new("print_i32", vec![("value", I32)], Void),
+        Builtin::new("print_string", vec![("value", String)], Void),
+        // print(a, b, c) - любое количество аргументов любого типа через пробел
+        Builtin::variadic("print", vec![], Void),
+
+        // Embedded
+        Builtin::new("gpio_set", vec![("pin", I32), ("value", I32)], Void),
+        Builtin::new("gpio_toggle", vec![("pin", I32)], Void),
+        Builtin::new("delay", vec![("ms", I32)], Void),
+
+        // Графика
+        Builtin::new("init_graphics", vec![("width", I32), ("height", I32), ("title", String)], Void),
+        Builtin::new("clear_screen", rgb(), Void),
+        Builtin::new("draw_pixel", with_rgb(vec![("x", I32), ("y", I32)]), Void),
+        Builtin::new("draw_rect", with_rgb(vec![("x", I32), ("y", I32), ("width", I32), ("height", I32)]), Void),
+        Builtin::new("draw_circle", with_rgb(vec![("center_x", I32), ("center_y", I32), ("radius", I32)]), Void),
+        Builtin::new("draw_line", with_rgb(vec![("x1", I32), ("y1", I32), ("x2", I32), ("y2", I32)]), Void),
+        Builtin::new("render", vec![], Void),
+        // Текст шрифтом 5x7; с --contrast-warnings проверяется контраст с фоном
+        Builtin::new("draw_text", with_rgb(vec![("x", I32), ("y", I32), ("text", String)]), Void),
+
+        // Доступность: палитра для дальтоников и контраст по WCAG (1..21)
+        Builtin::new("palette", vec![("name", String)], Struct("Color".to_string())),
+        Builtin::new("contrast_ratio", vec![("a", Struct("Color".to_string())), ("b", Struct("Color".to_string()))], F32),
+
+        // Время и ввод
+        Builtin::new("get_time", vec![], F32),
+        Builtin::new("sleep", vec![("ms", I32)], Void),
+        Builtin::new("is_key_pressed", vec![("key", I32)], Bool),
+        // Срабатывают в одном кадре: прыжок или пауза по одному нажатию
+        Builtin::new("key_just_pressed", vec![("key", I32)], Bool),
+        Builtin::new("key_just_released", vec![("key", I32)], Bool),
+        // Пауза отладки (F5): движок сам останавливает цикл кадра, программе
+        // остаётся нарисовать заставку паузы; F6 - один кадр, F7 - замедление
+        Builtin::new("is_paused", vec![], Bool),
+        // Символы, набранные с прошлого кадра; Backspace - "\u{8}", Enter - "\n"
+        Builtin::new("get_typed_text", vec![], String),
+        // Окно закрывается: цикл кадра завершится после текущей итерации
+        Builtin::new("window_should_close", vec![], Bool),
+        // on_exit("имя_функции") - функция без аргументов, вызываемая при
+        // завершении программы (в том числе при закрытии окна)
+        Builtin::new("on_exit", vec![("function", String)], Void),
+        // exit(код) завершает программу: вызывается обработчик on_exit,
+        // окно закрывается, процесс возвращает код
+        Builtin::new("exit", vec![("code", I32)], Never),
+
+        // Потоки: spawn("имя_функции") запускает функцию без аргументов,
+        // join возвращает её результат (0 для void)
+        Builtin::new("spawn", vec![("function", String)], I32),
+        Builtin::new("join", vec![("handle", I32)], I32),
+        Builtin::new("channel_send", vec![("channel", I32), ("value", I32)], Void),
+        Builtin::new("channel_recv", vec![("channel", I32)], I32),
+
+        // Сопрограммы: start_coroutine("имя_функции") создаёт сопрограмму,
+        // resume выполняет её до следующего yield_frame() (false - завершена)
+        Builtin::new("start_coroutine", vec![("function", String)], I32),
+        Builtin::new("resume", vec![("handle", I32)], Bool),
+        Builtin::new("yield_frame", vec![], Void),
+
+        // Математика
+        Builtin::new("sin", vec![("angle", F32)], F32),
+        Builtin::new("cos", vec![("angle", F32)], F32),
+        // По таблице: быстрее, точность - до шестого знака. `--fast-math`
+        // подставляет их вместо sin/cos
+        Builtin::new("fast_sin", vec![("angle", F32)], F32),
+        Builtin::new("fast_cos", vec![("angle", F32)], F32),
+        // Числа в диапазоне (ranges.rs), для i32 и для f32
+        Builtin::numeric("clamp", &["x", "lo", "hi"]),
+        Builtin::numeric("wrap", &["x", "lo", "hi"]),
+        Builtin::numeric("map_range", &["x", "a1", "a2", "b1", "b2"]),
+
+        // Анимация: скорость в единицах в секунду умножается на delta_time()
+        // (длительность прошлого кадра, 0 без окна), см. animation.rs
+        Builtin::new("delta_time", vec![], F32),
+        Builtin::new("lerp", vec![("a", F32), ("b", F32), ("t", F32)], F32),
+        Builtin::new("ease_in_out", vec![("t", F32)], F32),
+        Builtin::new("move_towards", vec![("current", F32), ("target", F32), ("max_delta", F32)], F32),
+
+        // Отладка: строка со статистикой памяти интерпретатора и журнал,
+        // который показывается поверх кадра по клавише `
+        Builtin::new("mem_stats", vec![], String),
+        Builtin::new("console_log", vec![("message", String)], Void),
+        // FPS, время кадра, шаги интерпретатора, draw-вызовы и число
+        // структур поверх кадра (при каждом render)
+        Builtin::new("debug_hud", vec![("enabled", Bool)], Void),
+        // assert(условие, "сообщение") - проверяется в отладочном режиме,
+        // удаляется оптимизатором в release
+        Builtin::new("assert", vec![("condition", Bool), ("message", String)], Void),
+
+        // Постоянное хранилище программы (настройки, рекорды), см. storage.rs
+        Builtin::new("store_set", vec![("key", String), ("value", String)], Void),
+        Builtin::new("store_get", vec![("key", String)], String),
+
+        // Путь к ресурсу относительно assets_dir из aetos.toml или каталога скрипта
+        Builtin::new("resolve_asset", vec![("path", String)], String),
+
+        // Случайные числа: random_int(lo, hi) - целое из [lo, hi), random_float - из [0, 1)
+        Builtin::new("random_int", vec![("lo", I32), ("hi", I32)], I32),
+        Builtin::new("random_float", vec![], F32),
+
+        // Сеть: тело ответа на GET-запрос; ошибка соединения или статус
+        // не 2xx - ошибка времени выполнения
+        Builtin::new("http_get", vec![("url", String)], String),
+
+        // Строки: индексы и длины в символах, выход за границы - ошибка времени выполнения
+        Builtin::new("str_len", vec![("text", String)], I32),
+        Builtin::new("substring", vec![("text", String), ("start", I32), ("end", I32)], String),
+        Builtin::new("char_at", vec![("text", String), ("index", I32)], String),
+        Builtin::new("contains", vec![("text", String), ("pattern", String)], Bool),
+        Builtin::new("split", vec![("text", String), ("separator", String)], Array(Box::new(String))),
+        Builtin::new("to_upper", vec![("text", String)], String),
+        Builtin::new("to_lower", vec![("text", String)], String),
+        Builtin::new("parse_i32", vec![("text", String)], I32),
+        // format("x={} y={}", x, y) - `{}` заменяются аргументами любого типа
+        Builtin::variadic("format", vec![("fmt", String)], String),
+
+        // Массивы и функции-значения: результат - новый массив, исходный не меняется.
+        // sort устойчива: less(a, b) - a должен стоять раньше b
+        Builtin::generic("map", &["T", "U"], vec![("values", slice(t())), ("f", function(vec![t()], u()))], Array(Box::new(u()))),
+        Builtin::generic("filter", &["T"], vec![("values", slice(t())), ("keep", function(vec![t()], Bool))], Array(Box::new(t()))),
+        Builtin::generic("sort", &["T"], vec![("values", slice(t())), ("less", function(vec![t(), t()], Bool))], Array(Box::new(t()))),
+        Builtin::generic("fold", &["T", "U"], vec![("values", slice(t())), ("initial", u()), ("f", function(vec![u(), t()], u()))], u()),
+    ]
+}
+
+fn t() -> Type {
+    Type::Struct("T".to_string())
+}
+
+fn u() -> Type {
+    Type::Struct("U".to_string())
+}
+
+fn slice(element: Type) -> Type {
+    Type::Slice(Box::new(element))
+}
+
+fn function(params: Vec<Type>, return_type: Type) -> Type {
+    Type::Function(params, Box::new(return_type))
+}
+
+/// Все встроенные функции (таблица строится один раз)
+pub fn builtins() -> &'static [Builtin] {
+    static BUILTINS: OnceLock<Vec<Builtin>> = OnceLock::new();
+    BUILTINS.get_or_init(build_builtins)
+}
+
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    builtins().iter().find(|builtin| builtin.name == name)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    lookup(name).is_some()
+}
+
+/// Color { r, g, b } - результат palette и литералов `#RRGGBB`
+pub fn color_struct() -> Struct {
+    struct_def("Color", &["r", "g", "b"])
+}
+
+fn struct_def(name: &str, fields: &[&str]) -> Struct {
+    Struct {
+        name: name.to_string(),
+        fields: fields.iter()
+            .map(|field| StructField {
+                name: field.to_string(),
+                field_type: Type::I32,
+                is_public: true,
+                default: None,
+            })
+            .collect(),
+        is_public: true,
+        line: 0,
+        derives: Vec::new(),
+        type_params: Vec::new(),
+        module: 0,
+    }
+}
+
+fn build_stdlib() -> Program {
+    let mut functions: Vec<Function> = builtins().iter()
+        .map(|builtin| Function {
+            name: builtin.name.to_string(),
+            params: builtin.params.iter()
+                .map(|(name, ty)| Parameter {
+                    name: name.to_string(),
+                    param_type: ty.clone(),
+                })
+                .collect(),
+            return_type: builtin.return_type.clone(),
+            body: vec![],
+            is_public: true,
+            line: 0,
+            attributes: Vec::new(),
+            type_params: Vec::new(),
+            module: 0,
+        })
+        .collect();
+
+    // Утилиты, написанные на самом Aetos
+    functions.push(Function {
+        name: "rgb".to_string(),
+        params: ["r", "g", "b"].iter()
+            .map(|name| Parameter {
+                name: name.to_string(),
+                param_type: Type::I32,
+            })
+            .collect(),
+        return_type: Type::Struct("Color".to_string()),
+        body: vec![
+            Statement::Return {
+                value: Expression::StructInitialization {
+                    struct_name: "Color".to_string(),
+                    fields: vec![
+                        ("r".to_string(), Expression::Variable("r".to_string())),
+                        ("g".to_string(), Expression::Variable("g".to_string())),
+                        ("b".to_string(), Expression::Variable("b".to_string())),
+                    ],
+                    base: None,
+                },
+                position: Position::default(),
+            }
+        ],
+        is_public: true,
+        line: 0,
+        attributes: Vec::new(),
+        type_params: Vec::new(),
+        module: 0,
+    });
 
-pub fn get_stdlib() -> Program {
     Program {
         structs: vec![
             // Графические структуры
-            Struct {
-                name: "Point".to_string(),
-                fields: vec![
-                    StructField {
-                        name: "x".to_string(),
-                        field_type: Type::I32,
-                    },
-                    StructField {
-                        name: "y".to_string(),
-                        field_type: Type::I32,
-                    },
-                ],
-            },
-            Struct {
-                name: "Color".to_string(),
-                fields: vec![
-                    StructField {
-                        name: "r".to_string(),
-                        field_type: Type::I32,
-                    },
-                    StructField {
-                        name: "g".to_string(),
-                        field_type: Type::I32,
-                    },
-                    StructField {
-                        name: "b".to_string(),
-                        field_type: Type::I32,
-                    },
-                ],
-            },
-            Struct {
-                name: "Rect".to_string(),
-                fields: vec![
-                    StructField {
-                        name: "x".to_string(),
-                        field_type: Type::I32,
-                    },
-                    StructField {
-                        name: "y".to_string(),
-                        field_type: Type::I32,
-                    },
-                    StructField {
-                        name: "width".to_string(),
-                        field_type: Type::I32,
-                    },
-                    StructField {
-                        name: "height".to_string(),
-                        field_type: Type::I32,
-                    },
-                ],
-            },
-        ],
-        functions: vec![
-            // Базовые функции вывода
-            Function {
-                name: "print".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "value".to_string(),
-                        param_type: Type::I32,
-                    }
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            // Графические функции
-            Function {
-                name: "init_graphics".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "width".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "height".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "title".to_string(),
-                        param_type: Type::String,
-                    },
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            Function {
-                name: "clear_screen".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "color".to_string(),
-                        param_type: Type::Struct("Color".to_string()),
-                    }
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            Function {
-                name: "draw_pixel".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "x".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "y".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "color".to_string(),
-                        param_type: Type::Struct("Color".to_string()),
-                    }
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            Function {
-                name: "draw_rect".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "rect".to_string(),
-                        param_type: Type::Struct("Rect".to_string()),
-                    },
-                    Parameter {
-                        name: "color".to_string(),
-                        param_type: Type::Struct("Color".to_string()),
-                    }
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            Function {
-                name: "draw_circle".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "center_x".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "center_y".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "radius".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "color".to_string(),
-                        param_type: Type::Struct("Color".to_string()),
-                    }
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            Function {
-                name: "draw_line".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "x1".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "y1".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "x2".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "y2".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "color".to_string(),
-                        param_type: Type::Struct("Color".to_string()),
-                    }
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            Function {
-                name: "render".to_string(),
-                params: vec![],
-                return_type: Type::Void,
-                body: vec![],
-            },
-            
-            Function {
-                name: "get_time".to_string(),
-                params: vec![],
-                return_type: Type::F64,
-                body: vec![],
-            },
-            
-            Function {
-                name: "is_key_pressed".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "key".to_string(),
-                        param_type: Type::I32,
-                    }
-                ],
-                return_type: Type::Bool,
-                body: vec![],
-            },
-            
-            Function {
-                name: "get_mouse_pos".to_string(),
-                params: vec![],
-                return_type: Type::Struct("Point".to_string()),
-                body: vec![],
-            },
-            
-            // Утилиты
-            Function {
-                name: "rgb".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "r".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "g".to_string(),
-                        param_type: Type::I32,
-                    },
-                    Parameter {
-                        name: "b".to_string(),
-                        param_type: Type::I32,
-                    },
-                ],
-                return_type: Type::Struct("Color".to_string()),
-                body: vec![
-                    Statement::Return {
-                        value: Expression::StructInitialization {
-                            struct_name: "Color".to_string(),
-                            fields: vec![
-                                ("r".to_string(), Expression::Variable("r".to_string())),
-                                ("g".to_string(), Expression::Variable("g".to_string())),
-                                ("b".to_string(), Expression::Variable("b".to_string())),
-                            ],
-                        },
-                    }
-                ],
-            },
-            
-            // Математические функции
-            Function {
-                name: "sin".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "angle".to_string(),
-                        param_type: Type::F32,
-                    }
-                ],
-                return_type: Type::F32,
-                body: vec![],
-            },
-            
-            Function {
-                name: "cos".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "angle".to_string(),
-                        param_type: Type::F32,
-                    }
-                ],
-                return_type: Type::F32,
-                body: vec![],
-            },
-            
-            // Функция задержки
-            Function {
-                name: "sleep".to_string(),
-                params: vec![
-                    Parameter {
-                        name: "ms".to_string(),
-                        param_type: Type::I32,
-                    }
-                ],
-                return_type: Type::Void,
-                body: vec![],
-            },
+            struct_def("Point", &["x", "y"]),
+            color_struct(),
+            struct_def("Rect", &["x", "y", "width", "height"]),
         ],
+        enums: Vec::new(),
+        imports: Vec::new(),
+        consts: Vec::new(),
+        statics: Vec::new(),
+        traits: Vec::new(),
+        trait_impls: Vec::new(),
+        functions,
     }
-}
\ No newline at end of file
+}
+
+/// AST стандартной библиотеки (строится один раз и кэшируется)
+pub fn get_stdlib() -> &'static Program {
+    static STDLIB: OnceLock<Program> = OnceLock::new();
+    STDLIB.get_or_init(build_stdlib)
+}
@@ -0,0 +1,75 @@
+// storage.rs - постоянное хранилище ключ-значение (store_set / store_get)
+//
+// У каждой программы свой JSON-файл `<имя программы>.json` в каталоге
+// данных платформы, например ~/.local/share/aetos/store на Linux.
+// Значения - строки; файл перезаписывается целиком при каждом store_set,
+// поэтому хранилище рассчитано на настройки и таблицы рекордов, а не на
+// большие объёмы данных.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+    values: BTreeMap<String, String>,
+}
+
+impl Store {
+    /// Открывает хранилище в файле; отсутствующий или повреждённый файл - пустое хранилище
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let values = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self { path, values }
+    }
+
+    /// Хранилище программы в каталоге данных платформы
+    pub fn for_program(program: &str) -> Option<Self> {
+        let name: String = program.chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        Some(Self::open(data_dir()?.join("aetos").join("store").join(format!("{}.json", name))))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Записывает значение и сразу сохраняет файл
+    pub fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.values.insert(key.to_string(), value.to_string());
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.values)?;
+        // Через временный файл, чтобы сбой посреди записи не испортил данные
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, json)?;
+        std::fs::rename(&temp, &self.path)
+    }
+}
+
+/// Каталог пользовательских данных платформы
+pub fn data_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
@@ -0,0 +1,73 @@
+// strings.rs - строковые встроенные функции (str_len, substring, split...)
+//
+// Индексы и длины считаются в символах Unicode, а не в байтах, поэтому
+// substring и char_at не могут разрезать символ пополам.
+
+/// Длина строки в символах
+pub fn str_len(text: &str) -> i32 {
+    text.chars().count() as i32
+}
+
+/// Символы с индексами [start, end)
+pub fn substring(text: &str, start: i32, end: i32) -> Result<String, String> {
+    let len = str_len(text);
+    if start < 0 || end < start || end > len {
+        return Err(format!("substring: range {}..{} out of bounds for length {}", start, end, len));
+    }
+    Ok(text.chars().skip(start as usize).take((end - start) as usize).collect())
+}
+
+/// Символ с индексом index в виде строки из одного символа
+pub fn char_at(text: &str, index: i32) -> Result<String, String> {
+    usize::try_from(index).ok()
+        .and_then(|index| text.chars().nth(index))
+        .map(String::from)
+        .ok_or_else(|| format!("char_at: index {} out of bounds for length {}", index, str_len(text)))
+}
+
+/// Части строки между разделителями; пустые части сохраняются ("a,,b" - три части)
+pub fn split(text: &str, separator: &str) -> Result<Vec<String>, String> {
+    if separator.is_empty() {
+        return Err("split: separator must not be empty".to_string());
+    }
+    Ok(text.split(separator).map(String::from).collect())
+}
+
+/// Шаблон с подстановкой аргументов вместо `{}`; `{{` и `}}` - сами скобки:
+/// format("x={} y={}", ["1", "2"]) = "x=1 y=2"
+pub fn format(template: &str, args: &[String]) -> Result<String, String> {
+    // Текст между подстановками
+    let mut pieces = vec![String::new()];
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                pieces.last_mut().unwrap().push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                pieces.push(String::new());
+            }
+            ('{', _) => return Err("format: unmatched `{`, use `{{` for a brace".to_string()),
+            ('}', _) => return Err("format: unmatched `}`, use `}}` for a brace".to_string()),
+            _ => pieces.last_mut().unwrap().push(c),
+        }
+    }
+    if pieces.len() - 1 != args.len() {
+        return Err(format!("format: {} placeholders but {} arguments", pieces.len() - 1, args.len()));
+    }
+
+    let mut result = pieces[0].clone();
+    for (arg, piece) in args.iter().zip(&pieces[1..]) {
+        result.push_str(arg);
+        result.push_str(piece);
+    }
+    Ok(result)
+}
+
+/// Целое из строки; пробелы по краям допускаются
+pub fn parse_i32(text: &str) -> Result<i32, String> {
+    text.trim().parse()
+        .map_err(|_| format!("parse_i32: invalid integer {:?}", text))
+}
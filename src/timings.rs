@@ -0,0 +1,63 @@
+// timings.rs - замеры времени стадий компиляции (`aetosc compile --timings`)
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+pub struct Timings {
+    phases: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Выполняет стадию и запоминает, сколько она заняла
+    pub fn time<T>(&mut self, phase: &str, run: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = run();
+        self.phases.push((phase.to_string(), start.elapsed()));
+        result
+    }
+
+    pub fn phases(&self) -> &[(String, Duration)] {
+        &self.phases
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total();
+        writeln!(f, "{:<32} {:>12} {:>7}", "phase", "time", "share")?;
+        for (phase, duration) in &self.phases {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            writeln!(f, "{:<32} {:>9.3} ms {:>6.1}%", phase, millis(*duration), share)?;
+        }
+        writeln!(f, "{:<32} {:>9.3} ms", "total", millis(total))?;
+        match peak_rss_kb() {
+            Some(kb) => write!(f, "{:<32} {:>9} KB", "peak RSS", kb),
+            None => write!(f, "{:<32} {:>12}", "peak RSS", "n/a"),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Пиковый объём резидентной памяти процесса (VmHWM), только Linux
+pub fn peak_rss_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status.lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
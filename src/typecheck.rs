@@ -1,7 +1,15 @@
 // typecheck.rs - исправленная версия
 
 use crate::ast::*;
-use std::collections::HashMap;
+use crate::const_eval;
+use crate::derive;
+use crate::generics::{self, GenericError};
+use crate::lint::LintWarning;
+use crate::stdlib;
+use crate::strings;
+use crate::visit;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -21,6 +29,18 @@ pub enum TypeCheckError {
     #[error("Undefined field: {field} in struct {struct_name}")]
     UndefinedField { struct_name: String, field: String },
     
+    #[error("Field {field} of struct {struct_name} is private to the file that declares it; mark it `pub` to use it from other files")]
+    PrivateField { struct_name: String, field: String },
+    
+    #[error("Cannot derive {derive} for {struct_name}: field {field} has type {found}")]
+    CannotDerive { struct_name: String, derive: String, field: String, found: Type },
+    
+    #[error("Missing field: {field} in struct {struct_name} (it has no default value)")]
+    MissingField { struct_name: String, field: String },
+    
+    #[error("Function {name} is started by spawn and must return i32 or void (join returns i32), found {found}")]
+    SpawnReturnType { name: String, found: Type },
+    
     #[error("Function parameter count mismatch: expected {expected}, found {found}")]
     ParameterCountMismatch { expected: usize, found: usize },
     
@@ -33,6 +53,12 @@ pub enum TypeCheckError {
     #[error("Duplicate struct definition: {name}")]
     DuplicateStruct { name: String },
     
+    #[error("Duplicate field: {field} in struct {struct_name}")]
+    DuplicateField { struct_name: String, field: String },
+    
+    #[error("Struct {name} contains itself by value through {path}: use ref {name} to store it on the heap")]
+    RecursiveStruct { name: String, path: String },
+    
     #[error("Invalid return type: expected {expected}, found {found}")]
     InvalidReturnType { expected: Type, found: Type },
     
@@ -42,41 +68,341 @@ pub enum TypeCheckError {
     #[error("Cannot use variable after move: {name}")]
     UseAfterMove { name: String },
     
+    #[error("Cannot borrow {name} as mutable: it is already borrowed")]
+    AlreadyBorrowed { name: String },
+    
+    #[error("Cannot borrow {name}: it is already borrowed as mutable")]
+    AlreadyMutablyBorrowed { name: String },
+    
+    #[error("Cannot assign to {name} while it is borrowed")]
+    AssignWhileBorrowed { name: String },
+    
+    #[error("Cannot move {name} while it is borrowed")]
+    MoveWhileBorrowed { name: String },
+    
     #[error("Condition must be boolean, found {found}")]
     NonBooleanCondition { found: Type },
+    
+    #[error("Function {name} cannot be exported: {reason}")]
+    InvalidExport { name: String, reason: String },
+    
+    #[error("Cannot index into {found}")]
+    NotIndexable { found: Type },
+    
+    #[error("Cannot slice {found}")]
+    NotSliceable { found: Type },
+    
+    #[error("Cannot assign to an element of {found}: only fixed-size arrays [T; N] are mutable")]
+    NotIndexAssignable { found: Type },
+    
+    #[error("Index out of bounds: index {index} but length is {length}")]
+    IndexOutOfBounds { index: i32, length: usize },
+    
+    #[error("Duplicate enum definition: {name}")]
+    DuplicateEnum { name: String },
+    
+    #[error("Duplicate variant: {variant} in enum {enum_name}")]
+    DuplicateVariant { enum_name: String, variant: String },
+    
+    #[error("Undefined variant: {variant} in enum {enum_name}")]
+    UndefinedVariant { enum_name: String, variant: String },
+    
+    #[error("Variant {enum_name}::{variant} has {expected} fields, found {found}")]
+    VariantFieldCountMismatch { enum_name: String, variant: String, expected: usize, found: usize },
+    
+    #[error("Cannot compare {found} with == or !=: it has variants with data, use match")]
+    NotComparable { found: Type },
+    
+    #[error("Operator `{operator}` cannot be applied to {found}")]
+    UnsupportedOperator { operator: BinaryOperator, found: Type },
+    
+    #[error("Cannot concatenate string and {found}: only numbers and bools are converted to string automatically")]
+    InvalidConcatenation { found: Type },
+    
+    #[error("Non-exhaustive match on {found}: missing {missing}")]
+    NonExhaustiveMatch { found: Type, missing: String },
+    
+    #[error("No method {method} on {found}")]
+    UndefinedMethod { found: Type, method: String },
+    
+    #[error("{name} has no self parameter: call it as {name}(...)")]
+    NotAMethod { name: String },
+    
+    #[error("Constant {name} must be initialized with literals and other constants")]
+    NonConstantInitializer { name: String },
+    
+    #[error("Default value of {struct_name}.{field} must be built from literals and constants")]
+    NonConstantFieldDefault { struct_name: String, field: String },
+    
+    #[error("Constant {name} must have a number, bool or string type, found {found}")]
+    InvalidConstantType { name: String, found: Type },
+    
+    #[error("Cannot assign to constant {name}")]
+    AssignToConstant { name: String },
+    
+    #[error("Cannot assign twice to immutable variable {name}: consider adding mut (`let mut {name}`)")]
+    AssignToImmutableVariable { name: String },
+    
+    #[error("Cannot assign to a field of immutable variable {name}: declare it with `let mut`")]
+    AssignToImmutable { name: String },
+    
+    #[error("Cannot assign to an element of immutable variable {name}: declare it with `let mut`")]
+    AssignToImmutableElement { name: String },
+    
+    #[error("Static {name} must be initialized with literals and constants")]
+    NonConstantStaticInitializer { name: String },
+    
+    #[error("Const function {function} cannot call {callee}: only const functions and pure builtins are allowed")]
+    NotConstCall { function: String, callee: String },
+    
+    #[error("Const function {function} cannot use static {name}")]
+    StaticInConstFn { function: String, name: String },
+    
+    #[error("Static {name} must have a number, bool or string type, found {found}")]
+    InvalidStaticType { name: String, found: Type },
+    
+    #[error("Cannot assign to immutable static {name}: declare it `static mut`")]
+    AssignToImmutableStatic { name: String },
+    
+    #[error("`{keyword}` outside of a loop")]
+    LoopExitOutsideLoop { keyword: String },
+    
+    #[error("Undefined loop label '{label}")]
+    UndefinedLabel { label: String },
+    
+    #[error("Operator `?` cannot be applied to {found}: expected Option or Result")]
+    NotTryable { found: Type },
+    
+    #[error("Operator `?` on {found} cannot return from a function returning {return_type}")]
+    TryReturnMismatch { found: Type, return_type: Type },
+    
+    #[error("Variable {name} of type {found} is not a function")]
+    NotCallable { name: String, found: Type },
+    
+    #[error("`loop` without `break` or `return` never finishes, but the function returns {return_type}")]
+    InfiniteLoop { return_type: Type },
+
+    #[error("Integer literal {value} does not fit in i32; add the i64 suffix or use it where i64 is expected")]
+    IntegerLiteralOutOfRange { value: i64 },
+
+    #[error("Function {function} can reach its end without returning {return_type}")]
+    MissingReturn { function: String, return_type: Type },
+
+    #[error("Invalid format string: {message}")]
+    InvalidFormat { message: String },
+
+    #[error("Duplicate trait definition: {name}")]
+    DuplicateTrait { name: String },
+
+    #[error("Undefined trait: {name}")]
+    UndefinedTrait { name: String },
+
+    #[error("impl {trait_name} for {type_name} is missing method {method}")]
+    MissingTraitMethod { trait_name: String, type_name: String, method: String },
+
+    #[error("Method {method} is not a member of trait {trait_name}")]
+    NotTraitMember { trait_name: String, method: String },
+
+    #[error("Method {type_name}::{method} does not match trait {trait_name}: expected {}, found {}", signatures.0, signatures.1)]
+    TraitMethodMismatch { trait_name: String, type_name: String, method: String, signatures: Box<(Type, Type)> },
+
+    #[error("{0}")]
+    Generic(GenericError),
 }
 
 type TypeCheckResult<T> = Result<T, TypeCheckError>;
 
+/// Сколько ошибок сообщать из одной функции: дальше обычно идут каскадные
+const MAX_ERRORS_PER_FUNCTION: usize = 10;
+
+/// Все ошибки, найденные за один запуск проверки типов
+#[derive(Debug)]
+pub struct TypeCheckErrors {
+    pub errors: Vec<TypeCheckError>,
+    /// Функция, в которой найдена ошибка с тем же номером; None - вне функций
+    pub functions: Vec<Option<String>>,
+    /// Ошибки сверх лимита на функцию, не попавшие в список
+    pub suppressed: usize,
+}
+
+impl TypeCheckErrors {
+    pub fn first(&self) -> &TypeCheckError {
+        &self.errors[0]
+    }
+}
+
+impl std::fmt::Display for TypeCheckErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let lines: Vec<String> = self.errors.iter().zip(&self.functions)
+            .map(|(e, function)| match function {
+                Some(function) => format!("in fn {}: {}", function, e),
+                None => e.to_string(),
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))?;
+        if self.suppressed > 0 {
+            write!(f, "\n... and {} more errors", self.suppressed)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TypeCheckErrors {}
+
+/// Типы выражений проверенной программы. Выражения в AST не нумеруются,
+/// поэтому ключ - адрес узла: типы относятся только к выражениям той
+/// копии программы, которую хранит `TypedProgram`
+#[derive(Debug, Clone, Default)]
+pub struct ExpressionTypes {
+    types: HashMap<usize, Type>,
+}
+
+impl ExpressionTypes {
+    fn key(expression: &Expression) -> usize {
+        std::ptr::from_ref(expression) as usize
+    }
+
+    fn insert(&mut self, expression: &Expression, ty: Type) {
+        self.types.insert(Self::key(expression), ty);
+    }
+
+    /// Тип выражения или None для узла, которого нет в проверенной программе
+    pub fn get(&self, expression: &Expression) -> Option<&Type> {
+        self.types.get(&Self::key(expression))
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+}
+
+/// Результат проверки типов: программа после подстановки обобщений и тип
+/// каждого её выражения. Программа доступна только для чтения: изменение
+/// или копия узла теряет его тип
+#[derive(Debug)]
+pub struct TypedProgram {
+    program: Program,
+    types: ExpressionTypes,
+}
+
+impl TypedProgram {
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn types(&self) -> &ExpressionTypes {
+        &self.types
+    }
+
+    /// Тип выражения из `program()`
+    pub fn type_of(&self, expression: &Expression) -> Option<&Type> {
+        self.types.get(expression)
+    }
+
+    pub fn into_program(self) -> Program {
+        self.program
+    }
+}
+
+/// Узлы выражений программы: по ним из таблицы убираются типы временных
+/// выражений, которые проверка строит сама (производные методы, `a[i] = v`)
+struct ExpressionNodes(HashSet<usize>);
+
+impl visit::Visitor for ExpressionNodes {
+    fn visit_expression(&mut self, expression: &Expression) {
+        self.0.insert(ExpressionTypes::key(expression));
+        visit::walk_expression(self, expression);
+    }
+}
+
 #[derive(Debug, Clone)]
 enum VariableState {
     Available,
     Moved,
-    Borrowed,
+}
+
+/// Действующее заимствование переменной `owner`. Временное (аргумент
+/// вызова) заканчивается вместе с оператором, сохранённое в `let` - с блоком
+#[derive(Debug, Clone)]
+struct ActiveBorrow {
+    owner: String,
+    mutable: bool,
+    held: bool,
 }
 
 #[derive(Debug, Clone)]
 struct VariableInfo {
     var_type: Type,
     state: VariableState,
+    // Объявлена через `let mut`: можно присваивать полям
+    mutable: bool,
 }
 
 #[derive(Debug, Clone)]
 struct FunctionInfo {
     return_type: Type,
     params: Vec<Type>,
+    // Вариадические функции: params - обязательные аргументы, остальные любого типа
+    variadic: bool,
+    // Перегрузка для i32 и f32, см. stdlib::Builtin::numeric
+    numeric: bool,
+    // Параметры типа выводятся из аргументов, см. stdlib::Builtin::type_params
+    generic: bool,
 }
 
 #[derive(Debug, Clone)]
 struct StructInfo {
     fields: HashMap<String, Type>,
+    // Поля без значения по умолчанию в порядке объявления
+    required: Vec<String>,
+    // Поля без `pub` доступны только в файле структуры (module)
+    private: HashSet<String>,
+    module: usize,
 }
 
 pub struct TypeChecker {
     variables: HashMap<String, VariableInfo>,
     functions: HashMap<String, FunctionInfo>,
     structs: HashMap<String, StructInfo>,
+    enums: HashMap<String, Enum>,
+    traits: HashMap<String, Trait>,
+    // Пары (трейт, тип) из `impl Трейт for Тип`
+    trait_impls: HashSet<(String, String)>,
+    // Функции из impl-блоков с параметром self (вызываются через точку)
+    methods: HashSet<String>,
+    // Константы и статические переменные верхнего уровня видны во всех
+    // функциях и не перекрываются локальными именами
+    constants: HashMap<String, Type>,
+    // Статические переменные: имя -> объявлена ли как `static mut`
+    statics: HashMap<String, bool>,
+    // Функции, объявленные как `const fn`
+    const_functions: HashSet<String>,
     current_function_return: Option<Type>,
+    current_function: String,
+    // Файл проверяемой функции, см. Function::module
+    current_module: usize,
+    // Проверяется тело const fn: вызывать можно только const fn и чистые
+    // встроенные функции, статические переменные недоступны
+    in_const_fn: bool,
+    // Метки объемлющих циклов, внутренний - последний
+    loop_labels: Vec<Option<String>>,
+    // Заимствования в порядке появления, вложенный блок - в конце
+    borrows: Vec<ActiveBorrow>,
+    // Открытые блоки, внутренний - последний: объявленные в блоке имена
+    // и перекрытые ими переменные внешних блоков
+    scopes: Vec<Vec<(String, Option<VariableInfo>)>>,
+    // Текущий оператор содержит вызов, который не возвращает управление
+    diverges: bool,
+    expression_types: ExpressionTypes,
+    errors: Vec<TypeCheckError>,
+    error_functions: Vec<Option<String>>,
+    warnings: Vec<LintWarning>,
+    function_errors: usize,
+    suppressed: usize,
 }
 
 impl TypeChecker {
@@ -85,7 +411,27 @@ impl TypeChecker {
             variables: HashMap::new(),
             functions: HashMap::new(),
             structs: HashMap::new(),
+            enums: HashMap::new(),
+            traits: HashMap::new(),
+            trait_impls: HashSet::new(),
+            methods: HashSet::new(),
+            constants: HashMap::new(),
+            statics: HashMap::new(),
+            const_functions: HashSet::new(),
             current_function_return: None,
+            current_function: String::new(),
+            current_module: 0,
+            in_const_fn: false,
+            loop_labels: Vec::new(),
+            borrows: Vec::new(),
+            scopes: Vec::new(),
+            diverges: false,
+            expression_types: ExpressionTypes::default(),
+            errors: Vec::new(),
+            error_functions: Vec::new(),
+            warnings: Vec::new(),
+            function_errors: 0,
+            suppressed: 0,
         };
         
         checker.add_builtin_functions();
@@ -93,141 +439,278 @@ impl TypeChecker {
     }
     
     fn add_builtin_functions(&mut self) {
-        self.functions.insert(
-            "print_i32".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32],
-            },
-        );
-    
-        self.functions.insert(
-            "print_string".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::String],
-            },
-        );
-        
-        self.functions.insert(
-            "print".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32],
-            },
-        );
-        
-        // Embedded functions
-        self.functions.insert(
-            "gpio_set".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32, Type::I32],
-            },
-        );
-        
-        self.functions.insert(
-            "gpio_toggle".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32],
-            },
-        );
-        
-        self.functions.insert(
-            "delay".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32],
-            },
-        );
+        self.functions.extend(Self::builtin_summary().clone());
+    }
+    
+    /// Сигнатуры встроенных функций из stdlib (вычисляются один раз)
+    fn builtin_summary() -> &'static HashMap<String, FunctionInfo> {
+        static SUMMARY: OnceLock<HashMap<String, FunctionInfo>> = OnceLock::new();
+        SUMMARY.get_or_init(|| {
+            stdlib::builtins().iter()
+                .map(|builtin| {
+                    (
+                        builtin.name.to_string(),
+                        FunctionInfo {
+                            return_type: builtin.return_type.clone(),
+                            params: builtin.param_types(),
+                            variadic: builtin.variadic,
+                            numeric: builtin.numeric,
+                            generic: !builtin.type_params.is_empty(),
+                        },
+                    )
+                })
+                .collect()
+        })
+    }
+    
+    /// Экспортируемые функции вызываются из C/JS, поэтому в сигнатуре
+    /// допустимы только скалярные типы
+    fn check_export_signature(function: &Function) -> TypeCheckResult<()> {
+        let is_abi_type = |ty: &Type| !matches!(ty, Type::String | Type::Struct(_) | Type::Ref(_) | Type::Array(_) | Type::FixedArray(..) | Type::Enum(_));
         
-        // Графические функции
-        self.functions.insert(
-            "init_graphics".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32, Type::I32, Type::String],
-            },
-        );
-    
-        self.functions.insert(
-            "clear_screen".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32, Type::I32, Type::I32],
-            },
-        );
+        for param in &function.params {
+            if !is_abi_type(&param.param_type) {
+                return Err(TypeCheckError::InvalidExport {
+                    name: function.name.clone(),
+                    reason: format!("parameter `{}` has non-scalar type {}", param.name, param.param_type),
+                });
+            }
+        }
         
-        self.functions.insert(
-            "draw_circle".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32, Type::I32, Type::I32, Type::I32, Type::I32, Type::I32],
-            },
-        );
+        if !is_abi_type(&function.return_type) {
+            return Err(TypeCheckError::InvalidExport {
+                name: function.name.clone(),
+                reason: format!("non-scalar return type {}", function.return_type),
+            });
+        }
         
-        self.functions.insert(
-            "draw_line".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32, Type::I32, Type::I32, Type::I32, Type::I32, Type::I32, Type::I32],
-            },
-        );
+        Ok(())
+    }
+    
+    fn struct_info(struct_def: &Struct) -> StructInfo {
+        let mut fields = HashMap::new();
+        for field in &struct_def.fields {
+            fields.insert(field.name.clone(), field.field_type.clone());
+        }
+        let required = struct_def.fields.iter()
+            .filter(|field| field.default.is_none())
+            .map(|field| field.name.clone())
+            .collect();
+        let private = struct_def.fields.iter()
+            .filter(|field| !field.is_public)
+            .map(|field| field.name.clone())
+            .collect();
+        StructInfo { fields, required, private, module: struct_def.module }
+    }
+
+    /// Поле без `pub` читается и задаётся только в файле своей структуры
+    fn check_field_visibility(&self, struct_name: &str, field_name: &str) -> TypeCheckResult<()> {
+        match self.structs.get(struct_name) {
+            Some(info) if info.module != self.current_module && info.private.contains(field_name) => {
+                Err(TypeCheckError::PrivateField {
+                    struct_name: struct_name.to_string(),
+                    field: field_name.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Структура, которая хранит себя по значению (прямо, через Option или
+    /// через другие структуры), имела бы бесконечный размер. Цикл разрывают
+    /// ref T и массивы: их элементы лежат в куче. О каждом цикле сообщается
+    /// один раз - для первой по порядку объявления структуры в нём
+    fn check_recursive_structs(&mut self, program: &Program) {
+        let structs: HashMap<&str, &Struct> = program.structs.iter()
+            .map(|struct_def| (struct_def.name.as_str(), struct_def))
+            .collect();
+        let mut reported = HashSet::new();
+        for struct_def in &program.structs {
+            if reported.contains(&struct_def.name) {
+                continue;
+            }
+            let mut path = Vec::new();
+            if Self::find_value_cycle(&structs, &struct_def.name, &struct_def.name, &mut path, &mut HashSet::new()) {
+                reported.extend(path.iter().map(|(owner, _): &(String, String)| owner.clone()));
+                let path: Vec<String> = path.iter().map(|(owner, field)| format!("{}.{}", owner, field)).collect();
+                self.report(TypeCheckError::RecursiveStruct {
+                    name: struct_def.name.clone(),
+                    path: path.join(" -> "),
+                });
+            }
+        }
+    }
+    
+    /// Ищет путь по полям от структуры `current` обратно к `target`;
+    /// найденный путь остаётся в `path` парами (структура, поле)
+    fn find_value_cycle<'a>(structs: &HashMap<&str, &'a Struct>, target: &str, current: &'a str, path: &mut Vec<(String, String)>, visited: &mut HashSet<&'a str>) -> bool {
+        if !visited.insert(current) {
+            return false;
+        }
+        let Some(struct_def) = structs.get(current) else {
+            return false;
+        };
+        for field in &struct_def.fields {
+            let mut contained = Vec::new();
+            Self::value_structs(&field.field_type, &mut contained);
+            for name in contained {
+                path.push((current.to_string(), field.name.clone()));
+                if name == target || Self::find_value_cycle(structs, target, name, path, visited) {
+                    return true;
+                }
+                path.pop();
+            }
+        }
+        false
+    }
+    
+    /// Структуры, которые тип хранит прямо в значении, а не в куче
+    fn value_structs<'a>(field_type: &'a Type, out: &mut Vec<&'a str>) {
+        match field_type {
+            Type::Struct(name) => out.push(name),
+            Type::Option(inner) => Self::value_structs(inner, out),
+            Type::Result(ok, err) => {
+                Self::value_structs(ok, out);
+                Self::value_structs(err, out);
+            }
+            _ => {}
+        }
+    }
+    
+    /// Проверяет всю программу, не останавливаясь на первой ошибке
+    pub fn check_program(&mut self, program: &Program) -> Result<TypedProgram, TypeCheckErrors> {
+        self.errors.clear();
+        self.error_functions.clear();
+        self.current_function.clear();
+        self.warnings.clear();
+        self.suppressed = 0;
+        self.function_errors = 0;
+        self.in_const_fn = false;
+        self.expression_types = ExpressionTypes::default();
         
-        self.functions.insert(
-            "render".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![],
-            },
-        );
+        // Обобщённые объявления заменяются копиями для используемых типов,
+        // дальше проверяются только копии; копию забирает TypedProgram
+        let program = match generics::monomorphize(program) {
+            Ok(program) => program.into_owned(),
+            Err(e) => {
+                return Err(TypeCheckErrors { errors: vec![TypeCheckError::Generic(e)], functions: vec![None], suppressed: 0 });
+            }
+        };
+        self.check_declarations(&program);
         
-        // В функции add_builtin_functions в typecheck.rs
-        self.functions.insert(
-            "get_time".to_string(),
-            FunctionInfo {
-                return_type: Type::F32,  // Изменено с F64 на F32
-                params: vec![],
-            },
-        );
+        if !self.errors.is_empty() {
+            return Err(TypeCheckErrors {
+                errors: std::mem::take(&mut self.errors),
+                functions: std::mem::take(&mut self.error_functions),
+                suppressed: self.suppressed,
+            });
+        }
         
-        self.functions.insert(
-            "sleep".to_string(),
-            FunctionInfo {
-                return_type: Type::Void,
-                params: vec![Type::I32],
-            },
-        );
+        let mut nodes = ExpressionNodes(HashSet::new());
+        visit::walk_program(&mut nodes, &program);
+        for value in program.consts.iter().map(|c| &c.value).chain(program.statics.iter().map(|s| &s.value)) {
+            visit::Visitor::visit_expression(&mut nodes, value);
+        }
+        let mut types = std::mem::take(&mut self.expression_types);
+        types.types.retain(|key, _| nodes.0.contains(key));
+        Ok(TypedProgram { program, types })
     }
     
-    pub fn check_program(&mut self, program: &Program) -> TypeCheckResult<()> {
+    /// Объявления и тела функций; ошибки копятся в `errors`
+    fn check_declarations(&mut self, program: &Program) {
+        
         // Сначала собираем информацию о структурах
         for struct_def in &program.structs {
             if self.structs.contains_key(&struct_def.name) {
-                return Err(TypeCheckError::DuplicateStruct {
+                self.report(TypeCheckError::DuplicateStruct {
                     name: struct_def.name.clone(),
                 });
+                continue;
             }
-            
-            let mut fields = HashMap::new();
-            for field in &struct_def.fields {
-                fields.insert(field.name.clone(), field.field_type.clone());
+            for (i, field) in struct_def.fields.iter().enumerate() {
+                if struct_def.fields[..i].iter().any(|other| other.name == field.name) {
+                    self.report(TypeCheckError::DuplicateField {
+                        struct_name: struct_def.name.clone(),
+                        field: field.name.clone(),
+                    });
+                }
             }
-            
-            self.structs.insert(
-                struct_def.name.clone(),
-                StructInfo { fields },
-            );
+            self.structs.insert(struct_def.name.clone(), Self::struct_info(struct_def));
+        }
+        // Color стандартной библиотеки (palette, литералы `#RRGGBB`) доступен
+        // и без объявления; своя структура Color его заменяет
+        if !self.structs.contains_key("Color") {
+            let color = stdlib::color_struct();
+            self.structs.insert(color.name.clone(), Self::struct_info(&color));
+        }
+        
+        self.check_recursive_structs(program);
+        
+        for enum_def in &program.enums {
+            if self.enums.contains_key(&enum_def.name) || self.structs.contains_key(&enum_def.name) {
+                self.report(TypeCheckError::DuplicateEnum {
+                    name: enum_def.name.clone(),
+                });
+                continue;
+            }
+            for (i, variant) in enum_def.variants.iter().enumerate() {
+                if enum_def.variants[..i].iter().any(|other| other.name == variant.name) {
+                    self.report(TypeCheckError::DuplicateVariant {
+                        enum_name: enum_def.name.clone(),
+                        variant: variant.name.clone(),
+                    });
+                }
+            }
+            self.enums.insert(enum_def.name.clone(), enum_def.clone());
+        }
+        
+        for trait_def in &program.traits {
+            let name = &trait_def.name;
+            if self.traits.contains_key(name) || self.structs.contains_key(name) || self.enums.contains_key(name) {
+                self.report(TypeCheckError::DuplicateTrait { name: name.clone() });
+                continue;
+            }
+            self.traits.insert(name.clone(), trait_def.clone());
+        }
+        
+        // Методы из @derive проверяются так же, как написанные вручную
+        let (derived, derive_errors) = derive::derived_functions(program);
+        for error in derive_errors {
+            self.report(TypeCheckError::CannotDerive {
+                struct_name: error.struct_name,
+                derive: error.derive.name().to_string(),
+                field: error.field,
+                found: error.found,
+            });
         }
         
         // Сначала собираем информацию о ВСЕХ функциях (включая пользовательские)
         let mut function_info = HashMap::new();
-        for function in &program.functions {
+        for function in program.functions.iter().chain(&derived) {
             if function_info.contains_key(&function.name) {
-                return Err(TypeCheckError::DuplicateFunction {
+                self.report(TypeCheckError::DuplicateFunction {
                     name: function.name.clone(),
                 });
+                continue;
+            }
+            
+            if let Some((type_name, method)) = split_method_name(&function.name) {
+                if !self.structs.contains_key(type_name) && !self.enums.contains_key(type_name) {
+                    self.report(TypeCheckError::UndefinedStruct {
+                        name: type_name.to_string(),
+                    });
+                    continue;
+                }
+                // Shape::Circle(..) должно однозначно означать вариант или функцию
+                if self.enums.get(type_name).is_some_and(|enum_def| enum_def.variant(method).is_some()) {
+                    self.report(TypeCheckError::DuplicateFunction {
+                        name: function.name.clone(),
+                    });
+                    continue;
+                }
+                if function.params.first().is_some_and(|param| param.name == "self") {
+                    self.methods.insert(function.name.clone());
+                }
             }
             
             let param_types: Vec<Type> = function.params.iter()
@@ -239,6 +722,9 @@ impl TypeChecker {
                 FunctionInfo {
                     return_type: function.return_type.clone(),
                     params: param_types,
+                    variadic: false,
+                    numeric: false,
+                    generic: false,
                 },
             );
         }
@@ -248,103 +734,666 @@ impl TypeChecker {
             self.functions.insert(name.clone(), info.clone());
         }
         
-        // Проверяем функции
-        for function in &program.functions {
-            self.check_function(function)?;
+        // Реализации трейтов известны до проверки тел: тип можно передать
+        // как трейт в любой функции
+        for trait_impl in &program.trait_impls {
+            if let Err(e) = self.check_trait_impl(trait_impl) {
+                self.report(e);
+            }
+        }
+        self.const_functions = program.functions.iter()
+            .filter(|function| function.is_const())
+            .map(|function| function.name.clone())
+            .collect();
+        
+        // Инициализаторы могут вызывать const fn, поэтому проверяются после
+        // регистрации функций
+        self.check_constants(&program.consts);
+        self.check_field_defaults(&program.structs);
+        self.check_statics(&program.statics);
+        
+        // Проверяем функции; ошибки копятся, проверка продолжается
+        for function in program.functions.iter().chain(&derived) {
+            self.function_errors = 0;
+            self.current_function = function.name.clone();
+            if function.is_exported() {
+                if let Err(e) = Self::check_export_signature(function) {
+                    self.report(e);
+                }
+            }
+            self.check_function(function);
+        }
+    }
+    
+    /// Методы блока `impl Трейт for Тип` совпадают с сигнатурами трейта
+    fn check_trait_impl(&mut self, trait_impl: &TraitImpl) -> TypeCheckResult<()> {
+        let TraitImpl { trait_name, type_name, .. } = trait_impl;
+        let trait_def = self.traits.get(trait_name)
+            .ok_or_else(|| TypeCheckError::UndefinedTrait { name: trait_name.clone() })?;
+        if !self.structs.contains_key(type_name) && !self.enums.contains_key(type_name) {
+            return Err(TypeCheckError::UndefinedStruct { name: type_name.clone() });
+        }
+        if let Some(extra) = trait_impl.methods.iter().find(|method| trait_def.method(method).is_none()) {
+            return Err(TypeCheckError::NotTraitMember { trait_name: trait_name.clone(), method: extra.clone() });
         }
         
+        for method in &trait_def.methods {
+            let name = method_name(type_name, &method.name);
+            let function_info = match self.functions.get(&name) {
+                Some(info) if self.methods.contains(&name) => info,
+                _ => {
+                    return Err(TypeCheckError::MissingTraitMethod {
+                        trait_name: trait_name.clone(),
+                        type_name: type_name.clone(),
+                        method: method.name.clone(),
+                    });
+                }
+            };
+            // self у трейта и у типа разный, сравниваются остальные параметры
+            let expected = Type::Function(
+                method.params[1..].iter().map(|param| param.param_type.clone()).collect(),
+                Box::new(method.return_type.clone()),
+            );
+            let found = Type::Function(function_info.params[1..].to_vec(), Box::new(function_info.return_type.clone()));
+            if expected != found {
+                return Err(TypeCheckError::TraitMethodMismatch {
+                    trait_name: trait_name.clone(),
+                    type_name: type_name.clone(),
+                    method: method.name.clone(),
+                    signatures: Box::new((expected, found)),
+                });
+            }
+        }
+        self.trait_impls.insert((trait_name.clone(), type_name.clone()));
         Ok(())
     }
     
-    fn check_function(&mut self, function: &Function) -> TypeCheckResult<()> {
+    fn is_trait(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Struct(name) if self.traits.contains_key(name))
+    }
+    
+    fn implements(&self, type_name: &str, trait_name: &str) -> bool {
+        self.trait_impls.contains(&(trait_name.to_string(), type_name.to_string()))
+    }
+    
+    /// Константа видит только объявленные выше константы, поэтому циклов нет
+    fn check_constants(&mut self, consts: &[Const]) {
         self.variables.clear();
-        self.current_function_return = Some(function.return_type.clone());
-        
-        for param in &function.params {
-            if self.variables.contains_key(&param.name) {
-                return Err(TypeCheckError::DuplicateVariable {
-                    name: param.name.clone(),
+        for constant in consts {
+            if self.constants.contains_key(&constant.name) {
+                self.report(TypeCheckError::DuplicateVariable {
+                    name: constant.name.clone(),
                 });
+                continue;
             }
-            
-            self.variables.insert(
-                param.name.clone(),
-                VariableInfo {
-                    var_type: param.param_type.clone(),
-                    state: VariableState::Available,
-                },
-            );
+            if let Err(e) = self.check_constant(constant) {
+                self.report(e);
+            }
+            // Объявляем и при ошибке, чтобы не порождать каскад "Undefined variable"
+            self.constants.insert(constant.name.clone(), constant.const_type.clone());
         }
-        
-        for statement in &function.body {
-            self.check_statement(statement)?;
+    }
+    
+    fn check_constant(&mut self, constant: &Const) -> TypeCheckResult<()> {
+        if !matches!(constant.const_type, Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::Bool | Type::String) {
+            return Err(TypeCheckError::InvalidConstantType {
+                name: constant.name.clone(),
+                found: constant.const_type.clone(),
+            });
+        }
+        if !self.is_constant_expression(&constant.value) {
+            return Err(TypeCheckError::NonConstantInitializer {
+                name: constant.name.clone(),
+            });
+        }
+        let value_type = self.check_expression_as(&constant.value, &constant.const_type)?;
+        if !self.types_are_compatible(&constant.const_type, &value_type) {
+            return Err(TypeCheckError::TypeMismatch {
+                expected: constant.const_type.clone(),
+                found: value_type,
+            });
         }
-        
         Ok(())
     }
     
-    fn check_statement(&mut self, statement: &Statement) -> TypeCheckResult<()> {
-        match statement {
-            Statement::VariableDeclaration { name, var_type, value, mutable: _ } => {
-                if self.variables.contains_key(name) {
-                    return Err(TypeCheckError::DuplicateVariable {
-                        name: name.clone(),
-                    });
-                }
-                
-                let expr_type = self.check_expression(value)?;
-                
-                // Разрешаем неявное приведение i32 -> f32
-                if !self.types_are_compatible(var_type, &expr_type) {
-                    return Err(TypeCheckError::TypeMismatch {
-                        expected: var_type.clone(),
-                        found: expr_type,
-                    });
+    /// Значения полей по умолчанию, как и константы, вычисляются один раз
+    /// при загрузке программы; статические переменные им ещё не видны
+    fn check_field_defaults(&mut self, structs: &[Struct]) {
+        for struct_def in structs {
+            for field in &struct_def.fields {
+                let Some(default) = &field.default else {
+                    continue;
+                };
+                if let Err(e) = self.check_field_default(&struct_def.name, field, default) {
+                    self.report(e);
                 }
-                
-                self.variables.insert(
-                    name.clone(),
-                    VariableInfo {
-                        var_type: var_type.clone(),
-                        state: VariableState::Available,
-                    },
-                );
-                
-                Ok(())
             }
-
-            // В функции check_assignment (около строки 327):
-            Statement::Assignment { name, value } => {
-                // Сначала получаем тип выражения
-                let expr_type = self.check_expression(value)?;
-    
-                // Затем получаем тип переменной
-                let var_type = {
-                    let var_info = self.variables.get(name)
-                        .ok_or_else(|| TypeCheckError::UndefinedVariable {
-                            name: name.clone(),
-                        })?;
-                    var_info.var_type.clone()
-                };
+        }
+    }
     
-                // Проверяем совместимость типов
-                if !self.types_are_compatible(&var_type, &expr_type) {
-                    return Err(TypeCheckError::TypeMismatch {
-                        expected: var_type,
-                        found: expr_type,
-                    });
-                }
+    fn check_field_default(&mut self, struct_name: &str, field: &StructField, default: &Expression) -> TypeCheckResult<()> {
+        if !self.is_constant_expression(default) {
+            return Err(TypeCheckError::NonConstantFieldDefault {
+                struct_name: struct_name.to_string(),
+                field: field.name.clone(),
+            });
+        }
+        let value_type = self.check_expression(default)?;
+        if !self.types_are_compatible(&field.field_type, &value_type) {
+            return Err(TypeCheckError::TypeMismatch {
+                expected: field.field_type.clone(),
+                found: value_type,
+            });
+        }
+        Ok(())
+    }
     
-                Ok(())
+    /// Начальные значения статических переменных видят только константы:
+    /// так они не зависят от порядка инициализации
+    fn check_statics(&mut self, statics: &[Static]) {
+        for static_def in statics {
+            if let Err(e) = self.check_static(static_def) {
+                self.report(e);
             }
-            
-            Statement::Return { value } => {
+        }
+        for static_def in statics {
+            if self.constants.contains_key(&static_def.name) {
+                self.report(TypeCheckError::DuplicateVariable {
+                    name: static_def.name.clone(),
+                });
+                continue;
+            }
+            self.constants.insert(static_def.name.clone(), static_def.static_type.clone());
+            self.statics.insert(static_def.name.clone(), static_def.mutable);
+        }
+    }
+    
+    fn check_static(&mut self, static_def: &Static) -> TypeCheckResult<()> {
+        if !matches!(static_def.static_type, Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::Bool | Type::String) {
+            return Err(TypeCheckError::InvalidStaticType {
+                name: static_def.name.clone(),
+                found: static_def.static_type.clone(),
+            });
+        }
+        if !self.is_constant_expression(&static_def.value) {
+            return Err(TypeCheckError::NonConstantStaticInitializer {
+                name: static_def.name.clone(),
+            });
+        }
+        let value_type = self.check_expression_as(&static_def.value, &static_def.static_type)?;
+        if !self.types_are_compatible(&static_def.static_type, &value_type) {
+            return Err(TypeCheckError::TypeMismatch {
+                expected: static_def.static_type.clone(),
+                found: value_type,
+            });
+        }
+        Ok(())
+    }
+    
+    /// Выражение, которое сворачивается при компиляции
+    fn is_constant_expression(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::IntegerLiteral(_)
+            | Expression::FloatLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::BoolLiteral(_) => true,
+            // Локальных переменных здесь нет: неизвестное имя сообщит check_expression
+            Expression::Variable(_) => true,
+            Expression::BinaryExpression { left, right, .. } => {
+                self.is_constant_expression(left) && self.is_constant_expression(right)
+            }
+            Expression::TypeCast { expression, .. } => self.is_constant_expression(expression),
+            // Вызов const fn вычисляется при компиляции (const_eval.rs)
+            Expression::FunctionCall { name, args } => {
+                self.const_functions.contains(name) && args.iter().all(|arg| self.is_constant_expression(arg))
+            }
+            _ => false,
+        }
+    }
+    
+    /// Статические переменные меняются во время работы, константы - нет
+    fn check_static_access(&self, name: &str) -> TypeCheckResult<()> {
+        if self.in_const_fn && self.statics.contains_key(name) {
+            return Err(TypeCheckError::StaticInConstFn {
+                function: self.current_function.clone(),
+                name: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+    
+    /// В const fn можно вызывать только const fn и чистые встроенные функции
+    fn check_const_call(&self, callee: &str) -> TypeCheckResult<()> {
+        if !self.in_const_fn || self.const_functions.contains(callee) || const_eval::is_const_builtin(callee) {
+            return Ok(());
+        }
+        Err(TypeCheckError::NotConstCall {
+            function: self.current_function.clone(),
+            callee: callee.to_string(),
+        })
+    }
+    
+    fn report(&mut self, error: TypeCheckError) {
+        if self.function_errors < MAX_ERRORS_PER_FUNCTION {
+            self.errors.push(error);
+            self.error_functions.push(Some(self.current_function.clone()).filter(|name| !name.is_empty()));
+        } else {
+            self.suppressed += 1;
+        }
+        self.function_errors += 1;
+    }
+    
+    /// Предупреждения последней проверки (недостижимый код)
+    pub fn warnings(&self) -> &[LintWarning] {
+        &self.warnings
+    }
+    
+    /// Операторы вложенного блока в своей области видимости
+    fn check_statements(&mut self, statements: &[Statement]) {
+        self.scopes.push(Vec::new());
+        self.check_block(statements);
+        self.exit_scope();
+    }
+    
+    /// Закрывает блок: его переменные исчезают, перекрытые ими внешние снова
+    /// видны. Изменения внешних переменных (move) после блока остаются
+    fn exit_scope(&mut self) {
+        let scope = self.scopes.pop().expect("open scope");
+        for (name, shadowed) in scope.into_iter().rev() {
+            match shadowed {
+                Some(info) => self.variables.insert(name, info),
+                None => self.variables.remove(&name),
+            };
+        }
+    }
+    
+    /// Имя можно объявить, если в текущем блоке его ещё нет; переменную
+    /// внешнего блока новая перекрывает, константу - нет
+    fn check_new_variable(&self, name: &str) -> TypeCheckResult<()> {
+        let in_block = self.scopes.last()
+            .is_some_and(|scope| scope.iter().any(|(declared, _)| declared == name));
+        if in_block || self.constants.contains_key(name) {
+            return Err(TypeCheckError::DuplicateVariable {
+                name: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+    
+    fn declare_variable(&mut self, name: &str, info: VariableInfo) {
+        let shadowed = self.variables.insert(name.to_string(), info);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.to_string(), shadowed));
+        }
+    }
+    
+    /// Ветки if и match начинаются с одного состояния переменных; после
+    /// ветвления переменная перемещена, если её переместила хоть одна ветка
+    fn merge_branch(&mut self, branch: HashMap<String, VariableInfo>) {
+        for (name, info) in branch {
+            if let (VariableState::Moved, Some(current)) = (&info.state, self.variables.get_mut(&name)) {
+                current.state = VariableState::Moved;
+            }
+        }
+    }
+    
+    /// Проверяет операторы по очереди; ошибка в одном не мешает проверить остальные
+    fn check_block(&mut self, statements: &[Statement]) {
+        // Вызов exit во вложенном блоке (ветке if, теле цикла) не делает
+        // недостижимым код после всего оператора
+        let outer_diverges = std::mem::take(&mut self.diverges);
+        let block_borrows = self.borrows.len();
+        // Оператор, после которого блок не продолжается: return, break, continue
+        let mut exit: Option<&str> = None;
+        let mut reported = false;
+        for statement in statements {
+            let reason = if self.diverges { Some("a call that never returns") } else { exit };
+            if let Some(reason) = reason.filter(|_| !reported) {
+                self.warnings.push(LintWarning {
+                    function: self.current_function.clone(),
+                    message: format!("unreachable code after {}", reason),
+                });
+                reported = true;
+            }
+            let statement_borrows = self.borrows.len();
+            if let Err(e) = self.check_statement(statement) {
+                self.report(e);
+            }
+            exit = exit.or(match statement {
+                Statement::Return { .. } => Some("return"),
+                Statement::Break { .. } => Some("break"),
+                Statement::Continue { .. } => Some("continue"),
+                _ => None,
+            });
+            // Временные заимствования оператора заканчиваются вместе с ним
+            let ended = self.borrows.split_off(statement_borrows);
+            self.borrows.extend(ended.into_iter().filter(|borrow| borrow.held));
+        }
+        // Переменные блока, державшие заимствования, вышли из области видимости
+        self.borrows.truncate(block_borrows);
+        self.diverges = outer_diverges;
+    }
+    
+    /// `borrow(x)` не уживается с `mut_borrow(x)`, а `mut_borrow(x)` - с любым другим
+    fn start_borrow(&mut self, owner: &str, mutable: bool) -> TypeCheckResult<()> {
+        for borrow in self.borrows.iter().filter(|borrow| borrow.owner == owner) {
+            if mutable {
+                return Err(TypeCheckError::AlreadyBorrowed { name: owner.to_string() });
+            }
+            if borrow.mutable {
+                return Err(TypeCheckError::AlreadyMutablyBorrowed { name: owner.to_string() });
+            }
+        }
+        self.borrows.push(ActiveBorrow { owner: owner.to_string(), mutable, held: false });
+        Ok(())
+    }
+    
+    /// Переменная, которой принадлежит заимствуемое место: p в p.position[0]
+    fn borrowed_variable(expression: &Expression) -> Option<&String> {
+        match expression {
+            Expression::Variable(name) => Some(name),
+            Expression::FieldAccess { expression, .. } | Expression::Index { expression, .. } => {
+                Self::borrowed_variable(expression)
+            }
+            _ => None,
+        }
+    }
+    
+    fn is_borrowed(&self, name: &str) -> bool {
+        self.borrows.iter().any(|borrow| borrow.owner == name)
+    }
+    
+    /// Присваивание переменной или её полю, пока она заимствована. Временные
+    /// заимствования в самом значении к моменту присваивания уже закончились
+    fn check_not_borrowed(&self, name: &str) -> TypeCheckResult<()> {
+        if self.borrows.iter().any(|borrow| borrow.held && borrow.owner == name) {
+            return Err(TypeCheckError::AssignWhileBorrowed { name: name.to_string() });
+        }
+        Ok(())
+    }
+    
+    fn check_function(&mut self, function: &Function) {
+        self.variables.clear();
+        self.borrows.clear();
+        self.scopes.clear();
+        self.current_function = function.name.clone();
+        self.current_function_return = Some(function.return_type.clone());
+        self.current_module = function.module;
+        self.in_const_fn = function.is_const();
+        
+        // Параметры объявлены в блоке тела: `let` с тем же именем - повтор
+        self.scopes.push(Vec::new());
+        for param in &function.params {
+            if let Err(e) = self.check_new_variable(&param.name) {
+                self.report(e);
+                continue;
+            }
+            
+            self.declare_variable(
+                &param.name,
+                VariableInfo {
+                    var_type: param.param_type.clone(),
+                    state: VariableState::Available,
+                    mutable: false,
+                },
+            );
+        }
+        
+        // Контракты видят только параметры, постусловия - ещё и `result`
+        for condition in function.requires() {
+            if let Err(e) = self.check_condition(condition) {
+                self.report(e);
+            }
+        }
+        if function.ensures().next().is_some() {
+            let params_scope = self.variables.clone();
+            if function.return_type != Type::Void {
+                self.variables.insert(
+                    "result".to_string(),
+                    VariableInfo {
+                        var_type: function.return_type.clone(),
+                        state: VariableState::Available,
+                        mutable: false,
+                    },
+                );
+            }
+            for condition in function.ensures() {
+                if let Err(e) = self.check_condition(condition) {
+                    self.report(e);
+                }
+            }
+            self.variables = params_scope;
+        }
+        
+        self.check_block(&function.body);
+        self.exit_scope();
+        
+        // Иначе интерпретатор вернул бы Void, а WASM - значение по умолчанию
+        let return_type = &function.return_type;
+        if *return_type != Type::Void && *return_type != Type::Never && !self.always_returns(&function.body) {
+            self.report(TypeCheckError::MissingReturn {
+                function: function.name.clone(),
+                return_type: return_type.clone(),
+            });
+        }
+    }
+    
+    /// Каждый путь через операторы заканчивается return, вызовом exit
+    /// или циклом loop без break
+    fn always_returns(&self, statements: &[Statement]) -> bool {
+        statements.iter().any(|statement| match statement {
+            Statement::Return { .. } => true,
+            Statement::Expression(expression, _) => self.always_diverges(expression),
+            Statement::Block { statements, .. } => self.always_returns(statements),
+            Statement::If { then_branch, else_branch: Some(else_branch), .. } => {
+                self.always_returns(then_branch) && self.always_returns(else_branch)
+            }
+            Statement::Loop { body, label, .. } => !visit::loop_exits(label, body).breaks,
+            _ => false,
+        })
+    }
+    
+    /// Выражение никогда не вычисляется до конца: exit или ветвление,
+    /// все ветки которого такие
+    fn always_diverges(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::FunctionCall { name, .. } => !self.variables.contains_key(name)
+                && self.functions.get(name).is_some_and(|info| info.return_type == Type::Never),
+            Expression::If { then_branch, else_branch, .. } => {
+                self.always_diverges(then_branch) && self.always_diverges(else_branch)
+            }
+            Expression::Match { arms, .. } => arms.iter().all(|arm| self.always_diverges(&arm.body)),
+            _ => false,
+        }
+    }
+    
+    fn check_loop_body(&mut self, label: &Option<String>, body: &[Statement]) {
+        self.loop_labels.push(label.clone());
+        self.check_statements(body);
+        self.loop_labels.pop();
+    }
+    
+    fn check_condition(&mut self, condition: &Expression) -> TypeCheckResult<()> {
+        let cond_type = self.check_expression(condition)?;
+        if cond_type != Type::Bool {
+            return Err(TypeCheckError::NonBooleanCondition {
+                found: cond_type,
+            });
+        }
+        Ok(())
+    }
+    
+    fn check_statement(&mut self, statement: &Statement) -> TypeCheckResult<()> {
+        match statement {
+            Statement::VariableDeclaration { name, var_type, value, mutable, .. } => {
+                self.check_new_variable(name)?;
+                
+                let borrows_before = self.borrows.len();
+                let expr_type = self.check_expression_as(value, var_type);
+                
+                // let view: Point = borrow(p); - заимствование длится, пока видна view
+                if matches!(value, Expression::Borrow { .. }) && self.borrows.len() > borrows_before {
+                    if let Some(borrow) = self.borrows.last_mut() {
+                        borrow.held = true;
+                    }
+                }
+                
+                // Переменная объявляется даже при ошибке в инициализаторе,
+                // чтобы не порождать каскад "Undefined variable"
+                self.declare_variable(
+                    name,
+                    VariableInfo {
+                        var_type: var_type.clone(),
+                        state: VariableState::Available,
+                        mutable: *mutable,
+                    },
+                );
+                let expr_type = expr_type?;
+                
+                // Разрешаем неявное приведение i32 -> f32
+                if !self.types_are_compatible(var_type, &expr_type) {
+                    return Err(TypeCheckError::TypeMismatch {
+                        expected: var_type.clone(),
+                        found: expr_type,
+                    });
+                }
+                
+                Ok(())
+            }
+
+            Statement::DestructureStruct { struct_name, bindings, value, mutable, .. } => {
+                let struct_fields = self.structs.get(struct_name)
+                    .ok_or_else(|| TypeCheckError::UndefinedStruct {
+                        name: struct_name.clone(),
+                    })?
+                    .fields.clone();
+                
+                let expr_type = self.check_expression(value);
+                
+                // Каждая привязка - обычная переменная с типом поля
+                for (field, binding) in bindings {
+                    let field_type = struct_fields.get(field)
+                        .cloned()
+                        .ok_or_else(|| TypeCheckError::UndefinedField {
+                            struct_name: struct_name.clone(),
+                            field: field.clone(),
+                        })?;
+                    self.check_field_visibility(struct_name, field)?;
+                    self.check_new_variable(binding)?;
+                    self.declare_variable(
+                        binding,
+                        VariableInfo {
+                            var_type: field_type,
+                            state: VariableState::Available,
+                            mutable: *mutable,
+                        },
+                    );
+                }
+                
+                let expr_type = expr_type?;
+                let expected = Type::Struct(struct_name.clone());
+                let same_struct = match &expr_type {
+                    Type::Ref(inner) => **inner == expected,
+                    other => *other == expected,
+                };
+                if !same_struct {
+                    return Err(TypeCheckError::TypeMismatch {
+                        expected,
+                        found: expr_type,
+                    });
+                }
+                
+                Ok(())
+            }
+
+            // В функции check_assignment (около строки 327):
+            Statement::Assignment { name, value, .. } => {
+                // Сначала получаем тип выражения
+                let expr_type = self.check_expression(value)?;
+    
+                if !self.variables.contains_key(name) && self.constants.contains_key(name) {
+                    self.check_static_access(name)?;
+                    return match self.statics.get(name) {
+                        Some(true) => self.check_assignment_type(&self.constants[name].clone(), expr_type),
+                        Some(false) => Err(TypeCheckError::AssignToImmutableStatic {
+                            name: name.clone(),
+                        }),
+                        None => Err(TypeCheckError::AssignToConstant {
+                            name: name.clone(),
+                        }),
+                    };
+                }
+                
+                // Затем получаем тип переменной
+                let var_info = self.variables.get(name)
+                    .ok_or_else(|| TypeCheckError::UndefinedVariable {
+                        name: name.clone(),
+                    })?;
+                if !var_info.mutable {
+                    return Err(TypeCheckError::AssignToImmutableVariable {
+                        name: name.clone(),
+                    });
+                }
+                let var_type = var_info.var_type.clone();
+                self.check_not_borrowed(name)?;
+                self.check_assignment_type(&var_type, expr_type)
+            }
+            
+            Statement::FieldAssignment { name, fields, value, .. } => {
+                let expr_type = self.check_expression(value)?;
+                
+                // Через ref меняется общая структура, а не сама переменная,
+                // поэтому `let mut` нужен, только если на пути нет ref
+                let mut target_type = self.check_expression(&Expression::Variable(name.clone()))?;
+                let mut through_ref = false;
+                for field_name in fields {
+                    through_ref |= matches!(target_type, Type::Ref(_));
+                    target_type = self.field_type(&target_type, field_name)?;
+                }
+                
+                let mutable = self.variables.get(name).is_some_and(|info| info.mutable);
+                if !mutable && !through_ref {
+                    return Err(TypeCheckError::AssignToImmutable {
+                        name: name.clone(),
+                    });
+                }
+                self.check_not_borrowed(name)?;
+                
+                self.check_assignment_type(&target_type, expr_type)
+            }
+            
+            Statement::IndexAssignment { name, index, value, .. } => {
+                let expr_type = self.check_expression(value)?;
+                let target_type = self.check_expression(&Expression::Index {
+                    expression: Box::new(Expression::Variable(name.clone())),
+                    index: Box::new(index.clone()),
+                })?;
+                
+                // Менять можно только массив фиксированной длины: у него своя
+                // память, а обычный массив разделяется между копиями
+                let var_type = self.variables.get(name).map(|info| info.var_type.clone());
+                if !matches!(var_type, Some(Type::FixedArray(..))) {
+                    return Err(TypeCheckError::NotIndexAssignable {
+                        found: var_type.unwrap_or(Type::Void),
+                    });
+                }
+                if !self.variables.get(name).is_some_and(|info| info.mutable) {
+                    return Err(TypeCheckError::AssignToImmutableElement {
+                        name: name.clone(),
+                    });
+                }
+                self.check_not_borrowed(name)?;
+                
+                self.check_assignment_type(&target_type, expr_type)
+            }
+            
+            Statement::Return { value, .. } => {
                 let return_type = self.current_function_return
                     .as_ref()
                     .expect("Return outside of function")
                     .clone();
                 
-                let expr_type = self.check_expression(value)?;
+                let expr_type = self.check_expression_as(value, &return_type)?;
                 
                 // Разрешаем неявное приведение типов для возвращаемых значений
                 if !self.types_are_compatible(&return_type, &expr_type) {
@@ -357,87 +1406,518 @@ impl TypeChecker {
                 Ok(())
             }
             
-            Statement::Expression(expr) => {
+            Statement::Expression(expr, _) => {
                 self.check_expression(expr)?;
                 Ok(())
             }
             
-            Statement::Block { statements } => {
-                let old_variables = self.variables.clone();
+            // Выражение проверяется там, где записан defer: переменные блока уже объявлены
+            Statement::Defer { expression, .. } => {
+                self.check_expression(expression)?;
+                Ok(())
+            }
+            
+            Statement::Block { statements, .. } => {
+                self.check_statements(statements);
+                Ok(())
+            }
+            
+            Statement::While { condition, body, label, .. } => {
+                let cond_type = self.check_expression(condition)?;
+                if cond_type != Type::Bool {
+                    return Err(TypeCheckError::NonBooleanCondition {
+                        found: cond_type,
+                    });
+                }
+                
+                self.check_loop_body(label, body);
+                
+                Ok(())
+            }
+            
+            Statement::Loop { body, label, .. } => {
+                self.check_loop_body(label, body);
                 
-                for stmt in statements {
-                    self.check_statement(stmt)?;
+                // Бесконечный цикл (игровой) допустим только в void-функции:
+                // значение из него функция не вернёт
+                let return_type = self.current_function_return.clone().unwrap_or(Type::Void);
+                let exits = visit::loop_exits(label, body);
+                if return_type != Type::Void && !exits.breaks && !exits.returns {
+                    return Err(TypeCheckError::InfiniteLoop { return_type });
                 }
                 
-                self.variables = old_variables;
                 Ok(())
             }
             
-            Statement::While { condition, body } => {
+            Statement::Break { label, .. } | Statement::Continue { label, .. } => {
+                let keyword = if matches!(statement, Statement::Break { .. }) { "break" } else { "continue" };
+                if self.loop_labels.is_empty() {
+                    return Err(TypeCheckError::LoopExitOutsideLoop {
+                        keyword: keyword.to_string(),
+                    });
+                }
+                if let Some(label) = label {
+                    if !self.loop_labels.iter().any(|known| known.as_ref() == Some(label)) {
+                        return Err(TypeCheckError::UndefinedLabel {
+                            label: label.clone(),
+                        });
+                    }
+                }
+                Ok(())
+            }
+            
+            Statement::For { variable, start, end, body, label, .. } => {
+                for bound in [start, end] {
+                    let bound_type = self.check_expression(bound)?;
+                    if bound_type != Type::I32 {
+                        return Err(TypeCheckError::TypeMismatch {
+                            expected: Type::I32,
+                            found: bound_type,
+                        });
+                    }
+                }
+                
+                if self.constants.contains_key(variable) {
+                    return Err(TypeCheckError::DuplicateVariable {
+                        name: variable.clone(),
+                    });
+                }
+                
+                // Переменная цикла видна только в теле и может перекрыть внешнюю
+                self.scopes.push(Vec::new());
+                self.declare_variable(
+                    variable,
+                    VariableInfo {
+                        var_type: Type::I32,
+                        state: VariableState::Available,
+                        mutable: false,
+                    },
+                );
+                self.check_loop_body(label, body);
+                self.exit_scope();
+                
+                Ok(())
+            }
+            
+            Statement::If { condition, then_branch, else_branch, .. } => {
                 let cond_type = self.check_expression(condition)?;
                 if cond_type != Type::Bool {
                     return Err(TypeCheckError::NonBooleanCondition {
                         found: cond_type,
                     });
                 }
-                
-                let old_variables = self.variables.clone();
-                for stmt in body {
-                    self.check_statement(stmt)?;
+                
+                let before = self.variables.clone();
+                self.check_statements(then_branch);
+                let then_variables = std::mem::replace(&mut self.variables, before);
+                
+                if let Some(else_branch) = else_branch {
+                    self.check_statements(else_branch);
+                }
+                self.merge_branch(then_variables);
+                
+                Ok(())
+            }
+        }
+    }
+
+    /// Тип поля структуры; у `ref T` поле читается через ссылку
+    fn field_type(&self, owner: &Type, field_name: &str) -> TypeCheckResult<Type> {
+        match owner {
+            Type::Struct(struct_name) => {
+                let struct_info = self.structs.get(struct_name)
+                    .ok_or_else(|| TypeCheckError::UndefinedStruct {
+                        name: struct_name.clone(),
+                    })?;
+                
+                let field_type = struct_info.fields.get(field_name)
+                    .cloned()
+                    .ok_or_else(|| TypeCheckError::UndefinedField {
+                        struct_name: struct_name.clone(),
+                        field: field_name.to_string(),
+                    })?;
+                self.check_field_visibility(struct_name, field_name)?;
+                Ok(field_type)
+            }
+            Type::Ref(inner) => self.field_type(inner, field_name),
+            _ => Err(TypeCheckError::TypeMismatch {
+                expected: Type::Struct("any".to_string()),
+                found: owner.clone(),
+            }),
+        }
+    }
+
+    /// Типы аргументов вызова; количество проверяет вызывающий
+    fn check_arguments(&mut self, params: &[Type], args: &[Expression]) -> TypeCheckResult<()> {
+        for (arg, expected_type) in args.iter().zip(params) {
+            let arg_type = self.check_expression_as(arg, expected_type)?;
+            if !self.types_are_compatible(expected_type, &arg_type) {
+                return Err(TypeCheckError::TypeMismatch {
+                    expected: expected_type.clone(),
+                    found: arg_type,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Аргументы встроенной функции с параметрами типа (map, filter);
+    /// результат - тип возврата с выведенными параметрами
+    fn check_generic_arguments(&mut self, name: &str, args: &[Expression]) -> TypeCheckResult<Type> {
+        let builtin = stdlib::lookup(name).expect("generic builtin");
+        let arg_types = args.iter()
+            .map(|arg| self.check_expression(arg))
+            .collect::<TypeCheckResult<Vec<_>>>()?;
+        let bindings = builtin.bind_type_params(&arg_types);
+        for ((_, param), arg_type) in builtin.params.iter().zip(arg_types) {
+            let expected_type = stdlib::substitute(param, &bindings);
+            if !self.types_are_compatible(&expected_type, &arg_type) {
+                return Err(TypeCheckError::TypeMismatch {
+                    expected: expected_type,
+                    found: arg_type,
+                });
+            }
+        }
+        Ok(stdlib::substitute(&builtin.return_type, &bindings))
+    }
+
+    /// Аргументы перегруженной числовой функции; результат i32, если все они i32
+    fn check_numeric_arguments(&mut self, args: &[Expression]) -> TypeCheckResult<Type> {
+        let mut result_type = Type::I32;
+        for arg in args {
+            match self.check_expression(arg)? {
+                Type::I32 => {}
+                Type::F32 => result_type = Type::F32,
+                found => return Err(TypeCheckError::TypeMismatch {
+                    expected: Type::F32,
+                    found,
+                }),
+            }
+        }
+        Ok(result_type)
+    }
+
+    fn check_assignment_type(&self, target_type: &Type, value_type: Type) -> TypeCheckResult<()> {
+        if !self.types_are_compatible(target_type, &value_type) {
+            return Err(TypeCheckError::TypeMismatch {
+                expected: target_type.clone(),
+                found: value_type,
+            });
+        }
+        Ok(())
+    }
+
+    fn types_are_compatible(&self, expected: &Type, actual: &Type) -> bool {
+        match (expected, actual) {
+            // Тот же тип - всегда совместим
+            (a, b) if a == b => true,
+            
+            // До значения never дело не дойдёт
+            (_, Type::Never) => true,
+            
+            // Литерал [a, b, c] - массив [T; 3]; на месте [T] длина не важна.
+            // Массив передаётся как срез целиком. Элементы не преобразуются,
+            // пустой литерал [] подходит к массиву любого типа
+            (Type::Array(expected) | Type::Slice(expected), Type::Array(element) | Type::FixedArray(element, _)) => {
+                self.array_element_fits(expected, element)
+            }
+            (Type::FixedArray(expected, length), Type::FixedArray(element, actual_length)) if length == actual_length => {
+                self.array_element_fits(expected, element)
+            }
+            
+            // Значение типа, реализующего трейт, подходит туда, где ожидается трейт
+            (Type::Struct(expected), Type::Struct(actual) | Type::Enum(actual)) if self.implements(actual, expected) => true,
+            
+            // В аннотациях перечисление записано как имя, парсер не отличает его от структуры
+            (Type::Struct(a), Type::Enum(b)) | (Type::Enum(a), Type::Struct(b)) => a == b,
+            
+            // None, Ok(x) и Err(e) известны лишь частично: void подходит к любому параметру
+            (Type::Option(expected), Type::Option(actual)) => self.type_arguments_compatible(expected, actual),
+            (Type::Result(expected_ok, expected_err), Type::Result(actual_ok, actual_err)) => {
+                self.type_arguments_compatible(expected_ok, actual_ok)
+                    && self.type_arguments_compatible(expected_err, actual_err)
+            }
+            
+            // Функции совместимы, если совместимы параметры и результат
+            (Type::Function(expected_params, expected_return), Type::Function(actual_params, actual_return)) => {
+                expected_params.len() == actual_params.len()
+                    && expected_params.iter().zip(actual_params)
+                        .all(|(expected, actual)| self.types_are_compatible(actual, expected))
+                    && self.types_are_compatible(expected_return, actual_return)
+            }
+            
+            // Числовые преобразования
+            (Type::F32, Type::I32) => true,  // i32 -> f32
+            (Type::F64, Type::I32) => true,  // i32 -> f64
+            (Type::F64, Type::F32) => true,  // f32 -> f64
+            (Type::I64, Type::I32) => true,  // i32 -> i64
+            
+            // Для арифметических операций
+            (Type::F32, Type::F32) => true,
+            (Type::I32, Type::I32) => true,
+            (Type::F64, Type::F64) => true,
+            (Type::I64, Type::I64) => true,
+            
+            // Во всех остальных случаях - не совместимы
+            _ => false,
+        }
+    }
+
+    /// Элемент массива подходит без преобразования чисел: вложенный [T; N]
+    /// подходит на место [T], реализующий трейт тип - на место трейта
+    fn array_element_fits(&self, expected: &Type, element: &Type) -> bool {
+        expected == element
+            || *element == Type::Void
+            || (matches!(expected, Type::Array(_) | Type::FixedArray(..)) || self.is_trait(expected))
+                && self.types_are_compatible(expected, element)
+    }
+
+    /// Индекс-литерал; -1 парсер записывает как 0 - 1
+    fn literal_index(index: &Expression) -> Option<i32> {
+        match index {
+            Expression::IntegerLiteral(value) => i32::try_from(*value).ok(),
+            Expression::BinaryExpression { left, operator: BinaryOperator::Subtract, right } => {
+                match (&**left, &**right) {
+                    (Expression::IntegerLiteral(0), Expression::IntegerLiteral(value)) => i32::try_from(-value).ok(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Тот же тип с [T] на месте всех [T; N]
+    fn unsized_array(ty: &Type) -> Type {
+        match ty {
+            Type::Array(element) | Type::FixedArray(element, _) => Type::Array(Box::new(Self::unsized_array(element))),
+            other => other.clone(),
+        }
+    }
+
+    fn check_lambda(&mut self, params: &[Parameter], return_type: &Type, body: &[Statement]) -> TypeCheckResult<Type> {
+        // Замыкание видит окружение, но его собственные переменные наружу не выходят
+        let outer_variables = self.variables.clone();
+        let outer_return = self.current_function_return.replace(return_type.clone());
+        let outer_labels = std::mem::take(&mut self.loop_labels);
+        
+        let mut result = Ok(());
+        let mut seen = HashSet::new();
+        for param in params {
+            if !seen.insert(&param.name) || self.constants.contains_key(&param.name) {
+                result = Err(TypeCheckError::DuplicateVariable {
+                    name: param.name.clone(),
+                });
+                break;
+            }
+            self.variables.insert(
+                param.name.clone(),
+                VariableInfo {
+                    var_type: param.param_type.clone(),
+                    state: VariableState::Available,
+                    mutable: false,
+                },
+            );
+        }
+        if result.is_ok() {
+            self.check_statements(body);
+        }
+        
+        self.variables = outer_variables;
+        self.current_function_return = outer_return;
+        self.loop_labels = outer_labels;
+        result?;
+        
+        Ok(Type::Function(
+            params.iter().map(|param| param.param_type.clone()).collect(),
+            Box::new(return_type.clone()),
+        ))
+    }
+
+    fn type_arguments_compatible(&self, expected: &Type, actual: &Type) -> bool {
+        *expected == Type::Void || *actual == Type::Void || self.types_are_compatible(expected, actual)
+    }
+
+    /// Типы полей варианта встроенного Option/Result; None - перечисление не встроенное.
+    /// `subject` - тип значения, если он известен (в образцах match)
+    fn builtin_variant_fields(enum_name: &str, variant: &str, subject: Option<&Type>) -> Option<TypeCheckResult<Vec<Type>>> {
+        let unknown = Type::Void;
+        let fields = match (enum_name, variant, subject) {
+            ("Option", "Some", Some(Type::Option(inner))) => vec![(**inner).clone()],
+            ("Result", "Ok", Some(Type::Result(ok, _))) => vec![(**ok).clone()],
+            ("Result", "Err", Some(Type::Result(_, err))) => vec![(**err).clone()],
+            ("Option", "Some", _) | ("Result", "Ok" | "Err", _) => vec![unknown],
+            ("Option", "None", _) => Vec::new(),
+            ("Option" | "Result", _, _) => return Some(Err(TypeCheckError::UndefinedVariant {
+                enum_name: enum_name.to_string(),
+                variant: variant.to_string(),
+            })),
+            _ => return None,
+        };
+        Some(Ok(fields))
+    }
+
+    fn check_string_operation(&self, operator: &BinaryOperator, left: Type, right: Type) -> TypeCheckResult<Type> {
+        let is_scalar = |ty: &Type| matches!(ty, Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::Bool);
+        match operator {
+            BinaryOperator::Add => {
+                let other = if left == Type::String { right } else { left };
+                if other == Type::String || is_scalar(&other) {
+                    Ok(Type::String)
+                } else {
+                    Err(TypeCheckError::InvalidConcatenation { found: other })
+                }
+            }
+            BinaryOperator::Eq | BinaryOperator::Neq => {
+                if left == right {
+                    Ok(Type::Bool)
+                } else {
+                    Err(TypeCheckError::TypeMismatch {
+                        expected: left,
+                        found: right,
+                    })
                 }
-                self.variables = old_variables;
-                
-                Ok(())
             }
-            
-            Statement::If { condition, then_branch, else_branch } => {
-                let cond_type = self.check_expression(condition)?;
-                if cond_type != Type::Bool {
-                    return Err(TypeCheckError::NonBooleanCondition {
-                        found: cond_type,
+            _ => Err(TypeCheckError::UnsupportedOperator {
+                operator: operator.clone(),
+                found: Type::String,
+            }),
+        }
+    }
+    
+    /// Имя из аннотации типа, которое оказалось перечислением
+    fn enum_type(&self, ty: Type) -> Type {
+        match ty {
+            Type::Struct(name) if self.enums.contains_key(&name) => Type::Enum(name),
+            other => other,
+        }
+    }
+    
+    fn lookup_variant(&self, enum_name: &str, variant: &str) -> TypeCheckResult<&Variant> {
+        let enum_def = self.enums.get(enum_name)
+            .ok_or_else(|| TypeCheckError::UndefinedStruct {
+                name: enum_name.to_string(),
+            })?;
+        enum_def.variant(variant)
+            .ok_or_else(|| TypeCheckError::UndefinedVariant {
+                enum_name: enum_name.to_string(),
+                variant: variant.to_string(),
+            })
+    }
+    
+    /// Проверяет образец и возвращает переменные, которые он связывает
+    fn check_pattern(&self, pattern: &Pattern, subject_type: &Type) -> TypeCheckResult<Vec<(String, Type)>> {
+        let mut bound = Vec::new();
+        let pattern_type = match pattern {
+            Pattern::Wildcard => return Ok(bound),
+            Pattern::Integer(_) => Type::I32,
+            Pattern::Bool(_) => Type::Bool,
+            Pattern::Variant { enum_name, variant, bindings } => {
+                let builtin = Self::builtin_variant_fields(enum_name, variant, Some(subject_type));
+                let fields = match &builtin {
+                    Some(fields) => fields.as_ref().map_err(|_| TypeCheckError::UndefinedVariant {
+                        enum_name: enum_name.clone(),
+                        variant: variant.clone(),
+                    })?,
+                    None => &self.lookup_variant(enum_name, variant)?.fields,
+                };
+                if bindings.len() != fields.len() {
+                    return Err(TypeCheckError::VariantFieldCountMismatch {
+                        enum_name: enum_name.clone(),
+                        variant: variant.clone(),
+                        expected: fields.len(),
+                        found: bindings.len(),
                     });
                 }
-                
-                let old_variables = self.variables.clone();
-                for stmt in then_branch {
-                    self.check_statement(stmt)?;
-                }
-                self.variables = old_variables.clone();
-                
-                if let Some(else_branch) = else_branch {
-                    for stmt in else_branch {
-                        self.check_statement(stmt)?;
+                for (name, field_type) in bindings.iter().zip(fields) {
+                    // Константы не перекрываются: оптимизатор подставляет их по имени
+                    if self.constants.contains_key(name) {
+                        return Err(TypeCheckError::DuplicateVariable {
+                            name: name.clone(),
+                        });
+                    }
+                    if name != "_" {
+                        bound.push((name.clone(), field_type.clone()));
                     }
                 }
-                self.variables = old_variables;
-                
-                Ok(())
+                match (enum_name.as_str(), subject_type) {
+                    ("Option", Type::Option(_)) | ("Result", Type::Result(..)) => return Ok(bound),
+                    _ if builtin.is_some() => return Err(TypeCheckError::TypeMismatch {
+                        expected: subject_type.clone(),
+                        found: Type::Struct(enum_name.clone()),
+                    }),
+                    _ => Type::Enum(enum_name.clone()),
+                }
             }
+        };
+        if pattern_type != *subject_type {
+            return Err(TypeCheckError::TypeMismatch {
+                expected: subject_type.clone(),
+                found: pattern_type,
+            });
         }
+        Ok(bound)
     }
-
-    fn types_are_compatible(&self, expected: &Type, actual: &Type) -> bool {
-        match (expected, actual) {
-            // Тот же тип - всегда совместим
-            (a, b) if a == b => true,
-            
-            // Числовые преобразования
-            (Type::F32, Type::I32) => true,  // i32 -> f32
-            (Type::F64, Type::I32) => true,  // i32 -> f64
-            (Type::F64, Type::F32) => true,  // f32 -> f64
-            (Type::I64, Type::I32) => true,  // i32 -> i64
-            
-            // Для арифметических операций
-            (Type::F32, Type::F32) => true,
-            (Type::I32, Type::I32) => true,
-            (Type::F64, Type::F64) => true,
-            (Type::I64, Type::I64) => true,
-            
-            // Во всех остальных случаях - не совместимы
-            _ => false,
+    
+    /// Перечисление и bool должны быть покрыты полностью, остальное - через `_`
+    fn check_exhaustive(&self, arms: &[MatchArm], subject_type: &Type) -> TypeCheckResult<()> {
+        if arms.iter().any(|arm| arm.pattern == Pattern::Wildcard) {
+            return Ok(());
+        }
+        let missing: Vec<String> = match subject_type {
+            Type::Enum(enum_name) => self.enums[enum_name].variants.iter()
+                .filter(|variant| !arms.iter().any(|arm| matches!(
+                    &arm.pattern,
+                    Pattern::Variant { variant: v, .. } if *v == variant.name
+                )))
+                .map(|variant| format!("{}::{}", enum_name, variant.name))
+                .collect(),
+            Type::Option(_) | Type::Result(..) => {
+                let variants = if matches!(subject_type, Type::Option(_)) { ["Some", "None"] } else { ["Ok", "Err"] };
+                variants.into_iter()
+                    .filter(|variant| !arms.iter().any(|arm| matches!(
+                        &arm.pattern,
+                        Pattern::Variant { variant: v, .. } if v == variant
+                    )))
+                    .map(str::to_string)
+                    .collect()
+            }
+            Type::Bool => [true, false].into_iter()
+                .filter(|value| !arms.iter().any(|arm| arm.pattern == Pattern::Bool(*value)))
+                .map(|value| value.to_string())
+                .collect(),
+            _ => vec!["_".to_string()],
+        };
+        if missing.is_empty() {
+            return Ok(());
         }
+        Err(TypeCheckError::NonExhaustiveMatch {
+            found: subject_type.clone(),
+            missing: missing.join(", "),
+        })
     }
-
+    
+    /// Ветка недостижима, если раньше уже был тот же образец или предыдущие
+    /// ветки покрывают все значения (в том числе через `_`): после удаления
+    /// варианта или добавления `_` такие ветки обычно забывают убрать
+    fn check_unreachable_arms(&mut self, arms: &[MatchArm], subject_type: &Type) {
+        for (i, arm) in arms.iter().enumerate().skip(1) {
+            let earlier = &arms[..i];
+            let repeated = earlier.iter().any(|other| match (&other.pattern, &arm.pattern) {
+                (
+                    Pattern::Variant { enum_name, variant, .. },
+                    Pattern::Variant { enum_name: other_enum, variant: other_variant, .. },
+                ) => enum_name == other_enum && variant == other_variant,
+                (earlier, pattern) => earlier == pattern,
+            });
+            if repeated || self.check_exhaustive(earlier, subject_type).is_ok() {
+                self.warnings.push(LintWarning {
+                    function: self.current_function.clone(),
+                    message: format!("unreachable match arm `{}`: earlier arms already cover it", arm.pattern),
+                });
+            }
+        }
+    }
+    
     fn get_common_numeric_type(&self, left: &Type, right: &Type) -> Option<Type> {
         match (left, right) {
             // Если типы одинаковые - возвращаем тот же тип
@@ -454,14 +1934,70 @@ impl TypeChecker {
         }
     }
     
+    /// Выражение там, где тип известен заранее. Числовой литерал без суффикса
+    /// принимает ожидаемый тип: в `let x: i64 = 5;` литерал - i64, а не i32,
+    /// расширяемый при присваивании
+    fn check_expression_as(&mut self, expression: &Expression, expected: &Type) -> TypeCheckResult<Type> {
+        if Self::adopts_type(expression, expected) {
+            self.annotate_literal(expression, expected);
+            return Ok(expected.clone());
+        }
+        self.check_expression(expression)
+    }
+
+    /// Целый литерал подходит к любому числовому типу, дробный - к f32 и f64.
+    /// `-5` разобран как `0 - 5` и тоже считается литералом
+    fn adopts_type(expression: &Expression, expected: &Type) -> bool {
+        match (expression, expected) {
+            (Expression::IntegerLiteral(_), Type::I64 | Type::F32 | Type::F64) => true,
+            (Expression::FloatLiteral(_), Type::F64) => true,
+            (Expression::BinaryExpression { left, operator: BinaryOperator::Subtract, right }, _) => {
+                **left == Expression::IntegerLiteral(0) && Self::adopts_type(right, expected)
+            }
+            _ => false,
+        }
+    }
+
+    fn annotate_literal(&mut self, expression: &Expression, ty: &Type) {
+        self.expression_types.insert(expression, ty.clone());
+        if let Expression::BinaryExpression { left, right, .. } = expression {
+            self.annotate_literal(left, ty);
+            self.annotate_literal(right, ty);
+        }
+    }
+
     fn check_expression(&mut self, expression: &Expression) -> TypeCheckResult<Type> {
+        let ty = self.check_expression_kind(expression)?;
+        self.expression_types.insert(expression, ty.clone());
+        Ok(ty)
+    }
+
+    fn check_expression_kind(&mut self, expression: &Expression) -> TypeCheckResult<Type> {
         match expression {
+            Expression::IntegerLiteral(value) if i32::try_from(*value).is_err() => {
+                Err(TypeCheckError::IntegerLiteralOutOfRange { value: *value })
+            }
             Expression::IntegerLiteral(_) => Ok(Type::I32),
             Expression::FloatLiteral(_) => Ok(Type::F32),
             Expression::StringLiteral(_) => Ok(Type::String),
             Expression::BoolLiteral(_) => Ok(Type::Bool),
             
             Expression::Variable(name) => {
+                if !self.variables.contains_key(name) {
+                    if let Some(const_type) = self.constants.get(name) {
+                        self.check_static_access(name)?;
+                        return Ok(const_type.clone());
+                    }
+                }
+                // Имя пользовательской функции - значение-функция
+                if !self.variables.contains_key(name) && !Self::builtin_summary().contains_key(name) {
+                    if let Some(function_info) = self.functions.get(name) {
+                        return Ok(Type::Function(
+                            function_info.params.clone(),
+                            Box::new(function_info.return_type.clone()),
+                        ));
+                    }
+                }
                 let var_info = self.variables.get(name)
                     .ok_or_else(|| TypeCheckError::UndefinedVariable {
                         name: name.clone(),
@@ -480,12 +2016,37 @@ impl TypeChecker {
                 let left_type = self.check_expression(left)?;
                 let right_type = self.check_expression(right)?;
                 
+                // Перечисления сравниваются только на равенство
+                let left_enum = self.enum_type(left_type.clone());
+                let right_enum = self.enum_type(right_type.clone());
+                if matches!(left_enum, Type::Enum(_)) || matches!(right_enum, Type::Enum(_)) {
+                    if left_enum == right_enum && matches!(operator, BinaryOperator::Eq | BinaryOperator::Neq) {
+                        if let Type::Enum(name) = &left_enum {
+                            if self.enums[name].has_payload() {
+                                return Err(TypeCheckError::NotComparable { found: left_enum });
+                            }
+                        }
+                        return Ok(Type::Bool);
+                    }
+                    return Err(TypeCheckError::TypeMismatch {
+                        expected: left_type,
+                        found: right_type,
+                    });
+                }
+                
+                // Строки: `+` склеивает (числа и bool преобразуются в строку),
+                // `==` и `!=` сравнивают содержимое
+                if left_type == Type::String || right_type == Type::String {
+                    return self.check_string_operation(operator, left_type, right_type);
+                }
+                
                 // Проверяем совместимость типов для операторов
                 match operator {
                     BinaryOperator::Add |
                     BinaryOperator::Subtract |
                     BinaryOperator::Multiply |
-                    BinaryOperator::Divide => {
+                    BinaryOperator::Divide |
+                    BinaryOperator::Mod => {
                         // Для арифметических операций находим общий тип
                         if let Some(common_type) = self.get_common_numeric_type(&left_type, &right_type) {
                             Ok(common_type)
@@ -514,6 +2075,23 @@ impl TypeChecker {
                         }
                     }
                     
+                    // Только целые; сдвиг на число бит >= ширины берётся по модулю ширины
+                    BinaryOperator::BitAnd |
+                    BinaryOperator::BitOr |
+                    BinaryOperator::BitXor |
+                    BinaryOperator::Shl |
+                    BinaryOperator::Shr => {
+                        for operand in [&left_type, &right_type] {
+                            if !matches!(operand, Type::I32 | Type::I64) {
+                                return Err(TypeCheckError::UnsupportedOperator {
+                                    operator: operator.clone(),
+                                    found: operand.clone(),
+                                });
+                            }
+                        }
+                        Ok(self.get_common_numeric_type(&left_type, &right_type).unwrap_or(left_type))
+                    }
+                    
                     BinaryOperator::And | BinaryOperator::Or => {
                         if left_type != Type::Bool || right_type != Type::Bool {
                             return Err(TypeCheckError::TypeMismatch {
@@ -527,33 +2105,110 @@ impl TypeChecker {
             }
             
             Expression::FunctionCall { name, args } => {
+                // Локальная переменная-функция закрывает одноимённую функцию
+                if let Some(var_info) = self.variables.get(name) {
+                    let (params, return_type) = match &var_info.var_type {
+                        Type::Function(params, return_type) => (params.clone(), (**return_type).clone()),
+                        other => return Err(TypeCheckError::NotCallable {
+                            name: name.clone(),
+                            found: other.clone(),
+                        }),
+                    };
+                    if let VariableState::Moved = var_info.state {
+                        return Err(TypeCheckError::UseAfterMove {
+                            name: name.clone(),
+                        });
+                    }
+                    if args.len() != params.len() {
+                        return Err(TypeCheckError::ParameterCountMismatch {
+                            expected: params.len(),
+                            found: args.len(),
+                        });
+                    }
+                    // Что за функция в переменной, при компиляции неизвестно
+                    self.check_const_call(name)?;
+                    self.check_arguments(&params, args)?;
+                    return Ok(return_type);
+                }
+                
                 let function_info = self.functions.get(name)
                     .ok_or_else(|| TypeCheckError::UndefinedFunction {
                         name: name.clone(),
                     })?
                     .clone();
+                self.check_const_call(name)?;
                 
-                if args.len() != function_info.params.len() {
+                let arity_ok = if function_info.variadic {
+                    args.len() >= function_info.params.len()
+                } else {
+                    args.len() == function_info.params.len()
+                };
+                if !arity_ok {
                     return Err(TypeCheckError::ParameterCountMismatch {
                         expected: function_info.params.len(),
                         found: args.len(),
                     });
                 }
                 
-                for (arg, expected_type) in args.iter().zip(&function_info.params) {
+                // Дополнительные аргументы вариадической функции принимаются любого типа
+                for arg in args.iter().skip(function_info.params.len()) {
                     let arg_type = self.check_expression(arg)?;
-                    if !self.types_are_compatible(expected_type, &arg_type) {
+                    if arg_type == Type::Void {
                         return Err(TypeCheckError::TypeMismatch {
-                            expected: expected_type.clone(),
+                            expected: Type::String,
                             found: arg_type,
                         });
                     }
                 }
                 
+                if function_info.numeric {
+                    return self.check_numeric_arguments(args);
+                }
+                if function_info.generic {
+                    return self.check_generic_arguments(name, args);
+                }
+                self.check_arguments(&function_info.params, args)?;
+                
+                // spawn("name") и start_coroutine("name") запускают
+                // пользовательскую функцию без параметров
+                if name == "spawn" || name == "start_coroutine" {
+                    if let Some(Expression::StringLiteral(target)) = args.first() {
+                        let target_info = self.functions.get(target)
+                            .ok_or_else(|| TypeCheckError::UndefinedFunction {
+                                name: target.clone(),
+                            })?;
+                        if !target_info.params.is_empty() {
+                            return Err(TypeCheckError::ParameterCountMismatch {
+                                expected: 0,
+                                found: target_info.params.len(),
+                            });
+                        }
+                        // join возвращает i32: результат потока или 0 для void
+                        let returns = &target_info.return_type;
+                        if name == "spawn" && !matches!(returns, Type::I32 | Type::Void | Type::Never) {
+                            return Err(TypeCheckError::SpawnReturnType {
+                                name: target.clone(),
+                                found: returns.clone(),
+                            });
+                        }
+                    }
+                }
+                
+                // Число `{}` в литеральном шаблоне известно при компиляции
+                if name == "format" {
+                    if let Some(Expression::StringLiteral(template)) = args.first() {
+                        strings::format(template, &vec![String::new(); args.len() - 1])
+                            .map_err(|message| TypeCheckError::InvalidFormat { message })?;
+                    }
+                }
+                
+                if function_info.return_type == Type::Never {
+                    self.diverges = true;
+                }
                 Ok(function_info.return_type.clone())
             }
             
-            Expression::StructInitialization { struct_name, fields } => {
+            Expression::StructInitialization { struct_name, fields, base } => {
                 let struct_info = self.structs.get(struct_name)
                     .ok_or_else(|| TypeCheckError::UndefinedStruct {
                         name: struct_name.clone(),
@@ -562,42 +2217,86 @@ impl TypeChecker {
                 // Создаем копию информации о структуре для использования в цикле
                 let struct_fields = struct_info.fields.clone();
                 
+                // Без `..base` поле без значения по умолчанию нужно указать
+                if base.is_none() {
+                    let missing = struct_info.required.iter()
+                        .find(|required| !fields.iter().any(|(name, _)| name == *required));
+                    if let Some(field) = missing {
+                        return Err(TypeCheckError::MissingField {
+                            struct_name: struct_name.clone(),
+                            field: field.clone(),
+                        });
+                    }
+                }
+                
                 // Проверяем, что все поля присутствуют и типы совпадают
                 for (field_name, field_expr) in fields {
                     let expected_type = struct_fields.get(field_name)
                         .ok_or_else(|| TypeCheckError::UndefinedField {
                             struct_name: struct_name.clone(),
                             field: field_name.clone(),
-                        })?;
+                        })?
+                        .clone();
+                    self.check_field_visibility(struct_name, field_name)?;
                     
-                    let actual_type = self.check_expression(field_expr)?;
-                    if !self.types_are_compatible(expected_type, &actual_type) {
+                    let actual_type = self.check_expression_as(field_expr, &expected_type)?;
+                    if !self.types_are_compatible(&expected_type, &actual_type) {
                         return Err(TypeCheckError::TypeMismatch {
-                            expected: expected_type.clone(),
+                            expected: expected_type,
                             found: actual_type,
                         });
                     }
                 }
                 
+                // Недостающие поля берутся из значения той же структуры
+                if let Some(base) = base {
+                    let base_type = self.check_expression(base)?;
+                    let expected = Type::Struct(struct_name.clone());
+                    let same_struct = match &base_type {
+                        Type::Ref(inner) => **inner == expected,
+                        other => *other == expected,
+                    };
+                    if !same_struct {
+                        return Err(TypeCheckError::TypeMismatch {
+                            expected,
+                            found: base_type,
+                        });
+                    }
+                }
+                
                 Ok(Type::Struct(struct_name.clone()))
             }
             
             Expression::FieldAccess { expression, field_name } => {
                 let expr_type = self.check_expression(expression)?;
+                self.field_type(&expr_type, field_name)
+            }
+            
+            Expression::Move { expression } => {
+                let expr_type = self.check_expression(expression)?;
                 
-                if let Type::Struct(struct_name) = expr_type {
-                    let struct_info = self.structs.get(&struct_name)
-                        .ok_or_else(|| TypeCheckError::UndefinedStruct {
-                            name: struct_name.clone(),
-                        })?;
-                    
-                    let field_type = struct_info.fields.get(field_name)
-                        .ok_or_else(|| TypeCheckError::UndefinedField {
-                            struct_name: struct_name.clone(),
-                            field: field_name.clone(),
-                        })?;
-                    
-                    Ok(field_type.clone())
+                // Ссылки - общие дескрипторы: move() копирует дескриптор,
+                // а исходная переменная остаётся доступной
+                if let Expression::Variable(name) = expression.as_ref() {
+                    if self.is_borrowed(name) {
+                        return Err(TypeCheckError::MoveWhileBorrowed { name: name.clone() });
+                    }
+                    if let Some(var_info) = self.variables.get_mut(name) {
+                        if !matches!(var_info.var_type, Type::Ref(_)) {
+                            var_info.state = VariableState::Moved;
+                        }
+                    }
+                }
+                
+                Ok(expr_type)
+            }
+            
+            Expression::Ref { expression } => {
+                let expr_type = self.check_expression(expression)?;
+                
+                // В куче размещаются только структуры
+                if let Type::Struct(_) = expr_type {
+                    Ok(Type::Ref(Box::new(expr_type)))
                 } else {
                     Err(TypeCheckError::TypeMismatch {
                         expected: Type::Struct("any".to_string()),
@@ -606,31 +2305,303 @@ impl TypeChecker {
                 }
             }
             
-            Expression::Move { expression } => {
+            Expression::ArrayLiteral { elements } => {
+                // Тип массива задаёт первый элемент, остальные должны совпадать;
+                // в [sprite as Drawable, enemy] остальные реализуют трейт
+                let mut element_type = Type::Void;
+                for (position, element) in elements.iter().enumerate() {
+                    let actual_type = self.check_expression(element)?;
+                    if position == 0 {
+                        element_type = actual_type;
+                    } else if actual_type != element_type && Self::unsized_array(&actual_type) == Self::unsized_array(&element_type) {
+                        // [[1, 2], [3]] - вложенные массивы разной длины, это [[i32]]
+                        element_type = Self::unsized_array(&element_type);
+                    } else if actual_type != element_type
+                        && !(self.is_trait(&element_type) && self.types_are_compatible(&element_type, &actual_type))
+                    {
+                        return Err(TypeCheckError::TypeMismatch {
+                            expected: element_type,
+                            found: actual_type,
+                        });
+                    }
+                }
+                Ok(Type::FixedArray(Box::new(element_type), elements.len()))
+            }
+            
+            Expression::ArrayRepeat { value, count } => {
+                let element_type = self.check_expression(value)?;
+                Ok(Type::FixedArray(Box::new(element_type), *count))
+            }
+            
+            Expression::Index { expression, index } => {
                 let expr_type = self.check_expression(expression)?;
-                
-                if let Expression::Variable(name) = expression.as_ref() {
-                    if let Some(var_info) = self.variables.get_mut(name) {
-                        var_info.state = VariableState::Moved;
+                let index_type = self.check_expression(index)?;
+                if index_type != Type::I32 {
+                    return Err(TypeCheckError::TypeMismatch {
+                        expected: Type::I32,
+                        found: index_type,
+                    });
+                }
+                match expr_type {
+                    // Длина известна - индекс-литерал проверяется сразу
+                    Type::FixedArray(element, length) => match Self::literal_index(index) {
+                        Some(index) if index < 0 || index as usize >= length => {
+                            Err(TypeCheckError::IndexOutOfBounds { index, length })
+                        }
+                        _ => Ok(*element),
+                    },
+                    Type::Array(element) | Type::Slice(element) => Ok(*element),
+                    found => Err(TypeCheckError::NotIndexable { found }),
+                }
+            }
+            
+            Expression::Slice { expression, start, end } => {
+                let expr_type = self.check_expression(expression)?;
+                for bound in [start, end].into_iter().flatten() {
+                    let bound_type = self.check_expression(bound)?;
+                    if bound_type != Type::I32 {
+                        return Err(TypeCheckError::TypeMismatch {
+                            expected: Type::I32,
+                            found: bound_type,
+                        });
+                    }
+                }
+                match expr_type {
+                    Type::Array(element) | Type::Slice(element) | Type::FixedArray(element, _) => Ok(Type::Slice(element)),
+                    found => Err(TypeCheckError::NotSliceable { found }),
+                }
+            }
+            
+            // values.len() - длина массива или среза
+            Expression::MethodCall { receiver, method, args } if method == "len" && matches!(
+                self.check_expression(receiver),
+                Ok(Type::Array(_) | Type::Slice(_) | Type::FixedArray(..))
+            ) => {
+                if !args.is_empty() {
+                    return Err(TypeCheckError::ParameterCountMismatch {
+                        expected: 0,
+                        found: args.len(),
+                    });
+                }
+                Ok(Type::I32)
+            }
+            
+            // Point::new(..) - функция из impl-блока без self
+            Expression::EnumVariant { enum_name, variant, args } if self.functions.contains_key(&method_name(enum_name, variant)) => {
+                let function_info = self.functions[&method_name(enum_name, variant)].clone();
+                if args.len() != function_info.params.len() {
+                    return Err(TypeCheckError::ParameterCountMismatch {
+                        expected: function_info.params.len(),
+                        found: args.len(),
+                    });
+                }
+                self.check_arguments(&function_info.params, args)?;
+                Ok(function_info.return_type)
+            }
+            
+            Expression::MethodCall { receiver, method, args } => {
+                let receiver_type = self.check_expression(receiver)?;
+                let receiver_type = self.enum_type(receiver_type);
+                let type_name = match &receiver_type {
+                    Type::Struct(name) | Type::Enum(name) => name,
+                    Type::Ref(inner) => match inner.as_ref() {
+                        Type::Struct(name) => name,
+                        _ => return Err(TypeCheckError::UndefinedMethod { found: receiver_type.clone(), method: method.clone() }),
+                    },
+                    _ => return Err(TypeCheckError::UndefinedMethod { found: receiver_type.clone(), method: method.clone() }),
+                };
+                // Метод трейта: реализация выбирается по значению при выполнении
+                if let Some(trait_def) = self.traits.get(type_name) {
+                    let method_def = trait_def.method(method)
+                        .cloned()
+                        .ok_or_else(|| TypeCheckError::UndefinedMethod {
+                            found: receiver_type.clone(),
+                            method: method.clone(),
+                        })?;
+                    let params: Vec<Type> = method_def.params[1..].iter().map(|param| param.param_type.clone()).collect();
+                    if args.len() != params.len() {
+                        return Err(TypeCheckError::ParameterCountMismatch {
+                            expected: params.len(),
+                            found: args.len(),
+                        });
+                    }
+                    self.check_arguments(&params, args)?;
+                    return Ok(method_def.return_type);
+                }
+                let name = method_name(type_name, method);
+                let function_info = self.functions.get(&name)
+                    .cloned()
+                    .ok_or_else(|| TypeCheckError::UndefinedMethod {
+                        found: receiver_type.clone(),
+                        method: method.clone(),
+                    })?;
+                if !self.methods.contains(&name) {
+                    return Err(TypeCheckError::NotAMethod { name });
+                }
+                self.check_const_call(&name)?;
+                // Первый параметр - сам получатель
+                let params = &function_info.params[1..];
+                if args.len() != params.len() {
+                    return Err(TypeCheckError::ParameterCountMismatch {
+                        expected: params.len(),
+                        found: args.len(),
+                    });
+                }
+                self.check_arguments(params, args)?;
+                Ok(function_info.return_type)
+            }
+            
+            Expression::EnumVariant { enum_name, variant, args } if enum_name == "Option" || enum_name == "Result" => {
+                let fields = Self::builtin_variant_fields(enum_name, variant, None)
+                    .expect("builtin enum")?;
+                if args.len() != fields.len() {
+                    return Err(TypeCheckError::VariantFieldCountMismatch {
+                        enum_name: enum_name.clone(),
+                        variant: variant.clone(),
+                        expected: fields.len(),
+                        found: args.len(),
+                    });
+                }
+                let payload = match args.first() {
+                    Some(arg) => self.check_expression(arg)?,
+                    None => Type::Void,
+                };
+                Ok(match variant.as_str() {
+                    "Ok" => Type::Result(Box::new(payload), Box::new(Type::Void)),
+                    "Err" => Type::Result(Box::new(Type::Void), Box::new(payload)),
+                    _ => Type::Option(Box::new(payload)),
+                })
+            }
+            
+            Expression::Try { expression } => {
+                let found = self.check_expression(expression)?;
+                let return_type = self.current_function_return.clone().unwrap_or(Type::Void);
+                match (&found, &return_type) {
+                    (Type::Option(inner), Type::Option(_)) => Ok((**inner).clone()),
+                    (Type::Result(ok, err), Type::Result(_, return_err)) if self.type_arguments_compatible(return_err, err) => {
+                        Ok((**ok).clone())
+                    }
+                    (Type::Option(_) | Type::Result(..), _) => Err(TypeCheckError::TryReturnMismatch {
+                        found: found.clone(),
+                        return_type,
+                    }),
+                    _ => Err(TypeCheckError::NotTryable { found }),
+                }
+            }
+            
+            Expression::Lambda { params, return_type, body } => {
+                self.check_lambda(params, return_type, body)
+            }
+            
+            Expression::EnumVariant { enum_name, variant, args } => {
+                let fields = self.lookup_variant(enum_name, variant)?.fields.clone();
+                if args.len() != fields.len() {
+                    return Err(TypeCheckError::VariantFieldCountMismatch {
+                        enum_name: enum_name.clone(),
+                        variant: variant.clone(),
+                        expected: fields.len(),
+                        found: args.len(),
+                    });
+                }
+                for (arg, field_type) in args.iter().zip(&fields) {
+                    let arg_type = self.check_expression_as(arg, field_type)?;
+                    if !self.types_are_compatible(field_type, &arg_type) {
+                        return Err(TypeCheckError::TypeMismatch {
+                            expected: field_type.clone(),
+                            found: arg_type,
+                        });
                     }
                 }
+                Ok(Type::Enum(enum_name.clone()))
+            }
+            
+            Expression::Match { subject, arms } => {
+                let subject_type = self.check_expression(subject)?;
+                let subject_type = self.enum_type(subject_type);
                 
-                Ok(expr_type)
+                let mut arm_bindings = Vec::new();
+                for arm in arms {
+                    arm_bindings.push(self.check_pattern(&arm.pattern, &subject_type)?);
+                }
+                self.check_exhaustive(arms, &subject_type)?;
+                self.check_unreachable_arms(arms, &subject_type);
+                
+                // Тип match - тип первой ветки (кроме never), остальные должны
+                // с ним совпадать; связанные образцом поля видны только в своей ветке.
+                // exit в одной ветке не делает недостижимым код после match
+                let diverged_before = self.diverges;
+                let before = self.variables.clone();
+                let mut arm_variables = Vec::new();
+                let mut result_type = Type::Void;
+                for (i, (arm, bindings)) in arms.iter().zip(arm_bindings).enumerate() {
+                    self.scopes.push(Vec::new());
+                    for (name, var_type) in bindings {
+                        self.declare_variable(&name, VariableInfo {
+                            var_type,
+                            state: VariableState::Available,
+                            mutable: false,
+                        });
+                    }
+                    let arm_type = self.check_expression(&arm.body);
+                    self.exit_scope();
+                    arm_variables.push(std::mem::replace(&mut self.variables, before.clone()));
+                    let arm_type = arm_type?;
+                    if i == 0 || result_type == Type::Never {
+                        result_type = arm_type;
+                    } else if !self.types_are_compatible(&result_type, &arm_type) {
+                        return Err(TypeCheckError::TypeMismatch {
+                            expected: result_type,
+                            found: arm_type,
+                        });
+                    }
+                }
+                for variables in arm_variables {
+                    self.merge_branch(variables);
+                }
+                self.diverges = diverged_before || result_type == Type::Never;
+                Ok(result_type)
             }
             
-            Expression::Borrow { expression, mutable: _ } => {
+            Expression::If { condition, then_branch, else_branch } => {
+                let cond_type = self.check_expression(condition)?;
+                if cond_type != Type::Bool {
+                    return Err(TypeCheckError::NonBooleanCondition {
+                        found: cond_type,
+                    });
+                }
+                // Как у match: ветка с exit не определяет тип
+                let diverged_before = self.diverges;
+                let then_type = self.check_expression(then_branch)?;
+                let else_type = self.check_expression(else_branch)?;
+                let result_type = if then_type == Type::Never {
+                    else_type
+                } else if else_type == Type::Never || self.types_are_compatible(&then_type, &else_type) {
+                    then_type
+                } else {
+                    return Err(TypeCheckError::TypeMismatch {
+                        expected: then_type,
+                        found: else_type,
+                    });
+                };
+                self.diverges = diverged_before || result_type == Type::Never;
+                Ok(result_type)
+            }
+            
+            Expression::Borrow { expression, mutable } => {
                 let expr_type = self.check_expression(expression)?;
                 
-                if let Expression::Variable(name) = expression.as_ref() {
-                    if let Some(var_info) = self.variables.get_mut(name) {
-                        var_info.state = VariableState::Borrowed;
+                // borrow(p.position) заимствует всю переменную p
+                if let Some(name) = Self::borrowed_variable(expression) {
+                    if self.variables.contains_key(name) {
+                        self.start_borrow(name, *mutable)?;
                     }
                 }
                 
                 Ok(expr_type)
             }
+            // 3000000000i64 - литерал, приведённый к i64: он сразу получает этот тип
             Expression::TypeCast { expression, target_type } => {
-                let expr_type = self.check_expression(expression)?;
+                let expr_type = self.check_expression_as(expression, target_type)?;
     
                 // Проверяем допустимые преобразования типов
                 match (&expr_type, target_type) {
@@ -641,6 +2612,10 @@ impl TypeChecker {
                     (Type::F32, Type::F64) => Ok(Type::F64),
                     (Type::F64, Type::F32) => Ok(Type::F32),
                     (same, same2) if same == same2 => Ok(target_type.clone()), // Тот же тип
+                    // sprite as Drawable - значение как трейт, который реализует его тип
+                    (Type::Struct(actual) | Type::Enum(actual), Type::Struct(name)) if self.implements(actual, name) => {
+                        Ok(target_type.clone())
+                    }
                     _ => Err(TypeCheckError::TypeMismatch {
                         expected: target_type.clone(),
                         found: expr_type,
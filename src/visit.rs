@@ -0,0 +1,377 @@
+// visit.rs - обход AST для анализаторов
+
+use crate::ast::*;
+use std::collections::HashSet;
+
+/// Обход AST только для чтения. Методы по умолчанию рекурсивно обходят
+/// дочерние узлы; переопределите нужный метод и вызовите `walk_*`,
+/// чтобы продолжить обход.
+pub trait Visitor {
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for function in &program.functions {
+        visitor.visit_function(function);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &Function) {
+    for condition in function.requires().chain(function.ensures()) {
+        visitor.visit_expression(condition);
+    }
+    for statement in &function.body {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::VariableDeclaration { value, .. } => visitor.visit_expression(value),
+        Statement::DestructureStruct { value, .. } => visitor.visit_expression(value),
+        Statement::Assignment { value, .. } => visitor.visit_expression(value),
+        Statement::FieldAssignment { value, .. } => visitor.visit_expression(value),
+        Statement::IndexAssignment { index, value, .. } => {
+            visitor.visit_expression(index);
+            visitor.visit_expression(value);
+        }
+        Statement::Return { value, .. } => visitor.visit_expression(value),
+        Statement::Expression(expr, _) => visitor.visit_expression(expr),
+        Statement::Defer { expression, .. } => visitor.visit_expression(expression),
+        Statement::Block { statements, .. } => {
+            for stmt in statements {
+                visitor.visit_statement(stmt);
+            }
+        }
+        Statement::While { condition, body, .. } => {
+            visitor.visit_expression(condition);
+            for stmt in body {
+                visitor.visit_statement(stmt);
+            }
+        }
+        Statement::Loop { body, .. } => {
+            for stmt in body {
+                visitor.visit_statement(stmt);
+            }
+        }
+        Statement::For { start, end, body, .. } => {
+            visitor.visit_expression(start);
+            visitor.visit_expression(end);
+            for stmt in body {
+                visitor.visit_statement(stmt);
+            }
+        }
+        Statement::If { condition, then_branch, else_branch, .. } => {
+            visitor.visit_expression(condition);
+            for stmt in then_branch {
+                visitor.visit_statement(stmt);
+            }
+            if let Some(else_branch) = else_branch {
+                for stmt in else_branch {
+                    visitor.visit_statement(stmt);
+                }
+            }
+        }
+        Statement::Break { .. } | Statement::Continue { .. } => {}
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::IntegerLiteral(_)
+        | Expression::FloatLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::BoolLiteral(_)
+        | Expression::Variable(_) => {}
+        Expression::BinaryExpression { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::FunctionCall { args, .. } | Expression::EnumVariant { args, .. } => {
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::StructInitialization { fields, base, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expression(value);
+            }
+            if let Some(base) = base {
+                visitor.visit_expression(base);
+            }
+        }
+        Expression::FieldAccess { expression, .. }
+        | Expression::TypeCast { expression, .. }
+        | Expression::Move { expression }
+        | Expression::Borrow { expression, .. }
+        | Expression::Ref { expression }
+        | Expression::Try { expression }
+        | Expression::ArrayRepeat { value: expression, .. } => visitor.visit_expression(expression),
+        Expression::ArrayLiteral { elements } => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Expression::Index { expression, index } => {
+            visitor.visit_expression(expression);
+            visitor.visit_expression(index);
+        }
+        Expression::Slice { expression, start, end } => {
+            visitor.visit_expression(expression);
+            for bound in [start, end].into_iter().flatten() {
+                visitor.visit_expression(bound);
+            }
+        }
+        Expression::Match { subject, arms } => {
+            visitor.visit_expression(subject);
+            for arm in arms {
+                visitor.visit_expression(&arm.body);
+            }
+        }
+        Expression::If { condition, then_branch, else_branch } => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(then_branch);
+            visitor.visit_expression(else_branch);
+        }
+        Expression::MethodCall { receiver, args, .. } => {
+            visitor.visit_expression(receiver);
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Lambda { body, .. } => {
+            for stmt in body {
+                visitor.visit_statement(stmt);
+            }
+        }
+    }
+}
+
+/// Обход AST с изменением узлов на месте (переписывающие проходы
+/// оптимизатора); устроен так же, как Visitor
+pub trait VisitorMut {
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, function: &mut Function) {
+    for attribute in &mut function.attributes {
+        if let Attribute::Requires(condition) | Attribute::Ensures(condition) = attribute {
+            visitor.visit_expression_mut(condition);
+        }
+    }
+    for statement in &mut function.body {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::VariableDeclaration { value, .. }
+        | Statement::DestructureStruct { value, .. }
+        | Statement::Assignment { value, .. }
+        | Statement::FieldAssignment { value, .. }
+        | Statement::Return { value, .. }
+        | Statement::Expression(value, _)
+        | Statement::Defer { expression: value, .. } => visitor.visit_expression_mut(value),
+        Statement::IndexAssignment { index, value, .. } => {
+            visitor.visit_expression_mut(index);
+            visitor.visit_expression_mut(value);
+        }
+        Statement::Block { statements: body, .. } | Statement::Loop { body, .. } => {
+            for stmt in body {
+                visitor.visit_statement_mut(stmt);
+            }
+        }
+        Statement::While { condition, body, .. } => {
+            visitor.visit_expression_mut(condition);
+            for stmt in body {
+                visitor.visit_statement_mut(stmt);
+            }
+        }
+        Statement::For { start, end, body, .. } => {
+            visitor.visit_expression_mut(start);
+            visitor.visit_expression_mut(end);
+            for stmt in body {
+                visitor.visit_statement_mut(stmt);
+            }
+        }
+        Statement::If { condition, then_branch, else_branch, .. } => {
+            visitor.visit_expression_mut(condition);
+            for stmt in then_branch.iter_mut().chain(else_branch.iter_mut().flatten()) {
+                visitor.visit_statement_mut(stmt);
+            }
+        }
+        Statement::Break { .. } | Statement::Continue { .. } => {}
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::IntegerLiteral(_)
+        | Expression::FloatLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::BoolLiteral(_)
+        | Expression::Variable(_) => {}
+        Expression::BinaryExpression { left, right, .. } => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        Expression::FunctionCall { args, .. }
+        | Expression::EnumVariant { args, .. }
+        | Expression::ArrayLiteral { elements: args } => {
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Expression::StructInitialization { fields, base, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expression_mut(value);
+            }
+            if let Some(base) = base {
+                visitor.visit_expression_mut(base);
+            }
+        }
+        Expression::FieldAccess { expression, .. }
+        | Expression::TypeCast { expression, .. }
+        | Expression::Move { expression }
+        | Expression::Borrow { expression, .. }
+        | Expression::Ref { expression }
+        | Expression::Try { expression }
+        | Expression::ArrayRepeat { value: expression, .. } => visitor.visit_expression_mut(expression),
+        Expression::Index { expression, index } => {
+            visitor.visit_expression_mut(expression);
+            visitor.visit_expression_mut(index);
+        }
+        Expression::Slice { expression, start, end } => {
+            visitor.visit_expression_mut(expression);
+            for bound in [start, end].into_iter().flatten() {
+                visitor.visit_expression_mut(bound);
+            }
+        }
+        Expression::Match { subject, arms } => {
+            visitor.visit_expression_mut(subject);
+            for arm in arms {
+                visitor.visit_expression_mut(&mut arm.body);
+            }
+        }
+        Expression::If { condition, then_branch, else_branch } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_expression_mut(then_branch);
+            visitor.visit_expression_mut(else_branch);
+        }
+        Expression::MethodCall { receiver, args, .. } => {
+            visitor.visit_expression_mut(receiver);
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Expression::Lambda { body, .. } => {
+            for stmt in body {
+                visitor.visit_statement_mut(stmt);
+            }
+        }
+    }
+}
+
+/// Как тело цикла с меткой `label` может его покинуть
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LoopExits {
+    /// break без метки вне вложенных циклов или break с меткой этого цикла
+    pub breaks: bool,
+    pub returns: bool,
+}
+
+pub fn loop_exits(label: &Option<String>, body: &[Statement]) -> LoopExits {
+    let mut finder = LoopExitFinder { label, depth: 0, exits: LoopExits::default() };
+    for statement in body {
+        finder.visit_statement(statement);
+    }
+    finder.exits
+}
+
+struct LoopExitFinder<'a> {
+    label: &'a Option<String>,
+    // Вложенность циклов внутри тела: break без метки там выходит из них
+    depth: usize,
+    exits: LoopExits,
+}
+
+impl Visitor for LoopExitFinder<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Break { label: None, .. } if self.depth == 0 => self.exits.breaks = true,
+            Statement::Break { label: Some(target), .. } if self.label.as_ref() == Some(target) => self.exits.breaks = true,
+            Statement::Return { .. } => self.exits.returns = true,
+            Statement::While { .. } | Statement::Loop { .. } | Statement::For { .. } => {
+                self.depth += 1;
+                walk_statement(self, statement);
+                self.depth -= 1;
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+
+    // return в теле замыкания выходит из замыкания, а не из цикла
+    fn visit_expression(&mut self, _expression: &Expression) {}
+}
+
+/// Локальные имена функции: параметры, переменные (в том числе из
+/// деструктуризации и образцов match), счётчики for и параметры замыканий
+pub fn local_names(function: &Function) -> HashSet<String> {
+    struct LocalCollector(HashSet<String>);
+
+    impl Visitor for LocalCollector {
+        fn visit_statement(&mut self, statement: &Statement) {
+            match statement {
+                Statement::VariableDeclaration { name, .. } => {
+                    self.0.insert(name.clone());
+                }
+                Statement::DestructureStruct { bindings, .. } => {
+                    self.0.extend(bindings.iter().map(|(_, name)| name.clone()));
+                }
+                Statement::For { variable, .. } => {
+                    self.0.insert(variable.clone());
+                }
+                _ => {}
+            }
+            walk_statement(self, statement);
+        }
+
+        fn visit_expression(&mut self, expression: &Expression) {
+            match expression {
+                Expression::Lambda { params, .. } => {
+                    self.0.extend(params.iter().map(|param| param.name.clone()));
+                }
+                Expression::Match { arms, .. } => {
+                    for arm in arms {
+                        if let Pattern::Variant { bindings, .. } = &arm.pattern {
+                            self.0.extend(bindings.iter().cloned());
+                        }
+                    }
+                }
+                _ => {}
+            }
+            walk_expression(self, expression);
+        }
+    }
+
+    let mut collector = LocalCollector(function.params.iter().map(|param| param.name.clone()).collect());
+    collector.visit_function(function);
+    collector.0
+}
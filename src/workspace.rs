@@ -0,0 +1,448 @@
+// workspace.rs - несколько пакетов в одном рабочем пространстве
+//
+// aetos.toml в корне перечисляет пакеты-участники:
+//
+//     [workspace]
+//     members = ["engine", "game"]
+//     target_dir = "target"        # необязательно, по умолчанию target
+//
+// Каждый участник - каталог со своим aetos.toml и секцией [package].
+// Пакет может зависеть от другого по пути:
+//
+//     [dependencies]
+//     engine = { path = "../engine" }
+//
+// и импортировать его файлы по имени: `import engine;` загружает точку
+// входа пакета (`entry`, по умолчанию main.aetos), а
+// `import "engine/physics.aetos";` - файл внутри его каталога. Имя
+// зависимости важнее одноимённого файла рядом с импортирующим.
+//
+// Зависимость из реестра записывается диапазоном версий (`ui_kit = "1.2"`)
+// и берётся из каталога .aetos/packages, куда её ставит `aetosc add`
+// (registry.rs); используется старшая установленная версия из диапазона.
+//
+// `aetosc build` и `aetosc check` обходят участников так, что зависимости
+// идут раньше зависящих от них пакетов; результаты сборки всех пакетов
+// складываются в общий каталог target_dir.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+pub const MANIFEST: &str = "aetos.toml";
+pub const DEFAULT_ENTRY: &str = "main.aetos";
+pub const DEFAULT_TARGET_DIR: &str = "target";
+/// Пакеты из реестра, общие для всего рабочего пространства
+pub const PACKAGES_DIR: &str = ".aetos/packages";
+
+#[derive(Error, Debug)]
+pub enum WorkspaceError {
+    #[error("Cannot read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("{path}:{line}: {message}")]
+    Syntax { path: PathBuf, line: usize, message: String },
+
+    #[error("{path} has no [package] section")]
+    NotAPackage { path: PathBuf },
+
+    #[error("Package {package} depends on {dependency}, but {path} is not a package")]
+    MissingDependency { package: String, dependency: String, path: PathBuf },
+
+    #[error("Package {package} depends on {dependency} {requirement}, which is not installed; run `aetosc add {dependency}`")]
+    NotInstalled { package: String, dependency: String, requirement: String },
+
+    #[error("Invalid version requirement {requirement} for {dependency}: {message}")]
+    Requirement { dependency: String, requirement: String, message: String },
+
+    #[error("Duplicate package {name}: {first} and {second}")]
+    Duplicate { name: String, first: PathBuf, second: PathBuf },
+
+    #[error("Dependency cycle between packages: {}", .cycle.join(" -> "))]
+    Cycle { cycle: Vec<String> },
+}
+
+/// Содержимое одного aetos.toml; понимает только то, что нужно компилятору:
+/// строки, массивы строк и таблицы `{ path = "..." }`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    pub package: Option<PackageSection>,
+    pub workspace: Option<WorkspaceSection>,
+    pub dependencies: Vec<(String, DependencySource)>,
+    /// `[registry] url = "..."`
+    pub registry: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PackageSection {
+    pub name: String,
+    pub version: Option<String>,
+    pub entry: Option<String>,
+    pub crate_type: Option<String>,
+    /// Редакция языка (edition.rs)
+    pub edition: Option<String>,
+    /// Каталог ресурсов относительно манифеста (assets.rs)
+    pub assets_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencySource {
+    /// `{ path = "../engine" }` - каталог относительно манифеста
+    Path(PathBuf),
+    /// `"1.2"` или `{ version = "1.2" }` - диапазон версий из реестра
+    Registry(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkspaceSection {
+    pub members: Vec<String>,
+    pub target_dir: Option<String>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str, path: &Path) -> Result<Self, WorkspaceError> {
+        let error = |line: usize, message: String| WorkspaceError::Syntax { path: path.to_path_buf(), line, message };
+        let mut manifest = Manifest::default();
+        let mut section = String::new();
+        let mut lines = text.lines().enumerate();
+
+        while let Some((index, line)) = lines.next() {
+            let line_number = index + 1;
+            let mut line = strip_comment(line).trim().to_string();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                section = line.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+                match section.as_str() {
+                    "package" => manifest.package = Some(PackageSection::default()),
+                    "workspace" => manifest.workspace = Some(WorkspaceSection::default()),
+                    _ => {}
+                }
+                continue;
+            }
+            // Массив может занимать несколько строк
+            while line.contains("= [") && !line.ends_with(']') {
+                match lines.next() {
+                    Some((_, next)) => line.push_str(strip_comment(next).trim()),
+                    None => return Err(error(line_number, "unterminated array".to_string())),
+                }
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error(line_number, format!("expected `key = value`, found `{}`", line)));
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            match (section.as_str(), key) {
+                ("package", "name") => manifest.package_mut().name = string_value(value),
+                ("package", "version") => manifest.package_mut().version = Some(string_value(value)),
+                ("package", "entry") => manifest.package_mut().entry = Some(string_value(value)),
+                ("package", "crate_type") => manifest.package_mut().crate_type = Some(string_value(value)),
+                ("package", "edition") => manifest.package_mut().edition = Some(string_value(value)),
+                ("package", "assets_dir") => manifest.package_mut().assets_dir = Some(string_value(value)),
+                ("workspace", "members") => {
+                    manifest.workspace_mut().members = array_value(value)
+                        .ok_or_else(|| error(line_number, "members must be an array of strings".to_string()))?;
+                }
+                ("workspace", "target_dir") => manifest.workspace_mut().target_dir = Some(string_value(value)),
+                ("dependencies", name) => {
+                    let source = if value.starts_with('"') {
+                        DependencySource::Registry(string_value(value))
+                    } else if let Some(path) = table_value(value, "path") {
+                        DependencySource::Path(PathBuf::from(path))
+                    } else if let Some(version) = table_value(value, "version") {
+                        DependencySource::Registry(version)
+                    } else {
+                        return Err(error(line_number, format!(
+                            "dependency {} needs a version (\"1.2\") or `{{ path = \"...\" }}`", name
+                        )));
+                    };
+                    manifest.dependencies.push((name.to_string(), source));
+                }
+                ("registry", "url") => manifest.registry = Some(string_value(value)),
+                _ => {}
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, WorkspaceError> {
+        let text = fs::read_to_string(path).map_err(|source| WorkspaceError::Io { path: path.to_path_buf(), source })?;
+        Self::parse(&text, path)
+    }
+
+    /// Ближайший aetos.toml, начиная с каталога `dir`, и каталог, где он лежит;
+    /// None, если манифеста нет или он не разбирается
+    pub fn nearest(dir: &Path) -> Option<(PathBuf, Self)> {
+        let ancestor = dir.ancestors().find(|ancestor| ancestor.join(MANIFEST).is_file())?;
+        let manifest = Self::read(&ancestor.join(MANIFEST)).ok()?;
+        Some((ancestor.to_path_buf(), manifest))
+    }
+
+    fn package_mut(&mut self) -> &mut PackageSection {
+        self.package.get_or_insert_with(PackageSection::default)
+    }
+
+    fn workspace_mut(&mut self) -> &mut WorkspaceSection {
+        self.workspace.get_or_insert_with(WorkspaceSection::default)
+    }
+}
+
+/// `#` вне строки начинает комментарий
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn string_value(value: &str) -> String {
+    value.trim().trim_matches('"').to_string()
+}
+
+/// `["a", "b"]`
+fn array_value(value: &str) -> Option<Vec<String>> {
+    let items = value.strip_prefix('[')?.strip_suffix(']')?;
+    Some(items.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(string_value)
+        .collect())
+}
+
+/// Значение ключа из `{ path = "../engine" }`
+fn table_value(value: &str, key: &str) -> Option<String> {
+    let fields = value.strip_prefix('{')?.strip_suffix('}')?;
+    fields.split(',')
+        .filter_map(|field| field.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| string_value(value))
+}
+
+/// Пакет: каталог с aetos.toml и секцией [package]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub dir: PathBuf,
+    pub entry: PathBuf,
+    /// Имя зависимости -> каталог её пакета
+    pub dependencies: Vec<(String, PathBuf)>,
+    pub is_lib: bool,
+}
+
+impl Package {
+    pub fn load(dir: &Path) -> Result<Self, WorkspaceError> {
+        let path = dir.join(MANIFEST);
+        let manifest = Manifest::read(&path)?;
+        let package = manifest.package.ok_or(WorkspaceError::NotAPackage { path })?;
+        let name = if package.name.is_empty() {
+            dir.file_name().map_or("package".to_string(), |name| name.to_string_lossy().to_string())
+        } else {
+            package.name
+        };
+        let mut dependencies = Vec::new();
+        for (dependency, source) in manifest.dependencies {
+            let dependency_dir = match source {
+                DependencySource::Path(path) => normalize(&dir.join(path)),
+                DependencySource::Registry(requirement) => {
+                    let parsed = semver::VersionReq::parse(&requirement).map_err(|e| WorkspaceError::Requirement {
+                        dependency: dependency.clone(),
+                        requirement: requirement.clone(),
+                        message: e.to_string(),
+                    })?;
+                    installed_package(dir, &dependency, &parsed).ok_or_else(|| WorkspaceError::NotInstalled {
+                        package: name.clone(),
+                        dependency: dependency.clone(),
+                        requirement,
+                    })?
+                }
+            };
+            dependencies.push((dependency, dependency_dir));
+        }
+        Ok(Package {
+            entry: dir.join(package.entry.as_deref().unwrap_or(DEFAULT_ENTRY)),
+            name,
+            dir: dir.to_path_buf(),
+            dependencies,
+            is_lib: package.crate_type.as_deref() == Some("lib"),
+        })
+    }
+
+    /// Ближайший пакет, содержащий каталог `dir`
+    pub fn containing(dir: &Path) -> Option<Self> {
+        dir.ancestors()
+            .find(|ancestor| ancestor.join(MANIFEST).is_file())
+            .and_then(|ancestor| Package::load(ancestor).ok())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub target_dir: PathBuf,
+    /// Участники в порядке сборки: зависимости раньше зависящих
+    pub packages: Vec<Package>,
+}
+
+impl Workspace {
+    /// Рабочее пространство из aetos.toml в каталоге `root`; манифест без
+    /// [workspace] - пространство из одного пакета
+    pub fn load(root: &Path) -> Result<Self, WorkspaceError> {
+        let root = normalize(root);
+        let manifest = Manifest::read(&root.join(MANIFEST))?;
+        let (members, target_dir) = match manifest.workspace {
+            Some(workspace) => (
+                workspace.members.iter().map(|member| normalize(&root.join(member))).collect(),
+                workspace.target_dir,
+            ),
+            None => (vec![root.clone()], None),
+        };
+        let target_dir = root.join(target_dir.as_deref().unwrap_or(DEFAULT_TARGET_DIR));
+
+        let mut packages: Vec<Package> = Vec::new();
+        for dir in members {
+            let package = Package::load(&dir)?;
+            if let Some(first) = packages.iter().find(|other| other.name == package.name) {
+                return Err(WorkspaceError::Duplicate {
+                    name: package.name,
+                    first: first.dir.clone(),
+                    second: dir,
+                });
+            }
+            packages.push(package);
+        }
+        for package in &packages {
+            for (dependency, dir) in &package.dependencies {
+                if !dir.join(MANIFEST).is_file() {
+                    return Err(WorkspaceError::MissingDependency {
+                        package: package.name.clone(),
+                        dependency: dependency.clone(),
+                        path: dir.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(Workspace { root, target_dir, packages: build_order(packages)? })
+    }
+
+    /// Ближайший каталог с aetos.toml, содержащим [workspace], начиная с `dir`
+    pub fn find_root(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .find(|ancestor| {
+                Manifest::read(&ancestor.join(MANIFEST)).is_ok_and(|manifest| manifest.workspace.is_some())
+            })
+            .map(Path::to_path_buf)
+    }
+
+    /// Куда собирается пакет: `<target_dir>/<name>.<extension>`
+    pub fn output_path(&self, package: &Package, extension: &str) -> PathBuf {
+        self.target_dir.join(format!("{}.{}", package.name, extension))
+    }
+}
+
+/// Топологическая сортировка участников; зависимости вне рабочего
+/// пространства порядка не задают
+fn build_order(packages: Vec<Package>) -> Result<Vec<Package>, WorkspaceError> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State { New, Visiting, Done }
+
+    fn visit(
+        index: usize,
+        packages: &[Package],
+        states: &mut [State],
+        path: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> Result<(), WorkspaceError> {
+        match states[index] {
+            State::Done => return Ok(()),
+            State::Visiting => {
+                let start = path.iter().position(|&i| i == index).unwrap_or(0);
+                let mut cycle: Vec<String> = path[start..].iter().map(|&i| packages[i].name.clone()).collect();
+                cycle.push(packages[index].name.clone());
+                return Err(WorkspaceError::Cycle { cycle });
+            }
+            State::New => {}
+        }
+        states[index] = State::Visiting;
+        path.push(index);
+        for (_, dir) in &packages[index].dependencies {
+            if let Some(dependency) = packages.iter().position(|package| package.dir == *dir) {
+                visit(dependency, packages, states, path, order)?;
+            }
+        }
+        path.pop();
+        states[index] = State::Done;
+        order.push(index);
+        Ok(())
+    }
+
+    let mut states = vec![State::New; packages.len()];
+    let mut order = Vec::new();
+    for index in 0..packages.len() {
+        visit(index, &packages, &mut states, &mut Vec::new(), &mut order)?;
+    }
+    Ok(order.into_iter().map(|index| packages[index].clone()).collect())
+}
+
+/// Файл для импорта `import_path` из каталога `dir`, если импорт начинается
+/// с имени зависимости пакета, в котором лежит `dir`
+pub fn resolve_dependency(dir: &Path, import_path: &str) -> Option<PathBuf> {
+    let mut components = Path::new(import_path).components();
+    let Some(Component::Normal(first)) = components.next() else {
+        return None;
+    };
+    let first = first.to_str()?;
+    let rest = components.as_path();
+    // `import engine;` записывается парсером как engine.aetos
+    let (name, whole_package) = match first.strip_suffix(".aetos") {
+        Some(name) if rest.as_os_str().is_empty() => (name, true),
+        _ => (first, false),
+    };
+
+    let package = Package::containing(dir)?;
+    let (_, dependency_dir) = package.dependencies.iter().find(|(dependency, _)| dependency == name)?;
+    if whole_package {
+        Package::load(dependency_dir).ok().map(|dependency| dependency.entry)
+    } else {
+        Some(dependency_dir.join(rest))
+    }
+}
+
+/// Каталог пакета из реестра: старшая версия из диапазона в ближайшем
+/// .aetos/packages (`ui_kit-1.2.0`)
+pub fn installed_package(dir: &Path, name: &str, requirement: &semver::VersionReq) -> Option<PathBuf> {
+    let packages_dir = dir.ancestors()
+        .map(|ancestor| ancestor.join(PACKAGES_DIR))
+        .find(|packages_dir| packages_dir.is_dir())?;
+    fs::read_dir(packages_dir).ok()?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let version = path.file_name()?.to_str()?.strip_prefix(name)?.strip_prefix('-')?;
+            let version = semver::Version::parse(version).ok()?;
+            requirement.matches(&version).then_some((version, path))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, path)| path)
+}
+
+/// Путь без `.` и `a/..`
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
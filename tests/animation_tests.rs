@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use aetos::animation::{ease_in_out, lerp, move_towards, FrameClock, MAX_DELTA_TIME};
+    use aetos::examples;
+    use aetos::interpreter::Interpreter;
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(lerp(10.0, 20.0, 0.25), 12.5);
+        assert_eq!(lerp(10.0, 20.0, 2.0), 20.0);
+        assert_eq!(lerp(10.0, 20.0, -1.0), 10.0);
+
+        assert_eq!(ease_in_out(0.0), 0.0);
+        assert_eq!(ease_in_out(0.5), 0.5);
+        assert_eq!(ease_in_out(1.0), 1.0);
+        assert!(ease_in_out(0.1) < 0.1 && ease_in_out(0.9) > 0.9);
+
+        assert_eq!(move_towards(0.0, 10.0, 3.0), 3.0);
+        assert_eq!(move_towards(0.0, -10.0, 3.0), -3.0);
+        assert_eq!(move_towards(9.0, 10.0, 3.0), 10.0);
+    }
+
+    #[test]
+    fn test_frame_clock_is_capped() {
+        let mut clock = FrameClock::new();
+        assert_eq!(clock.delta(), 0.0);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let delta = clock.tick();
+        assert!(delta >= 0.005 && delta <= MAX_DELTA_TIME);
+        assert_eq!(clock.delta(), delta);
+    }
+
+    #[test]
+    fn test_animation_builtins() {
+        let code = r#"
+            fn main() -> i32 {
+                print(lerp(0, 8, 0.5));
+                print(ease_in_out(0.5));
+                print(move_towards(1.0, 2, 5.0));
+                print(delta_time());
+                return 0;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        assert_eq!(interpreter.take_output(), vec!["4", "0.5", "2", "0"]);
+    }
+
+    #[test]
+    fn test_easing_example_typechecks() {
+        let example = examples::find("easing").unwrap();
+        assert!(example.is_graphics());
+        let program = Parser::new(example.source).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+    }
+}
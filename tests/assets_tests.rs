@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use aetos::assets::AssetResolver;
+    use aetos::interpreter::Interpreter;
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+    use std::path::{Path, PathBuf};
+
+    fn temp_project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aetos-assets-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolves_relative_to_script() {
+        let dir = temp_project("script");
+        let assets = AssetResolver::for_script(&dir.join("src").join("game.aetos"));
+        assert_eq!(assets.resolve("sprites/ship.png"), dir.join("src").join("sprites/ship.png"));
+
+        // Абсолютные пути не меняются
+        let absolute = dir.join("music.ogg");
+        assert_eq!(assets.resolve(absolute.to_str().unwrap()), absolute);
+
+        // Относительный каталог фиксируется от текущего
+        let relative = AssetResolver::for_script(Path::new("game.aetos"));
+        assert_eq!(relative.base(), std::env::current_dir().unwrap().join("."));
+    }
+
+    #[test]
+    fn test_manifest_assets_dir() {
+        let dir = temp_project("manifest");
+        std::fs::write(dir.join("aetos.toml"), "[package]\nname = \"game\"\nassets_dir = \"assets\"\n").unwrap();
+        let assets = AssetResolver::for_script(&dir.join("src").join("main.aetos"));
+        assert_eq!(assets.base(), dir.join("assets"));
+        assert_eq!(assets.resolve("ship.png"), dir.join("assets").join("ship.png"));
+    }
+
+    #[test]
+    fn test_manifest_assets_dir_outside_package_is_ignored() {
+        // Ключ читается только из [package], а не из любой секции
+        let dir = temp_project("section");
+        std::fs::write(dir.join("aetos.toml"), "[package]\nname = \"game\"\n\n[registry]\nassets_dir = \"assets\"\n").unwrap();
+        let assets = AssetResolver::for_script(&dir.join("src").join("main.aetos"));
+        assert_eq!(assets.base(), dir.join("src"));
+    }
+
+    #[test]
+    fn test_resolve_asset_builtin() {
+        let code = r#"
+            fn main() -> i32 {
+                print_string(resolve_asset("ship.png"));
+                return 0;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+
+        let dir = temp_project("builtin");
+        let mut interpreter = Interpreter::new();
+        interpreter.set_asset_resolver(AssetResolver::new(&dir));
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        assert_eq!(interpreter.take_output(), vec![dir.join("ship.png").to_string_lossy().into_owned()]);
+    }
+}
@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+    use aetos::capabilities::{Capabilities, Capability, CapabilityDenied};
+    use aetos::embed::{CompiledScript, ScriptError};
+    use aetos::interpreter::Interpreter;
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+
+    fn run(code: &str, capabilities: Capabilities) -> (Result<(), Box<dyn std::error::Error>>, Vec<String>) {
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::with_capabilities(capabilities);
+        interpreter.capture_output();
+        let result = interpreter.interpret_program(&program, 0, 0, "test");
+        (result, interpreter.take_output())
+    }
+
+    #[test]
+    fn test_builtin_capabilities() {
+        assert_eq!(Capability::required_by("draw_rect"), Some(Capability::Graphics));
+        assert_eq!(Capability::required_by("store_get"), Some(Capability::Fs));
+        assert_eq!(Capability::required_by("sleep"), Some(Capability::Time));
+        assert_eq!(Capability::required_by("spawn"), Some(Capability::Exec));
+        assert_eq!(Capability::required_by("random_int"), Some(Capability::Random));
+        assert_eq!(Capability::required_by("http_get"), Some(Capability::Net));
+        assert_eq!(Capability::required_by("print"), None);
+        assert_eq!(Capability::required_by("substring"), None);
+
+        assert!(Capabilities::all().check("store_set").is_ok());
+        let denied = Capabilities::none().check("store_set").unwrap_err();
+        assert_eq!(denied.capability, Capability::Fs);
+        assert_eq!(denied.to_string(), "store_set requires the `fs` capability, which is denied in this interpreter");
+        assert_eq!(Capabilities::default(), Capabilities::all());
+    }
+
+    #[test]
+    fn test_sandbox_denies_builtins_but_not_computation() {
+        let code = r#"
+            fn main() -> i32 {
+                print(to_upper("ok"), 2 * 21);
+                store_set("score", "10");
+                print("unreachable");
+                return 0;
+            }
+        "#;
+        let (result, output) = run(code, Capabilities::none());
+        let error = result.unwrap_err();
+        let denied = error.downcast_ref::<CapabilityDenied>().expect("capability error");
+        assert_eq!(denied.builtin, "store_set");
+        assert_eq!(output, vec!["OK 42"]);
+
+        let timed = "fn main() -> i32 { let t: f32 = get_time(); return 0; }";
+        let time_only = Capabilities { time: true, ..Capabilities::none() };
+        assert!(run(timed, time_only).0.is_ok());
+        assert!(run(timed, Capabilities::none()).0.is_err());
+    }
+
+    #[test]
+    fn test_random_and_network_need_capabilities() {
+        let dice = r#"
+            fn main() -> i32 {
+                let roll: i32 = random_int(1, 7);
+                print(roll >= 1 && roll < 7, random_float() < 1.0);
+                return 0;
+            }
+        "#;
+        let random_only = Capabilities { random: true, ..Capabilities::none() };
+        let (result, output) = run(dice, random_only);
+        assert!(result.is_ok());
+        assert_eq!(output, vec!["true true"]);
+
+        let error = run(dice, Capabilities::none()).0.unwrap_err();
+        let denied = error.downcast_ref::<CapabilityDenied>().expect("capability error");
+        assert_eq!((denied.builtin.as_str(), denied.capability), ("random_int", Capability::Random));
+
+        // Запрет проверяется до запроса: сеть в тесте не нужна
+        let fetch = r#"
+            fn main() -> i32 {
+                print(http_get("http://localhost/scores"));
+                return 0;
+            }
+        "#;
+        let error = run(fetch, Capabilities { random: true, ..Capabilities::none() }).0.unwrap_err();
+        let denied = error.downcast_ref::<CapabilityDenied>().expect("capability error");
+        assert_eq!((denied.builtin.as_str(), denied.capability), ("http_get", Capability::Net));
+    }
+
+    #[test]
+    fn test_threads_inherit_capabilities() {
+        let code = r#"
+            fn worker() -> i32 {
+                sleep(1);
+                return 1;
+            }
+
+            fn main() -> i32 {
+                let handle: i32 = spawn("worker");
+                print(join(handle));
+                return 0;
+            }
+        "#;
+        let exec_only = Capabilities { exec: true, ..Capabilities::none() };
+        let (result, _) = run(code, exec_only);
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("sleep requires the `time` capability"), "{}", message);
+
+        let (result, _) = run(code, Capabilities::none());
+        assert!(result.unwrap_err().is::<CapabilityDenied>());
+
+        // sleep открыл бы окно; без graphics программа остаётся консольной
+        let headless = Capabilities { graphics: false, ..Capabilities::all() };
+        let (result, output) = run(code, headless);
+        assert!(result.is_ok());
+        assert_eq!(output, vec!["1"]);
+    }
+
+    #[test]
+    fn test_sandboxed_script() {
+        let source = r#"
+            fn save(score: i32) -> i32 {
+                store_set("score", "1");
+                return score;
+            }
+
+            fn double(x: i32) -> i32 {
+                return x * 2;
+            }
+        "#;
+        let mut script = CompiledScript::compile_with_capabilities(source, Capabilities::none()).unwrap();
+        let value = script.call("double", &[aetos::interpreter::RuntimeValue::Integer(4)]).unwrap();
+        assert!(matches!(value, aetos::interpreter::RuntimeValue::Integer(8)));
+        match script.call("save", &[aetos::interpreter::RuntimeValue::Integer(1)]) {
+            Err(ScriptError::Runtime(message)) => assert!(message.contains("`fs` capability"), "{}", message),
+            other => panic!("expected a capability error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
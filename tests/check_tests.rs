@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use aetos::check::{check_file, check_files, collect_sources};
+
+    #[test]
+    fn test_collect_sources_from_directory_and_glob() {
+        let all = collect_sources("examples", true).unwrap();
+        assert!(all.iter().all(|path| path.extension().unwrap() == "aetos"));
+        
+        let simple = collect_sources("examples/simple*.aetos", false).unwrap();
+        assert!(!simple.is_empty());
+        assert!(simple.len() < all.len());
+        assert!(simple.iter().all(|path| {
+            path.file_name().unwrap().to_str().unwrap().starts_with("simple")
+        }));
+    }
+
+    #[test]
+    fn test_check_files_reports_in_order() {
+        let files = collect_sources("examples", false).unwrap();
+        let reports = check_files(&files);
+        assert_eq!(reports.len(), files.len());
+        for (report, path) in reports.iter().zip(&files) {
+            assert_eq!(&report.path, path);
+        }
+        
+        let hello = reports.iter().find(|r| r.path.ends_with("hello.aetos")).unwrap();
+        assert!(hello.passed());
+    }
+
+    #[test]
+    fn test_parse_error_reports_byte_in_windows_file() {
+        let path = std::env::temp_dir().join(format!("aetos-check-crlf-{}.aetos", std::process::id()));
+        std::fs::write(&path, "\u{feff}fn main() -> i32 {\r\n    let x: i32 = 1 $ 2;\r\n    return 0;\r\n}\r\n").unwrap();
+        let report = check_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // Строка и колонка - как в тексте, байт - как в файле с BOM и `\r\n`
+        let error = report.error.unwrap();
+        assert!(error.contains("line 2, column 20 (byte 42)"), "{}", error);
+    }
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::process::{Command, Output};
+
+    fn write_program(name: &str, code: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("aetos-cli-{}-{}.aetos", name, std::process::id()));
+        std::fs::write(&path, code).unwrap();
+        path
+    }
+
+    fn aetosc(args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_aetosc")).args(args).output().unwrap()
+    }
+
+    #[test]
+    fn test_fast_math_conflicts_with_strict_fp() {
+        let path = write_program("fast-math", "fn main() {\n    print(sin(1.0));\n}\n");
+        let output = aetosc(&["run", path.to_str().unwrap(), "--strict-fp", "--fast-math"]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("cannot be used with"), "{}", stderr);
+        assert!(stderr.contains("--strict-fp"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_type_errors_are_printed_one_per_line_with_function() {
+        let path = write_program("type-errors", "fn helper() -> i32 {\n    return missing;\n}\n\nfn main() -> i32 {\n    let x: i32 = true;\n    return 0;\n}\n");
+        for command in ["check", "run", "compile"] {
+            let output = aetosc(&[command, path.to_str().unwrap()]);
+            assert!(!output.status.success(), "{}", command);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let errors: Vec<&str> = stderr.lines().filter(|line| line.starts_with("Error:")).collect();
+            assert_eq!(errors, vec![
+                "Error: in fn helper: Undefined variable: missing",
+                "Error: in fn main: Type mismatch: expected i32, found bool",
+            ], "{}", command);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
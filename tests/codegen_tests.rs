@@ -3,6 +3,44 @@ mod tests {
     use aetos::ast::*;
     use aetos::parser::Parser;
 
+    /// Текст модуля переводится в двоичный WASM и проходит валидатор
+    fn validate(wat: &str) -> Vec<u8> {
+        let binary = wat::parse_str(wat).unwrap_or_else(|error| panic!("{}\n{}", error, wat));
+        wasmparser::validate(&binary).unwrap_or_else(|error| panic!("{}\n{}", error, wat));
+        binary
+    }
+
+    /// Запускает `main` модуля в wasmi: результат и строки, напечатанные
+    /// импортами print из модуля env
+    fn run(wat: &str) -> (i32, Vec<String>) {
+        use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+
+        type Output = Vec<String>;
+
+        let binary = validate(wat);
+        let engine = Engine::default();
+        let module = Module::new(&engine, &binary).unwrap();
+        let mut store = Store::new(&engine, Output::new());
+        let mut linker = Linker::<Output>::new(&engine);
+        linker.func_wrap("env", "print_i32", |mut caller: Caller<'_, Output>, value: i32| {
+            caller.data_mut().push(value.to_string());
+        }).unwrap();
+        linker.func_wrap("env", "print_f32", |mut caller: Caller<'_, Output>, value: f32| {
+            caller.data_mut().push(value.to_string());
+        }).unwrap();
+        linker.func_wrap("env", "print_string", |mut caller: Caller<'_, Output>, pointer: i32| {
+            let memory = caller.get_export("memory").and_then(Extern::into_memory).unwrap();
+            let bytes = &memory.data(&caller)[pointer as usize..];
+            let end = bytes.iter().position(|&byte| byte == 0).unwrap();
+            let text = String::from_utf8(bytes[..end].to_vec()).unwrap();
+            caller.data_mut().push(text);
+        }).unwrap();
+        let instance = linker.instantiate(&mut store, &module).unwrap().start(&mut store).unwrap();
+        let main = instance.get_typed_func::<(), i32>(&store, "main").unwrap();
+        let result = main.call(&mut store, ()).unwrap_or_else(|error| panic!("{}\n{}", error, wat));
+        (result, store.into_data())
+    }
+
     #[test]
     fn test_basic_parsing() {
         let code = r#"
@@ -29,4 +67,873 @@ mod tests {
         assert_eq!(program.functions.len(), 1);
         assert_eq!(program.functions[0].name, "add");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_library_exports() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::codegen::CrateType;
+
+        let code = r#"
+            @export
+            fn add(a: i32, b: i32) -> i32 {
+                return a + b;
+            }
+
+            @no_mangle
+            fn mul(a: i32, b: i32) -> i32 {
+                return a * b;
+            }
+
+            fn main() -> i32 {
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        assert_eq!(program.functions[0].attributes, vec![Attribute::Export]);
+        
+        let wat = WasmGenerator::with_crate_type(CrateType::Lib, "math").generate(&program).unwrap();
+        assert!(wat.contains("(export \"_AN4math3add\" (func $add))"));
+        assert!(wat.contains("(export \"mul\" (func $mul))"));
+        assert!(!wat.contains("(export \"main\""));
+        validate(&wat);
+    }
+
+    #[test]
+    fn test_export_requires_scalar_signature() {
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            @export
+            fn greet(name: string) -> i32 {
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        assert!(TypeChecker::new().check_program(&program).is_err());
+        assert!(Parser::new("@inline fn f() -> i32 { return 0; }").parse_program().is_err());
+    }
+
+    #[test]
+    fn test_array_literal_and_index() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn main() -> i32 {
+                let values: [f32] = [1.5, 2.5];
+                let grid: [[i32]] = [[1, 2]];
+                let x: f32 = values[1];
+                return grid[0][1];
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        match &program.functions[0].body[0] {
+            Statement::VariableDeclaration { var_type, value, .. } => {
+                assert_eq!(var_type, &Type::Array(Box::new(Type::F32)));
+                assert_eq!(var_type.to_string(), "[f32]");
+                assert!(matches!(value, Expression::ArrayLiteral { elements } if elements.len() == 2));
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+        
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("(global $__heap_ptr (mut i32) (i32.const 1024))"));
+        assert!(wat.contains("f32.store offset=8"));
+        assert!(wat.contains("f32.load offset=4"));
+        assert!(wat.contains("i32.load offset=4"));
+        assert!(wat.contains("unreachable"));
+        assert_eq!(run(&wat), (2, Vec::new()));
+
+        // Проверенная программа: тайпчекер записывает литералы как [T; N]
+        let typed = aetos::typecheck::TypeChecker::new().check_program(&program).unwrap();
+        let wat = WasmGenerator::new().generate_typed(&typed).unwrap();
+        assert!(wat.contains("f32.store offset=8"), "{}", wat);
+        assert_eq!(run(&wat), (2, Vec::new()));
+    }
+
+    #[test]
+    fn test_match_on_enum() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            enum Light { Red, Yellow, Green }
+
+            fn main() -> i32 {
+                let light: Light = Light::Green;
+                return match light {
+                    Light::Red => 1,
+                    Light::Green => 3,
+                    _ => 2,
+                };
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        let variants: Vec<&str> = program.enums[0].variants.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(variants, vec!["Red", "Yellow", "Green"]);
+        assert!(!program.enums[0].has_payload());
+        TypeChecker::new().check_program(&program).unwrap();
+        
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("i32.const 2\nlocal.set $light"));
+        assert!(wat.contains("i32.eq\nif (result i32)"));
+        assert!(!wat.contains("unreachable"));
+    }
+
+    #[test]
+    fn test_enum_payload_lowering() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            enum Shape { Circle(i32), Rect(i32, f64), Empty }
+
+            fn width(shape: Shape) -> i32 {
+                return match shape {
+                    Shape::Circle(r) => r * 2,
+                    Shape::Rect(w, _) => w,
+                    Shape::Empty => 0,
+                };
+            }
+
+            fn main() -> i32 {
+                return width(Shape::Rect(3, 1.5));
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        assert_eq!(program.enums[0].variants[1].fields, vec![Type::I32, Type::F64]);
+        TypeChecker::new().check_program(&program).unwrap();
+        
+        // Вариант с данными - блок в куче: тег, затем поля
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("i32.const 16\ni32.add\nglobal.set $__heap_ptr"));
+        assert!(wat.contains("i32.const 1\ni32.store\n"));
+        assert!(wat.contains("f64.store offset=8"));
+        assert!(wat.contains("i32.load\ni32.const 0\ni32.eq"));
+        assert!(wat.contains("i32.load offset=4\nlocal.set $r"));
+        assert_eq!(run(&wat), (3, Vec::new()));
+    }
+
+    #[test]
+    fn test_method_call_lowering() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            enum Direction { Left, Right }
+
+            impl Direction {
+                fn first() -> Direction {
+                    return Direction::Left;
+                }
+
+                fn step(self, speed: i32) -> i32 {
+                    return match self {
+                        Direction::Left => 0 - speed,
+                        Direction::Right => speed,
+                    };
+                }
+            }
+
+            fn main() -> i32 {
+                let direction: Direction = Direction::first();
+                return direction.step(3);
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let names: Vec<&str> = program.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["Direction::first", "Direction::step", "main"]);
+        TypeChecker::new().check_program(&program).unwrap();
+
+        // Метод - обычная функция, получатель передаётся первым аргументом
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("call $Direction::first\n"));
+        assert!(wat.contains("local.get $direction\ni32.const 3\ncall $Direction::step\n"));
+    }
+
+    #[test]
+    fn test_constants_are_inlined() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            const SIZE: i32 = 8 * 4;
+            const SCALE: f32 = 2;
+
+            fn area() -> i32 {
+                return SIZE * SIZE;
+            }
+
+            fn scale(x: f32) -> f32 {
+                return x * SCALE;
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("i32.const 32\ni32.const 32\ni32.mul"));
+        assert!(wat.contains("f32.const 2"));
+        assert!(!wat.contains("local.get $SIZE"));
+    }
+
+    #[test]
+    fn test_trailing_expression_is_function_value() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn add(a: i32, b: i32) -> i32 {
+                a + b
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        assert!(matches!(
+            program.functions[0].body.as_slice(),
+            [Statement::Return { value: Expression::BinaryExpression { operator: BinaryOperator::Add, .. }, .. }]
+        ));
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("i32.add\nreturn"));
+    }
+
+    #[test]
+    fn test_labeled_break_and_continue_lowering() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn search(limit: i32) -> i32 {
+                let mut hits: i32 = 0;
+                'rows: for y in 0..limit {
+                    for x in 0..limit {
+                        if (x == y) {
+                            continue;
+                        }
+                        if x + y > 5 {
+                            break 'rows;
+                        }
+                        hits = hits + 1;
+                    }
+                }
+                return hits;
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        // continue внутреннего for ведёт к увеличению счётчика, break - из внешнего цикла
+        assert!(wat.contains("block $continue_1"));
+        assert!(wat.contains("br $continue_1\n"));
+        assert!(wat.contains("br $break_0\n"));
+        validate(&wat);
+    }
+
+    #[test]
+    fn test_loops_and_labeled_jumps_run() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            fn search(limit: i32) -> i32 {
+                let mut hits: i32 = 0;
+                'rows: for y in 0..limit {
+                    for x in 0..limit {
+                        if (x == y) {
+                            continue;
+                        }
+                        if (x + y > 5) {
+                            break 'rows;
+                        }
+                        hits = hits + 1;
+                    }
+                }
+                return hits;
+            }
+
+            fn first_power(limit: i32) -> i32 {
+                let mut value: i32 = 1;
+                loop {
+                    if (value > limit) {
+                        return value;
+                    }
+                    value = value * 2;
+                }
+            }
+
+            fn count_pairs() -> i32 {
+                let mut pairs: i32 = 0;
+                let mut i: i32 = 0;
+                'outer: loop {
+                    i = i + 1;
+                    let mut j: i32 = 0;
+                    while (j < 10) {
+                        j = j + 1;
+                        if (j > i) {
+                            continue 'outer;
+                        }
+                        if (i == 4) {
+                            break 'outer;
+                        }
+                        pairs = pairs + 1;
+                    }
+                }
+                return pairs;
+            }
+
+            fn main() -> i32 {
+                print(search(5));
+                print(first_power(20));
+                return count_pairs();
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let wat = WasmGenerator::new().generate_typed(&typed).unwrap();
+        // Метки циклов проходят валидатор, переходы ведут к нужному циклу
+        assert_eq!(run(&wat), (6, vec!["11".to_string(), "32".to_string()]));
+    }
+
+    #[test]
+    fn test_compound_assignment_desugars() {
+        let code = r#"
+            fn main() -> void {
+                let mut x: i32 = 1;
+                x *= 2 + 3;
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        // Правая часть целиком - правый операнд: x * (2 + 3)
+        match &program.functions[0].body[1] {
+            Statement::Assignment { name, value: Expression::BinaryExpression { left, operator, right }, .. } => {
+                assert_eq!(name, "x");
+                assert_eq!(**left, Expression::Variable("x".to_string()));
+                assert_eq!(*operator, BinaryOperator::Multiply);
+                assert!(matches!(**right, Expression::BinaryExpression { operator: BinaryOperator::Add, .. }));
+            }
+            other => panic!("expected assignment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_modulo_lowering() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn wrap(x: i32, width: i32) -> i32 {
+                x % width
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("local.get $width\ni32.rem_s\nreturn"));
+    }
+
+    #[test]
+    fn test_bitwise_lowering() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn toggle(register: i32, pin: i32) -> i32 {
+                register ^ 1 << pin
+            }
+
+            fn high_bits(register: i32) -> i32 {
+                register >> 4 & 15
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("i32.const 1\nlocal.get $pin\ni32.shl\ni32.xor\nreturn"), "{}", wat);
+        assert!(wat.contains("i32.const 4\ni32.shr_s\ni32.const 15\ni32.and\nreturn"), "{}", wat);
+    }
+
+    #[test]
+    fn test_loop_lowering() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn first_above(limit: i32) -> i32 {
+                let mut value: i32 = 1;
+                loop {
+                    if (value > limit) {
+                        return value;
+                    }
+                    value = value * 2;
+                }
+            }
+
+            fn count(limit: i32) -> i32 {
+                let mut n: i32 = 0;
+                loop {
+                    n = n + 1;
+                    if (n == limit) {
+                        break;
+                    }
+                }
+                return n;
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        let first_above = wat.split("(func $first_above (param $limit i32) (result i32)\n").nth(1).unwrap().split("(func $count").next().unwrap();
+        let count = wat.split("(func $count (param $limit i32) (result i32)\n").nth(1).unwrap();
+        // Без проверки условия в начале; без break после цикла - unreachable
+        assert!(first_above.contains("loop $continue_0\nlocal.get $value"), "{}", first_above);
+        assert!(first_above.contains("br $continue_0\nend\nend\nunreachable\n"), "{}", first_above);
+        assert!(count.contains("br $break_0\n"), "{}", count);
+        assert!(!count.contains("unreachable"), "{}", count);
+        validate(&wat);
+    }
+
+    #[test]
+    fn test_locals_are_declared_before_body() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            enum Shape { Circle(i32), Rect(i32, f64) }
+
+            fn measure(shape: Shape, n: i32) -> i32 {
+                let mut total: i32 = 0;
+                for i in 0..n {
+                    let scale: f64 = 1.5;
+                    total = total + i;
+                }
+                return total + match shape {
+                    Shape::Circle(r) => r,
+                    Shape::Rect(w, _) => w,
+                };
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        let body = wat.split("(func $measure (param $shape i32) (param $n i32) (result i32)\n").nth(1).unwrap();
+        let header: Vec<&str> = body.lines().map(str::trim).take_while(|line| line.starts_with("(local")).collect();
+        // Параметры объявлены в (param), счётчик и граница for - i32, $__tmp - временные
+        assert_eq!(header, vec![
+            "(local $total i32)",
+            "(local $i i32)",
+            "(local $scale f64)",
+            "(local $r i32)",
+            "(local $w i32)",
+            "(local $__tmp0 i32)",
+            "(local $__tmp1 i32)",
+        ]);
+    }
+
+    #[test]
+    fn test_shadowed_locals_are_renamed() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            fn main() -> i32 {
+                let x: i32 = 40;
+                if (x > 1) {
+                    let x: f32 = 1.5;
+                    print(x);
+                }
+                for i in 0..2 {
+                    let x: i32 = x + i;
+                    print(x);
+                }
+                if (x > 0) {
+                    let x: i32 = x + 2;
+                    return x;
+                }
+                return 0;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let wat = WasmGenerator::new().generate_typed(&typed).unwrap();
+        // Одноимённые переменные разных типов - разные локальные WAT
+        assert!(wat.contains("(local $x i32)\n    (local $x_1 f32)\n    (local $i i32)\n    (local $x_2 i32)\n    (local $x_3 i32)\n"), "{}", wat);
+        // Значение затеняющей переменной читает внешнюю
+        assert!(wat.contains("local.get $x\nlocal.get $i\ni32.add\nlocal.set $x_2\n"), "{}", wat);
+        let expected: Vec<String> = ["1.5", "40", "41"].iter().map(|line| line.to_string()).collect();
+        assert_eq!(run(&wat), (42, expected));
+    }
+
+    #[test]
+    fn test_backend_registry() {
+        use aetos::codegen::{Backend, CodeGenError, CrateType};
+
+        for name in Backend::NAMES {
+            assert_eq!(Backend::parse(name).unwrap().to_string(), name);
+        }
+        assert_eq!(Backend::parse("jvm"), None);
+        assert_eq!(Backend::default(), Backend::Wasm);
+
+        let generator = Backend::Wasm.generator(CrateType::Bin, "main", false).unwrap();
+        assert_eq!(generator.extension(), "wat");
+        match Backend::Wasm.generator(CrateType::Bin, "main", true) {
+            Err(CodeGenError::Unsupported { feature }) => assert_eq!(feature, "LTO"),
+            _ => panic!("the wasm backend should reject --lto"),
+        }
+
+        for backend in [Backend::Llvm, Backend::Embedded] {
+            match backend.generator(CrateType::Bin, "main", false) {
+                #[cfg(feature = "llvm")]
+                Ok(_) => {}
+                #[cfg(not(feature = "llvm"))]
+                Err(CodeGenError::Unavailable { backend: unavailable }) => assert_eq!(unavailable, backend),
+                _ => panic!("unexpected result for the {} backend", backend),
+            }
+        }
+        #[cfg(feature = "llvm")]
+        {
+            assert_eq!(Backend::Llvm.generator(CrateType::Bin, "main", false).unwrap().extension(), "o");
+            assert_eq!(Backend::Embedded.generator(CrateType::Bin, "main", false).unwrap().extension(), "s");
+        }
+    }
+
+    #[test]
+    fn test_builtins_are_imported_and_module_runs() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            fn sub(a: i32, b: i32) -> i32 {
+                return a - b;
+            }
+
+            fn main() -> i32 {
+                print("diff");
+                print(sub(10, 3));
+                return sub(100, 1);
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let wat = WasmGenerator::new().generate_typed(&typed).unwrap();
+        // Импорт на каждый набор типов аргументов print; у void-вызова нет drop
+        assert!(wat.contains("(import \"env\" \"print_string\" (func $env.print_string (param i32)))"), "{}", wat);
+        assert!(wat.contains("(import \"env\" \"print_i32\" (func $env.print_i32 (param i32)))"), "{}", wat);
+        assert!(!wat.contains("drop"), "{}", wat);
+        // Аргументы передаются в порядке объявления параметров
+        assert_eq!(run(&wat), (99, vec!["diff".to_string(), "7".to_string()]));
+    }
+
+    #[test]
+    fn test_unsupported_features_are_errors() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::codegen::CodeGenError;
+
+        let code = r#"
+            struct Point { x: i32, y: i32 }
+
+            fn main() -> i32 {
+                let p: Point = Point { x: 1, y: 2 };
+                return 0;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        match WasmGenerator::new().generate(&program) {
+            Err(CodeGenError::Unsupported { feature }) => assert_eq!(feature, "Struct initialization"),
+            other => panic!("expected an unsupported feature error, got {:?}", other),
+        }
+
+        // Встроенные Option и Result - ошибка, а не паника
+        let code = r#"
+            fn unwrap_or_zero(value: Option<i32>) -> i32 {
+                match value {
+                    Some(number) => number,
+                    None => 0,
+                }
+            }
+
+            fn main() -> i32 {
+                return unwrap_or_zero(Some(5));
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = aetos::typecheck::TypeChecker::new().check_program(&program).unwrap();
+        match WasmGenerator::new().generate_typed(&typed) {
+            Err(CodeGenError::Unsupported { feature }) => assert_eq!(feature, "Option and Result"),
+            other => panic!("expected an unsupported feature error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_wasm_backend_writes_module() {
+        use aetos::codegen::{Backend, CrateType};
+
+        let program = Parser::new("fn main() -> void { print(1 + 2); }").parse_program().unwrap();
+        let typed = aetos::typecheck::TypeChecker::new().check_program(&program).unwrap();
+        let path = std::env::temp_dir().join(format!("aetos_backend_{}.wat", std::process::id()));
+        let mut generator = Backend::Wasm.generator(CrateType::Bin, "main", false).unwrap();
+        generator.generate(&typed, &path).unwrap();
+        let module = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(module.starts_with("(module"), "{}", module);
+        validate(&module);
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn test_llvm_backends_write_output() {
+        use aetos::codegen::{Backend, CrateType};
+
+        let code = r#"
+            fn blink(pin: i32, times: i32) -> void {
+                for i in 0..times {
+                    gpio_toggle(pin);
+                    delay(100 * i);
+                }
+            }
+
+            fn main() -> void {
+                blink(13, 3);
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = aetos::typecheck::TypeChecker::new().check_program(&program).unwrap();
+
+        let path = std::env::temp_dir().join(format!("aetos_backend_{}.s", std::process::id()));
+        Backend::Embedded.generator(CrateType::Bin, "main", false).unwrap().generate(&typed, &path).unwrap();
+        let assembly = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(assembly.contains("blink:") && assembly.contains("bl\tgpio_toggle"), "{}", assembly);
+
+        // Без функций платы программа собирается и для текущей машины
+        let program = Parser::new("fn main() -> i32 { let x: i32 = 40; return x + 2; }").parse_program().unwrap();
+        let typed = aetos::typecheck::TypeChecker::new().check_program(&program).unwrap();
+        let path = std::env::temp_dir().join(format!("aetos_backend_{}.o", std::process::id()));
+        Backend::Llvm.generator(CrateType::Bin, "main", false).unwrap().generate(&typed, &path).unwrap();
+        let object = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!object.is_empty());
+        #[cfg(target_os = "linux")]
+        assert!(object.starts_with(b"\x7fELF"));
+    }
+
+    #[test]
+    fn test_mixed_arithmetic_uses_checked_types() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            fn step(x: f32, dt: i32) -> bool {
+                return x * dt > 2;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let wat = WasmGenerator::new().generate_typed(&typed).unwrap();
+        // f32 умножается на приведённый i32, сравнение тоже в f32
+        assert!(wat.contains("local.get $dt\nf32.convert_i32_s\nf32.mul\n"), "{}", wat);
+        assert!(wat.contains("i32.const 2\nf32.convert_i32_s\nf32.gt\n"), "{}", wat);
+    }
+
+    #[test]
+    fn test_literals_use_expected_type() {
+        use aetos::codegen::wasm::WasmGenerator;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            fn main() -> void {
+                let x: i64 = 5;
+                let y: f64 = 1.5f64;
+                let z: i64 = 3000000000i64;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let wat = WasmGenerator::new().generate_typed(&typed).unwrap();
+        assert!(wat.contains("i64.const 5\nlocal.set $x"), "{}", wat);
+        assert!(wat.contains("f64.const 1.5\nlocal.set $y"), "{}", wat);
+        assert!(wat.contains("i64.const 3000000000\nlocal.set $z"), "{}", wat);
+    }
+
+    #[test]
+    fn test_string_literals_in_data_segment() {
+        use aetos::codegen::wasm::WasmGenerator;
+        
+        let code = r#"
+            fn main() -> i32 {
+                print_string("a\"b\\c\nd");
+                print_string("ж");
+                print_string("a\"b\\c\nd");
+                return 0;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        // Кавычки, обратная косая черта, перевод строки и UTF-8 - байтами
+        assert!(wat.contains(r#"(data (i32.const 16) "a\22b\5cc\0ad\00\d0\b6\00")"#), "{}", wat);
+        // Повторный литерал ссылается на те же байты
+        assert_eq!(wat.matches("i32.const 16\n").count(), 2);
+        assert!(wat.contains("i32.const 24\n"));
+        assert!(wat.contains("(global $__heap_ptr (mut i32) (i32.const 1024))"));
+        // Среда читает строку из экспортированной памяти до нулевого байта
+        let printed: Vec<String> = ["a\"b\\c\nd", "ж", "a\"b\\c\nd"].iter().map(|text| text.to_string()).collect();
+        assert_eq!(run(&wat), (0, printed));
+    }
+
+    #[test]
+    fn test_fixed_array_stack_frame() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn main() -> i32 {
+                let mut buffer: [i32; 4] = [0; 4];
+                buffer[1] = 5;
+                let copy: [i32; 4] = buffer;
+                buffer[1] = 7;
+                return copy[1] + buffer.len();
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        match &program.functions[0].body[0] {
+            Statement::VariableDeclaration { var_type, value, .. } => {
+                assert_eq!(var_type, &Type::FixedArray(Box::new(Type::I32), 4));
+                assert_eq!(var_type.to_string(), "[i32; 4]");
+                assert!(matches!(value, Expression::ArrayRepeat { count: 4, .. }));
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+        assert!(matches!(&program.functions[0].body[1], Statement::IndexAssignment { name, .. } if name == "buffer"));
+        
+        // Оба массива - в кадре функции на стеке (по 24 байта с выравниванием),
+        // куча не используется; перед return кадр освобождается
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("(global $__stack_ptr (mut i32) (i32.const 65536))"));
+        assert!(wat.contains("global.get $__stack_ptr\nlocal.tee $__frame_base\ni32.const 48\ni32.sub\nglobal.set $__stack_ptr\n"));
+        assert!(wat.contains("local.get $__frame_base\ni32.const 24\ni32.sub\nlocal.set $buffer\n"));
+        assert!(wat.contains("local.get $__frame_base\nglobal.set $__stack_ptr\nreturn\n"));
+        assert!(!wat.contains("global.set $__heap_ptr"));
+        // copy - копия: запись в buffer после присваивания её не меняет
+        assert_eq!(run(&wat), (9, Vec::new()));
+    }
+
+    #[test]
+    fn test_slice_copies_range() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn total(values: &[i32]) -> i32 {
+                return values[0] + values.len();
+            }
+
+            fn main() -> i32 {
+                let numbers: [i32] = [1, 2, 3, 4];
+                return total(numbers[1..]) + total(numbers);
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        assert_eq!(program.functions[0].params[0].param_type.to_string(), "&[i32]");
+        
+        // Срез - копия с раскладкой массива: длина, затем элементы
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("(param $values i32)"));
+        assert!(wat.contains("i32.gt_u\ni32.or\nif\nunreachable\nend\n"));
+        assert!(wat.contains("i32.load offset=4\ni32.store offset=4\n"));
+    }
+
+    #[test]
+    fn test_statics_are_globals() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            const START: i32 = 2 * 5;
+            static mut score: i32 = START;
+            static NAME: string = "Player";
+
+            fn add(points: i32) -> i32 {
+                score = score + points;
+                return score;
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("(global $score (mut i32) (i32.const 10))"));
+        assert!(wat.contains("(global $NAME i32 (i32.const 16))"));
+        assert!(wat.contains("global.get $score\nlocal.get $points\ni32.add\nglobal.set $score\n"));
+    }
+
+    #[test]
+    fn test_if_expression_lowering() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn clamp(x: i32) -> i32 {
+                return if x > 10 { 10 } else if x < 0 { 0 } else { x };
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(wat.contains("i32.gt_s\nif (result i32)\ni32.const 10\nelse\n"));
+        assert!(wat.contains("i32.lt_s\nif (result i32)\ni32.const 0\nelse\nlocal.get $x\nend\nend\n"));
+    }
+
+    #[test]
+    fn test_defer_runs_before_every_exit() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn tick(n: i32) -> i32 {
+                return n;
+            }
+
+            fn count(limit: i32) -> i32 {
+                defer tick(1);
+                let mut total: i32 = 0;
+                while (total < limit) {
+                    defer tick(2);
+                    if (total == 5) {
+                        break;
+                    }
+                    total = total + 1;
+                }
+                return total;
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        // break выполняет defer тела цикла, но не функции
+        assert!(wat.contains("if\ni32.const 2\ncall $tick\ndrop\nbr $break_0\nend\n"));
+        // конец итерации - тоже
+        assert!(wat.contains("i32.const 2\ncall $tick\ndrop\nbr $continue_0\n"));
+        // return вычисляет значение до defer функции
+        assert!(wat.contains("local.get $total\nlocal.set $__tmp0\ni32.const 1\ncall $tick\ndrop\nlocal.get $__tmp0\nreturn\n"));
+    }
+
+    #[test]
+    fn test_range_builtins_are_inlined() {
+        use aetos::codegen::wasm::WasmGenerator;
+
+        let code = r#"
+            fn cell(x: i32) -> i32 {
+                return wrap(clamp(x, 0, 99), 0, 10);
+            }
+
+            fn hue(x: f32) -> f32 {
+                return map_range(x, 0, 800, 0.0, 1.0);
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        let wat = WasmGenerator::new().generate(&program).unwrap();
+        assert!(!wat.contains("call $clamp") && !wat.contains("call $wrap") && !wat.contains("call $map_range"));
+        // Целые clamp и wrap: select вместо ветвлений, остаток поправляется до неотрицательного
+        assert!(wat.contains("i32.gt_s\nselect\n"));
+        assert!(wat.contains("i32.rem_s\n"));
+        assert!(wat.contains("(local $__tmp0 i32)"));
+        // С аргументом f32 всё считается в f32, целые аргументы преобразуются
+        assert!(wat.contains("i32.const 800\nf32.convert_i32_s\nlocal.set $__tmp2\n"));
+        assert!(wat.contains("f32.mul\n") && wat.contains("f32.div\nf32.add\n"));
+        assert!(wat.contains("(local $__tmp0 f32)"));
+    }
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod tests {
+    use aetos::color::{average_color, contrast_ratio, palette, relative_luminance, PALETTE, WCAG_AA_TEXT};
+
+    #[test]
+    fn test_contrast_ratio_matches_wcag() {
+        let (black, white) = ([0, 0, 0], [255, 255, 255]);
+        assert!((relative_luminance(white) - 1.0).abs() < 1e-6);
+        assert!((contrast_ratio(black, white) - 21.0).abs() < 1e-4);
+        assert_eq!(contrast_ratio(white, black), contrast_ratio(black, white));
+        assert_eq!(contrast_ratio(white, white), 1.0);
+        // #777777 на белом - известный пограничный случай: 4.48:1
+        let ratio = contrast_ratio([0x77, 0x77, 0x77], white);
+        assert!((ratio - 4.48).abs() < 0.01 && ratio < WCAG_AA_TEXT, "{}", ratio);
+    }
+
+    #[test]
+    fn test_palette_is_named_and_distinct() {
+        assert_eq!(palette("vermillion"), Some([213, 94, 0]));
+        assert_eq!(palette("no_such_color"), None);
+        for (i, (_, a)) in PALETTE.iter().enumerate() {
+            for (_, b) in &PALETTE[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_average_color_clips_to_frame() {
+        let (width, height) = (4, 2);
+        let mut buffer = vec![0x000000; width * height];
+        buffer[3] = 0xFFFFFF;
+        buffer[7] = 0xFFFFFF;
+        assert_eq!(average_color(&buffer, width, height, 2, 0, 10, 10), [127, 127, 127]);
+        assert_eq!(average_color(&buffer, width, height, 10, 10, 2, 2), [0, 0, 0]);
+    }
+}
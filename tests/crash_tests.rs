@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use aetos::ast::Position;
+    use aetos::crash::{self, CrashReport, StackFrame};
+    use aetos::interpreter::Interpreter;
+    use aetos::parser::Parser;
+
+    #[test]
+    fn test_report_captures_stack_statement_and_output() {
+        let code = r#"
+            fn divide(a: i32, b: i32) -> i32 {
+                print("dividing", a, b);
+                return a / b;
+            }
+
+            fn main() -> i32 {
+                print("start");
+                let y: i32 = divide(5, 0);
+                return y;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let error = interpreter.interpret_program(&program, 0, 0, "test").unwrap_err();
+        
+        let report = interpreter.crash_report(&error.to_string(), "game.aetos", code);
+        assert_eq!(report.error, "Division by zero");
+        // В каждом кадре - выполнявшийся оператор: в main - вызов divide
+        assert_eq!(report.call_stack, vec![
+            StackFrame { function: "main".to_string(), line: 9, column: 17 },
+            StackFrame { function: "divide".to_string(), line: 4, column: 17 },
+        ]);
+        // Самый внутренний оператор, а не вызов divide в main
+        assert_eq!(report.statement, Some((Position { line: 4, column: 17 }, "return a / b;".to_string())));
+        assert_eq!(report.recent_output, vec!["start", "dividing 5 0"]);
+    }
+
+    #[test]
+    fn test_frame_returns_to_enclosing_statement() {
+        let code = r#"
+            fn main() -> i32 {
+                let mut i: i32 = 0;
+                while (10 / (2 - i) > 0) {
+                    i = i + 1;
+                }
+                return i;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let mut interpreter = Interpreter::new();
+        let error = interpreter.interpret_program(&program, 0, 0, "test").unwrap_err();
+
+        // Условие while падает после тела: позиция - цикл, а не `i = i + 1`
+        let report = interpreter.crash_report(&error.to_string(), "loop.aetos", code);
+        assert_eq!(report.call_stack, vec![StackFrame { function: "main".to_string(), line: 4, column: 17 }]);
+        assert_eq!(report.statement, Some((Position { line: 4, column: 17 }, "while (10 / (2 - i) > 0) {".to_string())));
+    }
+
+    #[test]
+    fn test_report_rendering_and_file() {
+        let mut report = CrashReport::new("panic: boom", "game.aetos", "a");
+        report.call_stack = vec![
+            StackFrame { function: "main".to_string(), line: 3, column: 5 },
+            StackFrame { function: "update".to_string(), line: 10, column: 9 },
+        ];
+        report.set_statement(Position { line: 10, column: 9 }, "fn update() {\n\n\n\n\n\n\n\n\n        draw(x);\n}");
+        let text = report.render();
+        assert!(text.starts_with("Aetos crash report\ncompiler: aetosc "));
+        assert!(text.contains("source: game.aetos (fnv1a af63dc4c8601ec8c)"), "{}", text);
+        assert!(text.contains("statement (line 10, column 9):\n    draw(x);\n"), "{}", text);
+        assert!(text.contains("    fn update (line 10, column 9)\n    fn main (line 3, column 5)\n"), "{}", text);
+        assert!(text.contains("recent output:\n    <none>"));
+
+        let dir = std::env::temp_dir().join(format!("aetos-crashes-{}", std::process::id()));
+        let path = report.write_to(&dir).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), text);
+        let _ = std::fs::remove_dir_all(dir);
+        
+        assert_eq!(crash::source_hash(""), 0xcbf29ce484222325);
+    }
+}
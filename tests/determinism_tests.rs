@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use aetos::determinism::{audit_determinism, DeterminismIssue};
+    use aetos::parser::Parser;
+
+    fn audit(code: &str) -> Vec<DeterminismIssue> {
+        let program = Parser::new(code).parse_program().unwrap();
+        audit_determinism(&program)
+    }
+
+    #[test]
+    fn test_reports_call_chain_to_nondeterministic_builtin() {
+        let code = r#"
+            fn jitter() -> f32 {
+                return get_time() * 0.01;
+            }
+
+            fn step_enemy(x: f32) -> f32 {
+                return x + jitter();
+            }
+
+            @deterministic
+            fn simulate(x: f32) -> f32 {
+                return step_enemy(x);
+            }
+
+            fn main() -> i32 {
+                print(simulate(1.0), get_time());
+                return 0;
+            }
+        "#;
+
+        let issues = audit(code);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].root, "simulate");
+        assert_eq!(issues[0].builtin, "get_time");
+        assert_eq!(issues[0].source, "time");
+        assert_eq!(issues[0].path, vec!["simulate", "step_enemy", "jitter"]);
+        assert_eq!(
+            issues[0].to_string(),
+            "@deterministic fn simulate calls get_time() (time) via simulate -> step_enemy -> jitter"
+        );
+    }
+
+    #[test]
+    fn test_direct_calls_and_recursion() {
+        let code = r#"
+            @deterministic
+            fn tick(n: i32) -> i32 {
+                if is_key_pressed(32) {
+                    return tick(n - 1);
+                }
+                store_set("n", "1");
+                return n;
+            }
+        "#;
+
+        let issues = audit(code);
+        let builtins: Vec<&str> = issues.iter().map(|issue| issue.builtin.as_str()).collect();
+        assert_eq!(builtins, vec!["is_key_pressed", "store_set"]);
+        assert_eq!(issues[1].to_string(), "@deterministic fn tick calls store_set() (file)");
+    }
+
+    #[test]
+    fn test_pure_and_unannotated_code_is_clean() {
+        let code = r#"
+            @deterministic
+            fn advance(x: i32, jump: bool) -> i32 {
+                if (jump) {
+                    return x + 1;
+                }
+                return x;
+            }
+
+            fn main() -> i32 {
+                print(advance(0, is_key_pressed(32)), get_time());
+                return 0;
+            }
+        "#;
+
+        assert!(audit(code).is_empty());
+    }
+}
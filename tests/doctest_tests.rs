@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use aetos::doctest::extract;
+    use aetos::parser::Parser;
+
+    const SOURCE: &str = r#"
+/// Складывает два числа.
+///
+/// ```
+/// print(add(1, 2)); // => 3
+/// ```
+///
+/// ```ignore
+/// not aetos code
+/// ```
+fn add(a: i32, b: i32) -> i32 {
+    return a + b;
+}
+
+fn main() -> i32 {
+    return 0;
+}
+"#;
+
+    #[test]
+    fn test_extract_doc_blocks() {
+        let tests = extract(SOURCE);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].item, "add");
+        assert_eq!(tests[0].line, 5);
+        assert_eq!(tests[0].expected_output, vec!["3".to_string()]);
+    }
+
+    #[test]
+    fn test_run_doc_example_against_module() {
+        let module = Parser::new(SOURCE).parse_program().unwrap();
+        let mut test = extract(SOURCE).remove(0);
+        assert!(test.run(&module).is_ok());
+        
+        test.expected_output = vec!["4".to_string()];
+        assert!(test.run(&module).is_err());
+    }
+
+    #[test]
+    fn test_doc_example_sees_consts_and_trait_impls() {
+        let source = r#"
+const SCALE: i32 = 3;
+
+trait Area {
+    fn area(self) -> i32;
+}
+
+struct Square {
+    side: i32,
+}
+
+/// ```
+/// let shape: Area = Square { side: 2 } as Area;
+/// print(shape.area()); // => 12
+/// ```
+impl Area for Square {
+    fn area(self) -> i32 {
+        return self.side * self.side * SCALE;
+    }
+}
+
+fn main() -> i32 {
+    return 0;
+}
+"#;
+        let module = Parser::new(source).parse_program().unwrap();
+        let tests = extract(source);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].run(&module), Ok(()));
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use aetos::edition::Edition;
+    use aetos::parser::Parser;
+
+    #[test]
+    fn test_pragma_selects_edition() {
+        let code = "//! edition: 2023\nfn main() -> i32 { return 0; }";
+        assert_eq!(Edition::from_pragma(code), Some(Edition::E2023));
+        assert_eq!(Edition::from_pragma("fn main() -> i32 { return 0; }"), None);
+    }
+
+    #[test]
+    fn test_new_keywords_are_identifiers_in_old_edition() {
+        let code = r#"
+            fn main() -> i32 {
+                let match: i32 = 1;
+                return match;
+            }
+        "#;
+        
+        assert!(Parser::with_edition(code, Edition::E2023).parse_program().is_ok());
+        assert!(Parser::with_edition(code, Edition::E2024).parse_program().is_err());
+    }
+
+    #[test]
+    fn test_file_without_edition_is_latest() {
+        let code = r#"
+            enum Light { Red, Green }
+
+            fn main() -> i32 {
+                let light: Light = Light::Green;
+                return match light { Light::Red => 0, Light::Green => 1 };
+            }
+        "#;
+        let dir = std::env::temp_dir().join(format!("aetos_edition_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let edition = Edition::detect(code, &dir.join("new.aetos"));
+        assert_eq!(edition, Edition::LATEST);
+        assert!(Parser::with_edition(code, edition).parse_program().is_ok());
+
+        // Старый код с trait в роли имени помечается редакцией в aetos.toml
+        let old = "fn main() -> i32 { let trait: i32 = 2; return trait; }";
+        std::fs::write(dir.join("aetos.toml"), "[package]\nname = \"old\"\nedition = \"2023\"\n").unwrap();
+        let edition = Edition::detect(old, &dir.join("old.aetos"));
+        assert_eq!(edition, Edition::E2023);
+        assert!(Parser::with_edition(old, edition).parse_program().is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spec_programs_parse_in_detected_edition() {
+        // CLI выбирает редакцию через detect, как и здесь
+        let dir = std::path::Path::new("tests/spec");
+        for name in ["match.aetos", "enums.aetos", "traits.aetos", "statics.aetos", "defer.aetos", "labeled_loops.aetos", "bitwise.aetos"] {
+            let path = dir.join(name);
+            let code = std::fs::read_to_string(&path).unwrap();
+            let edition = Edition::detect(&code, &path);
+            if let Err(error) = Parser::with_edition(&code, edition).parse_program() {
+                panic!("{}: {:?}", name, error);
+            }
+        }
+    }
+}
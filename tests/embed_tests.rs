@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use aetos::embed::{CompiledScript, ScriptError};
+    use aetos::interpreter::RuntimeValue;
+
+    const SCRIPT: &str = r#"
+        fn speed() -> f32 {
+            return 2.0;
+        }
+
+        fn update(x: f32, dt: f32) -> f32 {
+            return x + speed() * dt;
+        }
+    "#;
+
+    #[test]
+    fn test_call_script_function() {
+        let mut script = CompiledScript::compile(SCRIPT).unwrap();
+        assert!(script.has_function("update"));
+        let x = script.call("update", &[RuntimeValue::Float(1.0), RuntimeValue::Float(0.5)]).unwrap();
+        assert!(matches!(x, RuntimeValue::Float(v) if v == 2.0));
+        // Целые подходят к вещественным параметрам
+        let x = script.call("update", &[RuntimeValue::Integer(1), RuntimeValue::Integer(1)]).unwrap();
+        assert!(matches!(x, RuntimeValue::Float(v) if v == 3.0));
+    }
+
+    #[test]
+    fn test_call_checks_arguments() {
+        let mut script = CompiledScript::compile(SCRIPT).unwrap();
+        assert!(matches!(script.call("draw", &[]), Err(ScriptError::UndefinedFunction(_))));
+        assert!(matches!(
+            script.call("update", &[RuntimeValue::Float(1.0)]),
+            Err(ScriptError::ArgumentCount { expected: 2, found: 1, .. })
+        ));
+        assert!(matches!(
+            script.call("update", &[RuntimeValue::Boolean(true), RuntimeValue::Float(1.0)]),
+            Err(ScriptError::ArgumentType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_compile_reports_type_errors() {
+        let result = CompiledScript::compile("fn update() -> i32 { return true; }");
+        assert!(matches!(result, Err(ScriptError::Type(_))));
+        assert!(matches!(CompiledScript::compile("fn update( {"), Err(ScriptError::Parse(_))));
+    }
+
+    #[test]
+    fn test_hot_reload() {
+        let mut script = CompiledScript::compile(SCRIPT).unwrap();
+        assert!(!script.reload(SCRIPT).unwrap());
+
+        let faster = SCRIPT.replace("return 2.0;", "return 10.0;");
+        assert!(script.reload(&faster).unwrap());
+        let x = script.call("update", &[RuntimeValue::Float(0.0), RuntimeValue::Float(1.0)]).unwrap();
+        assert!(matches!(x, RuntimeValue::Float(v) if v == 10.0));
+
+        // Сломанная версия не заменяет работающую
+        assert!(script.reload("fn update() -> f32 { return x; }").is_err());
+        let x = script.call("update", &[RuntimeValue::Float(0.0), RuntimeValue::Float(1.0)]).unwrap();
+        assert!(matches!(x, RuntimeValue::Float(v) if v == 10.0));
+    }
+
+    #[test]
+    fn test_runtime_error() {
+        let mut script = CompiledScript::compile("fn div(a: i32, b: i32) -> i32 { return a / b; }").unwrap();
+        assert!(matches!(
+            script.call("div", &[RuntimeValue::Integer(1), RuntimeValue::Integer(0)]),
+            Err(ScriptError::Runtime(_))
+        ));
+    }
+
+    #[test]
+    fn test_hot_reload_keeps_statics() {
+        let source = r#"
+            static mut hits: i32 = 0;
+
+            fn hit() -> i32 {
+                hits = hits + 1;
+                return hits;
+            }
+        "#;
+        let mut script = CompiledScript::compile(source).unwrap();
+        script.call("hit", &[]).unwrap();
+        script.call("hit", &[]).unwrap();
+
+        // Переменная с тем же именем и типом переживает замену кода
+        assert!(script.reload(&source.replace("hits + 1", "hits + 10")).unwrap());
+        let hits = script.call("hit", &[]).unwrap();
+        assert!(matches!(hits, RuntimeValue::Integer(12)));
+
+        // Смена типа - новое начальное значение
+        let source = source.replace("hits: i32 = 0", "hits: i64 = 0").replace("-> i32", "-> i64");
+        assert!(script.reload(&source).unwrap());
+        assert!(matches!(script.call("hit", &[]).unwrap(), RuntimeValue::Long(1)));
+    }
+
+    #[test]
+    fn test_hot_reload_statics_initializer_new_and_removed() {
+        let source = r#"
+            static mut hits: i32 = 0;
+
+            fn hit() -> i32 {
+                hits = hits + 1;
+                return hits;
+            }
+        "#;
+        let mut script = CompiledScript::compile(source).unwrap();
+        script.call("hit", &[]).unwrap();
+
+        // Новый инициализатор не сбрасывает значение, новый static получает свой
+        let with_misses = source.replace("hits: i32 = 0", "hits: i32 = 100;\n            static mut misses: i32 = 7")
+            .replace("return hits;", "return hits * 1000 + misses;");
+        assert!(script.reload(&with_misses).unwrap());
+        assert!(matches!(script.call("hit", &[]).unwrap(), RuntimeValue::Integer(2007)));
+
+        // Удалённый static пропадает: вернувшись, он начинает заново
+        let without_hits = "static mut misses: i32 = 7;\nfn hit() -> i32 { return misses; }";
+        assert!(script.reload(without_hits).unwrap());
+        assert!(script.reload(&with_misses).unwrap());
+        assert!(matches!(script.call("hit", &[]).unwrap(), RuntimeValue::Integer(101007)));
+    }
+}
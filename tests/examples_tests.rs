@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod tests {
+    use aetos::edition::Edition;
+    use aetos::examples;
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+
+    #[test]
+    fn test_examples_are_embedded() {
+        let all = examples::all();
+        assert!(!all.is_empty());
+        assert!(all.windows(2).all(|pair| pair[0].name <= pair[1].name));
+    }
+
+    #[test]
+    fn test_find_example_by_name() {
+        let hello = examples::find("hello").unwrap();
+        assert!(hello.source.contains("fn main"));
+        assert!(!hello.is_graphics());
+        assert!(examples::find("hello.aetos").is_some());
+        assert!(examples::find("no_such_example").is_none());
+    }
+
+    #[test]
+    fn test_examples_type_check() {
+        for example in examples::all() {
+            // Как при запуске: редакция из прагмы, без неё - самая ранняя
+            let edition = Edition::from_pragma(example.source).unwrap_or_default();
+            let program = Parser::with_edition(example.source, edition).parse_program()
+                .unwrap_or_else(|error| panic!("example {}: {:?}", example.name, error));
+            if let Err(errors) = TypeChecker::new().check_program(&program) {
+                panic!("example {}: {:?}", example.name, errors);
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use aetos::fp;
+
+    #[test]
+    fn test_strict_trig_matches_libm() {
+        for i in -2000..=2000 {
+            let x = i as f32 * 0.01;
+            assert!((fp::sin(x) - x.sin()).abs() < 1e-6, "sin({})", x);
+            assert!((fp::cos(x) - x.cos()).abs() < 1e-6, "cos({})", x);
+        }
+        assert_eq!(fp::sin(0.0), 0.0);
+        assert_eq!(fp::cos(0.0), 1.0);
+        assert!(fp::sin(f32::NAN).is_nan());
+        assert!(fp::cos(f32::INFINITY).is_nan());
+    }
+
+    #[test]
+    fn test_fast_trig_matches_sin() {
+        for i in -20000..=20000 {
+            let x = i as f32 * 0.001;
+            assert!((fp::fast_sin(x) - fp::sin(x)).abs() < 1e-6, "fast_sin({})", x);
+            assert!((fp::fast_cos(x) - fp::cos(x)).abs() < 1e-6, "fast_cos({})", x);
+        }
+        assert_eq!(fp::fast_sin(0.0), 0.0);
+        assert_eq!(fp::fast_cos(0.0), 1.0);
+        assert!(fp::fast_sin(f32::NAN).is_nan());
+        assert!(fp::fast_cos(f32::INFINITY).is_nan());
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod tests {
+    use aetos::font;
+    use aetos::graphics_engine::{key_from_code, ConsoleLog, DebugHud, FrameControl, FrameStats, KeyboardState, TextInput};
+    use minifb::Key;
+
+    #[test]
+    fn test_console_log_keeps_last_lines() {
+        let mut console = ConsoleLog::new(3);
+        console.push("one");
+        console.push("two\nthree");
+        console.push("four");
+        
+        assert_eq!(console.lines().collect::<Vec<_>>(), vec!["two", "three", "four"]);
+        assert!(!console.visible);
+        console.toggle();
+        assert!(console.visible);
+    }
+
+    #[test]
+    fn test_overlay_draws_text_on_dimmed_panel() {
+        let (width, height) = (40, 20);
+        let mut buffer = vec![0xFFFFFF; width * height];
+        let mut console = ConsoleLog::new(2);
+        console.push("I");
+        console.draw(&mut buffer, width, height);
+        
+        // Панель одной строки затемнена, ниже кадр не тронут
+        let panel_height = font::LINE_HEIGHT + 4;
+        assert_eq!(buffer[(panel_height - 1) * width + width - 1], 0x3F3F3F);
+        assert_eq!(buffer[panel_height * width], 0xFFFFFF);
+        // Верхняя перекладина буквы I начинается на второй колонке глифа
+        assert_eq!(buffer[2 * width + 4], 0x3F3F3F);
+        assert_eq!(buffer[2 * width + 5], 0x7FFF7F);
+    }
+
+    #[test]
+    fn test_text_input_collects_chars_and_signals() {
+        let mut input = TextInput::default();
+        input.add_char('H' as u32);
+        input.add_char('i' as u32);
+        input.add_char(8); // управляющий символ приходит через key_down
+        input.key_down(Key::Backspace);
+        input.key_down(Key::LeftShift);
+        input.add_char('!' as u32);
+        input.key_down(Key::Enter);
+        
+        assert_eq!(input.take(), "Hi\u{8}!\n");
+        assert_eq!(input.take(), "");
+    }
+
+    #[test]
+    fn test_key_from_code_accepts_chars_and_arrow_codes() {
+        assert_eq!(key_from_code('w' as i32), Some(Key::W));
+        assert_eq!(key_from_code('W' as i32), Some(Key::W));
+        assert_eq!(key_from_code('7' as i32), Some(Key::Key7));
+        assert_eq!(key_from_code(' ' as i32), Some(Key::Space));
+        assert_eq!(key_from_code('\n' as i32), Some(Key::Enter));
+        assert_eq!(key_from_code(38), Some(Key::Up));
+        assert_eq!(key_from_code('!' as i32), None);
+    }
+
+    #[test]
+    fn test_keyboard_edges_fire_for_one_frame() {
+        let mut keys = KeyboardState::default();
+        let frames = [
+            vec![],
+            vec![Key::Space],
+            vec![Key::Space, Key::W],
+            vec![Key::W],
+            vec![],
+        ];
+        let mut log = Vec::new();
+        for down in &frames {
+            keys.update(|key| down.contains(&key));
+            log.push((
+                keys.is_down(Key::Space),
+                keys.just_pressed(Key::Space),
+                keys.just_released(Key::Space),
+            ));
+        }
+        assert_eq!(log, vec![
+            (false, false, false),
+            (true, true, false),
+            (true, false, false),
+            (false, false, true),
+            (false, false, false),
+        ]);
+        assert!(keys.just_released(Key::W));
+        // Неотслеживаемые клавиши всегда отпущены
+        keys.update(|_| true);
+        assert!(!keys.is_down(Key::F1));
+    }
+
+    #[test]
+    fn test_frame_control_pause_and_step() {
+        let mut control = FrameControl::default();
+        assert!(control.advance());
+
+        control.handle_keys(|key| key == Key::F5);
+        assert!(control.is_paused());
+        // Один кадр на заставку паузы, дальше цикл стоит
+        assert!(control.advance());
+        assert!(!control.advance());
+
+        control.handle_keys(|key| key == Key::F6);
+        assert!(control.advance());
+        assert!(!control.advance());
+
+        control.handle_keys(|key| key == Key::F7);
+        assert!(control.slow_motion());
+        control.handle_keys(|key| key == Key::F5);
+        assert!(!control.is_paused());
+        assert!(control.advance());
+        assert!(control.advance());
+
+        // Без паузы F6 ничего не копит
+        control.handle_keys(|key| key == Key::F6);
+        control.handle_keys(|key| key == Key::F5);
+        assert!(control.advance());
+        assert!(!control.advance());
+    }
+
+    #[test]
+    fn test_debug_hud_reports_frame_stats() {
+        let stats = FrameStats { frame_time: 0.02, steps: 1500, draw_calls: 42, entities: 7, allocations: 3 };
+        assert_eq!(stats.fps(), 50);
+        assert_eq!(stats.lines(), vec!["FPS 50", "frame 20.0 ms", "steps 1500", "draws 42", "entities 7", "allocs 3"]);
+        assert_eq!(FrameStats::default().fps(), 0);
+
+        // Панель в правом верхнем углу: левый край кадра не тронут
+        let (width, height) = (200, 80);
+        let mut buffer = vec![0xFFFFFF; width * height];
+        let mut hud = DebugHud::default();
+        hud.record(stats);
+        hud.draw(&mut buffer, width, height);
+        assert_eq!(buffer[0], 0xFFFFFF);
+        assert_eq!(buffer[width - 1], 0x3F3F3F);
+        assert_eq!(buffer[(height - 1) * width + width - 1], 0xFFFFFF);
+    }
+}
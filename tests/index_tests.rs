@@ -0,0 +1,128 @@
+#[cfg(test)]
+mod tests {
+    use aetos::edition::Edition;
+    use aetos::index::{Location, SymbolIndex, SymbolKind, INDEX_FILE};
+    use std::path::PathBuf;
+
+    const GAME: &str = r#"import shapes;
+
+static mut score: i32 = 0;
+
+fn main() -> i32 {
+    let p: Point = Point::new(1, 2);
+    score = score + p.length();
+    return area(3);
+}
+"#;
+
+    const SHAPES: &str = r#"pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl Point {
+    pub fn new(x: i32, y: i32) -> Point {
+        return Point { x: x, y: y };
+    }
+
+    pub fn length(self) -> i32 {
+        return self.x + self.y;
+    }
+}
+
+pub fn area(side: i32) -> i32 {
+    return side * side;
+}
+"#;
+
+    fn index() -> SymbolIndex {
+        let mut index = SymbolIndex::default();
+        index.index_source("game.aetos", GAME, Edition::LATEST);
+        index.index_source("lib/shapes.aetos", SHAPES, Edition::LATEST);
+        index
+    }
+
+    fn at(file: &str, line: usize, column: usize) -> Location {
+        Location { file: file.to_string(), line, column }
+    }
+
+    #[test]
+    fn test_definitions_and_kinds() {
+        let index = index();
+        let (location, symbol) = &index.definitions("Point::length")[0];
+        assert_eq!(*location, at("lib/shapes.aetos", 11, 12));
+        assert_eq!(symbol.kind, SymbolKind::Method);
+        assert_eq!(index.definitions("score")[0].1.kind, SymbolKind::Static);
+        assert_eq!(index.definitions("Point")[0].0, at("lib/shapes.aetos", 1, 12));
+
+        let names: Vec<&str> = index.workspace_symbols("POINT")
+            .iter()
+            .map(|(_, symbol)| symbol.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Point", "Point::new", "Point::length"]);
+    }
+
+    #[test]
+    fn test_generic_impl_methods() {
+        let mut index = SymbolIndex::default();
+        index.index_source("pair.aetos", r#"struct Pair<T, U> { left: T, right: U }
+impl<T, U> Pair<T, U> {
+    fn left(self) -> T { return self.left; }
+}
+fn main() -> i32 { let p: Pair<i32, f32> = Pair { left: 1, right: 2.0 }; return p.left(); }
+"#, Edition::LATEST);
+        assert_eq!(index.definitions("Pair")[0].0, at("pair.aetos", 1, 8));
+        let (location, symbol) = &index.definitions("Pair::left")[0];
+        assert_eq!(*location, at("pair.aetos", 3, 8));
+        assert_eq!(symbol.kind, SymbolKind::Method);
+    }
+
+    #[test]
+    fn test_go_to_definition_across_files() {
+        let index = index();
+        // area(3) в game.aetos, Point::new и p.length()
+        assert_eq!(index.definition_at("game.aetos", 8, 13), Some(at("lib/shapes.aetos", 16, 8)));
+        assert_eq!(index.definition_at("game.aetos", 6, 27), Some(at("lib/shapes.aetos", 7, 12)));
+        assert_eq!(index.definition_at("game.aetos", 7, 24), Some(at("lib/shapes.aetos", 11, 12)));
+        assert_eq!(index.definition_at("game.aetos", 7, 5), Some(at("game.aetos", 3, 12)));
+        // Локальная переменная не индексируется
+        assert_eq!(index.definition_at("game.aetos", 6, 9), None);
+
+        let references = index.references("lib/shapes.aetos", "Point::length");
+        assert_eq!(references, vec![at("game.aetos", 7, 23)]);
+        assert_eq!(index.references("game.aetos", "score").len(), 2);
+    }
+
+    #[test]
+    fn test_references_at_position() {
+        let index = index();
+        // С упоминания p.length() и с объявления - одни и те же ссылки
+        assert_eq!(index.references_at("game.aetos", 7, 24), vec![at("game.aetos", 7, 23)]);
+        assert_eq!(index.references_at("lib/shapes.aetos", 11, 12), vec![at("game.aetos", 7, 23)]);
+        assert!(index.references_at("game.aetos", 6, 9).is_empty());
+        assert_eq!(at("lib/shapes.aetos", 11, 12).to_string(), "lib/shapes.aetos:11:12");
+    }
+
+    #[test]
+    fn test_update_writes_and_refreshes_index() {
+        let dir = std::env::temp_dir().join(format!("aetos-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let game = dir.join("game.aetos");
+        std::fs::write(&game, "fn main() -> i32 {\n    return 0;\n}\n").unwrap();
+
+        let path = SymbolIndex::update(&dir, &[game.clone()]).unwrap();
+        assert_eq!(path, dir.join(INDEX_FILE));
+        let index = SymbolIndex::load(&dir);
+        assert!(index.is_fresh("game.aetos", "fn main() -> i32 {\n    return 0;\n}\n"));
+        assert_eq!(index.definitions("main").len(), 1);
+
+        // Удалённые файлы пропадают из индекса, повреждённый индекс строится заново
+        std::fs::remove_file(&game).unwrap();
+        SymbolIndex::update(&dir, &[] as &[PathBuf]).unwrap();
+        assert!(SymbolIndex::load(&dir).files.is_empty());
+        std::fs::write(dir.join(INDEX_FILE), "not json").unwrap();
+        assert!(SymbolIndex::load(&dir).files.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,442 @@
+#[cfg(test)]
+mod tests {
+    use aetos::interpreter::{Interpreter, RuntimeValue};
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    fn point(x: i32) -> RuntimeValue {
+        let mut fields = HashMap::new();
+        fields.insert("x".to_string(), RuntimeValue::Integer(x));
+        RuntimeValue::Struct(Rc::from("Point"), Rc::new(fields))
+    }
+
+    #[test]
+    fn test_struct_copy_on_write() {
+        let original = point(1);
+        let mut copy = original.clone();
+        
+        // Копия разделяет поля до первой записи
+        if let (RuntimeValue::Struct(_, a), RuntimeValue::Struct(_, b)) = (&original, &copy) {
+            assert!(Rc::ptr_eq(a, b));
+        }
+        
+        copy.set_field("x", RuntimeValue::Integer(2)).unwrap();
+        assert_eq!(original.to_string(), "Point { x: 1 }");
+        assert_eq!(copy.to_string(), "Point { x: 2 }");
+        assert!(copy.set_field("y", RuntimeValue::Integer(0)).is_err());
+    }
+
+    fn run(code: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        Interpreter::new().interpret_program(&program, 0, 0, "test")
+    }
+
+    #[test]
+    fn test_strict_fp_follows_ieee() {
+        let code = r#"
+            fn main() -> i32 {
+                let inf: f32 = 1.0 / 0.0;
+                let nan: f32 = 0.0 / 0.0;
+                // Любая неверная проверка приводит к делению на ноль
+                if (nan == nan) {
+                    let failed: i32 = 1 / 0;
+                }
+                if (nan < inf) {
+                    let failed: i32 = 1 / 0;
+                }
+                if (inf < 1000000.0) {
+                    let failed: i32 = 1 / 0;
+                }
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        assert!(Interpreter::new().interpret_program(&program, 0, 0, "test").is_err());
+        
+        let mut interpreter = Interpreter::new();
+        interpreter.set_strict_fp(true);
+        assert!(interpreter.interpret_program(&program, 0, 0, "test").is_ok());
+    }
+
+    #[test]
+    fn test_mem_stats_counts_shared_structs_once() {
+        let code = r#"
+            struct Particle {
+                x: i32,
+                y: i32,
+            }
+
+            fn main() -> i32 {
+                let a: Particle = Particle { x: 1, y: 2 };
+                let b: Particle = a;
+                print(mem_stats());
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        
+        let output = interpreter.take_output();
+        assert!(output[0].starts_with("live_values=4 live_structs=1 interned_strings=1 allocations=2 "));
+        assert_eq!(interpreter.mem_stats().allocations, 3);
+    }
+
+    #[test]
+    fn test_refs_share_state_and_values_copy() {
+        let code = r#"
+            struct Entity {
+                hp: i32,
+            }
+
+            fn damage(target: ref Entity) -> void {
+                target.hp = target.hp - 10;
+            }
+
+            fn main() -> i32 {
+                let player: ref Entity = ref(Entity { hp: 100 });
+                let alias: ref Entity = player;
+                damage(alias);
+                alias.hp = alias.hp - 5;
+                print(player.hp, player);
+
+                let value: Entity = Entity { hp: 100 };
+                let mut copy: Entity = value;
+                copy.hp = 1;
+                print(value.hp, copy.hp);
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        assert_eq!(interpreter.take_output(), vec!["85 ref Entity { hp: 85 }", "100 1"]);
+    }
+
+    #[test]
+    fn test_console_log_without_window_prints() {
+        let code = r#"
+            fn main() -> i32 {
+                console_log("player spawned");
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        assert_eq!(interpreter.take_output(), vec!["player spawned"]);
+    }
+
+    #[test]
+    fn test_contracts_and_asserts() {
+        let code = r#"
+            @requires(divisor != 0)
+            @ensures(result * divisor <= value)
+            fn divide(value: i32, divisor: i32) -> i32 {
+                return value / divisor;
+            }
+
+            fn main() -> i32 {
+                let ok: i32 = divide(10, 3);
+                assert(ok == 3, "10 / 3 should be 3");
+                let bad: i32 = divide(1, 0);
+                return 0;
+            }
+        "#;
+        
+        let error = run(code).unwrap_err();
+        assert_eq!(error.to_string(), "precondition #1 of fn divide (line 2) failed");
+        
+        let failing_assert = r#"
+            fn main() -> i32 {
+                assert(1 > 2, "math is broken");
+                return 0;
+            }
+        "#;
+        assert_eq!(run(failing_assert).unwrap_err().to_string(), "assertion failed: math is broken");
+    }
+
+    #[test]
+    fn test_spawn_join_and_channels() {
+        let code = r#"
+            fn worker() -> i32 {
+                channel_send(1, 40);
+                return 2;
+            }
+
+            fn main() -> i32 {
+                let handle: i32 = spawn("worker");
+                let message: i32 = channel_recv(1);
+                let result: i32 = join(handle);
+                if message + result != 42 {
+                    let boom: i32 = 1 / 0;
+                }
+                return 0;
+            }
+        "#;
+        
+        assert!(run(code).is_ok());
+    }
+
+    #[test]
+    fn test_channel_recv_fails_without_senders() {
+        let code = r#"
+            fn main() -> i32 {
+                return channel_recv(3);
+            }
+        "#;
+        let error = run(code).unwrap_err().to_string();
+        assert!(error.contains("channel 3 is empty"), "{}", error);
+
+        // Отправитель ещё работает - главный поток дожидается сообщения
+        let code = r#"
+            fn worker() -> void {
+                let mut i: i32 = 0;
+                while i < 10000 {
+                    i = i + 1;
+                }
+                channel_send(3, 7);
+            }
+
+            fn main() -> i32 {
+                let handle: i32 = spawn("worker");
+                let message: i32 = channel_recv(3);
+                join(handle);
+                assert(message == 7, "message lost");
+                return 0;
+            }
+        "#;
+        run(code).unwrap();
+    }
+
+    #[test]
+    fn test_graphics_rejected_in_spawned_thread() {
+        let code = r#"
+            fn worker() -> i32 {
+                if is_key_pressed(32) {
+                    return 1;
+                }
+                return 0;
+            }
+
+            fn main() -> i32 {
+                let handle: i32 = spawn("worker");
+                let result: i32 = join(handle);
+                return result;
+            }
+        "#;
+        
+        let error = run(code).unwrap_err().to_string();
+        assert!(error.contains("main thread"), "{}", error);
+    }
+
+    #[test]
+    fn test_coroutine_resumes_after_yield_frame() {
+        let code = r#"
+            fn cutscene() -> void {
+                let mut step: i32 = 0;
+                while step < 3 {
+                    step = step + 1;
+                    channel_send(7, step);
+                    yield_frame();
+                }
+            }
+
+            fn main() -> i32 {
+                let scene: i32 = start_coroutine("cutscene");
+                let mut frames: i32 = 0;
+                while resume(scene) {
+                    frames = frames + 1;
+                    if frames != channel_recv(7) {
+                        let boom: i32 = 1 / 0;
+                    }
+                }
+                if frames != 3 {
+                    let boom: i32 = 1 / 0;
+                }
+                return 0;
+            }
+        "#;
+        
+        assert!(run(code).is_ok());
+    }
+
+    #[test]
+    fn test_for_loop_in_coroutine_yields_each_iteration() {
+        let code = r#"
+            fn countdown() -> void {
+                for i in 0..4 {
+                    channel_send(3, 4 - i);
+                    yield_frame();
+                }
+            }
+
+            fn main() -> i32 {
+                let scene: i32 = start_coroutine("countdown");
+                let mut frames: i32 = 0;
+                while resume(scene) {
+                    if 4 - frames != channel_recv(3) {
+                        let boom: i32 = 1 / 0;
+                    }
+                    frames = frames + 1;
+                }
+                if frames != 4 {
+                    let boom: i32 = 1 / 0;
+                }
+                return 0;
+            }
+        "#;
+        
+        assert!(run(code).is_ok());
+    }
+
+    #[test]
+    fn test_yield_frame_outside_coroutine_fails() {
+        let code = r#"
+            fn main() -> i32 {
+                yield_frame();
+                return 0;
+            }
+        "#;
+        
+        assert!(run(code).is_err());
+    }
+
+    #[test]
+    fn test_on_exit_runs_after_main() {
+        let code = r#"
+            fn save() -> void {
+                print_string("saved");
+            }
+
+            fn main() -> i32 {
+                on_exit("save");
+                print(window_should_close());
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        assert_eq!(interpreter.take_output(), vec!["false", "saved"]);
+        
+        assert!(run(r#"fn main() -> i32 { on_exit("missing"); return 0; }"#).is_err());
+    }
+
+    #[test]
+    fn test_init_graphics_validates_window_size() {
+        // Программа с init_graphics открывает окно сама, поэтому до вызова
+        // окна нет и ошибка видна без дисплея
+        let error = run(r#"
+            fn main() -> i32 {
+                init_graphics(0, 600, "Game");
+                return 0;
+            }
+        "#).unwrap_err();
+        assert_eq!(error.to_string(), "init_graphics: window size must be positive, got 0x600");
+    }
+
+    #[test]
+    fn test_exit_stops_program_with_code() {
+        let code = r#"
+            fn save() -> void {
+                print_string("saved");
+            }
+
+            fn main() -> i32 {
+                on_exit("save");
+                for i in 0..3 {
+                    print(i);
+                    if (i == 1) {
+                        exit(7);
+                    }
+                }
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        assert_eq!(interpreter.take_output(), vec!["0", "1", "saved"]);
+        assert_eq!(interpreter.exit_code(), Some(7));
+    }
+
+    #[test]
+    fn test_closed_window_stops_nested_loops() {
+        let code = r#"
+            fn save() -> void {
+                print_string("saved");
+            }
+
+            fn main() -> i32 {
+                on_exit("save");
+                print(window_should_close());
+                for i in 0..3 {
+                    let mut j: i32 = 0;
+                    while j < 3 {
+                        print(j);
+                        j = j + 1;
+                    }
+                    print(99);
+                }
+                print(100);
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        // Как будто окно закрыли до первого кадра
+        interpreter.should_exit = true;
+        interpreter.interpret_program(&program, 0, 0, "test").unwrap();
+        assert_eq!(interpreter.take_output(), vec!["true", "0", "saved"]);
+    }
+
+    #[test]
+    fn test_range_builtins_for_i32_and_f32() {
+        let code = r#"
+            fn main() -> i32 {
+                let low: i32 = clamp(-5, 0, 10);
+                let angle: f32 = wrap(370.0, 0, 360);
+                print(low, clamp(15, 0, 10), clamp(2.5, 0.0, 1.0));
+                print(wrap(5, 0, 4), wrap(-1, 0, 4), wrap(-9, -2, 3), angle, wrap(-0.5, 0.0, 2.0));
+                print(map_range(5, 0, 10, 100, 200), map_range(3, 0, 4, 0, 10), map_range(0.5, 0, 1, -1.0, 1.0));
+                return wrap(7, 3, 3);
+            }
+        "#;
+
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let error = interpreter.interpret_program(&program, 0, 0, "test").unwrap_err();
+        assert!(error.to_string().contains("wrap: empty range"), "{}", error);
+        assert_eq!(interpreter.take_output(), vec![
+            "0 10 1",
+            "1 3 1 10 1.5",
+            "150 7 0",
+        ]);
+    }
+}
@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use aetos::lexer::{Lexer, Token};
+
+    #[test]
+    fn test_unicode_identifiers_and_strings() {
+        let tokens: Vec<Token> = Lexer::new("let счёт = \"🎮 ok\"; _x1 größe").collect();
+        assert_eq!(tokens, vec![
+            Token::KeywordLet,
+            Token::Identifier("счёт".to_string()),
+            Token::OperatorAssign,
+            Token::StringLiteral("🎮 ok".to_string()),
+            Token::Semicolon,
+            Token::Identifier("_x1".to_string()),
+            Token::Identifier("größe".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_columns_count_characters() {
+        let mut lexer = Lexer::new("\"ёж\" имя\n  🎮 x");
+        let mut positions = Vec::new();
+        while let Some((token, line)) = lexer.next_with_line() {
+            positions.push((token, line, lexer.column()));
+        }
+        assert_eq!(positions, vec![
+            (Token::StringLiteral("ёж".to_string()), 1, 1),
+            (Token::Identifier("имя".to_string()), 1, 6),
+            (Token::Invalid("🎮".to_string()), 2, 3),
+            (Token::Identifier("x".to_string()), 2, 5),
+        ]);
+    }
+
+    #[test]
+    fn test_format_string_literal() {
+        let tokens: Vec<Token> = Lexer::new(r#"f"a {x}" f "b""#).collect();
+        assert_eq!(tokens, vec![
+            Token::FormatString("a {x}".to_string()),
+            Token::Identifier("f".to_string()),
+            Token::StringLiteral("b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_char_literals_and_labels() {
+        let tokens: Vec<Token> = Lexer::new(r"'w' '\n' '\'' 'ж' 'outer: '\q'").collect();
+        assert_eq!(tokens, vec![
+            Token::CharLiteral(119),
+            Token::CharLiteral(10),
+            Token::CharLiteral(39),
+            Token::CharLiteral(1078),
+            Token::Label("outer".to_string()),
+            Token::Colon,
+            Token::Invalid(r"'\q'".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let tokens: Vec<Token> = Lexer::new("a & b | c ^ d << 1 >> 2 && e || f").collect();
+        let operators: Vec<Token> = tokens.into_iter()
+            .filter(|token| !matches!(token, Token::Identifier(_) | Token::IntegerLiteral(_)))
+            .collect();
+        assert_eq!(operators, vec![
+            Token::OperatorBitAnd,
+            Token::Pipe,
+            Token::OperatorBitXor,
+            Token::OperatorShl,
+            Token::OperatorShr,
+            Token::OperatorAnd,
+            Token::OperatorOr,
+        ]);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let tokens: Vec<Token> = Lexer::new("a /* x /* y */ z */ b /**/ c // d\ne").collect();
+        assert_eq!(tokens, vec![
+            Token::Identifier("a".to_string()),
+            Token::Identifier("b".to_string()),
+            Token::Identifier("c".to_string()),
+            Token::Identifier("e".to_string()),
+        ]);
+
+        // Строки после многострочного комментария считаются верно
+        let mut lexer = Lexer::new("/*\n\n*/ x");
+        assert_eq!(lexer.next_with_line(), Some((Token::Identifier("x".to_string()), 3)));
+        assert_eq!(lexer.column(), 4);
+
+        let tokens: Vec<Token> = Lexer::new("a /* b /* c */").collect();
+        assert_eq!(tokens, vec![Token::Identifier("a".to_string()), Token::UnterminatedComment]);
+    }
+
+    #[test]
+    fn test_string_escapes_and_raw_strings() {
+        let source = "\"a\\tb\\\"c\\\\\" r\"C:\\dir\" r#\"say \"hi\"\"# f\"{x}\\n\" \"two\nlines\" \"bad\\q\"";
+        let tokens: Vec<Token> = Lexer::new(source).collect();
+        assert_eq!(tokens, vec![
+            Token::StringLiteral("a\tb\"c\\".to_string()),
+            Token::StringLiteral("C:\\dir".to_string()),
+            Token::StringLiteral("say \"hi\"".to_string()),
+            Token::FormatString("{x}\n".to_string()),
+            Token::StringLiteral("two\nlines".to_string()),
+            Token::Invalid("\"bad\\q\"".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_color_literals() {
+        let tokens: Vec<Token> = Lexer::new("#ff00aa #87CEEB #fff #ff00aag #").collect();
+        assert_eq!(tokens, vec![
+            Token::ColorLiteral([255, 0, 170]),
+            Token::ColorLiteral([135, 206, 235]),
+            Token::Invalid("#fff".to_string()),
+            Token::Invalid("#ff00aag".to_string()),
+            Token::Invalid("#".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_numeric_suffixes() {
+        use aetos::ast::Type;
+
+        let tokens: Vec<Token> = Lexer::new("5i64 2f32 1.5f64 7i32 3000000000i64 3000000000i32 5x64").collect();
+        assert_eq!(tokens, vec![
+            Token::SuffixedInteger((5, Type::I64)),
+            Token::SuffixedFloat((2.0, Type::F32)),
+            Token::SuffixedFloat((1.5, Type::F64)),
+            Token::SuffixedInteger((7, Type::I32)),
+            Token::SuffixedInteger((3_000_000_000, Type::I64)),
+            Token::Invalid("3000000000i32".to_string()),
+            Token::IntegerLiteral(5),
+            Token::Identifier("x64".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use aetos::lint::lint_program;
+    use aetos::parser::Parser;
+
+    fn lint(code: &str) -> Vec<aetos::lint::LintWarning> {
+        let mut parser = Parser::new(code);
+        let program = parser.parse_program().unwrap();
+        lint_program(&program)
+    }
+
+    #[test]
+    fn test_loop_without_render_is_flagged() {
+        let code = r#"
+            fn main() -> i32 {
+                let mut x: i32 = 0;
+                while true {
+                    clear_screen(0, 0, 0);
+                    x = x + 1;
+                }
+                render();
+                return 0;
+            }
+        "#;
+        
+        let warnings = lint(code);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].function, "main");
+    }
+
+    #[test]
+    fn test_frame_loop_with_nested_loops_is_clean() {
+        let code = r#"
+            fn draw_row(y: i32) -> void {
+                let mut x: i32 = 0;
+                while x < 10 {
+                    draw_circle(x, y, 2, 255, 255, 255);
+                    x = x + 1;
+                }
+            }
+
+            fn main() -> i32 {
+                while true {
+                    clear_screen(0, 0, 0);
+                    let mut y: i32 = 0;
+                    while y < 10 {
+                        draw_row(y);
+                        y = y + 1;
+                    }
+                    render();
+                }
+                return 0;
+            }
+        "#;
+        
+        assert!(lint(code).is_empty());
+    }
+
+    #[test]
+    fn test_bounded_loops_in_graphics_program_are_clean() {
+        let code = r#"
+            fn main() -> void {
+                init_graphics(100, 100, "demo");
+                let mut xs: [i32; 8] = [0; 8];
+                for i in 0..8 {
+                    xs[i] = i * 10;
+                }
+                let mut n: i32 = 0;
+                while n < 3 {
+                    n = n + 1;
+                }
+                while !window_should_close() {
+                    for i in 0..8 {
+                        draw_circle(xs[i], 50, 2, 255, 255, 255);
+                    }
+                    render();
+                }
+            }
+        "#;
+        
+        assert!(lint(code).is_empty());
+    }
+
+    #[test]
+    fn test_window_loop_without_render_is_flagged() {
+        let code = r#"
+            fn main() -> void {
+                init_graphics(100, 100, "demo");
+                while !window_should_close() {
+                    clear_screen(0, 0, 0);
+                }
+            }
+        "#;
+        
+        assert_eq!(lint(code).len(), 1);
+    }
+
+    #[test]
+    fn test_console_program_is_not_linted() {
+        let code = r#"
+            fn main() -> i32 {
+                let mut i: i32 = 0;
+                while i < 3 {
+                    i = i + 1;
+                }
+                return i;
+            }
+        "#;
+        
+        assert!(lint(code).is_empty());
+    }
+
+    #[test]
+    fn test_unused_variables_and_parameters() {
+        let code = r#"
+            fn scale(value: i32, factor: i32, _unused: i32) -> i32 {
+                let doubled: i32 = value * 2;
+                let mut total: i32 = 0;
+                total = 5;
+                { let hidden: i32 = 1; }
+                { let hidden: i32 = 2; print(hidden); }
+                let apply: fn(i32) -> i32 = |x: i32| -> i32 { x + value };
+                return apply(1);
+            }
+
+            fn main() -> i32 {
+                return scale(1, 2, 3);
+            }
+        "#;
+
+        let messages: Vec<String> = lint(code).iter().map(|warning| warning.message.clone()).collect();
+        assert_eq!(messages, [
+            "unused parameter `factor`; prefix it with an underscore if this is intended",
+            "unused variable `doubled`; prefix it with an underscore if this is intended",
+            "unused variable `total`; prefix it with an underscore if this is intended",
+            "unused variable `hidden`; prefix it with an underscore if this is intended",
+        ]);
+    }
+
+    #[test]
+    fn test_functions_unreachable_from_main() {
+        let code = r#"
+            fn helper() -> i32 { return 1; }
+            fn double(x: i32) -> i32 { return x * 2; }
+            fn worker() -> void { print(helper()); }
+            fn orphan() -> i32 { return orphan_helper(); }
+            fn orphan_helper() -> i32 { return 2; }
+            pub fn api() -> i32 { return 3; }
+
+            fn main() -> i32 {
+                spawn("worker");
+                let values: [i32] = map([1, 2], double);
+                return values[0];
+            }
+        "#;
+
+        let unreachable: Vec<String> = lint(code).iter().map(|warning| warning.function.clone()).collect();
+        assert_eq!(unreachable, ["orphan", "orphan_helper"]);
+
+        // Библиотека без main не проверяется
+        assert!(lint("fn helper() -> i32 { return 1; }").is_empty());
+    }
+}
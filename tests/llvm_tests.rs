@@ -0,0 +1,258 @@
+// Бэкенд LLVM собирается только с feature `llvm`
+#![cfg(feature = "llvm")]
+
+#[cfg(test)]
+mod tests {
+    use aetos::codegen::llvm::emit_ir;
+    use aetos::codegen::CodeGenError;
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+
+    /// IR программы; emit_ir возвращает модуль только после верификатора
+    fn ir(code: &str) -> String {
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        emit_ir(&typed, false).unwrap_or_else(|error| panic!("{}\n{}", error, code))
+    }
+
+    /// Текст функции `name` из IR
+    fn function<'a>(ir: &'a str, name: &str) -> &'a str {
+        let start = ir.find(&format!("@{}(", name)).unwrap_or_else(|| panic!("no function {}\n{}", name, ir));
+        let end = ir[start..].find("\n}\n").unwrap() + start;
+        &ir[start..end]
+    }
+
+    #[test]
+    fn test_returns_in_both_branches_end_the_function() {
+        let ir = ir(r#"
+            fn sign(x: i32) -> i32 {
+                if (x < 0) {
+                    return 0 - 1;
+                } else {
+                    return 1;
+                }
+            }
+        "#);
+        let sign = function(&ir, "sign");
+        // Обе ветви завершены return: блока слияния и возврата по умолчанию нет
+        assert!(!sign.contains("if_end"), "{}", sign);
+        assert_eq!(sign.matches("ret i32").count(), 2, "{}", sign);
+    }
+
+    #[test]
+    fn test_return_in_one_branch_falls_through() {
+        let ir = ir(r#"
+            fn clamp(x: i32) -> i32 {
+                if (x > 10) {
+                    return 10;
+                }
+                let y: i32 = x * 2;
+                return y;
+            }
+
+            fn report(x: i32) -> void {
+                if (x > 0) {
+                    report(x - 1);
+                } else {
+                    let y: i32 = clamp(x);
+                }
+            }
+        "#);
+        let clamp = function(&ir, "clamp");
+        assert!(clamp.contains("if_end:"), "{}", clamp);
+        assert_eq!(clamp.matches("ret i32").count(), 2, "{}", clamp);
+        // Обе ветви void-функции сходятся к единственному ret void
+        let report = function(&ir, "report");
+        assert!(report.contains("call void @report("), "{}", report);
+        assert_eq!(report.matches("ret void").count(), 1, "{}", report);
+    }
+
+    #[test]
+    fn test_return_inside_while_body() {
+        let ir = ir(r#"
+            fn find(limit: i32) -> i32 {
+                let mut i: i32 = 0;
+                while (i < limit) {
+                    if (i * i > 50) {
+                        return i;
+                    }
+                    i = i + 1;
+                }
+                return 0 - 1;
+            }
+
+            fn spin(limit: i32) -> i32 {
+                let mut i: i32 = 0;
+                while (i < limit) {
+                    return i;
+                }
+                return limit;
+            }
+        "#);
+        let find = function(&ir, "find");
+        assert!(find.contains("br label %while_condition"), "{}", find);
+        // Тело, завершённое return, не возвращается к условию
+        let spin = function(&ir, "spin");
+        assert_eq!(spin.matches("br label %while_condition").count(), 1, "{}", spin);
+    }
+
+    #[test]
+    fn test_loop_exits_only_through_return() {
+        let ir = ir(r#"
+            fn first_power(limit: i32) -> i32 {
+                let mut value: i32 = 1;
+                loop {
+                    if (value > limit) {
+                        return value;
+                    }
+                    value = value * 2;
+                }
+            }
+        "#);
+        let first_power = function(&ir, "first_power");
+        // Без break в блок после цикла не попасть
+        assert!(first_power.contains("loop_end:                                         ; No predecessors!\n  unreachable"), "{}", first_power);
+    }
+
+    #[test]
+    fn test_labeled_break_and_continue() {
+        let ir = ir(r#"
+            fn search(limit: i32) -> i32 {
+                let mut hits: i32 = 0;
+                'rows: for y in 0..limit {
+                    for x in 0..limit {
+                        if (x == y) {
+                            continue;
+                        }
+                        if (x + y > 5) {
+                            break 'rows;
+                        }
+                        hits = hits + 1;
+                    }
+                }
+                return hits;
+            }
+
+            fn count_pairs() -> i32 {
+                let mut pairs: i32 = 0;
+                let mut i: i32 = 0;
+                'outer: loop {
+                    i = i + 1;
+                    let mut j: i32 = 0;
+                    while (j < 10) {
+                        j = j + 1;
+                        if (j > i) {
+                            continue 'outer;
+                        }
+                        if (i > 3) {
+                            break 'outer;
+                        }
+                        pairs = pairs + 1;
+                    }
+                }
+                return pairs;
+            }
+        "#);
+        let search = function(&ir, "search");
+        // continue ведёт к шагу внутреннего for, break 'rows - за внешний
+        assert!(search.contains("if_then:                                          ; preds = %for_body6\n  br label %for_step7\n"), "{}", search);
+        assert!(search.contains("if_then13:                                        ; preds = %if_end\n  br label %for_end\n"), "{}", search);
+        let count_pairs = function(&ir, "count_pairs");
+        assert!(count_pairs.contains("br label %loop_body\n"), "{}", count_pairs);
+        assert!(count_pairs.contains("br label %loop_end\n"), "{}", count_pairs);
+        assert!(!count_pairs.contains("unreachable"), "{}", count_pairs);
+    }
+
+    #[test]
+    fn test_if_expression_and_mixed_conditions() {
+        let ir = ir(r#"
+            fn pick(flag: bool, x: i32) -> f32 {
+                let scaled: f32 = if (flag && x > 2) { x * 1.5 } else { 0.5 };
+                while (flag) {
+                    return scaled;
+                }
+                return scaled + 1;
+            }
+        "#);
+        let pick = function(&ir, "pick");
+        assert!(pick.contains("phi float"), "{}", pick);
+        assert!(pick.contains("sitofp i32"), "{}", pick);
+        // bool-параметр уже i1 и не сравнивается с i32 0
+        assert!(!pick.contains("icmp ne i1"), "{}", pick);
+    }
+
+    const LTO_PROGRAM: &str = r#"
+        fn square(x: i32) -> i32 {
+            return x * x;
+        }
+
+        fn unused(x: i32) -> i32 {
+            return x + 1;
+        }
+
+        fn main() -> i32 {
+            let mut total: i32 = 0;
+            for i in 0..4 {
+                total = total + square(i);
+            }
+            return total;
+        }
+    "#;
+
+    #[test]
+    fn test_lto_inlines_and_removes_unused_functions() {
+        let program = Parser::new(LTO_PROGRAM).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+
+        let plain = emit_ir(&typed, false).unwrap();
+        assert!(plain.contains("define i32 @square(") && plain.contains("define i32 @unused("), "{}", plain);
+
+        // Снаружи видна только main: square встраивается, unused удаляется,
+        // переменные переходят из стека в регистры
+        let optimized = emit_ir(&typed, true).unwrap();
+        assert!(!optimized.contains("@square") && !optimized.contains("@unused"), "{}", optimized);
+        assert!(optimized.contains("define i32 @main()"), "{}", optimized);
+        assert!(optimized.contains("mul i32") && !optimized.contains("alloca"), "{}", optimized);
+
+        // Без main неизвестно, что вызовут снаружи, и функции остаются
+        let library = LTO_PROGRAM.replace("fn main()", "fn total()");
+        let program = Parser::new(&library).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let optimized = emit_ir(&typed, true).unwrap();
+        assert!(optimized.contains("@square(") && optimized.contains("@unused("), "{}", optimized);
+    }
+
+    #[test]
+    fn test_lto_object_is_not_larger() {
+        use aetos::codegen::{Backend, CrateType};
+
+        let program = Parser::new(LTO_PROGRAM).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let object_size = |lto: bool| {
+            let path = std::env::temp_dir().join(format!("aetos_lto_{}_{}.o", lto, std::process::id()));
+            Backend::Llvm.generator(CrateType::Bin, "main", lto).unwrap().generate(&typed, &path).unwrap();
+            let size = std::fs::metadata(&path).unwrap().len();
+            std::fs::remove_file(&path).unwrap();
+            size
+        };
+        assert!(object_size(true) <= object_size(false));
+    }
+
+    #[test]
+    fn test_unsupported_features_are_errors() {
+        let code = r#"
+            struct Point { x: i32, y: i32 }
+
+            fn main() -> i32 {
+                let p: Point = Point { x: 1, y: 2 };
+                return p.x;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        match emit_ir(&typed, false) {
+            Err(CodeGenError::Llvm { message }) => assert!(message.contains("not implemented in LLVM backend"), "{}", message),
+            other => panic!("expected an unsupported feature error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
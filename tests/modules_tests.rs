@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod tests {
+    use aetos::interpreter::Interpreter;
+    use aetos::modules::{link_imports, ModuleError};
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+    use std::path::{Path, PathBuf};
+
+    fn temp_project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aetos-modules-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        // import и pub - ключевые слова редакции 2024
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("aetos.toml"), "[package]\nname = \"demo\"\nedition = \"2024\"\n").unwrap();
+        for (path, source) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    fn load(path: &Path) -> Result<aetos::ast::Program, ModuleError> {
+        let source = std::fs::read_to_string(path).unwrap();
+        let program = Parser::new(&source).parse_program().unwrap();
+        link_imports(program, path)
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        let program = Parser::new("import \"lib/math.aetos\";\nimport physics;\nfn main() -> i32 { return 0; }")
+            .parse_program()
+            .unwrap();
+        let paths: Vec<&str> = program.imports.iter().map(|import| import.path.as_str()).collect();
+        assert_eq!(paths, vec!["lib/math.aetos", "physics.aetos"]);
+        assert_eq!(program.imports[1].line, 2);
+    }
+
+    #[test]
+    fn test_program_over_several_files() {
+        let dir = temp_project("link", &[
+            ("main.aetos", r#"
+                import "lib/geometry.aetos";
+                import "lib/math.aetos";
+
+                fn main() -> i32 {
+                    let p: Point = Point { x: 3, y: 4 };
+                    print(square(p.x) + square(p.y));
+                    print(area(p));
+                    return 0;
+                }
+            "#),
+            // Модули импортируют друг друга - каждый загружается один раз
+            ("lib/math.aetos", r#"
+                import "geometry.aetos";
+
+                pub fn square(x: i32) -> i32 {
+                    return x * x;
+                }
+            "#),
+            ("lib/geometry.aetos", r#"
+                import "./math.aetos";
+
+                pub struct Point {
+                    pub x: i32,
+                    pub y: i32,
+                }
+
+                pub fn area(p: Point) -> i32 {
+                    return p.x * p.y;
+                }
+            "#),
+        ]);
+
+        let program = load(&dir.join("main.aetos")).unwrap();
+        let names: Vec<&str> = program.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["square", "area", "main"]);
+        TypeChecker::new().check_program(&program).unwrap();
+
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.interpret_program(&program, 0, 0, "modules").unwrap();
+        assert_eq!(interpreter.take_output(), vec!["25", "12"]);
+    }
+
+    #[test]
+    fn test_duplicate_across_files() {
+        let dir = temp_project("duplicate", &[
+            ("main.aetos", "import \"util.aetos\";\nfn clamp(x: i32) -> i32 { return x; }\nfn main() -> i32 { return clamp(1); }"),
+            ("util.aetos", "fn clamp(x: i32) -> i32 { return 0; }"),
+        ]);
+
+        let error = load(&dir.join("main.aetos")).unwrap_err();
+        assert!(matches!(&error, ModuleError::Duplicate { kind: "function", name, .. } if name == "clamp"));
+        assert!(error.to_string().contains("util.aetos and"));
+    }
+
+    #[test]
+    fn test_private_item_across_files() {
+        let dir = temp_project("private", &[
+            ("main.aetos", r#"
+                import "shapes.aetos";
+
+                fn main() -> i32 {
+                    let s: Shape = Shape::Square(2);
+                    return side(s) + LIMIT;
+                }
+            "#),
+            ("shapes.aetos", r#"
+                pub enum Shape { Square(i32) }
+                pub const LIMIT: i32 = 10;
+                fn side(s: Shape) -> i32 {
+                    return match s { Shape::Square(n) => n };
+                }
+            "#),
+        ]);
+
+        let error = load(&dir.join("main.aetos")).unwrap_err();
+        assert!(matches!(&error, ModuleError::Private { kind: "function", name, .. } if name == "side"), "{}", error);
+        assert!(error.to_string().contains("shapes.aetos is used in"));
+
+        // Внутри своего файла закрытые объявления доступны
+        let dir = temp_project("private-local", &[
+            ("main.aetos", "import \"util.aetos\";\nfn main() -> i32 { return twice(2); }"),
+            ("util.aetos", "fn helper(x: i32) -> i32 { return x; }\npub fn twice(x: i32) -> i32 { return helper(x) * 2; }"),
+        ]);
+        load(&dir.join("main.aetos")).unwrap();
+    }
+
+    #[test]
+    fn test_private_field_across_files() {
+        let dir = temp_project("private-field", &[
+            ("main.aetos", r#"
+                import "account.aetos";
+
+                fn main() -> i32 {
+                    let a: Account = open(5);
+                    print(a.owner);
+                    return a.balance;
+                }
+            "#),
+            ("account.aetos", r#"
+                pub struct Account {
+                    pub owner: i32,
+                    balance: i32,
+                }
+
+                pub fn open(owner: i32) -> Account {
+                    let a: Account = Account { owner: owner, balance: 0 };
+                    return Account { balance: a.balance + 100, ..a };
+                }
+            "#),
+        ]);
+
+        let program = load(&dir.join("main.aetos")).unwrap();
+        let errors = TypeChecker::new().check_program(&program).unwrap_err();
+        let messages: Vec<String> = errors.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].contains("Field balance of struct Account is private"), "{}", messages[0]);
+    }
+
+    #[test]
+    fn test_missing_module() {
+        let dir = temp_project("missing", &[
+            ("main.aetos", "fn main() -> i32 { return 0; }\nimport \"nowhere.aetos\";"),
+        ]);
+
+        let error = load(&dir.join("main.aetos")).unwrap_err();
+        assert!(matches!(error, ModuleError::Io { line: 2, .. }));
+    }
+}
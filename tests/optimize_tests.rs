@@ -41,7 +41,7 @@ mod tests {
         }
         
         // Проверяем что второе statement - return переменной
-        if let aetos::ast::Statement::Return { value } = &main_fn.body[1] {
+        if let aetos::ast::Statement::Return { value, .. } = &main_fn.body[1] {
             if let aetos::ast::Expression::Variable(name) = value {
                 assert_eq!(name, "x");
             } else {
@@ -91,7 +91,7 @@ mod tests {
         // После оптимизации должен остаться только return с константой
         assert_eq!(main_fn.body.len(), 1);
         
-        if let aetos::ast::Statement::Return { value: aetos::ast::Expression::IntegerLiteral(result) } = &main_fn.body[0] {
+        if let aetos::ast::Statement::Return { value: aetos::ast::Expression::IntegerLiteral(result), .. } = &main_fn.body[0] {
             assert_eq!(*result, 14); // 2 + (3 * 4) = 14
         } else {
             panic!("Expected return with integer literal after optimization");
@@ -109,10 +109,295 @@ mod tests {
         let program = parse_and_optimize(code);
         let main_fn = program.functions.iter().find(|f| f.name == "main").unwrap();
         
-        if let aetos::ast::Statement::Return { value: aetos::ast::Expression::BoolLiteral(result) } = &main_fn.body[0] {
+        if let aetos::ast::Statement::Return { value: aetos::ast::Expression::BoolLiteral(result), .. } = &main_fn.body[0] {
             assert!(*result); // true && false || true = true
         } else {
             panic!("Expected return with boolean literal after optimization");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_release_strips_contracts() {
+        let code = r#"
+            @requires(x > 0)
+            @ensures(result > x)
+            fn grow(x: i32) -> i32 {
+                assert(x < 100, "x too large");
+                return x + 1;
+            }
+        "#;
+        
+        let mut debug = Parser::new(code).parse_program().unwrap();
+        Optimizer::default().optimize(&mut debug);
+        assert_eq!(debug.functions[0].attributes.len(), 2);
+        assert_eq!(debug.functions[0].body.len(), 2);
+        
+        let mut release = Parser::new(code).parse_program().unwrap();
+        Optimizer::release().optimize(&mut release);
+        assert!(release.functions[0].attributes.is_empty());
+        assert_eq!(release.functions[0].body.len(), 1);
+    }
+
+    #[test]
+    fn test_top_level_constants_are_folded() {
+        let code = r#"
+            const WIDTH: i32 = 800;
+            const HALF_WIDTH: i32 = WIDTH / 2;
+            const SPEED: f32 = 3;
+
+            fn main() -> i32 {
+                let center: i32 = HALF_WIDTH + 10;
+                print(SPEED);
+                return center;
+            }
+        "#;
+
+        let program = parse_and_optimize(code);
+        assert_eq!(program.consts[1].value, aetos::ast::Expression::IntegerLiteral(400));
+        assert_eq!(program.consts[2].value, aetos::ast::Expression::FloatLiteral(3.0));
+
+        let main_fn = program.functions.iter().find(|f| f.name == "main").unwrap();
+        match &main_fn.body[0] {
+            aetos::ast::Statement::VariableDeclaration { value, .. } => {
+                assert_eq!(*value, aetos::ast::Expression::IntegerLiteral(410));
+            }
+            other => panic!("Expected variable declaration, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_statements_after_exit_are_pruned() {
+        let code = r#"
+            fn main() -> i32 {
+                print(1);
+                if (true) {
+                    exit(3);
+                    print(2);
+                }
+                exit(0);
+                print(3);
+                return 0;
+            }
+        "#;
+
+        let program = parse_and_optimize(code);
+        let body = &program.functions[0].body;
+        assert_eq!(body.len(), 3);
+        match &body[1] {
+            aetos::ast::Statement::If { then_branch, .. } => assert_eq!(then_branch.len(), 1),
+            other => panic!("Expected if, found {:?}", other),
+        }
+
+        // Своя функция exit может вернуть управление - ничего не удаляем
+        let shadowed = parse_and_optimize(r#"
+            fn exit(code: i32) -> void { print(code); }
+            fn main() -> i32 { exit(1); return 0; }
+        "#);
+        assert!(matches!(
+            shadowed.functions[1].body.last(),
+            Some(aetos::ast::Statement::Return { .. })
+        ));
+    }
+
+    #[test]
+    fn test_statics_are_not_folded() {
+        let code = r#"
+            const BONUS: i32 = 5;
+            static mut score: i32 = BONUS * 2;
+
+            fn bump() -> i32 {
+                if (score > 100) {
+                    return 0;
+                }
+                score = score + BONUS;
+                return score;
+            }
+
+            fn main() -> i32 {
+                bump();
+                return score;
+            }
+        "#;
+
+        let program = parse_and_optimize(code);
+        assert_eq!(program.statics[0].value, aetos::ast::Expression::IntegerLiteral(10));
+
+        // score меняется во время работы, а bump с return внутри if не встраивается
+        let main_fn = program.functions.iter().find(|f| f.name == "main").unwrap();
+        assert_eq!(main_fn.body.len(), 2);
+        assert_eq!(
+            main_fn.body[1],
+            aetos::ast::Statement::Return {
+                value: aetos::ast::Expression::Variable("score".to_string()),
+                position: aetos::ast::Position { line: 15, column: 17 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_struct_field_defaults_are_folded() {
+        let code = r#"
+            const WIDTH: i32 = 800;
+
+            struct Config {
+                width: i32 = WIDTH / 2,
+                scale: f32 = 2,
+                title: string,
+            }
+
+            fn main() -> i32 {
+                return 0;
+            }
+        "#;
+
+        let program = parse_and_optimize(code);
+        let fields = &program.structs[0].fields;
+        assert_eq!(fields[0].default, Some(aetos::ast::Expression::IntegerLiteral(400)));
+        assert_eq!(fields[1].default, Some(aetos::ast::Expression::FloatLiteral(2.0)));
+        assert_eq!(fields[2].default, None);
+    }
+
+    #[test]
+    fn test_const_fn_calls_are_evaluated() {
+        use aetos::ast::Expression;
+
+        let code = r#"
+            struct Color {
+                r: i32,
+                g: i32,
+            }
+
+            const fn shade(level: i32) -> Color {
+                let mut total: i32 = 0;
+                for i in 0..level {
+                    total = total + i;
+                }
+                return Color { r: total, g: level * 2 };
+            }
+
+            const fn spin(n: i32) -> i32 {
+                let mut i: i32 = 0;
+                while (true) {
+                    i = i + 1;
+                }
+                return n;
+            }
+
+            fn main() -> i32 {
+                let color: Color = shade(4);
+                let count: i32 = spin(2);
+                let level: i32 = 3;
+                let other: Color = shade(level);
+                return color.r + other.g + count;
+            }
+        "#;
+
+        let program = parse_and_optimize(code);
+        let main_fn = program.functions.iter().find(|f| f.name == "main").unwrap();
+        let value = |variable: &str| main_fn.body.iter()
+            .find_map(|statement| match statement {
+                aetos::ast::Statement::VariableDeclaration { name, value, .. } if name == variable => Some(value.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(value("color"), Expression::StructInitialization {
+            struct_name: "Color".to_string(),
+            fields: vec![
+                ("g".to_string(), Expression::IntegerLiteral(8)),
+                ("r".to_string(), Expression::IntegerLiteral(6)),
+            ],
+            base: None,
+        });
+        // Бесконечный цикл упирается в лимит шагов, вызов остаётся до запуска
+        assert!(matches!(value("count"), Expression::FunctionCall { ref name, .. } if name == "spin"));
+        // Переменная - не литерал
+        assert!(matches!(value("other"), Expression::FunctionCall { ref name, .. } if name == "shade"));
+    }
+
+    #[test]
+    fn test_fast_math_replaces_trig_calls() {
+        use aetos::ast::{Expression, Statement};
+
+        let code = r#"
+            fn wave(t: f32) -> f32 {
+                return sin(t) * cos(t * 2.0);
+            }
+
+            fn shifted(cos: fn(f32) -> f32, t: f32) -> f32 {
+                return cos(t);
+            }
+        "#;
+
+        let mut program = aetos::parser::Parser::new(code).parse_program().unwrap();
+        let mut optimizer = Optimizer::default();
+        optimizer.fast_math = true;
+        assert_eq!(optimizer.passes()[0], "fast_math");
+        optimizer.run_pass("fast_math", &mut program);
+
+        let called = |function: &str| -> Vec<String> {
+            let function = program.functions.iter().find(|f| f.name == function).unwrap();
+            let Statement::Return { value, .. } = &function.body[0] else { panic!("expected return") };
+            let mut names = Vec::new();
+            let mut pending = vec![value.clone()];
+            while let Some(expression) = pending.pop() {
+                match expression {
+                    Expression::FunctionCall { name, args } => {
+                        names.push(name);
+                        pending.extend(args);
+                    }
+                    Expression::BinaryExpression { left, right, .. } => pending.extend([*left, *right]),
+                    _ => {}
+                }
+            }
+            names.sort();
+            names
+        };
+        assert_eq!(called("wave"), vec!["fast_cos", "fast_sin"]);
+        // Параметр cos перекрывает встроенную функцию
+        assert_eq!(called("shifted"), vec!["cos"]);
+        assert!(!Optimizer::default().passes().contains(&"fast_math"));
+    }
+
+    #[test]
+    fn test_color_literal_channels_are_folded() {
+        use aetos::ast::{Expression, Statement};
+
+        let code = r#"
+            fn accent() -> i32 {
+                return #ff8800.g + #ff8800.b;
+            }
+        "#;
+
+        let mut program = aetos::parser::Parser::new(code).parse_program().unwrap();
+        Optimizer::default().run_pass("constant_folding", &mut program);
+        let Statement::Return { value, .. } = &program.functions[0].body[0] else { panic!("expected return") };
+        assert_eq!(*value, Expression::IntegerLiteral(136));
+    }
+
+    #[test]
+    fn test_inlined_calls_get_own_scope() {
+        use aetos::ast::Statement;
+        use aetos::typecheck::TypeChecker;
+
+        let code = r#"
+            fn hit(amount: i32) -> void {
+                print(amount);
+            }
+
+            fn main() -> void {
+                let amount: i32 = 3;
+                hit(1);
+                hit(amount);
+                print(amount);
+            }
+        "#;
+
+        let mut program = Parser::new(code).parse_program().unwrap();
+        Optimizer::default().run_pass("inline_functions", &mut program);
+        let main_fn = program.functions.iter().find(|f| f.name == "main").unwrap();
+        assert!(matches!(main_fn.body[1], Statement::Block { .. }));
+        assert!(matches!(main_fn.body[2], Statement::Block { .. }));
+        // Параметры встроенных вызовов не конфликтуют с `amount` из main
+        assert!(TypeChecker::new().check_program(&program).is_ok());
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod tests {
+    use aetos::registry::{self, Registry, RegistryError};
+    use aetos::workspace::Package;
+    use semver::VersionReq;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aetos-registry-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, source) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    fn library(dir: &std::path::Path, name: &str, version: &str, dependencies: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join("aetos.toml"),
+            format!("[package]\nname = \"{}\"\nversion = \"{}\"\nentry = \"lib.aetos\"\n\n[dependencies]\n{}", name, version, dependencies),
+        ).unwrap();
+        std::fs::write(dir.join("lib.aetos"), format!("pub fn {}_version() -> string {{ return \"{}\"; }}\n", name, version)).unwrap();
+    }
+
+    #[test]
+    fn test_publish_and_install_with_dependencies() {
+        let root = temp_dir("install", &[]);
+        let registry = Registry::new(&root.join("registry").to_string_lossy()).unwrap();
+        for version in ["0.3.0", "0.3.2", "0.4.0"] {
+            library(&root.join("tween"), "tween", version, "");
+            registry.publish(&root.join("tween")).unwrap();
+        }
+        library(&root.join("ui_kit"), "ui_kit", "1.2.0", "tween = \"0.3\"\n");
+        let (_, _, checksum) = registry.publish(&root.join("ui_kit")).unwrap();
+        assert_eq!(checksum.len(), 64);
+
+        let error = registry.publish(&root.join("ui_kit")).unwrap_err();
+        assert!(matches!(error, RegistryError::AlreadyPublished { .. }));
+
+        // Зависимость ставится по диапазону из индекса, а не старшей версией
+        let packages_dir = root.join("app").join(".aetos/packages");
+        let installed = registry.install("ui_kit", &VersionReq::STAR, &packages_dir).unwrap();
+        let versions: Vec<String> = installed.iter().map(|p| format!("{} {}", p.name, p.version)).collect();
+        assert_eq!(versions, vec!["ui_kit 1.2.0", "tween 0.3.2"]);
+        assert!(packages_dir.join("tween-0.3.2/lib.aetos").is_file());
+
+        // Пакет приложения видит установленную зависимость
+        std::fs::write(root.join("app/aetos.toml"), "[package]\nname = \"app\"\n\n[dependencies]\nui_kit = \"1\"\n").unwrap();
+        let app = Package::load(&root.join("app")).unwrap();
+        assert_eq!(app.dependencies, vec![("ui_kit".to_string(), packages_dir.join("ui_kit-1.2.0"))]);
+
+        let error = registry.install("tween", &VersionReq::parse("2").unwrap(), &packages_dir).unwrap_err();
+        assert_eq!(error.to_string(), "No version of tween matches ^2 (available: 0.3.0, 0.3.2, 0.4.0)");
+    }
+
+    #[test]
+    fn test_checksum_is_verified() {
+        let root = temp_dir("checksum", &[]);
+        let registry = Registry::new(&root.join("registry").to_string_lossy()).unwrap();
+        library(&root.join("tween"), "tween", "1.0.0", "");
+        registry.publish(&root.join("tween")).unwrap();
+
+        // Архив воспроизводим: та же сумма при повторной упаковке
+        let archive = root.join("registry/packages/tween/tween-1.0.0.tar.gz");
+        let packed = registry::pack(&root.join("tween"), "tween-1.0.0").unwrap();
+        assert_eq!(registry::checksum(&packed), registry::checksum(&std::fs::read(&archive).unwrap()));
+
+        let mut tampered = std::fs::read(&archive).unwrap();
+        tampered.extend_from_slice(b"extra");
+        std::fs::write(&archive, tampered).unwrap();
+        let error = registry.install("tween", &VersionReq::STAR, &root.join("packages")).unwrap_err();
+        assert!(matches!(error, RegistryError::Checksum { .. }));
+        assert!(!root.join("packages/tween-1.0.0").exists());
+    }
+
+    #[test]
+    fn test_unsafe_names_and_insecure_registries_are_rejected() {
+        let error = Registry::new("http://example.com/registry").unwrap_err();
+        assert!(matches!(error, RegistryError::InsecureRegistry { .. }));
+        assert!(Registry::new("https://example.com/registry").is_ok());
+
+        let root = temp_dir("names", &[]);
+        let registry = Registry::new(&root.join("registry").to_string_lossy()).unwrap();
+        let error = registry.index("../escape").unwrap_err();
+        assert!(matches!(error, RegistryError::InvalidName { .. }));
+
+        // Имя зависимости из индекса проверяется до обращения к файлам
+        library(&root.join("tween"), "tween", "1.0.0", "");
+        registry.publish(&root.join("tween")).unwrap();
+        let index = root.join("registry/index/tween.json");
+        let json = std::fs::read_to_string(&index).unwrap()
+            .replace("\"dependencies\": {}", "\"dependencies\": { \"../../escape\": \"1\" }");
+        std::fs::write(&index, json).unwrap();
+        let error = registry.install("tween", &VersionReq::STAR, &root.join("packages")).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid package name \"../../escape\": only a-z, 0-9, _ and - are allowed");
+    }
+
+    #[test]
+    fn test_unpack_rejects_entries_outside_prefix() {
+        fn archive(paths: &[&str]) -> Vec<u8> {
+            let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+            for path in paths {
+                let mut header = tar::Header::new_gnu();
+                // set_path не пропускает `..`, поэтому имя пишется напрямую
+                header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+                header.set_size(2);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append(&header, &b"{}"[..]).unwrap();
+            }
+            builder.into_inner().unwrap().finish().unwrap()
+        }
+
+        let root = temp_dir("unpack", &[]);
+        let packages_dir = root.join("packages");
+        for paths in [
+            &["tween-1.0.0/lib.aetos", "tween-1.0.0/../escape.aetos"][..],
+            &["tween-1.0.0/lib.aetos", "other-1.0.0/lib.aetos"][..],
+        ] {
+            let error = registry::unpack(&archive(paths), &packages_dir, "tween-1.0.0").unwrap_err();
+            assert!(matches!(error, RegistryError::ArchiveEntry { .. }), "{:?}", paths);
+            assert!(!packages_dir.join("tween-1.0.0").exists());
+        }
+
+        registry::unpack(&archive(&["tween-1.0.0/lib.aetos"]), &packages_dir, "tween-1.0.0").unwrap();
+        assert!(packages_dir.join("tween-1.0.0/lib.aetos").is_file());
+    }
+
+    #[test]
+    fn test_path_dependencies_are_not_published() {
+        let root = temp_dir("path", &[
+            ("game/aetos.toml", "[package]\nname = \"game\"\nversion = \"0.1.0\"\n\n[dependencies]\nengine = { path = \"../engine\" }\n"),
+        ]);
+        let error = Registry::new(&root.join("registry").to_string_lossy()).unwrap().publish(&root.join("game")).unwrap_err();
+        assert!(matches!(error, RegistryError::PathDependency { .. }));
+    }
+
+    #[test]
+    fn test_add_dependency_to_manifest() {
+        let manifest = "[package]\nname = \"app\"\n\n[dependencies]\ntween = \"0.3\"\n\n[registry]\nurl = \"https://example.com\"\n";
+        assert_eq!(
+            registry::add_dependency(manifest, "ui_kit", "1.2.0"),
+            "[package]\nname = \"app\"\n\n[dependencies]\ntween = \"0.3\"\nui_kit = \"1.2.0\"\n\n[registry]\nurl = \"https://example.com\"\n",
+        );
+        assert_eq!(
+            registry::add_dependency(manifest, "tween", "0.4"),
+            manifest.replace("\"0.3\"", "\"0.4\""),
+        );
+        assert_eq!(
+            registry::add_dependency("[package]\nname = \"app\"\n", "tween", "0.3"),
+            "[package]\nname = \"app\"\n\n[dependencies]\ntween = \"0.3\"\n",
+        );
+    }
+}
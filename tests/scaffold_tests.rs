@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod tests {
+    use aetos::edition::Edition;
+    use aetos::lint::lint_program;
+    use aetos::parser::Parser;
+    use aetos::scaffold::{manifest, Template};
+    use aetos::typecheck::TypeChecker;
+
+    #[test]
+    fn test_templates_type_check() {
+        for name in Template::NAMES {
+            let template = Template::parse(name).unwrap();
+            let source = template.main_source("demo");
+            // Редакция, которую записывает manifest нового проекта
+            let program = Parser::with_edition(&source, Edition::LATEST).parse_program().unwrap();
+            assert!(TypeChecker::new().check_program(&program).is_ok(), "template {}", name);
+            assert!(lint_program(&program).is_empty(), "template {}", name);
+        }
+    }
+
+    #[test]
+    fn test_game_template_exits_on_escape() {
+        let source = Template::Game.main_source("demo");
+        assert!(source.contains("выход - Escape"));
+        // 27 - код Escape для is_key_pressed
+        assert!(source.contains("if is_key_pressed(27) {\n            break;"), "{}", source);
+    }
+
+    #[test]
+    fn test_manifest_records_edition() {
+        let dir = std::env::temp_dir().join(format!("aetos_scaffold_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("aetos.toml"), manifest("demo")).unwrap();
+        assert_eq!(Edition::from_manifest(&dir), Some(Edition::LATEST));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
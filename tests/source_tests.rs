@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use aetos::edition::Edition;
+    use aetos::lexer::Lexer;
+    use aetos::parser::{ParseError, Parser};
+    use aetos::source::SourceFile;
+
+    fn tokens_with_positions(text: &str) -> Vec<(String, usize, usize)> {
+        let mut lexer = Lexer::new(text);
+        let mut positions = Vec::new();
+        while let Some((token, line)) = lexer.next_with_line() {
+            positions.push((format!("{:?}", token), line, lexer.column()));
+        }
+        positions
+    }
+
+    #[test]
+    fn test_bom_is_stripped() {
+        let source = SourceFile::new("\u{feff}//! edition: 2023\nfn main() -> i32 { return 0; }");
+        assert!(source.text().starts_with("//! edition"));
+        assert_eq!(Edition::from_pragma(source.text()), Some(Edition::E2023));
+        // Смещения после BOM сдвинуты на его три байта
+        assert_eq!(source.original_offset(0), 3);
+        assert_eq!(source.original_offset(10), 13);
+    }
+
+    #[test]
+    fn test_crlf_is_normalized_with_original_offsets() {
+        let source = SourceFile::new("let a\r\nlet b\rlet c\r\n");
+        assert_eq!(source.text(), "let a\nlet b\nlet c\n");
+        // Каждый удалённый `\r` сдвигает всё, что после него
+        assert_eq!(source.original_offset(4), 4);
+        assert_eq!(source.original_offset(5), 6);
+        assert_eq!(source.original_offset(6), 7);
+        // Одиночный `\r` заменён, а не удалён
+        assert_eq!(source.original_offset(12), 13);
+        assert_eq!(source.original_offset(17), 19);
+    }
+
+    #[test]
+    fn test_error_positions_map_to_original_bytes() {
+        let raw = "\u{feff}fn main() -> i32 {\r\n    let x: i32 = 1 $ 2;\r\n    return 0;\r\n}\r\n";
+        let source = SourceFile::new(raw);
+        let error = Parser::new(source.text()).parse_program().unwrap_err();
+        let (line, column) = error.position().unwrap();
+        assert_eq!((line, column), (2, 20));
+        let byte = source.original_position(line, column).unwrap();
+        assert_eq!(&raw.as_bytes()[byte..byte + 1], b"$");
+
+        assert_eq!(source.offset(1, 1), Some(0));
+        assert_eq!(source.original_position(1, 1), Some(3));
+        // Колонка за концом строки - её перевод строки (`\n` после `\r`)
+        assert_eq!(source.original_position(1, 40), Some(22));
+        assert_eq!(source.offset(9, 1), None);
+    }
+
+    #[test]
+    fn test_crlf_source_lexes_like_lf() {
+        let lf = "fn main() -> void {\n    let x: i32 = 1;\n}\n";
+        let windows = format!("\u{feff}{}", lf.replace('\n', "\r\n"));
+        let normalized = SourceFile::new(&windows);
+        assert_eq!(tokens_with_positions(normalized.text()), tokens_with_positions(lf));
+        assert!(Parser::new(normalized.text()).parse_program().is_ok());
+    }
+
+    #[test]
+    fn test_non_utf8_sources_are_rejected() {
+        let utf16 = SourceFile::from_bytes(&[0xFF, 0xFE, b'f', 0]).unwrap_err();
+        assert!(utf16.to_string().contains("UTF-16"), "{}", utf16);
+
+        let broken = SourceFile::from_bytes(b"fn \xC3(").unwrap_err();
+        assert_eq!(broken.to_string(), "source is not valid UTF-8 at byte 3");
+
+        let with_bom = SourceFile::from_bytes(b"\xEF\xBB\xBFfn").unwrap();
+        assert_eq!(with_bom.text(), "fn");
+    }
+
+    #[test]
+    fn test_snippet_expands_tabs() {
+        let code = "fn main() -> void {\r\n\tlet x: i32 = 1 # 2;\r\n}\r\n";
+        let source = SourceFile::new(code);
+        let error = Parser::new(source.text()).parse_program().unwrap_err();
+        let (line, column) = match error {
+            ParseError::InvalidCharacter { line, column, .. } => (line, column),
+            other => panic!("expected invalid character, got {:?}", other),
+        };
+        assert_eq!((line, column), (2, 17));
+        assert_eq!(
+            source.snippet(line, column).unwrap(),
+            "2 |     let x: i32 = 1 # 2;\n  |                    ^"
+        );
+    }
+
+    #[test]
+    fn test_snippet_aligns_tabs_to_tab_stops() {
+        let source = SourceFile::new("ab\tc\n");
+        assert_eq!(source.snippet(1, 4).unwrap(), "1 | ab  c\n  |     ^");
+        // Колонка за концом строки указывает на её конец
+        assert_eq!(source.snippet(1, 9).unwrap(), "1 | ab  c\n  |      ^");
+        assert_eq!(source.snippet(3, 1), None);
+    }
+}
@@ -0,0 +1,121 @@
+// Спецификация языка в виде программ из tests/spec.
+//
+// Каждый файл - самостоятельная программа, ожидания записаны в комментариях:
+// - `// => текст` - очередная строка вывода программы;
+// - `// error: стадия: текст` - ошибка на стадии parse, type или runtime,
+//   сообщение которой содержит текст. Ошибок одной стадии может быть
+//   несколько (проверка типов сообщает все).
+// Файл без `// error:` должен пройти все стадии без ошибок. Программа из
+// нескольких файлов - каталог, ожидания записаны в его main.aetos.
+#[cfg(test)]
+mod tests {
+    use aetos::interpreter::Interpreter;
+    use aetos::modules::link_imports;
+    use aetos::parser::Parser;
+    use aetos::typecheck::TypeChecker;
+    use std::path::Path;
+
+    const SPEC_DIR: &str = "tests/spec";
+
+    struct Expectations {
+        output: Vec<String>,
+        errors: Vec<(String, String)>,
+    }
+
+    fn expectations(source: &str) -> Expectations {
+        let mut output = Vec::new();
+        let mut errors = Vec::new();
+        for line in source.lines().map(str::trim) {
+            if let Some(expected) = line.strip_prefix("// =>") {
+                output.push(expected.trim().to_string());
+            } else if let Some(error) = line.strip_prefix("// error:") {
+                let (stage, message) = error.split_once(':').unwrap_or((error, ""));
+                errors.push((stage.trim().to_string(), message.trim().to_string()));
+            }
+        }
+        Expectations { output, errors }
+    }
+
+    /// Проводит программу через все стадии; возвращает вывод и ошибки первой упавшей стадии
+    fn run(source: &str, path: &Path) -> (Vec<String>, Vec<(String, String)>) {
+        let program = match Parser::new(source).parse_program() {
+            Ok(program) => program,
+            Err(e) => return (Vec::new(), vec![("parse".to_string(), e.to_string())]),
+        };
+        let program = match link_imports(program, path) {
+            Ok(program) => program,
+            Err(e) => return (Vec::new(), vec![("parse".to_string(), e.to_string())]),
+        };
+
+        if let Err(errors) = TypeChecker::new().check_program(&program) {
+            let errors = errors.errors.iter()
+                .map(|e| ("type".to_string(), e.to_string()))
+                .collect();
+            return (Vec::new(), errors);
+        }
+
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        let result = interpreter.interpret_program(&program, 0, 0, "spec");
+        let output = interpreter.take_output();
+        match result {
+            Ok(()) => (output, Vec::new()),
+            Err(e) => (output, vec![("runtime".to_string(), e.to_string())]),
+        }
+    }
+
+    fn check(path: &Path) -> Result<(), String> {
+        let path = if path.is_dir() { path.join("main.aetos") } else { path.to_path_buf() };
+        let source = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let expected = expectations(&source);
+        let (output, errors) = run(&source, &path);
+
+        if output != expected.output {
+            return Err(format!("output mismatch\n  expected: {:?}\n  found:    {:?}", expected.output, output));
+        }
+        if errors.len() != expected.errors.len() {
+            return Err(format!("expected errors {:?}, found {:?}", expected.errors, errors));
+        }
+        for ((stage, message), (expected_stage, expected_message)) in errors.iter().zip(&expected.errors) {
+            if stage != expected_stage || !message.contains(expected_message.as_str()) {
+                return Err(format!(
+                    "expected {} error containing {:?}, found {} error {:?}",
+                    expected_stage, expected_message, stage, message
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn spec_files() -> Vec<std::path::PathBuf> {
+        let mut files: Vec<_> = std::fs::read_dir(SPEC_DIR)
+            .expect("tests/spec is missing")
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "aetos") || path.join("main.aetos").is_file())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_spec_corpus() {
+        let files = spec_files();
+        assert!(!files.is_empty());
+
+        let failures: Vec<String> = files.iter()
+            .filter_map(|path| check(path).err().map(|e| format!("{}: {}", path.display(), e)))
+            .collect();
+        assert!(failures.is_empty(), "{} spec file(s) failed:\n{}", failures.len(), failures.join("\n"));
+    }
+
+    #[test]
+    fn test_expectation_comments() {
+        let source = "// => 1\n// error: type: Undefined variable\n// error: runtime\nfn main() -> i32 {}";
+        let parsed = expectations(source);
+        assert_eq!(parsed.output, vec!["1".to_string()]);
+        assert_eq!(parsed.errors, vec![
+            ("type".to_string(), "Undefined variable".to_string()),
+            ("runtime".to_string(), String::new()),
+        ]);
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use aetos::parser::Parser;
+    use aetos::stats::{stats_path, UsageStats};
+    use std::path::Path;
+
+    #[test]
+    fn test_counts_features_and_builtins() {
+        let code = r#"
+            struct Point { x: i32, y: i32 }
+
+            impl Point {
+                fn sum(self) -> i32 { return self.x + self.y; }
+            }
+
+            fn sleep(value: i32) -> i32 { return value; }
+
+            fn main() -> i32 {
+                let mut total: i32 = 0;
+                for i in 0..3 {
+                    total = total + i;
+                }
+                let p: Point = Point { x: 1, y: 2 };
+                print(p.sum());
+                print(total);
+                sleep(str_len("abc"));
+                return 0;
+            }
+        "#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        let stats = UsageStats::collect(&program, "main.aetos");
+        assert_eq!((stats.functions, stats.methods, stats.structs), (2, 1, 1));
+        assert_eq!(stats.features.get("for"), Some(&1));
+        assert_eq!(stats.features.get("let_mut"), Some(&1));
+        assert_eq!(stats.features.get("method_call"), Some(&1));
+        assert_eq!(stats.features.get("while"), None);
+        
+        // Своя sleep перекрывает встроенную и в статистику не попадает
+        let builtins: Vec<(&str, usize)> = stats.builtins.iter().map(|(name, count)| (name.as_str(), *count)).collect();
+        assert_eq!(builtins, vec![("print", 2), ("str_len", 1)]);
+        
+        let json = stats.to_json();
+        assert!(json.contains("\"source\": \"main.aetos\""));
+        assert!(json.contains("\"print\": 2"));
+    }
+
+    #[test]
+    fn test_stats_path_is_next_to_output() {
+        assert_eq!(stats_path("stats.json", "build/game.wat"), Path::new("build/stats.json"));
+        assert_eq!(stats_path("stats.json", "game.wat"), Path::new("stats.json"));
+        assert_eq!(stats_path("/tmp/stats.json", "build/game.wat"), Path::new("/tmp/stats.json"));
+    }
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use aetos::ast::Type;
+    use aetos::stdlib::{builtins, get_stdlib, lookup, substitute};
+
+    #[test]
+    fn test_stdlib_is_cached() {
+        assert!(std::ptr::eq(get_stdlib(), get_stdlib()));
+    }
+
+    #[test]
+    fn test_stdlib_matches_builtin_table() {
+        let program = get_stdlib();
+        for builtin in builtins() {
+            let function = program.functions.iter()
+                .find(|f| f.name == builtin.name)
+                .unwrap_or_else(|| panic!("{} missing from stdlib AST", builtin.name));
+            assert_eq!(function.return_type, builtin.return_type);
+            assert_eq!(function.params.len(), builtin.params.len());
+        }
+        assert!(lookup("print").unwrap().variadic);
+        assert!(lookup("no_such_builtin").is_none());
+    }
+
+    #[test]
+    fn test_generic_builtin_binding() {
+        let map = lookup("map").unwrap();
+        let args = [
+            Type::FixedArray(Box::new(Type::I32), 3),
+            Type::Function(vec![Type::I32], Box::new(Type::String)),
+        ];
+        let bindings = map.bind_type_params(&args);
+        assert_eq!(bindings["T"], Type::I32);
+        assert_eq!(bindings["U"], Type::String);
+        assert_eq!(substitute(&map.return_type, &bindings), Type::Array(Box::new(Type::String)));
+
+        // Элементы пустого литерала не выводят T
+        let filter = lookup("filter").unwrap();
+        let bindings = filter.bind_type_params(&[Type::FixedArray(Box::new(Type::Void), 0)]);
+        assert!(bindings.is_empty());
+        assert!(lookup("print").unwrap().type_params.is_empty());
+    }
+}
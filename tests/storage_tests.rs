@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use aetos::interpreter::Interpreter;
+    use aetos::parser::Parser;
+    use aetos::storage::Store;
+    use aetos::typecheck::TypeChecker;
+    use std::path::PathBuf;
+
+    fn temp_store_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aetos-store-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("nested").join("game.json")
+    }
+
+    #[test]
+    fn test_store_persists_between_opens() {
+        let path = temp_store_path("persist");
+        let mut store = Store::open(&path);
+        assert_eq!(store.get("high_score"), None);
+        store.set("high_score", "1200").unwrap();
+        store.set("player", "Ann \"the Ace\"").unwrap();
+
+        let reopened = Store::open(&path);
+        assert_eq!(reopened.get("high_score"), Some("1200"));
+        assert_eq!(reopened.get("player"), Some("Ann \"the Ace\""));
+
+        // Повреждённый файл не ломает программу
+        std::fs::write(&path, "{ not json").unwrap();
+        assert_eq!(Store::open(&path).get("high_score"), None);
+    }
+
+    #[test]
+    fn test_store_builtins() {
+        let code = r#"
+            fn main() -> i32 {
+                print_string(store_get("volume"));
+                store_set("volume", "7");
+                print_string(store_get("volume"));
+                return 0;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        TypeChecker::new().check_program(&program).unwrap();
+
+        let path = temp_store_path("builtins");
+        for expected in [vec!["", "7"], vec!["7", "7"]] {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_store(Store::open(&path));
+            interpreter.capture_output();
+            interpreter.interpret_program(&program, 0, 0, "settings").unwrap();
+            assert_eq!(interpreter.take_output(), expected);
+        }
+    }
+}
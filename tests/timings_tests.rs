@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use aetos::optimize::Optimizer;
+    use aetos::timings::Timings;
+
+    #[test]
+    fn test_timings_report_lists_phases() {
+        let mut timings = Timings::new();
+        let value = timings.time("parse", || 42);
+        timings.time("codegen", || ());
+        
+        assert_eq!(value, 42);
+        let names: Vec<&str> = timings.phases().iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["parse", "codegen"]);
+        
+        let report = timings.to_string();
+        assert!(report.contains("parse"));
+        assert!(report.contains("total"));
+        assert!(report.contains("peak RSS"));
+    }
+
+    #[test]
+    fn test_optimizer_passes_follow_flags() {
+        assert_eq!(
+            Optimizer::default().passes(),
+            vec!["constant_folding", "dead_code_elimination", "inline_functions"]
+        );
+        assert_eq!(Optimizer::release().passes()[0], "strip_assertions");
+    }
+}
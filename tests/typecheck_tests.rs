@@ -1,13 +1,15 @@
 #[cfg(test)]
 mod tests {
+    use aetos::ast::{Derive, Expression, Statement, Type};
+    use aetos::generics::{self, GenericError};
     use aetos::parser::Parser;
-    use aetos::typecheck::TypeChecker;
+    use aetos::typecheck::{TypeCheckError, TypeChecker};
 
-    fn parse_and_check(code: &str) -> Result<(), aetos::typecheck::TypeCheckError> {
+    fn parse_and_check(code: &str) -> Result<(), aetos::typecheck::TypeCheckErrors> {
         let mut parser = Parser::new(code);
         let program = parser.parse_program().unwrap();
         let mut checker = TypeChecker::new(); // Добавили mut
-        checker.check_program(&program)
+        checker.check_program(&program).map(|_| ())
     }
 
     #[test]
@@ -61,4 +63,615 @@ mod tests {
         
         assert!(parse_and_check(code).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_variadic_print() {
+        let code = r#"
+            fn main() -> i32 {
+                let x: i32 = 5;
+                let y: f32 = 2.5;
+                print("x =", x, "y =", y, true);
+                print();
+                return 0;
+            }
+        "#;
+        
+        assert!(parse_and_check(code).is_ok());
+    }
+
+    #[test]
+    fn test_pub_visibility_markers() {
+        let code = r#"
+pub struct Point {
+    pub x: i32,
+    y: i32,
+}
+
+fn helper() -> i32 {
+    return 1;
+}
+
+pub fn main() -> i32 {
+    return helper();
+}
+"#;
+        
+        let program = Parser::new(code).parse_program().unwrap();
+        let point = &program.structs[0];
+        assert!(point.is_public);
+        assert_eq!(point.line, 2);
+        assert!(point.fields[0].is_public);
+        assert!(!point.fields[1].is_public);
+        assert!(!program.functions[0].is_public);
+        assert_eq!(program.functions[0].line, 7);
+        assert!(program.functions[1].is_public);
+        assert_eq!(program.functions[1].line, 11);
+        assert!(parse_and_check(code).is_ok());
+    }
+
+    #[test]
+    fn test_ref_types() {
+        let shared = r#"
+            struct Node {
+                value: i32,
+            }
+
+            fn main() -> i32 {
+                let a: ref Node = ref(Node { value: 1 });
+                let b: ref Node = move(a);
+                a.value = 2;
+                return a.value + b.value;
+            }
+        "#;
+        assert!(parse_and_check(shared).is_ok());
+        
+        let moved_value = r#"
+            struct Node {
+                value: i32,
+            }
+
+            fn main() -> i32 {
+                let mut a: Node = Node { value: 1 };
+                let b: Node = move(a);
+                a.value = 2;
+                return b.value;
+            }
+        "#;
+        assert!(parse_and_check(moved_value).is_err());
+        
+        let ref_of_scalar = r#"
+            fn main() -> i32 {
+                let a: i32 = 1;
+                let b: ref i32 = ref(a);
+                return 0;
+            }
+        "#;
+        assert!(parse_and_check(ref_of_scalar).is_err());
+    }
+
+    #[test]
+    fn test_derive_attributes() {
+        let code = r#"
+            @derive(debug, eq)
+            @derive(clone)
+            pub struct Point {
+                x: i32,
+            }
+
+            fn main() -> i32 {
+                let p: Point = Point { x: 1 };
+                if (p.eq(p.clone())) {
+                    return 0;
+                }
+                return 1;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        assert_eq!(program.structs[0].derives, vec![Derive::Debug, Derive::Eq, Derive::Clone]);
+        assert!(program.structs[0].is_public);
+        assert!(parse_and_check(code).is_ok());
+
+        assert!(Parser::new("@derive(hash) struct P { x: i32 }").parse_program().is_err());
+        assert!(Parser::new("@derive(debug) fn main() -> i32 { return 0; }").parse_program().is_err());
+        assert!(Parser::new("@export struct P { x: i32 }").parse_program().is_err());
+    }
+
+    #[test]
+    fn test_struct_field_defaults() {
+        let code = r#"
+            const WIDTH: i32 = 800;
+
+            struct Config {
+                width: i32 = WIDTH,
+                height: i32 = WIDTH / 2,
+                title: string,
+            }
+
+            fn main() -> i32 {
+                let a: Config = Config { title: "a" };
+                let b: Config = Config { width: 10, ..a };
+                return a.height + b.width;
+            }
+        "#;
+        assert!(parse_and_check(code).is_ok());
+
+        let missing = code.replace(r#"Config { title: "a" }"#, "Config { width: 1 }");
+        let errors = parse_and_check(&missing).unwrap_err();
+        assert!(matches!(
+            errors.errors.as_slice(),
+            [TypeCheckError::MissingField { field, .. }] if field == "title"
+        ));
+
+        let static_default = r#"
+            static mut LIMIT: i32 = 3;
+
+            struct Config {
+                limit: i32 = LIMIT,
+            }
+
+            fn main() -> i32 {
+                return 0;
+            }
+        "#;
+        assert!(parse_and_check(static_default).is_err());
+    }
+
+    #[test]
+    fn test_field_assignment_requires_let_mut() {
+        let immutable = r#"
+            struct Point {
+                x: i32,
+            }
+
+            fn main() -> i32 {
+                let p: Point = Point { x: 1 };
+                p.x = 2;
+                return p.x;
+            }
+        "#;
+        let errors = parse_and_check(immutable).unwrap_err();
+        assert!(matches!(
+            errors.errors.as_slice(),
+            [TypeCheckError::AssignToImmutable { name }] if name == "p"
+        ));
+
+        let mutable = r#"
+            struct Point {
+                x: i32,
+            }
+
+            fn shift(p: ref Point) -> void {
+                p.x = p.x + 1;
+            }
+
+            fn main() -> i32 {
+                let mut p: Point = Point { x: 1 };
+                p.x = 2;
+                let shared: ref Point = ref(Point { x: 1 });
+                shift(shared);
+                return p.x + shared.x;
+            }
+        "#;
+        assert!(parse_and_check(mutable).is_ok());
+    }
+    
+    #[test]
+    fn test_assignment_requires_let_mut() {
+        let errors = parse_and_check(r#"
+            fn main() -> i32 {
+                let count: i32 = 0;
+                count = count + 1;
+                return count;
+            }
+        "#).unwrap_err();
+        assert!(matches!(
+            errors.errors.as_slice(),
+            [TypeCheckError::AssignToImmutableVariable { name }] if name == "count"
+        ));
+        assert!(errors.errors[0].to_string().contains("consider adding mut"));
+
+        assert!(parse_and_check(r#"
+            fn main() -> i32 {
+                let mut count: i32 = 0;
+                count = count + 1;
+                return count;
+            }
+        "#).is_ok());
+    }
+
+    #[test]
+    fn test_literals_adopt_expected_type() {
+        let program = Parser::new(r#"
+            fn main() -> i64 {
+                let x: i64 = 5;
+                let y: f64 = -2;
+                let z: f32 = 7 / 2;
+                let big: i64 = 3000000000i64;
+                return 1i64;
+            }
+        "#).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let body = &typed.program().functions[0].body;
+        let value_type = |index: usize| match &body[index] {
+            Statement::VariableDeclaration { value, .. } | Statement::Return { value, .. } => typed.type_of(value).cloned(),
+            other => panic!("unexpected statement {:?}", other),
+        };
+        assert_eq!(value_type(0), Some(Type::I64));
+        assert_eq!(value_type(1), Some(Type::F64));
+        // Деление не литерал: считается в i32 и расширяется при присваивании
+        assert_eq!(value_type(2), Some(Type::I32));
+        assert_eq!(value_type(3), Some(Type::I64));
+        assert_eq!(value_type(4), Some(Type::I64));
+        let Statement::VariableDeclaration { value: Expression::TypeCast { expression, .. }, .. } = &body[3] else {
+            panic!("3000000000i64 is a cast literal");
+        };
+        assert_eq!(**expression, Expression::IntegerLiteral(3_000_000_000));
+
+        assert!(parse_and_check("fn main() -> i32 { let x: i32 = 1.5f64; return x; }").is_err());
+        assert!(parse_and_check("fn main() -> i32 { let x: i32 = 5i64; return x; }").is_err());
+        assert!(parse_and_check("fn main() -> i32 { let x: i32 = 3000000000i64 as i32; return x; }").is_ok());
+    }
+
+    #[test]
+    fn test_struct_field_validation() {
+        let errors = parse_and_check(r#"
+            struct Point { x: i32, x: i32 }
+            struct Node { value: i32, next: Node }
+            fn main() -> i32 { return 0; }
+        "#).unwrap_err();
+        assert!(matches!(
+            errors.errors.as_slice(),
+            [
+                TypeCheckError::DuplicateField { struct_name, field },
+                TypeCheckError::RecursiveStruct { name, .. },
+            ] if struct_name == "Point" && field == "x" && name == "Node"
+        ));
+        assert_eq!(errors.first().to_string(), "Duplicate field: x in struct Point");
+    }
+
+    #[test]
+    fn test_typed_program_annotates_expressions() {
+        let program = Parser::new(r#"
+            fn scale(v: f32, k: i32) -> f32 {
+                return v * k + 1;
+            }
+        "#).parse_program().unwrap();
+        let typed = TypeChecker::new().check_program(&program).unwrap();
+        let Statement::Return { value, .. } = &typed.program().functions[0].body[0] else {
+            panic!("expected return");
+        };
+        assert_eq!(typed.type_of(value), Some(&Type::F32));
+        let Expression::BinaryExpression { left, right, .. } = value else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(typed.type_of(right), Some(&Type::I32));
+        let Expression::BinaryExpression { right: k, .. } = left.as_ref() else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(typed.type_of(k), Some(&Type::I32));
+        assert_eq!(typed.types().len(), 5);
+        // Выражения исходной программы - другие узлы
+        assert_eq!(typed.type_of(&Expression::IntegerLiteral(1)), None);
+    }
+
+    #[test]
+    fn test_block_scopes_and_shadowing() {
+        // Затенение во вложенном блоке допустимо, внешняя переменная снова видна после него
+        assert!(parse_and_check(r#"
+            fn main() -> i32 {
+                let x: i32 = 1;
+                {
+                    let x: bool = true;
+                    print(x);
+                }
+                return x;
+            }
+        "#).is_ok());
+
+        let errors = parse_and_check(r#"
+            fn main() -> i32 {
+                let x: i32 = 1;
+                let x: i32 = 2;
+                return x;
+            }
+        "#).unwrap_err();
+        assert!(matches!(
+            errors.errors.as_slice(),
+            [TypeCheckError::DuplicateVariable { name }] if name == "x"
+        ));
+
+        // move во вложенном блоке действует и после него
+        let errors = parse_and_check(r#"
+            struct Point { x: i32 }
+            fn main() -> i32 {
+                let p: Point = Point { x: 1 };
+                {
+                    let q: Point = move(p);
+                }
+                return p.x;
+            }
+        "#).unwrap_err();
+        assert!(matches!(
+            errors.errors.as_slice(),
+            [TypeCheckError::UseAfterMove { name }] if name == "p"
+        ));
+    }
+    
+    #[test]
+    fn test_reports_all_errors() {
+        let code = r#"
+            fn first() -> i32 {
+                let a: i32 = missing;
+                let b: bool = a;
+                return a;
+            }
+
+            fn second() -> i32 {
+                if (true) {
+                    let c: i32 = "text";
+                }
+                return unknown;
+            }
+        "#;
+        let errors = parse_and_check(code).unwrap_err();
+        assert_eq!(errors.errors.len(), 4, "{}", errors);
+        assert_eq!(errors.to_string().lines().count(), 4);
+        
+        let mut many = String::from("fn main() -> i32 {\n");
+        for i in 0..15 {
+            many.push_str(&format!("    let v{}: i32 = nothing;\n", i));
+        }
+        many.push_str("    return 0;\n}\n");
+        let errors = parse_and_check(&many).unwrap_err();
+        assert_eq!(errors.errors.len(), 10);
+        assert_eq!(errors.suppressed, 5);
+        assert!(errors.to_string().ends_with("... and 5 more errors"));
+    }
+
+    #[test]
+    fn test_code_after_exit_is_unreachable() {
+        let code = r#"
+            fn fail(code: i32) -> i32 {
+                if (code > 0) {
+                    exit(code);
+                }
+                print("ok");
+                return 0;
+            }
+
+            fn main() -> i32 {
+                exit(2);
+                print("never");
+                return 0;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let mut checker = TypeChecker::new();
+        checker.check_program(&program).unwrap();
+        // exit внутри ветки if не делает недостижимым код после неё
+        let warnings: Vec<String> = checker.warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings, vec!["warning in fn main: unreachable code after a call that never returns"]);
+        
+        // never подходит на место любого значения
+        assert!(parse_and_check(r#"fn main() -> i32 { let x: i32 = exit(1); return x; }"#).is_ok());
+    }
+
+    #[test]
+    fn test_code_after_return_break_continue_is_unreachable() {
+        let code = r#"
+            fn main() -> i32 {
+                let mut i: i32 = 0;
+                while (i < 3) {
+                    i = i + 1;
+                    if (i == 2) {
+                        continue;
+                        print("skipped");
+                    }
+                    break;
+                    print("never");
+                }
+                return i;
+                print("after return");
+                print("reported once");
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let mut checker = TypeChecker::new();
+        checker.check_program(&program).unwrap();
+        let warnings: Vec<String> = checker.warnings().iter().map(|w| w.message.clone()).collect();
+        assert_eq!(warnings, [
+            "unreachable code after continue",
+            "unreachable code after break",
+            "unreachable code after return",
+        ]);
+    }
+
+    #[test]
+    fn test_unreachable_match_arms() {
+        let code = r#"
+            enum Light { Red, Yellow, Green }
+
+            fn main() -> i32 {
+                let light: Light = Light::Red;
+                let a: i32 = match light {
+                    Light::Red => 1,
+                    Light::Yellow => 2,
+                    Light::Green => 3,
+                    _ => 0,
+                };
+                let b: i32 = match a {
+                    1 => 10,
+                    1 => 11,
+                    _ => 0,
+                    2 => 12,
+                };
+                let c: i32 = match light {
+                    Light::Red => 1,
+                    _ => 0,
+                };
+                return a + b + c;
+            }
+        "#;
+        let program = Parser::new(code).parse_program().unwrap();
+        let mut checker = TypeChecker::new();
+        checker.check_program(&program).unwrap();
+        let warnings: Vec<String> = checker.warnings().iter().map(|w| w.message.clone()).collect();
+        assert_eq!(warnings, [
+            "unreachable match arm `_`: earlier arms already cover it",
+            "unreachable match arm `1`: earlier arms already cover it",
+            "unreachable match arm `2`: earlier arms already cover it",
+        ]);
+
+        // Новый вариант без ветки - ошибка в каждом match без `_`
+        let errors = parse_and_check(r#"
+            enum Light { Red, Green, Blue }
+            fn main() -> i32 {
+                let light: Light = Light::Red;
+                return match light {
+                    Light::Red => 1,
+                    Light::Green => 2,
+                };
+            }
+        "#).unwrap_err();
+        assert_eq!(errors.first().to_string(), "Non-exhaustive match on Light: missing Light::Blue");
+    }
+
+    #[test]
+    fn test_slices() {
+        // Массив подходит туда, где ждут срез, но не наоборот
+        assert!(parse_and_check(r#"
+            fn first(values: &[i32]) -> i32 { return values[0]; }
+            fn main() -> i32 {
+                let numbers: [i32] = [1, 2, 3];
+                let tail: &[i32] = numbers[1..];
+                return first(numbers) + first(tail[..1]) + tail.len();
+            }
+        "#).is_ok());
+        assert!(parse_and_check(r#"
+            fn main() -> i32 { let numbers: [i32] = [1, 2]; let copy: [i32] = numbers[..]; return 0; }
+        "#).is_err());
+        assert!(parse_and_check(r#"
+            fn main() -> i32 { let numbers: [i32] = [1, 2]; return numbers.len(1); }
+        "#).is_err());
+        assert!(parse_and_check(r#"
+            fn main() -> i32 { let numbers: [f32] = [1.0]; let s: &[i32] = numbers[0..1]; return 0; }
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_fixed_arrays() {
+        // [T; N] подходит на место [T] и &[T], в том числе в обобщённой функции;
+        // элемент вложенного массива заменяется массивом той же длины
+        assert!(parse_and_check(r#"
+            struct Mixer { levels: [f32; 2] }
+            fn first<T>(values: [T]) -> T { return values[0]; }
+            fn total(values: &[i32]) -> i32 { return values[0] + values.len(); }
+            fn main() -> i32 {
+                let mut grid: [[i32; 2]; 3] = [[0; 2]; 3];
+                grid[2] = [4, 5];
+                let mixer: Mixer = Mixer { levels: [0.5, 1.0] };
+                let level: f32 = first(mixer.levels);
+                return first(grid[2]) + total(grid[0]) + grid.len();
+            }
+        "#).is_ok());
+        assert!(parse_and_check(r#"
+            fn main() -> i32 { let mut grid: [[i32; 2]; 3] = [[0; 2]; 3]; grid[0] = [1, 2, 3]; return 0; }
+        "#).is_err());
+        assert!(parse_and_check(r#"
+            fn main() -> i32 { let mut buffer: [i32; 2] = [0; 2]; buffer[0] = 1.5; return 0; }
+        "#).is_err());
+        assert!(parse_and_check(r#"
+            fn main() -> i32 { let buffer: [i32; 2] = [0; 2]; return buffer[-1]; }
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_missing_return() {
+        // Путь заканчивается return, хвостовым выражением, exit или loop без break
+        assert!(parse_and_check(r#"
+            fn sign(x: i32) -> i32 { if (x < 0) { return 0 - 1; } else { return 1; } }
+            fn tail(x: i32) -> i32 { x + 1 }
+            fn fail(x: i32) -> i32 { if (x > 0) { return x; } exit(1); }
+            fn poll() -> i32 { loop { if (true) { return 1; } } }
+            fn main() -> i32 { return sign(1) + tail(1) + fail(1) + poll(); }
+        "#).is_ok());
+        let result = parse_and_check(r#"
+            fn positive(x: i32) -> i32 { if (x > 0) { return x; } }
+            fn main() -> i32 { return positive(1); }
+        "#);
+        assert!(matches!(
+            &result.unwrap_err().errors[..],
+            [TypeCheckError::MissingReturn { function, return_type: Type::I32 }] if function == "positive"
+        ));
+        assert!(parse_and_check(r#"
+            fn first() -> i32 { loop { break; } }
+            fn main() -> i32 { return first(); }
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_borrow_scopes() {
+        // Заимствование из вложенного блока не мешает менять переменную после него
+        assert!(parse_and_check(r#"
+            struct Point { x: i32 }
+            fn read(p: Point) -> i32 { p.x }
+            fn main() -> i32 {
+                let mut p: Point = Point { x: 1 };
+                { let view: Point = borrow(p); print(read(view)); }
+                p.x = read(mut_borrow(p)) + 1;
+                p.x = read(borrow(p)) + read(borrow(p));
+                return p.x;
+            }
+        "#).is_ok());
+        let result = parse_and_check(r#"
+            struct Point { x: i32 }
+            fn main() -> i32 {
+                let mut p: Point = Point { x: 1 };
+                let view: Point = borrow(p);
+                p.x = 2;
+                return 0;
+            }
+        "#);
+        assert!(matches!(
+            &result.unwrap_err().errors[..],
+            [TypeCheckError::AssignWhileBorrowed { name }] if name == "p"
+        ));
+        assert!(parse_and_check(r#"
+            struct Point { x: i32 }
+            fn both(a: Point, b: Point) -> i32 { a.x + b.x }
+            fn main() -> i32 { let p: Point = Point { x: 1 }; return both(borrow(p), mut_borrow(p)); }
+        "#).is_err());
+    }
+
+    #[test]
+    fn test_generic_instances() {
+        let program = Parser::new(r#"
+            struct Pair<T> { left: T, right: T }
+            impl<T> Pair<T> {
+                fn left(self) -> T { return self.left; }
+            }
+            fn max<T>(a: T, b: T) -> T { return a; }
+            fn main() -> i32 {
+                let p: Pair<f32> = Pair { left: max(1.5, 2.5), right: 0.5 };
+                let q: Pair<Pair<i32>> = Pair { left: Pair { left: 1, right: 2 }, right: Pair { left: 3, right: 4 } };
+                return max(1, q.left.left());
+            }
+        "#).parse_program().unwrap();
+
+        let concrete = generics::monomorphize(&program).unwrap();
+        let functions: Vec<&str> = concrete.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(functions, vec!["Pair<f32>::left", "Pair<i32>::left", "Pair<Pair<i32>>::left", "max<f32>", "max<i32>", "main"]);
+        let structs: Vec<&str> = concrete.structs.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(structs, vec!["Pair<f32>", "Pair<i32>", "Pair<Pair<i32>>"]);
+        assert_eq!(concrete.structs[2].fields[0].field_type, Type::Struct("Pair<i32>".to_string()));
+        assert!(TypeChecker::new().check_program(&program).is_ok());
+
+        // Копия, создающая копию с более длинным типом, не строится бесконечно
+        let error = parse_and_check(r#"
+            struct Box<T> { value: T }
+            fn nest<T>(value: T) -> i32 { return nest(Box { value: value }); }
+            fn main() -> i32 { return nest(1); }
+        "#).unwrap_err();
+        assert!(matches!(error.first(), TypeCheckError::Generic(GenericError::TooManyInstances { name }) if name == "nest"));
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use aetos::modules::link_imports;
+    use aetos::parser::Parser;
+    use aetos::scaffold;
+    use aetos::workspace::{DependencySource, Manifest, Workspace, WorkspaceError};
+    use std::path::{Path, PathBuf};
+
+    fn temp_workspace(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aetos-workspace-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, source) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = Manifest::parse(r#"
+            [workspace]
+            members = [
+                "engine",  # движок
+                "game",
+            ]
+            target_dir = "build"
+
+            [package]
+            name = "game"
+            edition = "2024"
+
+            [dependencies]
+            engine = { path = "../engine" }
+        "#, Path::new("aetos.toml")).unwrap();
+
+        let workspace = manifest.workspace.unwrap();
+        assert_eq!(workspace.members, vec!["engine", "game"]);
+        assert_eq!(workspace.target_dir.as_deref(), Some("build"));
+        let package = manifest.package.unwrap();
+        assert_eq!(package.name, "game");
+        assert_eq!(package.edition.as_deref(), Some("2024"));
+        assert_eq!(manifest.dependencies, vec![("engine".to_string(), DependencySource::Path(PathBuf::from("../engine")))]);
+
+        let scaffolded = Manifest::parse(&scaffold::manifest("demo"), Path::new("aetos.toml")).unwrap();
+        assert_eq!(scaffolded.package.unwrap().entry.as_deref(), Some("main.aetos"));
+
+        let registry = Manifest::parse("[dependencies]\nui_kit = \"1.2\"\ntween = { version = \"0.3\" }\n", Path::new("aetos.toml")).unwrap();
+        assert_eq!(registry.dependencies[1], ("tween".to_string(), DependencySource::Registry("0.3".to_string())));
+
+        let error = Manifest::parse("[dependencies]\nengine = { git = \"../engine\" }\n", Path::new("aetos.toml")).unwrap_err();
+        assert!(matches!(error, WorkspaceError::Syntax { line: 2, .. }));
+    }
+
+    #[test]
+    fn test_members_in_build_order_with_path_dependencies() {
+        let root = temp_workspace("order", &[
+            ("aetos.toml", "[workspace]\nmembers = [\"game\", \"engine\"]\n"),
+            ("game/aetos.toml", "[package]\nname = \"game\"\nedition = \"2024\"\n\n[dependencies]\nengine = { path = \"../engine\" }\n"),
+            ("game/main.aetos", r#"
+                import engine;
+                import "engine/physics.aetos";
+
+                fn main() -> i32 {
+                    return speed() + gravity();
+                }
+            "#),
+            ("engine/aetos.toml", "[package]\nname = \"engine\"\nedition = \"2024\"\nentry = \"lib.aetos\"\ncrate_type = \"lib\"\n"),
+            ("engine/lib.aetos", "import \"physics.aetos\";\npub fn speed() -> i32 { return gravity() * 2; }\n"),
+            ("engine/physics.aetos", "pub fn gravity() -> i32 { return 10; }\n"),
+        ]);
+
+        let workspace = Workspace::load(&root).unwrap();
+        let names: Vec<&str> = workspace.packages.iter().map(|package| package.name.as_str()).collect();
+        assert_eq!(names, vec!["engine", "game"]);
+        assert!(workspace.packages[0].is_lib);
+        assert_eq!(workspace.target_dir, root.join("target"));
+        assert_eq!(workspace.output_path(&workspace.packages[1], "wat"), root.join("target/game.wat"));
+        assert_eq!(Workspace::find_root(&root.join("game")), Some(root.clone()));
+
+        // Импорт по имени зависимости загружает файлы из каталога пакета
+        let entry = root.join("game/main.aetos");
+        let program = Parser::new(&std::fs::read_to_string(&entry).unwrap()).parse_program().unwrap();
+        let program = link_imports(program, &entry).unwrap();
+        let functions: Vec<&str> = program.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(functions, vec!["gravity", "speed", "main"]);
+    }
+
+    #[test]
+    fn test_dependency_errors() {
+        let root = temp_workspace("cycle", &[
+            ("aetos.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+            ("a/aetos.toml", "[package]\nname = \"a\"\n[dependencies]\nb = { path = \"../b\" }\n"),
+            ("b/aetos.toml", "[package]\nname = \"b\"\n[dependencies]\na = { path = \"../a\" }\n"),
+        ]);
+        let error = Workspace::load(&root).unwrap_err();
+        assert_eq!(error.to_string(), "Dependency cycle between packages: a -> b -> a");
+
+        let root = temp_workspace("missing", &[
+            ("aetos.toml", "[workspace]\nmembers = [\"a\"]\n"),
+            ("a/aetos.toml", "[package]\nname = \"a\"\n[dependencies]\nzip = { path = \"../zip\" }\n"),
+        ]);
+        let error = Workspace::load(&root).unwrap_err();
+        assert!(matches!(&error, WorkspaceError::MissingDependency { dependency, .. } if dependency == "zip"));
+    }
+}